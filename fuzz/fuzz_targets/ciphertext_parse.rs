@@ -0,0 +1,19 @@
+//! 密文解析：任意字节按两种分量顺序与 DER 解析，成功时重新编码后必须得到相同的分量
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sm2_co_sign_core::{CiphertextLayout, Sm2Ciphertext};
+
+fuzz_target!(|data: &[u8]| {
+    for layout in [CiphertextLayout::C1C3C2, CiphertextLayout::C1C2C3] {
+        if let Ok(parsed) = Sm2Ciphertext::parse_with_layout(data, layout) {
+            let encoded = parsed.to_bytes(layout);
+            assert_eq!(Sm2Ciphertext::parse_with_layout(&encoded, layout).unwrap(), parsed);
+            assert_eq!(Sm2Ciphertext::from_der(&parsed.to_der()).unwrap(), parsed);
+        }
+    }
+    if let Ok(parsed) = Sm2Ciphertext::from_der(data) {
+        assert_eq!(Sm2Ciphertext::parse(&parsed.to_der()).unwrap(), parsed);
+    }
+});
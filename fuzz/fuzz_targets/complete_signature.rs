@@ -0,0 +1,35 @@
+//! 服务端输入驱动的签名 / 解密完成：k1、D1 与 r/s2/s3、T2 及密文分量均为任意字节
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sm2_co_sign_core::CoSignProtocol;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    k1: &'a [u8],
+    d1: &'a [u8],
+    r: &'a [u8],
+    s2: &'a [u8],
+    s3: &'a [u8],
+    t2: &'a [u8],
+    c1: &'a [u8],
+    c3: &'a [u8],
+    c2: &'a [u8],
+}
+
+fuzz_target!(|input: Input| {
+    let protocol = CoSignProtocol::new().unwrap();
+
+    if let Ok((r, s)) = protocol.complete_signature(input.k1, input.d1, input.r, input.s2, input.s3) {
+        assert_eq!(r.len(), 32);
+        assert_eq!(s.len(), 32);
+        let _ = protocol.is_degenerate_signature(&r, &s);
+    }
+
+    if let Ok(plaintext) = protocol.complete_decryption(input.t2, input.c1, input.c3, input.c2) {
+        assert_eq!(plaintext.len(), input.c2.len());
+    }
+    let _ = protocol.decrypt_prepare(input.d1, input.c1);
+});
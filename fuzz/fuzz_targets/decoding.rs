@@ -0,0 +1,47 @@
+//! 文本与 DER 解码：Base64 / Base64URL / 十六进制、签名与公钥 DER、PEM、服务端 JSON 响应
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sm2_co_sign_core::asn1::{self, DerReader};
+use sm2_co_sign_core::protocol::{base64_decode, base64url_decode, hex_decode};
+use sm2_co_sign_core::{pem, DecryptResponse, PublicKey, Signature, SignResponse};
+
+/// 递归遍历 DER 结构，深度受限以免构造的深层嵌套拖慢单次执行
+fn walk(data: &[u8], depth: usize) {
+    let mut reader = DerReader::new(data);
+    while !reader.is_empty() {
+        match reader.read_any() {
+            // 构造类型（bit 5 置位）继续展开
+            Ok((tag, contents)) if tag & 0x20 != 0 && depth < 16 => walk(contents, depth + 1),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    walk(data, 0);
+
+    if let Ok(raw) = asn1::signature_from_der(data) {
+        assert_eq!(raw.len(), 64);
+        assert_eq!(asn1::signature_from_der(&asn1::signature_to_der(&raw).unwrap()).unwrap(), raw);
+    }
+    if let Ok(public_key) = asn1::public_key_from_spki(data) {
+        assert_eq!(public_key.len(), 64);
+    }
+    let _ = PublicKey::from_slice(data);
+    let _ = Signature::from_bytes(data);
+
+    let _ = serde_json::from_slice::<SignResponse>(data);
+    let _ = serde_json::from_slice::<DecryptResponse>(data);
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = base64_decode(text);
+        let _ = base64url_decode(text);
+        let _ = hex_decode(text);
+        let _ = pem::decode(text, pem::PUBLIC_KEY_LABEL);
+        let _ = PublicKey::from_pem(text);
+        let _ = text.parse::<Signature>();
+    }
+});
@@ -0,0 +1,170 @@
+//! FFI 入口：任意输入与长度参数
+//!
+//! 长度参数截断到实际缓冲区长度（超出部分属于调用方违约，不可测试），输出容量在 [0, 实际容量] 内任取，
+//! 覆盖容量不足与长度字段互相矛盾的情况。任何入口返回 `COSIGN_ERR_INTERNAL` 都说明库内部发生了 panic。
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sm2_co_sign_ffi::*;
+use std::ffi::CString;
+use std::os::raw::{c_int, c_ulong};
+
+const OUT_LEN: usize = 4096;
+
+#[derive(Debug, Arbitrary)]
+enum Call<'a> {
+    Base64Decode(&'a [u8]),
+    Base64UrlDecode(&'a [u8]),
+    HexDecode(&'a [u8]),
+    SignatureFromDer(&'a [u8]),
+    Sm2Sign { key: &'a [u8], message: &'a [u8] },
+    Sm2Verify { public_key: &'a [u8], message: &'a [u8], signature: &'a [u8] },
+    Sm2Decrypt { key: &'a [u8], ciphertext: &'a [u8] },
+    Sm4CbcDecrypt { key: &'a [u8], iv: &'a [u8], data: &'a [u8] },
+    Sm4GcmDecrypt { key: &'a [u8], iv: &'a [u8], aad: &'a [u8], data: &'a [u8] },
+    CompleteSignature { k1: &'a [u8], d1: &'a [u8], r: &'a [u8], s2: &'a [u8], s3: &'a [u8] },
+    DecryptPrepare { d1: &'a [u8], c1: &'a [u8] },
+    CompleteDecryption { t2: &'a [u8], c1: &'a [u8], c3: &'a [u8], c2: &'a [u8] },
+    CiphertextSplit { ciphertext: &'a [u8], layout: c_int },
+    HashMessage { mode: c_int, input: &'a [u8], public_key: &'a [u8] },
+    ContextImport { blob: &'a [u8], key: &'a [u8] },
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    call: Call<'a>,
+    /// 各输入长度参数的截断比例，按顺序取用
+    lengths: [u16; 5],
+    out_cap: u16,
+}
+
+/// 调用方声明的长度：不超过实际长度
+fn len(data: &[u8], hint: u16) -> c_ulong {
+    if data.is_empty() {
+        0
+    } else {
+        (hint as usize % (data.len() + 1)) as c_ulong
+    }
+}
+
+fn decode(text: &[u8], cap: c_ulong, f: extern "C" fn(*const std::os::raw::c_char, *mut u8, c_ulong, *mut c_ulong) -> c_int) -> c_int {
+    // 内嵌 NUL 的输入无法构造 C 字符串，对 C 调用方而言等价于在 NUL 处截断
+    let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+    let text = CString::new(&text[..end]).unwrap();
+    let mut out = [0u8; OUT_LEN];
+    let mut out_len = 0;
+    f(text.as_ptr(), out.as_mut_ptr(), cap, &mut out_len)
+}
+
+fuzz_target!(|input: Input| {
+    let l = input.lengths;
+    let cap = input.out_cap as c_ulong % (OUT_LEN as c_ulong + 1);
+    let mut out = [0u8; OUT_LEN];
+    let mut out_len: c_ulong = 0;
+    let ctx = cosign_context_new();
+    assert!(!ctx.is_null());
+
+    let rc = match input.call {
+        Call::Base64Decode(text) => decode(text, cap, cosign_base64_decode),
+        Call::Base64UrlDecode(text) => decode(text, cap, cosign_base64url_decode),
+        Call::HexDecode(text) => decode(text, cap, cosign_hex_decode),
+        Call::SignatureFromDer(der) => {
+            cosign_signature_from_der(der.as_ptr(), len(der, l[0]), out.as_mut_ptr(), cap, &mut out_len)
+        }
+        Call::Sm2Sign { key, message } => cosign_sm2_sign(
+            key.as_ptr(), len(key, l[0]),
+            message.as_ptr(), len(message, l[1]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::Sm2Verify { public_key, message, signature } => cosign_sm2_verify(
+            public_key.as_ptr(), len(public_key, l[0]),
+            message.as_ptr(), len(message, l[1]),
+            signature.as_ptr(), len(signature, l[2]),
+        ),
+        Call::Sm2Decrypt { key, ciphertext } => cosign_sm2_decrypt(
+            key.as_ptr(), len(key, l[0]),
+            ciphertext.as_ptr(), len(ciphertext, l[1]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::Sm4CbcDecrypt { key, iv, data } => cosign_sm4_cbc_decrypt(
+            key.as_ptr(), len(key, l[0]),
+            iv.as_ptr(), len(iv, l[1]),
+            data.as_ptr(), len(data, l[2]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::Sm4GcmDecrypt { key, iv, aad, data } => cosign_sm4_gcm_decrypt(
+            key.as_ptr(), len(key, l[0]),
+            iv.as_ptr(), len(iv, l[1]),
+            aad.as_ptr(), len(aad, l[2]),
+            data.as_ptr(), len(data, l[3]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::CompleteSignature { k1, d1, r, s2, s3 } => {
+            let mut out_s = [0u8; 64];
+            let mut out_s_len = 0;
+            let s_cap = cap % (out_s.len() as c_ulong + 1);
+            cosign_complete_signature(
+                ctx,
+                k1.as_ptr(), len(k1, l[0]),
+                d1.as_ptr(), len(d1, l[1]),
+                r.as_ptr(), len(r, l[2]),
+                s2.as_ptr(), len(s2, l[3]),
+                s3.as_ptr(), len(s3, l[4]),
+                out.as_mut_ptr(), cap, &mut out_len,
+                out_s.as_mut_ptr(), s_cap, &mut out_s_len,
+            )
+        }
+        Call::DecryptPrepare { d1, c1 } => cosign_decrypt_prepare(
+            ctx,
+            d1.as_ptr(), len(d1, l[0]),
+            c1.as_ptr(), len(c1, l[1]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::CompleteDecryption { t2, c1, c3, c2 } => cosign_complete_decryption(
+            ctx,
+            t2.as_ptr(), len(t2, l[0]),
+            c1.as_ptr(), len(c1, l[1]),
+            c3.as_ptr(), len(c3, l[2]),
+            c2.as_ptr(), len(c2, l[3]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::CiphertextSplit { ciphertext, layout } => {
+            let mut parts = cosign_ciphertext_parts_t { c1: [0; 64], c3: [0; 32], c2: std::ptr::null(), c2_len: 0 };
+            let declared = len(ciphertext, l[0]);
+            let rc = cosign_ciphertext_split_ex(ciphertext.as_ptr(), declared, layout, &mut parts);
+            if rc == COSIGN_OK {
+                // C2 必须落在调用方声明的范围内
+                let start = ciphertext.as_ptr() as usize;
+                let c2 = parts.c2 as usize;
+                assert!(c2 >= start && c2 + parts.c2_len as usize <= start + declared as usize);
+                let t2 = [0x11u8; 64];
+                let rc = cosign_complete_decryption_ex(ctx, t2.as_ptr(), 64, &parts, out.as_mut_ptr(), cap, &mut out_len);
+                assert_ne!(rc, COSIGN_ERR_INTERNAL);
+            }
+            rc
+        }
+        Call::HashMessage { mode, input, public_key } => cosign_hash_message_ex(
+            ctx,
+            mode,
+            input.as_ptr(), len(input, l[0]),
+            public_key.as_ptr(), len(public_key, l[1]),
+            out.as_mut_ptr(), cap, &mut out_len,
+        ),
+        Call::ContextImport { blob, key } => {
+            let mut imported = std::ptr::null_mut();
+            let rc = cosign_context_import(blob.as_ptr(), len(blob, l[0]), key.as_ptr(), len(key, l[1]), &mut imported);
+            if rc == COSIGN_OK {
+                cosign_context_free(imported);
+            }
+            rc
+        }
+    };
+
+    assert_ne!(rc, COSIGN_ERR_INTERNAL, "FFI entry point panicked");
+    if rc == COSIGN_OK {
+        assert!(out_len as usize <= cap as usize);
+    }
+    cosign_context_free(ctx);
+});
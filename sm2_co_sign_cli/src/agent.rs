@@ -0,0 +1,199 @@
+//! 签名代理（Unix 域套接字）
+//!
+//! 类似 ssh-agent：进程内持有解锁后的 D1，本机其他进程通过套接字请求协同签名，无需接触 D1。
+//! 请求与响应均为帧：`长度(u32 BE，不含自身) || 类型(1) || 载荷`，同一连接可发送多个请求。
+//!
+//! | 类型 | 方向 | 载荷 |
+//! |------|------|------|
+//! | 0x01 | 请求 | 无，获取协同公钥 |
+//! | 0x02 | 请求 | 原文，e = SM3(M)（与 `sign` 命令一致） |
+//! | 0x03 | 请求 | 原文，e = SM3(ZA \|\| M)（标准 SM3withSM2，默认用户标识） |
+//! | 0x04 | 请求 | 32 字节消息哈希 e |
+//! | 0x80 | 响应 | 失败原因（UTF-8） |
+//! | 0x81 | 响应 | 协同公钥（64 字节 x\|\|y） |
+//! | 0x82 | 响应 | 签名（64 字节 r\|\|s） |
+
+use crate::signer::{HashMode, Signer};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+
+pub const MSG_PUBLIC_KEY_REQUEST: u8 = 0x01;
+pub const MSG_SIGN_REQUEST: u8 = 0x02;
+pub const MSG_SIGN_ZA_REQUEST: u8 = 0x03;
+pub const MSG_SIGN_DIGEST_REQUEST: u8 = 0x04;
+pub const MSG_FAILURE: u8 = 0x80;
+pub const MSG_PUBLIC_KEY: u8 = 0x81;
+pub const MSG_SIGNATURE: u8 = 0x82;
+
+/// 帧长度上限（大文件请由调用方先计算哈希，使用 0x04 请求）
+const MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+/// 代理请求
+#[derive(Debug, PartialEq, Eq)]
+pub enum Request {
+    PublicKey,
+    Sign(Vec<u8>),
+    SignZa(Vec<u8>),
+    SignDigest(Vec<u8>),
+}
+
+impl Request {
+    /// 由帧类型与载荷解析请求
+    pub fn decode(kind: u8, payload: Vec<u8>) -> Result<Self, String> {
+        match kind {
+            MSG_PUBLIC_KEY_REQUEST if payload.is_empty() => Ok(Self::PublicKey),
+            MSG_PUBLIC_KEY_REQUEST => Err("public key request takes no payload".to_string()),
+            MSG_SIGN_REQUEST => Ok(Self::Sign(payload)),
+            MSG_SIGN_ZA_REQUEST => Ok(Self::SignZa(payload)),
+            MSG_SIGN_DIGEST_REQUEST if payload.len() == 32 => Ok(Self::SignDigest(payload)),
+            MSG_SIGN_DIGEST_REQUEST => Err("digest must be 32 bytes".to_string()),
+            _ => Err(format!("unknown request type 0x{:02x}", kind)),
+        }
+    }
+}
+
+/// 代理响应
+#[derive(Debug, PartialEq, Eq)]
+pub enum Response {
+    Failure(String),
+    PublicKey(Vec<u8>),
+    Signature(Vec<u8>),
+}
+
+impl Response {
+    /// 编码为 (帧类型, 载荷)
+    pub fn encode(&self) -> (u8, &[u8]) {
+        match self {
+            Self::Failure(message) => (MSG_FAILURE, message.as_bytes()),
+            Self::PublicKey(public_key) => (MSG_PUBLIC_KEY, public_key),
+            Self::Signature(signature) => (MSG_SIGNATURE, signature),
+        }
+    }
+}
+
+/// 读取一帧，连接正常关闭时返回 None
+pub async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<Option<(u8, Vec<u8>)>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_FRAME_LEN {
+        anyhow::bail!("invalid frame length: {}", len);
+    }
+
+    let mut frame = vec![0u8; len];
+    reader.read_exact(&mut frame).await?;
+    let payload = frame.split_off(1);
+    Ok(Some((frame[0], payload)))
+}
+
+/// 写入一帧
+pub async fn write_frame(writer: &mut (impl AsyncWrite + Unpin), kind: u8, payload: &[u8]) -> anyhow::Result<()> {
+    let len = u32::try_from(payload.len() + 1)?;
+    let mut frame = Vec::with_capacity(payload.len() + 5);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.push(kind);
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// 签名代理
+pub struct Agent {
+    signer: Signer,
+}
+
+impl Agent {
+    pub fn new(signer: Signer) -> Self {
+        Self { signer }
+    }
+
+    /// 接受连接并处理请求，直到出错或被取消
+    pub async fn serve(self: Arc<Self>, listener: UnixListener, owner_uid: u32) -> anyhow::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            // Reason: 套接字文件权限之外再校验对端 uid，拒绝其他用户的进程
+            match stream.peer_cred() {
+                Ok(cred) if cred.uid() == owner_uid => {}
+                Ok(cred) => {
+                    tracing::warn!("Agent rejected connection from uid {}", cred.uid());
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Agent failed to read peer credentials: {}", e);
+                    continue;
+                }
+            }
+            let agent = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = agent.handle_connection(stream).await {
+                    tracing::warn!("Agent connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: UnixStream) -> anyhow::Result<()> {
+        while let Some((kind, payload)) = read_frame(&mut stream).await? {
+            let response = match Request::decode(kind, payload) {
+                Ok(request) => self.handle(&request).await,
+                Err(message) => Response::Failure(message),
+            };
+            let (kind, payload) = response.encode();
+            write_frame(&mut stream, kind, payload).await?;
+        }
+        Ok(())
+    }
+
+    /// 处理单个请求
+    pub async fn handle(&self, request: &Request) -> Response {
+        let result = match request {
+            Request::PublicKey => return Response::PublicKey(self.signer.public_key().to_vec()),
+            Request::Sign(message) => self.signer.sign(HashMode::Sm3, message).await,
+            Request::SignZa(message) => self.signer.sign(HashMode::Za, message).await,
+            Request::SignDigest(e) => self.signer.sign(HashMode::Digest, e).await,
+        };
+        match result {
+            Ok(signature) => Response::Signature(signature),
+            Err(e) => Response::Failure(format!("{:#}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        write_frame(&mut client, MSG_SIGN_REQUEST, b"hello").await.unwrap();
+        write_frame(&mut client, MSG_PUBLIC_KEY_REQUEST, &[]).await.unwrap();
+        drop(client);
+
+        assert_eq!(read_frame(&mut server).await.unwrap(), Some((MSG_SIGN_REQUEST, b"hello".to_vec())));
+        assert_eq!(read_frame(&mut server).await.unwrap(), Some((MSG_PUBLIC_KEY_REQUEST, Vec::new())));
+        assert_eq!(read_frame(&mut server).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_frame_length() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(&0u32.to_be_bytes()).await.unwrap();
+        assert!(read_frame(&mut server).await.is_err());
+    }
+
+    #[test]
+    fn test_request_decode() {
+        assert_eq!(Request::decode(MSG_PUBLIC_KEY_REQUEST, Vec::new()), Ok(Request::PublicKey));
+        assert_eq!(Request::decode(MSG_SIGN_DIGEST_REQUEST, vec![0u8; 32]), Ok(Request::SignDigest(vec![0u8; 32])));
+        assert!(Request::decode(MSG_SIGN_DIGEST_REQUEST, vec![0u8; 31]).is_err());
+        assert!(Request::decode(MSG_PUBLIC_KEY_REQUEST, vec![1]).is_err());
+        assert!(Request::decode(0x7f, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! 性能测试统计
+//!
+//! 收集每次操作的耗时，计算均值、分位数与吞吐量。
+
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// 单项操作的耗时统计
+pub struct Stats {
+    pub name: &'static str,
+    samples: Vec<Duration>,
+    total: Duration,
+}
+
+impl Stats {
+    /// 重复执行 `iterations` 次并记录每次耗时
+    pub fn measure(name: &'static str, iterations: u32, mut f: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<Self> {
+        let mut samples = Vec::with_capacity(iterations as usize);
+        for _ in 0..iterations {
+            let start = Instant::now();
+            f()?;
+            samples.push(start.elapsed());
+        }
+        Ok(Self::from_samples(name, samples))
+    }
+
+    /// 由已记录的耗时创建统计
+    pub fn from_samples(name: &'static str, mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let total = samples.iter().sum();
+        Self { name, samples, total }
+    }
+
+    /// 第 p 百分位耗时（最近秩法，p 取 0-100）
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = ((p / 100.0) * self.samples.len() as f64).ceil() as usize;
+        self.samples[rank.clamp(1, self.samples.len()) - 1]
+    }
+
+    /// 平均耗时
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.total / self.samples.len() as u32
+    }
+
+    /// 吞吐量（次/秒，按顺序执行计算）
+    pub fn throughput(&self) -> f64 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+        self.samples.len() as f64 / self.total.as_secs_f64()
+    }
+
+    /// 单行文本报告
+    pub fn report(&self) -> String {
+        format!(
+            "{:<20} n={:<6} mean={:>9.3}ms p50={:>9.3}ms p90={:>9.3}ms p99={:>9.3}ms max={:>9.3}ms {:>10.1} ops/s",
+            self.name,
+            self.samples.len(),
+            ms(self.mean()),
+            ms(self.percentile(50.0)),
+            ms(self.percentile(90.0)),
+            ms(self.percentile(99.0)),
+            ms(self.percentile(100.0)),
+            self.throughput(),
+        )
+    }
+
+    /// JSON 报告（耗时单位毫秒）
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "iterations": self.samples.len(),
+            "mean_ms": ms(self.mean()),
+            "p50_ms": ms(self.percentile(50.0)),
+            "p90_ms": ms(self.percentile(90.0)),
+            "p99_ms": ms(self.percentile(99.0)),
+            "max_ms": ms(self.percentile(100.0)),
+            "ops_per_sec": self.throughput(),
+        })
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let samples = (1..=100).rev().map(Duration::from_millis).collect();
+        let stats = Stats::from_samples("t", samples);
+        assert_eq!(stats.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(stats.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(stats.percentile(100.0), Duration::from_millis(100));
+        assert_eq!(stats.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(stats.mean(), Duration::from_micros(50_500));
+    }
+
+    #[test]
+    fn test_empty_stats() {
+        let stats = Stats::from_samples("t", Vec::new());
+        assert_eq!(stats.percentile(50.0), Duration::ZERO);
+        assert_eq!(stats.mean(), Duration::ZERO);
+        assert_eq!(stats.throughput(), 0.0);
+    }
+}
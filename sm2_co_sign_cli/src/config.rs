@@ -0,0 +1,291 @@
+//! CLI 配置文件
+//!
+//! 默认位于 `~/.config/sm2-co-sign/config.toml`，按命名 profile 组织：
+//!
+//! ```toml
+//! default_profile = "prod"
+//!
+//! [profiles.prod]
+//! server = "https://cosign.example.com"
+//! timeout = 60
+//! verify_tls = true
+//! ca_cert = "/etc/sm2-co-sign/ca.pem"
+//! key_dir = "/home/alice/.sm2-co-sign/prod"
+//! # 网关响应格式：standard（{code, message, data}）或 status-msg-result（{status, msg, result}）
+//! envelope = "standard"
+//! # 请求与响应中二进制字段的编码：base64（默认）、base64url 或 hex
+//! field_encoding = "base64"
+//! # 请求体编码：json（默认）或 cbor（需 cbor feature 与网关支持）
+//! body_format = "json"
+//! # 每次签名后验证结果，不通过时中止并提示轮换密钥
+//! paranoid = true
+//! # 签名结果退化时最多重试的轮数
+//! max_sign_attempts = 3
+//! # 签名时先交换随机数承诺再发送 Q1（需网关支持）
+//! nonce_commitment = true
+//! # D1 保护方式：passphrase（口令加密）或 tpm（密封到本机 TPM 2.0，需 tpm feature）
+//! keystore = "tpm"
+//! # keystore = "tpm" 时绑定的 SHA-256 PCR，为空或不设置时不绑定
+//! tpm_pcrs = [0, 7]
+//! # 密钥轮换策略：D1 年龄（天）或使用次数达到 recommend 阈值时提示执行 key rotate，达到 require 阈值时拒绝签名
+//! rotation_recommend_days = 90
+//! rotation_require_days = 365
+//! rotation_recommend_uses = 100000
+//! rotation_require_uses = 1000000
+//! ```
+//!
+//! 优先级：命令行参数 > profile > 内置默认值。
+
+use crate::keystore::Backend;
+use anyhow::Context;
+use serde::Deserialize;
+use sm2_co_sign_core::{BodyFormat, Encoding, FieldEnvelope, ResponseEnvelope, RotationPolicy};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 未指定 default_profile 时使用的 profile 名称
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 配置文件
+#[derive(Debug, Default, Deserialize)]
+pub struct ConfigFile {
+    /// 默认 profile 名称
+    pub default_profile: Option<String>,
+    /// 命名 profile
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// 单个 profile 的配置项，未设置的项使用命令行参数或内置默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Profile {
+    /// 服务器地址
+    pub server: Option<String>,
+    /// 请求超时（秒）
+    pub timeout: Option<u64>,
+    /// 是否验证 TLS 证书
+    pub verify_tls: Option<bool>,
+    /// 额外信任的 CA 证书（PEM）
+    pub ca_cert: Option<PathBuf>,
+    /// 双向 TLS 客户端证书（PEM）
+    pub client_cert: Option<PathBuf>,
+    /// 双向 TLS 客户端私钥（PEM）
+    pub client_key: Option<PathBuf>,
+    /// 本地密钥与会话文件目录
+    pub key_dir: Option<PathBuf>,
+    /// 服务端响应外层格式
+    pub envelope: Option<EnvelopeFormat>,
+    /// 请求与响应中二进制字段的编码
+    pub field_encoding: Option<Encoding>,
+    /// 请求体编码
+    pub body_format: Option<BodyFormat>,
+    /// 偏执模式：每次签名后验证结果
+    pub paranoid: Option<bool>,
+    /// 签名结果退化时最多尝试的轮数
+    pub max_sign_attempts: Option<u32>,
+    /// 签名时使用随机数承诺
+    pub nonce_commitment: Option<bool>,
+    /// 新写入 D1 的保护方式
+    pub keystore: Option<KeystoreKind>,
+    /// TPM 密封绑定的 SHA-256 PCR 编号
+    pub tpm_pcrs: Option<Vec<u32>>,
+    /// D1 年龄达到该天数时建议轮换
+    pub rotation_recommend_days: Option<u64>,
+    /// D1 年龄达到该天数时必须轮换
+    pub rotation_require_days: Option<u64>,
+    /// D1 使用次数达到该值时建议轮换
+    pub rotation_recommend_uses: Option<u64>,
+    /// D1 使用次数达到该值时必须轮换
+    pub rotation_require_uses: Option<u64>,
+}
+
+impl Profile {
+    /// 密钥轮换策略，未设置的阈值不限制
+    pub fn rotation_policy(&self) -> RotationPolicy {
+        let days = |days: u64| Duration::from_secs(days.saturating_mul(86400));
+        RotationPolicy {
+            recommend_after: self.rotation_recommend_days.map(days),
+            require_after: self.rotation_require_days.map(days),
+            recommend_after_uses: self.rotation_recommend_uses,
+            require_after_uses: self.rotation_require_uses,
+        }
+    }
+}
+
+/// 配置文件中可选的 D1 保护方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeystoreKind {
+    /// 口令加密
+    #[default]
+    Passphrase,
+    /// 密封到本机 TPM 2.0
+    Tpm,
+}
+
+impl KeystoreKind {
+    /// 转换为密钥库使用的保护方式，`pcrs` 仅对 TPM 生效
+    pub fn backend(self, pcrs: Option<Vec<u32>>) -> Backend {
+        match self {
+            KeystoreKind::Passphrase => Backend::Passphrase,
+            KeystoreKind::Tpm => Backend::Tpm {
+                pcrs: pcrs.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// 配置文件中可选的响应外层格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvelopeFormat {
+    /// `{code, message, data}`
+    Standard,
+    /// `{status, msg, result}`
+    StatusMsgResult,
+}
+
+impl EnvelopeFormat {
+    /// 转换为客户端使用的响应外层
+    pub fn envelope(self) -> Arc<dyn ResponseEnvelope> {
+        match self {
+            EnvelopeFormat::Standard => Arc::new(FieldEnvelope::standard()),
+            EnvelopeFormat::StatusMsgResult => Arc::new(FieldEnvelope::status_msg_result()),
+        }
+    }
+}
+
+/// 默认配置文件路径
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("sm2-co-sign").join("config.toml"))
+}
+
+impl ConfigFile {
+    /// 加载配置文件，文件不存在时返回空配置
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取配置文件失败: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("解析配置文件失败: {:?}", path))
+    }
+
+    /// 解析 TOML 配置内容
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// 解析当前生效的 profile 名称
+    ///
+    /// 优先使用 `--profile` 指定的名称，其次为 default_profile，最后为 "default"。
+    pub fn profile_name<'a>(&'a self, requested: Option<&'a str>) -> anyhow::Result<&'a str> {
+        let name = requested
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE);
+        // Reason: profile 名称会作为目录名使用，禁止路径分隔符防止写出密钥目录之外
+        if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+            anyhow::bail!("无效的 profile 名称: {:?}", name);
+        }
+        Ok(name)
+    }
+
+    /// 获取指定 profile，配置文件中不存在时返回空 profile
+    pub fn profile(&self, name: &str) -> Profile {
+        self.profiles.get(name).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles() {
+        let config = ConfigFile::parse(
+            r#"
+            default_profile = "prod"
+
+            [profiles.prod]
+            server = "https://cosign.example.com"
+            timeout = 60
+            ca_cert = "/tmp/ca.pem"
+            key_dir = "/tmp/prod"
+            paranoid = true
+            max_sign_attempts = 5
+            nonce_commitment = true
+            keystore = "tpm"
+            tpm_pcrs = [0, 7]
+            rotation_recommend_days = 90
+            rotation_require_uses = 1000
+
+            [profiles.dev]
+            server = "http://127.0.0.1:7094"
+            verify_tls = false
+            envelope = "status-msg-result"
+            field_encoding = "hex"
+            body_format = "cbor"
+            "#,
+        )
+        .unwrap();
+
+        let name = config.profile_name(None).unwrap();
+        assert_eq!(name, "prod");
+        let profile = config.profile(name);
+        assert_eq!(profile.server.as_deref(), Some("https://cosign.example.com"));
+        assert_eq!(profile.timeout, Some(60));
+        assert_eq!(profile.verify_tls, None);
+        assert_eq!(profile.ca_cert, Some(PathBuf::from("/tmp/ca.pem")));
+        assert_eq!(profile.client_cert, None);
+        assert_eq!(profile.key_dir, Some(PathBuf::from("/tmp/prod")));
+        assert_eq!(profile.envelope, None);
+        assert_eq!(profile.field_encoding, None);
+        assert_eq!(profile.body_format, None);
+        assert_eq!(profile.paranoid, Some(true));
+        assert_eq!(profile.max_sign_attempts, Some(5));
+        assert_eq!(profile.nonce_commitment, Some(true));
+        assert_eq!(profile.keystore, Some(KeystoreKind::Tpm));
+        assert_eq!(
+            KeystoreKind::Tpm.backend(profile.tpm_pcrs.clone()),
+            Backend::Tpm { pcrs: vec![0, 7] }
+        );
+        assert_eq!(
+            profile.rotation_policy(),
+            RotationPolicy {
+                recommend_after: Some(Duration::from_secs(90 * 86400)),
+                require_after_uses: Some(1000),
+                ..RotationPolicy::default()
+            }
+        );
+        assert_eq!(config.profiles.len(), 2);
+
+        // --profile 优先于 default_profile
+        let name = config.profile_name(Some("dev")).unwrap();
+        assert_eq!(config.profile(name).verify_tls, Some(false));
+        assert_eq!(config.profile(name).envelope, Some(EnvelopeFormat::StatusMsgResult));
+        assert_eq!(config.profile(name).field_encoding, Some(Encoding::Hex));
+        assert_eq!(config.profile(name).body_format, Some(BodyFormat::Cbor));
+        assert_eq!(config.profile(name).keystore.unwrap_or_default().backend(None), Backend::Passphrase);
+        assert!(config.profile("missing").server.is_none());
+        assert!(config.profile("missing").rotation_policy().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_profile_name() {
+        let config = ConfigFile::default();
+        assert_eq!(config.profile_name(None).unwrap(), DEFAULT_PROFILE);
+        assert!(config.profile_name(Some("../etc")).is_err());
+        assert!(config.profile_name(Some("..")).is_err());
+        assert!(config.profile_name(Some("")).is_err());
+    }
+
+    #[test]
+    fn test_empty_config() {
+        let config = ConfigFile::parse("").unwrap();
+        assert!(config.profile(DEFAULT_PROFILE).server.is_none());
+        assert!(ConfigFile::parse("profiles = 1").is_err());
+        assert!(ConfigFile::parse("[profiles.x]\nenvelope = \"xml\"").is_err());
+    }
+}
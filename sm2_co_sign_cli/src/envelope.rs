@@ -0,0 +1,211 @@
+//! SM2 + SM4 数字信封
+//!
+//! 随机生成 SM4 密钥，使用接收方 SM2 公钥加密密钥，原文使用 SM4-GCM 分块流式加密，
+//! 大文件无需整体读入内存。文件格式：
+//!
+//! ```text
+//! "SM2ENV" || 版本(1) || 分块大小(u32 BE) || 密钥密文长度(u16 BE) || 密钥密文 || 分块...
+//! ```
+//!
+//! 密钥密文为 SM2 加密的 `SM4 密钥(16) || IV 前缀(7)`。每个分块为 `SM4-GCM 密文 || 标签(16)`，
+//! IV 为 `IV 前缀 || 分块序号(u32 BE) || 末块标志(1)`，AAD 为整个文件头；
+//! 末块明文长度总是小于分块大小（可以为空），截断、重排或替换分块均会导致解密失败。
+
+use sm2_co_sign_core::{sm4, CoSignProtocol};
+use std::io::{Read, Write};
+use zeroize::Zeroizing;
+
+/// 文件魔数
+const MAGIC: &[u8; 6] = b"SM2ENV";
+/// 格式版本
+const VERSION: u8 = 1;
+/// 默认分块大小
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+/// 分块大小上限
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+/// IV 前缀长度（IV 其余 5 字节为分块序号与末块标志）
+const IV_PREFIX_LEN: usize = 7;
+/// 密钥材料长度：SM4 密钥 || IV 前缀
+const KEY_MATERIAL_LEN: usize = sm4::SM4_KEY_LEN + IV_PREFIX_LEN;
+
+/// 信封文件头
+pub struct Header {
+    chunk_size: u32,
+    /// SM2 加密的密钥材料
+    pub key_ciphertext: Vec<u8>,
+    /// 原始文件头字节，作为每个分块的 AAD
+    bytes: Vec<u8>,
+}
+
+impl Header {
+    fn new(chunk_size: u32, key_ciphertext: Vec<u8>) -> anyhow::Result<Self> {
+        let key_len = u16::try_from(key_ciphertext.len()).map_err(|_| anyhow::anyhow!("密钥密文过长"))?;
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&chunk_size.to_be_bytes());
+        bytes.extend_from_slice(&key_len.to_be_bytes());
+        bytes.extend_from_slice(&key_ciphertext);
+        Ok(Self { chunk_size, key_ciphertext, bytes })
+    }
+
+    /// 从输入流读取文件头
+    pub fn read(input: &mut impl Read) -> anyhow::Result<Self> {
+        let mut fixed = [0u8; 13];
+        input
+            .read_exact(&mut fixed)
+            .map_err(|_| anyhow::anyhow!("不是数字信封文件（文件头不完整）"))?;
+        if &fixed[..6] != MAGIC {
+            anyhow::bail!("不是数字信封文件");
+        }
+        if fixed[6] != VERSION {
+            anyhow::bail!("不支持的数字信封版本: {}", fixed[6]);
+        }
+        let chunk_size = u32::from_be_bytes(fixed[7..11].try_into()?);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            anyhow::bail!("无效的分块大小: {}", chunk_size);
+        }
+        let key_len = u16::from_be_bytes(fixed[11..13].try_into()?) as usize;
+
+        let mut key_ciphertext = vec![0u8; key_len];
+        input
+            .read_exact(&mut key_ciphertext)
+            .map_err(|_| anyhow::anyhow!("数字信封文件头不完整"))?;
+        Self::new(chunk_size, key_ciphertext)
+    }
+
+    /// 文件头字节数
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+/// 分块 IV
+fn chunk_iv(prefix: &[u8], index: u32, last: bool) -> Vec<u8> {
+    let mut iv = prefix.to_vec();
+    iv.extend_from_slice(&index.to_be_bytes());
+    iv.push(last as u8);
+    iv
+}
+
+/// 尽量读满缓冲区，返回实际读取的字节数（小于缓冲区长度表示已到末尾）
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn next_index(index: u32) -> anyhow::Result<u32> {
+    index.checked_add(1).ok_or_else(|| anyhow::anyhow!("数据过大，分块数超出上限"))
+}
+
+/// 使用接收方公钥（64 字节 x||y）加密，返回原文字节数
+pub fn seal(public_key: &[u8], chunk_size: u32, mut input: impl Read, mut output: impl Write) -> anyhow::Result<u64> {
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        anyhow::bail!("分块大小须在 1 到 {} 字节之间", MAX_CHUNK_SIZE);
+    }
+    let material = Zeroizing::new(CoSignProtocol::generate_random(KEY_MATERIAL_LEN));
+    let (key, prefix) = material.split_at(sm4::SM4_KEY_LEN);
+    let header = Header::new(chunk_size, CoSignProtocol::encrypt(public_key, &material)?)?;
+    output.write_all(&header.bytes)?;
+
+    let mut buf = Zeroizing::new(vec![0u8; chunk_size as usize]);
+    let mut index = 0u32;
+    let mut total = 0u64;
+    loop {
+        let len = read_full(&mut input, &mut buf)?;
+        let last = len < buf.len();
+        let chunk = sm4::sm4_gcm_encrypt(key, &chunk_iv(prefix, index, last), &header.bytes, &buf[..len])?;
+        output.write_all(&chunk)?;
+        total += len as u64;
+        if last {
+            break;
+        }
+        index = next_index(index)?;
+    }
+    output.flush()?;
+    Ok(total)
+}
+
+/// 使用解密得到的密钥材料解密分块，返回原文字节数
+///
+/// 分块逐个校验后写出，出错时输出可能不完整，调用方应丢弃。
+pub fn open_body(header: &Header, material: &[u8], mut input: impl Read, mut output: impl Write) -> anyhow::Result<u64> {
+    if material.len() != KEY_MATERIAL_LEN {
+        anyhow::bail!("数字信封密钥长度错误");
+    }
+    let (key, prefix) = material.split_at(sm4::SM4_KEY_LEN);
+
+    let mut buf = vec![0u8; header.chunk_size as usize + sm4::SM4_GCM_TAG_LEN];
+    let mut index = 0u32;
+    let mut total = 0u64;
+    loop {
+        let len = read_full(&mut input, &mut buf)?;
+        let last = len < buf.len();
+        if len < sm4::SM4_GCM_TAG_LEN {
+            anyhow::bail!("数字信封数据被截断");
+        }
+        let plaintext = Zeroizing::new(
+            sm4::sm4_gcm_decrypt(key, &chunk_iv(prefix, index, last), &header.bytes, &buf[..len])
+                .map_err(|_| anyhow::anyhow!("数字信封第 {} 块校验失败，数据已损坏或被篡改", index))?,
+        );
+        output.write_all(&plaintext)?;
+        total += plaintext.len() as u64;
+        if last {
+            break;
+        }
+        index = next_index(index)?;
+    }
+    output.flush()?;
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(private_key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut input = data;
+        let header = Header::read(&mut input)?;
+        let material = CoSignProtocol::decrypt(private_key, &header.key_ciphertext)?.unwrap();
+        let mut plaintext = Vec::new();
+        open_body(&header, &material, input, &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let (private_key, public_key) = CoSignProtocol::generate_keypair();
+        for len in [0usize, 1, 15, 16, 17, 48, 100] {
+            let message = vec![0x5au8; len];
+            let mut sealed = Vec::new();
+            assert_eq!(seal(&public_key, 16, message.as_slice(), &mut sealed).unwrap(), len as u64);
+            assert_eq!(open(&private_key, &sealed).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_envelope_tamper_detected() {
+        let (private_key, public_key) = CoSignProtocol::generate_keypair();
+        let mut sealed = Vec::new();
+        seal(&public_key, 16, [0x11u8; 40].as_slice(), &mut sealed).unwrap();
+
+        // 截断到分块边界
+        let chunk = 16 + sm4::SM4_GCM_TAG_LEN;
+        assert!(open(&private_key, &sealed[..sealed.len() - (40 - 32 + sm4::SM4_GCM_TAG_LEN)]).is_err());
+        assert!(open(&private_key, &sealed[..sealed.len() - chunk]).is_err());
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert!(open(&private_key, &tampered).is_err());
+
+        assert!(open(&private_key, b"not an envelope").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! 输入输出数据格式
+
+use clap::ValueEnum;
+use sm2_co_sign_core::Encoding;
+
+/// 消息、签名、密文等二进制数据的编码格式（命令行取值，对应核心库的 [`Encoding`]）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DataFormat {
+    /// 原始字节
+    Raw,
+    /// 十六进制文本
+    Hex,
+    /// Base64 文本
+    Base64,
+    /// URL 安全字符集的 Base64 文本（无填充）
+    Base64url,
+}
+
+impl From<DataFormat> for Encoding {
+    fn from(format: DataFormat) -> Self {
+        match format {
+            DataFormat::Raw => Encoding::Raw,
+            DataFormat::Hex => Encoding::Hex,
+            DataFormat::Base64 => Encoding::Base64,
+            DataFormat::Base64url => Encoding::Base64Url,
+        }
+    }
+}
+
+impl DataFormat {
+    /// 按格式解码输入数据，文本格式忽略首尾空白
+    pub fn decode(self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Encoding::from(self)
+            .decode(data)
+            .map_err(|e| anyhow::Error::new(e).context(format!("输入不是有效的 {} 编码", Encoding::from(self))))
+    }
+
+    /// 按格式编码输出数据
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        Encoding::from(self).encode(data)
+    }
+}
+
+/// 签名输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SignatureFormat {
+    /// r||s 共 64 字节
+    Raw,
+    /// ASN.1 DER 编码 `SEQUENCE { r INTEGER, s INTEGER }`
+    Der,
+    /// GM/T 0010 PKCS#7 签名数据（分离式，需已安装用户证书）
+    P7,
+    /// `SM2 SIGNATURE` PEM 文本（内容为 DER）
+    Pem,
+}
+
+/// 命令的输入输出格式
+#[derive(Debug, Clone, Copy)]
+pub struct Formats {
+    /// 输入格式
+    pub input: DataFormat,
+    /// 输出格式，未指定时写文件为原始字节、终端显示为十六进制
+    pub output: Option<DataFormat>,
+}
+
+impl Formats {
+    /// 编码写入文件的数据
+    pub fn encode_file(&self, data: &[u8]) -> Vec<u8> {
+        self.output.unwrap_or(DataFormat::Raw).encode(data)
+    }
+
+    /// 编码在终端显示的数据
+    pub fn encode_display(&self, data: &[u8]) -> String {
+        String::from_utf8_lossy(&self.output.unwrap_or(DataFormat::Hex).encode(data)).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = [0x00u8, 0x01, 0xfe, 0xff];
+        for format in [DataFormat::Raw, DataFormat::Hex, DataFormat::Base64, DataFormat::Base64url] {
+            assert_eq!(format.decode(&format.encode(&data)).unwrap(), data);
+        }
+        assert_eq!(DataFormat::Hex.encode(&data), b"0001feff");
+        // 文本格式忽略末尾换行
+        assert_eq!(DataFormat::Base64.decode(b"AAH+/w==\n").unwrap(), data);
+        assert!(DataFormat::Hex.decode(b"zz").is_err());
+        assert_eq!(DataFormat::Base64url.encode(&data), b"AAH-_w");
+    }
+}
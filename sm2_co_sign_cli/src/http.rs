@@ -0,0 +1,108 @@
+//! 最小 HTTP/1.1 服务端实现
+//!
+//! `mock-server` 与 `serve` 共用：每个连接处理一个请求，响应后关闭；
+//! 请求体仅支持 Content-Length 方式的 JSON。
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// 请求体大小上限
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// HTTP 请求
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// 请求头（名称保持原样，查找时忽略大小写）
+    pub headers: Vec<(String, String)>,
+    pub body: Value,
+}
+
+impl Request {
+    /// 查找请求头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `Authorization: Bearer <token>` 中的 Token
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.header("authorization").and_then(|value| value.strip_prefix("Bearer "))
+    }
+}
+
+/// 读取并解析 HTTP/1.1 请求
+pub async fn read_request(reader: &mut BufReader<TcpStream>) -> anyhow::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().ok_or_else(|| anyhow::anyhow!("malformed request line"))?;
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut content_length = 0usize;
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            anyhow::bail!("unexpected end of request headers");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse()?;
+            }
+            headers.push((name.to_string(), value.to_string()));
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        anyhow::bail!("request body too large");
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+    let body = if body.is_empty() { Value::Null } else { serde_json::from_slice(&body)? };
+
+    Ok(Request { method, path, headers, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        502 => "Bad Gateway",
+        _ => "Internal Server Error",
+    }
+}
+
+/// 写出 JSON 响应并关闭连接
+pub async fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, String)],
+    body: Option<&Value>,
+) -> anyhow::Result<()> {
+    let body = body.map(Value::to_string).unwrap_or_default();
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason(status));
+    if !body.is_empty() {
+        response.push_str("Content-Type: application/json\r\n");
+    }
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body));
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! 密钥导出文件
+//!
+//! 导出内容包括加密后的 D1 密钥库、公钥与用户ID，用于在机器之间迁移密钥。
+//! PEM 格式包含两个块：`SM2 CO-SIGN KEY`（Base64 编码的 JSON 导出包）
+//! 与标准 `PUBLIC KEY`（SubjectPublicKeyInfo，便于其他工具读取公钥）。
+
+use crate::x509::public_key_to_spki;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sm2_co_sign_core::pem::{self, PUBLIC_KEY_LABEL};
+
+/// 导出包格式版本
+const BUNDLE_VERSION: u32 = 1;
+/// 导出包 PEM 标签
+const KEY_LABEL: &str = "SM2 CO-SIGN KEY";
+
+/// 导出文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyFormat {
+    /// PEM 文本
+    Pem,
+    /// JSON 文本
+    Json,
+}
+
+/// 密钥导出包
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyBundle {
+    pub version: u32,
+    pub user_id: String,
+    /// 公钥（十六进制）
+    pub public_key: String,
+    /// 加密后的 D1 密钥库
+    pub d1: serde_json::Value,
+    /// D1 对应的 P1 = D1·G（十六进制），导入时用于校验 D1 未被替换；旧版导出包无此字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p1: Option<String>,
+}
+
+impl KeyBundle {
+    /// 由加密后的 D1 密钥库文件内容与 P1 创建导出包
+    pub fn new(user_id: &str, public_key: &[u8], keystore: &[u8], p1: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            user_id: user_id.trim().to_string(),
+            public_key: hex::encode(public_key),
+            d1: serde_json::from_slice(keystore)?,
+            p1: Some(hex::encode(p1)),
+        })
+    }
+
+    /// 加密后的 D1 密钥库文件内容
+    pub fn keystore(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(&self.d1)?)
+    }
+
+    /// 公钥字节
+    pub fn public_key(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(hex::decode(&self.public_key)?)
+    }
+
+    /// P1 字节（旧版导出包返回 None）
+    pub fn p1(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.p1.as_deref().map(hex::decode).transpose()?)
+    }
+
+    /// 按格式编码导出包
+    pub fn encode(&self, format: KeyFormat) -> anyhow::Result<Vec<u8>> {
+        let json = serde_json::to_vec_pretty(self)?;
+        match format {
+            KeyFormat::Json => Ok(json),
+            KeyFormat::Pem => {
+                let mut text = pem::encode(KEY_LABEL, &json);
+                text.push_str(&pem::encode(PUBLIC_KEY_LABEL, &public_key_to_spki(&self.public_key()?)?));
+                Ok(text.into_bytes())
+            }
+        }
+    }
+
+    /// 解码导出包，自动识别 PEM 与 JSON 格式
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let text = std::str::from_utf8(data).map_err(|_| anyhow::anyhow!("密钥文件不是有效的文本编码"))?;
+        let bundle: Self = if text.trim_start().starts_with("-----BEGIN") {
+            let json = pem::decode(text, KEY_LABEL)?;
+            serde_json::from_slice(&json)?
+        } else {
+            serde_json::from_str(text)?
+        };
+        if bundle.version != BUNDLE_VERSION {
+            anyhow::bail!("不支持的密钥文件版本: {}", bundle.version);
+        }
+
+        // Reason: PUBLIC KEY 块可能被单独替换，与导出包内的公钥不一致时拒绝导入
+        if pem::contains(text, PUBLIC_KEY_LABEL) {
+            let spki = pem::decode(text, PUBLIC_KEY_LABEL)?;
+            if spki != public_key_to_spki(&bundle.public_key()?)? {
+                anyhow::bail!("密钥文件中的 PUBLIC KEY 与导出包公钥不一致");
+            }
+        }
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> KeyBundle {
+        let keystore = br#"{"version":1,"ciphertext":"00"}"#;
+        KeyBundle::new("user-1\n", &[0x11; 64], keystore, &[0x33; 64]).unwrap()
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        for format in [KeyFormat::Pem, KeyFormat::Json] {
+            let encoded = bundle().encode(format).unwrap();
+            let decoded = KeyBundle::decode(&encoded).unwrap();
+            assert_eq!(decoded.user_id, "user-1");
+            assert_eq!(decoded.public_key().unwrap(), vec![0x11; 64]);
+            assert_eq!(decoded.d1["ciphertext"], "00");
+            assert_eq!(decoded.p1().unwrap(), Some(vec![0x33; 64]));
+        }
+    }
+
+    #[test]
+    fn test_legacy_bundle_without_p1() {
+        let json = br#"{"version":1,"user_id":"user-1","public_key":"11","d1":{"version":1}}"#;
+        let decoded = KeyBundle::decode(json).unwrap();
+        assert_eq!(decoded.p1().unwrap(), None);
+    }
+
+    #[test]
+    fn test_public_key_mismatch() {
+        let pem = String::from_utf8(bundle().encode(KeyFormat::Pem).unwrap()).unwrap();
+        let other = pem::encode(PUBLIC_KEY_LABEL, &public_key_to_spki(&[0x22; 64]).unwrap());
+        let start = pem.find("-----BEGIN PUBLIC KEY-----").unwrap();
+        let tampered = format!("{}{}", &pem[..start], other);
+        assert!(KeyBundle::decode(tampered.as_bytes()).is_err());
+    }
+}
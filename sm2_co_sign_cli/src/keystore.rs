@@ -0,0 +1,319 @@
+//! 口令保护的 D1 密钥库
+//!
+//! D1 文件以 JSON 保存，口令经 PBKDF2-HMAC-SM3 派生 SM4 密钥，使用 SM4-GCM 加密：
+//!
+//! ```json
+//! {"version":1,"kdf":"pbkdf2-hmac-sm3","iterations":100000,"salt":"...","cipher":"sm4-gcm","iv":"...","ciphertext":"..."}
+//! ```
+//!
+//! 口令优先读取环境变量 SM2_COSIGN_PASSPHRASE，否则交互输入；同一进程内只输入一次。
+//!
+//! profile 配置 `keystore = "tpm"` 时，新写入的 D1 改为密封到本机 TPM 2.0（见 `tpm` 模块，需 `tpm` feature），
+//! 读取时按文件格式自动识别，不需要口令。
+
+use serde::{Deserialize, Serialize};
+use sm2_co_sign_core::{sm4, CoSignProtocol};
+use std::path::Path;
+use std::sync::OnceLock;
+use zeroize::Zeroizing;
+
+/// 非交互场景下提供密钥库口令的环境变量
+pub const PASSPHRASE_ENV: &str = "SM2_COSIGN_PASSPHRASE";
+
+/// 密钥库格式版本
+const KEYSTORE_VERSION: u32 = 1;
+/// 默认 PBKDF2 迭代次数，也是读取密钥库时接受的下限
+const DEFAULT_ITERATIONS: u32 = 100_000;
+/// 读取密钥库时接受的迭代次数上限，防止被篡改的文件让解密长时间挂起
+const MAX_ITERATIONS: u32 = 10 * DEFAULT_ITERATIONS;
+/// 盐长度
+const SALT_LEN: usize = 16;
+/// GCM 附加认证数据，绑定密钥库格式
+const KEYSTORE_AAD: &[u8] = b"sm2-co-sign-keystore-v1";
+/// HMAC-SM3 分组长度
+const SM3_BLOCK_LEN: usize = 64;
+
+/// TPM 密封文件的 `backend` 标记
+pub const TPM_BACKEND: &str = "tpm2";
+
+/// 进程内缓存的口令
+static PASSPHRASE: OnceLock<Zeroizing<String>> = OnceLock::new();
+/// 新写入 D1 的保护方式
+static BACKEND: OnceLock<Backend> = OnceLock::new();
+
+/// D1 文件的保护方式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// 口令派生密钥加密
+    #[default]
+    Passphrase,
+    /// 密封到本机 TPM，`pcrs` 非空时解封要求这些 SHA-256 PCR 与密封时一致
+    Tpm { pcrs: Vec<u32> },
+}
+
+/// 设置新写入 D1 的保护方式，进程启动时调用一次
+pub fn configure(backend: Backend) -> anyhow::Result<()> {
+    if matches!(backend, Backend::Tpm { .. }) && !cfg!(feature = "tpm") {
+        anyhow::bail!("keystore = \"tpm\" 需要以 tpm feature 编译（cargo build --features tpm）");
+    }
+    let _ = BACKEND.set(backend);
+    Ok(())
+}
+
+fn backend() -> &'static Backend {
+    BACKEND.get_or_init(Backend::default)
+}
+
+/// 密钥库文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    cipher: String,
+    iv: String,
+    ciphertext: String,
+}
+
+/// HMAC-SM3
+fn hmac_sm3(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut block = if key.len() > SM3_BLOCK_LEN {
+        Zeroizing::new(CoSignProtocol::sm3_hash(key))
+    } else {
+        Zeroizing::new(key.to_vec())
+    };
+    block.resize(SM3_BLOCK_LEN, 0);
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(data);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&CoSignProtocol::sm3_hash(&inner));
+    CoSignProtocol::sm3_hash(&outer)
+}
+
+/// PBKDF2-HMAC-SM3，派生 SM4 密钥（单个输出分组即可满足 16 字节）
+fn derive_key(passphrase: &[u8], salt: &[u8], iterations: u32) -> Zeroizing<Vec<u8>> {
+    let mut input = salt.to_vec();
+    input.extend_from_slice(&1u32.to_be_bytes());
+
+    let mut u = Zeroizing::new(hmac_sm3(passphrase, &input));
+    let mut t = Zeroizing::new(u.to_vec());
+    for _ in 1..iterations {
+        u = Zeroizing::new(hmac_sm3(passphrase, &u));
+        t.iter_mut().zip(u.iter()).for_each(|(a, b)| *a ^= b);
+    }
+    t.truncate(sm4::SM4_KEY_LEN);
+    t
+}
+
+fn seal_with_iterations(d1: &[u8], passphrase: &str, iterations: u32) -> anyhow::Result<Vec<u8>> {
+    let salt = CoSignProtocol::generate_random(SALT_LEN);
+    let iv = CoSignProtocol::generate_random(sm4::SM4_GCM_IV_LEN);
+    let key = derive_key(passphrase.as_bytes(), &salt, iterations);
+    let ciphertext = sm4::sm4_gcm_encrypt(&key, &iv, KEYSTORE_AAD, d1)?;
+
+    let file = KeystoreFile {
+        version: KEYSTORE_VERSION,
+        kdf: "pbkdf2-hmac-sm3".to_string(),
+        iterations,
+        salt: hex::encode(salt),
+        cipher: "sm4-gcm".to_string(),
+        iv: hex::encode(iv),
+        ciphertext: hex::encode(ciphertext),
+    };
+    Ok(serde_json::to_vec_pretty(&file)?)
+}
+
+/// 使用口令加密 D1，返回密钥库文件内容
+pub fn seal(d1: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    seal_with_iterations(d1, passphrase, DEFAULT_ITERATIONS)
+}
+
+/// 使用口令解密密钥库文件内容
+pub fn open(data: &[u8], passphrase: &str) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let file: KeystoreFile = serde_json::from_slice(data)?;
+    if file.version != KEYSTORE_VERSION || file.kdf != "pbkdf2-hmac-sm3" || file.cipher != "sm4-gcm" {
+        anyhow::bail!("不支持的密钥库格式（version {}, {}, {}）", file.version, file.kdf, file.cipher);
+    }
+
+    // Reason: 迭代次数取自文件本身，过小会失去抗暴力破解能力，过大会使 CLI 与签名代理挂起
+    if !(DEFAULT_ITERATIONS..=MAX_ITERATIONS).contains(&file.iterations) {
+        anyhow::bail!(
+            "密钥库迭代次数 {} 超出允许范围 {}–{}，文件可能已被篡改或损坏",
+            file.iterations,
+            DEFAULT_ITERATIONS,
+            MAX_ITERATIONS
+        );
+    }
+
+    let salt = hex::decode(&file.salt)?;
+    let iv = hex::decode(&file.iv)?;
+    let ciphertext = hex::decode(&file.ciphertext)?;
+    let key = derive_key(passphrase.as_bytes(), &salt, file.iterations);
+
+    sm4::sm4_gcm_decrypt(&key, &iv, KEYSTORE_AAD, &ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow::anyhow!("密钥库口令错误或文件已损坏"))
+}
+
+/// 是否为密钥库格式（旧版 D1 文件为原始字节）
+pub fn is_keystore(data: &[u8]) -> bool {
+    serde_json::from_slice::<KeystoreFile>(data).is_ok()
+}
+
+/// TPM 密封文件的格式标记
+#[derive(Deserialize)]
+struct SealedMarker {
+    backend: String,
+}
+
+/// 是否为 TPM 密封的 D1 文件
+pub fn is_tpm_sealed(data: &[u8]) -> bool {
+    serde_json::from_slice::<SealedMarker>(data).is_ok_and(|marker| marker.backend == TPM_BACKEND)
+}
+
+#[cfg(feature = "tpm")]
+fn tpm_seal(d1: &[u8], pcrs: &[u32]) -> anyhow::Result<Vec<u8>> {
+    crate::tpm::seal(d1, pcrs)
+}
+
+#[cfg(not(feature = "tpm"))]
+fn tpm_seal(_d1: &[u8], _pcrs: &[u32]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("未以 tpm feature 编译")
+}
+
+#[cfg(feature = "tpm")]
+fn tpm_unseal(data: &[u8]) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    crate::tpm::unseal(data)
+}
+
+#[cfg(not(feature = "tpm"))]
+fn tpm_unseal(_data: &[u8]) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    anyhow::bail!("D1 文件由 TPM 密封，需要以 tpm feature 编译的 sm2-cosign 才能解封")
+}
+
+/// 写入 D1 之前的准备：口令方式先取得口令，TPM 方式先确认 TPM 可用
+///
+/// 注册、初始化密钥后 D1 只保存一次，准备失败时应在请求服务端之前中止。
+pub fn prepare_write() -> anyhow::Result<()> {
+    match backend() {
+        Backend::Passphrase => passphrase(true).map(|_| ()),
+        #[cfg(feature = "tpm")]
+        Backend::Tpm { .. } => crate::tpm::probe(),
+        #[cfg(not(feature = "tpm"))]
+        Backend::Tpm { .. } => anyhow::bail!("未以 tpm feature 编译"),
+    }
+}
+
+/// 获取密钥库口令：进程内缓存 > 环境变量 > 交互输入（不回显）
+///
+/// `confirm` 为 true 时（新建密钥库）交互输入需确认一次。
+pub fn passphrase(confirm: bool) -> anyhow::Result<&'static str> {
+    if let Some(cached) = PASSPHRASE.get() {
+        return Ok(cached.as_str());
+    }
+
+    let value = match std::env::var(PASSPHRASE_ENV) {
+        Ok(value) => Zeroizing::new(value),
+        Err(_) => {
+            let value = Zeroizing::new(
+                rpassword::prompt_password("密钥库口令: ")
+                    .map_err(|e| anyhow::anyhow!("无法读取口令（非交互环境请设置 {}）: {}", PASSPHRASE_ENV, e))?,
+            );
+            if confirm {
+                let again = Zeroizing::new(rpassword::prompt_password("确认密钥库口令: ")?);
+                if *again != *value {
+                    anyhow::bail!("两次输入的口令不一致");
+                }
+            }
+            value
+        }
+    };
+    if value.is_empty() {
+        anyhow::bail!("密钥库口令不能为空");
+    }
+    crate::logging::add_secret(&value);
+
+    Ok(PASSPHRASE.get_or_init(|| value).as_str())
+}
+
+/// 交互输入另一个密钥库（如托管员私钥）的口令，不读取环境变量也不缓存
+pub fn prompt_passphrase(prompt: &str) -> anyhow::Result<Zeroizing<String>> {
+    let value = Zeroizing::new(rpassword::prompt_password(prompt).map_err(|e| anyhow::anyhow!("无法读取口令: {}", e))?);
+    crate::logging::add_secret(&value);
+    Ok(value)
+}
+
+/// 解锁读取到的 D1 文件内容，返回 (D1, 是否为旧版明文文件)
+pub fn unlock_d1(data: &[u8]) -> anyhow::Result<(Zeroizing<Vec<u8>>, bool)> {
+    if is_tpm_sealed(data) {
+        Ok((tpm_unseal(data)?, false))
+    } else if is_keystore(data) {
+        Ok((open(data, passphrase(false)?)?, false))
+    } else {
+        Ok((Zeroizing::new(data.to_vec()), true))
+    }
+}
+
+/// 按配置的保护方式加密并写入 D1（Unix 下文件权限为 0600）
+pub fn write_d1(path: &Path, d1: &[u8]) -> anyhow::Result<()> {
+    let sealed = match backend() {
+        Backend::Passphrase => seal(d1, passphrase(true)?)?,
+        Backend::Tpm { pcrs } => tpm_seal(d1, pcrs)?,
+    };
+    write_sealed(path, &sealed)
+}
+
+/// 写入已加密的密钥库文件内容（Unix 下文件权限为 0600）
+pub fn write_sealed(path: &Path, sealed: &[u8]) -> anyhow::Result<()> {
+    crate::paths::write_private(path, sealed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let d1 = [0x42u8; 32];
+        let sealed = seal(&d1, "correct horse").unwrap();
+        assert!(is_keystore(&sealed));
+        assert!(!is_keystore(&d1));
+
+        assert_eq!(open(&sealed, "correct horse").unwrap().as_slice(), &d1);
+        assert!(open(&sealed, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_out_of_range_iterations() {
+        for iterations in [0, 1, DEFAULT_ITERATIONS - 1, MAX_ITERATIONS + 1, u32::MAX] {
+            let sealed = seal_with_iterations(&[0x42u8; 32], "correct horse", iterations.min(10)).unwrap();
+            let mut file: KeystoreFile = serde_json::from_slice(&sealed).unwrap();
+            file.iterations = iterations;
+            let err = open(&serde_json::to_vec(&file).unwrap(), "correct horse").unwrap_err();
+            assert!(err.to_string().contains("迭代次数"));
+        }
+    }
+
+    #[test]
+    fn test_tpm_sealed_detection() {
+        let sealed = br#"{"version":1,"backend":"tpm2","pcr_bank":"sha256","pcrs":[7],"public":"00","private":"00"}"#;
+        assert!(is_tpm_sealed(sealed));
+        assert!(!is_keystore(sealed));
+
+        let keystore = seal_with_iterations(&[0x42u8; 32], "correct horse", 10).unwrap();
+        assert!(!is_tpm_sealed(&keystore));
+        assert!(!is_tpm_sealed(&[0x42u8; 32]));
+    }
+
+    #[test]
+    fn test_derive_key() {
+        let key = derive_key(b"password", b"salt", 2);
+        assert_eq!(key.len(), sm4::SM4_KEY_LEN);
+        assert_eq!(key, derive_key(b"password", b"salt", 2));
+        assert_ne!(key, derive_key(b"password", b"salt", 3));
+        assert_ne!(key, derive_key(b"password", b"pepper", 2));
+    }
+}
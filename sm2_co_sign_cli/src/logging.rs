@@ -0,0 +1,126 @@
+//! 日志输出
+//!
+//! 使用 tracing-subscriber 将客户端的 debug!/info! 等日志输出到 stderr。
+//! 每条日志写出前经过脱敏：运行期登记的密码、Token、口令，以及 `Bearer <token>` 均替换为占位符。
+
+use sm2_co_sign_core::REDACTED;
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::EnvFilter;
+
+/// 运行期登记的敏感值
+static SECRETS: OnceLock<Mutex<Vec<String>>> = OnceLock::new();
+
+fn secrets() -> std::sync::MutexGuard<'static, Vec<String>> {
+    SECRETS
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 登记需要从日志中隐藏的敏感值
+pub fn add_secret(secret: &str) {
+    let secret = secret.trim();
+    if !secret.is_empty() {
+        secrets().push(secret.to_string());
+    }
+}
+
+/// 初始化日志：`--log-level` > `-v` 次数 > 环境变量 RUST_LOG > 仅输出警告
+///
+/// `-v` 为 info，`-vv` 为 debug，`-vvv` 及以上为 trace。
+pub fn init(verbose: u8, log_level: Option<&str>) -> anyhow::Result<()> {
+    let filter = match (log_level, verbose) {
+        (Some(level), _) => EnvFilter::try_new(level).map_err(|e| anyhow::anyhow!("无效的日志级别 {}: {}", level, e))?,
+        (None, 0) => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        (None, 1) => EnvFilter::new("info"),
+        (None, 2) => EnvFilter::new("debug"),
+        (None, _) => EnvFilter::new("trace"),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(RedactingWriter::default)
+        .with_target(verbose > 1 || log_level.is_some())
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("初始化日志失败: {}", e))
+}
+
+/// 隐藏文本中的敏感值
+fn redact(text: &str) -> String {
+    let mut text = redact_bearer(text);
+    for secret in secrets().iter() {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    text
+}
+
+/// 隐藏 `Bearer <token>` 中的 Token
+fn redact_bearer(text: &str) -> String {
+    const BEARER: &str = "Bearer ";
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(BEARER) {
+        let start = pos + BEARER.len();
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == ',')
+            .map_or(rest.len(), |len| start + len);
+        if end > start {
+            result.push_str(REDACTED);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 缓存单条日志，写出到 stderr 前脱敏
+#[derive(Default)]
+struct RedactingWriter {
+    buffer: Vec<u8>,
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = redact(&String::from_utf8_lossy(&self.buffer));
+            self.buffer.clear();
+            std::io::stderr().write_all(text.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_bearer() {
+        assert_eq!(redact_bearer("Authorization: Bearer abc.def"), format!("Authorization: Bearer {}", REDACTED));
+        assert_eq!(
+            redact_bearer(r#"{"auth":"Bearer xyz","n":1}"#),
+            format!(r#"{{"auth":"Bearer {}","n":1}}"#, REDACTED)
+        );
+        assert_eq!(redact_bearer("no token here"), "no token here");
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        add_secret("s3cr3t-value");
+        add_secret("  ");
+        assert_eq!(redact("password=s3cr3t-value ok"), format!("password={} ok", REDACTED));
+    }
+}
@@ -1,16 +1,118 @@
 //! SM2 协同签名 CLI 工具
 
+#[cfg(unix)]
+mod agent;
+mod bench;
+mod config;
+mod envelope;
+mod format;
+mod http;
+mod keyfile;
+mod keystore;
+mod logging;
+mod mock_server;
+mod output;
+mod paths;
+mod qr;
+mod serve;
+mod signer;
+mod stdio;
+#[cfg(feature = "tpm")]
+mod tpm;
+mod x509;
+
+use bench::Stats;
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
-use sm2_co_sign_core::{CoSignClient, ClientConfig};
-use std::path::PathBuf;
+use serde_json::json;
+use config::{ConfigFile, EnvelopeFormat};
+use format::{DataFormat, Formats, SignatureFormat};
+use keyfile::{KeyBundle, KeyFormat};
+use x509::{Certificate, KeyPurpose};
+use output::{exit_code, Output, UsageError};
+use paths::StatePaths;
+use qr::QrArgs;
+use sm2_co_sign_core::protocol::DEFAULT_USER_ID;
+use sm2_co_sign_core::{asn1, pem, pkcs7, xmldsig, ApiRequest, CoSignClient, CoSignProtocol, ClientConfig, DigestMode, ErrorKind, KeyLifetime, PublicKey, Recipient, RotationStatus, Session, SignOptions, REDACTED};
+use sm2_co_sign_core::escrow::{EscrowPackage, ESCROW_OFFICERS};
+use sm2_co_sign_core::receipt::{self, Receipt, ReceiptChain};
+use sm2_co_sign_core::sm3::Sm3;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// 默认服务器地址
+const DEFAULT_SERVER: &str = "http://127.0.0.1:7094";
+
+/// 非交互场景下提供密码的环境变量
+const PASSWORD_ENV: &str = "SM2_COSIGN_PASSWORD";
+
+/// 自动重新登录使用的用户名环境变量
+const USERNAME_ENV: &str = "SM2_COSIGN_USERNAME";
 
 #[derive(Parser)]
 #[command(name = "sm2-co-sign")]
 #[command(about = "SM2 协同签名客户端工具", long_about = None)]
 struct Cli {
-    /// 服务器地址
-    #[arg(short, long, default_value = "http://127.0.0.1:7094")]
-    server: String,
+    /// 服务器地址（默认 http://127.0.0.1:7094）
+    #[arg(short, long)]
+    server: Option<String>,
+
+    /// 配置文件路径（默认 ~/.config/sm2-co-sign/config.toml）
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// 使用的 profile（每个 profile 拥有独立的 Token、D1 与公钥）
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// 输出详细日志（-v 为 info，-vv 为 debug，-vvv 为 trace），日志写到 stderr 且已脱敏
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// 日志过滤规则，如 debug 或 sm2_co_sign_core=trace（优先于 -v）
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// 请求超时（秒，默认 30）
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+
+    /// 不验证服务端 TLS 证书（仅用于测试环境）
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    /// 额外信任的 CA 证书（PEM）
+    #[arg(long, global = true)]
+    ca_cert: Option<PathBuf>,
+
+    /// 双向 TLS 客户端证书（PEM，需同时指定 --client-key）
+    #[arg(long, global = true, requires = "client_key")]
+    client_cert: Option<PathBuf>,
+
+    /// 双向 TLS 客户端私钥（PEM，需同时指定 --client-cert）
+    #[arg(long, global = true, requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// 密钥目录，存放 D1、Token、用户ID、公钥等本地文件（默认 ~/.local/share/sm2-co-sign）
+    #[arg(long, global = true)]
+    key_dir: Option<PathBuf>,
+
+    /// 以 JSON 格式输出结果（便于脚本调用）
+    #[arg(long)]
+    json: bool,
+
+    /// 不显示大文件处理进度条（stderr 不是终端时自动隐藏）
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// 消息、签名、密文输入的编码格式
+    #[arg(long, value_enum, global = true, default_value = "raw")]
+    in_format: DataFormat,
+
+    /// 签名、密文、明文输出的编码格式（默认：写文件为 raw，终端显示为 hex）
+    #[arg(long, value_enum, global = true)]
+    out_format: Option<DataFormat>,
 
     #[command(subcommand)]
     command: Commands,
@@ -23,234 +125,3164 @@ enum Commands {
         /// 用户名
         #[arg(short, long)]
         username: String,
-        /// 密码
+        /// 密码（不推荐：会留在 shell 历史中；省略时读取 SM2_COSIGN_PASSWORD 或交互输入）
         #[arg(short, long)]
-        password: String,
+        password: Option<String>,
+        /// 只执行本地计算，打印将要发送的请求（请求体已脱敏）而不实际发送
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        qr: QrArgs,
     },
     /// 用户登录
     Login {
         /// 用户名
         #[arg(short, long)]
         username: String,
-        /// 密码
+        /// 密码（不推荐：会留在 shell 历史中；省略时读取 SM2_COSIGN_PASSWORD 或交互输入）
         #[arg(short, long)]
-        password: String,
+        password: Option<String>,
+        /// Token 保存路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// 使用密钥库口令加密保存密码，Token 过期时自动重新登录
+        #[arg(long)]
+        remember: bool,
     },
     /// 用户登出
     Logout {
-        /// Token 文件路径
-        #[arg(short, long, default_value = ".token")]
-        token_file: PathBuf,
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+    },
+    /// 查看当前用户信息
+    Whoami {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        #[command(flatten)]
+        qr: QrArgs,
+    },
+    /// 初始化（重置）密钥，需先登录
+    InitKey {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 覆盖已存在的 D1 文件
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        qr: QrArgs,
     },
     /// 协同签名
     Sign {
-        /// Token 文件路径
-        #[arg(short, long, default_value = ".token")]
-        token_file: PathBuf,
-        /// D1 文件路径
-        #[arg(long, default_value = ".d1")]
-        d1_file: PathBuf,
-        /// 消息文件路径
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 消息文件路径（- 表示 stdin）
         #[arg(short, long)]
         message: PathBuf,
-        /// 输出签名文件路径
+        /// 输出签名文件路径（- 表示 stdout）
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// 签名格式：raw 为 r||s，der 为 ASN.1 DER，p7 为 PKCS#7 签名数据（需先执行 cert install），pem 为 PEM 文本
+        #[arg(long, value_enum, default_value = "raw")]
+        sig_format: SignatureFormat,
+        /// 交易流水号，随签名请求发送供网关审计
+        #[arg(long)]
+        transaction_id: Option<String>,
+        /// 业务类型
+        #[arg(long)]
+        business_type: Option<String>,
+        /// 备注
+        #[arg(long)]
+        remark: Option<String>,
+        /// 以 local keygen 生成的私钥签发操作回执，追加到密钥目录的回执文件
+        #[arg(long)]
+        receipt: bool,
+        /// 只执行本地计算，打印将要发送的请求（请求体已脱敏）而不实际发送
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        qr: QrArgs,
+    },
+    /// 协同签名并输出 GM/T 0010 PKCS#7 签名数据（SignedData）
+    P7sign {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 消息文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 签名者证书（PEM 或 DER，默认为 cert install 保存的证书）
+        #[arg(long)]
+        cert: Option<PathBuf>,
+        /// 不在签名数据中包含原文
+        #[arg(long)]
+        detached: bool,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// PDF 协同签名
+    ///
+    /// 输入须为已准备好签名域的 PDF（含 /ByteRange 占位与全零的 /Contents 十六进制串），
+    /// 签名以 GM/T 0010 PKCS#7（分离式）写入 /Contents。
+    PdfSign {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 待签名 PDF（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// 签名者证书（PEM 或 DER，默认为 cert install 保存的证书）
+        #[arg(long)]
+        cert: Option<PathBuf>,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// XML 数字签名（XMLDSig，SM2-SM3）
+    ///
+    /// 默认生成封内签名，`<ds:Signature>` 插入根元素末尾；指定 --detached 时对输入的原始字节生成分离签名，
+    /// 输出独立的签名文档。
+    XmlSign {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 待签名文件（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// 签名者证书（PEM 或 DER，默认为 cert install 保存的证书），写入 KeyInfo
+        #[arg(long)]
+        cert: Option<PathBuf>,
+        /// 生成分离签名，参数为写入 Reference 的 URI（如文件名）
+        #[arg(long, value_name = "URI")]
+        detached: Option<String>,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// 批量协同签名
+    ///
+    /// 清单文件每行一个待签名文件路径（忽略空行与 # 开头的注释），
+    /// 签名写入输出目录下的 `<文件名>.sig`，汇总报告写入 `report.json`。
+    /// 有文件签名失败时退出码为 1。
+    SignBatch {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 清单文件路径
+        #[arg(long)]
+        manifest: PathBuf,
+        /// 签名输出目录
+        #[arg(long)]
+        out_dir: PathBuf,
     },
     /// 协同解密
     Decrypt {
-        /// Token 文件路径
-        #[arg(short, long, default_value = ".token")]
-        token_file: PathBuf,
-        /// D1 文件路径
-        #[arg(long, default_value = ".d1")]
-        d1_file: PathBuf,
-        /// 密文文件路径
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 密文文件路径（- 表示 stdin）
         #[arg(short, long)]
         ciphertext: PathBuf,
-        /// 输出明文文件路径
+        /// 输出明文文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 以 local keygen 生成的私钥签发操作回执，追加到密钥目录的回执文件
+        #[arg(long)]
+        receipt: bool,
+        /// 只执行本地计算，打印将要发送的请求（请求体已脱敏）而不实际发送
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// SM2 加密（本地计算，无需登录；--to 首次加密给某用户时需登录以查询其公钥）
+    Encrypt {
+        /// 明文文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+        /// 加密给该用户：向服务端查询其公钥，首次取得后固定在密钥目录中
+        #[arg(long, conflicts_with = "public_key")]
+        to: Option<String>,
+        /// 输出密文文件路径（- 表示 stdout）
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// 以 `SM2 CIPHERTEXT` PEM 文本输出（内容为 GM/T 0009 DER），忽略 --out-format
+        #[arg(long)]
+        armor: bool,
+    },
+    /// 计算 SM3 摘要（本地计算，无需登录）
+    ///
+    /// 默认输出 SM3(M)，与 sign 子命令提交的消息哈希一致；
+    /// --za 时输出标准 SM2 签名预处理的 SM3(ZA || M)。
+    Sm3 {
+        /// 输入文件路径（- 表示 stdin）
+        file: PathBuf,
+        /// 计算 SM3(ZA || M)，ZA 由默认用户标识与公钥计算
+        #[arg(long)]
+        za: bool,
+        /// 公钥文件路径（默认位于密钥目录，仅 --za 时使用）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+    /// 验证签名
+    ///
+    /// 验签通过时退出码为 0，验签失败时退出码为 6
+    Verify {
+        /// 消息文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 签名文件路径（原始 r||s、DER 或十六进制文本）
+        #[arg(long)]
+        signature: PathBuf,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+    /// 校验对方证书：签名链、有效期与密钥用途
+    ///
+    /// 全部通过时退出码为 0，否则为 6
+    VerifyCert {
+        /// 待校验证书（PEM 或 DER）
+        #[arg(long)]
+        cert: PathBuf,
+        /// 受信任的 CA 证书（PEM 可包含根证书与中间证书）
+        #[arg(long)]
+        ca: PathBuf,
+        /// 要求的密钥用途
+        #[arg(long, value_enum, default_value = "sign")]
+        purpose: KeyPurpose,
+    },
+    /// 生成 PKCS#10 证书请求或自签名证书（由协同签名完成签名）
+    Csr {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 证书主题，如 "CN=Alice,O=Corp"（支持 CN、C、ST、L、O、OU、emailAddress）
+        #[arg(long)]
+        subject: String,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+        /// 以 PEM 格式输出（默认 DER）
+        #[arg(long)]
+        pem: bool,
+        /// 生成有效期为指定天数的自签名证书，而不是证书请求
+        #[arg(long, value_name = "DAYS")]
+        self_signed: Option<u32>,
+    },
+    /// 本地 SM2 运算（使用完整私钥，非协同，无需登录）
+    Local {
+        #[command(subcommand)]
+        command: LocalCommands,
+    },
+    /// 用户证书管理
+    Cert {
+        #[command(subcommand)]
+        command: CertCommands,
+    },
+    /// Token 管理
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+    /// 密钥导出与导入
+    Key {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+    /// SM2 + SM4 数字信封，适用于大文件加解密
+    Envelope {
+        #[command(subcommand)]
+        command: EnvelopeCommands,
+    },
+    /// 操作回执（sign / decrypt 的 --receipt 签发）的校验与导出
+    Receipt {
+        #[command(subcommand)]
+        command: ReceiptCommands,
     },
     /// 健康检查
     Health,
+    /// 算法自检（已知答案测试与本地 SM2 往返，不访问网络）
+    Selftest,
+    /// 启动签名代理：持有解锁后的 D1，通过 Unix 域套接字为本机其他进程提供协同签名
+    #[cfg(unix)]
+    Agent {
+        /// 套接字路径（默认位于密钥目录的 agent.sock）
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+    },
+    /// 启动本地 HTTP 服务：为本机浏览器扩展与旧系统提供签名、解密与验签 REST 接口
+    Serve {
+        /// 监听地址（仅允许回环地址）
+        #[arg(long, default_value = "127.0.0.1:7890")]
+        listen: String,
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 允许跨域访问的 Origin，可多次指定（如 chrome-extension://<id>）
+        #[arg(long = "allow-origin")]
+        allow_origin: Vec<String>,
+    },
+    /// 启动本地模拟服务端（内存中模拟 D2 计算，仅用于开发测试）
+    MockServer {
+        /// 监听地址
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// 监听端口（默认与 CLI 默认服务器地址一致）
+        #[arg(long, default_value_t = 7094)]
+        port: u16,
+    },
+    /// 性能测试：本地协议各步骤耗时，可选对服务端的完整协同签名
+    Bench {
+        /// 每项测试的执行次数
+        #[arg(short = 'n', long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+        /// 同时测试对当前服务端的完整协同签名（需已登录并注册）
+        #[arg(long)]
+        remote: bool,
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LocalCommands {
+    /// 生成本地 SM2 密钥对，私钥使用密钥库口令加密保存
+    Keygen {
+        /// 私钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+        /// 覆盖已存在的私钥文件
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        qr: QrArgs,
+    },
+    /// 标准 SM2 签名（e = SM3(ZA || M)，默认用户标识）
+    Sign {
+        /// 私钥文件路径（密钥库、原始 32 字节或十六进制，默认位于密钥目录）
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+        /// 消息文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 输出签名文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        qr: QrArgs,
+    },
+    /// 标准 SM2 验签
+    ///
+    /// 验签通过时退出码为 0，验签失败时退出码为 6
+    Verify {
+        /// 消息文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 签名文件路径（原始 r||s、DER 或十六进制文本）
+        #[arg(long)]
+        signature: PathBuf,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+    /// 标准 SM2 加密
+    Encrypt {
+        /// 明文文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        message: PathBuf,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+        /// 输出密文文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 以 `SM2 CIPHERTEXT` PEM 文本输出（内容为 GM/T 0009 DER），忽略 --out-format
+        #[arg(long)]
+        armor: bool,
+    },
+    /// 标准 SM2 解密
+    Decrypt {
+        /// 私钥文件路径（密钥库、原始 32 字节或十六进制，默认位于密钥目录）
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+        /// 密文文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        ciphertext: PathBuf,
+        /// 输出明文文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CertCommands {
+    /// 显示证书信息（默认读取本地证书）
+    Show {
+        /// 从服务端获取证书而不是读取本地证书
+        #[arg(long)]
+        remote: bool,
+        /// Token 文件路径（默认位于密钥目录，仅 --remote 时使用）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// 证书文件路径（默认位于密钥目录）
+        #[arg(long)]
+        cert_file: Option<PathBuf>,
+    },
+    /// 从服务端获取证书，校验与协同公钥一致后保存到密钥目录
+    Install {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// 证书保存路径（默认位于密钥目录）
+        #[arg(long)]
+        cert_file: Option<PathBuf>,
+    },
+    /// 校验证书公钥与协同公钥是否一致
+    ///
+    /// 一致时退出码为 0，不一致时退出码为 6
+    Verify {
+        /// 证书文件路径（PEM 或 DER，默认位于密钥目录）
+        #[arg(long)]
+        cert_file: Option<PathBuf>,
+        /// 公钥文件路径（默认位于密钥目录）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// 查看 Token 过期时间，并向服务端确认是否有效
+    Status {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+    },
+    /// 删除本地 Token（不通知服务端）
+    Clear {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyCommands {
+    /// 导出加密后的 D1、公钥与用户ID，用于迁移到其他机器
+    Export {
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 导出文件格式
+        #[arg(long, value_enum, default_value = "pem")]
+        format: KeyFormat,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// 导入 key export 生成的密钥文件（PEM 或 JSON）
+    Import {
+        /// 密钥文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 覆盖已存在的 D1 文件
+        #[arg(long)]
+        force: bool,
+    },
+    /// 将 D1 拆分后分别加密给两名托管员，两人的私钥同时参与才能恢复
+    EscrowExport {
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 托管员 A 的 SM2 公钥文件（PEM、DER、十六进制或原始字节）
+        #[arg(long)]
+        officer_a: PathBuf,
+        /// 托管员 B 的 SM2 公钥文件
+        #[arg(long)]
+        officer_b: PathBuf,
+        /// 输出托管包路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// 以两名托管员的私钥恢复 key escrow-export 托管的 D1
+    EscrowImport {
+        /// 托管包路径（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// 托管员 A 的私钥文件（local keygen 生成的密钥库，或原始 32 字节 / 十六进制）
+        #[arg(long)]
+        officer_a_key: PathBuf,
+        /// 托管员 B 的私钥文件
+        #[arg(long)]
+        officer_b_key: PathBuf,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 覆盖已存在的 D1 文件
+        #[arg(long)]
+        force: bool,
+    },
+    /// 刷新私钥分量（新的 D1/D2，公钥不变），服务端确认后替换本地 D1
+    Rotate {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvelopeCommands {
+    /// 使用接收方 SM2 公钥加密（本地计算，无需登录）
+    Encrypt {
+        /// 明文文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// 输出信封文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+        /// 接收方公钥文件路径（默认为密钥目录中的协同公钥）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+        /// SM4 分块大小（字节）
+        #[arg(long, default_value_t = envelope::DEFAULT_CHUNK_SIZE)]
+        chunk_size: u32,
+    },
+    /// 协同解密信封中的 SM4 密钥，再流式解密原文
+    Decrypt {
+        /// Token 文件路径（默认位于密钥目录）
+        #[arg(short, long)]
+        token_file: Option<PathBuf>,
+        /// D1 文件路径（默认位于密钥目录）
+        #[arg(long)]
+        d1_file: Option<PathBuf>,
+        /// 信封文件路径（- 表示 stdin）
+        #[arg(short, long)]
+        input: PathBuf,
+        /// 输出明文文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReceiptCommands {
+    /// 校验回执链：每张回执的签名、序号连续且与上一张链接
+    Verify {
+        /// 回执文件路径（- 表示 stdin，默认位于密钥目录）
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// 要求回执由该公钥签发（local keygen 生成的公钥文件）
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+    /// 校验后导出指定序号范围的回执，用于争议处理
+    Export {
+        /// 回执文件路径（- 表示 stdin，默认位于密钥目录）
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// 起始序号（含）
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        /// 结束序号（含，默认到最后一张）
+        #[arg(long)]
+        to: Option<u64>,
+        /// 输出文件路径（- 表示 stdout）
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+impl Commands {
+    /// 命令结果是否写到 stdout（`-o -`）
+    fn writes_to_stdout(&self) -> bool {
+        match self {
+            Commands::Sign { output, .. }
+            | Commands::Decrypt { output, .. }
+            | Commands::Encrypt { output, .. }
+            | Commands::Local {
+                command:
+                    LocalCommands::Sign { output, .. }
+                    | LocalCommands::Encrypt { output, .. }
+                    | LocalCommands::Decrypt { output, .. },
+            } => output.as_deref().is_some_and(stdio::is_stdio),
+            Commands::Csr { output, .. }
+            | Commands::P7sign { output, .. }
+            | Commands::PdfSign { output, .. }
+            | Commands::XmlSign { output, .. }
+            | Commands::Key {
+                command: KeyCommands::Export { output, .. } | KeyCommands::EscrowExport { output, .. },
+            }
+            | Commands::Envelope {
+                command: EnvelopeCommands::Encrypt { output, .. } | EnvelopeCommands::Decrypt { output, .. },
+            }
+            | Commands::Receipt { command: ReceiptCommands::Export { output, .. } } => stdio::is_stdio(output),
+            _ => false,
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
-    
+async fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // Reason: 解析失败时无法得到 --json 标志，直接检查原始参数；帮助与版本信息仍按 clap 默认处理
+        Err(e) if e.use_stderr() && std::env::args().any(|arg| arg == "--json") => {
+            let err: anyhow::Error = UsageError(e.to_string().trim().to_string()).into();
+            std::process::exit(Output::new(true, false).error(&err));
+        }
+        Err(e) => e.exit(),
+    };
+    let out = Output::new(cli.json, cli.command.writes_to_stdout()).with_quiet(cli.quiet);
+
+    if cli.json && cli.command.writes_to_stdout() {
+        std::process::exit(out.error(&UsageError("--json 不能与 -o - 同时使用".to_string()).into()));
+    }
+
+    if let Err(e) = logging::init(cli.verbose, cli.log_level.as_deref()) {
+        std::process::exit(out.error(&e));
+    }
+
+    if let Err(e) = run(cli, &out).await {
+        std::process::exit(out.error(&e));
+    }
+}
+
+async fn run(cli: Cli, out: &Output) -> anyhow::Result<()> {
+    // 加载配置文件，命令行参数优先于 profile
+    let config_file = match cli.config.clone().or_else(config::default_config_path) {
+        Some(path) => ConfigFile::load(&path)?,
+        None => ConfigFile::default(),
+    };
+    let profile_name = config_file.profile_name(cli.profile.as_deref())?;
+    let profile = config_file.profile(profile_name);
+    keystore::configure(profile.keystore.unwrap_or_default().backend(profile.tpm_pcrs.clone()))?;
+    let rotation_policy = profile.rotation_policy();
+
     let config = ClientConfig {
-        server_url: cli.server.clone(),
-        timeout: 30,
-        verify_tls: false,
+        server_url: cli
+            .server
+            .clone()
+            .or(profile.server)
+            .unwrap_or_else(|| DEFAULT_SERVER.to_string()),
+        timeout: cli.timeout.or(profile.timeout).unwrap_or(30),
+        verify_tls: !cli.insecure && profile.verify_tls.unwrap_or(true),
+        ca_cert_pem: cli
+            .ca_cert
+            .clone()
+            .or(profile.ca_cert)
+            .map(|path| read_pem(&path, "CA 证书"))
+            .transpose()?,
+        client_identity_pem: client_identity(
+            cli.client_cert.clone().or(profile.client_cert),
+            cli.client_key.clone().or(profile.client_key),
+        )?,
+        envelope: profile.envelope.unwrap_or(EnvelopeFormat::Standard).envelope(),
+        encoding: profile.field_encoding.unwrap_or_default(),
+        body_format: profile.body_format.unwrap_or_default(),
+        paranoid: profile.paranoid.unwrap_or(false),
+        max_sign_attempts: profile.max_sign_attempts.unwrap_or(3),
+        nonce_commitment: profile.nonce_commitment.unwrap_or(false),
+        transport: None,
+        key_protector: None,
+        device_attestor: None,
+        receipts: None,
+        rotation_policy,
+    };
+    if !config.verify_tls {
+        out.warn("警告：已关闭 TLS 证书验证，连接可能被中间人攻击");
+    }
+    // 密钥目录：--key-dir > profile 的 key_dir > 命名 profile 的独立目录 > 默认目录
+    let key_dir = match cli.key_dir.clone().or(profile.key_dir) {
+        Some(dir) => dir,
+        None if cli.profile.is_some() => paths::profile_dir(profile_name),
+        None => paths::default_key_dir(),
     };
-    
+    let paths = StatePaths::new(key_dir);
+    let formats = Formats {
+        input: cli.in_format,
+        output: cli.out_format,
+    };
+
     match cli.command {
-        Commands::Register { username, password } => {
-            do_register(&config, &username, &password).await?;
+        Commands::Register { username, password, dry_run, qr } => {
+            let password = resolve_password(password, true)?;
+            do_register(out, &config, &paths, &username, &password, dry_run, &qr).await?;
         }
-        Commands::Login { username, password } => {
-            do_login(&config, &username, &password).await?;
+        Commands::Login { username, password, token_file, remember } => {
+            let password = resolve_password(password, false)?;
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            do_login(out, &config, &paths, &token_file, &username, &password, remember).await?;
         }
         Commands::Logout { token_file } => {
-            do_logout(&config, &token_file).await?;
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            do_logout(out, &config, &paths, &token_file).await?;
+        }
+        Commands::Whoami { token_file, qr } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            do_whoami(out, &config, &paths, &token_file, &qr).await?;
+        }
+        Commands::InitKey { token_file, d1_file, force, qr } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            do_init_key(out, &config, &paths, &token_file, &d1_file, force, &qr).await?;
         }
-        Commands::Sign { token_file, d1_file, message, output } => {
-            do_sign(&config, &token_file, &d1_file, &message, output.as_ref()).await?;
+        Commands::Sign {
+            token_file,
+            d1_file,
+            message,
+            output,
+            sig_format,
+            transaction_id,
+            business_type,
+            remark,
+            receipt,
+            dry_run,
+            qr,
+        } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let options = SignOptions { transaction_id, business_type, remark };
+            let receipts = open_receipts(out, &paths, receipt)?;
+            let config = ClientConfig { receipts: receipts.clone(), ..config };
+            do_sign(
+                out,
+                &config,
+                &paths,
+                &token_file,
+                &d1_file,
+                &message,
+                output.as_ref(),
+                formats,
+                sig_format,
+                &options,
+                dry_run,
+                &qr,
+            )
+            .await?;
+            save_receipts(out, &paths, receipts.as_deref())?;
         }
-        Commands::Decrypt { token_file, d1_file, ciphertext, output } => {
-            do_decrypt(&config, &token_file, &d1_file, &ciphertext, output.as_ref()).await?;
+        Commands::P7sign { token_file, d1_file, message, cert, detached, output } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let cert = cert.unwrap_or_else(|| paths.certificate());
+            do_p7sign(out, &config, &paths, &token_file, &d1_file, &message, &cert, detached, &output, formats).await?;
         }
+        Commands::PdfSign { token_file, d1_file, input, cert, output } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let cert = cert.unwrap_or_else(|| paths.certificate());
+            do_pdf_sign(out, &config, &paths, &token_file, &d1_file, &input, &cert, &output).await?;
+        }
+        Commands::XmlSign { token_file, d1_file, input, cert, detached, output } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let cert = cert.unwrap_or_else(|| paths.certificate());
+            do_xml_sign(out, &config, &paths, &token_file, &d1_file, &input, &cert, detached.as_deref(), &output).await?;
+        }
+        Commands::SignBatch { token_file, d1_file, manifest, out_dir } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            if !do_sign_batch(out, &config, &paths, &token_file, &d1_file, &manifest, &out_dir, formats).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Decrypt { token_file, d1_file, ciphertext, output, receipt, dry_run } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let receipts = open_receipts(out, &paths, receipt)?;
+            let config = ClientConfig { receipts: receipts.clone(), ..config };
+            do_decrypt(out, &config, &paths, &token_file, &d1_file, &ciphertext, output.as_ref(), formats, dry_run).await?;
+            save_receipts(out, &paths, receipts.as_deref())?;
+        }
+        Commands::Encrypt { message, to: Some(username), output, armor, .. } => {
+            do_encrypt_for(out, &config, &paths, &username, &message, output.as_ref(), formats, armor).await?;
+        }
+        Commands::Encrypt { message, public_key, to: None, output, armor } => {
+            let public_key = public_key.unwrap_or_else(|| paths.public_key());
+            do_encrypt(out, &message, &public_key, output.as_ref(), formats, armor)?;
+        }
+        Commands::Sm3 { file, za, public_key } => {
+            let public_key = public_key.unwrap_or_else(|| paths.public_key());
+            do_sm3(out, &file, za.then_some(&public_key), formats)?;
+        }
+        Commands::Verify { message, signature, public_key } => {
+            let public_key = public_key.unwrap_or_else(|| paths.public_key());
+            if !do_verify(out, &message, &signature, &public_key, formats)? {
+                std::process::exit(exit_code::VERIFICATION);
+            }
+        }
+        Commands::VerifyCert { cert, ca, purpose } => {
+            if !do_verify_cert(out, &cert, &ca, purpose)? {
+                std::process::exit(exit_code::VERIFICATION);
+            }
+        }
+        Commands::Csr { token_file, d1_file, subject, output, pem, self_signed } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            do_csr(out, &config, &paths, &token_file, &d1_file, &subject, &output, pem, self_signed).await?;
+        }
+        Commands::Local { command } => match command {
+            LocalCommands::Keygen { key_file, public_key, force, qr } => {
+                let key_file = key_file.unwrap_or_else(|| paths.local_key());
+                let public_key = public_key.unwrap_or_else(|| paths.local_public_key());
+                do_local_keygen(out, &paths, &key_file, &public_key, force, &qr)?;
+            }
+            LocalCommands::Sign { key_file, message, output, qr } => {
+                let key_file = key_file.unwrap_or_else(|| paths.local_key());
+                do_local_sign(out, &key_file, &message, output.as_ref(), formats, &qr)?;
+            }
+            LocalCommands::Verify { message, signature, public_key } => {
+                let public_key = public_key.unwrap_or_else(|| paths.local_public_key());
+                if !do_local_verify(out, &message, &signature, &public_key, formats)? {
+                    std::process::exit(exit_code::VERIFICATION);
+                }
+            }
+            LocalCommands::Encrypt { message, public_key, output, armor } => {
+                let public_key = public_key.unwrap_or_else(|| paths.local_public_key());
+                do_encrypt(out, &message, &public_key, output.as_ref(), formats, armor)?;
+            }
+            LocalCommands::Decrypt { key_file, ciphertext, output } => {
+                let key_file = key_file.unwrap_or_else(|| paths.local_key());
+                do_local_decrypt(out, &key_file, &ciphertext, output.as_ref(), formats)?;
+            }
+        },
+        Commands::Cert { command } => match command {
+            CertCommands::Show { remote, token_file, cert_file } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                let cert_file = cert_file.unwrap_or_else(|| paths.certificate());
+                do_cert_show(out, &config, &paths, remote, &token_file, &cert_file).await?;
+            }
+            CertCommands::Install { token_file, cert_file } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                let cert_file = cert_file.unwrap_or_else(|| paths.certificate());
+                do_cert_install(out, &config, &paths, &token_file, &cert_file).await?;
+            }
+            CertCommands::Verify { cert_file, public_key } => {
+                let cert_file = cert_file.unwrap_or_else(|| paths.certificate());
+                let public_key = public_key.unwrap_or_else(|| paths.public_key());
+                if !do_cert_verify(out, &cert_file, &public_key)? {
+                    std::process::exit(exit_code::VERIFICATION);
+                }
+            }
+        },
+        Commands::Token { command } => match command {
+            TokenCommands::Status { token_file } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                do_token_status(out, &config, &paths, &token_file).await?;
+            }
+            TokenCommands::Clear { token_file } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                do_token_clear(out, &token_file)?;
+            }
+        },
+        Commands::Envelope { command } => match command {
+            EnvelopeCommands::Encrypt { input, output, public_key, chunk_size } => {
+                let public_key = public_key.unwrap_or_else(|| paths.public_key());
+                do_envelope_encrypt(out, &input, &output, &public_key, chunk_size)?;
+            }
+            EnvelopeCommands::Decrypt { token_file, d1_file, input, output } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_envelope_decrypt(out, &config, &paths, &token_file, &d1_file, &input, &output).await?;
+            }
+        },
+        Commands::Receipt { command } => match command {
+            ReceiptCommands::Verify { input, public_key } => {
+                let input = input.unwrap_or_else(|| paths.receipts());
+                if !do_receipt_verify(out, &input, public_key.as_ref())? {
+                    std::process::exit(exit_code::VERIFICATION);
+                }
+            }
+            ReceiptCommands::Export { input, from, to, output } => {
+                let input = input.unwrap_or_else(|| paths.receipts());
+                do_receipt_export(out, &input, from, to, &output)?;
+            }
+        },
+        Commands::Key { command } => match command {
+            KeyCommands::Export { d1_file, format, output } => {
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_key_export(out, &paths, &d1_file, format, &output)?;
+            }
+            KeyCommands::Import { input, d1_file, force } => {
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_key_import(out, &paths, &input, &d1_file, force)?;
+            }
+            KeyCommands::EscrowExport { d1_file, officer_a, officer_b, output } => {
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_key_escrow_export(out, &paths, &d1_file, [&officer_a, &officer_b], &output)?;
+            }
+            KeyCommands::EscrowImport { input, officer_a_key, officer_b_key, d1_file, force } => {
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_key_escrow_import(out, &paths, &input, [&officer_a_key, &officer_b_key], &d1_file, force)?;
+            }
+            KeyCommands::Rotate { token_file, d1_file } => {
+                let token_file = token_file.unwrap_or_else(|| paths.token());
+                let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+                do_key_rotate(out, &config, &paths, &token_file, &d1_file).await?;
+            }
+        },
         Commands::Health => {
-            do_health(&config).await?;
+            do_health(out, &config).await?;
+        }
+        Commands::Bench { iterations, remote, token_file, d1_file } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            let remote = remote.then_some((&token_file, &d1_file));
+            do_bench(out, &config, &paths, iterations, remote).await?;
+        }
+        #[cfg(unix)]
+        Commands::Agent { socket, token_file, d1_file } => {
+            let socket = socket.unwrap_or_else(|| paths.agent_socket());
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            do_agent(out, &config, &paths, &socket, &token_file, &d1_file).await?;
+        }
+        Commands::Serve { listen, token_file, d1_file, allow_origin } => {
+            let token_file = token_file.unwrap_or_else(|| paths.token());
+            let d1_file = d1_file.unwrap_or_else(|| paths.d1());
+            do_serve(out, &config, &paths, &listen, &token_file, &d1_file, allow_origin).await?;
+        }
+        Commands::MockServer { bind, port } => {
+            do_mock_server(out, &bind, port).await?;
+        }
+        Commands::Selftest => {
+            if !do_selftest(out) {
+                std::process::exit(exit_code::VERIFICATION);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取 PEM 文件
+fn read_pem(path: &PathBuf, what: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("读取{}失败 {:?}: {}", what, path, e))
+}
+
+/// 拼接客户端证书与私钥，供双向 TLS 使用
+fn client_identity(cert: Option<PathBuf>, key: Option<PathBuf>) -> anyhow::Result<Option<Vec<u8>>> {
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            let mut identity = read_pem(&cert, "客户端证书")?;
+            identity.push(b'\n');
+            identity.extend(read_pem(&key, "客户端私钥")?);
+            Ok(Some(identity))
+        }
+        (None, None) => Ok(None),
+        _ => Err(anyhow::anyhow!("客户端证书与私钥需同时指定")),
+    }
+}
+
+/// 获取密码：命令行参数 > 环境变量 SM2_COSIGN_PASSWORD > 交互输入（不回显）
+fn resolve_password(arg: Option<String>, confirm: bool) -> anyhow::Result<String> {
+    let password = read_password(arg, confirm)?;
+    logging::add_secret(&password);
+    Ok(password)
+}
+
+fn read_password(arg: Option<String>, confirm: bool) -> anyhow::Result<String> {
+    if let Some(password) = arg {
+        return Ok(password);
+    }
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+
+    let password = rpassword::prompt_password("密码: ")
+        .map_err(|e| anyhow::anyhow!("无法读取密码（非交互环境请设置 {}）: {}", PASSWORD_ENV, e))?;
+    if confirm {
+        let again = rpassword::prompt_password("确认密码: ")?;
+        if again != password {
+            return Err(anyhow::anyhow!("两次输入的密码不一致"));
         }
     }
-    
+    Ok(password)
+}
+
+/// 输出 dry-run 模式下将要发送的请求
+fn print_dry_run(out: &Output, request: &ApiRequest) -> anyhow::Result<()> {
+    let request = request.redacted();
+    out.info("[dry-run] 未发送请求");
+    out.info(format!("{} {}", request.method, request.url));
+    if request.authenticated {
+        out.info(format!("Authorization: Bearer {}", REDACTED));
+    }
+    out.info(serde_json::to_string_pretty(&request.body)?);
+    out.data(json!({ "dry_run": true, "request": request }));
     Ok(())
 }
 
-async fn do_register(config: &ClientConfig, username: &str, password: &str) -> anyhow::Result<()> {
-    println!("正在注册用户: {}", username);
-    
+async fn do_register(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    username: &str,
+    password: &str,
+    dry_run: bool,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    if dry_run {
+        let client = CoSignClient::new(config.clone())?;
+        return print_dry_run(out, &client.dry_run_register(username, password).await?);
+    }
+
+    out.info(format!("正在注册用户: {}", username));
+
+    // Reason: 注册成功后 D1 只在本地保存一次，先取得密钥库口令（或确认 TPM 可用），避免保存失败导致 D1 丢失
+    keystore::prepare_write()?;
+
     let client = CoSignClient::new(config.clone())?;
     let key_pair = client.register(username, password).await?;
-    
-    println!("注册成功!");
-    println!("用户ID: {}", key_pair.user_id);
-    println!("请保存您的私钥分量 d1");
-    
-    // 保存 d1 到文件
-    std::fs::write(".d1", &key_pair.d1)?;
-    println!("私钥分量已保存到 .d1 文件");
-    
+
+    out.info("注册成功!");
+    out.info(format!("用户ID: {}", key_pair.user_id));
+    out.info("请保存您的私钥分量 d1");
+
+    paths.ensure_dir()?;
+
+    // 使用口令加密（或密封到 TPM）保存 d1
+    keystore::write_d1(&paths.d1(), &key_pair.d1)?;
+    out.info(format!("私钥分量已保存到 {:?}", paths.d1()));
+    write_key_lifetime(paths, &key_pair.lifetime)?;
+
     // 保存 user_id 到文件
-    std::fs::write(".user_id", &key_pair.user_id)?;
-    println!("用户ID已保存到 .user_id 文件");
-    
+    std::fs::write(paths.user_id(), &key_pair.user_id)?;
+    out.info(format!("用户ID已保存到 {:?}", paths.user_id()));
+
     // 保存公钥到文件
-    std::fs::write(".public_key", &key_pair.public_key)?;
-    println!("公钥已保存到 .public_key 文件");
-    
+    std::fs::write(paths.public_key(), &key_pair.public_key)?;
+    out.info(format!("公钥已保存到 {:?}", paths.public_key()));
+    qr.emit(out, "公钥", &qr::payload(None, &key_pair.public_key))?;
+
+    out.data(json!({
+        "user_id": key_pair.user_id,
+        "public_key": hex::encode(&key_pair.public_key),
+        "key_dir": paths.dir(),
+    }));
+
     Ok(())
 }
 
-async fn do_login(config: &ClientConfig, username: &str, password: &str) -> anyhow::Result<()> {
-    println!("正在登录用户: {}", username);
-    
+async fn do_login(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    username: &str,
+    password: &str,
+    remember: bool,
+) -> anyhow::Result<()> {
+    out.info(format!("正在登录用户: {}", username));
+
     let client = CoSignClient::new(config.clone())?;
     let session = client.login(username, password).await?;
-    
-    println!("登录成功!");
-    println!("Token: {}", session.token);
-    
-    // 保存 token 到文件
-    std::fs::write(".token", &session.token)?;
-    println!("Token 已保存到 .token 文件");
-    
-    // 保存 user_id 到文件
-    std::fs::write(".user_id", &session.user_id)?;
-    println!("用户ID已保存到 .user_id 文件");
-    
+
+    logging::add_secret(&session.token);
+    out.info("登录成功!");
+
+    save_session(paths, token_file, username, &session)?;
+    out.info(format!("Token 已保存到 {:?}", token_file));
+    out.info(format!("用户ID已保存到 {:?}", paths.user_id()));
+
+    if remember {
+        let sealed = keystore::seal(password.as_bytes(), keystore::passphrase(true)?)?;
+        keystore::write_sealed(&paths.credentials(), &sealed)?;
+        out.info(format!("密码已加密保存到 {:?}，Token 过期时将自动重新登录", paths.credentials()));
+    }
+
+    out.data(json!({
+        "user_id": session.user_id,
+        "expires_at": session.expires_at,
+    }));
+
     Ok(())
 }
 
-async fn do_logout(config: &ClientConfig, _token_file: &PathBuf) -> anyhow::Result<()> {
-    println!("正在登出...");
-    
+async fn do_logout(out: &Output, config: &ClientConfig, paths: &StatePaths, token_file: &PathBuf) -> anyhow::Result<()> {
+    let token = read_token(token_file)
+        .map_err(|_| anyhow::Error::new(sm2_co_sign_core::Error::NotAuthenticated).context(format!("未登录（{:?} 文件不存在）", token_file)))?;
+    let user_id = std::fs::read_to_string(paths.user_id()).unwrap_or_default();
+
+    out.info("正在登出...");
+
     let client = CoSignClient::new(config.clone())?;
+    client.set_session(token, user_id).await?;
     client.logout().await?;
-    
+
     // 删除 token 文件
-    let _ = std::fs::remove_file(".token");
-    
-    println!("登出成功!");
-    
+    remove_token(token_file)?;
+    // Reason: 主动登出后不应再被自动重新登录
+    let _ = std::fs::remove_file(paths.credentials());
+
+    out.info("登出成功!");
+    out.data(json!({}));
+
     Ok(())
 }
 
-async fn do_sign(config: &ClientConfig, _token_file: &PathBuf, d1_file: &PathBuf, message_file: &PathBuf, output: Option<&PathBuf>) -> anyhow::Result<()> {
-    // 读取必要的文件
-    let token = std::fs::read_to_string(".token")
-        .map_err(|_| anyhow::anyhow!("请先登录（.token 文件不存在）"))?;
-    let d1 = std::fs::read(d1_file)
-        .map_err(|_| anyhow::anyhow!("请先注册（.d1 文件不存在）"))?;
-    let user_id = std::fs::read_to_string(".user_id")
-        .map_err(|_| anyhow::anyhow!("请先注册（.user_id 文件不存在）"))?;
-    let public_key = std::fs::read(".public_key")
-        .map_err(|_| anyhow::anyhow!("请先注册（.public_key 文件不存在）"))?;
-    let message = std::fs::read(message_file)?;
-    
-    println!("正在签名...");
-    
-    // 创建客户端并设置会话
+async fn do_whoami(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    let token = read_token(token_file)
+        .map_err(|_| login_required(token_file))?;
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| login_required(paths.user_id()))?;
+    let expires_at = read_expires_at(token_file);
+
     let client = CoSignClient::new(config.clone())?;
-    
-    // 手动设置会话和密钥对
-    client.set_session(token, user_id.clone()).await?;
-    client.set_key_pair(d1, public_key, user_id).await?;
-    
-    // 执行签名
-    let signature = client.sign(&message).await?;
-    
-    // 组合签名 r || s
-    let mut sig_bytes = Vec::with_capacity(64);
-    sig_bytes.extend_from_slice(&signature.r);
-    sig_bytes.extend_from_slice(&signature.s);
-    
-    if let Some(output_path) = output {
-        std::fs::write(output_path, &sig_bytes)?;
-        println!("签名已保存到: {:?}", output_path);
-    } else {
-        println!("签名: {}", hex::encode(&sig_bytes));
+    client.set_session(token, user_id).await?;
+    let info = client.get_user_info().await?;
+
+    out.info(format!("用户ID: {}", info.id));
+    out.info(format!("用户名: {}", info.username));
+    out.info(format!("公钥: {}", info.public_key));
+    out.info(format!("密钥状态: {}", info.status));
+    out.info(format!("注册时间: {}", info.created_at));
+    match expires_at {
+        Some(expires_at) => out.info(format!("Token 过期时间: {}", describe_expires_at(expires_at))),
+        None => out.info("Token 过期时间: 未知"),
+    }
+
+    // 核对本地公钥与服务端记录是否一致
+    let local_public_key = match std::fs::read(paths.public_key()) {
+        Ok(local) if config.encoding.decode_str(&info.public_key).ok().as_deref() == Some(local.as_slice()) => {
+            out.info("本地公钥: 与服务端一致");
+            "match"
+        }
+        Ok(_) => {
+            out.info("本地公钥: 与服务端不一致，请重新初始化密钥");
+            "mismatch"
+        }
+        Err(_) => {
+            out.info(format!("本地公钥: 未找到 {:?}", paths.public_key()));
+            "missing"
+        }
+    };
+    let lifetime = read_key_lifetime(paths);
+    let rotation = config.rotation_policy.status(&lifetime);
+    match rotation {
+        RotationStatus::Ok => out.info(format!("本地密钥: {}", describe_key_lifetime(&lifetime))),
+        RotationStatus::Recommended => {
+            out.warn(format!("本地密钥: {}，建议执行 key rotate 轮换", describe_key_lifetime(&lifetime)))
+        }
+        RotationStatus::Required => {
+            out.warn(format!("本地密钥: {}，已达到轮换策略上限，须执行 key rotate", describe_key_lifetime(&lifetime)))
+        }
     }
-    
+    if qr.enabled() {
+        // Reason: 服务端按字段编码（默认 Base64）返回公钥，二维码与其他命令统一使用十六进制
+        let public_key =
+            config.encoding.decode_str(&info.public_key).map_err(|e| anyhow::anyhow!("服务端返回的公钥无效: {}", e))?;
+        qr.emit(out, "公钥", &qr::payload(None, &public_key))?;
+    }
+
+    out.data(json!({
+        "user_id": info.id,
+        "username": info.username,
+        "public_key": info.public_key,
+        "status": info.status,
+        "created_at": info.created_at,
+        "token_expires_at": expires_at,
+        "local_public_key": local_public_key,
+        "key_created_at": lifetime.created_at,
+        "key_usage_count": lifetime.usage_count,
+        "key_rotation": rotation,
+    }));
+
     Ok(())
 }
 
-async fn do_decrypt(config: &ClientConfig, _token_file: &PathBuf, d1_file: &PathBuf, ciphertext_file: &PathBuf, output: Option<&PathBuf>) -> anyhow::Result<()> {
-    // 读取必要的文件
-    let token = std::fs::read_to_string(".token")
-        .map_err(|_| anyhow::anyhow!("请先登录（.token 文件不存在）"))?;
-    let d1 = std::fs::read(d1_file)
-        .map_err(|_| anyhow::anyhow!("请先注册（.d1 文件不存在）"))?;
-    let user_id = std::fs::read_to_string(".user_id")
-        .map_err(|_| anyhow::anyhow!("请先注册（.user_id 文件不存在）"))?;
-    let public_key = std::fs::read(".public_key")
-        .map_err(|_| anyhow::anyhow!("请先注册（.public_key 文件不存在）"))?;
-    let ciphertext = std::fs::read(ciphertext_file)?;
-    
-    println!("正在解密...");
-    
-    // 创建客户端并设置会话
-    let client = CoSignClient::new(config.clone())?;
-    
-    // 手动设置会话和密钥对
-    client.set_session(token, user_id.clone()).await?;
-    client.set_key_pair(d1, public_key, user_id).await?;
-    
-    // 执行解密
-    let plaintext = client.decrypt(&ciphertext).await?;
-    
-    if let Some(output_path) = output {
-        std::fs::write(output_path, &plaintext)?;
-        println!("明文已保存到: {:?}", output_path);
-    } else {
-        println!("明文: {}", String::from_utf8_lossy(&plaintext));
-    }
-    
+/// 保存登录结果：Token、过期时间、用户 ID 与用户名
+fn save_session(paths: &StatePaths, token_file: &PathBuf, username: &str, session: &Session) -> anyhow::Result<()> {
+    paths.ensure_dir()?;
+    paths::write_private(token_file, &session.token)?;
+    let expires_at = session.expires_at.map(|expires_at| expires_at.to_rfc3339()).unwrap_or_default();
+    paths::write_private(&paths::token_expires_at(token_file), expires_at)?;
+    paths::write_private(&paths.user_id(), &session.user_id)?;
+    paths::write_private(&paths.username(), username)?;
     Ok(())
 }
 
-async fn do_health(config: &ClientConfig) -> anyhow::Result<()> {
-    let client = CoSignClient::new(config.clone())?;
-    let healthy = client.health_check().await?;
-    
-    if healthy {
-        println!("服务状态: 正常");
-    } else {
-        println!("服务状态: 异常");
+/// 自动重新登录可用的凭据
+///
+/// 用户名取 `SM2_COSIGN_USERNAME` 或最近登录保存的 `.username`；
+/// 密码取 `SM2_COSIGN_PASSWORD` 或 `login --remember` 加密保存的 `.credentials`。
+/// 任一缺失时返回 None。
+fn saved_credentials(paths: &StatePaths) -> anyhow::Result<Option<(String, Zeroizing<String>)>> {
+    let username = match std::env::var(USERNAME_ENV) {
+        Ok(username) => username,
+        Err(_) => match std::fs::read_to_string(paths.username()) {
+            Ok(username) => username.trim().to_string(),
+            Err(_) => return Ok(None),
+        },
+    };
+    if username.is_empty() {
+        return Ok(None);
+    }
+
+    let password = match std::env::var(PASSWORD_ENV) {
+        Ok(password) => Zeroizing::new(password),
+        Err(_) => match std::fs::read(paths.credentials()) {
+            Ok(sealed) => {
+                let password = keystore::open(&sealed, keystore::passphrase(false)?)?;
+                Zeroizing::new(
+                    String::from_utf8(password.to_vec())
+                        .map_err(|_| anyhow::anyhow!("{:?} 内容无效", paths.credentials()))?,
+                )
+            }
+            Err(_) => return Ok(None),
+        },
+    };
+    logging::add_secret(&password);
+    Ok(Some((username, password)))
+}
+
+/// 本地记录的 Token 剩余有效期不足该值时提前重新登录，避免操作途中过期
+const TOKEN_RENEW_MARGIN_SECS: i64 = 60;
+
+/// 读取 Token；有可用凭据时按本地过期时间判断，即将过期、已过期或缺失则自动重新登录
+///
+/// 只有本地没有过期时间记录时才向服务端确认 Token 有效。
+async fn ensure_token(out: &Output, config: &ClientConfig, paths: &StatePaths, token_file: &PathBuf) -> anyhow::Result<String> {
+    let token = read_token(token_file);
+    // Reason: 无凭据时无法重新登录，保持原有行为，避免每次操作多一次校验请求
+    let Some((username, password)) = saved_credentials(paths)? else {
+        return token.map_err(|_| login_required(token_file));
+    };
+
+    // Reason: 本地记录了过期时间时直接据此判断，避免每次签名、解密前多一次校验请求
+    let expires_at = read_expires_at(token_file);
+    let renew_at = Utc::now() + chrono::Duration::seconds(TOKEN_RENEW_MARGIN_SECS);
+    match token {
+        Ok(token) if expires_at.is_some_and(|expires_at| expires_at > renew_at) => return Ok(token),
+        Ok(_) if expires_at.is_some() => out.warn(format!("Token 即将或已经过期，正在自动重新登录用户 {}", username)),
+        Ok(token) => {
+            let user_id = std::fs::read_to_string(paths.user_id()).unwrap_or_default();
+            let client = CoSignClient::new(config.clone())?;
+            client.set_session(token.clone(), user_id).await?;
+            match client.get_user_info().await {
+                Ok(_) => return Ok(token),
+                Err(e) if e.kind() == ErrorKind::Network => return Err(e.into()),
+                Err(e) => out.warn(format!("Token 已失效（{}），正在自动重新登录用户 {}", e, username)),
+            }
+        }
+        Err(_) => out.warn(format!("未找到 Token，正在自动登录用户 {}", username)),
+    }
+
+    let client = CoSignClient::new(config.clone())?;
+    let session = client.login(&username, &password).await?;
+    logging::add_secret(&session.token);
+    save_session(paths, token_file, &username, &session)?;
+    out.info("自动登录成功");
+    Ok(session.token.to_string())
+}
+
+/// 缺少登录状态文件时的错误（退出码为认证失败）
+fn login_required(path: impl std::fmt::Debug) -> anyhow::Error {
+    anyhow::Error::new(sm2_co_sign_core::Error::NotAuthenticated).context(format!("请先登录（{:?} 文件不存在）", path))
+}
+
+/// 读取 Token 文件，并登记为日志中需隐藏的敏感值
+fn read_token(token_file: &PathBuf) -> std::io::Result<String> {
+    // Reason: 手工编辑的 Token 文件常带换行，而 Token 校验不接受空白字符
+    let token = std::fs::read_to_string(token_file)?.trim().to_string();
+    logging::add_secret(&token);
+    Ok(token)
+}
+
+/// 读取保存的 Token 过期时间；文件缺失或内容无法解析时为 None
+fn read_expires_at(token_file: &Path) -> Option<DateTime<Utc>> {
+    let text = std::fs::read_to_string(paths::token_expires_at(token_file)).ok()?;
+    sm2_co_sign_core::parse_expires_at(&text).ok().flatten()
+}
+
+/// 过期时间与剩余时间的展示文本，如 `2024-01-01T08:00:00+08:00（剩余 1 小时 5 分钟）`
+fn describe_expires_at(expires_at: DateTime<Utc>) -> String {
+    let local = expires_at.with_timezone(&chrono::Local).to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+    let remaining = (expires_at - Utc::now()).num_minutes();
+    if remaining <= 0 {
+        format!("{}（已过期）", local)
+    } else if remaining < 60 {
+        format!("{}（剩余 {} 分钟）", local, remaining)
+    } else {
+        format!("{}（剩余 {} 小时 {} 分钟）", local, remaining / 60, remaining % 60)
+    }
+}
+
+/// 删除 Token 及其过期时间文件，返回 Token 文件是否存在
+fn remove_token(token_file: &PathBuf) -> anyhow::Result<bool> {
+    let existed = match std::fs::remove_file(token_file) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(anyhow::anyhow!("删除 Token 文件失败 {:?}: {}", token_file, e)),
+    };
+    let _ = std::fs::remove_file(paths::token_expires_at(token_file));
+    Ok(existed)
+}
+
+async fn do_token_status(out: &Output, config: &ClientConfig, paths: &StatePaths, token_file: &PathBuf) -> anyhow::Result<()> {
+    let token = match read_token(token_file) {
+        Ok(token) => token,
+        Err(_) => {
+            out.info(format!("未登录（{:?} 文件不存在）", token_file));
+            out.data(json!({ "token_file": token_file, "logged_in": false }));
+            return Ok(());
+        }
+    };
+    let user_id = std::fs::read_to_string(paths.user_id()).unwrap_or_default();
+    let expires_at = read_expires_at(token_file);
+
+    // Reason: Token 可能被服务端提前吊销，以服务端校验结果为准
+    let client = CoSignClient::new(config.clone())?;
+    client.set_session(token, user_id.clone()).await?;
+    let valid = match client.get_user_info().await {
+        Ok(_) => true,
+        Err(e) if e.kind() == ErrorKind::Network => return Err(e.into()),
+        Err(e) => {
+            out.warn(format!("Token 校验失败: {}", e));
+            false
+        }
+    };
+
+    out.info(format!("Token 文件: {:?}", token_file));
+    if !user_id.is_empty() {
+        out.info(format!("用户ID: {}", user_id));
+    }
+    if let Some(expires_at) = expires_at {
+        out.info(format!("过期时间: {}", describe_expires_at(expires_at)));
+    }
+    out.info(format!("状态: {}", if valid { "有效" } else { "无效" }));
+
+    out.data(json!({
+        "token_file": token_file,
+        "logged_in": true,
+        "user_id": (!user_id.is_empty()).then_some(user_id),
+        "expires_at": expires_at,
+        "expired": expires_at.is_some_and(|expires_at| expires_at <= Utc::now()),
+        "valid": valid,
+    }));
+
+    Ok(())
+}
+
+fn do_token_clear(out: &Output, token_file: &PathBuf) -> anyhow::Result<()> {
+    let removed = remove_token(token_file)?;
+    if removed {
+        out.info(format!("已删除 Token: {:?}", token_file));
+    } else {
+        out.info(format!("Token 文件不存在: {:?}", token_file));
+    }
+    out.data(json!({ "token_file": token_file, "removed": removed }));
+
+    Ok(())
+}
+
+async fn do_init_key(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    force: bool,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    // Reason: 重新初始化后服务端的 D2 随之更换，旧 D1 将无法再参与签名，覆盖前需显式确认
+    if d1_file.exists() && !force {
+        return Err(anyhow::anyhow!("D1 文件已存在: {:?}，如需重新初始化请添加 --force", d1_file));
+    }
+
+    let token = read_token(token_file)
+        .map_err(|_| login_required(token_file))?;
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| login_required(paths.user_id()))?;
+
+    keystore::prepare_write()?;
+
+    out.info("正在初始化密钥...");
+
+    let client = CoSignClient::new(config.clone())?;
+    client.set_session(token, user_id).await?;
+    let key_pair = client.init_key().await?;
+
+    out.info("密钥初始化成功!");
+
+    keystore::write_d1(d1_file, &key_pair.d1)?;
+    out.info(format!("私钥分量已保存到 {:?}", d1_file));
+    write_key_lifetime(paths, &key_pair.lifetime)?;
+
+    std::fs::write(paths.public_key(), &key_pair.public_key)?;
+    out.info(format!("公钥已保存到 {:?}", paths.public_key()));
+    qr.emit(out, "公钥", &qr::payload(None, &key_pair.public_key))?;
+
+    out.data(json!({
+        "user_id": key_pair.user_id,
+        "public_key": hex::encode(&key_pair.public_key),
+    }));
+
+    Ok(())
+}
+
+/// 从本地文件恢复会话与密钥对，创建已登录的客户端
+async fn load_client(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+) -> anyhow::Result<CoSignClient> {
+    load_client_for(out, config, paths, token_file, d1_file, false).await
+}
+
+/// 读取命令使用的 Token；dry-run 只读取 Token 文件，不向服务端校验、不自动登录、不改写任何文件
+async fn session_token(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    dry_run: bool,
+) -> anyhow::Result<String> {
+    if dry_run {
+        return read_token(token_file).map_err(|_| login_required(token_file));
+    }
+    ensure_token(out, config, paths, token_file).await
+}
+
+/// 加载客户端，`dry_run` 时 Token 按 [`session_token`] 只读取不刷新
+async fn load_client_for(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    dry_run: bool,
+) -> anyhow::Result<CoSignClient> {
+    // 读取必要的文件
+    let token = session_token(out, config, paths, token_file, dry_run).await?;
+    let d1_data = std::fs::read(d1_file).map_err(|_| {
+        // Reason: 旧版本将文件写在当前目录，升级后提示用户指定密钥目录
+        if std::path::Path::new(".d1").exists() {
+            anyhow::anyhow!("{:?} 文件不存在；当前目录下存在旧版 .d1 文件，可使用 --key-dir . 继续使用", d1_file)
+        } else {
+            anyhow::anyhow!("请先注册（{:?} 文件不存在）", d1_file)
+        }
+    })?;
+    let (d1, legacy) = keystore::unlock_d1(&d1_data)?;
+    if legacy {
+        out.warn(format!("{:?} 为未加密的旧版 D1 文件，建议执行 init-key --force 重新生成加密密钥库", d1_file));
+    }
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.user_id()))?;
+    if !paths.public_key().exists() {
+        anyhow::bail!("请先注册（{:?} 文件不存在）", paths.public_key());
+    }
+    let public_key = PublicKey::from_file(paths.public_key())
+        .map_err(|e| anyhow::Error::new(e).context(format!("公钥文件 {:?} 无效", paths.public_key())))?;
+
+    // 创建客户端并设置会话
+    let client = CoSignClient::new(config.clone())?;
+
+    // 手动设置会话和密钥对
+    client.set_session(token, user_id.clone()).await?;
+    client.set_key_pair(d1.to_vec(), public_key.to_vec(), user_id).await?;
+    client.set_key_lifetime(read_key_lifetime(paths)).await?;
+    match client.rotation_status().await {
+        RotationStatus::Required => out.warn("密钥已达到轮换策略的上限，签名前须执行 key rotate 刷新私钥分量"),
+        RotationStatus::Recommended => out.warn("密钥已达到建议轮换的年龄或使用次数，建议执行 key rotate 刷新私钥分量"),
+        RotationStatus::Ok => {}
+    }
+
+    Ok(client)
+}
+
+/// 读取密钥目录中的生命周期记录，文件不存在或无法解析时为未知
+fn read_key_lifetime(paths: &StatePaths) -> KeyLifetime {
+    std::fs::read(paths.key_lifetime())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// 保存新 D1 的生命周期记录
+fn write_key_lifetime(paths: &StatePaths, lifetime: &KeyLifetime) -> anyhow::Result<()> {
+    std::fs::write(paths.key_lifetime(), serde_json::to_vec(lifetime)?)?;
+    Ok(())
+}
+
+/// 签名、解密后保存更新后的使用次数
+async fn save_key_lifetime(paths: &StatePaths, client: &CoSignClient) -> anyhow::Result<()> {
+    match client.key_lifetime().await {
+        Some(lifetime) => write_key_lifetime(paths, &lifetime),
+        None => Ok(()),
+    }
+}
+
+/// 描述生命周期记录，如“已使用 12 次，创建于 30 天前”
+fn describe_key_lifetime(lifetime: &KeyLifetime) -> String {
+    match lifetime.age() {
+        Some(age) => format!("已使用 {} 次，创建于 {} 天前", lifetime.usage_count, age.as_secs() / 86400),
+        None => format!("已使用 {} 次，创建时间未知", lifetime.usage_count),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_sign(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    message_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+    sig_format: SignatureFormat,
+    options: &SignOptions,
+    dry_run: bool,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    let client = load_client_for(out, config, paths, token_file, d1_file, dry_run).await?;
+    // Reason: PKCS#7 接收方按标准 SM3withSM2 验签，需使用 e = SM3(ZA || M)；raw/der 保持与旧版本一致的 SM3(M)
+    let mode = match sig_format {
+        SignatureFormat::P7 => DigestMode::Za,
+        _ => DigestMode::Sm3,
+    };
+
+    if dry_run {
+        let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+        return print_dry_run(out, &client.dry_run_sign_with_options(&message, mode, options).await?);
+    }
+
+    // PKCS#7 需要签名者证书，签名前先检查
+    let certificate = match sig_format {
+        SignatureFormat::P7 => Some(load_signer_certificate(&client, &paths.certificate()).await?),
+        _ => None,
+    };
+
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("未加载密钥对"))?;
+    let uid = (mode == DigestMode::Za).then_some(DEFAULT_USER_ID);
+    let e = hash_message(out, message_file, formats.input, uid, &public_key)?;
+
+    out.info("正在签名...");
+
+    let signature = client.sign_digest_with_options(&e, options).await?;
+    save_key_lifetime(paths, &client).await?;
+    if uid.is_some() && !CoSignProtocol::new()?.verify_digest(&public_key, &e, &signature.r, &signature.s)? {
+        return Err(anyhow::anyhow!("协同签名结果验证失败，请检查公钥文件是否与 D1 匹配"));
+    }
+
+    // 组合签名 r || s
+    let sig_bytes = signature.to_bytes();
+
+    let encoded = match (sig_format, &certificate) {
+        (SignatureFormat::Der, _) => asn1::signature_to_der(&sig_bytes)?,
+        (SignatureFormat::P7, Some(certificate)) => {
+            pkcs7::signed_data(&certificate.der, None, &asn1::signature_to_der(&sig_bytes)?)?
+        }
+        (SignatureFormat::Pem, _) => pem::encode_signature(&sig_bytes)?.into_bytes(),
+        _ => sig_bytes.to_vec(),
+    };
+
+    // Reason: PEM 本身即为文本，不再按 --out-format 编码；二维码仍使用其中的 DER
+    if sig_format == SignatureFormat::Pem {
+        write_armored(out, "签名", output, &encoded)?;
+        qr.emit(out, "签名", &qr::payload(formats.output, &asn1::signature_to_der(&sig_bytes)?))?;
+    } else {
+        if let Some(output_path) = output {
+            stdio::write_output(output_path, &formats.encode_file(&encoded))?;
+            out.info(format!("签名已保存到: {:?}", output_path));
+        } else {
+            out.info(format!("签名: {}", formats.encode_display(&encoded)));
+        }
+        qr.emit(out, "签名", &qr::payload(formats.output, &encoded))?;
+    }
+    if let Some(transaction_id) = &signature.metadata.transaction_id {
+        out.info(format!("交易流水号: {}", transaction_id));
+    }
+
+    out.data(json!({
+        "signature": hex::encode(sig_bytes),
+        "r": hex::encode(&sig_bytes[..32]),
+        "s": hex::encode(&sig_bytes[32..]),
+        "sig_format": format!("{:?}", sig_format).to_lowercase(),
+        "encoded": hex::encode(&encoded),
+        "output": output,
+        "metadata": signature.metadata,
+    }));
+
+    Ok(())
+}
+
+/// 计算待签名消息的哈希 e：`uid` 为 Some 时为 SM3(ZA || M)，否则为 SM3(M)
+///
+/// 原始格式输入流式读取，大文件无需整体读入内存，并显示进度。
+fn hash_message(
+    out: &Output,
+    message_file: &PathBuf,
+    input_format: DataFormat,
+    uid: Option<&[u8]>,
+    public_key: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let mut hasher = Sm3::new();
+    if let Some(uid) = uid {
+        hasher.update(&CoSignProtocol::calculate_za(uid, public_key)?);
+    }
+
+    if input_format == DataFormat::Raw {
+        let progress = out.progress(stdio::input_len(message_file), "计算摘要");
+        let mut reader = progress.wrap_read(stdio::open_input(message_file)?);
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        progress.finish_and_clear();
+    } else {
+        hasher.update(&input_format.decode(&stdio::read_input(message_file)?)?);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// 按标准 SM3withSM2 预处理 e = SM3(ZA || M) 进行协同签名，并在本地验证结果
+async fn sign_with_za(client: &CoSignClient, message: &[u8]) -> anyhow::Result<sm2_co_sign_core::Signature> {
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("未加载密钥对"))?;
+
+    let protocol = CoSignProtocol::new()?;
+    let e = protocol.calculate_message_hash_with_uid(message, DEFAULT_USER_ID, &public_key)?;
+    let signature = client.sign_digest(&e).await?;
+    if !protocol.verify_digest(&public_key, &e, &signature.r, &signature.s)? {
+        return Err(anyhow::anyhow!("协同签名结果验证失败，请检查公钥文件是否与 D1 匹配"));
+    }
+    Ok(signature)
+}
+
+/// 读取用户证书，并确认与当前协同公钥一致
+async fn load_signer_certificate(client: &CoSignClient, cert_file: &PathBuf) -> anyhow::Result<Certificate> {
+    let data = std::fs::read(cert_file)
+        .map_err(|_| anyhow::anyhow!("PKCS#7 签名需要用户证书，请先执行 cert install（{:?} 文件不存在）", cert_file))?;
+    let certificate = Certificate::parse(&data)?;
+
+    let public_key = client.get_public_key().await.map(|k| k.to_vec()).unwrap_or_default();
+    if !certificate.matches_public_key(&public_key) {
+        anyhow::bail!("证书 {:?} 的公钥与协同公钥不一致，请重新执行 cert install", cert_file);
+    }
+    Ok(certificate)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_p7sign(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    message_file: &PathBuf,
+    cert_file: &PathBuf,
+    detached: bool,
+    output: &PathBuf,
+    formats: Formats,
+) -> anyhow::Result<()> {
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let certificate = load_signer_certificate(&client, cert_file).await?;
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+
+    out.info("正在签名...");
+    let signature = sign_with_za(&client, &message).await?;
+    save_key_lifetime(paths, &client).await?;
+
+    let sig_bytes = signature.to_bytes();
+    let content = (!detached).then_some(message.as_slice());
+    let p7 = pkcs7::signed_data(&certificate.der, content, &asn1::signature_to_der(&sig_bytes)?)?;
+
+    stdio::write_output(output, &formats.encode_file(&p7))?;
+    out.info(format!("PKCS#7 签名数据已保存到: {:?}", output));
+
+    out.data(json!({
+        "signature": hex::encode(sig_bytes),
+        "signer": certificate.subject,
+        "detached": detached,
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_pdf_sign(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    input: &PathBuf,
+    cert_file: &PathBuf,
+    output: &PathBuf,
+) -> anyhow::Result<()> {
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let certificate = load_signer_certificate(&client, cert_file).await?;
+    let pdf = stdio::read_input(input)?;
+
+    out.info("正在签名...");
+    let signed = sm2_co_sign_core::pdf::sign_pdf(&client, &pdf, &certificate.der).await?;
+    save_key_lifetime(paths, &client).await?;
+
+    stdio::write_output(output, &signed)?;
+    out.info(format!("已签名的 PDF 已保存到: {:?}", output));
+
+    out.data(json!({
+        "signer": certificate.subject,
+        "size": signed.len(),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_xml_sign(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    input: &PathBuf,
+    cert_file: &PathBuf,
+    detached: Option<&str>,
+    output: &PathBuf,
+) -> anyhow::Result<()> {
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let certificate = load_signer_certificate(&client, cert_file).await?;
+    let data = stdio::read_input(input)?;
+
+    out.info("正在签名...");
+    let signed = match detached {
+        Some(uri) => xmldsig::sign_detached(&client, &data, uri, Some(&certificate.der)).await?,
+        None => xmldsig::sign_enveloped(&client, &data, Some(&certificate.der)).await?,
+    };
+    save_key_lifetime(paths, &client).await?;
+
+    stdio::write_output(output, &signed)?;
+    out.info(format!("XML 签名已保存到: {:?}", output));
+
+    out.data(json!({
+        "signer": certificate.subject,
+        "detached": detached.is_some(),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+/// 读取批量签名清单
+fn read_manifest(manifest: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
+    let content = std::fs::read_to_string(manifest)
+        .map_err(|e| anyhow::anyhow!("读取清单文件失败 {:?}: {}", manifest, e))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_sign_batch(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    manifest: &PathBuf,
+    out_dir: &PathBuf,
+    formats: Formats,
+) -> anyhow::Result<bool> {
+    let files = read_manifest(manifest)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("清单文件为空: {:?}", manifest));
+    }
+
+    // 签名文件以输入文件名命名，同名文件会互相覆盖，需提前拒绝
+    let mut targets = Vec::with_capacity(files.len());
+    let mut seen = std::collections::HashSet::new();
+    for file in &files {
+        let name = file
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("无效的文件路径: {:?}", file))?;
+        if !seen.insert(name.to_owned()) {
+            return Err(anyhow::anyhow!("清单中存在同名文件: {:?}", name));
+        }
+        let mut sig_name = name.to_owned();
+        sig_name.push(".sig");
+        targets.push(out_dir.join(sig_name));
+    }
+
+    // 所有文件复用同一个已登录的客户端
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut results = Vec::with_capacity(files.len());
+    let mut failed = 0usize;
+    for (file, target) in files.iter().zip(&targets) {
+        let result = async {
+            let message = formats.input.decode(&std::fs::read(file)?)?;
+            let signature = client.sign(&message, DigestMode::Sm3).await?;
+            let sig_bytes = signature.to_bytes();
+            std::fs::write(target, formats.encode_file(&sig_bytes))?;
+            anyhow::Ok(hex::encode(sig_bytes))
+        }
+        .await;
+
+        match result {
+            Ok(signature) => {
+                out.info(format!("已签名: {:?} -> {:?}", file, target));
+                results.push(json!({ "file": file, "signature_file": target, "signature": signature }));
+            }
+            Err(e) => {
+                failed += 1;
+                out.warn(format!("签名失败: {:?}: {:#}", file, e));
+                results.push(json!({ "file": file, "error": format!("{:#}", e) }));
+            }
+        }
+    }
+    save_key_lifetime(paths, &client).await?;
+
+    let report = json!({
+        "total": files.len(),
+        "succeeded": files.len() - failed,
+        "failed": failed,
+        "results": results,
+    });
+    let report_path = out_dir.join("report.json");
+    std::fs::write(&report_path, serde_json::to_vec_pretty(&report)?)?;
+
+    out.info(format!(
+        "批量签名完成: 成功 {} 个，失败 {} 个，报告已保存到 {:?}",
+        files.len() - failed,
+        failed,
+        report_path
+    ));
+    out.data(report);
+
+    Ok(failed == 0)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_decrypt(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    ciphertext_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let client = load_client_for(out, config, paths, token_file, d1_file, dry_run).await?;
+    let ciphertext = read_ciphertext(ciphertext_file, formats)?;
+
+    if dry_run {
+        return print_dry_run(out, &client.dry_run_decrypt(&ciphertext).await?);
+    }
+
+    out.info("正在解密...");
+
+    // 执行解密
+    let plaintext = client.decrypt(&ciphertext).await?;
+    save_key_lifetime(paths, &client).await?;
+
+    if let Some(output_path) = output {
+        stdio::write_output(output_path, &formats.encode_file(&plaintext))?;
+        out.info(format!("明文已保存到: {:?}", output_path));
+    } else if formats.output.is_some() {
+        out.info(format!("明文: {}", formats.encode_display(&plaintext)));
+    } else {
+        out.info(format!("明文: {}", String::from_utf8_lossy(&plaintext)));
+    }
+
+    out.data(json!({
+        "plaintext": hex::encode(&plaintext),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+/// 解析签名文件内容，支持原始 r||s（64字节）、DER 编码和十六进制文本
+fn parse_signature(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.len() == 64 {
+        return Ok(data.to_vec());
+    }
+    if pem::is_pem(data) {
+        let text = std::str::from_utf8(data).map_err(|_| anyhow::anyhow!("PEM 签名不是有效的文本"))?;
+        return Ok(pem::decode_signature(text)?);
+    }
+    if data.first() == Some(&asn1::TAG_SEQUENCE) {
+        if let Ok(raw) = asn1::signature_from_der(data) {
+            return Ok(raw);
+        }
+    }
+    // Reason: sign 子命令未指定 -o 时以十六进制输出签名，用户常直接保存终端输出
+    if let Ok(text) = std::str::from_utf8(data) {
+        if let Ok(decoded) = hex::decode(text.trim()) {
+            if !decoded.is_empty() {
+                return parse_signature(&decoded);
+            }
+        }
+    }
+    Err(anyhow::anyhow!("无法识别的签名格式（支持原始 64 字节、DER、PEM 或十六进制）"))
+}
+
+/// 读取密文：`SM2 CIPHERTEXT` PEM 自动识别，否则按 --in-format 解码
+fn read_ciphertext(ciphertext_file: &PathBuf, formats: Formats) -> anyhow::Result<Vec<u8>> {
+    let data = stdio::read_input(ciphertext_file)?;
+    if pem::is_pem(&data) {
+        let text = std::str::from_utf8(&data).map_err(|_| anyhow::anyhow!("PEM 密文不是有效的文本"))?;
+        return Ok(pem::decode_ciphertext(text)?);
+    }
+    formats.input.decode(&data)
+}
+
+/// 写出或显示 PEM 文本
+fn write_armored(out: &Output, label: &str, output: Option<&PathBuf>, armored: &[u8]) -> anyhow::Result<()> {
+    match output {
+        Some(output_path) => {
+            stdio::write_output(output_path, armored)?;
+            out.info(format!("{}已保存到: {:?}", label, output_path));
+        }
+        None => out.info(format!("{}:\n{}", label, String::from_utf8_lossy(armored).trim_end())),
+    }
+    Ok(())
+}
+
+fn do_verify(
+    out: &Output,
+    message_file: &PathBuf,
+    signature_file: &PathBuf,
+    public_key_file: &PathBuf,
+    formats: Formats,
+) -> anyhow::Result<bool> {
+    if stdio::is_stdio(message_file) && stdio::is_stdio(signature_file) {
+        return Err(anyhow::anyhow!("消息与签名不能同时从 stdin 读取"));
+    }
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+    let signature = parse_signature(&formats.input.decode(&stdio::read_input(signature_file)?)?)?;
+    let public_key = std::fs::read(public_key_file)
+        .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+
+    let protocol = CoSignProtocol::new()?;
+
+    // 与 sign 子命令使用相同的消息哈希
+    let e = protocol.calculate_message_hash(&message, &public_key, DigestMode::Sm3)?;
+    let valid = protocol.verify_digest(&public_key, &e, &signature[..32], &signature[32..])?;
+
+    if valid {
+        out.info("验签成功");
+    } else {
+        out.warn("验签失败");
+    }
+    out.data(json!({ "valid": valid }));
+
+    Ok(valid)
+}
+
+fn do_encrypt(
+    out: &Output,
+    message_file: &PathBuf,
+    public_key_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+    armor: bool,
+) -> anyhow::Result<()> {
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+    let public_key = std::fs::read(public_key_file)
+        .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+    // encrypt 要求 64 字节公钥（x||y），兼容带 04 前缀的格式
+    let public_key = match public_key.len() {
+        65 if public_key[0] == 0x04 => &public_key[1..],
+        _ => &public_key[..],
+    };
+
+    let ciphertext = CoSignProtocol::encrypt(public_key, &message)?;
+    write_ciphertext(out, &ciphertext, output, formats, armor)
+}
+
+/// 加密给用户 `username`：接收方公钥取自密钥目录的固定记录，没有记录时向服务端查询并固定
+#[allow(clippy::too_many_arguments)]
+async fn do_encrypt_for(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    username: &str,
+    message_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+    armor: bool,
+) -> anyhow::Result<()> {
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+    let client = CoSignClient::new(config.clone())?;
+    client.set_recipients(read_recipients(paths)?).await;
+    let pinned = client.get_recipient(username).await.is_some();
+
+    // Reason: 已固定的接收方直接本地加密，只有首次查询公钥时才需要登录
+    if !pinned {
+        let token = ensure_token(out, config, paths, &paths.token()).await?;
+        let user_id = std::fs::read_to_string(paths.user_id()).map_err(|_| login_required(paths.user_id()))?;
+        client.set_session(token, user_id).await?;
+    }
+
+    let ciphertext = client.encrypt_for(username, &message).await?;
+    if !pinned {
+        let recipient =
+            client.get_recipient(username).await.ok_or_else(|| anyhow::anyhow!("未能固定 {} 的公钥", username))?;
+        // Reason: 首次固定即信任服务端返回的公钥，打印出来便于与对方线下核对
+        out.info(format!("已固定 {} 的公钥: {}", username, hex::encode(recipient.public_key.as_bytes())));
+        write_recipients(paths, &client.get_recipients().await)?;
+    }
+    write_ciphertext(out, &ciphertext, output, formats, armor)
+}
+
+/// 保存已固定的接收方
+fn write_recipients(paths: &StatePaths, recipients: &[Recipient]) -> anyhow::Result<()> {
+    // Reason: 先写临时文件再原子替换，写入中断时不会截断已有的固定记录
+    let mut pending = paths.recipients().into_os_string();
+    pending.push(".new");
+    let pending = PathBuf::from(pending);
+    std::fs::write(&pending, serde_json::to_vec_pretty(recipients)?)?;
+    std::fs::rename(&pending, paths.recipients())?;
+    Ok(())
+}
+
+/// 读取已固定的接收方，文件不存在时为空
+fn read_recipients(paths: &StatePaths) -> anyhow::Result<Vec<Recipient>> {
+    match std::fs::read(paths.recipients()) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("接收方记录文件 {:?} 无效: {}", paths.recipients(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 按 --armor / 输出格式写出密文
+fn write_ciphertext(
+    out: &Output,
+    ciphertext: &[u8],
+    output: Option<&PathBuf>,
+    formats: Formats,
+    armor: bool,
+) -> anyhow::Result<()> {
+    if armor {
+        write_armored(out, "密文", output, pem::encode_ciphertext(ciphertext)?.as_bytes())?;
+    } else if let Some(output_path) = output {
+        stdio::write_output(output_path, &formats.encode_file(ciphertext))?;
+        out.info(format!("密文已保存到: {:?}", output_path));
+    } else {
+        out.info(format!("密文: {}", formats.encode_display(ciphertext)));
+    }
+
+    out.data(json!({
+        "ciphertext": hex::encode(ciphertext),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_csr(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    subject: &str,
+    output: &PathBuf,
+    pem_output: bool,
+    self_signed: Option<u32>,
+) -> anyhow::Result<()> {
+    use sm2_co_sign_core::x509::x509_cert::der::Encode;
+
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("未加载密钥对"))?;
+    let name = x509::subject_name(subject)?;
+    let (kind, key) = match self_signed {
+        Some(_) => ("自签名证书", "certificate"),
+        None => ("证书请求", "csr"),
+    };
+
+    out.info(format!("正在签名{}...", kind));
+
+    // Reason: CA 按标准 SM3withSM2 验证证书请求，核心库以 e = SM3(ZA || M) 签名并用公钥验证结果
+    let (label, der) = match self_signed {
+        Some(days) => {
+            let validity = std::time::Duration::from_secs(u64::from(days) * 86400);
+            let certificate = sm2_co_sign_core::x509::build_self_signed(&client, name, &public_key, validity, false).await?;
+            (x509::CERTIFICATE_LABEL, certificate.to_der()?)
+        }
+        None => {
+            let request = sm2_co_sign_core::x509::build_csr(&client, name, &public_key).await?;
+            ("CERTIFICATE REQUEST", request.to_der()?)
+        }
+    };
+    save_key_lifetime(paths, &client).await?;
+
+    let data = if pem_output {
+        pem::encode(label, &der).into_bytes()
+    } else {
+        der.clone()
+    };
+    stdio::write_output(output, &data)?;
+    out.info(format!("{}已保存到: {:?}", kind, output));
+
+    out.data(json!({
+        "subject": subject,
+        key: hex::encode(&der),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+/// 使用本地 Token 从服务端获取用户证书（DER）
+async fn fetch_certificate(config: &ClientConfig, paths: &StatePaths, token_file: &PathBuf) -> anyhow::Result<Vec<u8>> {
+    let token = read_token(token_file)
+        .map_err(|_| login_required(token_file))?;
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| login_required(paths.user_id()))?;
+
+    let client = CoSignClient::new(config.clone())?;
+    client.set_session(token, user_id).await?;
+    Ok(client.get_certificate().await?)
+}
+
+/// 显示证书字段，返回对应的 JSON 数据
+fn show_certificate(out: &Output, cert: &Certificate) -> serde_json::Value {
+    out.info(format!("序列号: {}", cert.serial));
+    out.info(format!("签发者: {}", cert.issuer));
+    out.info(format!("主题: {}", cert.subject));
+    out.info(format!("有效期: {} 至 {}", cert.not_before, cert.not_after));
+    out.info(format!("公钥: {}", hex::encode(&cert.public_key)));
+    json!({
+        "serial": cert.serial,
+        "issuer": cert.issuer,
+        "subject": cert.subject,
+        "not_before": cert.not_before,
+        "not_after": cert.not_after,
+        "public_key": hex::encode(&cert.public_key),
+    })
+}
+
+async fn do_cert_show(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    remote: bool,
+    token_file: &PathBuf,
+    cert_file: &PathBuf,
+) -> anyhow::Result<()> {
+    let data = if remote {
+        fetch_certificate(config, paths, token_file).await?
+    } else {
+        std::fs::read(cert_file)
+            .map_err(|_| anyhow::anyhow!("证书文件不存在: {:?}，可执行 cert install 从服务端获取", cert_file))?
+    };
+    let cert = Certificate::parse(&data)?;
+
+    out.data(show_certificate(out, &cert));
+
+    Ok(())
+}
+
+async fn do_cert_install(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    cert_file: &PathBuf,
+) -> anyhow::Result<()> {
+    let public_key = std::fs::read(paths.public_key())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.public_key()))?;
+
+    out.info("正在获取证书...");
+    let der = fetch_certificate(config, paths, token_file).await?;
+    let cert = Certificate::from_der(&der)?;
+
+    // Reason: 证书公钥与本地协同公钥不一致时，该证书无法用于验证本机生成的签名
+    if !cert.matches_public_key(&public_key) {
+        return Err(anyhow::anyhow!("证书公钥与协同公钥不一致，未保存证书"));
+    }
+
+    let info = show_certificate(out, &cert);
+    paths.ensure_dir()?;
+    std::fs::write(cert_file, pem::encode(x509::CERTIFICATE_LABEL, &der))?;
+    out.info(format!("证书已保存到 {:?}", cert_file));
+
+    out.data(info);
+
+    Ok(())
+}
+
+fn do_cert_verify(out: &Output, cert_file: &PathBuf, public_key_file: &PathBuf) -> anyhow::Result<bool> {
+    let data = std::fs::read(cert_file).map_err(|_| anyhow::anyhow!("证书文件不存在: {:?}", cert_file))?;
+    let cert = Certificate::parse(&data)?;
+    let public_key = std::fs::read(public_key_file)
+        .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+
+    let valid = cert.matches_public_key(&public_key);
+    if valid {
+        out.info("证书公钥与协同公钥一致");
+    } else {
+        out.warn("证书公钥与协同公钥不一致");
+    }
+    out.data(json!({ "valid": valid, "subject": cert.subject }));
+
+    Ok(valid)
+}
+
+fn do_verify_cert(out: &Output, cert_file: &PathBuf, ca_file: &PathBuf, purpose: KeyPurpose) -> anyhow::Result<bool> {
+    let data = std::fs::read(cert_file).map_err(|_| anyhow::anyhow!("证书文件不存在: {:?}", cert_file))?;
+    let cert = Certificate::parse(&data)?;
+    let data = std::fs::read(ca_file).map_err(|_| anyhow::anyhow!("CA 证书文件不存在: {:?}", ca_file))?;
+    let trusted = Certificate::parse_all(&data)?;
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let checks = x509::verify_certificate(&cert, &trusted, &x509::format_time(now), purpose);
+
+    out.info(format!("主题: {}", cert.subject));
+    out.info(format!("签发者: {}", cert.issuer));
+    out.info(format!("有效期: {} 至 {}", cert.not_before, cert.not_after));
+    if cert.key_usage.is_some() {
+        out.info(format!("密钥用途: {}", cert.key_usage_names().join(", ")));
+    }
+    for check in &checks {
+        match &check.error {
+            None => out.info(format!("[PASS] {}", check.name)),
+            Some(error) => out.info(format!("[FAIL] {}: {}", check.name, error)),
+        }
+    }
+
+    let valid = checks.iter().all(|c| c.passed());
+    if valid {
+        out.info("证书校验通过");
+    } else {
+        out.warn("证书校验失败");
+    }
+
+    out.data(json!({
+        "valid": valid,
+        "subject": cert.subject,
+        "issuer": cert.issuer,
+        "serial": cert.serial,
+        "not_before": cert.not_before,
+        "not_after": cert.not_after,
+        "key_usage": cert.key_usage.is_some().then(|| cert.key_usage_names()),
+        "checks": checks
+            .iter()
+            .map(|c| json!({ "name": c.name, "passed": c.passed(), "error": c.error }))
+            .collect::<Vec<_>>(),
+    }));
+
+    Ok(valid)
+}
+
+fn do_key_export(out: &Output, paths: &StatePaths, d1_file: &PathBuf, format: KeyFormat, output: &PathBuf) -> anyhow::Result<()> {
+    let d1_data = std::fs::read(d1_file).map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", d1_file))?;
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.user_id()))?;
+    let public_key = std::fs::read(paths.public_key())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.public_key()))?;
+
+    // Reason: TPM 密封的 D1 离开本机无法解封，导出没有意义；解封后重新加密导出会绕过 TPM 保护
+    if keystore::is_tpm_sealed(&d1_data) {
+        anyhow::bail!("{:?} 由本机 TPM 密封，不能导出；迁移到新机器需在新机器上执行 init-key 生成新密钥", d1_file);
+    }
+
+    // Reason: 导出包携带 P1 供导入时校验，需先解锁 D1；同时确认口令能打开将要导出的密钥库
+    let (d1, legacy) = keystore::unlock_d1(&d1_data)?;
+    let p1 = CoSignProtocol::new()?.calculate_p1(&d1)?;
+
+    // Reason: 导出文件只携带加密后的 D1，旧版明文 D1 先用口令加密
+    let keystore = if legacy {
+        out.warn(format!("{:?} 为未加密的旧版 D1 文件，导出前将使用口令加密", d1_file));
+        keystore::seal(&d1, keystore::passphrase(true)?)?
+    } else {
+        d1_data
+    };
+
+    let bundle = KeyBundle::new(&user_id, &public_key, &keystore, &p1)?;
+    stdio::write_output(output, &bundle.encode(format)?)?;
+    out.info(format!("密钥已导出到: {:?}", output));
+
+    out.data(json!({
+        "user_id": bundle.user_id,
+        "public_key": bundle.public_key,
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+fn do_key_import(out: &Output, paths: &StatePaths, input: &PathBuf, d1_file: &PathBuf, force: bool) -> anyhow::Result<()> {
+    // Reason: 覆盖现有 D1 会丢失原密钥，与 init-key 一致需显式确认
+    if d1_file.exists() && !force {
+        return Err(anyhow::anyhow!("D1 文件已存在: {:?}，如需覆盖请添加 --force", d1_file));
+    }
+
+    let bundle = KeyBundle::decode(&stdio::read_input(input)?)?;
+    let keystore = bundle.keystore()?;
+    if !keystore::is_keystore(&keystore) {
+        return Err(anyhow::anyhow!("密钥文件中的 D1 不是有效的密钥库格式"));
+    }
+    PublicKey::from_slice(&bundle.public_key()?).map_err(|e| anyhow::Error::new(e).context("密钥文件中的公钥无效"))?;
+
+    // Reason: 覆盖本地文件前先用口令解锁并校验 D1，口令错误或 D1 被替换时保留原密钥不动
+    let d1 = keystore::open(&keystore, keystore::passphrase(false)?)?;
+    match bundle.p1()? {
+        Some(p1) => {
+            if CoSignProtocol::new()?.calculate_p1(&d1)? != p1 {
+                anyhow::bail!("密钥文件中的 D1 与 P1 不匹配，文件可能已被篡改");
+            }
+        }
+        None => out.warn("密钥文件为旧版导出包，不含 P1，无法校验 D1 与公钥的对应关系"),
+    }
+
+    paths.ensure_dir()?;
+    keystore::write_sealed(d1_file, &keystore)?;
+    out.info(format!("私钥分量已保存到 {:?}", d1_file));
+    // Reason: 导入的 D1 创建时间未知，旧记录属于被替换的 D1
+    let _ = std::fs::remove_file(paths.key_lifetime());
+
+    std::fs::write(paths.public_key(), bundle.public_key()?)?;
+    out.info(format!("公钥已保存到 {:?}", paths.public_key()));
+
+    std::fs::write(paths.user_id(), &bundle.user_id)?;
+    out.info(format!("用户ID已保存到 {:?}", paths.user_id()));
+
+    out.data(json!({
+        "user_id": bundle.user_id,
+        "public_key": bundle.public_key,
+    }));
+
+    Ok(())
+}
+
+fn do_key_escrow_export(
+    out: &Output,
+    paths: &StatePaths,
+    d1_file: &PathBuf,
+    officers: [&PathBuf; ESCROW_OFFICERS],
+    output: &PathBuf,
+) -> anyhow::Result<()> {
+    let d1_data = std::fs::read(d1_file).map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", d1_file))?;
+    let user_id = std::fs::read_to_string(paths.user_id())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.user_id()))?;
+    let public_key = std::fs::read(paths.public_key())
+        .map_err(|_| anyhow::anyhow!("请先注册（{:?} 文件不存在）", paths.public_key()))?;
+    let officer_key = |path: &PathBuf| {
+        PublicKey::from_file(path).map_err(|e| anyhow::Error::new(e).context(format!("托管员公钥文件 {:?} 无效", path)))
+    };
+    let (officer_a, officer_b) = (officer_key(officers[0])?, officer_key(officers[1])?);
+
+    // Reason: 与 key export 不同，托管必须解出 D1 明文才能拆分；TPM 密封的 D1 按设计不离开本机，同样拒绝
+    if keystore::is_tpm_sealed(&d1_data) {
+        anyhow::bail!("{:?} 由本机 TPM 密封，不能托管导出", d1_file);
+    }
+    let (d1, legacy) = keystore::unlock_d1(&d1_data)?;
+    if legacy {
+        out.warn(format!("{:?} 为未加密的旧版 D1 文件", d1_file));
+    }
+
+    let package = EscrowPackage::seal(&d1, user_id.trim(), &public_key, [officer_a.as_bytes(), officer_b.as_bytes()])?;
+    stdio::write_output(output, &package.to_json()?)?;
+    out.info(format!("D1 托管包已保存到: {:?}（恢复需两名托管员的私钥同时参与）", output));
+
+    out.data(json!({
+        "user_id": package.user_id,
+        "public_key": hex::encode(package.public_key.as_bytes()),
+        "officers": package.shares.iter().map(|share| hex::encode(share.officer.as_bytes())).collect::<Vec<_>>(),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+fn do_key_escrow_import(
+    out: &Output,
+    paths: &StatePaths,
+    input: &PathBuf,
+    officer_keys: [&PathBuf; ESCROW_OFFICERS],
+    d1_file: &PathBuf,
+    force: bool,
+) -> anyhow::Result<()> {
+    if d1_file.exists() && !force {
+        return Err(anyhow::anyhow!("D1 文件已存在: {:?}，如需覆盖请添加 --force", d1_file));
+    }
+
+    let package = EscrowPackage::from_json(&stdio::read_input(input)?)?;
+
+    // Reason: 两名托管员的私钥通常使用各自的口令，不能复用进程内缓存的密钥库口令
+    let mut shares = Vec::with_capacity(ESCROW_OFFICERS);
+    for (index, (path, label)) in officer_keys.into_iter().zip(["A", "B"]).enumerate() {
+        let key = load_officer_key(out, path, label)?;
+        let share = package
+            .decrypt_share(index, &key)
+            .map_err(|e| anyhow::Error::new(e).context(format!("托管员 {} 的份额解密失败", label)))?;
+        shares.push(share);
+    }
+    let d1 = package.combine(&shares[0], &shares[1])?;
+
+    paths.ensure_dir()?;
+    keystore::prepare_write()?;
+    keystore::write_d1(d1_file, d1.as_bytes())?;
+    out.info(format!("私钥分量已恢复到 {:?}", d1_file));
+    // Reason: 恢复的 D1 创建时间未知，旧记录属于被替换的 D1
+    let _ = std::fs::remove_file(paths.key_lifetime());
+
+    std::fs::write(paths.public_key(), package.public_key.as_bytes())?;
+    out.info(format!("公钥已保存到 {:?}", paths.public_key()));
+
+    std::fs::write(paths.user_id(), &package.user_id)?;
+    out.info(format!("用户ID已保存到 {:?}", paths.user_id()));
+
+    out.data(json!({
+        "user_id": package.user_id,
+        "public_key": hex::encode(package.public_key.as_bytes()),
+    }));
+
+    Ok(())
+}
+
+/// 读取托管员私钥：密钥库格式单独提示输入该托管员的口令
+fn load_officer_key(out: &Output, key_file: &PathBuf, label: &str) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let data = std::fs::read(key_file).map_err(|_| anyhow::anyhow!("托管员 {} 的私钥文件不存在: {:?}", label, key_file))?;
+    if keystore::is_tpm_sealed(&data) {
+        return Ok(keystore::unlock_d1(&data)?.0);
+    }
+    if keystore::is_keystore(&data) {
+        let passphrase = keystore::prompt_passphrase(&format!("托管员 {} 的密钥库口令: ", label))?;
+        return keystore::open(&data, &passphrase);
+    }
+    out.warn(format!("{:?} 为未加密的私钥文件", key_file));
+    parse_raw_key(Zeroizing::new(data))
+}
+
+async fn do_key_rotate(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+) -> anyhow::Result<()> {
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let refresh = client.prepare_key_refresh().await?;
+
+    // Reason: 先落盘新 D1 再提交，服务端确认后原子替换；避免服务端已切换而本地新 D1 丢失
+    let mut pending = d1_file.clone().into_os_string();
+    pending.push(".new");
+    let pending = PathBuf::from(pending);
+    keystore::write_d1(&pending, &refresh.d1)?;
+
+    out.info("正在刷新私钥分量...");
+    if let Err(e) = client.refresh_key(&refresh).await {
+        // Reason: 网络错误或非 2xx 响应（如网关超时）时无法确定服务端是否已切换，保留新 D1 供人工确认
+        if matches!(e.kind(), ErrorKind::Network | ErrorKind::Http) {
+            return Err(anyhow::Error::new(e).context(format!(
+                "无法确认服务端是否已完成密钥轮换，本地 D1 未改变，新 D1 保留在 {:?}；若签名失败请用其替换 {:?}",
+                pending, d1_file
+            )));
+        }
+        let _ = std::fs::remove_file(&pending);
+        return Err(anyhow::Error::new(e).context("密钥轮换失败，本地 D1 未改变"));
+    }
+
+    std::fs::rename(&pending, d1_file).map_err(|e| {
+        anyhow::anyhow!(
+            "服务端已完成密钥轮换，但替换本地 D1 失败: {}；新 D1 保存在 {:?}，请手动替换 {:?}",
+            e,
+            pending,
+            d1_file
+        )
+    })?;
+    out.info(format!("私钥分量已刷新，新 D1 已保存到 {:?}，公钥保持不变", d1_file));
+    save_key_lifetime(paths, &client).await?;
+
+    let user_id = std::fs::read_to_string(paths.user_id()).unwrap_or_default();
+    out.data(json!({
+        "user_id": user_id,
+        "d1_file": d1_file,
+    }));
+
+    Ok(())
+}
+
+fn do_sm3(out: &Output, file: &PathBuf, za_public_key: Option<&PathBuf>, formats: Formats) -> anyhow::Result<()> {
+    let message = formats.input.decode(&stdio::read_input(file)?)?;
+
+    let digest = match za_public_key {
+        Some(public_key_file) => {
+            let public_key = std::fs::read(public_key_file)
+                .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+            CoSignProtocol::new()?.calculate_message_hash_with_uid(&message, DEFAULT_USER_ID, &public_key)?
+        }
+        None => CoSignProtocol::sm3_hash(&message),
+    };
+
+    out.info(formats.encode_display(&digest));
+    out.data(json!({
+        "digest": hex::encode(&digest),
+        "za": za_public_key.is_some(),
+    }));
+
+    Ok(())
+}
+
+/// 读取本地私钥：密钥库格式需口令解锁，兼容原始 32 字节与十六进制文本
+fn load_local_key(out: &Output, key_file: &PathBuf) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let data = std::fs::read(key_file)
+        .map_err(|_| anyhow::anyhow!("私钥文件不存在: {:?}，可执行 local keygen 生成", key_file))?;
+    let (key, legacy) = keystore::unlock_d1(&data)?;
+    if legacy {
+        out.warn(format!("{:?} 为未加密的私钥文件", key_file));
+        return parse_raw_key(key);
+    }
+    if key.len() != 32 {
+        return Err(anyhow::anyhow!("私钥长度错误: {}", key.len()));
+    }
+    Ok(key)
+}
+
+/// 解析未加密的私钥文件内容：原始 32 字节或十六进制文本
+fn parse_raw_key(data: Zeroizing<Vec<u8>>) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let key = match data.len() {
+        32 => data,
+        _ => Zeroizing::new(
+            std::str::from_utf8(&data)
+                .ok()
+                .and_then(|text| hex::decode(text.trim()).ok())
+                .ok_or_else(|| anyhow::anyhow!("无法识别的私钥格式（支持密钥库、原始 32 字节或十六进制）"))?,
+        ),
+    };
+    if key.len() != 32 {
+        return Err(anyhow::anyhow!("私钥长度错误: {}", key.len()));
+    }
+    Ok(key)
+}
+
+fn do_local_keygen(
+    out: &Output,
+    paths: &StatePaths,
+    key_file: &PathBuf,
+    public_key_file: &PathBuf,
+    force: bool,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    if key_file.exists() && !force {
+        return Err(anyhow::anyhow!("私钥文件已存在: {:?}，如需覆盖请添加 --force", key_file));
+    }
+
+    let (private_key, public_key) = CoSignProtocol::generate_keypair();
+    let private_key = Zeroizing::new(private_key);
+
+    paths.ensure_dir()?;
+    keystore::write_d1(key_file, &private_key)?;
+    out.info(format!("私钥已加密保存到 {:?}", key_file));
+    std::fs::write(public_key_file, &public_key)?;
+    out.info(format!("公钥已保存到 {:?}", public_key_file));
+    qr.emit(out, "公钥", &qr::payload(None, &public_key))?;
+
+    out.data(json!({ "public_key": hex::encode(&public_key) }));
+
+    Ok(())
+}
+
+fn do_local_sign(
+    out: &Output,
+    key_file: &PathBuf,
+    message_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+    qr: &QrArgs,
+) -> anyhow::Result<()> {
+    let private_key = load_local_key(out, key_file)?;
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+
+    let signature = CoSignProtocol::sign(&private_key, &message)?;
+
+    if let Some(output_path) = output {
+        stdio::write_output(output_path, &formats.encode_file(&signature))?;
+        out.info(format!("签名已保存到: {:?}", output_path));
+    } else {
+        out.info(format!("签名: {}", formats.encode_display(&signature)));
+    }
+    qr.emit(out, "签名", &qr::payload(formats.output, &signature))?;
+
+    out.data(json!({
+        "signature": hex::encode(&signature),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+fn do_local_verify(
+    out: &Output,
+    message_file: &PathBuf,
+    signature_file: &PathBuf,
+    public_key_file: &PathBuf,
+    formats: Formats,
+) -> anyhow::Result<bool> {
+    if stdio::is_stdio(message_file) && stdio::is_stdio(signature_file) {
+        return Err(anyhow::anyhow!("消息与签名不能同时从 stdin 读取"));
+    }
+    let message = formats.input.decode(&stdio::read_input(message_file)?)?;
+    let signature = parse_signature(&formats.input.decode(&stdio::read_input(signature_file)?)?)?;
+    let public_key = std::fs::read(public_key_file)
+        .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+
+    let valid = CoSignProtocol::verify(&public_key, &message, &signature)?;
+
+    if valid {
+        out.info("验签成功");
+    } else {
+        out.warn("验签失败");
+    }
+    out.data(json!({ "valid": valid }));
+
+    Ok(valid)
+}
+
+fn do_local_decrypt(
+    out: &Output,
+    key_file: &PathBuf,
+    ciphertext_file: &PathBuf,
+    output: Option<&PathBuf>,
+    formats: Formats,
+) -> anyhow::Result<()> {
+    let private_key = load_local_key(out, key_file)?;
+    let ciphertext = read_ciphertext(ciphertext_file, formats)?;
+
+    let plaintext = CoSignProtocol::decrypt(&private_key, &ciphertext)?
+        .ok_or_else(|| anyhow::anyhow!("解密失败：密文格式错误或与私钥不匹配"))?;
+
+    if let Some(output_path) = output {
+        stdio::write_output(output_path, &formats.encode_file(&plaintext))?;
+        out.info(format!("明文已保存到: {:?}", output_path));
+    } else if formats.output.is_some() {
+        out.info(format!("明文: {}", formats.encode_display(&plaintext)));
+    } else {
+        out.info(format!("明文: {}", String::from_utf8_lossy(&plaintext)));
+    }
+
+    out.data(json!({
+        "plaintext": hex::encode(&plaintext),
+        "output": output,
+    }));
+
+    Ok(())
+}
+
+/// `--receipt` 时以本地私钥打开回执链，接着回执文件中的最后一张继续签发
+fn open_receipts(
+    out: &Output,
+    paths: &StatePaths,
+    enabled: bool,
+) -> anyhow::Result<Option<std::sync::Arc<ReceiptChain>>> {
+    if !enabled {
+        return Ok(None);
+    }
+    let private_key = load_local_key(out, &paths.local_key())?;
+    let receipts_file = paths.receipts();
+    let receipts = if receipts_file.exists() { read_receipts(&receipts_file)? } else { Vec::new() };
+    let chain = match receipts.last() {
+        Some(last) => ReceiptChain::resume(&private_key, last)
+            .map_err(|e| anyhow::anyhow!("无法接着回执文件 {:?} 签发: {}", receipts_file, e))?,
+        None => ReceiptChain::new(&private_key)?,
+    };
+    Ok(Some(std::sync::Arc::new(chain)))
+}
+
+/// 将本次签发的回执逐行追加到回执文件
+fn save_receipts(out: &Output, paths: &StatePaths, chain: Option<&ReceiptChain>) -> anyhow::Result<()> {
+    let Some(chain) = chain else {
+        return Ok(());
+    };
+    let issued = chain.take_issued();
+    if issued.is_empty() {
+        return Ok(());
+    }
+    let receipts_file = paths.receipts();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&receipts_file)?;
+    for receipt in &issued {
+        writeln!(file, "{}", receipt.to_json()?)?;
+        out.info(format!("操作回执 #{} 已追加到 {:?}", receipt.sequence, receipts_file));
+    }
+    Ok(())
+}
+
+/// 读取逐行 JSON 的回执文件，跳过空行
+fn read_receipts(input: &PathBuf) -> anyhow::Result<Vec<Receipt>> {
+    let data = stdio::read_input(input)?;
+    let text = std::str::from_utf8(&data).map_err(|_| anyhow::anyhow!("回执文件不是有效的 UTF-8: {:?}", input))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            Receipt::from_json(line.as_bytes()).map_err(|e| anyhow::anyhow!("回执文件第 {} 行无效: {}", index + 1, e))
+        })
+        .collect()
+}
+
+fn do_receipt_verify(out: &Output, input: &PathBuf, public_key_file: Option<&PathBuf>) -> anyhow::Result<bool> {
+    let receipts = read_receipts(input)?;
+    let signer = public_key_file
+        .map(|path| {
+            let data = std::fs::read(path).map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", path))?;
+            Ok::<_, anyhow::Error>(PublicKey::from_slice(&data)?)
+        })
+        .transpose()?;
+
+    let result = receipt::verify_chain(&receipts, signer.as_ref());
+    match &result {
+        Ok(()) => out.info(format!("回执链有效，共 {} 张", receipts.len())),
+        Err(e) => out.warn(format!("回执链无效: {}", e)),
+    }
+    out.data(json!({
+        "valid": result.is_ok(),
+        "count": receipts.len(),
+        "first": receipts.first().map(|r| r.sequence),
+        "last": receipts.last().map(|r| r.sequence),
+        "signer": receipts.first().map(|r| hex::encode(r.signer.as_bytes())),
+        "error": result.as_ref().err().map(|e| e.to_string()),
+    }));
+    Ok(result.is_ok())
+}
+
+fn do_receipt_export(
+    out: &Output,
+    input: &PathBuf,
+    from: u64,
+    to: Option<u64>,
+    output: &PathBuf,
+) -> anyhow::Result<()> {
+    let receipts = read_receipts(input)?;
+    let selected: Vec<_> =
+        receipts.into_iter().filter(|r| r.sequence >= from && to.map_or(true, |to| r.sequence <= to)).collect();
+    if selected.is_empty() {
+        return Err(anyhow::anyhow!("回执文件中没有序号在指定范围内的回执"));
+    }
+    // Reason: 导出的片段用于向第三方举证，先确认其本身可以通过校验
+    receipt::verify_chain(&selected, None)?;
+
+    let mut data = Vec::new();
+    for receipt in &selected {
+        writeln!(data, "{}", receipt.to_json()?)?;
+    }
+    stdio::write_output(output, &data)?;
+    out.info(format!("已导出 {} 张回执到 {:?}", selected.len(), output));
+    out.data(json!({
+        "count": selected.len(),
+        "first": selected.first().map(|r| r.sequence),
+        "last": selected.last().map(|r| r.sequence),
+        "output": output,
+    }));
+    Ok(())
+}
+
+fn do_envelope_encrypt(
+    out: &Output,
+    input: &PathBuf,
+    output: &PathBuf,
+    public_key_file: &PathBuf,
+    chunk_size: u32,
+) -> anyhow::Result<()> {
+    let public_key = std::fs::read(public_key_file)
+        .map_err(|_| anyhow::anyhow!("公钥文件不存在: {:?}", public_key_file))?;
+    let public_key = match public_key.len() {
+        65 if public_key[0] == 0x04 => &public_key[1..],
+        _ => &public_key[..],
+    };
+
+    let progress = out.progress(stdio::input_len(input), "加密");
+    let reader = progress.wrap_read(stdio::open_input(input)?);
+    let result = stdio::create_output(output).and_then(|writer| envelope::seal(public_key, chunk_size, reader, writer));
+    progress.finish_and_clear();
+    let size = discard_on_error(output, result)?;
+    out.info(format!("数字信封已保存到: {:?}（原文 {} 字节）", output, size));
+
+    out.data(json!({ "output": output, "size": size }));
+
+    Ok(())
+}
+
+async fn do_envelope_decrypt(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    input: &PathBuf,
+    output: &PathBuf,
+) -> anyhow::Result<()> {
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let mut reader = stdio::open_input(input)?;
+    let header = envelope::Header::read(&mut reader)?;
+
+    out.info("正在协同解密信封密钥...");
+    let material = Zeroizing::new(client.decrypt(&header.key_ciphertext).await?);
+    save_key_lifetime(paths, &client).await?;
+
+    // 进度按信封文件大小计算，文件头已读取
+    let progress = out.progress(stdio::input_len(input), "解密");
+    progress.inc(header.size() as u64);
+    let reader = progress.wrap_read(reader);
+    let result = stdio::create_output(output).and_then(|writer| envelope::open_body(&header, &material, reader, writer));
+    progress.finish_and_clear();
+    let size = discard_on_error(output, result)?;
+    out.info(format!("明文已保存到: {:?}（{} 字节）", output, size));
+
+    out.data(json!({ "output": output, "size": size }));
+
+    Ok(())
+}
+
+/// 流式处理失败时删除不完整的输出文件
+fn discard_on_error<T>(output: &PathBuf, result: anyhow::Result<T>) -> anyhow::Result<T> {
+    if result.is_err() && !stdio::is_stdio(output) {
+        let _ = std::fs::remove_file(output);
+    }
+    result
+}
+
+async fn do_health(out: &Output, config: &ClientConfig) -> anyhow::Result<()> {
+    let client = CoSignClient::new(config.clone())?;
+    let healthy = client.health_check().await?;
+
+    if healthy {
+        out.info("服务状态: 正常");
+    } else {
+        out.info("服务状态: 异常");
+    }
+    out.data(json!({ "healthy": healthy }));
+
+    Ok(())
+}
+
+fn do_selftest(out: &Output) -> bool {
+    let results = sm2_co_sign_core::selftest::run();
+    for result in &results {
+        match &result.error {
+            None => out.info(format!("[PASS] {}", result.name)),
+            Some(error) => out.info(format!("[FAIL] {}: {}", result.name, error)),
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed == 0 {
+        out.info(format!("自检通过（{} 项）", results.len()));
+    } else {
+        out.warn(format!("自检失败: {}/{} 项未通过", failed, results.len()));
+    }
+
+    out.data(json!({
+        "passed": failed == 0,
+        "tests": results
+            .iter()
+            .map(|r| json!({ "name": r.name, "passed": r.passed(), "error": r.error }))
+            .collect::<Vec<_>>(),
+    }));
+
+    failed == 0
+}
+
+async fn do_bench(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    iterations: u32,
+    remote: Option<(&PathBuf, &PathBuf)>,
+) -> anyhow::Result<()> {
+    let protocol = CoSignProtocol::new()?;
+    let message = b"sm2 co-sign benchmark";
+    let d1 = protocol.generate_d1()?;
+    let p1 = protocol.calculate_p1(&d1)?;
+
+    out.info(format!("本地协议测试（{} 次）...", iterations));
+    let mut results = vec![
+        Stats::measure("keygen", iterations, || {
+            let d1 = protocol.generate_d1()?;
+            protocol.calculate_p1(&d1)?;
+            Ok(())
+        })?,
+        Stats::measure("sign_prepare", iterations, || {
+            protocol.calculate_message_hash_with_uid(message, DEFAULT_USER_ID, &p1)?;
+            let _session = protocol.sign_prepare()?;
+            Ok(())
+        })?,
+        // Reason: 完成步骤的耗时与 r/s2/s3 取值无关，以随机值代替服务端响应
+        Stats::measure("sign_complete", iterations, || {
+            let session = protocol.sign_prepare()?;
+            let [r, s2, s3] = [(); 3].map(|_| CoSignProtocol::generate_random(32));
+            session.complete(&protocol, &d1, &r, &s2, &s3)?;
+            Ok(())
+        })?,
+    ];
+
+    if let Some((token_file, d1_file)) = remote {
+        let client = load_client(out, config, paths, token_file, d1_file).await?;
+        out.info(format!("协同签名测试（{} 次，服务端 {}）...", iterations, config.server_url));
+        let mut samples = Vec::with_capacity(iterations as usize);
+        for _ in 0..iterations {
+            let start = std::time::Instant::now();
+            client.sign(message, DigestMode::Sm3).await?;
+            samples.push(start.elapsed());
+        }
+        save_key_lifetime(paths, &client).await?;
+        results.push(Stats::from_samples("remote_sign", samples));
+    }
+
+    for stats in &results {
+        out.info(stats.report());
+    }
+    out.data(json!({
+        "iterations": iterations,
+        "results": results.iter().map(Stats::to_json).collect::<Vec<_>>(),
+    }));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn do_agent(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    socket: &PathBuf,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+) -> anyhow::Result<()> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let public_key = client
+        .get_public_key()
+        .await
+        .map(|public_key| public_key.to_vec())
+        .ok_or_else(|| anyhow::anyhow!("未加载密钥对"))?;
+    let credentials = saved_credentials(paths)?;
+
+    // Reason: 仅清理上次异常退出遗留的套接字，不覆盖普通文件或仍在运行的代理
+    if let Ok(metadata) = std::fs::symlink_metadata(socket) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{:?} 已存在且不是套接字文件", socket);
+        }
+        if tokio::net::UnixStream::connect(socket).await.is_ok() {
+            anyhow::bail!("签名代理已在运行: {:?}", socket);
+        }
+        std::fs::remove_file(socket)?;
+    }
+    let listener = tokio::net::UnixListener::bind(socket).map_err(|e| anyhow::anyhow!("无法监听 {:?}: {}", socket, e))?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    let owner_uid = std::fs::metadata(socket)?.uid();
+
+    out.info(format!("签名代理已启动: {:?}（Ctrl+C 退出）", socket));
+    if credentials.is_none() {
+        out.warn("未找到登录凭据，Token 失效后需重新登录并重启代理");
+    }
+    out.data(json!({ "socket": socket }));
+
+    let signer = signer::Signer::new(client, public_key, credentials).with_lifetime_file(paths.key_lifetime());
+    let agent = std::sync::Arc::new(agent::Agent::new(signer));
+    let result = tokio::select! {
+        result = agent.serve(listener, owner_uid) => result,
+        _ = tokio::signal::ctrl_c() => {
+            out.info("签名代理已停止");
+            Ok(())
+        }
+    };
+    let _ = std::fs::remove_file(socket);
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn do_serve(
+    out: &Output,
+    config: &ClientConfig,
+    paths: &StatePaths,
+    listen: &str,
+    token_file: &PathBuf,
+    d1_file: &PathBuf,
+    allow_origin: Vec<String>,
+) -> anyhow::Result<()> {
+    let addr: std::net::SocketAddr = listen
+        .parse()
+        .map_err(|_| UsageError(format!("无效的监听地址: {}", listen)))?;
+    // Reason: 接口无 TLS，只以 API Token 认证，不允许暴露到本机以外
+    if !addr.ip().is_loopback() {
+        return Err(UsageError(format!("仅允许监听回环地址: {}", listen)).into());
+    }
+
+    let client = load_client(out, config, paths, token_file, d1_file).await?;
+    let public_key = client
+        .get_public_key()
+        .await
+        .map(|public_key| public_key.to_vec())
+        .ok_or_else(|| anyhow::anyhow!("未加载密钥对"))?;
+    let credentials = saved_credentials(paths)?;
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("无法监听 {}: {}", addr, e))?;
+    let addr = listener.local_addr()?;
+
+    // 每次启动生成新的 API Token，仅写入权限为 0600 的文件
+    let api_token = hex::encode(CoSignProtocol::generate_random(32));
+    logging::add_secret(&api_token);
+    let api_token_file = paths.serve_token();
+    keystore::write_sealed(&api_token_file, api_token.as_bytes())?;
+
+    out.info(format!("本地 HTTP 服务已启动: http://{}（Ctrl+C 退出）", addr));
+    out.info(format!("API Token 已写入 {:?}，请求须携带 Authorization: Bearer <Token>", api_token_file));
+    if credentials.is_none() {
+        out.warn("未找到登录凭据，Token 失效后需重新登录并重启服务");
+    }
+    out.data(json!({ "url": format!("http://{}", addr), "token_file": api_token_file }));
+
+    let server = std::sync::Arc::new(serve::Server::new(
+        signer::Signer::new(client, public_key, credentials).with_lifetime_file(paths.key_lifetime()),
+        api_token,
+        allow_origin,
+    ));
+    let result = tokio::select! {
+        result = server.serve(listener) => result,
+        _ = tokio::signal::ctrl_c() => {
+            out.info("本地 HTTP 服务已停止");
+            Ok(())
+        }
+    };
+    let _ = std::fs::remove_file(&api_token_file);
+    result
+}
+
+async fn do_mock_server(out: &Output, bind: &str, port: u16) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind((bind, port))
+        .await
+        .map_err(|e| anyhow::anyhow!("无法监听 {}:{}: {}", bind, port, e))?;
+    let addr = listener.local_addr()?;
+
+    out.warn("模拟服务端仅用于开发测试：D2 明文保存在内存中，退出后数据丢失");
+    out.info(format!("模拟服务端已启动: http://{}（Ctrl+C 退出）", addr));
+    out.data(json!({ "url": format!("http://{}", addr) }));
+
+    let server = std::sync::Arc::new(mock_server::MockServer::new());
+    tokio::select! {
+        result = server.serve(listener) => result,
+        _ = tokio::signal::ctrl_c() => {
+            out.info("模拟服务端已停止");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试独立的临时密钥目录
+    fn temp_paths(name: &str) -> StatePaths {
+        let dir = std::env::temp_dir().join(format!("sm2-cosign-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        StatePaths::new(dir)
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_token_has_no_side_effects() {
+        let paths = temp_paths("dry-run-token");
+        paths.ensure_dir().unwrap();
+        let token_file = paths.token();
+        let expires_at = paths::token_expires_at(&token_file);
+        std::fs::write(&token_file, "stale-token\n").unwrap();
+        std::fs::write(&expires_at, "2000-01-01T00:00:00Z").unwrap();
+        std::fs::write(paths.username(), "alice").unwrap();
+        std::env::set_var(PASSWORD_ENV, "password");
+
+        let out = Output::new(true, false);
+        // Reason: 服务端不可达，任何校验或登录请求都会失败
+        let config = ClientConfig { server_url: "http://127.0.0.1:1".to_string(), ..ClientConfig::default() };
+
+        // Token 已过期且有凭据，非 dry-run 时会自动重新登录
+        assert!(session_token(&out, &config, &paths, &token_file, false).await.is_err());
+
+        let token = session_token(&out, &config, &paths, &token_file, true).await.unwrap();
+        assert_eq!(token, "stale-token");
+        assert_eq!(std::fs::read_to_string(&token_file).unwrap(), "stale-token\n");
+        assert_eq!(std::fs::read_to_string(&expires_at).unwrap(), "2000-01-01T00:00:00Z");
+        std::fs::remove_dir_all(paths.dir()).unwrap();
     }
-    
-    Ok(())
 }
@@ -0,0 +1,736 @@
+//! 本地模拟服务端
+//!
+//! 在内存中模拟协同签名网关，服务端计算使用 core 的 `D2Simulator`，
+//! 便于在未部署真实网关时本地运行完整的 CLI 与客户端库流程。
+//! 仅实现客户端用到的接口，数据不落盘，进程退出即丢失；请勿用于生产环境。
+
+use crate::http::{self, Request};
+use serde_json::{json, Value};
+use sm2_co_sign_core::attestation::{self, AttestationStatement, DeviceEvidence};
+use sm2_co_sign_core::protocol::{base64_decode, base64_encode, hex_encode};
+use sm2_co_sign_core::simulator::{D2Nonce, D2Simulator};
+use sm2_co_sign_core::subkey;
+use sm2_co_sign_core::CoSignProtocol;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Token 有效期（秒）
+const TOKEN_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// 业务错误码
+const CODE_INVALID_PARAM: i32 = 400;
+const CODE_UNAUTHORIZED: i32 = 401;
+const CODE_NOT_FOUND: i32 = 404;
+const CODE_USER_EXISTS: i32 = 1001;
+const CODE_BAD_CREDENTIALS: i32 = 1002;
+
+struct User {
+    id: String,
+    username: String,
+    password: String,
+    /// 主 P1，用于校验子密钥的 P1_p
+    p1: Vec<u8>,
+    d2: Vec<u8>,
+    public_key: Vec<u8>,
+    /// 用途 -> 子密钥
+    sub_keys: HashMap<String, SubKey>,
+    created_at: u64,
+}
+
+/// 按用途派生的子密钥
+struct SubKey {
+    generation: u32,
+    d2: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+/// 请求所用的子密钥（用途, 代次），`None` 为主密钥
+type KeyScope = Option<(String, u32)>;
+
+/// 随机数承诺签名中等待客户端揭示 Q1 的会话
+struct PendingSign {
+    user_id: String,
+    scope: KeyScope,
+    e: Vec<u8>,
+    /// 客户端承诺 SM3(Q1)
+    commitment: Vec<u8>,
+    nonce: D2Nonce,
+}
+
+#[derive(Default)]
+struct State {
+    /// user_id -> 用户
+    users: HashMap<String, User>,
+    /// token -> user_id
+    tokens: HashMap<String, String>,
+    /// session_id -> 随机数承诺签名会话
+    pending_signs: HashMap<String, PendingSign>,
+}
+
+/// 模拟服务端
+pub struct MockServer {
+    simulator: D2Simulator,
+    state: Mutex<State>,
+}
+
+/// 业务处理结果：成功数据或 (错误码, 错误信息)
+type ApiResult = Result<Value, (i32, String)>;
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn invalid(msg: impl Into<String>) -> (i32, String) {
+    (CODE_INVALID_PARAM, msg.into())
+}
+
+/// 读取 Base64 编码的请求字段
+fn field_bytes(body: &Value, name: &str) -> Result<Vec<u8>, (i32, String)> {
+    let value = body[name].as_str().ok_or_else(|| invalid(format!("missing field: {}", name)))?;
+    base64_decode(value).map_err(|e| invalid(format!("invalid field {}: {}", name, e)))
+}
+
+fn field_str<'a>(body: &'a Value, name: &str) -> Result<&'a str, (i32, String)> {
+    body[name].as_str().ok_or_else(|| invalid(format!("missing field: {}", name)))
+}
+
+fn field_u32(body: &Value, name: &str) -> Result<u32, (i32, String)> {
+    body[name]
+        .as_u64()
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or_else(|| invalid(format!("missing field: {}", name)))
+}
+
+/// 校验请求中可选的设备证明：声明须绑定本次的 `subject` 与 P1，`sm2` 格式按叶证书公钥验签
+///
+/// 模拟服务端不校验设备证书链，真实网关须先对照其信任锚校验证书链。
+fn check_attestation(body: &Value, subject: &str, p1: &[u8]) -> Result<(), (i32, String)> {
+    let attestation = &body["attestation"];
+    if attestation.is_null() {
+        return Ok(());
+    }
+    let statement = field_bytes(attestation, "statement")?;
+    let parsed = AttestationStatement::from_bytes(&statement).map_err(|e| invalid(e.to_string()))?;
+    if parsed.subject != subject || parsed.p1 != p1 {
+        return Err(invalid("attestation statement does not match the request"));
+    }
+    let certificates = attestation["certificates"]
+        .as_array()
+        .map(|certs| certs.iter().filter_map(Value::as_str).map(base64_decode).collect::<Result<Vec<_>, _>>())
+        .transpose()
+        .map_err(|e| invalid(format!("invalid field certificates: {}", e)))?
+        .unwrap_or_default();
+    let evidence = DeviceEvidence {
+        format: field_str(attestation, "format")?.to_string(),
+        signature: field_bytes(attestation, "signature")?,
+        certificates,
+    };
+    let device_key = evidence.leaf_public_key().map_err(|e| invalid(e.to_string()))?;
+    match attestation::verify_sm2_evidence(&statement, &evidence, &device_key) {
+        Ok(true) => {
+            tracing::info!("Device attestation verified for {} (protected: {})", subject, parsed.protected);
+            Ok(())
+        }
+        Ok(false) => Err(invalid("attestation signature does not verify")),
+        Err(e) => Err(invalid(e.to_string())),
+    }
+}
+
+/// 读取请求中可选的 `purpose` / `generation`
+fn key_scope(body: &Value) -> Result<KeyScope, (i32, String)> {
+    if body["purpose"].is_null() {
+        return Ok(None);
+    }
+    Ok(Some((field_str(body, "purpose")?.to_string(), field_u32(body, "generation")?)))
+}
+
+impl Default for MockServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        Self {
+            simulator: D2Simulator::new(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 监听地址并处理请求，直到进程退出
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream).await {
+                    tracing::warn!("Mock server connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// 处理单个连接（每个连接一个请求，响应后关闭）
+    async fn handle_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let (status, body) = match http::read_request(&mut reader).await {
+            Ok(request) => {
+                tracing::info!("{} {}", request.method, request.path);
+                self.route(&request)
+            }
+            Err(e) => (400, json!({ "code": CODE_INVALID_PARAM, "message": e.to_string(), "data": null })),
+        };
+        http::write_response(reader.get_mut(), status, &[], Some(&body)).await
+    }
+
+    /// 路由请求，返回 (HTTP 状态码, 响应体)
+    fn route(&self, request: &Request) -> (u16, Value) {
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/mapi/health") => return (200, json!({ "status": "ok" })),
+            ("POST", "/api/register") => self.register(&request.body),
+            ("POST", "/api/login") => self.login(&request.body),
+            ("POST", "/api/logout") => self.logout(request),
+            ("POST", "/api/key/init") => self.key_init(request),
+            ("POST", "/api/key/refresh") => self.key_refresh(request),
+            ("POST", "/api/key/derive") => self.key_derive(request),
+            ("POST", "/api/sign") => self.sign(request),
+            ("POST", "/api/sign/commit") => self.sign_commit(request),
+            ("POST", "/api/sign/reveal") => self.sign_reveal(request),
+            ("POST", "/api/decrypt") => self.decrypt(request),
+            ("GET", "/api/user/info") => self.user_info(request),
+            ("POST", "/api/user/lookup") => self.user_lookup(request),
+            ("GET", "/api/user/cert") => Err((CODE_NOT_FOUND, "certificate not issued by mock server".to_string())),
+            _ => return (404, json!({ "code": CODE_NOT_FOUND, "message": "not found", "data": null })),
+        };
+
+        let body = match result {
+            Ok(data) => json!({ "code": 0, "message": "success", "data": data }),
+            Err((code, message)) => json!({ "code": code, "message": message, "data": null }),
+        };
+        (200, body)
+    }
+
+    /// 校验 Bearer Token，返回 user_id
+    fn authenticate(&self, request: &Request) -> Result<String, (i32, String)> {
+        let token = request.bearer_token().ok_or((CODE_UNAUTHORIZED, "missing token".to_string()))?;
+        self.state()
+            .tokens
+            .get(token)
+            .cloned()
+            .ok_or((CODE_UNAUTHORIZED, "invalid token".to_string()))
+    }
+
+    fn register(&self, body: &Value) -> ApiResult {
+        let username = field_str(body, "username")?;
+        let password = field_str(body, "password")?;
+        let p1 = field_bytes(body, "p1")?;
+        check_attestation(body, username, &p1)?;
+
+        let mut state = self.state();
+        if state.users.values().any(|u| u.username == username) {
+            return Err((CODE_USER_EXISTS, "user already exists".to_string()));
+        }
+        let key = self.simulator.generate_key(&p1).map_err(|e| invalid(e.to_string()))?;
+
+        let id = hex_encode(&CoSignProtocol::generate_random(8));
+        state.users.insert(
+            id.clone(),
+            User {
+                id: id.clone(),
+                username: username.to_string(),
+                password: password.to_string(),
+                p1,
+                d2: key.d2,
+                public_key: key.public_key.clone(),
+                sub_keys: HashMap::new(),
+                created_at: now_secs(),
+            },
+        );
+
+        Ok(json!({
+            "userId": id,
+            "p2": base64_encode(&key.p2),
+            "publicKey": base64_encode(&key.public_key),
+        }))
+    }
+
+    fn login(&self, body: &Value) -> ApiResult {
+        let username = field_str(body, "username")?;
+        let password = field_str(body, "password")?;
+
+        let mut state = self.state();
+        let user_id = state
+            .users
+            .values()
+            .find(|u| u.username == username && u.password == password)
+            .map(|u| u.id.clone())
+            .ok_or((CODE_BAD_CREDENTIALS, "invalid username or password".to_string()))?;
+
+        let token = hex_encode(&CoSignProtocol::generate_random(32));
+        state.tokens.insert(token.clone(), user_id.clone());
+
+        Ok(json!({
+            "token": token,
+            "userId": user_id,
+            "expiresAt": (now_secs() + TOKEN_TTL_SECS).to_string(),
+        }))
+    }
+
+    fn logout(&self, request: &Request) -> ApiResult {
+        self.authenticate(request)?;
+        if let Some(token) = request.bearer_token() {
+            self.state().tokens.remove(token);
+        }
+        Ok(Value::Null)
+    }
+
+    fn key_init(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let p1 = field_bytes(&request.body, "p1")?;
+        check_attestation(&request.body, &user_id, &p1)?;
+        let key = self.simulator.generate_key(&p1).map_err(|e| invalid(e.to_string()))?;
+
+        let mut state = self.state();
+        let user = state.users.get_mut(&user_id).ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+        user.p1 = p1;
+        user.d2 = key.d2;
+        user.public_key = key.public_key.clone();
+        // 新的主密钥不再能派生原有子密钥
+        user.sub_keys.clear();
+
+        Ok(json!({
+            "p2": base64_encode(&key.p2),
+            "publicKey": base64_encode(&key.public_key),
+        }))
+    }
+
+    fn key_refresh(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let factor = field_bytes(&request.body, "factor")?;
+        let p1 = field_bytes(&request.body, "p1")?;
+
+        let mut state = self.state();
+        let user = state.users.get_mut(&user_id).ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+        let key = self
+            .simulator
+            .refresh_key(&user.d2, &p1, &factor)
+            .map_err(|e| invalid(e.to_string()))?;
+        // Reason: P1 与刷新因子不匹配时保留原 D2，避免客户端与服务端分量错位
+        if key.public_key != user.public_key {
+            return Err(invalid("refreshed key does not match public key"));
+        }
+
+        // 子分量 D1_p = D1·τ 随主分量同乘 t，各子密钥的 D2 同步刷新，全部成功后才替换
+        let mut sub_d2 = Vec::with_capacity(user.sub_keys.len());
+        for (purpose, sub_key) in &user.sub_keys {
+            let sub_p1 = subkey::derive_sub_p1(&p1, purpose, sub_key.generation).map_err(|e| invalid(e.to_string()))?;
+            let refreshed = self
+                .simulator
+                .refresh_key(&sub_key.d2, &sub_p1, &factor)
+                .map_err(|e| invalid(e.to_string()))?;
+            if refreshed.public_key != sub_key.public_key {
+                return Err(invalid(format!("refreshed sub-key {} does not match public key", purpose)));
+            }
+            sub_d2.push((purpose.clone(), refreshed.d2));
+        }
+        for (purpose, d2) in sub_d2 {
+            if let Some(sub_key) = user.sub_keys.get_mut(&purpose) {
+                sub_key.d2 = d2;
+            }
+        }
+        user.p1 = p1;
+        user.d2 = key.d2;
+
+        Ok(json!({
+            "p2": base64_encode(&key.p2),
+            "publicKey": base64_encode(&key.public_key),
+        }))
+    }
+
+    /// 创建（代次 0）或轮换（代次加一）子密钥
+    fn key_derive(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let purpose = field_str(&request.body, "purpose")?;
+        let generation = field_u32(&request.body, "generation")?;
+        let sub_p1 = field_bytes(&request.body, "p1")?;
+
+        let mut state = self.state();
+        let user = state.users.get_mut(&user_id).ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+        let expected = user.sub_keys.get(purpose).map_or(0, |sub_key| sub_key.generation.saturating_add(1));
+        if generation != expected {
+            return Err(invalid(format!("sub-key {} expects generation {}", purpose, expected)));
+        }
+        let key = self
+            .simulator
+            .derive_sub_key(&user.p1, purpose, generation, &sub_p1)
+            .map_err(|e| invalid(e.to_string()))?;
+        user.sub_keys.insert(
+            purpose.to_string(),
+            SubKey {
+                generation,
+                d2: key.d2,
+                public_key: key.public_key.clone(),
+            },
+        );
+
+        Ok(json!({
+            "p2": base64_encode(&key.p2),
+            "publicKey": base64_encode(&key.public_key),
+        }))
+    }
+
+    /// 读取已认证用户主密钥或指定子密钥的 D2，子密钥代次须与当前一致
+    fn user_d2(&self, user_id: &str, scope: &KeyScope) -> Result<Vec<u8>, (i32, String)> {
+        let state = self.state();
+        let user = state.users.get(user_id).ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+        match scope {
+            None => Ok(user.d2.clone()),
+            Some((purpose, generation)) => user
+                .sub_keys
+                .get(purpose)
+                .filter(|sub_key| sub_key.generation == *generation)
+                .map(|sub_key| sub_key.d2.clone())
+                .ok_or((CODE_NOT_FOUND, format!("sub-key {} generation {} not found", purpose, generation))),
+        }
+    }
+
+    fn sign(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let q1 = field_bytes(&request.body, "q1")?;
+        let e = field_bytes(&request.body, "e")?;
+
+        let response = self
+            .simulator
+            .sign(&self.user_d2(&user_id, &key_scope(&request.body)?)?, &q1, &e)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(json!({
+            "r": base64_encode(&response.r),
+            "s2": base64_encode(&response.s2),
+            "s3": base64_encode(&response.s3),
+        }))
+    }
+
+    /// 随机数承诺签名第一轮：记录客户端承诺，返回服务端随机数承诺
+    fn sign_commit(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let e = field_bytes(&request.body, "e")?;
+        let commitment = field_bytes(&request.body, "commitment")?;
+        let scope = key_scope(&request.body)?;
+        let nonce = self.simulator.commit_nonce().map_err(|e| invalid(e.to_string()))?;
+        let server_commitment = nonce.commitment();
+
+        let session_id = hex_encode(&CoSignProtocol::generate_random(16));
+        self.state().pending_signs.insert(
+            session_id.clone(),
+            PendingSign {
+                user_id,
+                scope,
+                e,
+                commitment,
+                nonce,
+            },
+        );
+
+        Ok(json!({
+            "sessionId": session_id,
+            "commitment": base64_encode(&server_commitment),
+        }))
+    }
+
+    /// 随机数承诺签名第二轮：校验 Q1 与承诺一致后签名，并揭示 K3、Q2
+    fn sign_reveal(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let session_id = field_str(&request.body, "session_id")?;
+        let q1 = field_bytes(&request.body, "q1")?;
+
+        // Reason: 会话无论成功与否只能使用一次，同一组 k2、k3 不会用于两次签名
+        let pending = self
+            .state()
+            .pending_signs
+            .remove(session_id)
+            .filter(|pending| pending.user_id == user_id)
+            .ok_or((CODE_NOT_FOUND, "sign session not found".to_string()))?;
+        let (k3g, q2) = (pending.nonce.k3g().to_vec(), pending.nonce.q2().to_vec());
+        let d2 = self.user_d2(&user_id, &pending.scope)?;
+        let response = self
+            .simulator
+            .sign_committed(&d2, pending.nonce, &pending.commitment, &q1, &pending.e)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(json!({
+            "r": base64_encode(&response.r),
+            "s2": base64_encode(&response.s2),
+            "s3": base64_encode(&response.s3),
+            "k3g": base64_encode(&k3g),
+            "q2": base64_encode(&q2),
+        }))
+    }
+
+    fn decrypt(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let t1 = field_bytes(&request.body, "t1")?;
+
+        let t2 = self
+            .simulator
+            .decrypt(&self.user_d2(&user_id, &key_scope(&request.body)?)?, &t1)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(json!({ "t2": base64_encode(&t2) }))
+    }
+
+    fn user_info(&self, request: &Request) -> ApiResult {
+        let user_id = self.authenticate(request)?;
+        let state = self.state();
+        let user = state.users.get(&user_id).ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+
+        Ok(json!({
+            "id": user.id,
+            "username": user.username,
+            "publicKey": base64_encode(&user.public_key),
+            "status": 1,
+            "createdAt": user.created_at.to_string(),
+        }))
+    }
+
+    /// 按用户名查询其他用户的协同公钥（加密给对方使用），模拟服务端不签发证书
+    fn user_lookup(&self, request: &Request) -> ApiResult {
+        self.authenticate(request)?;
+        let username = field_str(&request.body, "username")?;
+        let state = self.state();
+        let user = state
+            .users
+            .values()
+            .find(|u| u.username == username)
+            .ok_or((CODE_NOT_FOUND, "user not found".to_string()))?;
+
+        Ok(json!({
+            "username": user.username,
+            "publicKey": base64_encode(&user.public_key),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, token: Option<&str>, body: Value) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: token
+                .map(|token| vec![("Authorization".to_string(), format!("Bearer {}", token))])
+                .unwrap_or_default(),
+            body,
+        }
+    }
+
+    #[test]
+    fn test_register_login_sign() {
+        let server = MockServer::new();
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = base64_encode(&protocol.calculate_p1(&d1).unwrap());
+
+        let body = json!({ "username": "alice", "password": "pw", "p1": p1 });
+        let (_, registered) = server.route(&request("POST", "/api/register", None, body.clone()));
+        assert_eq!(registered["code"], 0);
+        let (_, duplicate) = server.route(&request("POST", "/api/register", None, body));
+        assert_eq!(duplicate["code"], CODE_USER_EXISTS);
+
+        let (_, login) = server.route(&request(
+            "POST",
+            "/api/login",
+            None,
+            json!({ "username": "alice", "password": "pw" }),
+        ));
+        let token = login["data"]["token"].as_str().unwrap();
+
+        let session = protocol.sign_prepare().unwrap();
+        let sign_body = json!({ "q1": base64_encode(session.q1()), "e": base64_encode(&[0x11; 32]) });
+        let (_, signed) = server.route(&request("POST", "/api/sign", Some(token), sign_body.clone()));
+        assert_eq!(signed["code"], 0);
+        assert!(signed["data"]["s3"].is_string());
+
+        let lookup = |username: &str| request("POST", "/api/user/lookup", Some(token), json!({ "username": username }));
+        let (_, found) = server.route(&lookup("alice"));
+        assert_eq!(found["data"]["publicKey"], registered["data"]["publicKey"]);
+        let (_, missing) = server.route(&lookup("nobody"));
+        assert_eq!(missing["code"], CODE_NOT_FOUND);
+
+        let (_, denied) = server.route(&request("POST", "/api/sign", Some("bogus"), sign_body));
+        assert_eq!(denied["code"], CODE_UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_committed_sign() {
+        let server = MockServer::new();
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = base64_encode(&protocol.calculate_p1(&d1).unwrap());
+        let (_, registered) = server.route(&request(
+            "POST",
+            "/api/register",
+            None,
+            json!({ "username": "carol", "password": "pw", "p1": p1 }),
+        ));
+        let public_key = base64_decode(registered["data"]["publicKey"].as_str().unwrap()).unwrap();
+        let (_, login) = server.route(&request(
+            "POST",
+            "/api/login",
+            None,
+            json!({ "username": "carol", "password": "pw" }),
+        ));
+        let token = login["data"]["token"].as_str().unwrap();
+
+        let e = [0x11; 32];
+        let session = protocol.sign_prepare().unwrap();
+        let commit = json!({ "e": base64_encode(&e), "commitment": base64_encode(&session.commitment()) });
+        let (_, committed) = server.route(&request("POST", "/api/sign/commit", Some(token), commit));
+        let session_id = committed["data"]["sessionId"].as_str().unwrap();
+        let server_commitment = base64_decode(committed["data"]["commitment"].as_str().unwrap()).unwrap();
+
+        let reveal = json!({ "session_id": session_id, "q1": base64_encode(session.q1()) });
+        let (_, revealed) = server.route(&request("POST", "/api/sign/reveal", Some(token), reveal.clone()));
+        let field = |name: &str| base64_decode(revealed["data"][name].as_str().unwrap()).unwrap();
+        session
+            .verify_server_nonce(&e, &server_commitment, &field("k3g"), &field("q2"), &field("r"))
+            .unwrap();
+        let signature = session.complete(&protocol, &d1, &field("r"), &field("s2"), &field("s3")).unwrap();
+        assert!(protocol.verify_digest(&public_key, &e, &signature.r, &signature.s).unwrap());
+
+        // 会话只能揭示一次
+        let (_, replayed) = server.route(&request("POST", "/api/sign/reveal", Some(token), reveal));
+        assert_eq!(replayed["code"], CODE_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_key_refresh() {
+        let server = MockServer::new();
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = base64_encode(&protocol.calculate_p1(&d1).unwrap());
+        server.route(&request("POST", "/api/register", None, json!({ "username": "bob", "password": "pw", "p1": p1 })));
+        let (_, login) = server.route(&request(
+            "POST",
+            "/api/login",
+            None,
+            json!({ "username": "bob", "password": "pw" }),
+        ));
+        let token = login["data"]["token"].as_str().unwrap();
+
+        // 因子与新 P1 不匹配时拒绝
+        let factor = protocol.generate_d1().unwrap();
+        let other = protocol.generate_d1().unwrap();
+        let bad_p1 = protocol.calculate_p1(&protocol.refresh_d1(&d1, &other).unwrap()).unwrap();
+        let bad = json!({ "factor": base64_encode(&factor), "p1": base64_encode(&bad_p1) });
+        let (_, rejected) = server.route(&request("POST", "/api/key/refresh", Some(token), bad));
+        assert_eq!(rejected["code"], CODE_INVALID_PARAM);
+
+        let new_p1 = protocol.calculate_p1(&protocol.refresh_d1(&d1, &factor).unwrap()).unwrap();
+        let body = json!({ "factor": base64_encode(&factor), "p1": base64_encode(&new_p1) });
+        let (_, refreshed) = server.route(&request("POST", "/api/key/refresh", Some(token), body));
+        assert_eq!(refreshed["code"], 0);
+    }
+
+    #[test]
+    fn test_sub_key_derive_and_refresh() {
+        let server = MockServer::new();
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        server.route(&request(
+            "POST",
+            "/api/register",
+            None,
+            json!({ "username": "dave", "password": "pw", "p1": base64_encode(&p1) }),
+        ));
+        let (_, login) = server.route(&request(
+            "POST",
+            "/api/login",
+            None,
+            json!({ "username": "dave", "password": "pw" }),
+        ));
+        let token = login["data"]["token"].as_str().unwrap();
+
+        let derive = |d1: &[u8], generation: u32| {
+            let sub_d1 = subkey::derive_sub_d1(d1, subkey::PURPOSE_SIGN, generation).unwrap();
+            let sub_p1 = protocol.calculate_p1(&sub_d1).unwrap();
+            let body = json!({ "purpose": "sign", "generation": generation, "p1": base64_encode(&sub_p1) });
+            server.route(&request("POST", "/api/key/derive", Some(token), body)).1
+        };
+        // 只能从代次 0 开始，逐次加一
+        assert_eq!(derive(&d1, 1)["code"], CODE_INVALID_PARAM);
+        let derived = derive(&d1, 0);
+        assert_eq!(derived["code"], 0);
+        let public_key = base64_decode(derived["data"]["publicKey"].as_str().unwrap()).unwrap();
+
+        // 主密钥刷新后子密钥仍可用，公钥不变
+        let factor = protocol.generate_d1().unwrap();
+        let new_d1 = protocol.refresh_d1(&d1, &factor).unwrap();
+        let body = json!({
+            "factor": base64_encode(&factor),
+            "p1": base64_encode(&protocol.calculate_p1(&new_d1).unwrap()),
+        });
+        let (_, refreshed) = server.route(&request("POST", "/api/key/refresh", Some(token), body));
+        assert_eq!(refreshed["code"], 0);
+
+        let e = [0x22; 32];
+        let session = protocol.sign_prepare().unwrap();
+        let body = json!({
+            "q1": base64_encode(session.q1()),
+            "e": base64_encode(&e),
+            "purpose": "sign",
+            "generation": 0,
+        });
+        let (_, signed) = server.route(&request("POST", "/api/sign", Some(token), body.clone()));
+        let field = |name: &str| base64_decode(signed["data"][name].as_str().unwrap()).unwrap();
+        let sub_d1 = subkey::derive_sub_d1(&new_d1, subkey::PURPOSE_SIGN, 0).unwrap();
+        let signature = session.complete(&protocol, &sub_d1, &field("r"), &field("s2"), &field("s3")).unwrap();
+        assert!(protocol.verify_digest(&public_key, &e, &signature.r, &signature.s).unwrap());
+
+        // 轮换后旧代次失效
+        assert_eq!(derive(&new_d1, 1)["code"], 0);
+        let (_, stale) = server.route(&request("POST", "/api/sign", Some(token), body));
+        assert_eq!(stale["code"], CODE_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_register_rejects_mismatched_attestation() {
+        let server = MockServer::new();
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let other = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+
+        // 证明声明绑定的是另一个 P1
+        let statement = AttestationStatement::new("erin", &other, false).unwrap();
+        let evidence = DeviceEvidence {
+            format: attestation::FORMAT_SM2.to_string(),
+            signature: vec![0; 64],
+            certificates: Vec::new(),
+        };
+        let body = json!({
+            "username": "erin",
+            "password": "pw",
+            "p1": base64_encode(&p1),
+            "attestation": evidence.to_json(&statement),
+        });
+        let (_, rejected) = server.route(&request("POST", "/api/register", None, body));
+        assert_eq!(rejected["code"], CODE_INVALID_PARAM);
+    }
+
+    #[test]
+    fn test_unknown_route() {
+        let (status, _) = MockServer::new().route(&request("GET", "/nope", None, Value::Null));
+        assert_eq!(status, 404);
+    }
+}
@@ -0,0 +1,195 @@
+//! 命令输出
+//!
+//! 默认输出面向人的提示信息；`--json` 模式下屏蔽提示信息，每条命令输出一个 JSON 对象：
+//! - 成功（stdout）：`{"ok": true, "data": {...}}`
+//! - 失败（stderr）：`{"ok": false, "error": {"kind": "...", "code": ..., "exit_code": ..., "message": "..."}}`
+//!
+//! 进程退出码按错误分类区分，见 [`exit_code`]。
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::{json, Value};
+use sm2_co_sign_core::Error;
+
+/// 进程退出码
+pub mod exit_code {
+    /// 其他错误
+    pub const FAILURE: i32 = 1;
+    /// 命令行用法错误（与 clap 一致）
+    pub const USAGE: i32 = 2;
+    /// 未登录或认证失败
+    pub const AUTH: i32 = 3;
+    /// 网络错误
+    pub const NETWORK: i32 = 4;
+    /// 密码运算错误
+    pub const CRYPTO: i32 = 5;
+    /// 验签、证书校验、自检等校验未通过
+    pub const VERIFICATION: i32 = 6;
+}
+
+/// 命令行用法错误（参数组合无效等）
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// 服务端认证相关的业务错误码 / HTTP 状态码
+const AUTH_API_CODES: [i32; 2] = [401, 403];
+
+/// 输出模式
+pub struct Output {
+    json: bool,
+    /// 命令结果写到 stdout 时，提示信息改写到 stderr，避免混入数据
+    info_to_stderr: bool,
+    /// 不显示进度条
+    quiet: bool,
+}
+
+impl Output {
+    pub fn new(json: bool, info_to_stderr: bool) -> Self {
+        Self { json, info_to_stderr, quiet: false }
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// 输出提示信息（仅文本模式）
+    pub fn info(&self, msg: impl std::fmt::Display) {
+        if self.json {
+            return;
+        }
+        if self.info_to_stderr {
+            eprintln!("{}", msg);
+        } else {
+            println!("{}", msg);
+        }
+    }
+
+    /// 输出警告信息到 stderr（仅文本模式）
+    pub fn warn(&self, msg: impl std::fmt::Display) {
+        if !self.json {
+            eprintln!("{}", msg);
+        }
+    }
+
+    /// 输出命令结果（仅 JSON 模式）
+    pub fn data(&self, data: Value) {
+        if self.json {
+            println!("{}", json!({ "ok": true, "data": data }));
+        }
+    }
+
+    /// 创建按字节计数的进度条（total 未知时显示为计数器）
+    ///
+    /// 进度条写到 stderr；`--json`、`--quiet` 或 stderr 不是终端时不显示。
+    pub fn progress(&self, total: Option<u64>, message: &'static str) -> ProgressBar {
+        if self.json || self.quiet {
+            return ProgressBar::hidden();
+        }
+        let (bar, template) = match total {
+            Some(total) => (
+                ProgressBar::new(total),
+                "{msg} [{bar:40}] {bytes}/{total_bytes} {binary_bytes_per_sec} 剩余 {eta}",
+            ),
+            None => (ProgressBar::new_spinner(), "{msg} {spinner} {bytes} {binary_bytes_per_sec}"),
+        };
+        let style = ProgressStyle::with_template(template).expect("valid progress template");
+        bar.with_style(style.progress_chars("=> ")).with_message(message)
+    }
+
+    /// 输出错误到 stderr，返回对应的退出码
+    pub fn error(&self, err: &anyhow::Error) -> i32 {
+        if self.json {
+            eprintln!("{}", error_json(err));
+        } else {
+            eprintln!("Error: {:?}", err);
+        }
+        exit_code(err)
+    }
+}
+
+/// 错误对应的进程退出码
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match error_kind(err) {
+        ("usage", _) => exit_code::USAGE,
+        ("not_authenticated", _) => exit_code::AUTH,
+        ("api" | "http", Some(code)) if AUTH_API_CODES.contains(&code) => exit_code::AUTH,
+        ("network" | "http", _) => exit_code::NETWORK,
+        ("crypto", _) | ("invalid_point", _) => exit_code::CRYPTO,
+        _ => exit_code::FAILURE,
+    }
+}
+
+/// 错误分类与错误码（api 类错误携带服务端业务错误码，http 类错误携带 HTTP 状态码）
+fn error_kind(err: &anyhow::Error) -> (&'static str, Option<i32>) {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Api { code, .. }) => ("api", Some(*code)),
+        Some(Error::Http { status, .. }) => ("http", Some(i32::from(*status))),
+        Some(e) => (e.kind().as_str(), None),
+        None if err.downcast_ref::<UsageError>().is_some() => ("usage", None),
+        None if err.downcast_ref::<std::io::Error>().is_some() => ("io", None),
+        None => ("error", None),
+    }
+}
+
+/// 错误的 JSON 表示（`--json` 模式与 `serve` 共用）
+pub fn error_json(err: &anyhow::Error) -> Value {
+    let (kind, code) = error_kind(err);
+    json!({
+        "ok": false,
+        "error": {
+            "kind": kind,
+            "code": code,
+            "exit_code": exit_code(err),
+            "retryable": err.downcast_ref::<Error>().is_some_and(Error::is_retryable),
+            "message": format!("{:#}", err),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json() {
+        let err = anyhow::Error::new(Error::Api { code: 1001, message: "user exists".to_string() });
+        let value = error_json(&err);
+        assert_eq!(value["ok"], false);
+        assert_eq!(value["error"]["kind"], "api");
+        assert_eq!(value["error"]["code"], 1001);
+        assert_eq!(value["error"]["exit_code"], exit_code::FAILURE);
+        assert_eq!(value["error"]["retryable"], false);
+        assert_eq!(error_json(&Error::Network("timeout".to_string()).into())["error"]["retryable"], true);
+
+        let value = error_json(&Error::http("https://cosign.example.com/api/sign", 503, "busy").into());
+        assert_eq!(value["error"]["kind"], "http");
+        assert_eq!(value["error"]["code"], 503);
+        assert_eq!(value["error"]["retryable"], true);
+
+        let value = error_json(&anyhow::anyhow!("plain"));
+        assert_eq!(value["error"]["kind"], "error");
+        assert!(value["error"]["code"].is_null());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let auth = anyhow::Error::new(Error::NotAuthenticated).context("请先登录");
+        assert_eq!(exit_code(&auth), exit_code::AUTH);
+        let api = anyhow::Error::new(Error::Api { code: 401, message: "invalid token".to_string() });
+        assert_eq!(exit_code(&api), exit_code::AUTH);
+        assert_eq!(exit_code(&Error::Network("timeout".to_string()).into()), exit_code::NETWORK);
+        assert_eq!(exit_code(&Error::http("/api/sign", 401, "").into()), exit_code::AUTH);
+        assert_eq!(exit_code(&Error::http("/api/sign", 502, "").into()), exit_code::NETWORK);
+        assert_eq!(exit_code(&Error::Crypto("bad".to_string()).into()), exit_code::CRYPTO);
+        assert_eq!(exit_code(&UsageError("bad".to_string()).into()), exit_code::USAGE);
+        assert_eq!(exit_code(&anyhow::anyhow!("plain")), exit_code::FAILURE);
+    }
+}
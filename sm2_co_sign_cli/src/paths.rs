@@ -0,0 +1,169 @@
+//! 本地状态文件路径
+//!
+//! 注册、登录等命令产生的 D1、Token、用户 ID、公钥、证书等文件统一存放在密钥目录下。
+
+use std::path::{Path, PathBuf};
+
+/// 默认密钥目录
+///
+/// 位于用户数据目录下（Linux 为 `~/.local/share/sm2-co-sign`），
+/// 无法确定用户数据目录时退回当前目录。
+pub fn default_key_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("sm2-co-sign"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// 未配置 key_dir 的命名 profile 使用的密钥目录
+///
+/// 位于默认密钥目录下的 `profiles/<name>`，使不同 profile 的 Token、D1、公钥互不干扰。
+pub fn profile_dir(name: &str) -> PathBuf {
+    default_key_dir().join("profiles").join(name)
+}
+
+/// Token 文件对应的过期时间文件
+///
+/// 与 Token 文件位于同一目录，文件名追加 `_expires_at`（默认为 `.token_expires_at`）。
+pub fn token_expires_at(token_file: &Path) -> PathBuf {
+    let mut name = token_file.file_name().unwrap_or_default().to_owned();
+    name.push("_expires_at");
+    token_file.with_file_name(name)
+}
+
+/// 写入只允许当前用户读写的状态文件（Unix 下文件权限为 0600，已存在的文件同样收紧）
+///
+/// Token、用户 ID 等登录状态与密钥库使用同一权限，避免按 umask 创建为其他用户可读。
+pub fn write_private(path: &Path, data: impl AsRef<[u8]>) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+        // Reason: mode 只对新建文件生效，旧版本按 umask 创建的文件需显式收紧
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(data.as_ref())
+    }
+    #[cfg(not(unix))]
+    std::fs::write(path, data)
+}
+
+/// 密钥目录下的状态文件
+pub struct StatePaths {
+    dir: PathBuf,
+}
+
+impl StatePaths {
+    /// 以指定目录作为密钥目录
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// 密钥目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 确保密钥目录存在（Unix 下新建目录的权限为 0700）
+    pub fn ensure_dir(&self) -> std::io::Result<()> {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(&self.dir)
+    }
+
+    /// 私钥分量 D1
+    pub fn d1(&self) -> PathBuf {
+        self.dir.join(".d1")
+    }
+
+    /// 登录 Token
+    pub fn token(&self) -> PathBuf {
+        self.dir.join(".token")
+    }
+
+    /// 用户 ID
+    pub fn user_id(&self) -> PathBuf {
+        self.dir.join(".user_id")
+    }
+
+    /// 最近登录的用户名（自动重新登录使用）
+    pub fn username(&self) -> PathBuf {
+        self.dir.join(".username")
+    }
+
+    /// `login --remember` 保存的登录密码（密钥库格式加密）
+    pub fn credentials(&self) -> PathBuf {
+        self.dir.join(".credentials")
+    }
+
+    /// 协同公钥
+    pub fn public_key(&self) -> PathBuf {
+        self.dir.join(".public_key")
+    }
+
+    /// 本地（非协同）SM2 私钥
+    pub fn local_key(&self) -> PathBuf {
+        self.dir.join(".local_key")
+    }
+
+    /// 本地（非协同）SM2 公钥
+    pub fn local_public_key(&self) -> PathBuf {
+        self.dir.join(".local_public_key")
+    }
+
+    /// 签名代理套接字
+    pub fn agent_socket(&self) -> PathBuf {
+        self.dir.join("agent.sock")
+    }
+
+    /// 本地 HTTP 服务的 API Token
+    pub fn serve_token(&self) -> PathBuf {
+        self.dir.join(".serve_token")
+    }
+
+    /// 用户证书（PEM）
+    pub fn certificate(&self) -> PathBuf {
+        self.dir.join(".certificate")
+    }
+
+    /// 当前 D1 的生命周期记录（创建时间与使用次数）
+    pub fn key_lifetime(&self) -> PathBuf {
+        self.dir.join(".key_lifetime")
+    }
+
+    /// 操作回执（逐行 JSON，只追加）
+    pub fn receipts(&self) -> PathBuf {
+        self.dir.join(".receipts")
+    }
+
+    /// 已固定公钥的加密接收方（encrypt --to）
+    pub fn recipients(&self) -> PathBuf {
+        self.dir.join(".recipients")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_private_permissions() {
+        let paths = StatePaths::new(std::env::temp_dir().join(format!("sm2-cosign-paths-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(paths.dir());
+        paths.ensure_dir().unwrap();
+        assert_eq!(std::fs::metadata(paths.dir()).unwrap().permissions().mode() & 0o777, 0o700);
+
+        // 旧版本按 umask 创建的文件在重写时收紧
+        std::fs::write(paths.token(), "old").unwrap();
+        std::fs::set_permissions(paths.token(), std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&paths.token(), "token").unwrap();
+        assert_eq!(std::fs::metadata(paths.token()).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(paths.token()).unwrap(), "token");
+        std::fs::remove_dir_all(paths.dir()).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+//! 二维码输出
+//!
+//! 面对面核验时，将公钥或签名显示为终端二维码（可同时保存 PNG），供手机验签应用扫描。
+//! 二维码内容为十六进制大写文本（`--out-format base64` / `base64url` 时为对应的 Base64 文本）。
+
+use crate::format::DataFormat;
+use crate::output::Output;
+use clap::Args;
+use qrcode::render::unicode;
+use qrcode::QrCode;
+use std::path::PathBuf;
+
+/// PNG 图片的最小边长（像素）
+const PNG_MIN_SIZE: u32 = 320;
+
+/// 二维码输出参数
+#[derive(Args, Debug)]
+pub struct QrArgs {
+    /// 在终端显示二维码（--json 模式下不显示，可使用 --qr-png）
+    #[arg(long)]
+    pub qr: bool,
+    /// 将二维码保存为 PNG 图片
+    #[arg(long, value_name = "FILE")]
+    pub qr_png: Option<PathBuf>,
+}
+
+impl QrArgs {
+    /// 是否需要生成二维码
+    pub fn enabled(&self) -> bool {
+        self.qr || self.qr_png.is_some()
+    }
+
+    /// 按参数显示或保存二维码，未指定时不做任何事
+    pub fn emit(&self, out: &Output, label: &str, payload: &str) -> anyhow::Result<()> {
+        if !self.enabled() {
+            return Ok(());
+        }
+        let code = QrCode::new(payload.as_bytes()).map_err(|e| anyhow::anyhow!("{}过长，无法生成二维码: {}", label, e))?;
+
+        if self.qr {
+            out.info(format!("{}二维码:\n{}", label, render_terminal(&code)));
+        }
+        if let Some(path) = &self.qr_png {
+            code.render::<image::Luma<u8>>()
+                .min_dimensions(PNG_MIN_SIZE, PNG_MIN_SIZE)
+                .build()
+                .save(path)
+                .map_err(|e| anyhow::anyhow!("保存二维码图片失败 {:?}: {}", path, e))?;
+            out.info(format!("{}二维码已保存到 {:?}", label, path));
+        }
+        Ok(())
+    }
+}
+
+/// 二维码内容：Base64 / Base64URL 输出格式时为对应文本，否则为十六进制大写
+///
+/// Reason: 大写十六进制属于二维码字母数字模式，同样内容比小写（字节模式）所需版本更小、更易扫描
+pub fn payload(format: Option<DataFormat>, data: &[u8]) -> String {
+    match format {
+        Some(format @ (DataFormat::Base64 | DataFormat::Base64url)) => String::from_utf8_lossy(&format.encode(data)).into_owned(),
+        _ => hex::encode_upper(data),
+    }
+}
+
+/// 渲染为终端字符画（每个字符表示上下两个模块）
+fn render_terminal(code: &QrCode) -> String {
+    // Reason: 终端多为深色背景，反转颜色使暗模块显示为亮字符，保持扫描所需的深浅对比
+    code.render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        assert_eq!(payload(None, &[0xab, 0x01]), "AB01");
+        assert_eq!(payload(Some(DataFormat::Hex), &[0xab, 0x01]), "AB01");
+        assert_eq!(payload(Some(DataFormat::Base64), &[0xab, 0x01]), "qwE=");
+        assert_eq!(payload(Some(DataFormat::Base64url), &[0xab, 0x01]), "qwE");
+    }
+
+    #[test]
+    fn test_render_terminal() {
+        let code = QrCode::new(payload(None, &[0x42u8; 64]).as_bytes()).unwrap();
+        let rendered = render_terminal(&code);
+        // 含静区，行数为 (模块数 + 8) / 2 向上取整
+        assert_eq!(rendered.lines().count(), (code.width() + 8).div_ceil(2));
+    }
+}
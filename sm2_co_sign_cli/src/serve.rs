@@ -0,0 +1,243 @@
+//! 本地 HTTP 签名服务
+//!
+//! 为同一台机器上的浏览器扩展与旧系统提供最小 REST 接口，D1 只保存在本进程内。
+//! 除 `GET /health` 与 CORS 预检外，请求须携带 `Authorization: Bearer <API Token>`；
+//! 二进制字段均为 Base64，响应格式与 CLI `--json` 输出一致（`{"ok": true, "data": ...}`）。
+//!
+//! | 方法 | 路径 | 请求体 | 响应 data |
+//! |------|------|--------|-----------|
+//! | GET | /health | 无 | `{"status": "ok"}` |
+//! | GET | /public-key | 无 | `{"public_key"}` |
+//! | POST | /sign | `{"message", "hash"?}` | `{"signature", "der"}` |
+//! | POST | /decrypt | `{"ciphertext"}` | `{"plaintext"}` |
+//! | POST | /verify | `{"message", "signature", "public_key"?, "hash"?}` | `{"valid"}` |
+//!
+//! `hash` 取值 `sm3`（默认）、`za` 或 `digest`，含义与 `agent` 相同。
+
+use crate::http::{self, Request};
+use crate::output::{self, exit_code, UsageError};
+use crate::signer::{self, HashMode, Signer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sm2_co_sign_core::asn1;
+use sm2_co_sign_core::protocol::{base64_decode, base64_encode};
+use sm2_co_sign_core::CoSignProtocol;
+use std::sync::Arc;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+
+/// 允许的 Host 头主机名（防御 DNS 重绑定）
+const LOCAL_HOSTS: &[&str] = &["127.0.0.1", "localhost", "[::1]"];
+
+#[derive(Deserialize)]
+struct SignRequest {
+    message: String,
+    #[serde(default)]
+    hash: HashMode,
+}
+
+#[derive(Deserialize)]
+struct DecryptRequest {
+    ciphertext: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+    message: String,
+    signature: String,
+    public_key: Option<String>,
+    #[serde(default)]
+    hash: HashMode,
+}
+
+/// Host 头是否指向本机（忽略端口）
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    LOCAL_HOSTS.iter().any(|local| name.eq_ignore_ascii_case(local))
+}
+
+/// 常量时间比较 API Token
+fn token_matches(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len() && expected.bytes().zip(actual.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn decode_field(name: &str, value: &str) -> anyhow::Result<Vec<u8>> {
+    base64_decode(value).map_err(|e| UsageError(format!("字段 {} 不是有效的 Base64: {}", name, e)).into())
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &Value) -> anyhow::Result<T> {
+    serde_json::from_value(body.clone()).map_err(|e| UsageError(format!("请求体无效: {}", e)).into())
+}
+
+/// 错误对应的 HTTP 状态码
+fn error_status(err: &anyhow::Error) -> u16 {
+    match output::exit_code(err) {
+        exit_code::USAGE => 400,
+        exit_code::NETWORK => 502,
+        _ => 500,
+    }
+}
+
+/// 本地 HTTP 签名服务
+pub struct Server {
+    signer: Signer,
+    api_token: String,
+    /// 允许跨域访问的 Origin（如浏览器扩展的 `chrome-extension://<id>`）
+    allowed_origins: Vec<String>,
+}
+
+impl Server {
+    pub fn new(signer: Signer, api_token: String, allowed_origins: Vec<String>) -> Self {
+        Self { signer, api_token, allowed_origins }
+    }
+
+    /// 接受连接并处理请求，直到出错或被取消
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream).await {
+                    tracing::warn!("Serve connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// 处理单个连接（每个连接一个请求，响应后关闭）
+    async fn handle_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let request = match http::read_request(&mut reader).await {
+            Ok(request) => request,
+            Err(e) => {
+                let body = output::error_json(&anyhow::Error::new(UsageError(e.to_string())));
+                return http::write_response(reader.get_mut(), 400, &[], Some(&body)).await;
+            }
+        };
+        tracing::info!("{} {}", request.method, request.path);
+
+        let mut headers = Vec::new();
+        let (status, body) = self.dispatch(&request, &mut headers).await;
+        http::write_response(reader.get_mut(), status, &headers, body.as_ref()).await
+    }
+
+    /// 校验来源与认证后路由请求，返回 (HTTP 状态码, 响应体)
+    async fn dispatch(&self, request: &Request, headers: &mut Vec<(&'static str, String)>) -> (u16, Option<Value>) {
+        let forbidden = |message: &str| (403, Some(json!({ "ok": false, "error": { "kind": "forbidden", "message": message } })));
+
+        if !request.header("host").is_some_and(is_local_host) {
+            return forbidden("host not allowed");
+        }
+        // Reason: 浏览器跨域请求总会携带 Origin，未显式允许的网页不能借用本机签名服务
+        if let Some(origin) = request.header("origin") {
+            if !self.allowed_origins.iter().any(|allowed| allowed == origin) {
+                return forbidden("origin not allowed");
+            }
+            headers.push(("Access-Control-Allow-Origin", origin.to_string()));
+            headers.push(("Vary", "Origin".to_string()));
+            if request.method == "OPTIONS" {
+                headers.push(("Access-Control-Allow-Methods", "GET, POST, OPTIONS".to_string()));
+                headers.push(("Access-Control-Allow-Headers", "Authorization, Content-Type".to_string()));
+                return (204, None);
+            }
+        }
+
+        if (request.method.as_str(), request.path.as_str()) == ("GET", "/health") {
+            return (200, Some(json!({ "ok": true, "data": { "status": "ok" } })));
+        }
+        if !request.bearer_token().is_some_and(|token| token_matches(&self.api_token, token)) {
+            headers.push(("WWW-Authenticate", "Bearer".to_string()));
+            return (401, Some(json!({ "ok": false, "error": { "kind": "unauthorized", "message": "invalid API token" } })));
+        }
+
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/public-key") => Ok(json!({ "public_key": base64_encode(self.signer.public_key()) })),
+            ("POST", "/sign") => self.sign(&request.body).await,
+            ("POST", "/decrypt") => self.decrypt(&request.body).await,
+            ("POST", "/verify") => self.verify(&request.body),
+            _ => return (404, Some(json!({ "ok": false, "error": { "kind": "not_found", "message": "not found" } }))),
+        };
+        match result {
+            Ok(data) => (200, Some(json!({ "ok": true, "data": data }))),
+            Err(e) => {
+                tracing::warn!("{} {} failed: {:#}", request.method, request.path, e);
+                (error_status(&e), Some(output::error_json(&e)))
+            }
+        }
+    }
+
+    async fn sign(&self, body: &Value) -> anyhow::Result<Value> {
+        let request: SignRequest = parse_body(body)?;
+        let message = decode_field("message", &request.message)?;
+        let signature = self.signer.sign(request.hash, &message).await?;
+        Ok(json!({
+            "signature": base64_encode(&signature),
+            "der": base64_encode(&asn1::signature_to_der(&signature)?),
+        }))
+    }
+
+    async fn decrypt(&self, body: &Value) -> anyhow::Result<Value> {
+        let request: DecryptRequest = parse_body(body)?;
+        let ciphertext = decode_field("ciphertext", &request.ciphertext)?;
+        let plaintext = self.signer.decrypt(&ciphertext).await?;
+        Ok(json!({ "plaintext": base64_encode(&plaintext) }))
+    }
+
+    fn verify(&self, body: &Value) -> anyhow::Result<Value> {
+        let request: VerifyRequest = parse_body(body)?;
+        let message = decode_field("message", &request.message)?;
+        let signature = decode_field("signature", &request.signature)?;
+        let public_key = match &request.public_key {
+            Some(public_key) => decode_field("public_key", public_key)?,
+            None => self.signer.public_key().to_vec(),
+        };
+        // 支持原始 r||s（64 字节）与 DER 编码
+        let signature = if signature.len() == 64 {
+            signature
+        } else {
+            asn1::signature_from_der(&signature).map_err(|_| UsageError("无法识别的签名格式（支持原始 64 字节或 DER）".to_string()))?
+        };
+
+        let e = signer::digest(request.hash, &message, &public_key)?;
+        let valid = CoSignProtocol::new()?.verify_digest(&public_key, &e, &signature[..32], &signature[32..])?;
+        Ok(json!({ "valid": valid }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_host() {
+        assert!(is_local_host("127.0.0.1:7890"));
+        assert!(is_local_host("localhost"));
+        assert!(is_local_host("LOCALHOST:80"));
+        assert!(is_local_host("[::1]:7890"));
+        assert!(!is_local_host("evil.example:7890"));
+        assert!(!is_local_host("127.0.0.1.evil.example"));
+        assert!(!is_local_host(""));
+    }
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("abcd", "abcd"));
+        assert!(!token_matches("abcd", "abce"));
+        assert!(!token_matches("abcd", "abc"));
+    }
+
+    #[test]
+    fn test_parse_sign_request() {
+        let request: SignRequest = parse_body(&json!({ "message": "aGVsbG8=" })).unwrap();
+        assert_eq!(request.hash, HashMode::Sm3);
+        let request: SignRequest = parse_body(&json!({ "message": "aGVsbG8=", "hash": "za" })).unwrap();
+        assert_eq!(request.hash, HashMode::Za);
+
+        let err = parse_body::<SignRequest>(&json!({ "message": "aGVsbG8=", "hash": "md5" })).unwrap_err();
+        assert_eq!(error_status(&err), 400);
+        assert_eq!(error_status(&decode_field("message", "!!").unwrap_err()), 400);
+    }
+}
@@ -0,0 +1,130 @@
+//! 常驻签名服务共用的签名器
+//!
+//! `agent`（Unix 域套接字）与 `serve`（本地 HTTP）共用：持有已设置会话与密钥对的客户端，
+//! 每次签名后用协同公钥验证；Token 失效且有登录凭据时重新登录并重试一次。签名、解密后更新密钥生命周期记录。
+
+use crate::output::{self, exit_code, UsageError};
+use clap::ValueEnum;
+use serde::Deserialize;
+use sm2_co_sign_core::{CoSignClient, CoSignProtocol, DigestMode};
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// 签名前的消息预处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    /// e = SM3(M)（与 `sign` 命令一致）
+    #[default]
+    Sm3,
+    /// e = SM3(ZA || M)（标准 SM3withSM2，默认用户标识）
+    Za,
+    /// 输入即为 32 字节消息哈希 e
+    Digest,
+}
+
+impl From<HashMode> for DigestMode {
+    fn from(mode: HashMode) -> Self {
+        match mode {
+            HashMode::Sm3 => DigestMode::Sm3,
+            HashMode::Za => DigestMode::Za,
+            HashMode::Digest => DigestMode::Prehashed,
+        }
+    }
+}
+
+/// 按预处理方式计算消息哈希 e（`public_key` 为 64 字节 x||y）
+pub fn digest(mode: HashMode, data: &[u8], public_key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if mode == HashMode::Digest && data.len() != 32 {
+        return Err(UsageError("消息哈希须为 32 字节".to_string()).into());
+    }
+    Ok(CoSignProtocol::new()?.calculate_message_hash(data, public_key, mode.into())?)
+}
+
+/// 签名器
+pub struct Signer {
+    client: CoSignClient,
+    public_key: Vec<u8>,
+    /// Token 失效时重新登录使用的 (用户名, 密码)
+    credentials: Option<(String, Zeroizing<String>)>,
+    /// 密钥生命周期记录文件，每次签名、解密后更新
+    lifetime_file: Option<PathBuf>,
+}
+
+impl Signer {
+    /// `client` 需已设置会话与密钥对
+    pub fn new(client: CoSignClient, public_key: Vec<u8>, credentials: Option<(String, Zeroizing<String>)>) -> Self {
+        Self { client, public_key, credentials, lifetime_file: None }
+    }
+
+    /// 每次签名、解密后将密钥生命周期记录写入 `path`
+    pub fn with_lifetime_file(mut self, path: PathBuf) -> Self {
+        self.lifetime_file = Some(path);
+        self
+    }
+
+    /// 协同公钥（64 字节 x||y）
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// 协同签名，返回 64 字节 r||s
+    pub async fn sign(&self, mode: HashMode, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let signature = match self.try_sign(mode, data).await {
+            Err(e) if self.relogin(&e).await? => self.try_sign(mode, data).await,
+            result => result,
+        }?;
+        self.save_lifetime().await;
+        Ok(signature)
+    }
+
+    /// 协同解密
+    pub async fn decrypt(&self, ciphertext: &[u8]) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+        let attempt = || async { Ok::<_, anyhow::Error>(Zeroizing::new(self.client.decrypt(ciphertext).await?)) };
+        let plaintext = match attempt().await {
+            Err(e) if self.relogin(&e).await? => attempt().await,
+            result => result,
+        }?;
+        self.save_lifetime().await;
+        Ok(plaintext)
+    }
+
+    /// 写入生命周期记录；写入失败只记录日志，不影响已完成的签名、解密
+    async fn save_lifetime(&self) {
+        let (Some(path), Some(lifetime)) = (&self.lifetime_file, self.client.key_lifetime().await) else {
+            return;
+        };
+        let result =
+            serde_json::to_vec(&lifetime).map_err(anyhow::Error::new).and_then(|data| Ok(std::fs::write(path, data)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save key lifetime to {:?}: {}", path, e);
+        }
+    }
+
+    async fn try_sign(&self, mode: HashMode, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let e = digest(mode, data, &self.public_key)?;
+        let signature = self.client.sign_digest(&e).await?;
+        if !CoSignProtocol::new()?.verify_digest(&self.public_key, &e, &signature.r, &signature.s)? {
+            anyhow::bail!("协同签名结果验证失败，请检查公钥文件是否与 D1 匹配");
+        }
+        Ok(signature.to_bytes().to_vec())
+    }
+
+    /// 认证失败且有凭据时重新登录，返回是否应重试
+    async fn relogin(&self, err: &anyhow::Error) -> anyhow::Result<bool> {
+        let Some((username, password)) = &self.credentials else {
+            return Ok(false);
+        };
+        if output::exit_code(err) != exit_code::AUTH {
+            return Ok(false);
+        }
+        tracing::info!("Token rejected, logging in again as {}", username);
+        let session = self
+            .client
+            .login(username, password)
+            .await
+            .map_err(|e| anyhow::Error::new(e).context("重新登录失败"))?;
+        crate::logging::add_secret(&session.token);
+        Ok(true)
+    }
+}
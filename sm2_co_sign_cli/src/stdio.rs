@@ -0,0 +1,65 @@
+//! 标准输入输出
+//!
+//! 文件参数为 `-` 时从 stdin 读取或写入 stdout，便于在管道中使用。
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// 表示标准输入输出的路径参数
+pub const STDIO: &str = "-";
+
+/// 路径参数是否表示标准输入输出
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// 读取输入文件，`-` 表示 stdin
+pub fn read_input(path: &Path) -> anyhow::Result<Vec<u8>> {
+    if is_stdio(path) {
+        let mut data = Vec::new();
+        std::io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        std::fs::read(path).map_err(|e| anyhow::anyhow!("读取文件失败 {:?}: {}", path, e))
+    }
+}
+
+/// 输入文件大小，stdin 或无法获取时为 None
+pub fn input_len(path: &Path) -> Option<u64> {
+    if is_stdio(path) {
+        return None;
+    }
+    std::fs::metadata(path).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len())
+}
+
+/// 写入输出文件，`-` 表示 stdout
+pub fn write_output(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    if is_stdio(path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(data)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(path, data)?;
+    }
+    Ok(())
+}
+
+/// 打开输入流，`-` 表示 stdin，用于需要流式处理的大文件
+pub fn open_input(path: &Path) -> anyhow::Result<Box<dyn Read>> {
+    if is_stdio(path) {
+        Ok(Box::new(std::io::stdin().lock()))
+    } else {
+        let file = std::fs::File::open(path).map_err(|e| anyhow::anyhow!("读取文件失败 {:?}: {}", path, e))?;
+        Ok(Box::new(std::io::BufReader::new(file)))
+    }
+}
+
+/// 创建输出流，`-` 表示 stdout，用于需要流式处理的大文件
+pub fn create_output(path: &Path) -> anyhow::Result<Box<dyn Write>> {
+    if is_stdio(path) {
+        Ok(Box::new(std::io::stdout().lock()))
+    } else {
+        let file = std::fs::File::create(path).map_err(|e| anyhow::anyhow!("写入文件失败 {:?}: {}", path, e))?;
+        Ok(Box::new(std::io::BufWriter::new(file)))
+    }
+}
@@ -0,0 +1,208 @@
+//! TPM 2.0 密封的 D1 密钥库（`tpm` feature）
+//!
+//! D1 作为 keyed-hash 密封对象创建在本机 TPM 所有者层级的存储主密钥下，文件中只保存 TPM 返回的
+//! public / private 区（private 区由主密钥加密，离开这台机器的 TPM 无法解开）：
+//!
+//! ```json
+//! {"version":1,"backend":"tpm2","pcr_bank":"sha256","pcrs":[0,7],"public":"...","private":"..."}
+//! ```
+//!
+//! `pcrs` 非空时密封对象带 PolicyPCR 策略，只有这些 SHA-256 PCR 与密封时一致（同一固件、同一安全启动配置等）
+//! 才能解封；为空时任何能访问 TPM 的本机进程均可解封，但复制走的文件无用。
+//!
+//! 存储主密钥按标准 RSA-2048 模板每次重新派生，不占用持久句柄。TPM 通过 TCTI 访问：优先读取环境变量
+//! `TPM2TOOLS_TCTI` / `TCTI`，Linux 默认 `device:/dev/tpmrm0`，Windows 默认 `tbs`。
+
+use crate::keystore::TPM_BACKEND;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tss_esapi::attributes::{ObjectAttributesBuilder, SessionAttributesBuilder};
+use tss_esapi::constants::SessionType;
+use tss_esapi::handles::{KeyHandle, ObjectHandle};
+use tss_esapi::interface_types::algorithm::{HashingAlgorithm, PublicAlgorithm};
+use tss_esapi::interface_types::key_bits::RsaKeyBits;
+use tss_esapi::interface_types::resource_handles::Hierarchy;
+use tss_esapi::interface_types::session_handles::{AuthSession, PolicySession};
+use tss_esapi::structures::{
+    Digest, KeyedHashScheme, PcrSelectionList, PcrSelectionListBuilder, PcrSlot, Private, Public, PublicBuilder,
+    PublicKeyedHashParameters, RsaExponent, SensitiveData, SymmetricDefinition, SymmetricDefinitionObject,
+};
+use tss_esapi::traits::{Marshall, UnMarshall};
+use tss_esapi::{Context, TctiNameConf};
+use zeroize::Zeroizing;
+
+/// 密封文件格式版本
+const TPM_VERSION: u32 = 1;
+/// PCR 编号上限（PC 客户端平台为 24 个 PCR）
+const PCR_COUNT: u32 = 24;
+
+/// 密封文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedFile {
+    version: u32,
+    backend: String,
+    pcr_bank: String,
+    pcrs: Vec<u32>,
+    public: String,
+    private: String,
+}
+
+/// 打开 TPM
+fn context() -> anyhow::Result<Context> {
+    let tcti = match TctiNameConf::from_environment_variable() {
+        Ok(tcti) => tcti,
+        Err(_) if cfg!(windows) => TctiNameConf::from_str("tbs")?,
+        Err(_) => TctiNameConf::from_str("device:/dev/tpmrm0")?,
+    };
+    Context::new(tcti).map_err(|e| anyhow::anyhow!("无法打开 TPM（{}）: {}", tcti_hint(), e))
+}
+
+fn tcti_hint() -> &'static str {
+    if cfg!(windows) {
+        "需要 TPM 2.0 与 TBS 服务"
+    } else {
+        "检查 /dev/tpmrm0 权限（通常需加入 tss 组），或通过 TPM2TOOLS_TCTI 指定"
+    }
+}
+
+/// 派生存储主密钥
+fn primary_key(context: &mut Context) -> anyhow::Result<KeyHandle> {
+    let template = tss_esapi::utils::create_restricted_decryption_rsa_public(
+        SymmetricDefinitionObject::AES_128_CFB,
+        RsaKeyBits::Rsa2048,
+        RsaExponent::default(),
+    )?;
+    let primary = context.execute_with_nullauth_session(|ctx| {
+        ctx.create_primary(Hierarchy::Owner, template, None, None, None, None)
+    })?;
+    Ok(primary.key_handle)
+}
+
+/// 将 PCR 编号转换为 SHA-256 bank 的选择列表
+fn pcr_selection(pcrs: &[u32]) -> anyhow::Result<PcrSelectionList> {
+    let slots = pcrs
+        .iter()
+        .map(|&index| {
+            if index >= PCR_COUNT {
+                anyhow::bail!("无效的 PCR 编号: {}（应小于 {}）", index, PCR_COUNT);
+            }
+            Ok(PcrSlot::try_from(1u32 << index)?)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(PcrSelectionListBuilder::new()
+        .with_selection(HashingAlgorithm::Sha256, &slots)
+        .build()?)
+}
+
+/// 启动策略会话并执行 PolicyPCR；`trial` 为 true 时只用于计算策略摘要
+fn pcr_policy_session(context: &mut Context, selection: PcrSelectionList, trial: bool) -> anyhow::Result<AuthSession> {
+    let session_type = if trial { SessionType::Trial } else { SessionType::Policy };
+    let session = context
+        .start_auth_session(
+            None,
+            None,
+            None,
+            session_type,
+            SymmetricDefinition::AES_128_CFB,
+            HashingAlgorithm::Sha256,
+        )?
+        .ok_or_else(|| anyhow::anyhow!("TPM 未返回策略会话"))?;
+    // Reason: 解封结果经会话参数加密返回，避免 D1 以明文出现在 TPM 总线上
+    let (attributes, mask) = SessionAttributesBuilder::new().with_encrypt(true).build();
+    context.tr_sess_set_attributes(session, attributes, mask)?;
+    // 空摘要表示使用 TPM 当前的 PCR 值
+    context.policy_pcr(PolicySession::try_from(session)?, Digest::default(), selection)?;
+    Ok(session)
+}
+
+fn flush(context: &mut Context, handle: impl Into<ObjectHandle>) {
+    let _ = context.flush_context(handle.into());
+}
+
+/// 确认能打开 TPM 并派生存储主密钥，在生成 D1 之前调用，避免 D1 生成后无法保存
+pub fn probe() -> anyhow::Result<()> {
+    let mut context = context()?;
+    let primary = primary_key(&mut context)?;
+    flush(&mut context, primary);
+    Ok(())
+}
+
+/// 将 D1 密封到本机 TPM，`pcrs` 非空时绑定这些 SHA-256 PCR 的当前值
+pub fn seal(d1: &[u8], pcrs: &[u32]) -> anyhow::Result<Vec<u8>> {
+    let mut context = context()?;
+
+    let policy = if pcrs.is_empty() {
+        None
+    } else {
+        let session = pcr_policy_session(&mut context, pcr_selection(pcrs)?, true)?;
+        let digest = context.policy_get_digest(PolicySession::try_from(session)?);
+        flush(&mut context, tss_esapi::handles::SessionHandle::from(session));
+        Some(digest?)
+    };
+
+    // Reason: 绑定 PCR 时禁止以空口令授权，只能满足策略解封
+    let attributes = ObjectAttributesBuilder::new()
+        .with_fixed_tpm(true)
+        .with_fixed_parent(true)
+        .with_user_with_auth(policy.is_none())
+        .build()?;
+    let mut builder = PublicBuilder::new()
+        .with_public_algorithm(PublicAlgorithm::KeyedHash)
+        .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+        .with_object_attributes(attributes)
+        .with_keyed_hash_parameters(PublicKeyedHashParameters::new(KeyedHashScheme::Null))
+        .with_keyed_hash_unique_identifier(Digest::default());
+    if let Some(policy) = policy {
+        builder = builder.with_auth_policy(policy);
+    }
+    let template = builder.build()?;
+
+    let primary = primary_key(&mut context)?;
+    let sensitive = SensitiveData::try_from(d1.to_vec())?;
+    let created = context.execute_with_nullauth_session(|ctx| {
+        ctx.create(primary, template, None, Some(sensitive), None, None)
+    });
+    flush(&mut context, primary);
+    let created = created?;
+
+    let file = SealedFile {
+        version: TPM_VERSION,
+        backend: TPM_BACKEND.to_string(),
+        pcr_bank: "sha256".to_string(),
+        pcrs: pcrs.to_vec(),
+        public: hex::encode(created.out_public.marshall()?),
+        private: hex::encode(created.out_private.value()),
+    };
+    Ok(serde_json::to_vec_pretty(&file)?)
+}
+
+/// 解封 TPM 密封的 D1
+pub fn unseal(data: &[u8]) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+    let file: SealedFile = serde_json::from_slice(data)?;
+    if file.version != TPM_VERSION || file.backend != TPM_BACKEND || file.pcr_bank != "sha256" {
+        anyhow::bail!("不支持的 TPM 密钥库格式（version {}, {}, {}）", file.version, file.backend, file.pcr_bank);
+    }
+    let public = Public::unmarshall(&hex::decode(&file.public)?)?;
+    let private = Private::try_from(hex::decode(&file.private)?)?;
+
+    let mut context = context()?;
+    let primary = primary_key(&mut context)?;
+    let loaded = context.execute_with_nullauth_session(|ctx| ctx.load(primary, private, public));
+    flush(&mut context, primary);
+    let sealed = loaded.map_err(|e| anyhow::anyhow!("无法加载密封对象（不是本机 TPM 密封的 D1？）: {}", e))?;
+
+    let unsealed = if file.pcrs.is_empty() {
+        context.execute_with_nullauth_session(|ctx| ctx.unseal(sealed.into()))
+    } else {
+        let session = pcr_policy_session(&mut context, pcr_selection(&file.pcrs)?, false)?;
+        let result = context.execute_with_session(Some(session), |ctx| ctx.unseal(sealed.into()));
+        flush(&mut context, tss_esapi::handles::SessionHandle::from(session));
+        result
+    };
+    flush(&mut context, sealed);
+
+    let unsealed = unsealed.map_err(|e| {
+        anyhow::anyhow!("TPM 解封失败（PCR {:?} 与密封时不一致？固件或启动配置变更后需重新密封）: {}", file.pcrs, e)
+    })?;
+    Ok(Zeroizing::new(unsealed.value().to_vec()))
+}
@@ -0,0 +1,649 @@
+//! X.509 相关结构的 DER 编码
+//!
+//! 仅实现 CLI 需要的最小子集：SM2 公钥的 SubjectPublicKeyInfo、主题名称（Name）、
+//! 展示与校验证书所需的 X.509 证书解析。
+//! 证书请求与自签名证书、PKCS#7 签名数据分别由核心库的 `x509`、`pkcs7` 模块构造。
+
+use clap::ValueEnum;
+use sm2_co_sign_core::asn1::{OID_EC_PUBLIC_KEY, TAG_BIT_STRING, TAG_OID};
+use sm2_co_sign_core::protocol::DEFAULT_USER_ID;
+use sm2_co_sign_core::x509::x509_cert::der::Decode;
+use sm2_co_sign_core::x509::x509_cert::name::Name;
+use sm2_co_sign_core::{asn1, pem, CoSignProtocol};
+
+/// SET 标签
+const TAG_SET: u8 = 0x31;
+/// UTF8String 标签
+const TAG_UTF8_STRING: u8 = 0x0C;
+/// PrintableString 标签
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+/// IA5String 标签
+const TAG_IA5_STRING: u8 = 0x16;
+/// 证书版本 `[0] EXPLICIT Version` 标签
+const TAG_CERT_VERSION: u8 = 0xA0;
+/// OCTET STRING 标签
+const TAG_OCTET_STRING: u8 = 0x04;
+/// UTCTime 标签
+const TAG_UTC_TIME: u8 = 0x17;
+/// GeneralizedTime 标签
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+/// BOOLEAN 标签
+const TAG_BOOLEAN: u8 = 0x01;
+/// 证书扩展 `[3] EXPLICIT Extensions` 标签
+const TAG_CERT_EXTENSIONS: u8 = 0xA3;
+
+/// SM3withSM2 签名算法 (1.2.156.10197.1.501)
+const OID_SM3_WITH_SM2: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x01, 0x83, 0x75];
+/// 密钥用途扩展 (2.5.29.15)
+const OID_KEY_USAGE: &[u8] = &[0x55, 0x1D, 0x0F];
+/// 基本约束扩展 (2.5.29.19)
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1D, 0x13];
+
+/// KeyUsage 各位名称（RFC 5280 4.2.1.3，第 0 位为最高位）
+const KEY_USAGE_NAMES: &[&str] = &[
+    "digitalSignature",
+    "nonRepudiation",
+    "keyEncipherment",
+    "dataEncipherment",
+    "keyAgreement",
+    "keyCertSign",
+    "cRLSign",
+    "encipherOnly",
+    "decipherOnly",
+];
+const KEY_USAGE_DIGITAL_SIGNATURE: usize = 0;
+const KEY_USAGE_NON_REPUDIATION: usize = 1;
+const KEY_USAGE_KEY_ENCIPHERMENT: usize = 2;
+const KEY_USAGE_DATA_ENCIPHERMENT: usize = 3;
+const KEY_USAGE_KEY_AGREEMENT: usize = 4;
+const KEY_USAGE_KEY_CERT_SIGN: usize = 5;
+
+/// 证书链最大深度
+const MAX_CHAIN_DEPTH: usize = 8;
+
+/// 支持的主题属性：(名称, OID, 字符串类型)
+const NAME_ATTRIBUTES: &[(&str, &[u8], u8)] = &[
+    ("CN", &[0x55, 0x04, 0x03], TAG_UTF8_STRING),
+    ("C", &[0x55, 0x04, 0x06], TAG_PRINTABLE_STRING),
+    ("L", &[0x55, 0x04, 0x07], TAG_UTF8_STRING),
+    ("ST", &[0x55, 0x04, 0x08], TAG_UTF8_STRING),
+    ("O", &[0x55, 0x04, 0x0A], TAG_UTF8_STRING),
+    ("OU", &[0x55, 0x04, 0x0B], TAG_UTF8_STRING),
+    ("emailAddress", &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x01], TAG_IA5_STRING),
+];
+
+/// SM2 公钥编码为 SubjectPublicKeyInfo DER
+pub fn public_key_to_spki(public_key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(asn1::public_key_to_spki(public_key)?)
+}
+
+/// 将 `CN=Alice,O=Corp` 形式的主题编码为 Name DER（按书写顺序，每个属性一个 RDN）
+pub fn encode_subject(subject: &str) -> anyhow::Result<Vec<u8>> {
+    let mut rdns = Vec::new();
+    for part in subject.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("无效的主题属性: {}", part))?;
+        let (key, value) = (key.trim(), value.trim());
+        let (_, oid, string_tag) = NAME_ATTRIBUTES
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(key))
+            .ok_or_else(|| anyhow::anyhow!("不支持的主题属性: {}", key))?;
+        if value.is_empty() {
+            anyhow::bail!("主题属性 {} 的值为空", key);
+        }
+        if *string_tag != TAG_UTF8_STRING && !value.is_ascii() {
+            anyhow::bail!("主题属性 {} 只能包含 ASCII 字符", key);
+        }
+
+        let mut attribute = asn1::encode_tlv(TAG_OID, oid);
+        attribute.extend(asn1::encode_tlv(*string_tag, value.as_bytes()));
+        rdns.extend(asn1::encode_tlv(TAG_SET, &asn1::encode_sequence(&attribute)));
+    }
+    if rdns.is_empty() {
+        anyhow::bail!("主题不能为空");
+    }
+    Ok(asn1::encode_sequence(&rdns))
+}
+
+/// 主题编码为 x509-cert 的 Name，供核心库构造证书请求与自签名证书
+pub fn subject_name(subject: &str) -> anyhow::Result<Name> {
+    Ok(Name::from_der(&encode_subject(subject)?)?)
+}
+
+/// X.509 证书中展示与校验所需的字段
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    /// 序列号（十六进制）
+    pub serial: String,
+    /// 签发者
+    pub issuer: String,
+    /// 主题
+    pub subject: String,
+    /// 生效时间
+    pub not_before: String,
+    /// 失效时间
+    pub not_after: String,
+    /// 公钥（x||y，64 字节）
+    pub public_key: Vec<u8>,
+    /// 签发者 Name（DER）
+    pub issuer_der: Vec<u8>,
+    /// 主题 Name（DER）
+    pub subject_der: Vec<u8>,
+    /// 完整证书（DER）
+    pub der: Vec<u8>,
+    /// 待签名部分 TBSCertificate（DER）
+    pub tbs_der: Vec<u8>,
+    /// 签名算法 OID
+    pub signature_algorithm: Vec<u8>,
+    /// 签名值（DER 编码的 SM2 签名）
+    pub signature: Vec<u8>,
+    /// KeyUsage 位串（不含未用位字节），无该扩展时为 None
+    pub key_usage: Option<Vec<u8>>,
+    /// BasicConstraints 的 cA 标志，无该扩展时为 None
+    pub is_ca: Option<bool>,
+}
+
+/// 证书密钥用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyPurpose {
+    /// 签名证书（digitalSignature 或 nonRepudiation）
+    Sign,
+    /// 加密证书（keyEncipherment、dataEncipherment 或 keyAgreement）
+    Encrypt,
+    /// 不检查密钥用途
+    Any,
+}
+
+/// 单项证书校验结果
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// 校验项名称
+    pub name: String,
+    /// 失败原因，通过时为 None
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, result: anyhow::Result<()>) -> Self {
+        Self { name: name.into(), error: result.err().map(|e| format!("{:#}", e)) }
+    }
+
+    /// 是否通过
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl Certificate {
+    /// 解析 DER 编码的 X.509 证书
+    pub fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = asn1::DerReader::new(der);
+        let mut certificate = asn1::DerReader::new(reader.read(asn1::TAG_SEQUENCE)?);
+        let tbs_contents = certificate.read(asn1::TAG_SEQUENCE)?;
+        let mut tbs = asn1::DerReader::new(tbs_contents);
+
+        if tbs.peek_tag() == Some(TAG_CERT_VERSION) {
+            tbs.read(TAG_CERT_VERSION)?;
+        }
+        let serial = hex::encode(tbs.read_unsigned_integer()?);
+        tbs.read(asn1::TAG_SEQUENCE)?; // signature
+        let issuer_name = tbs.read(asn1::TAG_SEQUENCE)?;
+        let issuer = decode_name(issuer_name)?;
+        let mut validity = asn1::DerReader::new(tbs.read(asn1::TAG_SEQUENCE)?);
+        let not_before = decode_time(&mut validity)?;
+        let not_after = decode_time(&mut validity)?;
+        let subject_name = tbs.read(asn1::TAG_SEQUENCE)?;
+        let subject = decode_name(subject_name)?;
+        let public_key = public_key_from_spki(tbs.read(asn1::TAG_SEQUENCE)?)?;
+
+        // 跳过 issuerUniqueID / subjectUniqueID，解析扩展
+        let mut key_usage = None;
+        let mut is_ca = None;
+        while !tbs.is_empty() {
+            let (tag, contents) = tbs.read_any()?;
+            if tag != TAG_CERT_EXTENSIONS {
+                continue;
+            }
+            let mut extensions = asn1::DerReader::new(asn1::DerReader::new(contents).read(asn1::TAG_SEQUENCE)?);
+            while !extensions.is_empty() {
+                let mut extension = asn1::DerReader::new(extensions.read(asn1::TAG_SEQUENCE)?);
+                let oid = extension.read(TAG_OID)?;
+                if extension.peek_tag() == Some(TAG_BOOLEAN) {
+                    extension.read(TAG_BOOLEAN)?; // critical
+                }
+                let value = extension.read(TAG_OCTET_STRING)?;
+                if oid == OID_KEY_USAGE {
+                    match asn1::DerReader::new(value).read(TAG_BIT_STRING)? {
+                        [_unused, bits @ ..] => key_usage = Some(bits.to_vec()),
+                        [] => anyhow::bail!("无效的 KeyUsage 扩展"),
+                    }
+                } else if oid == OID_BASIC_CONSTRAINTS {
+                    let mut constraints = asn1::DerReader::new(asn1::DerReader::new(value).read(asn1::TAG_SEQUENCE)?);
+                    let ca = constraints.peek_tag() == Some(TAG_BOOLEAN) && constraints.read(TAG_BOOLEAN)? != [0x00u8].as_slice();
+                    is_ca = Some(ca);
+                }
+            }
+        }
+
+        let mut algorithm = asn1::DerReader::new(certificate.read(asn1::TAG_SEQUENCE)?);
+        let signature_algorithm = algorithm.read(TAG_OID)?.to_vec();
+        let signature = match certificate.read(TAG_BIT_STRING)? {
+            [0x00, signature @ ..] => signature.to_vec(),
+            _ => anyhow::bail!("无效的证书签名"),
+        };
+
+        Ok(Self {
+            serial,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            issuer_der: asn1::encode_sequence(issuer_name),
+            subject_der: asn1::encode_sequence(subject_name),
+            der: der.to_vec(),
+            tbs_der: asn1::encode_sequence(tbs_contents),
+            signature_algorithm,
+            signature,
+            key_usage,
+            is_ca,
+        })
+    }
+
+    /// 解析 PEM（可包含多个证书）或 DER 编码的证书列表
+    pub fn parse_all(data: &[u8]) -> anyhow::Result<Vec<Self>> {
+        match std::str::from_utf8(data) {
+            Ok(text) if pem::contains(text, CERTIFICATE_LABEL) => pem::decode_all(text, CERTIFICATE_LABEL)?
+                .iter()
+                .map(|der| Self::from_der(der))
+                .collect(),
+            _ => Ok(vec![Self::from_der(data)?]),
+        }
+    }
+
+    /// 是否为自签名证书（签发者与主题相同）
+    pub fn is_self_signed(&self) -> bool {
+        self.issuer_der == self.subject_der
+    }
+
+    /// KeyUsage 是否包含指定位；无该扩展时为 None
+    pub fn has_key_usage(&self, bit: usize) -> Option<bool> {
+        self.key_usage
+            .as_ref()
+            .map(|bits| bits.get(bit / 8).is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0))
+    }
+
+    /// KeyUsage 中已设置的用途名称
+    pub fn key_usage_names(&self) -> Vec<&'static str> {
+        (0..KEY_USAGE_NAMES.len())
+            .filter(|bit| self.has_key_usage(*bit) == Some(true))
+            .map(|bit| KEY_USAGE_NAMES[bit])
+            .collect()
+    }
+
+    /// 校验证书在指定时间（`format_time` 格式）处于有效期内
+    pub fn check_validity(&self, now: &str) -> anyhow::Result<()> {
+        // Reason: 时间均为定长 `YYYY-MM-DD HH:MM:SS UTC` 格式，可直接按字符串比较
+        if now < self.not_before.as_str() {
+            anyhow::bail!("证书尚未生效（生效时间 {}）", self.not_before);
+        }
+        if now > self.not_after.as_str() {
+            anyhow::bail!("证书已过期（失效时间 {}）", self.not_after);
+        }
+        Ok(())
+    }
+
+    /// 校验 KeyUsage 符合用途；无 KeyUsage 扩展时不限制
+    pub fn check_purpose(&self, purpose: KeyPurpose) -> anyhow::Result<()> {
+        let required: &[usize] = match purpose {
+            KeyPurpose::Sign => &[KEY_USAGE_DIGITAL_SIGNATURE, KEY_USAGE_NON_REPUDIATION],
+            KeyPurpose::Encrypt => &[KEY_USAGE_KEY_ENCIPHERMENT, KEY_USAGE_DATA_ENCIPHERMENT, KEY_USAGE_KEY_AGREEMENT],
+            KeyPurpose::Any => return Ok(()),
+        };
+        if self.key_usage.is_some() && !required.iter().any(|bit| self.has_key_usage(*bit) == Some(true)) {
+            anyhow::bail!("密钥用途不符（{}）", self.key_usage_names().join(", "));
+        }
+        Ok(())
+    }
+
+    /// 校验证书可作为 CA 签发证书
+    pub fn check_ca(&self) -> anyhow::Result<()> {
+        match self.is_ca {
+            Some(false) => anyhow::bail!("BasicConstraints 未标记为 CA"),
+            // Reason: 无扩展的 v1 根证书仍较常见，仅对自签名根证书放宽
+            None if !self.is_self_signed() => anyhow::bail!("缺少 BasicConstraints 扩展"),
+            _ => {}
+        }
+        if self.has_key_usage(KEY_USAGE_KEY_CERT_SIGN) == Some(false) {
+            anyhow::bail!("KeyUsage 不包含 keyCertSign");
+        }
+        Ok(())
+    }
+
+    /// 使用签发者公钥校验证书签名（SM3withSM2，默认用户标识）
+    pub fn verify_signed_by(&self, issuer: &Certificate) -> anyhow::Result<()> {
+        if self.signature_algorithm != OID_SM3_WITH_SM2 {
+            anyhow::bail!("不支持的签名算法 {}", oid_to_string(&self.signature_algorithm));
+        }
+        let protocol = CoSignProtocol::new()?;
+        let e = protocol.calculate_message_hash_with_uid(&self.tbs_der, DEFAULT_USER_ID, &issuer.public_key)?;
+        let signature = asn1::signature_from_der(&self.signature)?;
+        if !protocol.verify_digest(&issuer.public_key, &e, &signature[..32], &signature[32..])? {
+            anyhow::bail!("签名无效");
+        }
+        Ok(())
+    }
+
+    /// 解析 PEM 或 DER 编码的证书
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        match std::str::from_utf8(data) {
+            Ok(text) if pem::contains(text, CERTIFICATE_LABEL) => Self::from_der(&pem::decode(text, CERTIFICATE_LABEL)?),
+            _ => Self::from_der(data),
+        }
+    }
+
+    /// 证书公钥是否与给定公钥一致（支持 64 与 65 字节格式）
+    pub fn matches_public_key(&self, public_key: &[u8]) -> bool {
+        let public_key = match public_key.len() {
+            65 if public_key[0] == 0x04 => &public_key[1..],
+            _ => public_key,
+        };
+        self.public_key == public_key
+    }
+}
+
+pub use sm2_co_sign_core::pem::CERTIFICATE_LABEL;
+
+/// 校验证书：有效期、密钥用途，以及直到自签名根证书的签发链
+///
+/// `trusted` 为受信任的 CA 证书（含中间证书），每一级签发者均校验签名、CA 标志与有效期。
+pub fn verify_certificate(cert: &Certificate, trusted: &[Certificate], now: &str, purpose: KeyPurpose) -> Vec<CheckResult> {
+    let mut checks = vec![
+        CheckResult::new("validity", cert.check_validity(now)),
+        CheckResult::new("key_usage", cert.check_purpose(purpose)),
+    ];
+
+    let mut current = cert;
+    for _ in 0..MAX_CHAIN_DEPTH {
+        let Some(issuer) = trusted.iter().find(|ca| ca.subject_der == current.issuer_der) else {
+            checks.push(CheckResult::new(
+                "chain",
+                Err(anyhow::anyhow!("未找到签发者证书: {}", current.issuer)),
+            ));
+            return checks;
+        };
+        checks.push(CheckResult::new(format!("signature[{}]", current.subject), current.verify_signed_by(issuer)));
+        checks.push(CheckResult::new(format!("ca[{}]", issuer.subject), issuer.check_ca()));
+        checks.push(CheckResult::new(format!("validity[{}]", issuer.subject), issuer.check_validity(now)));
+        if issuer.is_self_signed() {
+            return checks;
+        }
+        current = issuer;
+    }
+    checks.push(CheckResult::new("chain", Err(anyhow::anyhow!("证书链超过 {} 级", MAX_CHAIN_DEPTH))));
+    checks
+}
+
+/// Unix 时间戳格式化为 `YYYY-MM-DD HH:MM:SS UTC`（与证书时间格式一致）
+pub fn format_time(unix_secs: u64) -> String {
+    let days = (unix_secs / 86400) as i64;
+    let secs = unix_secs % 86400;
+    // Reason: 公历日期换算（Howard Hinnant civil_from_days），避免引入日期库
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// 从 SubjectPublicKeyInfo 内容中取出 SM2 公钥（x||y）
+fn public_key_from_spki(spki: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut reader = asn1::DerReader::new(spki);
+    let mut algorithm = asn1::DerReader::new(reader.read(asn1::TAG_SEQUENCE)?);
+    if algorithm.read(TAG_OID)? != OID_EC_PUBLIC_KEY {
+        anyhow::bail!("证书公钥不是椭圆曲线公钥");
+    }
+    let bit_string = reader.read(TAG_BIT_STRING)?;
+    match bit_string {
+        [0x00, 0x04, point @ ..] if point.len() == 64 => Ok(point.to_vec()),
+        _ => anyhow::bail!("不支持的证书公钥格式"),
+    }
+}
+
+/// 将 Name 内容解码为 `CN=Alice, O=Corp` 形式
+fn decode_name(name: &[u8]) -> anyhow::Result<String> {
+    let mut parts = Vec::new();
+    let mut rdns = asn1::DerReader::new(name);
+    while !rdns.is_empty() {
+        let mut rdn = asn1::DerReader::new(rdns.read(TAG_SET)?);
+        while !rdn.is_empty() {
+            let mut attribute = asn1::DerReader::new(rdn.read(asn1::TAG_SEQUENCE)?);
+            let oid = attribute.read(TAG_OID)?;
+            let (_, value) = attribute.read_any()?;
+            let key = NAME_ATTRIBUTES
+                .iter()
+                .find(|(_, attr_oid, _)| *attr_oid == oid)
+                .map(|(name, _, _)| name.to_string())
+                .unwrap_or_else(|| oid_to_string(oid));
+            parts.push(format!("{}={}", key, String::from_utf8_lossy(value)));
+        }
+    }
+    Ok(parts.join(", "))
+}
+
+/// OID 编码转换为点分十进制
+fn oid_to_string(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value = 0u64;
+    for byte in oid {
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+/// 解码 UTCTime / GeneralizedTime 为 `YYYY-MM-DD HH:MM:SS UTC`
+fn decode_time(reader: &mut asn1::DerReader) -> anyhow::Result<String> {
+    let (tag, value) = reader.read_any()?;
+    let text = std::str::from_utf8(value).map_err(|_| anyhow::anyhow!("无效的证书时间"))?;
+    let digits = text.trim_end_matches('Z');
+    let full = match tag {
+        // Reason: RFC 5280 规定 UTCTime 年份 >= 50 表示 19xx
+        TAG_UTC_TIME if digits.len() == 12 => {
+            let century = if &digits[..2] >= "50" { "19" } else { "20" };
+            format!("{}{}", century, digits)
+        }
+        TAG_GENERALIZED_TIME if digits.len() == 14 => digits.to_string(),
+        _ => anyhow::bail!("无效的证书时间: {}", text),
+    };
+    if !full.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("无效的证书时间: {}", text);
+    }
+    Ok(format!(
+        "{}-{}-{} {}:{}:{} UTC",
+        &full[..4],
+        &full[4..6],
+        &full[6..8],
+        &full[8..10],
+        &full[10..12],
+        &full[12..14]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm2_co_sign_core::asn1::OID_SM2;
+
+    #[test]
+    fn test_encode_subject() {
+        assert_eq!(
+            encode_subject("CN=A").unwrap(),
+            vec![0x30, 0x0C, 0x31, 0x0A, 0x30, 0x08, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0C, 0x01, 0x41]
+        );
+        assert!(encode_subject("CN=Alice, O=Corp, C=CN").is_ok());
+        assert!(encode_subject("XX=Alice").is_err());
+        assert!(encode_subject("CN").is_err());
+        assert!(encode_subject("").is_err());
+        assert!(encode_subject("C=中国").is_err());
+    }
+
+    #[test]
+    fn test_subject_name() {
+        use sm2_co_sign_core::x509::x509_cert::der::Encode;
+
+        let name = subject_name("CN=Alice,O=Corp").unwrap();
+        assert_eq!(name.to_der().unwrap(), encode_subject("CN=Alice,O=Corp").unwrap());
+        assert!(subject_name("XX=Alice").is_err());
+    }
+
+    /// 构造测试用证书
+    fn test_certificate(public_key: &[u8]) -> Vec<u8> {
+        build_certificate("CN=Test CA,C=CN", "CN=Alice,O=Corp", public_key, None, None)
+    }
+
+    /// 构造证书；`extensions` 为 (OID, 扩展值 DER)，`signer` 为签发者私钥（None 时签名为占位值）
+    fn build_certificate(
+        issuer: &str,
+        subject: &str,
+        public_key: &[u8],
+        extensions: Option<&[(&[u8], Vec<u8>)]>,
+        signer: Option<&[u8]>,
+    ) -> Vec<u8> {
+        let mut tbs = asn1::encode_tlv(TAG_CERT_VERSION, &asn1::encode_unsigned_integer(&[2]));
+        tbs.extend(asn1::encode_unsigned_integer(&[0x01, 0x23]));
+        tbs.extend(asn1::encode_sequence(&asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2)));
+        tbs.extend(encode_subject(issuer).unwrap());
+        let mut validity = asn1::encode_tlv(TAG_UTC_TIME, b"250101000000Z");
+        validity.extend(asn1::encode_tlv(TAG_GENERALIZED_TIME, b"20351231235959Z"));
+        tbs.extend(asn1::encode_sequence(&validity));
+        tbs.extend(encode_subject(subject).unwrap());
+        tbs.extend(public_key_to_spki(public_key).unwrap());
+        if let Some(extensions) = extensions {
+            let mut list = Vec::new();
+            for (oid, value) in extensions {
+                let mut extension = asn1::encode_tlv(TAG_OID, oid);
+                extension.extend(asn1::encode_tlv(TAG_OCTET_STRING, value));
+                list.extend(asn1::encode_sequence(&extension));
+            }
+            tbs.extend(asn1::encode_tlv(TAG_CERT_EXTENSIONS, &asn1::encode_sequence(&list)));
+        }
+        let tbs = asn1::encode_sequence(&tbs);
+
+        let signature = match signer {
+            Some(private_key) => {
+                asn1::signature_to_der(&CoSignProtocol::sign(private_key, &tbs).unwrap()).unwrap()
+            }
+            None => vec![0x30, 0x00],
+        };
+        let mut certificate = tbs;
+        certificate.extend(asn1::encode_sequence(&asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2)));
+        certificate.extend(asn1::encode_tlv(TAG_BIT_STRING, &[&[0x00u8][..], &signature].concat()));
+        asn1::encode_sequence(&certificate)
+    }
+
+    fn key_usage(bits: &[u8]) -> Vec<u8> {
+        asn1::encode_tlv(TAG_BIT_STRING, &[&[0x00u8][..], bits].concat())
+    }
+
+    fn ca_constraints() -> Vec<u8> {
+        asn1::encode_sequence(&asn1::encode_tlv(TAG_BOOLEAN, &[0xFF]))
+    }
+
+    #[test]
+    fn test_verify_certificate_chain() {
+        let (root_key, root_public) = CoSignProtocol::generate_keypair();
+        let (leaf_key, leaf_public) = CoSignProtocol::generate_keypair();
+        let ca_extensions = [(OID_BASIC_CONSTRAINTS, ca_constraints()), (OID_KEY_USAGE, key_usage(&[0x06]))];
+        let root = build_certificate("CN=Root", "CN=Root", &root_public, Some(&ca_extensions), Some(&root_key));
+        let root = Certificate::from_der(&root).unwrap();
+        assert_eq!(root.is_ca, Some(true));
+        assert_eq!(root.key_usage_names(), vec!["keyCertSign", "cRLSign"]);
+
+        let sign_usage = [(OID_KEY_USAGE, key_usage(&[0xC0]))];
+        let leaf = build_certificate("CN=Root", "CN=Peer", &leaf_public, Some(&sign_usage), Some(&root_key));
+        let leaf = Certificate::from_der(&leaf).unwrap();
+        let now = "2026-06-01 00:00:00 UTC";
+
+        let checks = verify_certificate(&leaf, std::slice::from_ref(&root), now, KeyPurpose::Sign);
+        assert!(checks.iter().all(CheckResult::passed), "{:?}", checks);
+
+        // 用途不符、过期、签发者不受信任
+        let checks = verify_certificate(&leaf, std::slice::from_ref(&root), now, KeyPurpose::Encrypt);
+        assert!(!checks.iter().all(CheckResult::passed));
+        let checks = verify_certificate(&leaf, std::slice::from_ref(&root), "2036-01-01 00:00:00 UTC", KeyPurpose::Sign);
+        assert!(!checks[0].passed());
+        assert!(!verify_certificate(&leaf, &[], now, KeyPurpose::Sign).iter().all(CheckResult::passed));
+
+        // 他人签发的同名证书签名无效
+        let forged = build_certificate("CN=Root", "CN=Peer", &leaf_public, Some(&sign_usage), Some(&leaf_key));
+        let forged = Certificate::from_der(&forged).unwrap();
+        assert!(forged.verify_signed_by(&root).is_err());
+    }
+
+    #[test]
+    fn test_check_ca_requires_constraints() {
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        let intermediate = Certificate::from_der(&build_certificate("CN=Root", "CN=Sub", &public_key, None, None)).unwrap();
+        assert!(intermediate.check_ca().is_err());
+        let root = Certificate::from_der(&build_certificate("CN=Root", "CN=Root", &public_key, None, None)).unwrap();
+        assert!(root.check_ca().is_ok());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(1_735_689_600), "2025-01-01 00:00:00 UTC");
+        assert_eq!(format_time(951_825_661), "2000-02-29 12:01:01 UTC");
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let der = test_certificate(&[0x11; 64]);
+        for data in [der.clone(), pem::encode(CERTIFICATE_LABEL, &der).into_bytes()] {
+            let cert = Certificate::parse(&data).unwrap();
+            assert_eq!(cert.serial, "0123");
+            assert_eq!(cert.issuer, "CN=Test CA, C=CN");
+            assert_eq!(cert.subject, "CN=Alice, O=Corp");
+            assert_eq!(cert.not_before, "2025-01-01 00:00:00 UTC");
+            assert_eq!(cert.not_after, "2035-12-31 23:59:59 UTC");
+            assert!(cert.matches_public_key(&[0x11; 64]));
+            assert!(cert.matches_public_key(&[&[0x04u8][..], &[0x11; 64]].concat()));
+            assert!(!cert.matches_public_key(&[0x22; 64]));
+        }
+    }
+
+    #[test]
+    fn test_oid_to_string() {
+        use sm2_co_sign_core::pkcs7::{OID_GM_SIGNED_DATA, OID_SM2_SIGN, OID_SM3};
+
+        assert_eq!(oid_to_string(OID_SM3), "1.2.156.10197.1.401");
+        assert_eq!(oid_to_string(OID_SM2_SIGN), "1.2.156.10197.1.301.1");
+        assert_eq!(oid_to_string(OID_GM_SIGNED_DATA), "1.2.156.10197.6.1.4.2.2");
+        assert_eq!(oid_to_string(OID_SM2), "1.2.156.10197.1.301");
+        assert_eq!(oid_to_string(OID_EC_PUBLIC_KEY), "1.2.840.10045.2.1");
+    }
+}
@@ -0,0 +1,80 @@
+//! 核心协议运算的基准测试
+//!
+//! 用于发现依赖或曲线后端变更带来的性能回退：`cargo bench -p sm2_co_sign_core`。
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sm2_co_sign_core::simulator::D2Simulator;
+use sm2_co_sign_core::CoSignProtocol;
+
+fn bench_key(c: &mut Criterion) {
+    let protocol = CoSignProtocol::new().unwrap();
+    let d1 = protocol.generate_d1().unwrap();
+
+    c.bench_function("generate_d1", |b| b.iter(|| protocol.generate_d1().unwrap()));
+    c.bench_function("calculate_p1", |b| b.iter(|| protocol.calculate_p1(black_box(&d1)).unwrap()));
+}
+
+fn bench_sign(c: &mut Criterion) {
+    let protocol = CoSignProtocol::new().unwrap();
+    let simulator = D2Simulator::new();
+    let d1 = protocol.generate_d1().unwrap();
+    let key = simulator.generate_key(&protocol.calculate_p1(&d1).unwrap()).unwrap();
+    let e = CoSignProtocol::sm3_hash(b"benchmark message");
+
+    c.bench_function("sign_prepare", |b| b.iter(|| protocol.sign_prepare().unwrap()));
+
+    let (k1, q1) = protocol.sign_prepare().unwrap().into_parts();
+    let response = simulator.sign(&key.d2, &q1, &e).unwrap();
+    c.bench_function("complete_signature", |b| {
+        b.iter(|| {
+            protocol
+                .complete_signature(black_box(&k1), &d1, &response.r, &response.s2, &response.s3)
+                .unwrap()
+        })
+    });
+
+    let (r, s) = protocol.complete_signature(&k1, &d1, &response.r, &response.s2, &response.s3).unwrap();
+    c.bench_function("verify_digest", |b| {
+        b.iter(|| protocol.verify_digest(&key.public_key, black_box(&e), &r, &s).unwrap())
+    });
+}
+
+fn bench_encrypt(c: &mut Criterion) {
+    let protocol = CoSignProtocol::new().unwrap();
+    let (private_key, public_key) = CoSignProtocol::generate_keypair();
+    let d1 = protocol.generate_d1().unwrap();
+
+    let mut group = c.benchmark_group("sm2");
+    for size in [32usize, 1024, 16 * 1024] {
+        let message = vec![0x5au8; size];
+        let ciphertext = CoSignProtocol::encrypt(&public_key, &message).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("encrypt", size), &message, |b, message| {
+            b.iter(|| CoSignProtocol::encrypt(&public_key, message).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("decrypt", size), &ciphertext, |b, ciphertext| {
+            b.iter(|| CoSignProtocol::decrypt(&private_key, ciphertext).unwrap())
+        });
+    }
+    group.finish();
+
+    let ciphertext = CoSignProtocol::encrypt(&public_key, b"benchmark").unwrap();
+    c.bench_function("decrypt_prepare", |b| {
+        b.iter(|| protocol.decrypt_prepare(&d1, black_box(&ciphertext[1..65])).unwrap())
+    });
+}
+
+fn bench_kdf(c: &mut Criterion) {
+    let shared = [0x42u8; 64];
+    let mut group = c.benchmark_group("kdf");
+    for klen in [32usize, 1024, 16 * 1024] {
+        group.throughput(Throughput::Bytes(klen as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(klen), &klen, |b, &klen| {
+            b.iter(|| CoSignProtocol::kdf(black_box(&shared), klen))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_key, bench_sign, bench_encrypt, bench_kdf);
+criterion_main!(benches);
@@ -0,0 +1,345 @@
+//! ASN.1 DER 编解码
+//!
+//! 仅实现本库需要的最小子集（INTEGER、SEQUENCE 等基本 TLV），
+//! 用于 SM2 签名值 `SEQUENCE { r INTEGER, s INTEGER }`、SM2 密文与公钥 SubjectPublicKeyInfo 的转换。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::{Error, Result};
+
+/// INTEGER 标签
+pub const TAG_INTEGER: u8 = 0x02;
+/// SEQUENCE 标签
+pub const TAG_SEQUENCE: u8 = 0x30;
+/// BIT STRING 标签
+pub const TAG_BIT_STRING: u8 = 0x03;
+/// OCTET STRING 标签
+pub const TAG_OCTET_STRING: u8 = 0x04;
+/// NULL 标签
+pub const TAG_NULL: u8 = 0x05;
+/// OBJECT IDENTIFIER 标签
+pub const TAG_OID: u8 = 0x06;
+/// SET 标签
+pub const TAG_SET: u8 = 0x31;
+/// 证书版本 `[0] EXPLICIT Version` 标签
+pub const TAG_CERT_VERSION: u8 = 0xA0;
+
+/// id-ecPublicKey (1.2.840.10045.2.1)
+pub const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// SM2 曲线 (1.2.156.10197.1.301)
+pub const OID_SM2: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x01, 0x82, 0x2D];
+
+/// 编码 DER 长度字段
+pub fn encode_length(len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+        return;
+    }
+    let bytes = len.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    out.push(0x80 | (bytes.len() - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+/// 编码一个 TLV
+pub fn encode_tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(contents.len() + 4);
+    out.push(tag);
+    encode_length(contents.len(), &mut out);
+    out.extend_from_slice(contents);
+    out
+}
+
+/// 编码非负整数（大端字节）为 DER INTEGER
+///
+/// 去掉多余的前导零；最高位为 1 时补 0x00，避免被解析为负数。
+pub fn encode_unsigned_integer(value: &[u8]) -> Vec<u8> {
+    let skip = value.iter().take_while(|b| **b == 0).count();
+    let trimmed = &value[skip..];
+
+    let mut contents = Vec::with_capacity(trimmed.len() + 1);
+    if trimmed.is_empty() || trimmed[0] & 0x80 != 0 {
+        contents.push(0x00);
+    }
+    contents.extend_from_slice(trimmed);
+    encode_tlv(TAG_INTEGER, &contents)
+}
+
+/// 编码 SEQUENCE
+pub fn encode_sequence(contents: &[u8]) -> Vec<u8> {
+    encode_tlv(TAG_SEQUENCE, contents)
+}
+
+/// DER 读取器
+pub struct DerReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DerReader<'a> {
+    /// 创建读取器
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// 是否已读取完毕
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// 查看下一个标签（不前进）
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// 读取一个 TLV，返回 (标签, 内容)
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8])> {
+        let tag = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| Error::Encoding("Unexpected end of DER data".to_string()))?;
+        self.pos += 1;
+
+        let first = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| Error::Encoding("Missing DER length".to_string()))?;
+        self.pos += 1;
+
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let num = (first & 0x7f) as usize;
+            if num == 0 || num > core::mem::size_of::<usize>() {
+                return Err(Error::Encoding("Unsupported DER length encoding".to_string()));
+            }
+            let bytes = self
+                .data
+                .get(self.pos..self.pos + num)
+                .ok_or_else(|| Error::Encoding("Truncated DER length".to_string()))?;
+            self.pos += num;
+            bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+        };
+
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| Error::Encoding("Truncated DER value".to_string()))?;
+        let contents = &self.data[self.pos..end];
+        self.pos = end;
+        Ok((tag, contents))
+    }
+
+    /// 读取指定标签的 TLV，返回内容
+    pub fn read(&mut self, expected_tag: u8) -> Result<&'a [u8]> {
+        let (tag, contents) = self.read_any()?;
+        if tag != expected_tag {
+            return Err(Error::Encoding(format!(
+                "Unexpected DER tag 0x{:02x}, expected 0x{:02x}",
+                tag, expected_tag
+            )));
+        }
+        Ok(contents)
+    }
+
+    /// 读取非负 INTEGER，返回去掉符号填充字节后的大端字节
+    pub fn read_unsigned_integer(&mut self) -> Result<&'a [u8]> {
+        let contents = self.read(TAG_INTEGER)?;
+        if contents.is_empty() {
+            return Err(Error::Encoding("Empty DER INTEGER".to_string()));
+        }
+        if contents[0] & 0x80 != 0 {
+            return Err(Error::Encoding("Negative DER INTEGER".to_string()));
+        }
+        let skip = contents.iter().take_while(|b| **b == 0).count();
+        Ok(&contents[skip..])
+    }
+}
+
+/// 将 32 字节以内的大端整数左补零到 32 字节
+pub fn left_pad_32(value: &[u8]) -> Result<[u8; 32]> {
+    if value.len() > 32 {
+        return Err(Error::Encoding("Integer longer than 32 bytes".to_string()));
+    }
+    let mut out = [0u8; 32];
+    out[32 - value.len()..].copy_from_slice(value);
+    Ok(out)
+}
+
+/// 原始签名 r||s（64字节）转换为 DER 编码
+pub fn signature_to_der(raw: &[u8]) -> Result<Vec<u8>> {
+    if raw.len() != 64 {
+        return Err(Error::Encoding("Invalid signature length, expected 64 bytes".to_string()));
+    }
+
+    let mut contents = encode_unsigned_integer(&raw[0..32]);
+    contents.extend_from_slice(&encode_unsigned_integer(&raw[32..64]));
+    Ok(encode_sequence(&contents))
+}
+
+/// DER 编码签名转换为原始签名 r||s（64字节）
+pub fn signature_from_der(der: &[u8]) -> Result<Vec<u8>> {
+    let mut outer = DerReader::new(der);
+    let seq = outer.read(TAG_SEQUENCE)?;
+    if !outer.is_empty() {
+        return Err(Error::Encoding("Trailing data after DER signature".to_string()));
+    }
+
+    let mut inner = DerReader::new(seq);
+    let r = left_pad_32(inner.read_unsigned_integer()?)?;
+    let s = left_pad_32(inner.read_unsigned_integer()?)?;
+    if !inner.is_empty() {
+        return Err(Error::Encoding("Trailing data in DER signature sequence".to_string()));
+    }
+
+    let mut raw = Vec::with_capacity(64);
+    raw.extend_from_slice(&r);
+    raw.extend_from_slice(&s);
+    Ok(raw)
+}
+
+/// SM2 公钥（64 字节 x||y 或 65 字节 04||x||y）编码为 SubjectPublicKeyInfo DER
+///
+/// 只做格式转换，不校验点是否在曲线上（需要校验时使用 [`crate::PublicKey`]）。
+pub fn public_key_to_spki(public_key: &[u8]) -> Result<Vec<u8>> {
+    let point = match public_key.len() {
+        64 => [&[0x04u8][..], public_key].concat(),
+        65 if public_key[0] == 0x04 => public_key.to_vec(),
+        len => return Err(Error::Encoding(format!("Invalid public key length: {}", len))),
+    };
+
+    let mut algorithm = encode_tlv(TAG_OID, OID_EC_PUBLIC_KEY);
+    algorithm.extend(encode_tlv(TAG_OID, OID_SM2));
+    let mut bit_string = vec![0x00];
+    bit_string.extend(point);
+
+    let mut spki = encode_sequence(&algorithm);
+    spki.extend(encode_tlv(TAG_BIT_STRING, &bit_string));
+    Ok(encode_sequence(&spki))
+}
+
+/// 从 SubjectPublicKeyInfo DER 中取出 SM2 公钥（64 字节 x||y）
+pub fn public_key_from_spki(der: &[u8]) -> Result<Vec<u8>> {
+    let mut outer = DerReader::new(der);
+    let mut spki = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    if !outer.is_empty() {
+        return Err(Error::Encoding("Trailing data after SubjectPublicKeyInfo".to_string()));
+    }
+    let mut algorithm = DerReader::new(spki.read(TAG_SEQUENCE)?);
+    if algorithm.read(TAG_OID)? != OID_EC_PUBLIC_KEY {
+        return Err(Error::Encoding("Public key is not an EC public key".to_string()));
+    }
+    if !algorithm.is_empty() && algorithm.read(TAG_OID)? != OID_SM2 {
+        return Err(Error::Encoding("Public key curve is not SM2".to_string()));
+    }
+    match spki.read(TAG_BIT_STRING)? {
+        [0x00, 0x04, point @ ..] if point.len() == 64 => Ok(point.to_vec()),
+        _ => Err(Error::Encoding("Unsupported public key encoding, expected uncompressed point".to_string())),
+    }
+}
+
+/// 从 X.509 证书 DER 中取出 SM2 公钥（64 字节 x||y）
+///
+/// 只定位 `tbsCertificate.subjectPublicKeyInfo`，不校验证书签名与有效期。
+pub fn public_key_from_certificate(der: &[u8]) -> Result<Vec<u8>> {
+    let mut outer = DerReader::new(der);
+    let mut certificate = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    let mut tbs = DerReader::new(certificate.read(TAG_SEQUENCE)?);
+    // [0] EXPLICIT Version 可省略（v1）
+    if tbs.peek_tag() == Some(TAG_CERT_VERSION) {
+        tbs.read_any()?;
+    }
+    tbs.read(TAG_INTEGER)?;
+    // signature、issuer、validity、subject
+    for _ in 0..4 {
+        tbs.read(TAG_SEQUENCE)?;
+    }
+    public_key_from_spki(&encode_sequence(tbs.read(TAG_SEQUENCE)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_length() {
+        let mut out = Vec::new();
+        encode_length(0x7f, &mut out);
+        assert_eq!(out, vec![0x7f]);
+
+        let mut out = Vec::new();
+        encode_length(0x80, &mut out);
+        assert_eq!(out, vec![0x81, 0x80]);
+
+        let mut out = Vec::new();
+        encode_length(0x1234, &mut out);
+        assert_eq!(out, vec![0x82, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn test_integer_high_bit_padding() {
+        assert_eq!(encode_unsigned_integer(&[0x80]), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(encode_unsigned_integer(&[0x00, 0x00, 0x7f]), vec![0x02, 0x01, 0x7f]);
+        assert_eq!(encode_unsigned_integer(&[0x00]), vec![0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_public_key_spki_roundtrip() {
+        let point: Vec<u8> = (1..=64).collect();
+        let der = public_key_to_spki(&point).unwrap();
+        assert_eq!(der.len(), 91);
+        assert_eq!(public_key_from_spki(&der).unwrap(), point);
+
+        let mut with_prefix = vec![0x04];
+        with_prefix.extend_from_slice(&point);
+        assert_eq!(public_key_to_spki(&with_prefix).unwrap(), der);
+        assert!(public_key_to_spki(&point[..63]).is_err());
+        assert!(public_key_from_spki(&der[..90]).is_err());
+    }
+
+    #[test]
+    fn test_public_key_from_certificate() {
+        let point: Vec<u8> = (1..=64).collect();
+        let name = encode_sequence(&[]);
+        let mut tbs = encode_tlv(TAG_CERT_VERSION, &encode_unsigned_integer(&[2]));
+        tbs.extend(encode_unsigned_integer(&[0x01, 0x23]));
+        tbs.extend(encode_sequence(&encode_tlv(TAG_OID, OID_SM2)));
+        tbs.extend(&name);
+        tbs.extend(encode_sequence(&[]));
+        tbs.extend(&name);
+        tbs.extend(public_key_to_spki(&point).unwrap());
+        let mut certificate = encode_sequence(&tbs);
+        certificate.extend(encode_sequence(&encode_tlv(TAG_OID, OID_SM2)));
+        certificate.extend(encode_tlv(TAG_BIT_STRING, &[0x00]));
+
+        assert_eq!(public_key_from_certificate(&encode_sequence(&certificate)).unwrap(), point);
+        assert!(public_key_from_certificate(&encode_sequence(&tbs)).is_err());
+    }
+
+    #[test]
+    fn test_signature_der_roundtrip() {
+        let mut raw = vec![0u8; 64];
+        // r 最高位为 1，s 有前导零
+        raw[0] = 0xff;
+        raw[31] = 0x01;
+        raw[40] = 0x12;
+        raw[63] = 0x34;
+
+        let der = signature_to_der(&raw).unwrap();
+        assert_eq!(der[0], TAG_SEQUENCE);
+
+        let decoded = signature_from_der(&der).unwrap();
+        assert_eq!(decoded, raw);
+    }
+
+    #[test]
+    fn test_signature_from_der_rejects_garbage() {
+        assert!(signature_from_der(&[]).is_err());
+        assert!(signature_from_der(&[0x30, 0x05, 0x02, 0x01]).is_err());
+        // 末尾多余数据
+        let mut der = signature_to_der(&[1u8; 64]).unwrap();
+        der.push(0x00);
+        assert!(signature_from_der(&der).is_err());
+    }
+}
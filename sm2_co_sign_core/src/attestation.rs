@@ -0,0 +1,231 @@
+//! 设备端生成 D1 的密钥证明
+//!
+//! 注册（及重新初始化密钥）时，客户端为新生成的 D1 构造证明声明，交由设备密钥签名后随请求一并提交。设备密钥由
+//! 平台提供（Android Key Attestation、Apple App Attest、TPM AK、设备出厂 SM2 证书等），经 [`DeviceAttestor`]
+//! 回调接入；服务端校验设备证书链与签名后，即可确认 P1 对应的 D1 是在受信设备上生成的。
+//!
+//! 声明编码（版本 1，整数均为大端）：
+//!
+//! ```text
+//! "SM2-COSIGN-ATTEST-V1" || len(subject):u32 || subject || P1（64 字节 x||y）|| generated_at:u64 || protected:u8
+//! ```
+//!
+//! - `subject`：注册时为用户名，重新初始化密钥时为用户 ID
+//! - `generated_at`：D1 生成时间（Unix 秒）
+//! - `protected`：1 表示 D1 生成后立即交由 [`crate::KeyProtector`] 以平台密钥加密保存，明文不离开本进程
+//!
+//! 声明直接绑定 P1，每次注册的 D1 都不同，截获的证据无法用于其他密钥。请求中的 `attestation` 字段为
+//! `{format, statement, signature, certificates}`，后三者均为 Base64，`certificates` 为 DER 证书链、叶证书在前。
+
+use crate::asn1;
+use crate::error::{Error, Result};
+use crate::protocol::{base64_encode, CoSignProtocol};
+use crate::secret::{scalar_from_slice, PublicKey};
+use crate::sm3::{Sm3, SM3_DIGEST_LEN};
+use core::fmt::Debug;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// 声明编码的域分隔串
+pub const ATTESTATION_DOMAIN: &[u8] = b"SM2-COSIGN-ATTEST-V1";
+/// [`Sm2DeviceAttestor`] 的证据格式
+pub const FORMAT_SM2: &str = "sm2";
+
+/// 证明声明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestationStatement {
+    /// 用户名（注册）或用户 ID（重新初始化密钥）
+    pub subject: String,
+    /// 新 D1 对应的 P1（64 字节 x||y）
+    pub p1: Vec<u8>,
+    /// D1 生成时间（Unix 秒）
+    pub generated_at: u64,
+    /// D1 是否只以平台密钥加密的形式保存
+    pub protected: bool,
+}
+
+impl AttestationStatement {
+    /// 以当前时间构造声明，校验 P1 为曲线上的点
+    pub fn new(subject: &str, p1: &[u8], protected: bool) -> Result<Self> {
+        let p1 = PublicKey::from_slice(p1)?.as_bytes().to_vec();
+        let generated_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Ok(Self {
+            subject: subject.to_string(),
+            p1,
+            generated_at,
+            protected,
+        })
+    }
+
+    /// 按版本 1 规则编码，设备密钥签名的即为此字节串
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ATTESTATION_DOMAIN.len() + 4 + self.subject.len() + 64 + 9);
+        out.extend_from_slice(ATTESTATION_DOMAIN);
+        out.extend_from_slice(&(self.subject.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.subject.as_bytes());
+        out.extend_from_slice(&self.p1);
+        out.extend_from_slice(&self.generated_at.to_be_bytes());
+        out.push(u8::from(self.protected));
+        out
+    }
+
+    /// 解析声明编码（服务端校验时使用）
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::Encoding("Malformed attestation statement".to_string());
+        let rest = bytes.strip_prefix(ATTESTATION_DOMAIN).ok_or_else(invalid)?;
+        let (len, rest) = rest.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len().checked_sub(64 + 8 + 1) != Some(len) {
+            return Err(invalid());
+        }
+        let subject = std::str::from_utf8(&rest[..len]).map_err(|_| invalid())?.to_string();
+        let p1 = PublicKey::from_slice(&rest[len..len + 64])?.as_bytes().to_vec();
+        let generated_at = u64::from_be_bytes(rest[len + 64..len + 72].try_into().expect("8 bytes"));
+        let protected = match rest[len + 72] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid()),
+        };
+        Ok(Self { subject, p1, generated_at, protected })
+    }
+
+    /// 声明编码的 SM3 摘要，供只接受固定长度挑战值的平台使用（Android attestation challenge、
+    /// App Attest clientDataHash 等）
+    pub fn digest(&self) -> [u8; SM3_DIGEST_LEN] {
+        let mut hasher = Sm3::new();
+        hasher.update(&self.to_bytes());
+        hasher.finalize()
+    }
+}
+
+/// 设备密钥对声明的签名证据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceEvidence {
+    /// 证据格式，服务端据此选择校验方式（如 `sm2`、`android-key`、`apple-appattest`、`tpm2`）
+    pub format: String,
+    /// 设备密钥的签名（或平台返回的证明对象）
+    pub signature: Vec<u8>,
+    /// 设备证书链（DER，叶证书在前）
+    pub certificates: Vec<Vec<u8>>,
+}
+
+impl DeviceEvidence {
+    /// 注册请求中 `attestation` 字段的 JSON
+    pub fn to_json(&self, statement: &AttestationStatement) -> serde_json::Value {
+        serde_json::json!({
+            "format": self.format,
+            "statement": base64_encode(&statement.to_bytes()),
+            "signature": base64_encode(&self.signature),
+            "certificates": self.certificates.iter().map(|cert| base64_encode(cert)).collect::<Vec<_>>(),
+        })
+    }
+
+    /// 叶证书中的设备公钥（不校验证书链，由服务端对照其信任锚完成）
+    pub fn leaf_public_key(&self) -> Result<PublicKey> {
+        let leaf = self
+            .certificates
+            .first()
+            .ok_or_else(|| Error::InvalidParam("Attestation evidence has no device certificate".to_string()))?;
+        PublicKey::from_slice(&asn1::public_key_from_certificate(leaf)?)
+    }
+}
+
+/// 设备密钥签名回调
+///
+/// 实现以设备密钥签名 [`AttestationStatement::to_bytes`]（或其 [`AttestationStatement::digest`]），
+/// 返回签名与设备证书链；设备密钥不可用或用户取消时返回错误，注册随之中止。
+pub trait DeviceAttestor: Debug + Send + Sync {
+    fn attest(&self, statement: &AttestationStatement) -> Result<DeviceEvidence>;
+}
+
+/// 以 SM2 设备密钥签名的参考实现（格式 `sm2`）
+///
+/// 签名为对声明编码的标准 SM2 签名（默认用户标识），r||s 共 64 字节。设备私钥应存放在安全芯片中，
+/// 此实现持有明文私钥，适用于私钥由安全存储临时取出的场景与测试。
+pub struct Sm2DeviceAttestor {
+    private_key: Zeroizing<Vec<u8>>,
+    certificates: Vec<Vec<u8>>,
+}
+
+impl Sm2DeviceAttestor {
+    /// `certificates` 为设备证书链（DER，叶证书在前）
+    pub fn new(private_key: &[u8], certificates: Vec<Vec<u8>>) -> Result<Self> {
+        scalar_from_slice(private_key, "device private key")?;
+        Ok(Self {
+            private_key: Zeroizing::new(private_key.to_vec()),
+            certificates,
+        })
+    }
+}
+
+impl Debug for Sm2DeviceAttestor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sm2DeviceAttestor")
+            .field("private_key", &crate::types::REDACTED)
+            .field("certificates", &self.certificates.len())
+            .finish()
+    }
+}
+
+impl DeviceAttestor for Sm2DeviceAttestor {
+    fn attest(&self, statement: &AttestationStatement) -> Result<DeviceEvidence> {
+        Ok(DeviceEvidence {
+            format: FORMAT_SM2.to_string(),
+            signature: CoSignProtocol::sign(&self.private_key, &statement.to_bytes())?,
+            certificates: self.certificates.clone(),
+        })
+    }
+}
+
+/// 校验 `sm2` 格式的证据：`device_public_key` 为服务端从已校验的设备证书中取出的公钥
+pub fn verify_sm2_evidence(statement: &[u8], evidence: &DeviceEvidence, device_public_key: &PublicKey) -> Result<bool> {
+    if evidence.format != FORMAT_SM2 {
+        return Err(Error::InvalidParam(format!("Unsupported attestation format {:?}", evidence.format)));
+    }
+    if evidence.signature.len() != 64 {
+        return Ok(false);
+    }
+    CoSignProtocol::verify(device_public_key.as_bytes(), statement, &evidence.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_roundtrip() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let statement = AttestationStatement::new("alice", &p1, true).unwrap();
+
+        let bytes = statement.to_bytes();
+        assert!(bytes.starts_with(ATTESTATION_DOMAIN));
+        assert_eq!(AttestationStatement::from_bytes(&bytes).unwrap(), statement);
+        assert!(AttestationStatement::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(AttestationStatement::new("alice", &[0u8; 64], false).is_err());
+    }
+
+    #[test]
+    fn test_sm2_evidence() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let statement = AttestationStatement::new("alice", &p1, false).unwrap();
+
+        let (device_key, device_public) = CoSignProtocol::generate_keypair();
+        let device_public = PublicKey::from_slice(&device_public).unwrap();
+        let attestor = Sm2DeviceAttestor::new(&device_key, Vec::new()).unwrap();
+        assert!(!format!("{:?}", attestor).contains(&hex::encode(&device_key)));
+
+        let evidence = attestor.attest(&statement).unwrap();
+        assert!(verify_sm2_evidence(&statement.to_bytes(), &evidence, &device_public).unwrap());
+        assert!(evidence.leaf_public_key().is_err());
+
+        // 声明被篡改时校验失败
+        let other = AttestationStatement { protected: true, ..statement.clone() };
+        assert!(!verify_sm2_evidence(&other.to_bytes(), &evidence, &device_public).unwrap());
+
+        let json = evidence.to_json(&statement);
+        assert_eq!(json["format"], FORMAT_SM2);
+        assert_eq!(json["statement"], base64_encode(&statement.to_bytes()));
+    }
+}
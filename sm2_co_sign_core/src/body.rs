@@ -0,0 +1,126 @@
+//! 请求体与响应体的编码
+//!
+//! 默认 JSON。移动端等带宽受限的场景可通过 [`ClientConfig::body_format`](crate::ClientConfig) 改用 CBOR
+//! （RFC 8949，需 `cbor` feature）：请求体以 `Content-Type: application/cbor` 发送，`Accept` 优先请求 CBOR 响应。
+//! 响应按其 `Content-Type` 解析，服务端不支持 CBOR 而返回 JSON 时同样可用。
+//!
+//! 两种编码共用同一套 serde 请求 / 响应结构与 [`ResponseEnvelope`](crate::ResponseEnvelope)，P1、Q1 等二进制字段
+//! 仍按 [`ClientConfig::encoding`](crate::ClientConfig) 以文本字符串传输，不使用 CBOR 字节串。
+
+use crate::error::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// JSON 的媒体类型
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+/// CBOR 的媒体类型
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// 请求体与响应体的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    /// JSON
+    #[default]
+    Json,
+    /// CBOR（需 `cbor` feature）
+    Cbor,
+}
+
+impl BodyFormat {
+    /// 当前构建是否支持该编码
+    pub fn is_supported(self) -> bool {
+        match self {
+            BodyFormat::Json => true,
+            BodyFormat::Cbor => cfg!(feature = "cbor"),
+        }
+    }
+
+    /// 请求的 `Content-Type`
+    pub fn content_type(self) -> &'static str {
+        match self {
+            BodyFormat::Json => JSON_CONTENT_TYPE,
+            BodyFormat::Cbor => CBOR_CONTENT_TYPE,
+        }
+    }
+
+    /// 请求的 `Accept`：CBOR 客户端同时接受 JSON，以兼容不支持 CBOR 的服务端
+    pub fn accept(self) -> &'static str {
+        match self {
+            BodyFormat::Json => JSON_CONTENT_TYPE,
+            BodyFormat::Cbor => "application/cbor, application/json;q=0.5",
+        }
+    }
+
+    /// 由响应的 `Content-Type` 判断编码，忽略参数与大小写，`+json` / `+cbor` 结构化后缀同样识别
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        if media_type == JSON_CONTENT_TYPE || media_type.ends_with("+json") {
+            Some(BodyFormat::Json)
+        } else if media_type == CBOR_CONTENT_TYPE || media_type.ends_with("+cbor") {
+            Some(BodyFormat::Cbor)
+        } else {
+            None
+        }
+    }
+
+    /// 编码请求体
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            BodyFormat::Json => serde_json::to_vec(value).map_err(|e| Error::Encoding(e.to_string())),
+            #[cfg(feature = "cbor")]
+            BodyFormat::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out).map_err(|e| Error::Encoding(e.to_string()))?;
+                Ok(out)
+            }
+            #[cfg(not(feature = "cbor"))]
+            BodyFormat::Cbor => Err(unsupported()),
+        }
+    }
+
+    /// 解码响应体
+    pub fn decode<T: DeserializeOwned>(self, data: &[u8]) -> Result<T> {
+        match self {
+            BodyFormat::Json => serde_json::from_slice(data).map_err(|e| Error::Encoding(e.to_string())),
+            #[cfg(feature = "cbor")]
+            BodyFormat::Cbor => ciborium::from_reader(data).map_err(|e| Error::Encoding(e.to_string())),
+            #[cfg(not(feature = "cbor"))]
+            BodyFormat::Cbor => Err(unsupported()),
+        }
+    }
+}
+
+#[cfg(not(feature = "cbor"))]
+fn unsupported() -> Error {
+    Error::InvalidParam("CBOR body format requires the cbor feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_from_content_type() {
+        assert_eq!(BodyFormat::from_content_type("application/json; charset=utf-8"), Some(BodyFormat::Json));
+        assert_eq!(BodyFormat::from_content_type("Application/CBOR"), Some(BodyFormat::Cbor));
+        assert_eq!(BodyFormat::from_content_type("application/problem+json"), Some(BodyFormat::Json));
+        assert_eq!(BodyFormat::from_content_type("text/html"), None);
+    }
+
+    #[test]
+    fn test_body_roundtrip() {
+        let body = json!({ "code": 0, "message": "ok", "data": { "p1": "AAH7/w==", "generation": 3, "list": [true, null] } });
+        let format = BodyFormat::Json;
+        assert_eq!(format.decode::<Value>(&format.encode(&body).unwrap()).unwrap(), body);
+
+        if BodyFormat::Cbor.is_supported() {
+            let encoded = BodyFormat::Cbor.encode(&body).unwrap();
+            assert_eq!(BodyFormat::Cbor.decode::<Value>(&encoded).unwrap(), body);
+            assert!(encoded.len() < BodyFormat::Json.encode(&body).unwrap().len());
+        } else {
+            assert!(matches!(BodyFormat::Cbor.encode(&body), Err(Error::InvalidParam(_))));
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! SM2 密文解析
+//!
+//! 统一处理三种常见编码，客户端、协议层与 FFI 共用：
+//! - C1C3C2：`04 || x || y || C3 || C2`（GM/T 0003.4-2012）
+//! - C1C2C3：`04 || x || y || C2 || C3`（旧标准及部分厂商实现）
+//! - ASN.1 DER：`SEQUENCE { x INTEGER, y INTEGER, hash OCTET STRING, cipherText OCTET STRING }`（GM/T 0009）
+//!
+//! 原始拼接格式的 0x04 前缀可省略。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::asn1::{self, DerReader};
+use crate::error::{Error, Result};
+use crate::secret::PublicKey;
+
+/// C3 长度（SM3 杂凑值）
+pub const C3_LEN: usize = 32;
+
+/// 原始拼接格式中 C2 与 C3 的顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CiphertextLayout {
+    /// C1 || C3 || C2
+    #[default]
+    C1C3C2,
+    /// C1 || C2 || C3
+    C1C2C3,
+}
+
+/// 解析后的 SM2 密文，C2 借用输入缓冲区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sm2Ciphertext<'a> {
+    /// C1 = k·G（64 字节 x||y，已校验在曲线上）
+    pub c1: [u8; 64],
+    /// C3 = SM3(x2 || M || y2)
+    pub c3: [u8; C3_LEN],
+    /// C2 = M ⊕ t
+    pub c2: &'a [u8],
+}
+
+impl<'a> Sm2Ciphertext<'a> {
+    /// 解析密文：DER 编码自动识别，原始拼接格式按 C1C3C2 解析
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        Self::parse_with_layout(data, CiphertextLayout::C1C3C2)
+    }
+
+    /// 按指定拼接顺序解析原始格式，DER 编码仍自动识别
+    pub fn parse_with_layout(data: &'a [u8], layout: CiphertextLayout) -> Result<Self> {
+        if data.first() == Some(&asn1::TAG_SEQUENCE) {
+            if let Ok(ciphertext) = Self::from_der(data) {
+                return Ok(ciphertext);
+            }
+        }
+        if data.first() == Some(&0x04) {
+            let prefixed = Self::from_raw(&data[1..], layout);
+            // Reason: 省略前缀时 x 的首字节也可能是 0x04，按带前缀解析出的 C1 不在曲线上时再按无前缀解析
+            return prefixed.or_else(|e| Self::from_raw(data, layout).map_err(|_| e));
+        }
+        Self::from_raw(data, layout)
+    }
+
+    /// 解析 GM/T 0009 DER 编码
+    pub fn from_der(der: &'a [u8]) -> Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut inner = DerReader::new(outer.read(asn1::TAG_SEQUENCE)?);
+        if !outer.is_empty() {
+            return Err(Error::Encoding("Trailing data after SM2 ciphertext".to_string()));
+        }
+        let x = asn1::left_pad_32(inner.read_unsigned_integer()?)?;
+        let y = asn1::left_pad_32(inner.read_unsigned_integer()?)?;
+        let c3 = inner.read(asn1::TAG_OCTET_STRING)?;
+        let c2 = inner.read(asn1::TAG_OCTET_STRING)?;
+        if !inner.is_empty() {
+            return Err(Error::Encoding("Unexpected field in SM2 ciphertext".to_string()));
+        }
+
+        let mut c1 = [0u8; 64];
+        c1[..32].copy_from_slice(&x);
+        c1[32..].copy_from_slice(&y);
+        Self::new(c1, c3, c2)
+    }
+
+    /// 由各部分构造，校验 C1 在曲线上、C3 为 32 字节
+    pub fn new(c1: [u8; 64], c3: &[u8], c2: &'a [u8]) -> Result<Self> {
+        PublicKey::from_slice(&c1)?;
+        let c3 = c3
+            .try_into()
+            .map_err(|_| Error::InvalidParam(format!("Invalid C3 length {}, expected {} bytes", c3.len(), C3_LEN)))?;
+        Ok(Self { c1, c3, c2 })
+    }
+
+    /// 不含 0x04 前缀的原始拼接格式
+    fn from_raw(data: &'a [u8], layout: CiphertextLayout) -> Result<Self> {
+        if data.len() < 64 + C3_LEN {
+            return Err(Error::InvalidParam("Ciphertext too short".to_string()));
+        }
+        let (c1, rest) = data.split_at(64);
+        let (c3, c2) = match layout {
+            CiphertextLayout::C1C3C2 => rest.split_at(C3_LEN),
+            CiphertextLayout::C1C2C3 => {
+                let (c2, c3) = rest.split_at(rest.len() - C3_LEN);
+                (c3, c2)
+            }
+        };
+        Self::new(c1.try_into().expect("64 bytes"), c3, c2)
+    }
+
+    /// 编码为带 0x04 前缀的原始拼接格式
+    pub fn to_bytes(&self, layout: CiphertextLayout) -> Vec<u8> {
+        let mut out = Vec::with_capacity(1 + 64 + C3_LEN + self.c2.len());
+        out.push(0x04);
+        out.extend_from_slice(&self.c1);
+        match layout {
+            CiphertextLayout::C1C3C2 => {
+                out.extend_from_slice(&self.c3);
+                out.extend_from_slice(self.c2);
+            }
+            CiphertextLayout::C1C2C3 => {
+                out.extend_from_slice(self.c2);
+                out.extend_from_slice(&self.c3);
+            }
+        }
+        out
+    }
+
+    /// 编码为 GM/T 0009 DER
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut contents = asn1::encode_unsigned_integer(&self.c1[..32]);
+        contents.extend_from_slice(&asn1::encode_unsigned_integer(&self.c1[32..]));
+        contents.extend_from_slice(&asn1::encode_tlv(asn1::TAG_OCTET_STRING, &self.c3));
+        contents.extend_from_slice(&asn1::encode_tlv(asn1::TAG_OCTET_STRING, self.c2));
+        asn1::encode_sequence(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CoSignProtocol;
+
+    fn sample() -> (Vec<u8>, Vec<u8>) {
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        (CoSignProtocol::encrypt(&public_key, b"hello world").unwrap(), public_key)
+    }
+
+    #[test]
+    fn test_parse_c1c3c2() {
+        let (data, _) = sample();
+        let ciphertext = Sm2Ciphertext::parse(&data).unwrap();
+        assert_eq!(ciphertext.c1, data[1..65]);
+        assert_eq!(ciphertext.c3, data[65..97]);
+        assert_eq!(ciphertext.c2, &data[97..]);
+        assert_eq!(ciphertext.to_bytes(CiphertextLayout::C1C3C2), data);
+
+        // 省略 0x04 前缀
+        assert_eq!(Sm2Ciphertext::parse(&data[1..]).unwrap(), ciphertext);
+    }
+
+    #[test]
+    fn test_parse_c1c2c3_and_der() {
+        let (data, _) = sample();
+        let ciphertext = Sm2Ciphertext::parse(&data).unwrap();
+
+        let c1c2c3 = ciphertext.to_bytes(CiphertextLayout::C1C2C3);
+        assert_eq!(&c1c2c3[c1c2c3.len() - 32..], ciphertext.c3);
+        assert_eq!(Sm2Ciphertext::parse_with_layout(&c1c2c3, CiphertextLayout::C1C2C3).unwrap(), ciphertext);
+
+        let der = ciphertext.to_der();
+        assert_eq!(der[0], asn1::TAG_SEQUENCE);
+        assert_eq!(Sm2Ciphertext::parse(&der).unwrap(), ciphertext);
+        assert_eq!(Sm2Ciphertext::from_der(&der).unwrap(), ciphertext);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        let (data, _) = sample();
+        assert!(Sm2Ciphertext::parse(&data[..96]).is_err());
+
+        // C1 不在曲线上
+        let mut bad = data.clone();
+        bad[64] ^= 1;
+        assert!(Sm2Ciphertext::parse(&bad).is_err());
+
+        // DER 末尾多余数据
+        let mut der = Sm2Ciphertext::parse(&data).unwrap().to_der();
+        der.push(0);
+        assert!(Sm2Ciphertext::from_der(&der).is_err());
+    }
+}
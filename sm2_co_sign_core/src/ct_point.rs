@@ -0,0 +1,241 @@
+//! 常数时间标量乘与标量运算
+//!
+//! libsm 的点乘使用与标量取值相关的窗口与分支，耗时和访存模式会泄露标量信息。涉及 D1、k1、
+//! 完整私钥 d 的点乘改由 RustCrypto `sm2` 曲线实现（完备加法公式、常数时间标量运算与查表）完成，
+//! 客户端运行在可能被旁路观测的移动设备上时不泄露密钥分量。
+//! 完成签名时涉及 D1、k1 的模 n 运算同样在此完成，输入输出均为定长数组，不经过堆分配。
+//! 点减、验签等只涉及公开值的运算也在此实现，协议层因此不依赖 libsm，可在 no_std 下编译。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::asn1;
+use crate::error::{Error, Result};
+use sm2::elliptic_curve::ff::{Field, PrimeField};
+use sm2::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use sm2::{AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar};
+use zeroize::Zeroizing;
+
+/// SM2 曲线阶 n（大端）
+const N: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x72, 0x03, 0xdf, 0x6b, 0x21, 0xc6, 0x05, 0x2b, 0x53, 0xbb, 0xf4, 0x09, 0x39, 0xd5, 0x41, 0x23,
+];
+
+/// 计算 k·G，返回 64 字节 x||y
+pub(crate) fn mul_base(k: &[u8]) -> Result<[u8; 64]> {
+    encode(ProjectivePoint::GENERATOR * scalar(k)?)
+}
+
+/// 计算 k·P，P 为 64 字节 x||y，返回 64 字节 x||y
+pub(crate) fn mul_point(k: &[u8], point: &[u8]) -> Result<[u8; 64]> {
+    encode(ProjectivePoint::from(decode(point)?) * scalar(k)?)
+}
+
+/// 校验 64 字节 x||y 为曲线上的点
+pub(crate) fn validate_point(point: &[u8]) -> Result<()> {
+    decode(point).map(|_| ())
+}
+
+/// 计算 P - Q，P、Q 均为 64 字节 x||y，结果为无穷远点时返回错误
+pub(crate) fn sub_point(p: &[u8], q: &[u8]) -> Result<[u8; 64]> {
+    encode(ProjectivePoint::from(decode(p)?) - ProjectivePoint::from(decode(q)?))
+}
+
+/// 计算 P + Q，P、Q 均为 64 字节 x||y，结果为无穷远点时返回错误
+pub(crate) fn add_point(p: &[u8], q: &[u8]) -> Result<[u8; 64]> {
+    encode(ProjectivePoint::from(decode(p)?) + ProjectivePoint::from(decode(q)?))
+}
+
+/// r = (e + x1) mod n 是否成立，x1 为 64 字节点 x||y 的 x 坐标
+pub(crate) fn matches_r(e: &[u8; 32], point: &[u8; 64], r: &[u8; 32]) -> bool {
+    let x1: [u8; 32] = point[..32].try_into().expect("x coordinate is 32 bytes");
+    from_be(r).is_some_and(|r| reduce(e) + reduce(&x1) == r)
+}
+
+/// 计算 a·b mod n，a、b 须位于 [1, n-1]
+pub(crate) fn mul_scalars(a: &[u8], b: &[u8]) -> Result<[u8; 32]> {
+    Ok((scalar(a)? * scalar(b)?).to_repr().into())
+}
+
+/// 基于消息哈希 e 的 SM2 验签：(x1, y1) = s·G + t·P，t = r + s，R = (e + x1) mod n 与 r 比较
+///
+/// r、s 不在 [1, n-1] 内或 t = 0 时返回 `Ok(false)`，公钥不在曲线上时返回 `Error::InvalidPoint`。
+pub(crate) fn verify_digest(public_key: &[u8], e: &[u8; 32], r: &[u8; 32], s: &[u8; 32]) -> Result<bool> {
+    let (Some(r), Some(s)) = (from_be(r), from_be(s)) else {
+        return Ok(false);
+    };
+    let t = r + s;
+    if bool::from(r.is_zero() | s.is_zero() | t.is_zero()) {
+        return Ok(false);
+    }
+    let point = ProjectivePoint::from(decode(public_key)?);
+    let Ok(sum) = encode(ProjectivePoint::GENERATOR * s + point * t) else {
+        return Ok(false);
+    };
+    let x1: [u8; 32] = sum[..32].try_into().expect("x coordinate is 32 bytes");
+    Ok(reduce(e) + reduce(&x1) == r)
+}
+
+/// 32 字节大端是否为 [1, n-1] 内的标量
+pub(crate) fn is_valid_scalar(bytes: &[u8; 32]) -> bool {
+    from_be(bytes).is_some_and(|scalar| !bool::from(scalar.is_zero()))
+}
+
+/// 完成协同签名：s = (k1·s2 + s3 - r·d1) · d1⁻¹ mod n
+///
+/// 调用方需已校验各输入位于 [1, n-1]。
+pub(crate) fn complete_s(k1: &[u8; 32], d1: &[u8; 32], r: &[u8; 32], s2: &[u8; 32], s3: &[u8; 32]) -> Result<[u8; 32]> {
+    let parse = |bytes| from_be(bytes).ok_or_else(|| Error::Crypto("Scalar is not less than the curve order n".to_string()));
+    let (k1, d1, r, s2, s3) = (parse(k1)?, parse(d1)?, parse(r)?, parse(s2)?, parse(s3)?);
+    let d1_inv = Option::<Scalar>::from(d1.invert()).ok_or_else(|| Error::Crypto("D1 is zero".to_string()))?;
+    Ok(((k1 * s2 + s3 - r * d1) * d1_inv).to_repr().into())
+}
+
+/// 签名是否退化：r ≡ 0、s ≡ 0 或 r + s ≡ 0 (mod n)，不小于 n 的输入先取模
+pub(crate) fn is_degenerate(r: &[u8; 32], s: &[u8; 32]) -> bool {
+    let (r, s) = (reduce(r), reduce(s));
+    bool::from(r.is_zero() | s.is_zero() | (r + s).is_zero())
+}
+
+/// 32 字节大端转为标量，不小于 n 时返回 None
+fn from_be(bytes: &[u8; 32]) -> Option<Scalar> {
+    Scalar::from_repr(FieldBytes::from(*bytes)).into()
+}
+
+/// 32 字节大端按 n 取模
+///
+/// Reason: n > 2^255，任意 32 字节值小于 2n，至多减一次 n；只用于 e、x1、签名分量等公开值，不要求常数时间
+fn reduce(bytes: &[u8; 32]) -> Scalar {
+    from_be(bytes).unwrap_or_else(|| {
+        let mut diff = [0u8; 32];
+        let mut borrow = 0u16;
+        for i in (0..32).rev() {
+            let value = u16::from(bytes[i]).wrapping_sub(u16::from(N[i])).wrapping_sub(borrow);
+            diff[i] = value as u8;
+            borrow = (value >> 8) & 1;
+        }
+        from_be(&diff).expect("value below 2n reduces below n")
+    })
+}
+
+/// 大端字节转为 [1, n-1] 内的标量
+fn scalar(k: &[u8]) -> Result<Scalar> {
+    let bytes = Zeroizing::new(asn1::left_pad_32(k).map_err(|_| Error::Crypto("Scalar longer than 32 bytes".to_string()))?);
+    let scalar = from_be(&bytes).ok_or_else(|| Error::Crypto("Scalar is not less than the curve order n".to_string()))?;
+    if bool::from(scalar.is_zero()) {
+        return Err(Error::Crypto("Scalar is zero".to_string()));
+    }
+    Ok(scalar)
+}
+
+/// 64 字节 x||y 转为曲线点
+fn decode(point: &[u8]) -> Result<AffinePoint> {
+    if point.len() != 64 {
+        return Err(Error::InvalidPoint("Invalid point length, expected 64 bytes".to_string()));
+    }
+    let mut tagged = [0u8; 65];
+    tagged[0] = 0x04;
+    tagged[1..].copy_from_slice(point);
+    let encoded = EncodedPoint::from_bytes(tagged).map_err(|e| Error::InvalidPoint(e.to_string()))?;
+    Option::from(AffinePoint::from_encoded_point(&encoded))
+        .ok_or_else(|| Error::InvalidPoint("Point is not on the SM2 curve".to_string()))
+}
+
+/// 曲线点转为 64 字节 x||y，无穷远点返回错误
+fn encode(point: ProjectivePoint) -> Result<[u8; 64]> {
+    let encoded = point.to_affine().to_encoded_point(false);
+    encoded
+        .as_bytes()
+        .strip_prefix(&[0x04])
+        .and_then(|coords| coords.try_into().ok())
+        .ok_or_else(|| Error::Crypto("Point multiplication resulted in the point at infinity".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CoSignProtocol;
+    use crate::simulator::scalar_bytes;
+    use libsm::sm2::ecc::EccCtx;
+    use libsm::sm2::field::FieldElem;
+    use num_bigint::BigUint;
+
+    fn libsm_coords(ecc: &EccCtx, point: &libsm::sm2::ecc::Point) -> [u8; 64] {
+        let (x, y) = ecc.to_affine(point).unwrap();
+        let mut out = [0u8; 64];
+        out[..32].copy_from_slice(&scalar_bytes(&BigUint::from_bytes_be(&x.to_bytes())));
+        out[32..].copy_from_slice(&scalar_bytes(&BigUint::from_bytes_be(&y.to_bytes())));
+        out
+    }
+
+    #[test]
+    fn test_matches_libsm() {
+        let ecc = EccCtx::new();
+        for _ in 0..8 {
+            let k = ecc.random_uint();
+            let k_bytes = scalar_bytes(&k);
+            let expected = libsm_coords(&ecc, &ecc.g_mul(&k).unwrap());
+            assert_eq!(mul_base(&k_bytes).unwrap(), expected);
+
+            let other = CoSignProtocol::generate_keypair().1;
+            let x = FieldElem::from_bytes(&other[..32]).unwrap();
+            let y = FieldElem::from_bytes(&other[32..]).unwrap();
+            let point = ecc.new_point(&x, &y).unwrap();
+            let expected = libsm_coords(&ecc, &ecc.mul(&k, &point).unwrap());
+            assert_eq!(mul_point(&k_bytes, &other).unwrap(), expected);
+            // 去掉前导零的短标量结果相同
+            assert_eq!(mul_base(&k.to_bytes_be()).unwrap(), mul_base(&k_bytes).unwrap());
+
+            // 2k·G - k·G = k·G
+            let double = scalar_bytes(&((&k + &k) % ecc.get_n()));
+            let kg = mul_base(&k_bytes).unwrap();
+            assert_eq!(sub_point(&mul_base(&double).unwrap(), &kg).unwrap(), kg);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        assert!(matches!(mul_base(&[0u8; 32]), Err(Error::Crypto(_))));
+        assert!(matches!(mul_base(&[0xffu8; 32]), Err(Error::Crypto(_))));
+        assert!(matches!(mul_base(&[1u8; 33]), Err(Error::Crypto(_))));
+
+        let mut off_curve = [0u8; 64];
+        off_curve[31] = 1;
+        off_curve[63] = 1;
+        assert!(matches!(mul_point(&[1u8; 32], &off_curve), Err(Error::InvalidPoint(_))));
+        assert!(matches!(validate_point(&off_curve), Err(Error::InvalidPoint(_))));
+
+        let g = mul_base(&[1u8]).unwrap();
+        assert!(validate_point(&g).is_ok());
+        assert!(matches!(sub_point(&g, &g), Err(Error::Crypto(_))));
+    }
+
+    #[test]
+    fn test_scalar_arithmetic_matches_biguint() {
+        let ecc = EccCtx::new();
+        let n = ecc.get_n();
+        let [k1, d1, r, s2, s3] = [(); 5].map(|_| ecc.random_uint());
+        let expected = ((&k1 * &s2 + &s3 + n - (&r * &d1) % n) % n * d1.modpow(&(n - BigUint::from(2u32)), n)) % n;
+        let s = complete_s(&scalar_bytes(&k1), &scalar_bytes(&d1), &scalar_bytes(&r), &scalar_bytes(&s2), &scalar_bytes(&s3)).unwrap();
+        assert_eq!(s, scalar_bytes(&expected));
+
+        assert!(is_valid_scalar(&scalar_bytes(&k1)));
+        assert!(!is_valid_scalar(&[0u8; 32]));
+        assert!(!is_valid_scalar(&[0xffu8; 32]));
+
+        let expected = (&d1 * &k1) % n;
+        assert_eq!(mul_scalars(&scalar_bytes(&d1), &scalar_bytes(&k1)).unwrap(), scalar_bytes(&expected));
+        assert!(mul_scalars(&scalar_bytes(&d1), &[0u8; 32]).is_err());
+
+        let r_bytes = scalar_bytes(&r);
+        assert_eq!(N[..], n.to_bytes_be()[..]);
+        assert!(is_degenerate(&r_bytes, &scalar_bytes(&(n - &r))));
+        assert!(is_degenerate(&r_bytes, &[0u8; 32]));
+        assert!(is_degenerate(&r_bytes, &N));
+        assert!(!is_degenerate(&r_bytes, &r_bytes));
+        // 不小于 n 的分量按取模后的值判断
+        let all_ones = BigUint::from_bytes_be(&[0xffu8; 32]);
+        assert_eq!(reduce(&[0xffu8; 32]), from_be(&scalar_bytes(&(&all_ones % n))).unwrap());
+        assert!(is_degenerate(&scalar_bytes(&(n + n - &all_ones)), &[0xffu8; 32]));
+    }
+}
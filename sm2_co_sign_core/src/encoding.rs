@@ -0,0 +1,135 @@
+//! 二进制数据的编码
+//!
+//! 协议层辅助函数、客户端请求与响应中的二进制字段、CLI 的输入输出格式与 FFI 编解码函数统一使用
+//! [`Encoding`]。文本编码为十六进制、Base64 与 Base64URL，[`Encoding::Raw`] 表示原始字节，只用于文件与缓冲区。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::{Error, Result};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+    Engine,
+};
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// 二进制数据的编码方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// 十六进制（编码为小写，解码大小写均可）
+    Hex,
+    /// 标准 Base64（带填充）
+    #[default]
+    Base64,
+    /// URL 安全字符集的 Base64（无填充）
+    Base64Url,
+    /// 原始字节
+    Raw,
+}
+
+impl Encoding {
+    /// 全部编码方式
+    pub const ALL: [Encoding; 4] = [Encoding::Hex, Encoding::Base64, Encoding::Base64Url, Encoding::Raw];
+
+    /// 名称（`hex` / `base64` / `base64url` / `raw`），与 [`FromStr`] 及 serde 表示一致
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Raw => "raw",
+        }
+    }
+
+    /// 是否为文本编码
+    pub fn is_text(self) -> bool {
+        self != Encoding::Raw
+    }
+
+    /// 编码为文本；原始字节没有文本形式，返回 [`Error::InvalidParam`]
+    pub fn encode_str(self, data: &[u8]) -> Result<String> {
+        match self {
+            Encoding::Hex => Ok(hex::encode(data)),
+            Encoding::Base64 => Ok(BASE64.encode(data)),
+            Encoding::Base64Url => Ok(BASE64_URL.encode(data)),
+            Encoding::Raw => Err(Error::InvalidParam("Raw encoding has no text representation".to_string())),
+        }
+    }
+
+    /// 解码文本（不忽略空白）；原始字节即取文本的 UTF-8 字节
+    pub fn decode_str(self, text: &str) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Hex => hex::decode(text).map_err(|e| Error::Encoding(e.to_string())),
+            Encoding::Base64 => BASE64.decode(text).map_err(|e| Error::Encoding(e.to_string())),
+            Encoding::Base64Url => BASE64_URL.decode(text).map_err(|e| Error::Encoding(e.to_string())),
+            Encoding::Raw => Ok(text.as_bytes().to_vec()),
+        }
+    }
+
+    /// 编码为字节：文本编码为其 ASCII 字节，原始字节原样返回
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        match self.encode_str(data) {
+            Ok(text) => text.into_bytes(),
+            Err(_) => data.to_vec(),
+        }
+    }
+
+    /// 解码文件、缓冲区中的数据，文本编码忽略首尾空白（如末尾换行）
+    pub fn decode(self, data: &[u8]) -> Result<Vec<u8>> {
+        if self == Encoding::Raw {
+            return Ok(data.to_vec());
+        }
+        let text = core::str::from_utf8(data).map_err(|_| Error::Encoding(format!("{} input is not valid UTF-8", self)))?;
+        self.decode_str(text.trim())
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::InvalidParam(format!("Unknown encoding {:?}, expected hex, base64, base64url or raw", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = [0x00u8, 0x01, 0xfb, 0xff];
+        for encoding in Encoding::ALL {
+            assert_eq!(encoding.decode(&encoding.encode(&data)).unwrap(), data);
+            assert_eq!(encoding.name().parse::<Encoding>().unwrap(), encoding);
+        }
+        assert_eq!(Encoding::Hex.encode_str(&data).unwrap(), "0001fbff");
+        assert_eq!(Encoding::Base64.encode_str(&data).unwrap(), "AAH7/w==");
+        assert_eq!(Encoding::Base64Url.encode_str(&data).unwrap(), "AAH7_w");
+        assert!(matches!(Encoding::Raw.encode_str(&data), Err(Error::InvalidParam(_))));
+
+        // 文件内容忽略末尾换行，线上字段不忽略
+        assert_eq!(Encoding::Base64.decode(b"AAH7/w==\n").unwrap(), data);
+        assert!(Encoding::Base64.decode_str("AAH7/w==\n").is_err());
+        assert!(matches!(Encoding::Hex.decode(b"zz"), Err(Error::Encoding(_))));
+        assert!("pem".parse::<Encoding>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_serde_names() {
+        assert_eq!(serde_json::to_value(Encoding::Base64Url).unwrap(), "base64url");
+        assert_eq!(serde_json::from_value::<Encoding>("hex".into()).unwrap(), Encoding::Hex);
+    }
+}
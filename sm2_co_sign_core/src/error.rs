@@ -1,5 +1,7 @@
 //! 错误类型定义
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 use thiserror::Error;
 
 /// 错误类型
@@ -13,10 +15,31 @@ pub enum Error {
     #[error("Network error: {0}")]
     Network(String),
 
+    /// HTTP 传输错误（DNS 解析、TLS 握手、连接被拒绝、超时、响应解析等），保留底层错误
+    #[cfg(feature = "std")]
+    #[error("Network error: {context}")]
+    Transport {
+        context: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// 服务端返回非 2xx 状态码，携带请求地址、状态码与截断后的响应体
+    #[error("HTTP {status} from {endpoint}: {body}")]
+    Http { endpoint: String, status: u16, body: String },
+
     /// API 错误
     #[error("API error (code {code}): {message}")]
     Api { code: i32, message: String },
 
+    /// 服务端返回的协议数据不合法（长度、取值范围等），`field` 为出错的字段名
+    #[error("Invalid server response: {field} {reason}")]
+    InvalidServerResponse { field: String, reason: String },
+
+    /// 无效的椭圆曲线点
+    #[error("Invalid curve point: {0}")]
+    InvalidPoint(String),
+
     /// 参数错误
     #[error("Invalid parameter: {0}")]
     InvalidParam(String),
@@ -34,9 +57,208 @@ pub enum Error {
     NotAuthenticated,
 
     /// IO 错误
+    #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
+/// 错误分类（与 [`Error`] 的变体一一对应，不携带详细信息）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Crypto,
+    Network,
+    Http,
+    Api,
+    InvalidServerResponse,
+    InvalidPoint,
+    InvalidParam,
+    InvalidState,
+    Encoding,
+    NotAuthenticated,
+    Io,
+}
+
+impl ErrorKind {
+    /// 小写下划线形式的名称（如 `invalid_param`），用于日志与 JSON 输出
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Crypto => "crypto",
+            ErrorKind::Network => "network",
+            ErrorKind::Http => "http",
+            ErrorKind::Api => "api",
+            ErrorKind::InvalidServerResponse => "invalid_server_response",
+            ErrorKind::InvalidPoint => "invalid_point",
+            ErrorKind::InvalidParam => "invalid_param",
+            ErrorKind::InvalidState => "invalid_state",
+            ErrorKind::Encoding => "encoding",
+            ErrorKind::NotAuthenticated => "not_authenticated",
+            ErrorKind::Io => "io",
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 视为暂时性故障的 HTTP 状态码 / 服务端业务错误码（请求超时、限流、网关错误、服务不可用）
+const RETRYABLE_CODES: [i32; 5] = [408, 429, 502, 503, 504];
+
+/// `Error::Http` 中保留的响应体最大字节数
+pub const HTTP_BODY_LIMIT: usize = 512;
+
+impl Error {
+    /// 构造 `Error::InvalidServerResponse`
+    pub fn invalid_server_response(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Error::InvalidServerResponse {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// 构造 `Error::Http`，响应体超过 [`HTTP_BODY_LIMIT`] 时按字符边界截断
+    pub fn http(endpoint: impl Into<String>, status: u16, body: &str) -> Self {
+        let body = body.trim();
+        let body = if body.len() > HTTP_BODY_LIMIT {
+            let mut end = HTTP_BODY_LIMIT;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}...({} bytes total)", &body[..end], body.len())
+        } else {
+            body.to_string()
+        };
+        Error::Http {
+            endpoint: endpoint.into(),
+            status,
+            body,
+        }
+    }
+
+    /// 错误分类
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Crypto(_) => ErrorKind::Crypto,
+            Error::Network(_) => ErrorKind::Network,
+            #[cfg(feature = "std")]
+            Error::Transport { .. } => ErrorKind::Network,
+            Error::Http { .. } => ErrorKind::Http,
+            Error::Api { .. } => ErrorKind::Api,
+            Error::InvalidServerResponse { .. } => ErrorKind::InvalidServerResponse,
+            Error::InvalidPoint(_) => ErrorKind::InvalidPoint,
+            Error::InvalidParam(_) => ErrorKind::InvalidParam,
+            Error::InvalidState(_) => ErrorKind::InvalidState,
+            Error::Encoding(_) => ErrorKind::Encoding,
+            Error::NotAuthenticated => ErrorKind::NotAuthenticated,
+            #[cfg(feature = "std")]
+            Error::Io(_) => ErrorKind::Io,
+        }
+    }
+
+    /// 是否为暂时性故障，原样重试可能成功
+    ///
+    /// 网络错误、服务端限流/不可用及超时类 IO 错误可重试；密码学、参数、认证等错误重试结果不变，
+    /// 应直接返回给调用方。
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network(_) => true,
+            #[cfg(feature = "std")]
+            Error::Transport { source, .. } => transport_retryable(source.as_ref()),
+            Error::Http { status, .. } => RETRYABLE_CODES.contains(&i32::from(*status)),
+            Error::Api { code, .. } => RETRYABLE_CODES.contains(code),
+            #[cfg(feature = "std")]
+            Error::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+            ),
+            Error::Crypto(_)
+            | Error::InvalidServerResponse { .. }
+            | Error::InvalidPoint(_)
+            | Error::InvalidParam(_)
+            | Error::InvalidState(_)
+            | Error::Encoding(_)
+            | Error::NotAuthenticated => false,
+        }
+    }
+}
+
+/// 传输错误是否可重试：请求构造失败与响应解析失败重试结果不变
+#[cfg(feature = "std")]
+fn transport_retryable(source: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    #[cfg(feature = "client")]
+    if let Some(e) = source.downcast_ref::<reqwest::Error>() {
+        return !(e.is_builder() || e.is_decode() || e.is_redirect());
+    }
+    let _ = source;
+    true
+}
+
 /// 结果类型
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(Error::Network("timeout".to_string()).kind(), ErrorKind::Network);
+        assert_eq!(Error::NotAuthenticated.kind().as_str(), "not_authenticated");
+        assert_eq!(Error::InvalidParam("x".to_string()).kind().to_string(), "invalid_param");
+    }
+
+    #[test]
+    fn test_transport_keeps_source() {
+        use std::error::Error as _;
+
+        let err = Error::Transport {
+            context: "Request failed".to_string(),
+            source: Box::new(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused")),
+        };
+        assert_eq!(err.to_string(), "Network error: Request failed");
+        assert_eq!(err.source().unwrap().to_string(), "connection refused");
+    }
+
+    #[test]
+    fn test_http_error() {
+        let err = Error::http("https://cosign.example.com/api/login", 502, " Bad Gateway\n");
+        assert_eq!(err.to_string(), "HTTP 502 from https://cosign.example.com/api/login: Bad Gateway");
+        assert_eq!(err.kind(), ErrorKind::Http);
+        assert!(err.is_retryable());
+        assert!(!Error::http("/api/sign", 400, "").is_retryable());
+
+        // 截断不会切开多字节字符
+        let body = "错".repeat(HTTP_BODY_LIMIT);
+        let Error::Http { body: truncated, .. } = Error::http("/api/sign", 500, &body) else {
+            unreachable!()
+        };
+        assert!(truncated.len() < body.len());
+        assert!(truncated.starts_with("错错"));
+        assert!(truncated.ends_with(&format!("...({} bytes total)", body.len())));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(Error::Network("connection refused".to_string()).is_retryable());
+        assert!(Error::Api { code: 503, message: "busy".to_string() }.is_retryable());
+        assert!(Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut)).is_retryable());
+        let transport = Error::Transport {
+            context: "Request failed".to_string(),
+            source: Box::new(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+        };
+        assert_eq!(transport.kind(), ErrorKind::Network);
+        assert!(transport.is_retryable());
+
+        assert!(!Error::Api { code: 401, message: "invalid token".to_string() }.is_retryable());
+        assert!(!Error::Io(std::io::Error::from(std::io::ErrorKind::NotFound)).is_retryable());
+        assert!(!Error::Crypto("bad".to_string()).is_retryable());
+        assert!(!Error::NotAuthenticated.is_retryable());
+    }
+}
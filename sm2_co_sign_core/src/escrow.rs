@@ -0,0 +1,228 @@
+//! D1 的双人控制托管导出
+//!
+//! 监管要求私钥可恢复的部署中，D1 可以托管给两名托管员：D1 拆分为两个异或份额，分别以两名托管员的
+//! SM2 公钥加密，任何一名托管员单独解开自己的份额都得不到关于 D1 的任何信息，两人的私钥同时参与才能恢复。
+//!
+//! 份额明文（版本 1）：
+//!
+//! ```text
+//! "SM2-COSIGN-ESCROW-V1" || index:u8 || P1（64 字节 x||y）|| share（32 字节）
+//! share_a = 随机 32 字节，share_b = D1 ⊕ share_a
+//! ```
+//!
+//! 份额绑定序号与 P1，不同托管包或同一托管包的两个份额之间无法互换；合并后重新计算 D1·G 与托管包中的
+//! P1 比对，确认恢复出的正是托管时的 D1。托管包只包含公钥与密文，可以按普通文件保管。
+//!
+//! 托管的是导出时的 D1：密钥分量刷新（`key rotate`）后旧 D1 作废，需重新托管。
+
+use crate::ct_point;
+use crate::error::{Error, Result};
+use crate::protocol::{base64_decode, base64_encode, fill_random, CoSignProtocol};
+use crate::secret::{scalar_from_slice, PublicKey, D1};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// 托管包格式版本
+pub const ESCROW_VERSION: u32 = 1;
+/// 份额明文的域分隔串
+pub const ESCROW_DOMAIN: &[u8] = b"SM2-COSIGN-ESCROW-V1";
+/// 托管员人数
+pub const ESCROW_OFFICERS: usize = 2;
+
+/// 以一名托管员公钥加密的份额
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowShare {
+    /// 托管员公钥
+    pub officer: PublicKey,
+    /// 份额明文的 SM2 密文（C1C3C2，Base64）
+    pub ciphertext: String,
+}
+
+/// 托管包
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowPackage {
+    pub version: u32,
+    pub user_id: String,
+    /// 协同公钥
+    pub public_key: PublicKey,
+    /// 托管的 D1 对应的 P1，用于校验恢复结果
+    pub p1: PublicKey,
+    /// 托管时间（Unix 秒）
+    pub created_at: u64,
+    /// 两名托管员的份额，顺序即份额序号
+    pub shares: Vec<EscrowShare>,
+}
+
+impl EscrowPackage {
+    /// 拆分 D1 并分别加密给两名托管员
+    pub fn seal(d1: &[u8], user_id: &str, public_key: &[u8], officers: [&[u8]; ESCROW_OFFICERS]) -> Result<Self> {
+        let d1 = Zeroizing::new(scalar_from_slice(d1, "D1")?);
+        let public_key = PublicKey::from_slice(public_key)?;
+        let officer_a = PublicKey::from_slice(officers[0])?;
+        let officer_b = PublicKey::from_slice(officers[1])?;
+        // Reason: 两个份额加密给同一把公钥时一人即可恢复，失去双人控制的意义
+        if officer_a == officer_b {
+            return Err(Error::InvalidParam("Escrow officers must use different public keys".to_string()));
+        }
+        let p1 = PublicKey::from_slice(&ct_point::mul_base(&d1[..])?)?;
+
+        let mut share_a = Zeroizing::new([0u8; 32]);
+        fill_random(&mut share_a[..])?;
+        let mut share_b = Zeroizing::new([0u8; 32]);
+        for (b, (d, a)) in share_b.iter_mut().zip(d1.iter().zip(share_a.iter())) {
+            *b = d ^ a;
+        }
+
+        let shares = [(officer_a, share_a), (officer_b, share_b)]
+            .into_iter()
+            .enumerate()
+            .map(|(index, (officer, share))| {
+                let plaintext = Zeroizing::new(share_plaintext(index, &p1, &share));
+                let ciphertext = CoSignProtocol::encrypt(officer.as_bytes(), &plaintext)?;
+                Ok(EscrowShare { officer, ciphertext: base64_encode(&ciphertext) })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version: ESCROW_VERSION,
+            user_id: user_id.to_string(),
+            public_key,
+            p1,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            shares,
+        })
+    }
+
+    /// 解析托管包 JSON
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        let package: Self = serde_json::from_slice(data).map_err(|e| Error::Encoding(e.to_string()))?;
+        if package.version != ESCROW_VERSION {
+            return Err(Error::Encoding(format!("Unsupported escrow package version {}", package.version)));
+        }
+        if package.shares.len() != ESCROW_OFFICERS {
+            return Err(Error::Encoding(format!(
+                "Escrow package must contain {} shares, found {}",
+                ESCROW_OFFICERS,
+                package.shares.len()
+            )));
+        }
+        Ok(package)
+    }
+
+    /// 编码为 JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(|e| Error::Encoding(e.to_string()))
+    }
+
+    /// 托管员以私钥解开自己的份额；`index` 为份额序号（0 或 1）
+    pub fn decrypt_share(&self, index: usize, officer_private_key: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+        let share = self
+            .shares
+            .get(index)
+            .ok_or_else(|| Error::InvalidParam(format!("Escrow share index {} out of range", index)))?;
+        let officer_private_key = Zeroizing::new(scalar_from_slice(officer_private_key, "officer private key")?);
+        // Reason: 私钥与份额不对应时解密必然失败，先比对公钥给出明确的错误
+        if ct_point::mul_base(&officer_private_key[..])? != share.officer.as_bytes() {
+            return Err(Error::InvalidParam(format!("Private key does not belong to escrow officer {}", index)));
+        }
+
+        let ciphertext = base64_decode(&share.ciphertext)?;
+        let plaintext = CoSignProtocol::decrypt(&officer_private_key[..], &ciphertext)?
+            .map(Zeroizing::new)
+            .ok_or_else(|| Error::Crypto(format!("Failed to decrypt escrow share {}", index)))?;
+
+        let expected = share_plaintext(index, &self.p1, &[0u8; 32]);
+        let prefix = expected.len() - 32;
+        if plaintext.len() != expected.len() || plaintext[..prefix] != expected[..prefix] {
+            return Err(Error::Crypto(format!("Escrow share {} is not bound to this package", index)));
+        }
+        let mut out = Zeroizing::new([0u8; 32]);
+        out.copy_from_slice(&plaintext[prefix..]);
+        Ok(out)
+    }
+
+    /// 合并两个份额，校验恢复出的 D1 与托管包中的 P1 一致
+    pub fn combine(&self, share_a: &[u8; 32], share_b: &[u8; 32]) -> Result<D1> {
+        let mut d1 = Zeroizing::new([0u8; 32]);
+        for (d, (a, b)) in d1.iter_mut().zip(share_a.iter().zip(share_b.iter())) {
+            *d = a ^ b;
+        }
+        let d1 = D1::from_slice(&d1[..])
+            .map_err(|_| Error::Crypto("Escrow shares do not reconstruct a valid D1".to_string()))?;
+        if ct_point::mul_base(d1.as_bytes())? != self.p1.as_bytes() {
+            return Err(Error::Crypto("Escrow shares do not reconstruct the escrowed D1".to_string()));
+        }
+        Ok(d1)
+    }
+
+    /// 以两名托管员的私钥恢复 D1
+    pub fn recover(&self, officer_keys: [&[u8]; ESCROW_OFFICERS]) -> Result<D1> {
+        let share_a = self.decrypt_share(0, officer_keys[0])?;
+        let share_b = self.decrypt_share(1, officer_keys[1])?;
+        self.combine(&share_a, &share_b)
+    }
+}
+
+/// 份额明文：域分隔串 || 序号 || P1 || 份额
+fn share_plaintext(index: usize, p1: &PublicKey, share: &[u8; 32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(ESCROW_DOMAIN.len() + 1 + 64 + 32);
+    out.extend_from_slice(ESCROW_DOMAIN);
+    out.push(index as u8);
+    out.extend_from_slice(p1.as_bytes());
+    out.extend_from_slice(share);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escrow() -> (D1, EscrowPackage, [(Vec<u8>, Vec<u8>); 2]) {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        let officers = [CoSignProtocol::generate_keypair(), CoSignProtocol::generate_keypair()];
+        let officer_keys = [officers[0].1.as_slice(), officers[1].1.as_slice()];
+        let package = EscrowPackage::seal(d1.as_bytes(), "user-1", &public_key, officer_keys).unwrap();
+        (d1, package, officers)
+    }
+
+    #[test]
+    fn test_escrow_roundtrip() {
+        let (d1, package, officers) = escrow();
+        let package = EscrowPackage::from_json(&package.to_json().unwrap()).unwrap();
+        assert_eq!(package.user_id, "user-1");
+
+        let recovered = package.recover([officers[0].0.as_slice(), officers[1].0.as_slice()]).unwrap();
+        assert_eq!(recovered.as_bytes(), d1.as_bytes());
+
+        // 单个份额与 D1 无关
+        let share_a = package.decrypt_share(0, &officers[0].0).unwrap();
+        assert_ne!(&share_a[..], d1.as_bytes());
+    }
+
+    #[test]
+    fn test_escrow_requires_both_officers() {
+        let (_, package, officers) = escrow();
+
+        // 私钥与份额不对应
+        assert!(matches!(package.decrypt_share(1, &officers[0].0), Err(Error::InvalidParam(_))));
+        assert!(package.recover([officers[1].0.as_slice(), officers[0].0.as_slice()]).is_err());
+
+        // 其他托管包的份额无法混用
+        let (_, other, other_officers) = escrow();
+        let share_a = package.decrypt_share(0, &officers[0].0).unwrap();
+        let share_b = other.decrypt_share(1, &other_officers[1].0).unwrap();
+        assert!(matches!(package.combine(&share_a, &share_b), Err(Error::Crypto(_))));
+
+        let mut swapped = package.clone();
+        swapped.shares.swap(0, 1);
+        assert!(matches!(swapped.decrypt_share(0, &officers[1].0), Err(Error::Crypto(_))));
+
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        let d1 = CoSignProtocol::new().unwrap().generate_d1().unwrap();
+        let same = [officers[0].1.as_slice(), officers[0].1.as_slice()];
+        assert!(EscrowPackage::seal(d1.as_bytes(), "user-1", &public_key, same).is_err());
+    }
+}
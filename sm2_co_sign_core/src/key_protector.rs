@@ -0,0 +1,102 @@
+//! D1 的平台密钥保护
+//!
+//! 配置 [`KeyProtector`] 后，客户端内存中只保存平台密钥（iOS Secure Enclave、Android Keystore 等）加密后的
+//! D1（下称 wrapped D1）：每次签名、解密前调用 [`KeyProtector::unwrap`] 临时解开，计算完成后明文随 [`D1`]
+//! 一起清零；注册、初始化、刷新得到的新 D1 立即经 [`KeyProtector::wrap`] 重新加密，只保留 wrapped 形式。
+//!
+//! `unwrap` 可以要求用户验证（Face ID / Touch ID），验证失败或取消时返回错误，签名或解密随之中止。
+//! 经 C 接口接入的宿主使用 FFI 的 `cosign_key_protector_t` 回调，语义相同；Secure Enclave 的 Swift 参考实现见
+//! `sm2_co_sign_ffi/ios/SecureEnclaveKeyProtector.swift`。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::Result;
+use crate::secret::D1;
+use core::fmt::Debug;
+
+/// D1 的加密 / 解密回调
+///
+/// 实现不应缓存明文 D1；错误（平台密钥不可用、用户取消验证等）建议以 `Error::Crypto` 返回，原样传给调用方。
+pub trait KeyProtector: Debug + Send + Sync {
+    /// 用平台密钥加密 D1，返回 wrapped D1
+    fn wrap(&self, d1: &D1) -> Result<Vec<u8>>;
+
+    /// 解开 wrapped D1，返回的 [`D1`] 用完即丢弃
+    fn unwrap(&self, wrapped: &[u8]) -> Result<D1>;
+}
+
+/// 客户端保存的 D1：未配置 [`KeyProtector`] 时为明文，配置后为 wrapped D1
+#[cfg(feature = "client")]
+#[derive(Clone)]
+pub(crate) enum StoredD1 {
+    Clear(D1),
+    Wrapped(Vec<u8>),
+}
+
+#[cfg(feature = "client")]
+impl StoredD1 {
+    /// 按配置保存新的 D1
+    pub(crate) fn seal(d1: &D1, protector: Option<&dyn KeyProtector>) -> Result<Self> {
+        match protector {
+            Some(protector) => Ok(StoredD1::Wrapped(protector.wrap(d1)?)),
+            None => Ok(StoredD1::Clear(d1.clone())),
+        }
+    }
+
+    /// 取得明文 D1；wrapped D1 经 `protector` 解开
+    pub(crate) fn open(&self, protector: Option<&dyn KeyProtector>) -> Result<D1> {
+        match (self, protector) {
+            (StoredD1::Clear(d1), _) => Ok(d1.clone()),
+            (StoredD1::Wrapped(wrapped), Some(protector)) => protector.unwrap(wrapped),
+            (StoredD1::Wrapped(_), None) => Err(crate::error::Error::InvalidState(
+                "Wrapped D1 requires a key protector".to_string(),
+            )),
+        }
+    }
+
+    /// wrapped D1（未加密时为 `None`）
+    pub(crate) fn wrapped(&self) -> Option<&[u8]> {
+        match self {
+            StoredD1::Wrapped(wrapped) => Some(wrapped),
+            StoredD1::Clear(_) => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::protocol::CoSignProtocol;
+
+    /// 测试用保护回调：与固定字节异或
+    #[derive(Debug)]
+    struct XorProtector;
+
+    impl KeyProtector for XorProtector {
+        fn wrap(&self, d1: &D1) -> Result<Vec<u8>> {
+            Ok(d1.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn unwrap(&self, wrapped: &[u8]) -> Result<D1> {
+            if wrapped.len() != 32 {
+                return Err(Error::Crypto("Bad wrapped D1".to_string()));
+            }
+            D1::from_slice(&wrapped.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>())
+        }
+    }
+
+    #[test]
+    fn test_stored_d1_roundtrip() {
+        let d1 = CoSignProtocol::new().unwrap().generate_d1().unwrap();
+
+        let stored = StoredD1::seal(&d1, Some(&XorProtector)).unwrap();
+        assert_ne!(stored.wrapped().unwrap(), d1.as_bytes());
+        assert_eq!(stored.open(Some(&XorProtector)).unwrap().as_bytes(), d1.as_bytes());
+        assert!(matches!(stored.open(None), Err(Error::InvalidState(_))));
+
+        let clear = StoredD1::seal(&d1, None).unwrap();
+        assert!(clear.wrapped().is_none());
+        assert_eq!(clear.open(None).unwrap().as_bytes(), d1.as_bytes());
+    }
+}
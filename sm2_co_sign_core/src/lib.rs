@@ -3,14 +3,120 @@
 //! 提供完整的 SM2 协同签名协议实现，包括：
 //! - 密钥生成（D1/D2分片架构）
 //! - 协同签名
+//! - 密钥材料强类型封装（长度与取值范围校验、清零、Debug 脱敏）
+//! - 可选的锁定内存存放（`mlock` feature）
+//! - D1 的平台密钥保护（Secure Enclave / Keystore 加密保存，使用时临时解开）
+//! - 按用途派生子密钥分量（一次注册支持多个服务端分量可独立轮换的协同密钥）
+//! - 本地记录密钥年龄与使用次数，按轮换策略提示或要求刷新密钥分量
+//! - 设备端生成 D1 的密钥证明（设备密钥签名，随注册请求提交）
+//! - D1 的双人控制托管（拆分后分别加密给两名托管员，两人同时参与才能恢复）
 //! - 协同解密
+//! - 按用户名加密给其他用户（服务端查询接收方公钥，首次取得后固定）
+//! - 签名与解密的链式操作回执（本地密钥签名，可导出用于争议处理）
+//! - SM2 密文解析（C1C3C2 / C1C2C3 / ASN.1 DER）
+//! - 统一的二进制数据编码（Hex / Base64 / Base64URL / 原始字节）
+//! - 可配置的服务端响应外层格式
+//! - JSON / CBOR 请求体与响应体（CBOR 需 `cbor` feature）
+//! - 公钥 PEM / SubjectPublicKeyInfo 编解码
+//! - GM/T 0010 PKCS#7 签名数据
+//! - PDF 签名（`pdf` feature）
+//! - XML 数字签名（`xmldsig` feature）
+//! - GB/T 38540 电子印章与电子签章
+//! - 证书请求与自签名证书构造（`x509` feature）
+//! - SM3 流式杂凑
+//! - RustCrypto `signature` trait（`Signer` / `AsyncSigner` / `Verifier`）
+//! - SM4 对称加密（CBC / GCM）
+//! - 算法自检（已知答案测试）
+//! - 服务端 D2 模拟器（本地开发测试）
+//! - TLCP 双证书握手签名与预主密钥解密
+//! - 请求 ID 与 OpenTelemetry trace / 指标（`otel` feature）
+//! - 可替换的请求发送方式，及录制 / 回放服务端交互（`vcr` feature）
+//!
+//! 关闭默认的 `std` feature 时以 `no_std + alloc` 编译，只保留协议数学层（协同签名/解密的客户端计算、
+//! 标准加解密、KDF、SM3、SM4、Hex/Base64、ASN.1/PEM 编解码与密钥材料类型），供嵌入式终端、安全芯片等环境使用。
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// no_std 下补齐标准库 prelude 中的 alloc 类型与宏
+#[cfg(not(feature = "std"))]
+mod prelude {
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
 
+pub mod asn1;
+#[cfg(feature = "std")]
+pub mod attestation;
+#[cfg(feature = "std")]
+pub mod body;
+pub mod ciphertext;
+#[cfg(feature = "client")]
 pub mod client;
+mod ct_point;
+pub mod encoding;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod escrow;
+pub mod key_protector;
+pub mod lifetime;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pem;
+pub mod pkcs7;
 pub mod protocol;
+#[cfg(feature = "std")]
+pub mod receipt;
+#[cfg(feature = "std")]
+pub mod response;
+#[cfg(feature = "std")]
+pub mod seal;
+pub mod secret;
+pub mod secure_mem;
+#[cfg(feature = "std")]
+pub mod selftest;
+pub mod signer;
+#[cfg(feature = "std")]
+pub mod simulator;
+pub mod sm3;
+pub mod sm4;
+pub mod subkey;
+#[cfg(feature = "client")]
+pub mod telemetry;
+#[cfg(feature = "client")]
+pub mod tlcp;
+#[cfg(feature = "client")]
+pub mod transport;
 pub mod types;
+#[cfg(feature = "vcr")]
+pub mod vcr;
+#[cfg(feature = "x509")]
+pub mod x509;
+#[cfg(feature = "xmldsig")]
+pub mod xmldsig;
 
+#[cfg(feature = "client")]
 pub use client::{CoSignClient, ClientConfig};
-pub use error::{Error, Result};
-pub use protocol::CoSignProtocol;
+#[cfg(feature = "std")]
+pub use attestation::DeviceAttestor;
+#[cfg(feature = "std")]
+pub use body::BodyFormat;
+pub use ciphertext::{CiphertextLayout, Sm2Ciphertext};
+pub use encoding::Encoding;
+pub use error::{Error, ErrorKind, Result};
+pub use key_protector::KeyProtector;
+pub use lifetime::{KeyLifetime, RotationPolicy, RotationStatus};
+pub use protocol::{CoSignProtocol, DigestMode, SigningSession};
+#[cfg(feature = "std")]
+pub use receipt::{Receipt, ReceiptChain};
+#[cfg(feature = "std")]
+pub use response::{FieldEnvelope, ResponseEnvelope};
+pub use secret::{AuthToken, Nonce, PublicKey, D1};
+#[cfg(feature = "client")]
+pub use signer::CoSignSigner;
+pub use signature;
+pub use subkey::SubKey;
 pub use types::*;
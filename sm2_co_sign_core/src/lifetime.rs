@@ -0,0 +1,166 @@
+//! 密钥生命周期与轮换策略
+//!
+//! 客户端在本地记录当前 D1 的创建时间与使用次数：注册、初始化密钥与密钥分量刷新时重新计时并清零，每次以主密钥
+//! 成功完成协同签名或解密时计数加一。[`RotationPolicy`] 按年龄与次数给出 [`RotationStatus`]：达到“建议”阈值时
+//! 客户端记录警告，达到“必须”阈值时拒绝继续签名，直至刷新密钥分量（`refresh_key`）。
+//!
+//! 记录只保存在本地，不与服务端同步，由调用方随 D1 一起持久化（CLI 为密钥目录下的 `.key_lifetime`）。
+//! 创建时间未知（如从旧文件恢复）的密钥不按年龄判断。
+
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// 当前 D1 的本地生命周期记录
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyLifetime {
+    /// 创建时间（注册、初始化或刷新，Unix 秒），未知时为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    /// 已完成的签名、解密次数
+    #[serde(default)]
+    pub usage_count: u64,
+}
+
+impl KeyLifetime {
+    /// 创建于 `created_at`（Unix 秒）、尚未使用
+    pub fn starting_at(created_at: u64) -> Self {
+        Self { created_at: Some(created_at), usage_count: 0 }
+    }
+
+    /// 创建于当前时间、尚未使用
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        Self::starting_at(unix_now())
+    }
+
+    /// 截至 `now`（Unix 秒）的年龄，创建时间未知时为 `None`；时钟回拨时为 0
+    pub fn age_at(&self, now: u64) -> Option<Duration> {
+        self.created_at.map(|created_at| Duration::from_secs(now.saturating_sub(created_at)))
+    }
+
+    /// 截至当前时间的年龄
+    #[cfg(feature = "std")]
+    pub fn age(&self) -> Option<Duration> {
+        self.age_at(unix_now())
+    }
+}
+
+/// 按轮换策略判断的密钥状态，按严重程度排序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationStatus {
+    /// 无需轮换
+    #[default]
+    Ok,
+    /// 建议轮换，仍可继续签名
+    Recommended,
+    /// 必须轮换，拒绝继续签名
+    Required,
+}
+
+impl RotationStatus {
+    /// 名称（`ok` / `recommended` / `required`），与 serde 表示一致
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RotationStatus::Ok => "ok",
+            RotationStatus::Recommended => "recommended",
+            RotationStatus::Required => "required",
+        }
+    }
+}
+
+/// 密钥轮换策略，各阈值为 `None` 时不限制；默认不限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// 密钥年龄达到该值时建议轮换
+    pub recommend_after: Option<Duration>,
+    /// 密钥年龄达到该值时必须轮换
+    pub require_after: Option<Duration>,
+    /// 使用次数达到该值时建议轮换
+    pub recommend_after_uses: Option<u64>,
+    /// 使用次数达到该值时必须轮换（即最多签名、解密该次数）
+    pub require_after_uses: Option<u64>,
+}
+
+impl RotationPolicy {
+    /// 是否未设置任何阈值
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 截至 `now`（Unix 秒）的状态：任一“必须”阈值达到即为 [`RotationStatus::Required`]
+    pub fn status_at(&self, lifetime: &KeyLifetime, now: u64) -> RotationStatus {
+        let age = lifetime.age_at(now);
+        let reached = |max_age: Option<Duration>, max_uses: Option<u64>| {
+            max_age.zip(age).is_some_and(|(max_age, age)| age >= max_age)
+                || max_uses.is_some_and(|max_uses| lifetime.usage_count >= max_uses)
+        };
+        if reached(self.require_after, self.require_after_uses) {
+            RotationStatus::Required
+        } else if reached(self.recommend_after, self.recommend_after_uses) {
+            RotationStatus::Recommended
+        } else {
+            RotationStatus::Ok
+        }
+    }
+
+    /// 截至当前时间的状态
+    #[cfg(feature = "std")]
+    pub fn status(&self, lifetime: &KeyLifetime) -> RotationStatus {
+        self.status_at(lifetime, unix_now())
+    }
+}
+
+#[cfg(feature = "std")]
+fn unix_now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    #[test]
+    fn test_rotation_policy() {
+        let policy = RotationPolicy {
+            recommend_after: Some(Duration::from_secs(30 * DAY)),
+            require_after: Some(Duration::from_secs(90 * DAY)),
+            recommend_after_uses: Some(100),
+            require_after_uses: Some(1000),
+        };
+        let lifetime = KeyLifetime::starting_at(1_000_000);
+        assert_eq!(lifetime.age_at(1_000_000 + DAY), Some(Duration::from_secs(DAY)));
+        assert_eq!(lifetime.age_at(0), Some(Duration::ZERO));
+
+        assert_eq!(policy.status_at(&lifetime, 1_000_000 + DAY), RotationStatus::Ok);
+        assert_eq!(policy.status_at(&lifetime, 1_000_000 + 30 * DAY), RotationStatus::Recommended);
+        assert_eq!(policy.status_at(&lifetime, 1_000_000 + 90 * DAY), RotationStatus::Required);
+
+        let used = KeyLifetime { usage_count: 100, ..lifetime };
+        assert_eq!(policy.status_at(&used, 1_000_000), RotationStatus::Recommended);
+        let used = KeyLifetime { usage_count: 1000, ..lifetime };
+        assert_eq!(policy.status_at(&used, 1_000_000), RotationStatus::Required);
+
+        // 创建时间未知时只按次数判断
+        let unknown = KeyLifetime { created_at: None, usage_count: 5 };
+        assert_eq!(unknown.age_at(u64::MAX), None);
+        assert_eq!(policy.status_at(&unknown, u64::MAX), RotationStatus::Ok);
+
+        assert!(RotationPolicy::default().is_empty());
+        assert_eq!(RotationPolicy::default().status_at(&used, u64::MAX), RotationStatus::Ok);
+        assert!(RotationStatus::Required > RotationStatus::Recommended);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_key_lifetime_serde() {
+        let lifetime = KeyLifetime { created_at: Some(1_700_000_000), usage_count: 3 };
+        let json = serde_json::to_string(&lifetime).unwrap();
+        assert_eq!(json, r#"{"created_at":1700000000,"usage_count":3}"#);
+        assert_eq!(serde_json::from_str::<KeyLifetime>(&json).unwrap(), lifetime);
+        assert_eq!(serde_json::from_str::<KeyLifetime>("{}").unwrap(), KeyLifetime::default());
+        assert_eq!(serde_json::to_value(RotationStatus::Recommended).unwrap(), "recommended");
+    }
+}
@@ -0,0 +1,234 @@
+//! PDF 签名
+//!
+//! 输入为已准备好签名域的 PDF：签名字典中含 `/ByteRange [...]` 占位与全零的十六进制 `/Contents <00...>`
+//! （由 PDF 编辑库生成，如 pdf-lib 的 `/ByteRange [0 /********** /********** /**********]`）。
+//! 签名流程：
+//!
+//! 1. [`prepare`] 按 `/Contents` 的位置计算 ByteRange 并写回占位（空格补齐，文件长度不变）
+//! 2. [`byte_range_digest`] 计算 ByteRange 覆盖内容的 e = SM3(ZA || 内容)
+//! 3. 协同签名后组装不含原文的 GM/T 0010 PKCS#7（[`crate::pkcs7`]），[`embed`] 写入 `/Contents`
+//!
+//! [`sign_pdf`] 依次完成以上步骤。只处理文件中最后一个签名字典，不修改交叉引用表，
+//! 因此占位须由准备工具一次写好，签名不会改变任何对象的偏移。
+
+use crate::asn1;
+use crate::client::CoSignClient;
+use crate::error::{Error, Result};
+use crate::pkcs7;
+use crate::protocol::{CoSignProtocol, DEFAULT_USER_ID};
+use crate::sm3::{Sm3, SM3_DIGEST_LEN};
+use std::ops::Range;
+
+const BYTE_RANGE_KEY: &[u8] = b"/ByteRange";
+const CONTENTS_KEY: &[u8] = b"/Contents";
+
+/// 签名字典中 ByteRange 与 Contents 占位的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignaturePlaceholder {
+    /// `[` 与 `]` 之间的文本
+    byte_range: Range<usize>,
+    /// `/Contents` 的十六进制字符串，含 `<` 与 `>`
+    contents: Range<usize>,
+    /// 文件长度
+    len: usize,
+}
+
+impl SignaturePlaceholder {
+    /// 定位最后一个签名字典中的占位
+    pub fn find(pdf: &[u8]) -> Result<Self> {
+        let key = rfind(pdf, BYTE_RANGE_KEY).ok_or_else(|| invalid("No /ByteRange in PDF"))?;
+        let open = skip_whitespace(pdf, key + BYTE_RANGE_KEY.len());
+        if pdf.get(open) != Some(&b'[') {
+            return Err(invalid("/ByteRange is not an array"));
+        }
+        let close = find(&pdf[open..], b"]").ok_or_else(|| invalid("Unterminated /ByteRange"))? + open;
+
+        // Reason: /Contents 可能在 /ByteRange 之前或之后，从所在字典的起点查找
+        let dict = rfind(&pdf[..key], b"<<").ok_or_else(|| invalid("/ByteRange outside a dictionary"))?;
+        let contents_key = find(&pdf[dict..], CONTENTS_KEY).ok_or_else(|| invalid("No /Contents in signature dictionary"))?;
+        let start = skip_whitespace(pdf, dict + contents_key + CONTENTS_KEY.len());
+        if pdf.get(start) != Some(&b'<') {
+            return Err(invalid("/Contents is not a hex string"));
+        }
+        let end = find(&pdf[start..], b">").ok_or_else(|| invalid("Unterminated /Contents"))? + start + 1;
+        let hex = &pdf[start + 1..end - 1];
+        if hex.is_empty() || hex.len() % 2 != 0 || !hex.iter().all(u8::is_ascii_hexdigit) {
+            return Err(invalid("/Contents placeholder must be an even number of hex digits"));
+        }
+
+        Ok(Self { byte_range: open + 1..close, contents: start..end, len: pdf.len() })
+    }
+
+    /// ByteRange 数组：`[0, <之前的长度, >之后的偏移, 之后的长度]`
+    pub fn byte_range(&self) -> [usize; 4] {
+        [0, self.contents.start, self.contents.end, self.len - self.contents.end]
+    }
+
+    /// `/Contents` 可容纳的签名数据字节数
+    pub fn capacity(&self) -> usize {
+        (self.contents.len() - 2) / 2
+    }
+
+    /// ByteRange 覆盖的两段内容
+    fn segments<'a>(&self, pdf: &'a [u8]) -> [&'a [u8]; 2] {
+        [&pdf[..self.contents.start], &pdf[self.contents.end..]]
+    }
+}
+
+/// 定位占位并写入 ByteRange
+pub fn prepare(pdf: &mut [u8]) -> Result<SignaturePlaceholder> {
+    let placeholder = SignaturePlaceholder::find(pdf)?;
+    let [_, a, b, c] = placeholder.byte_range();
+    let text = format!("0 {} {} {}", a, b, c);
+    let slot = &mut pdf[placeholder.byte_range.clone()];
+    if text.len() > slot.len() {
+        return Err(invalid("/ByteRange placeholder is too short"));
+    }
+    slot.fill(b' ');
+    slot[..text.len()].copy_from_slice(text.as_bytes());
+    Ok(placeholder)
+}
+
+/// ByteRange 覆盖内容的消息哈希 e = SM3(ZA || 内容)，ZA 使用默认用户标识
+pub fn byte_range_digest(pdf: &[u8], placeholder: &SignaturePlaceholder, public_key: &[u8]) -> Result<[u8; SM3_DIGEST_LEN]> {
+    if pdf.len() != placeholder.len {
+        return Err(Error::InvalidParam("PDF length changed since the placeholder was located".to_string()));
+    }
+    let mut hasher = Sm3::new();
+    hasher.update(&CoSignProtocol::new()?.za(DEFAULT_USER_ID, public_key)?);
+    for segment in placeholder.segments(pdf) {
+        hasher.update(segment);
+    }
+    Ok(hasher.finalize())
+}
+
+/// 将 DER 编码的签名数据以十六进制写入 `/Contents`，剩余位置保持为 0
+pub fn embed(pdf: &mut [u8], placeholder: &SignaturePlaceholder, signature: &[u8]) -> Result<()> {
+    if signature.len() > placeholder.capacity() {
+        return Err(Error::InvalidParam(format!(
+            "Signature of {} bytes exceeds the /Contents placeholder of {} bytes",
+            signature.len(),
+            placeholder.capacity()
+        )));
+    }
+    let slot = &mut pdf[placeholder.contents.start + 1..placeholder.contents.end - 1];
+    slot.fill(b'0');
+    slot[..signature.len() * 2].copy_from_slice(hex::encode_upper(signature).as_bytes());
+    Ok(())
+}
+
+/// 对已准备签名域的 PDF 协同签名，返回签名后的 PDF
+///
+/// `certificate` 为签名者证书 DER，须与客户端的协同公钥一致；签名结果先用公钥验证再写入。
+pub async fn sign_pdf(client: &CoSignClient, pdf: &[u8], certificate: &[u8]) -> Result<Vec<u8>> {
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or(Error::InvalidState("No key pair available".to_string()))?;
+    if asn1::public_key_from_certificate(certificate)? != public_key.as_bytes() {
+        return Err(Error::InvalidParam("Certificate does not match the co-sign public key".to_string()));
+    }
+
+    let mut pdf = pdf.to_vec();
+    let placeholder = prepare(&mut pdf)?;
+    let e = byte_range_digest(&pdf, &placeholder, &public_key)?;
+    let signature = client.sign_digest(&e).await?;
+    if !CoSignProtocol::new()?.verify_digest(&public_key, &e, &signature.r, &signature.s)? {
+        return Err(Error::Crypto("Co-signature does not verify against the public key".to_string()));
+    }
+
+    let p7 = pkcs7::signed_data(certificate, None, &asn1::signature_to_der(&signature.to_bytes())?)?;
+    embed(&mut pdf, &placeholder, &p7)?;
+    Ok(pdf)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidParam(format!("Invalid PDF signature placeholder: {}", reason))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|window| window == needle)
+}
+
+/// 跳过 PDF 空白字符（ISO 32000-1 7.2.2）
+fn skip_whitespace(pdf: &[u8], mut pos: usize) -> usize {
+    while matches!(pdf.get(pos), Some(b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')) {
+        pos += 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepared_pdf(contents_len: usize) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Sig /Filter /Adobe.PPKLite /SubFilter /adbe.pkcs7.detached\n\
+            /ByteRange [0 /********** /********** /**********]\n/Contents <"
+            .to_vec();
+        pdf.extend(std::iter::repeat(b'0').take(contents_len * 2));
+        pdf.extend_from_slice(b">\n>>\nendobj\n%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn test_prepare_writes_byte_range() {
+        let mut pdf = prepared_pdf(16);
+        let len = pdf.len();
+        let placeholder = prepare(&mut pdf).unwrap();
+        assert_eq!(pdf.len(), len);
+        assert_eq!(placeholder.capacity(), 16);
+
+        let [_, a, b, c] = placeholder.byte_range();
+        assert_eq!(pdf[a], b'<');
+        assert_eq!(pdf[b - 1], b'>');
+        assert_eq!(b + c, len);
+        let text = format!("[0 {} {} {}", a, b, c);
+        assert!(pdf.windows(text.len()).any(|window| window == text.as_bytes()));
+        // 写入后再次定位得到同样的占位
+        assert_eq!(SignaturePlaceholder::find(&pdf).unwrap(), placeholder);
+    }
+
+    #[test]
+    fn test_digest_covers_byte_range() {
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        let mut pdf = prepared_pdf(16);
+        let placeholder = prepare(&mut pdf).unwrap();
+        let [_, a, b, _] = placeholder.byte_range();
+
+        let content = [&pdf[..a], &pdf[b..]].concat();
+        let expected = CoSignProtocol::new()
+            .unwrap()
+            .calculate_message_hash_with_uid(&content, DEFAULT_USER_ID, &public_key)
+            .unwrap();
+        let e = byte_range_digest(&pdf, &placeholder, &public_key).unwrap();
+        assert_eq!(e.to_vec(), expected);
+
+        // 写入签名不影响摘要
+        embed(&mut pdf, &placeholder, &[0xAB; 4]).unwrap();
+        assert_eq!(byte_range_digest(&pdf, &placeholder, &public_key).unwrap(), e);
+    }
+
+    #[test]
+    fn test_embed() {
+        let mut pdf = prepared_pdf(4);
+        let placeholder = prepare(&mut pdf).unwrap();
+        embed(&mut pdf, &placeholder, &[0xAB, 0x01]).unwrap();
+        assert!(pdf.windows(10).any(|window| window == b"<AB010000>"));
+        assert!(matches!(embed(&mut pdf, &placeholder, &[0u8; 5]), Err(Error::InvalidParam(_))));
+    }
+
+    #[test]
+    fn test_invalid_placeholders() {
+        assert!(SignaturePlaceholder::find(b"%PDF-1.7\n%%EOF").is_err());
+        assert!(SignaturePlaceholder::find(b"<< /ByteRange [0 0 0 0] /Contents (abc) >>").is_err());
+        assert!(SignaturePlaceholder::find(b"<< /ByteRange [0 0 0 0] /Contents <000> >>").is_err());
+
+        let mut short = b"<< /ByteRange [] /Contents <0000> >>".to_vec();
+        assert!(prepare(&mut short).is_err());
+    }
+}
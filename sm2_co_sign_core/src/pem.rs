@@ -0,0 +1,170 @@
+//! PEM 编解码
+//!
+//! 除通用的 [`encode`] / [`decode`] 外，为本库产生的各类数据提供固定标签的读写函数，使其均可作为文本交由
+//! OpenSSL 等标准工具或人工处理：
+//!
+//! | 数据 | 标签 | 内容 |
+//! |------|------|------|
+//! | 公钥 | `PUBLIC KEY` | SubjectPublicKeyInfo（见 [`crate::PublicKey::to_pem`]） |
+//! | 证书 | `CERTIFICATE` | X.509 DER |
+//! | 签名 | `SM2 SIGNATURE` | `SEQUENCE { r INTEGER, s INTEGER }` |
+//! | 密文 | `SM2 CIPHERTEXT` | GM/T 0009 `SEQUENCE { x, y, hash, cipherText }` |
+//! | 加密保存的 D1 | `SM2 CO-SIGN ENCRYPTED D1` | 平台密钥或口令加密后的 D1（格式由加密方决定） |
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::{Error, Result};
+use crate::asn1;
+use crate::ciphertext::{CiphertextLayout, Sm2Ciphertext};
+use crate::protocol::{base64_decode, base64_encode};
+
+/// PEM 每行 Base64 字符数
+const PEM_LINE_LEN: usize = 64;
+
+/// SubjectPublicKeyInfo 公钥的 PEM 标签
+pub const PUBLIC_KEY_LABEL: &str = "PUBLIC KEY";
+/// X.509 证书的 PEM 标签
+pub const CERTIFICATE_LABEL: &str = "CERTIFICATE";
+/// SM2 签名（DER）的 PEM 标签
+pub const SIGNATURE_LABEL: &str = "SM2 SIGNATURE";
+/// SM2 密文（GM/T 0009 DER）的 PEM 标签
+pub const CIPHERTEXT_LABEL: &str = "SM2 CIPHERTEXT";
+/// 加密保存的 D1 的 PEM 标签
+pub const ENCRYPTED_D1_LABEL: &str = "SM2 CO-SIGN ENCRYPTED D1";
+
+/// 编码一个 PEM 块
+pub fn encode(label: &str, data: &[u8]) -> String {
+    let body = base64_encode(data);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(PEM_LINE_LEN) {
+        pem.push_str(core::str::from_utf8(line).expect("base64 is ascii"));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// 文本中是否包含指定标签的 PEM 块
+pub fn contains(text: &str, label: &str) -> bool {
+    text.contains(&format!("-----BEGIN {}-----", label))
+}
+
+/// 解码指定标签的 PEM 块
+pub fn decode(text: &str, label: &str) -> Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let start = text
+        .find(&begin)
+        .ok_or_else(|| Error::Encoding(format!("Missing PEM block {}", label)))?
+        + begin.len();
+    let len = text[start..]
+        .find(&end)
+        .ok_or_else(|| Error::Encoding(format!("PEM block {} has no end marker", label)))?;
+    let body: String = text[start..start + len].split_whitespace().collect();
+    base64_decode(&body)
+}
+
+/// 按顺序解码文本中所有指定标签的 PEM 块
+pub fn decode_all(text: &str, label: &str) -> Result<Vec<Vec<u8>>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&begin) {
+        rest = &rest[start..];
+        blocks.push(decode(rest, label)?);
+        rest = &rest[begin.len()..];
+    }
+    Ok(blocks)
+}
+
+/// 数据是否为 PEM 文本（忽略前导空白）
+pub fn is_pem(data: &[u8]) -> bool {
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(data.len());
+    data[start..].starts_with(b"-----BEGIN ")
+}
+
+/// 解码文本中的第一个 PEM 块，返回 (标签, 内容)
+pub fn decode_first(text: &str) -> Result<(String, Vec<u8>)> {
+    let start = text.find("-----BEGIN ").ok_or_else(|| Error::Encoding("No PEM block found".to_string()))?;
+    let rest = &text[start + "-----BEGIN ".len()..];
+    let label = rest
+        .find("-----")
+        .map(|end| &rest[..end])
+        .ok_or_else(|| Error::Encoding("Malformed PEM header".to_string()))?;
+    Ok((label.to_string(), decode(&text[start..], label)?))
+}
+
+/// 将 r||s（64 字节）签名编码为 `SM2 SIGNATURE` PEM（内容为 DER）
+pub fn encode_signature(signature: &[u8]) -> Result<String> {
+    Ok(encode(SIGNATURE_LABEL, &asn1::signature_to_der(signature)?))
+}
+
+/// 解码 `SM2 SIGNATURE` PEM，返回 r||s（64 字节）
+pub fn decode_signature(text: &str) -> Result<Vec<u8>> {
+    asn1::signature_from_der(&decode(text, SIGNATURE_LABEL)?)
+}
+
+/// 将密文编码为 `SM2 CIPHERTEXT` PEM（内容为 GM/T 0009 DER）；输入按 [`Sm2Ciphertext::parse`] 识别
+pub fn encode_ciphertext(ciphertext: &[u8]) -> Result<String> {
+    Ok(encode(CIPHERTEXT_LABEL, &Sm2Ciphertext::parse(ciphertext)?.to_der()))
+}
+
+/// 解码 `SM2 CIPHERTEXT` PEM，返回 C1C3C2 拼接的密文（含 0x04 前缀）
+pub fn decode_ciphertext(text: &str) -> Result<Vec<u8>> {
+    let der = decode(text, CIPHERTEXT_LABEL)?;
+    Ok(Sm2Ciphertext::from_der(&der)?.to_bytes(CiphertextLayout::C1C3C2))
+}
+
+/// 将加密后的 D1（如 [`crate::KeyProtector`] 返回的 wrapped D1）编码为 `SM2 CO-SIGN ENCRYPTED D1` PEM
+pub fn encode_encrypted_d1(wrapped: &[u8]) -> String {
+    encode(ENCRYPTED_D1_LABEL, wrapped)
+}
+
+/// 解码 `SM2 CO-SIGN ENCRYPTED D1` PEM
+pub fn decode_encrypted_d1(text: &str) -> Result<Vec<u8>> {
+    decode(text, ENCRYPTED_D1_LABEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pem_roundtrip() {
+        let data: Vec<u8> = (0..100u8).collect();
+        let pem = encode("TEST", &data);
+        assert!(pem.lines().all(|line| line.len() <= PEM_LINE_LEN));
+        assert!(contains(&pem, "TEST"));
+        assert_eq!(decode(&pem, "TEST").unwrap(), data);
+        assert!(decode(&pem, "OTHER").is_err());
+
+        let two = format!("{}{}", encode("TEST", b"a"), encode("TEST", b"b"));
+        assert_eq!(decode_all(&two, "TEST").unwrap(), vec![b"a".to_vec(), b"b".to_vec()]);
+
+        assert!(is_pem(format!("\n{}", pem).as_bytes()));
+        assert!(!is_pem(&data));
+        assert_eq!(decode_first(&two).unwrap(), ("TEST".to_string(), b"a".to_vec()));
+        assert!(decode_first("no pem here").is_err());
+    }
+
+    #[test]
+    fn test_typed_armor() {
+        let mut signature = [0u8; 64];
+        signature[31] = 1;
+        signature[63] = 2;
+        let pem = encode_signature(&signature).unwrap();
+        assert!(contains(&pem, SIGNATURE_LABEL));
+        assert_eq!(decode_signature(&pem).unwrap(), signature);
+        assert!(decode_ciphertext(&pem).is_err());
+
+        let mut k = [0u8; 32];
+        k[31] = 7;
+        let public_key = crate::ct_point::mul_base(&k).unwrap();
+        let ciphertext = crate::protocol::CoSignProtocol::encrypt(&public_key, b"hello").unwrap();
+        let pem = encode_ciphertext(&ciphertext).unwrap();
+        assert_eq!(decode_ciphertext(&pem).unwrap(), ciphertext);
+
+        let wrapped = b"wrapped d1".to_vec();
+        assert_eq!(decode_encrypted_d1(&encode_encrypted_d1(&wrapped)).unwrap(), wrapped);
+    }
+}
@@ -0,0 +1,147 @@
+//! GM/T 0010 PKCS#7 签名数据编码
+//!
+//! SignedData 不含签名属性（authenticatedAttributes），签名值为对原文按 SM3withSM2（含 ZA）计算的
+//! DER 签名；签名者证书一并放入 certificates 字段，便于接收方验签。CLI 的 `p7sign` 与 PDF 签名共用。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::asn1::{
+    self, DerReader, TAG_CERT_VERSION, TAG_INTEGER, TAG_NULL, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE, TAG_SET,
+};
+use crate::error::Result;
+
+/// ContentInfo 内容 `[0] EXPLICIT` 与 SignedData 证书集合 `[0] IMPLICIT` 标签
+pub const TAG_CONTEXT_0: u8 = 0xA0;
+
+/// SM3 杂凑算法 (1.2.156.10197.1.401)
+pub const OID_SM3: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x01, 0x83, 0x11];
+/// SM2 签名算法 sm2-1 (1.2.156.10197.1.301.1)
+pub const OID_SM2_SIGN: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x01, 0x82, 0x2D, 0x01];
+/// GM/T 0010 data 类型 (1.2.156.10197.6.1.4.2.1)
+pub const OID_GM_DATA: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x06, 0x01, 0x04, 0x02, 0x01];
+/// GM/T 0010 signedData 类型 (1.2.156.10197.6.1.4.2.2)
+pub const OID_GM_SIGNED_DATA: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x06, 0x01, 0x04, 0x02, 0x02];
+
+/// 算法标识 `SEQUENCE { algorithm OID, parameters NULL }`
+pub fn algorithm_identifier(oid: &[u8]) -> Vec<u8> {
+    let mut algorithm = asn1::encode_tlv(TAG_OID, oid);
+    algorithm.extend(asn1::encode_tlv(TAG_NULL, &[]));
+    asn1::encode_sequence(&algorithm)
+}
+
+/// 由证书 DER 构造 `IssuerAndSerialNumber`，颁发者与序列号按证书中的编码原样使用
+pub fn issuer_and_serial_number(certificate: &[u8]) -> Result<Vec<u8>> {
+    let mut outer = DerReader::new(certificate);
+    let mut certificate = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    let mut tbs = DerReader::new(certificate.read(TAG_SEQUENCE)?);
+    if tbs.peek_tag() == Some(TAG_CERT_VERSION) {
+        tbs.read_any()?;
+    }
+    let serial = asn1::encode_tlv(TAG_INTEGER, tbs.read(TAG_INTEGER)?);
+    tbs.read(TAG_SEQUENCE)?;
+    let mut contents = asn1::encode_sequence(tbs.read(TAG_SEQUENCE)?);
+    contents.extend(serial);
+    Ok(asn1::encode_sequence(&contents))
+}
+
+/// 组装 GM/T 0010 PKCS#7 签名数据
+///
+/// `certificate` 为签名者证书 DER；`content` 为 None 时不含原文（分离式签名）。
+pub fn signed_data(certificate: &[u8], content: Option<&[u8]>, signature_der: &[u8]) -> Result<Vec<u8>> {
+    let mut signer_info = asn1::encode_unsigned_integer(&[1]);
+    signer_info.extend(issuer_and_serial_number(certificate)?);
+    signer_info.extend(algorithm_identifier(OID_SM3));
+    signer_info.extend(algorithm_identifier(OID_SM2_SIGN));
+    signer_info.extend(asn1::encode_tlv(TAG_OCTET_STRING, signature_der));
+
+    let mut signed_data = asn1::encode_unsigned_integer(&[1]);
+    signed_data.extend(asn1::encode_tlv(TAG_SET, &algorithm_identifier(OID_SM3)));
+    let mut content_info = asn1::encode_tlv(TAG_OID, OID_GM_DATA);
+    if let Some(content) = content {
+        content_info.extend(asn1::encode_tlv(TAG_CONTEXT_0, &asn1::encode_tlv(TAG_OCTET_STRING, content)));
+    }
+    signed_data.extend(asn1::encode_sequence(&content_info));
+    signed_data.extend(asn1::encode_tlv(TAG_CONTEXT_0, certificate));
+    signed_data.extend(asn1::encode_tlv(TAG_SET, &asn1::encode_sequence(&signer_info)));
+
+    let mut content_info = asn1::encode_tlv(TAG_OID, OID_GM_SIGNED_DATA);
+    content_info.extend(asn1::encode_tlv(TAG_CONTEXT_0, &asn1::encode_sequence(&signed_data)));
+    Ok(asn1::encode_sequence(&content_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 颁发者为 `SEQUENCE {}`、序列号为 0x0102 的最小证书
+    fn test_certificate() -> Vec<u8> {
+        let empty = asn1::encode_sequence(&[]);
+        let mut tbs = asn1::encode_tlv(TAG_CERT_VERSION, &asn1::encode_unsigned_integer(&[2]));
+        tbs.extend(asn1::encode_tlv(TAG_INTEGER, &[0x01, 0x02]));
+        for _ in 0..4 {
+            tbs.extend(&empty);
+        }
+        tbs.extend(asn1::public_key_to_spki(&[0x11; 64]).unwrap());
+        let mut certificate = asn1::encode_sequence(&tbs);
+        certificate.extend(&empty);
+        certificate.extend(asn1::encode_tlv(asn1::TAG_BIT_STRING, &[0x00]));
+        asn1::encode_sequence(&certificate)
+    }
+
+    #[test]
+    fn test_issuer_and_serial_number() {
+        let mut expected = asn1::encode_sequence(&[]);
+        expected.extend([TAG_INTEGER, 0x02, 0x01, 0x02]);
+        assert_eq!(issuer_and_serial_number(&test_certificate()).unwrap(), asn1::encode_sequence(&expected));
+        assert!(issuer_and_serial_number(b"not a certificate").is_err());
+    }
+
+    #[test]
+    fn test_signed_data() {
+        let cert = test_certificate();
+        let p7 = signed_data(&cert, None, &[0x30, 0x00]).unwrap();
+
+        let mut reader = DerReader::new(&p7);
+        let mut content_info = DerReader::new(reader.read(TAG_SEQUENCE).unwrap());
+        assert!(reader.is_empty());
+        assert_eq!(content_info.read(TAG_OID).unwrap(), OID_GM_SIGNED_DATA);
+        let mut explicit = DerReader::new(content_info.read(TAG_CONTEXT_0).unwrap());
+        let mut signed_data = DerReader::new(explicit.read(TAG_SEQUENCE).unwrap());
+
+        assert_eq!(signed_data.read_unsigned_integer().unwrap(), &[1]);
+        assert_eq!(signed_data.read(TAG_SET).unwrap(), algorithm_identifier(OID_SM3).as_slice());
+        assert_eq!(
+            signed_data.read(TAG_SEQUENCE).unwrap(),
+            asn1::encode_tlv(TAG_OID, OID_GM_DATA).as_slice()
+        );
+        assert_eq!(signed_data.read(TAG_CONTEXT_0).unwrap(), cert.as_slice());
+        let mut signer_infos = DerReader::new(signed_data.read(TAG_SET).unwrap());
+        assert!(signed_data.is_empty());
+
+        let mut signer_info = DerReader::new(signer_infos.read(TAG_SEQUENCE).unwrap());
+        signer_info.read_unsigned_integer().unwrap();
+        let issuer_and_serial = signer_info.read(TAG_SEQUENCE).unwrap();
+        assert_eq!(asn1::encode_sequence(issuer_and_serial), issuer_and_serial_number(&cert).unwrap());
+        signer_info.read(TAG_SEQUENCE).unwrap();
+        assert_eq!(signer_info.read(TAG_SEQUENCE).unwrap(), &algorithm_identifier(OID_SM2_SIGN)[2..]);
+        assert_eq!(signer_info.read(TAG_OCTET_STRING).unwrap(), &[0x30, 0x00]);
+    }
+
+    #[test]
+    fn test_attached_content() {
+        let p7 = signed_data(&test_certificate(), Some(b"hello"), &[0x30, 0x00]).unwrap();
+
+        let mut reader = DerReader::new(&p7);
+        let mut content_info = DerReader::new(reader.read(TAG_SEQUENCE).unwrap());
+        content_info.read(TAG_OID).unwrap();
+        let mut explicit = DerReader::new(content_info.read(TAG_CONTEXT_0).unwrap());
+        let mut signed_data = DerReader::new(explicit.read(TAG_SEQUENCE).unwrap());
+        signed_data.read_unsigned_integer().unwrap();
+        signed_data.read(TAG_SET).unwrap();
+
+        let mut inner = DerReader::new(signed_data.read(TAG_SEQUENCE).unwrap());
+        assert_eq!(inner.read(TAG_OID).unwrap(), OID_GM_DATA);
+        let mut data = DerReader::new(inner.read(TAG_CONTEXT_0).unwrap());
+        assert_eq!(data.read(TAG_OCTET_STRING).unwrap(), b"hello");
+    }
+}
@@ -6,98 +6,314 @@
 //! 3. 解密：客户端发送 T1，服务端返回 T2，客户端计算共享密钥
 //!
 //! 依赖库说明：
-//! - libsm: 用于协同签名特有的椭圆曲线操作（点乘、点加、点坐标转换等）
-//! - gm-sdk-rs: 用于标准 SM2 签名验签、SM3 哈希（API 更简洁，开箱即用）
+//! - RustCrypto `sm2`（经 [`crate::ct_point`]）: 协同签名特有的椭圆曲线操作（点乘、点减、模 n 运算等）
+//! - gm-sdk-rs: 标准 SM2 签名验签与密钥对生成，需启用 `std` feature
+//!
+//! 除上述 gm-sdk-rs 接口外，本模块只依赖 `alloc`，可在 no_std 目标上使用。
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::asn1;
+use crate::ciphertext::Sm2Ciphertext;
+use crate::ct_point;
 use crate::error::{Error, Result};
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use gm_sdk::sm2::{sm2_sign, sm2_verify};
-use gm_sdk::sm3::sm3_hash as gm_sm3_hash;
-use libsm::sm2::ecc::EccCtx;
-use num_bigint::BigUint;
-use rand::RngCore;
+#[cfg(feature = "std")]
+use crate::secret::PublicKey;
+use crate::secret::{scalar_from_slice, Nonce, D1};
+use crate::sm3::{Sm3, SM3_DIGEST_LEN};
+use crate::types::Signature;
+use crate::encoding::Encoding;
+#[cfg(feature = "std")]
+use gm_sdk::sm2::{sm2_generate_keypair, sm2_sign, sm2_verify};
+use zeroize::{Zeroize, Zeroizing};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+/// 默认用户标识（GM/T 0009 推荐值）
+pub const DEFAULT_USER_ID: &[u8] = b"1234567812345678";
+
+/// SM2 曲线参数 a
+const SM2_A: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfc,
+];
+/// SM2 曲线参数 b
+const SM2_B: [u8; 32] = [
+    0x28, 0xe9, 0xfa, 0x9e, 0x9d, 0x9f, 0x5e, 0x34, 0x4d, 0x5a, 0x9e, 0x4b, 0xcf, 0x65, 0x09, 0xa7,
+    0xf3, 0x97, 0x89, 0xf5, 0x15, 0xab, 0x8f, 0x92, 0xdd, 0xbc, 0xbd, 0x41, 0x4d, 0x94, 0x0e, 0x93,
+];
+/// SM2 基点 G 的 x 坐标
+const SM2_GX: [u8; 32] = [
+    0x32, 0xc4, 0xae, 0x2c, 0x1f, 0x19, 0x81, 0x19, 0x5f, 0x99, 0x04, 0x46, 0x6a, 0x39, 0xc9, 0x94,
+    0x8f, 0xe3, 0x0b, 0xbf, 0xf2, 0x66, 0x0b, 0xe1, 0x71, 0x5a, 0x45, 0x89, 0x33, 0x4c, 0x74, 0xc7,
+];
+/// SM2 基点 G 的 y 坐标
+const SM2_GY: [u8; 32] = [
+    0xbc, 0x37, 0x36, 0xa2, 0xf4, 0xf6, 0x77, 0x9c, 0x59, 0xbd, 0xce, 0xe3, 0x6b, 0x69, 0x21, 0x53,
+    0xd0, 0xa9, 0x87, 0x7c, 0xc6, 0x2a, 0x47, 0x40, 0x02, 0xdf, 0x32, 0xe5, 0x21, 0x39, 0xf0, 0xa0,
+];
+
+/// 签名输入的预处理方式，明确输入是原始消息还是外部计算好的消息哈希 e
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DigestMode {
+    /// 输入为原始消息，e = SM3(M)（与旧版本 `sign` 一致）
+    #[default]
+    Sm3,
+    /// 输入为原始消息，e = SM3(ZA || M)，使用默认用户标识（标准 SM3withSM2，可被标准工具验签）
+    Za,
+    /// 输入为外部计算好的 32 字节 e，原样使用
+    Prehashed,
+}
+
+/// 一次协同签名会话：持有 k1 及其对应的 Q1
+///
+/// 由 [`CoSignProtocol::sign_prepare`] 创建，[`SigningSession::complete`] 按值消费，
+/// 因此同一个 k1 无法完成两次签名（k1 重用可直接解出私钥），也不会把不同会话的 k1 与 Q1 混用。
+/// 未完成的会话在丢弃时清零 k1。
+#[derive(Debug)]
+#[must_use = "签名会话需调用 complete() 完成"]
+pub struct SigningSession {
+    k1: Nonce,
+    q1: [u8; 64],
+}
+
+impl SigningSession {
+    /// 发送给服务端的 Q1 = k1 * G（64 字节 x||y）
+    pub fn q1(&self) -> &[u8] {
+        &self.q1
+    }
+
+    /// 用服务端返回的 r、s2、s3 完成签名，会话随即失效
+    pub fn complete(self, protocol: &CoSignProtocol, d1: &[u8], r: &[u8], s2: &[u8], s3: &[u8]) -> Result<Signature> {
+        let (r, s) = protocol.complete_signature(&self.k1, d1, r, s2, s3)?;
+        Ok(Signature::new(r, s))
+    }
+
+    /// 随机数承诺扩展中先于 Q1 发送的承诺 SM3(Q1)
+    pub fn commitment(&self) -> [u8; SM3_DIGEST_LEN] {
+        CoSignProtocol::nonce_commitment(&[&self.q1])
+    }
+
+    /// 随机数承诺扩展：校验服务端揭示的随机数点与其承诺一致，且 r 由这些随机数点得出
+    ///
+    /// `commitment` 为服务端在收到 Q1 之前返回的 SM3(K3 || Q2)，`k3g` = k3·G 与 `q2` = k2·G 为随后揭示的
+    /// 曲线点（64 或 65 字节）。检查 r = (e + x1) mod n，(x1, y1) = k1·K3 + Q2 = k3·Q1 + k2·G，
+    /// 服务端因此无法在看到 Q1 之后改选 k2、k3 来影响合成的随机数点。
+    /// 本检查不涉及 s2、s3；需同时验证最终签名（偏执模式）才能确认签名确实使用了承诺的随机数。
+    pub fn verify_server_nonce(&self, e: &[u8], commitment: &[u8], k3g: &[u8], q2: &[u8], r: &[u8]) -> Result<()> {
+        let e: [u8; 32] = e
+            .try_into()
+            .map_err(|_| Error::InvalidParam("Message digest must be 32 bytes".to_string()))?;
+        let k3g = server_point("k3g", k3g)?;
+        let q2 = server_point("q2", q2)?;
+        if commitment != CoSignProtocol::nonce_commitment(&[&k3g, &q2]).as_slice() {
+            return Err(Error::invalid_server_response(
+                "commitment",
+                "does not match the revealed nonce points",
+            ));
+        }
+        let r = CoSignProtocol::server_scalar("r", r)?;
+        // Reason: k1 泄露即可由签名反推 D1，k1·K3 使用常数时间点乘
+        let point = ct_point::add_point(&ct_point::mul_point(&self.k1, &k3g)?, &q2)?;
+        if !ct_point::matches_r(&e, &point, &r) {
+            return Err(Error::invalid_server_response("r", "is not derived from the committed nonce"));
+        }
+        Ok(())
+    }
+
+    /// 拆出 k1 与 Q1
+    ///
+    /// 仅供需要跨越 FFI / WASM 边界保存 k1 的调用方使用，调用方需自行保证 k1 只使用一次。
+    pub fn into_parts(self) -> (Nonce, [u8; 64]) {
+        (self.k1, self.q1)
+    }
+}
+
+/// 最近一次计算的 ZA 及其输入
+#[cfg(feature = "std")]
+struct ZaEntry {
+    uid: Vec<u8>,
+    /// x||y
+    public_key: [u8; 64],
+    za: [u8; SM3_DIGEST_LEN],
+}
 
 /// 协同签名协议
 pub struct CoSignProtocol {
-    ecc: EccCtx,
+    /// Reason: ZA 只取决于 uid 与公钥，同一密钥连续签名时无需重复计算；
+    /// 只保留一项，密钥或 uid 变化时按键失配即视为失效
+    #[cfg(feature = "std")]
+    za_cache: Mutex<Option<ZaEntry>>,
 }
 
 impl CoSignProtocol {
     /// 创建协议实例
+    ///
+    /// 曲线运算不需要预先构造上下文，创建实例的开销可以忽略。
     pub fn new() -> Result<Self> {
-        let ecc = EccCtx::new();
-        Ok(Self { ecc })
+        Ok(Self {
+            #[cfg(feature = "std")]
+            za_cache: Mutex::new(None),
+        })
     }
 
     /// 生成随机数
+    ///
+    /// 系统随机源不可用时 panic。
     pub fn generate_random(bytes: usize) -> Vec<u8> {
-        let mut rng = rand::thread_rng();
         let mut data = vec![0u8; bytes];
-        rng.fill_bytes(&mut data);
+        fill_random(&mut data).expect("system random source unavailable");
         data
     }
 
     /// 计算 SM3 哈希
-    /// 使用 gm-sdk-rs 提供的简洁 API
     pub fn sm3_hash(data: &[u8]) -> Vec<u8> {
-        gm_sm3_hash(data).to_vec()
+        Sm3::digest(data).to_vec()
+    }
+
+    /// 随机数承诺：各曲线点（64 字节 x||y）依次拼接后的 SM3 杂凑
+    ///
+    /// 客户端承诺 SM3(Q1)，服务端承诺 SM3(K3 || Q2)，见 [`SigningSession::verify_server_nonce`]。
+    pub fn nonce_commitment(points: &[&[u8]]) -> [u8; SM3_DIGEST_LEN] {
+        let mut hasher = Sm3::new();
+        for point in points {
+            hasher.update(point);
+        }
+        hasher.finalize()
     }
 
     /// 生成客户端私钥分量 D1
-    /// 注意：此功能需要 libsm 的椭圆曲线随机数生成，gm-sdk-rs 不支持
-    pub fn generate_d1(&self) -> Result<Vec<u8>> {
-        let d1 = self.ecc.random_uint();
-        Ok(d1.to_bytes_be())
+    pub fn generate_d1(&self) -> Result<D1> {
+        D1::from_slice(&random_scalar()?[..])
     }
 
     /// 计算 P1 = d1 * G
-    /// 注意：此功能需要 libsm 的椭圆曲线点乘运算，gm-sdk-rs 不支持
+    ///
+    /// 使用常数时间点乘，见 [`crate::ct_point`]。
     pub fn calculate_p1(&self, d1: &[u8]) -> Result<Vec<u8>> {
-        let d1_big = BigUint::from_bytes_be(d1);
-        
-        let p1 = self.ecc.g_mul(&d1_big).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let (x, y) = self.ecc.to_affine(&p1).map_err(|e| Error::Crypto(e.to_string()))?;
-        let x_bytes = x.to_bytes();
-        let y_bytes = y.to_bytes();
-        
-        let mut p1_bytes = vec![0u8; 64];
-        let x_len = x_bytes.len();
-        let y_len = y_bytes.len();
-        p1_bytes[32 - x_len..32].copy_from_slice(&x_bytes);
-        p1_bytes[64 - y_len..64].copy_from_slice(&y_bytes);
-        
-        Ok(p1_bytes)
+        ct_point::mul_base(d1).map(|p1| p1.to_vec())
+    }
+
+    /// 密钥分量刷新：计算 D1' = D1·t mod n
+    ///
+    /// 服务端同步计算 D2' = D2·t，完整私钥 d = D1'·D2'⁻¹ - 1 与协同公钥保持不变，
+    /// 旧的 D1、D2 分量随之作废。
+    pub fn refresh_d1(&self, d1: &[u8], factor: &[u8]) -> Result<D1> {
+        let t = Zeroizing::new(scalar_from_slice(factor, "refresh factor")?);
+        let d1 = Zeroizing::new(scalar_from_slice(d1, "D1")?);
+        let refreshed = Zeroizing::new(ct_point::mul_scalars(&d1[..], &t[..])?);
+        D1::from_slice(&refreshed[..])
     }
 
     /// 签名预处理：生成 k1，计算 Q1 = k1 * G
-    /// 注意：此功能需要 libsm 的椭圆曲线点乘运算，gm-sdk-rs 不支持
-    pub fn sign_prepare(&self) -> Result<(Vec<u8>, Vec<u8>)> {
-        let k1 = self.ecc.random_uint();
-        
-        let q1 = self.ecc.g_mul(&k1).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let (x, y) = self.ecc.to_affine(&q1).map_err(|e| Error::Crypto(e.to_string()))?;
-        let x_bytes = x.to_bytes();
-        let y_bytes = y.to_bytes();
-        
-        let mut q1_bytes = vec![0u8; 64];
-        let x_len = x_bytes.len();
-        let y_len = y_bytes.len();
-        q1_bytes[32 - x_len..32].copy_from_slice(&x_bytes);
-        q1_bytes[64 - y_len..64].copy_from_slice(&y_bytes);
-        
-        Ok((k1.to_bytes_be(), q1_bytes))
+    pub fn sign_prepare(&self) -> Result<SigningSession> {
+        let k1 = Nonce::from_slice(&random_scalar()?[..])?;
+        // Reason: k1 泄露即可由签名反推 D1，与 D1 同样使用常数时间点乘
+        let q1 = ct_point::mul_base(&k1)?;
+
+        Ok(SigningSession { k1, q1 })
+    }
+
+    /// 按预处理方式计算消息哈希 e
+    ///
+    /// `public_key` 仅 [`DigestMode::Za`] 使用；[`DigestMode::Prehashed`] 要求输入恰为 32 字节。
+    pub fn calculate_message_hash(&self, input: &[u8], public_key: &[u8], mode: DigestMode) -> Result<Vec<u8>> {
+        match mode {
+            DigestMode::Sm3 => Ok(Self::sm3_hash(input)),
+            DigestMode::Za => self.calculate_message_hash_with_uid(input, DEFAULT_USER_ID, public_key),
+            DigestMode::Prehashed if input.len() == 32 => Ok(input.to_vec()),
+            DigestMode::Prehashed => Err(Error::InvalidParam("Message digest must be 32 bytes".to_string())),
+        }
+    }
+
+    /// 计算用户杂凑值 ZA
+    ///
+    /// ZA = SM3(ENTL || ID || a || b || xG || yG || xA || yA)，
+    /// 公钥支持 64 字节（x||y）和 65 字节（04||x||y）两种格式。
+    pub fn calculate_za(uid: &[u8], public_key: &[u8]) -> Result<Vec<u8>> {
+        Self::za_digest(uid, public_key).map(|za| za.to_vec())
+    }
+
+    fn za_digest(uid: &[u8], public_key: &[u8]) -> Result<[u8; SM3_DIGEST_LEN]> {
+        let pk = match public_key.len() {
+            64 => public_key,
+            65 if public_key[0] == 0x04 => &public_key[1..],
+            _ => return Err(Error::Crypto("Invalid public key length, expected 64 or 65 bytes".to_string())),
+        };
+        // ENTL 为 ID 的比特长度，占 2 字节
+        let entl = uid
+            .len()
+            .checked_mul(8)
+            .filter(|bits| *bits <= u16::MAX as usize)
+            .ok_or_else(|| Error::InvalidParam("User ID too long".to_string()))? as u16;
+
+        let mut hasher = Sm3::new();
+        hasher.update(&entl.to_be_bytes());
+        hasher.update(uid);
+        for param in [&SM2_A, &SM2_B, &SM2_GX, &SM2_GY] {
+            hasher.update(param);
+        }
+        hasher.update(pk);
+        Ok(hasher.finalize())
+    }
+
+    /// 计算 ZA，uid 与公钥均与上次调用相同时直接返回缓存值
+    ///
+    /// 结果与 [`CoSignProtocol::calculate_za`] 相同。
+    #[cfg(feature = "std")]
+    pub fn za(&self, uid: &[u8], public_key: &[u8]) -> Result<[u8; SM3_DIGEST_LEN]> {
+        let pk = match public_key {
+            [0x04, rest @ ..] if rest.len() == 64 => rest,
+            _ => public_key,
+        };
+        // Reason: 缓存内容可由输入重新计算，持锁线程 panic 后继续使用不影响正确性
+        let mut cache = self.za_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = cache.as_ref().filter(|entry| entry.uid == uid && entry.public_key[..] == *pk) {
+            return Ok(entry.za);
+        }
+        let za = Self::za_digest(uid, pk)?;
+        let public_key = pk.try_into().expect("normalized public key is 64 bytes");
+        *cache = Some(ZaEntry { uid: uid.to_vec(), public_key, za });
+        Ok(za)
+    }
+
+    /// 当前缓存的 ZA 对应的用户标识与公钥（x||y），未缓存时为 `None`
+    ///
+    /// 供 FFI 导出上下文状态，恢复时以同样的输入调用 [`CoSignProtocol::za`] 重新填充缓存。
+    #[cfg(feature = "std")]
+    pub fn cached_za_input(&self) -> Option<(Vec<u8>, [u8; 64])> {
+        let cache = self.za_cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.as_ref().map(|entry| (entry.uid.clone(), entry.public_key))
+    }
+
+    /// 计算 ZA
+    ///
+    /// Reason: no_std 下没有可跨线程共享的锁，不做缓存，每次重新计算
+    #[cfg(not(feature = "std"))]
+    pub fn za(&self, uid: &[u8], public_key: &[u8]) -> Result<[u8; SM3_DIGEST_LEN]> {
+        Self::za_digest(uid, public_key)
     }
 
-    /// 计算消息哈希 E
-    pub fn calculate_message_hash(&self, message: &[u8], _public_key: &[u8]) -> Result<Vec<u8>> {
-        Ok(Self::sm3_hash(message))
+    /// 计算带用户标识的消息哈希 e = SM3(ZA || M)
+    ///
+    /// 与标准 SM2 签名的预处理一致，生成的签名可被标准工具验证。ZA 经 [`CoSignProtocol::za`] 缓存。
+    pub fn calculate_message_hash_with_uid(
+        &self,
+        message: &[u8],
+        uid: &[u8],
+        public_key: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut hasher = Sm3::new();
+        hasher.update(&self.za(uid, public_key)?);
+        hasher.update(message);
+        Ok(hasher.finalize().to_vec())
     }
 
     /// 完成签名计算
     /// 注意：此功能是协同签名协议特有步骤，gm-sdk-rs 不支持
     ///
+    /// 直接传入 k1 的底层接口，供 FFI / WASM 使用；Rust 调用方应使用 [`SigningSession::complete`]。
+    ///
     /// 数学原理（d = d1·d2Inv - 1, 1+d = d1·d2Inv）：
     ///   服务端返回: s2 = d2·k3, s3 = d2·(k2+r)
     ///   s = (k1·s2 + s3 - r·d1) · d1⁻¹ mod n
@@ -111,69 +327,64 @@ impl CoSignProtocol {
         s2: &[u8],
         s3: &[u8],
     ) -> Result<(Vec<u8>, Vec<u8>)> {
-        let n = self.ecc.get_n();
-
-        let k1_big = BigUint::from_bytes_be(k1);
-        let d1_big = BigUint::from_bytes_be(d1);
-        let r_big = BigUint::from_bytes_be(r);
-        let s2_big = BigUint::from_bytes_be(s2);
-        let s3_big = BigUint::from_bytes_be(s3);
+        // Reason: 底层接口的 k1/d1 来自调用方（FFI / WASM），为零时 d1⁻¹ 不存在，结果会是静默的无效签名
+        let k1 = Zeroizing::new(scalar_from_slice(k1, "k1")?);
+        let d1 = Zeroizing::new(scalar_from_slice(d1, "D1")?);
+        // Reason: r/s2/s3 来自服务端，越界或为零时计算结果无意义，需在运算前拒绝并指明字段
+        let r = Self::server_scalar("r", r)?;
+        let s2 = Self::server_scalar("s2", s2)?;
+        let s3 = Self::server_scalar("s3", s3)?;
 
-        // s = (k1·s2 + s3 - r·d1) · d1⁻¹ mod n
-        // Reason: 服务端用 d2 计算 s2/s3，客户端需乘 d1⁻¹ 来抵消 d1，还原标准 SM2 签名
-        let k1_s2 = (&k1_big * &s2_big) % n;
-        let r_d1 = (&r_big * &d1_big) % n;
-        // 加 n 避免下溢（BigUint 无符号）
-        let inner = (k1_s2 + s3_big + n - r_d1) % n;
+        // Reason: 服务端用 d2 计算 s2/s3，客户端需乘 d1⁻¹ 来抵消 d1，还原标准 SM2 签名；
+        // 涉及 D1、k1 的模 n 运算使用常数时间实现
+        let s = ct_point::complete_s(&k1, &d1, &r, &s2, &s3)?;
 
-        // 用费马小定理求 d1 模逆：d1⁻¹ = d1^(n-2) mod n（n 为素数）
-        let n_minus_2 = n - BigUint::from(2u32);
-        let d1_inv = d1_big.modpow(&n_minus_2, n);
+        // Reason: 统一输出 32 字节，服务端返回的短 r 与高位为零的 s 都已左补零
+        Ok((r.to_vec(), s.to_vec()))
+    }
 
-        let s = (inner * d1_inv) % n;
+    /// 签名结果是否退化：r = 0、s = 0 或 (r + s) mod n = 0
+    ///
+    /// GM/T 0003.2 要求出现这些情况时换用新的随机数 k 重新签名；(r + s) mod n = 0 时验签中 t = 0，签名不可用。
+    /// 不小于 n 的分量先取模再判断；去掉前导零后仍超过 32 字节的分量不可能由签名运算得到，视为退化。
+    pub fn is_degenerate_signature(&self, r: &[u8], s: &[u8]) -> bool {
+        match (fixed_32(r), fixed_32(s)) {
+            (Some(r), Some(s)) => ct_point::is_degenerate(&r, &s),
+            _ => true,
+        }
+    }
 
-        Ok((r.to_vec(), s.to_bytes_be()))
+    /// 校验服务端返回的标量：不超过 32 字节，且位于 [1, n-1]
+    fn server_scalar(field: &str, value: &[u8]) -> Result<[u8; 32]> {
+        let scalar = asn1::left_pad_32(value).map_err(|_| {
+            Error::invalid_server_response(field, format!("is {} bytes, expected at most 32", value.len()))
+        })?;
+        if scalar == [0u8; 32] {
+            return Err(Error::invalid_server_response(field, "is zero"));
+        }
+        if !ct_point::is_valid_scalar(&scalar) {
+            return Err(Error::invalid_server_response(field, "is not less than the curve order n"));
+        }
+        Ok(scalar)
     }
 
     /// 解密预处理：计算 T1 = d1 * C1
-    /// 注意：此功能需要 libsm 的椭圆曲线点乘运算，gm-sdk-rs 不支持
+    ///
+    /// 使用常数时间点乘，见 [`crate::ct_point`]；C1 不在曲线上时返回 `Error::InvalidPoint`。
     pub fn decrypt_prepare(&self, d1: &[u8], c1: &[u8]) -> Result<Vec<u8>> {
         if c1.len() != 64 {
             return Err(Error::Crypto("Invalid C1 length, expected 64 bytes".to_string()));
         }
-        
-        let x_bytes = &c1[0..32];
-        let y_bytes = &c1[32..64];
-        
-        let x = libsm::sm2::field::FieldElem::from_bytes(x_bytes)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let y = libsm::sm2::field::FieldElem::from_bytes(y_bytes)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let c1_point = self.ecc.new_point(&x, &y).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let d1_big = BigUint::from_bytes_be(d1);
-        let t1_point = self.ecc.mul(&d1_big, &c1_point).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let (x, y) = self.ecc.to_affine(&t1_point).map_err(|e| Error::Crypto(e.to_string()))?;
-        let x_bytes = x.to_bytes();
-        let y_bytes = y.to_bytes();
-        
-        let mut t1_bytes = vec![0u8; 64];
-        let x_len = x_bytes.len();
-        let y_len = y_bytes.len();
-        t1_bytes[32 - x_len..32].copy_from_slice(&x_bytes);
-        t1_bytes[64 - y_len..64].copy_from_slice(&y_bytes);
-        
-        Ok(t1_bytes)
+        ct_point::mul_point(d1, c1).map(|t1| t1.to_vec())
     }
 
     /// 完成解密计算
     ///
     /// 数学原理：
     ///   d * C1 = (d1·d2⁻¹ - 1)·C1 = d2⁻¹·d1·C1 - C1 = T2 - C1
-    /// 所以共享点 = T2 - C1（椭圆曲线点减法）
-    /// 再用 KDF 派生密钥流解密 C2，并用 C3 做完整性校验。
+    /// 所以共享点 (x2, y2) = T2 - C1（椭圆曲线点减法）
+    /// 再按 GM/T 0003.4 用 t = KDF(x2 || y2, klen) 解密 C2，并校验 C3 = SM3(x2 || M || y2)，
+    /// 校验不通过时不返回任何明文。
     ///
     /// 参数：
     ///   t2:  服务端返回的 T2 = d2Inv * T1（64字节，x||y）
@@ -188,70 +399,108 @@ impl CoSignProtocol {
         c2: &[u8],
     ) -> Result<Vec<u8>> {
         if t2.len() != 64 {
-            return Err(Error::Crypto("Invalid T2 length, expected 64 bytes".to_string()));
+            return Err(Error::invalid_server_response("t2", format!("is {} bytes, expected 64", t2.len())));
         }
         if c1.len() != 64 {
             return Err(Error::Crypto("Invalid C1 length, expected 64 bytes".to_string()));
         }
+        if c3.len() != 32 {
+            return Err(Error::Crypto("Invalid C3 length, expected 32 bytes".to_string()));
+        }
+        // Reason: T2 = C1 时共享点为无穷远点，KDF 输入固定，无法解密
+        if t2 == c1 {
+            return Err(Error::invalid_server_response("t2", "equals C1, shared point is at infinity"));
+        }
 
-        // 解析 T2 和 C1 为椭圆曲线点
-        let t2_x = libsm::sm2::field::FieldElem::from_bytes(&t2[0..32])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let t2_y = libsm::sm2::field::FieldElem::from_bytes(&t2[32..64])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let t2_point = self.ecc.new_point(&t2_x, &t2_y)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-
-        let c1_x = libsm::sm2::field::FieldElem::from_bytes(&c1[0..32])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let c1_y = libsm::sm2::field::FieldElem::from_bytes(&c1[32..64])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let c1_point = self.ecc.new_point(&c1_x, &c1_y)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-
-        // 计算共享点 = T2 - C1（即 T2 + (-C1)）
-        // Reason: d·C1 = (d1·d2⁻¹-1)·C1 = T2 - C1，需减去 C1 才能得到正确的共享点
-        let neg_c1 = self.ecc.neg(&c1_point)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let shared_point = self.ecc.add(&t2_point, &neg_c1)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-
-        let (sx, sy) = self.ecc.to_affine(&shared_point)
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let sx_bytes = sx.to_bytes();
-        let sy_bytes = sy.to_bytes();
+        // T2 来自服务端，必须是曲线上的点
+        ct_point::validate_point(t2)
+            .map_err(|_| Error::invalid_server_response("t2", "is not a point on the SM2 curve"))?;
 
-        // 拼接完整的共享点坐标（各补零到32字节）
-        let mut shared_coord = vec![0u8; 64];
-        shared_coord[32 - sx_bytes.len()..32].copy_from_slice(&sx_bytes);
-        shared_coord[64 - sy_bytes.len()..64].copy_from_slice(&sy_bytes);
+        // 计算共享点 = T2 - C1
+        // Reason: d·C1 = (d1·d2⁻¹-1)·C1 = T2 - C1，需减去 C1 才能得到正确的共享点
+        let shared_coord = Zeroizing::new(ct_point::sub_point(t2, c1)?);
 
         // 用 KDF 派生密钥流，解密 C2
-        let key_stream = Self::kdf(&shared_coord, c2.len());
-        let plaintext: Vec<u8> = c2.iter().zip(key_stream.iter()).map(|(c, k)| c ^ k).collect();
-
-        // 校验 C3 完整性：C3 = SM3(shared_x || shared_y || plaintext)
-        let mut c3_input = shared_coord.to_vec();
-        c3_input.extend_from_slice(&plaintext);
-        let c3_check = Self::sm3_hash(&c3_input);
-        if c3_check != c3 {
+        let mut plaintext = c2.to_vec();
+        Self::kdf_xor(&shared_coord, &mut plaintext)?;
+
+        // 校验 C3 完整性，失败时清零已还原的明文
+        if !ct_eq(&Self::c3_digest(&shared_coord, &plaintext), c3) {
+            plaintext.zeroize();
             return Err(Error::Crypto("Decryption integrity check failed (C3 mismatch)".to_string()));
         }
 
         Ok(plaintext)
     }
 
+    /// 按 GM/T 0003.4 的 KDF 逐块生成密钥流，交给 `apply` 与 `data` 的对应字节合并
+    ///
+    /// Reason: 按 32 字节分块处理，不分配与消息等长的密钥流缓冲区；z 先吸收进杂凑状态，每块只需补充计数器
+    fn kdf_blocks(z: &[u8], data: &mut [u8], mut apply: impl FnMut(&mut u8, u8)) {
+        let mut prefix = Sm3::new();
+        prefix.update(z);
+        for (ct, chunk) in (1u32..).zip(data.chunks_mut(SM3_DIGEST_LEN)) {
+            let mut hasher = prefix.clone();
+            hasher.update(&ct.to_be_bytes());
+            let block = Zeroizing::new(hasher.finalize());
+            for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+                apply(byte, *key);
+            }
+        }
+    }
+
+    /// 用 t = KDF(x2 || y2, klen) 原地加解密 `data`，t 全为零时按标准拒绝并清零 `data`
+    fn kdf_xor(shared: &[u8; 64], data: &mut [u8]) -> Result<()> {
+        let mut any = 0u8;
+        Self::kdf_blocks(shared, data, |byte, key| {
+            any |= key;
+            *byte ^= key;
+        });
+        if !data.is_empty() && any == 0 {
+            data.zeroize();
+            return Err(Error::Crypto("KDF output is all zeros".to_string()));
+        }
+        Ok(())
+    }
+
+    /// C3 = SM3(x2 || M || y2)（GM/T 0003.4），`shared` 为 64 字节 x2||y2
+    fn c3_digest(shared: &[u8; 64], message: &[u8]) -> [u8; SM3_DIGEST_LEN] {
+        let mut hasher = Sm3::new();
+        hasher.update(&shared[..32]);
+        hasher.update(message);
+        hasher.update(&shared[32..]);
+        hasher.finalize()
+    }
+
+    /// 生成 SM2 密钥对（标准密钥，非协同）
+    /// 返回 (私钥 32 字节, 公钥 x||y 64 字节)，使用 gm-sdk-rs 提供的 API
+    #[cfg(feature = "std")]
+    pub fn generate_keypair() -> (Vec<u8>, Vec<u8>) {
+        let (private_key, public_key) = sm2_generate_keypair();
+        let public_key = public_key.to_vec();
+        // 统一为 64 字节公钥，与 encrypt 等接口一致
+        let public_key = match public_key.len() {
+            65 => public_key[1..].to_vec(),
+            _ => public_key,
+        };
+        (private_key.to_vec(), public_key)
+    }
+
     /// SM2 签名（标准签名，非协同）
     /// 使用 gm-sdk-rs 提供的简洁 API
+    #[cfg(feature = "std")]
     pub fn sign(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
         let sk: [u8; 32] = private_key.try_into()
             .map_err(|_| Error::Crypto("Invalid private key length, expected 32 bytes".to_string()))?;
+        // Reason: gm-sdk-rs 对 0 或 ≥ n 的私钥会 panic，需在调用前拒绝
+        scalar_from_slice(&sk, "private key")?.zeroize();
         let signature = sm2_sign(&sk, message);
         Ok(signature.to_vec())
     }
 
     /// SM2 验签（标准验签，非协同）
     /// 使用 gm-sdk-rs 提供的简洁 API
+    #[cfg(feature = "std")]
     pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
         if signature.len() != 64 {
             return Err(Error::Crypto("Invalid signature length, expected 64 bytes".to_string()));
@@ -271,153 +520,196 @@ impl CoSignProtocol {
             return Err(Error::Crypto("Invalid public key length, expected 64 or 65 bytes".to_string()));
         };
 
+        // Reason: 不在曲线上的公钥可能使 gm-sdk-rs 内部 panic，先按曲线方程校验
+        PublicKey::from_slice(&pk65)?;
+
         let sig: [u8; 64] = signature.try_into()
             .map_err(|_| Error::Crypto("Invalid signature length".to_string()))?;
 
         Ok(sm2_verify(&pk65, message, &sig))
     }
 
+    /// 基于消息哈希 e 的 SM2 验签
+    ///
+    /// e 由调用方预先计算（如 `calculate_message_hash_with_uid`），因此支持自定义用户标识，
+    /// 可用于在本地校验协同签名结果。
+    /// 注意：gm-sdk-rs 只提供基于原文的验签，此处基于 [`crate::ct_point`] 实现
+    pub fn verify_digest(&self, public_key: &[u8], e: &[u8], r: &[u8], s: &[u8]) -> Result<bool> {
+        let pk = match public_key.len() {
+            64 => public_key,
+            65 if public_key[0] == 0x04 => &public_key[1..],
+            _ => return Err(Error::Crypto("Invalid public key length, expected 64 or 65 bytes".to_string())),
+        };
+        let e = fixed_32(e).ok_or_else(|| Error::InvalidParam("Message digest longer than 32 bytes".to_string()))?;
+        // 超过 32 字节的 r、s 必然不小于 n，验签失败
+        let (Some(r), Some(s)) = (fixed_32(r), fixed_32(s)) else {
+            return Ok(false);
+        };
+        ct_point::verify_digest(pk, &e, &r, &s)
+    }
+
     /// SM2 加密（标准加密，非协同）
-    /// 注意：gm-sdk-rs 未提供加密功能，使用 libsm 实现
+    /// 注意：gm-sdk-rs 未提供加密功能，基于 [`crate::ct_point`] 实现
     pub fn encrypt(public_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
         if public_key.len() != 64 {
             return Err(Error::Crypto("Invalid public key length".to_string()));
         }
-        
-        let ecc = EccCtx::new();
-        
-        let x = libsm::sm2::field::FieldElem::from_bytes(&public_key[0..32])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let y = libsm::sm2::field::FieldElem::from_bytes(&public_key[32..64])
-            .map_err(|e| Error::Crypto(e.to_string()))?;
-        let pub_point = ecc.new_point(&x, &y).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let k = ecc.random_uint();
-        
-        let c1 = ecc.g_mul(&k).map_err(|e| Error::Crypto(e.to_string()))?;
-        let (c1_x, c1_y) = ecc.to_affine(&c1).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let k_pa = ecc.mul(&k, &pub_point).map_err(|e| Error::Crypto(e.to_string()))?;
-        let (k_pa_x, k_pa_y) = ecc.to_affine(&k_pa).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let mut kdf_input = Vec::new();
-        kdf_input.extend_from_slice(&k_pa_x.to_bytes());
-        kdf_input.extend_from_slice(&k_pa_y.to_bytes());
-        
-        let kdf_output = Self::kdf(&kdf_input, message.len());
-        let c2: Vec<u8> = message.iter().zip(kdf_output.iter()).map(|(m, k)| m ^ k).collect();
-        
-        let mut c3_input = Vec::new();
-        c3_input.extend_from_slice(&k_pa_x.to_bytes());
-        c3_input.extend_from_slice(&k_pa_y.to_bytes());
-        c3_input.extend_from_slice(message);
-        let c3 = Self::sm3_hash(&c3_input);
-        
-        let mut ciphertext = Vec::new();
-        ciphertext.push(0x04);
-        let c1_x_bytes = c1_x.to_bytes();
-        let c1_y_bytes = c1_y.to_bytes();
-        ciphertext.extend_from_slice(&vec![0u8; 32 - c1_x_bytes.len()]);
-        ciphertext.extend_from_slice(&c1_x_bytes);
-        ciphertext.extend_from_slice(&vec![0u8; 32 - c1_y_bytes.len()]);
-        ciphertext.extend_from_slice(&c1_y_bytes);
-        ciphertext.extend_from_slice(&c3);
-        ciphertext.extend_from_slice(&c2);
-        
-        Ok(ciphertext)
+
+        // Reason: t 全为零时 GM/T 0003.4 要求换新的随机数 k 重新计算，而不是让合法的加密失败
+        loop {
+            // Reason: 知道 k 即可由密文还原共享点，与 D1 同样使用常数时间点乘
+            let k = random_scalar()?;
+            let shared = Zeroizing::new(ct_point::mul_point(&k[..], public_key)?);
+            let c1 = ct_point::mul_base(&k[..])?;
+
+            let c3 = Self::c3_digest(&shared, message);
+
+            let mut ciphertext = Vec::with_capacity(1 + 64 + SM3_DIGEST_LEN + message.len());
+            ciphertext.push(0x04);
+            ciphertext.extend_from_slice(&c1);
+            ciphertext.extend_from_slice(&c3);
+            ciphertext.extend_from_slice(message);
+            if Self::kdf_xor(&shared, &mut ciphertext[1 + 64 + SM3_DIGEST_LEN..]).is_ok() {
+                return Ok(ciphertext);
+            }
+        }
     }
 
     /// SM2 解密（标准解密，非协同）
-    /// 注意：gm-sdk-rs 未提供解密功能，使用 libsm 实现
+    /// 注意：gm-sdk-rs 未提供解密功能，基于 [`crate::ct_point`] 实现
     pub fn decrypt(private_key: &[u8], ciphertext: &[u8]) -> Result<Option<Vec<u8>>> {
-        if ciphertext.len() < 97 {
-            return Ok(None);
-        }
-        
-        let ecc = EccCtx::new();
-        
-        if ciphertext[0] != 0x04 {
-            return Ok(None);
-        }
-        
-        let c1_x = libsm::sm2::field::FieldElem::from_bytes(&ciphertext[1..33])
-            .map_err(|_| Error::Crypto("Invalid C1 x coordinate".to_string()))?;
-        let c1_y = libsm::sm2::field::FieldElem::from_bytes(&ciphertext[33..65])
-            .map_err(|_| Error::Crypto("Invalid C1 y coordinate".to_string()))?;
-        let c1 = ecc.new_point(&c1_x, &c1_y).map_err(|e| Error::Crypto(e.to_string()))?;
-        
-        let c3 = &ciphertext[65..97];
-        let c2 = &ciphertext[97..];
-        
-        let d = BigUint::from_bytes_be(private_key);
-        let d_c1 = ecc.mul(&d, &c1).map_err(|e| Error::Crypto(e.to_string()))?;
-        let (d_c1_x, d_c1_y) = ecc.to_affine(&d_c1).map_err(|e| Error::Crypto(e.to_string()))?;
+        let ciphertext = match Sm2Ciphertext::parse(ciphertext) {
+            Ok(ciphertext) => ciphertext,
+            Err(_) => return Ok(None),
+        };
         
-        let mut kdf_input = Vec::new();
-        kdf_input.extend_from_slice(&d_c1_x.to_bytes());
-        kdf_input.extend_from_slice(&d_c1_y.to_bytes());
+        let c3 = &ciphertext.c3[..];
+        let c2 = ciphertext.c2;
         
-        let kdf_output = Self::kdf(&kdf_input, c2.len());
-        let plaintext: Vec<u8> = c2.iter().zip(kdf_output.iter()).map(|(c, k)| c ^ k).collect();
+        let shared = Zeroizing::new(ct_point::mul_point(private_key, &ciphertext.c1)?);
         
-        let mut c3_input = Vec::new();
-        c3_input.extend_from_slice(&d_c1_x.to_bytes());
-        c3_input.extend_from_slice(&d_c1_y.to_bytes());
-        c3_input.extend_from_slice(&plaintext);
-        let c3_check = Self::sm3_hash(&c3_input);
+        let mut plaintext = c2.to_vec();
+        Self::kdf_xor(&shared, &mut plaintext)?;
         
-        if c3_check != c3 {
+        if !ct_eq(&Self::c3_digest(&shared, &plaintext), c3) {
+            plaintext.zeroize();
             return Ok(None);
         }
         
         Ok(Some(plaintext))
     }
 
-    /// KDF 密钥派生函数
+    /// KDF 密钥派生函数（GM/T 0003.4，基于 SM3），输出 `klen` 字节
     /// 注意：gm-sdk-rs 未提供 KDF 功能
-    fn kdf(z: &[u8], klen: usize) -> Vec<u8> {
-        let mut result = Vec::new();
-        let mut ct = 1u32;
-        
-        while result.len() < klen {
-            let mut input = z.to_vec();
-            input.extend_from_slice(&ct.to_be_bytes());
-            let hash = Self::sm3_hash(&input);
-            result.extend_from_slice(&hash);
-            ct += 1;
-        }
-        
-        result.truncate(klen);
+    pub fn kdf(z: &[u8], klen: usize) -> Vec<u8> {
+        let mut result = vec![0u8; klen];
+        Self::kdf_blocks(z, &mut result, |byte, key| *byte = key);
         result
     }
 }
 
+/// 从系统随机源填充 `buf`
+///
+/// 经 getrandom 取数：常见操作系统与 wasm32（`js`）开箱可用，没有操作系统随机源的嵌入式目标
+/// 需按 getrandom 文档用 `register_custom_getrandom!` 接入硬件随机数发生器。
+pub(crate) fn fill_random(buf: &mut [u8]) -> Result<()> {
+    getrandom::getrandom(buf).map_err(|e| Error::Crypto(format!("Random source unavailable: {}", e)))
+}
+
+/// 生成 [1, n-1] 内的随机标量
+///
+/// Reason: 拒绝采样而非取模，避免取模带来的偏差；n 接近 2^256，重新采样的概率可以忽略
+fn random_scalar() -> Result<Zeroizing<[u8; 32]>> {
+    let mut k = Zeroizing::new([0u8; 32]);
+    loop {
+        fill_random(&mut k[..])?;
+        if ct_point::is_valid_scalar(&k) {
+            return Ok(k);
+        }
+    }
+}
+
+/// 去掉前导零后左补零为 32 字节，仍超过 32 字节时返回 None
+fn fixed_32(value: &[u8]) -> Option<[u8; 32]> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    asn1::left_pad_32(&value[start..]).ok()
+}
+
+/// 校验服务端返回的曲线点：64 字节 x||y 或 65 字节 04||x||y，且在曲线上
+pub(crate) fn server_point(field: &str, value: &[u8]) -> Result<[u8; 64]> {
+    let coords = match value.len() {
+        64 => value,
+        65 if value[0] == 0x04 => &value[1..],
+        len => {
+            return Err(Error::invalid_server_response(field, format!("is {} bytes, expected 64 or 65", len)));
+        }
+    };
+    ct_point::validate_point(coords).map_err(|_| Error::invalid_server_response(field, "is not on the SM2 curve"))?;
+    Ok(coords.try_into().expect("point is 64 bytes"))
+}
+
+/// 定长比较，耗时与首个不同字节的位置无关
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 impl Default for CoSignProtocol {
     fn default() -> Self {
         Self::new().expect("Failed to create protocol")
     }
 }
 
-/// Base64 编码
+/// Base64 编码（[`Encoding::Base64`]）
 pub fn base64_encode(data: &[u8]) -> String {
-    BASE64.encode(data)
+    Encoding::Base64.encode_str(data).expect("Base64 is a text encoding")
 }
 
-/// Base64 解码
+/// Base64 解码（[`Encoding::Base64`]）
 pub fn base64_decode(data: &str) -> Result<Vec<u8>> {
-    BASE64.decode(data).map_err(|e| Error::Encoding(e.to_string()))
+    Encoding::Base64.decode_str(data)
+}
+
+/// Base64URL 编码（[`Encoding::Base64Url`]，URL 安全字符集，无填充）
+pub fn base64url_encode(data: &[u8]) -> String {
+    Encoding::Base64Url.encode_str(data).expect("Base64URL is a text encoding")
+}
+
+/// Base64URL 解码（[`Encoding::Base64Url`]，URL 安全字符集，无填充）
+pub fn base64url_decode(data: &str) -> Result<Vec<u8>> {
+    Encoding::Base64Url.decode_str(data)
+}
+
+/// Hex 编码（[`Encoding::Hex`]，小写）
+pub fn hex_encode(data: &[u8]) -> String {
+    Encoding::Hex.encode_str(data).expect("hex is a text encoding")
+}
+
+/// Hex 解码（[`Encoding::Hex`]，大小写均可）
+pub fn hex_decode(data: &str) -> Result<Vec<u8>> {
+    Encoding::Hex.decode_str(data)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::simulator::{ecc, scalar_bytes};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_signing_session_debug_redacts_k1() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let session = protocol.sign_prepare().unwrap();
+        let debug = format!("{:?}", session);
+        let (k1, _) = session.into_parts();
+        assert!(!debug.contains(&format!("{:?}", k1.as_bytes())));
+        assert!(debug.contains(crate::types::REDACTED));
+    }
 
     #[test]
     fn test_generate_d1() {
         let protocol = CoSignProtocol::new().unwrap();
         let d1 = protocol.generate_d1().unwrap();
-        assert!(!d1.is_empty());
-        assert!(d1.len() <= 32);
+        assert_eq!(d1.len(), 32);
     }
 
     #[test]
@@ -435,27 +727,218 @@ mod tests {
         assert_eq!(hash.len(), 32);
     }
 
+    #[test]
+    fn test_calculate_za() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+
+        let za = CoSignProtocol::calculate_za(DEFAULT_USER_ID, &p1).unwrap();
+        assert_eq!(za.len(), 32);
+
+        // 64 字节与 65 字节公钥格式结果一致
+        let mut p1_65 = vec![0x04];
+        p1_65.extend_from_slice(&p1);
+        assert_eq!(CoSignProtocol::calculate_za(DEFAULT_USER_ID, &p1_65).unwrap(), za);
+
+        // 不同 uid 得到不同 ZA
+        assert_ne!(CoSignProtocol::calculate_za(b"alice", &p1).unwrap(), za);
+        assert!(CoSignProtocol::calculate_za(DEFAULT_USER_ID, &p1[..32]).is_err());
+    }
+
+    #[test]
+    fn test_kdf_blocks_match_definition() {
+        let z = CoSignProtocol::generate_random(64);
+        for klen in [0, 1, 31, 32, 33, 100] {
+            // t = Hash(Z || 1) || Hash(Z || 2) || ...，截取 klen 字节
+            let mut expected = Vec::new();
+            for ct in 1u32..=4 {
+                expected.extend(CoSignProtocol::sm3_hash(&[&z[..], &ct.to_be_bytes()].concat()));
+            }
+            expected.truncate(klen);
+            assert_eq!(CoSignProtocol::kdf(&z, klen), expected);
+
+            let shared: [u8; 64] = z.clone().try_into().unwrap();
+            let mut data = vec![0x5au8; klen];
+            CoSignProtocol::kdf_xor(&shared, &mut data).unwrap();
+            let xored: Vec<u8> = expected.iter().map(|k| k ^ 0x5a).collect();
+            assert_eq!(data, xored);
+        }
+    }
+
+    #[test]
+    fn test_za_cache() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let (_, p1) = CoSignProtocol::generate_keypair();
+        let (_, other) = CoSignProtocol::generate_keypair();
+        let za: [u8; 32] = CoSignProtocol::calculate_za(DEFAULT_USER_ID, &p1).unwrap().try_into().unwrap();
+
+        assert!(protocol.cached_za_input().is_none());
+        assert_eq!(protocol.za(DEFAULT_USER_ID, &p1).unwrap(), za);
+        let (uid, public_key) = protocol.cached_za_input().unwrap();
+        assert_eq!((&uid[..], &public_key[..]), (DEFAULT_USER_ID, &p1[..]));
+        // 命中缓存，65 字节格式与 64 字节视为同一公钥
+        assert_eq!(protocol.za(DEFAULT_USER_ID, &[&[0x04][..], &p1].concat()).unwrap(), za);
+        // 公钥或 uid 变化时重新计算
+        assert_eq!(protocol.za(DEFAULT_USER_ID, &other).unwrap(), CoSignProtocol::calculate_za(DEFAULT_USER_ID, &other).unwrap()[..]);
+        assert_eq!(protocol.za(b"alice", &other).unwrap(), CoSignProtocol::calculate_za(b"alice", &other).unwrap()[..]);
+        assert_eq!(protocol.za(DEFAULT_USER_ID, &p1).unwrap(), za);
+        // 非法公钥不写入缓存
+        assert!(protocol.za(DEFAULT_USER_ID, &p1[..32]).is_err());
+        assert_eq!(protocol.za(DEFAULT_USER_ID, &p1).unwrap(), za);
+    }
+
+    #[test]
+    fn test_message_hash_with_uid() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        let message = b"hello world";
+
+        let e = protocol.calculate_message_hash_with_uid(message, DEFAULT_USER_ID, &p1).unwrap();
+        assert_eq!(e.len(), 32);
+        assert_ne!(e, CoSignProtocol::sm3_hash(message));
+
+        let mut input = CoSignProtocol::calculate_za(DEFAULT_USER_ID, &p1).unwrap();
+        input.extend_from_slice(message);
+        assert_eq!(e, CoSignProtocol::sm3_hash(&input));
+    }
+
+    #[test]
+    fn test_message_hash_modes() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let message = b"hello world";
+
+        let e = protocol.calculate_message_hash(message, &p1, DigestMode::Sm3).unwrap();
+        assert_eq!(e, CoSignProtocol::sm3_hash(message));
+        let za = protocol.calculate_message_hash(message, &p1, DigestMode::Za).unwrap();
+        assert_eq!(za, protocol.calculate_message_hash_with_uid(message, DEFAULT_USER_ID, &p1).unwrap());
+
+        // 预计算的 e 原样使用，长度不是 32 字节时拒绝
+        assert_eq!(protocol.calculate_message_hash(&za, &p1, DigestMode::Prehashed).unwrap(), za);
+        assert!(matches!(
+            protocol.calculate_message_hash(message, &p1, DigestMode::Prehashed),
+            Err(Error::InvalidParam(_))
+        ));
+    }
+
+    #[test]
+    fn test_refresh_d1() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let factor = protocol.generate_d1().unwrap();
+
+        let refreshed = protocol.refresh_d1(&d1, &factor).unwrap();
+        assert_ne!(refreshed, d1);
+        assert!(protocol.refresh_d1(&d1, &[0u8; 32]).is_err());
+    }
+
     #[test]
     fn test_sign_prepare() {
         let protocol = CoSignProtocol::new().unwrap();
-        let (k1, q1) = protocol.sign_prepare().unwrap();
-        assert!(!k1.is_empty());
-        assert_eq!(q1.len(), 64);
+        let session = protocol.sign_prepare().unwrap();
+        assert_eq!(session.q1().len(), 64);
+        let q1 = session.q1().to_vec();
+        let (k1, parts_q1) = session.into_parts();
+        assert_eq!(k1.len(), 32);
+        assert_eq!(q1, parts_q1);
     }
 
     #[test]
     fn test_complete_signature() {
         let protocol = CoSignProtocol::new().unwrap();
         let d1 = protocol.generate_d1().unwrap();
-        let (k1, _q1) = protocol.sign_prepare().unwrap();
+        let session = protocol.sign_prepare().unwrap();
         
         let r = CoSignProtocol::generate_random(32);
         let s2 = CoSignProtocol::generate_random(32);
         let s3 = CoSignProtocol::generate_random(32);
         
-        let (r_out, s) = protocol.complete_signature(&k1, &d1, &r, &s2, &s3).unwrap();
-        assert_eq!(r_out.len(), 32);
-        assert!(s.len() <= 32);
+        let signature = session.complete(&protocol, &d1, &r, &s2, &s3).unwrap();
+        assert_eq!(signature.r.len(), 32);
+        assert_eq!(signature.s.len(), 32);
+    }
+
+    #[test]
+    fn test_is_degenerate_signature() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let n = ecc().get_n().clone();
+        let r = CoSignProtocol::sm3_hash(b"r");
+        assert!(!protocol.is_degenerate_signature(&r, &CoSignProtocol::sm3_hash(b"s")));
+        assert!(protocol.is_degenerate_signature(&r, &[0u8; 32]));
+        assert!(protocol.is_degenerate_signature(&[0u8; 32], &r));
+        // s = n - r
+        let s = scalar_bytes(&(&n - BigUint::from_bytes_be(&r) % &n));
+        assert!(protocol.is_degenerate_signature(&r, &s));
+        // 带前导零的分量按数值判断，超过 32 字节的分量视为退化
+        assert!(protocol.is_degenerate_signature(&r, &[&[0u8][..], &s].concat()));
+        assert!(!protocol.is_degenerate_signature(&[&[0u8][..], &r].concat(), &r));
+        assert!(protocol.is_degenerate_signature(&r, &[1u8; 33]));
+    }
+
+    #[test]
+    fn test_scalars_are_fixed_width() {
+        assert_eq!(scalar_bytes(&BigUint::from(1u32))[..], [vec![0u8; 31], vec![1]].concat());
+
+        let protocol = CoSignProtocol::new().unwrap();
+        for _ in 0..64 {
+            assert_eq!(protocol.generate_d1().unwrap().len(), 32);
+            assert_eq!(protocol.sign_prepare().unwrap().into_parts().0.len(), 32);
+        }
+
+        // 服务端返回高位为零、去掉前导零的 r 时，输出仍为 32 字节，且与补齐后的输入结果一致
+        let d1 = protocol.generate_d1().unwrap();
+        let k1 = protocol.sign_prepare().unwrap().into_parts().0;
+        let mut r = CoSignProtocol::generate_random(32);
+        r[0] = 0;
+        let s2 = CoSignProtocol::generate_random(32);
+        let s3 = CoSignProtocol::generate_random(32);
+        let short = protocol.complete_signature(&k1, &d1, &r[1..], &s2, &s3).unwrap();
+        let full = protocol.complete_signature(&k1, &d1, &r, &s2, &s3).unwrap();
+        assert_eq!(short, full);
+        assert_eq!(short.0, r);
+    }
+
+    #[test]
+    fn test_complete_signature_rejects_bad_server_values() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let k1 = CoSignProtocol::generate_random(32);
+        let valid = [0x11u8; 32];
+        let n = ecc().get_n().to_bytes_be();
+
+        let field_of = |r: &[u8], s2: &[u8], s3: &[u8]| match protocol.complete_signature(&k1, &d1, r, s2, s3) {
+            Err(Error::InvalidServerResponse { field, .. }) => field,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(field_of(&[0x11; 33], &valid, &valid), "r");
+        assert_eq!(field_of(&valid, &[0u8; 32], &valid), "s2");
+        assert_eq!(field_of(&valid, &valid, &[]), "s3");
+        assert_eq!(field_of(&n, &valid, &valid), "r");
+        assert_eq!(field_of(&valid, &valid, &[0xff; 32]), "s3");
+
+        // 短于 32 字节的合法值（高位为零）仍然接受
+        assert!(protocol.complete_signature(&k1, &d1, &valid[..31], &valid, &valid).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_inputs() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let n = ecc().get_n().to_bytes_be();
+        let valid = [0x11u8; 32];
+
+        assert!(matches!(CoSignProtocol::sign(&[0u8; 32], b"msg"), Err(Error::InvalidParam(_))));
+        assert!(matches!(CoSignProtocol::sign(&n, b"msg"), Err(Error::InvalidParam(_))));
+
+        let mut off_curve = [0u8; 64];
+        off_curve[31] = 1;
+        off_curve[63] = 1;
+        assert!(matches!(CoSignProtocol::verify(&off_curve, b"msg", &[1u8; 64]), Err(Error::InvalidPoint(_))));
+
+        assert!(matches!(protocol.complete_signature(&valid, &[0u8; 32], &valid, &valid, &valid), Err(Error::InvalidParam(_))));
+        assert!(matches!(protocol.complete_signature(&n, &valid, &valid, &valid, &valid), Err(Error::InvalidParam(_))));
+        assert!(matches!(protocol.complete_signature(&[], &valid, &valid, &valid, &valid), Err(Error::InvalidParam(_))));
     }
 
     #[test]
@@ -473,6 +956,43 @@ mod tests {
         assert!(valid);
     }
 
+    #[test]
+    fn test_generate_keypair() {
+        let (private_key, public_key) = CoSignProtocol::generate_keypair();
+        assert_eq!(private_key.len(), 32);
+        assert_eq!(public_key.len(), 64);
+
+        let message = b"hello world";
+        let signature = CoSignProtocol::sign(&private_key, message).unwrap();
+        assert!(CoSignProtocol::verify(&public_key, message, &signature).unwrap());
+
+        let ciphertext = CoSignProtocol::encrypt(&public_key, message).unwrap();
+        assert_eq!(CoSignProtocol::decrypt(&private_key, &ciphertext).unwrap().unwrap(), message);
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        use gm_sdk::sm2::sm2_generate_keypair;
+
+        let protocol = CoSignProtocol::new().unwrap();
+        let (private_key, public_key) = sm2_generate_keypair();
+        let message = b"hello world";
+
+        // 标准签名使用默认用户标识计算 ZA
+        let signature = CoSignProtocol::sign(&private_key, message).unwrap();
+        let e = protocol
+            .calculate_message_hash_with_uid(message, DEFAULT_USER_ID, &public_key)
+            .unwrap();
+        assert!(protocol.verify_digest(&public_key, &e, &signature[..32], &signature[32..]).unwrap());
+
+        // 其他用户标识得到的 e 无法通过验签
+        let other = protocol
+            .calculate_message_hash_with_uid(message, b"alice", &public_key)
+            .unwrap();
+        assert!(!protocol.verify_digest(&public_key, &other, &signature[..32], &signature[32..]).unwrap());
+        assert!(!protocol.verify_digest(&public_key, &e, &[0u8; 32], &signature[32..]).unwrap());
+    }
+
     #[test]
     fn test_sm2_encrypt_decrypt() {
         let protocol = CoSignProtocol::new().unwrap();
@@ -491,6 +1011,18 @@ mod tests {
         let plaintext = CoSignProtocol::decrypt(&sk, &ciphertext).unwrap();
         assert!(plaintext.is_some());
         assert_eq!(plaintext.unwrap().as_slice(), message);
+
+        // C3 = SM3(x2 || M || y2)，(x2, y2) = d·C1
+        let shared = ct_point::mul_point(&sk, &ciphertext[1..65]).unwrap();
+        let mut input = shared[..32].to_vec();
+        input.extend_from_slice(message);
+        input.extend_from_slice(&shared[32..]);
+        assert_eq!(&ciphertext[65..97], CoSignProtocol::sm3_hash(&input).as_slice());
+
+        // C3 不匹配时不返回明文
+        let mut tampered = ciphertext.clone();
+        tampered[70] ^= 1;
+        assert!(CoSignProtocol::decrypt(&sk, &tampered).unwrap().is_none());
     }
 
     #[test]
@@ -500,4 +1032,21 @@ mod tests {
         let decoded = base64_decode(&encoded).unwrap();
         assert_eq!(data.to_vec(), decoded);
     }
+
+    #[test]
+    fn test_base64url() {
+        let data = [0xfbu8, 0xff, 0xfe];
+        let encoded = base64url_encode(&data);
+        assert_eq!(encoded, "-__-");
+        assert_eq!(base64url_decode(&encoded).unwrap(), data.to_vec());
+    }
+
+    #[test]
+    fn test_hex() {
+        let data = b"hello world";
+        let encoded = hex_encode(data);
+        assert_eq!(encoded, "68656c6c6f20776f726c64");
+        assert_eq!(hex_decode(&encoded.to_uppercase()).unwrap(), data.to_vec());
+        assert!(hex_decode("abc").is_err());
+    }
 }
@@ -0,0 +1,397 @@
+//! 签名与解密的操作回执
+//!
+//! 配置 [`ClientConfig::receipts`](crate::ClientConfig) 后，每次协同签名、解密成功时客户端以本地 SM2 密钥签发一张回执，
+//! 记录操作类型、待签名摘要（解密为密文的 SM3）、时间与服务端响应的 SM3，并包含上一张回执的杂凑，形成链。
+//! 回执由用户自己的密钥签名、不依赖服务端，证明链中的每张回执由该密钥签发、内容未被改动。
+//!
+//! 链只对已持有较早链头（某张回执的 [`Receipt::hash`]）的一方防篡改：用户持有签名私钥，可以重新签发一条
+//! 省略或插入了记录的完整替换链，因此回执本身不能证明某次操作“从未”发生。需要这一点时，应定期把链头交给
+//! 用户无法控制的一方（审计方、服务端日志等）留存，之后导出的链必须经过该链头才可信。
+//!
+//! 签名的编码（版本 1，整数均为大端）：
+//!
+//! ```text
+//! "SM2-COSIGN-RECEIPT-V1" || version:u32 || sequence:u64 || operation:u8 || len(user_id):u32 || user_id
+//!     || digest(32) || timestamp:u64 || response_hash(32) || previous(32)
+//!     || len(transaction_id):u32 || transaction_id || signer(64 字节 x||y)
+//! ```
+//!
+//! - `operation`：0 为签名，1 为解密
+//! - `response_hash`：服务端响应业务数据（JSON）的 SM3
+//! - `previous`：上一张回执的 [`Receipt::hash`]，链的第一张为全零
+//! - `transaction_id`：签名时 [`crate::SignOptions`] 的交易流水号，未设置时长度为 0
+//!
+//! 回执的签名为对上述编码的标准 SM2 签名（默认用户标识），r||s 共 64 字节。
+
+use crate::ct_point;
+use crate::error::{Error, Result};
+use crate::protocol::CoSignProtocol;
+use crate::secret::{scalar_from_slice, PublicKey};
+use crate::sm3::Sm3;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// 回执格式版本
+pub const RECEIPT_VERSION: u32 = 1;
+/// 签名编码的域分隔串
+pub const RECEIPT_DOMAIN: &[u8] = b"SM2-COSIGN-RECEIPT-V1";
+/// 尚未取出的回执上限，防止长时间运行且从不取出的客户端无限占用内存
+pub const MAX_PENDING_RECEIPTS: usize = 10_000;
+
+/// 回执记录的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptOperation {
+    /// 协同签名
+    Sign,
+    /// 协同解密
+    Decrypt,
+}
+
+impl ReceiptOperation {
+    fn code(self) -> u8 {
+        match self {
+            ReceiptOperation::Sign => 0,
+            ReceiptOperation::Decrypt => 1,
+        }
+    }
+}
+
+/// 操作回执
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub version: u32,
+    /// 在链中的序号，从 0 开始
+    pub sequence: u64,
+    pub operation: ReceiptOperation,
+    pub user_id: String,
+    /// 签名为消息哈希 e，解密为密文的 SM3（十六进制）
+    #[serde(with = "hex_array")]
+    pub digest: [u8; 32],
+    /// 签发时间（Unix 秒）
+    pub timestamp: u64,
+    /// 服务端响应业务数据的 SM3（十六进制）
+    #[serde(with = "hex_array")]
+    pub response_hash: [u8; 32],
+    /// 上一张回执的杂凑（十六进制），链的第一张为全零
+    #[serde(with = "hex_array")]
+    pub previous: [u8; 32],
+    /// 签名时的交易流水号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    /// 签发回执的公钥
+    pub signer: PublicKey,
+    /// 回执签名 r||s（十六进制）
+    #[serde(with = "hex_array")]
+    pub signature: [u8; 64],
+}
+
+impl Receipt {
+    /// 按版本 1 规则编码，回执签名的即为此字节串
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let transaction_id = self.transaction_id.as_deref().unwrap_or_default();
+        // Reason: 定长字段共 189 字节（版本、序号、操作、两个长度前缀、三个杂凑、时间与公钥）
+        let mut out = Vec::with_capacity(RECEIPT_DOMAIN.len() + 189 + self.user_id.len() + transaction_id.len());
+        out.extend_from_slice(RECEIPT_DOMAIN);
+        out.extend_from_slice(&self.version.to_be_bytes());
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        out.push(self.operation.code());
+        out.extend_from_slice(&(self.user_id.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.user_id.as_bytes());
+        out.extend_from_slice(&self.digest);
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&self.response_hash);
+        out.extend_from_slice(&self.previous);
+        out.extend_from_slice(&(transaction_id.len() as u32).to_be_bytes());
+        out.extend_from_slice(transaction_id.as_bytes());
+        out.extend_from_slice(self.signer.as_bytes());
+        out
+    }
+
+    /// 回执杂凑 SM3(编码 || 签名)，下一张回执的 `previous`
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sm3::new();
+        hasher.update(&self.to_bytes());
+        hasher.update(&self.signature);
+        hasher.finalize()
+    }
+
+    /// 以回执中的公钥验证签名
+    pub fn verify(&self) -> Result<bool> {
+        if self.version != RECEIPT_VERSION {
+            return Err(Error::Encoding(format!("Unsupported receipt version {}", self.version)));
+        }
+        CoSignProtocol::verify(self.signer.as_bytes(), &self.to_bytes(), &self.signature)
+    }
+
+    /// 解析一行 JSON
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).map_err(|e| Error::Encoding(format!("Invalid receipt: {}", e)))
+    }
+
+    /// 编码为单行 JSON，便于逐行追加保存
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Encoding(e.to_string()))
+    }
+}
+
+/// 校验回执链：每张回执的签名、签发公钥一致、序号连续且 `previous` 与上一张的杂凑一致
+///
+/// 链从序号 0 开始时第一张的 `previous` 须为全零；导出的片段从中间开始时不检查第一张的 `previous`。
+/// `signer` 为 `Some` 时同时要求回执由该公钥签发。
+pub fn verify_chain(receipts: &[Receipt], signer: Option<&PublicKey>) -> Result<()> {
+    let broken = |sequence: u64, reason: &str| Err(Error::Crypto(format!("Receipt {} {}", sequence, reason)));
+    let Some(first) = receipts.first() else {
+        return Ok(());
+    };
+    let signer = signer.unwrap_or(&first.signer);
+    if first.sequence == 0 && first.previous != [0u8; 32] {
+        return broken(0, "starts the chain but has a non-zero previous hash");
+    }
+    let mut previous: Option<&Receipt> = None;
+    for receipt in receipts {
+        if &receipt.signer != signer {
+            return broken(receipt.sequence, "is signed by a different key");
+        }
+        if !receipt.verify()? {
+            return broken(receipt.sequence, "has an invalid signature");
+        }
+        if let Some(previous) = previous {
+            if previous.sequence.checked_add(1) != Some(receipt.sequence) {
+                return broken(receipt.sequence, &format!("does not follow receipt {}", previous.sequence));
+            }
+            if receipt.previous != previous.hash() {
+                return broken(receipt.sequence, "is not chained to the previous receipt");
+            }
+        }
+        previous = Some(receipt);
+    }
+    Ok(())
+}
+
+/// 回执链的签发方：持有本地签名私钥与链尾状态
+///
+/// 签发的回执暂存在内存中，调用方须定期以 [`ReceiptChain::take_issued`] 取出并自行持久化（如逐行追加到文件）。
+/// 暂存达到 [`MAX_PENDING_RECEIPTS`] 张时不再签发，签名、解密返回错误，直至取出。
+pub struct ReceiptChain {
+    private_key: Zeroizing<Vec<u8>>,
+    signer: PublicKey,
+    state: Mutex<ChainState>,
+    /// 暂存回执的上限
+    max_pending: usize,
+}
+
+struct ChainState {
+    next_sequence: u64,
+    previous: [u8; 32],
+    issued: Vec<Receipt>,
+}
+
+impl ReceiptChain {
+    /// 以 `private_key` 开始新的回执链
+    pub fn new(private_key: &[u8]) -> Result<Self> {
+        let scalar = Zeroizing::new(scalar_from_slice(private_key, "receipt private key")?);
+        Ok(Self {
+            private_key: Zeroizing::new(scalar.to_vec()),
+            signer: PublicKey::from_slice(&ct_point::mul_base(&scalar[..])?)?,
+            state: Mutex::new(ChainState { next_sequence: 0, previous: [0u8; 32], issued: Vec::new() }),
+            max_pending: MAX_PENDING_RECEIPTS,
+        })
+    }
+
+    /// 接着已有的最后一张回执继续签发；回执须由同一私钥签发且签名有效，序号已是最大值时返回 `Error::InvalidParam`
+    pub fn resume(private_key: &[u8], last: &Receipt) -> Result<Self> {
+        let chain = Self::new(private_key)?;
+        if last.signer != chain.signer {
+            return Err(Error::InvalidParam("Last receipt was issued by a different key".to_string()));
+        }
+        if !last.verify()? {
+            return Err(Error::Crypto(format!("Receipt {} has an invalid signature", last.sequence)));
+        }
+        let next_sequence = last
+            .sequence
+            .checked_add(1)
+            .ok_or_else(|| Error::InvalidParam(format!("Receipt sequence {} cannot be continued", last.sequence)))?;
+        {
+            let mut state = chain.lock();
+            state.next_sequence = next_sequence;
+            state.previous = last.hash();
+        }
+        Ok(chain)
+    }
+
+    /// 签发回执的公钥
+    pub fn signer(&self) -> &PublicKey {
+        &self.signer
+    }
+
+    /// 签发一张回执并链接到上一张；暂存已满或序号已用尽时返回 `Error::InvalidState`
+    pub fn issue(
+        &self,
+        operation: ReceiptOperation,
+        user_id: &str,
+        digest: &[u8; 32],
+        response_hash: &[u8; 32],
+        transaction_id: Option<&str>,
+    ) -> Result<Receipt> {
+        let mut state = self.lock();
+        let next_sequence = state
+            .next_sequence
+            .checked_add(1)
+            .ok_or_else(|| Error::InvalidState("Receipt sequence exhausted".to_string()))?;
+        if state.issued.len() >= self.max_pending {
+            return Err(Error::InvalidState(format!(
+                "{} receipts are pending, drain them with take_issued before issuing more",
+                state.issued.len()
+            )));
+        }
+        let mut receipt = Receipt {
+            version: RECEIPT_VERSION,
+            sequence: state.next_sequence,
+            operation,
+            user_id: user_id.to_string(),
+            digest: *digest,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            response_hash: *response_hash,
+            previous: state.previous,
+            transaction_id: transaction_id.map(str::to_string),
+            signer: self.signer.clone(),
+            signature: [0u8; 64],
+        };
+        let signature = CoSignProtocol::sign(&self.private_key, &receipt.to_bytes())?;
+        receipt.signature.copy_from_slice(&signature);
+
+        state.next_sequence = next_sequence;
+        state.previous = receipt.hash();
+        state.issued.push(receipt.clone());
+        Ok(receipt)
+    }
+
+    /// 取出已签发但尚未取出的回执
+    pub fn take_issued(&self) -> Vec<Receipt> {
+        std::mem::take(&mut self.lock().issued)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ChainState> {
+        // Reason: 状态只在签发成功后整体更新，持锁线程 panic 不会留下不一致的链尾
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Debug for ReceiptChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiptChain")
+            .field("private_key", &crate::types::REDACTED)
+            .field("signer", &self.signer)
+            .field("next_sequence", &self.lock().next_sequence)
+            .finish()
+    }
+}
+
+/// 定长字节数组的十六进制 serde
+mod hex_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = hex::decode(text).map_err(serde::de::Error::custom)?;
+        let len = bytes.len();
+        bytes.try_into().map_err(|_| serde::de::Error::custom(format!("expected {} bytes, got {}", N, len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain_of(count: usize) -> (Vec<u8>, Vec<Receipt>) {
+        let (private_key, _) = CoSignProtocol::generate_keypair();
+        let chain = ReceiptChain::new(&private_key).unwrap();
+        for i in 0..count {
+            let operation = if i % 2 == 0 { ReceiptOperation::Sign } else { ReceiptOperation::Decrypt };
+            chain.issue(operation, "user-1", &[i as u8; 32], &[0xaa; 32], Some("TX-1")).unwrap();
+        }
+        (private_key, chain.take_issued())
+    }
+
+    #[test]
+    fn test_receipt_chain() {
+        let (private_key, receipts) = chain_of(3);
+        assert_eq!(receipts.iter().map(|r| r.sequence).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(receipts[0].previous, [0u8; 32]);
+        assert_eq!(receipts[2].previous, receipts[1].hash());
+        verify_chain(&receipts, None).unwrap();
+
+        // 逐行 JSON 往返
+        let decoded = Receipt::from_json(receipts[1].to_json().unwrap().as_bytes()).unwrap();
+        assert_eq!(decoded, receipts[1]);
+
+        // 接着最后一张继续签发
+        let chain = ReceiptChain::resume(&private_key, &receipts[2]).unwrap();
+        let next = chain.issue(ReceiptOperation::Sign, "user-1", &[9; 32], &[0xbb; 32], None).unwrap();
+        assert_eq!(next.sequence, 3);
+        verify_chain(&[receipts[2].clone(), next], Some(chain.signer())).unwrap();
+        assert!(!format!("{:?}", chain).contains(&hex::encode(&private_key)));
+    }
+
+    #[test]
+    fn test_receipt_chain_detects_tampering() {
+        let (_, receipts) = chain_of(3);
+
+        // 删除中间一张
+        let gap = [receipts[0].clone(), receipts[2].clone()];
+        assert!(matches!(verify_chain(&gap, None), Err(Error::Crypto(_))));
+
+        // 修改内容
+        let mut tampered = receipts.clone();
+        tampered[1].digest[0] ^= 1;
+        assert!(matches!(verify_chain(&tampered, None), Err(Error::Crypto(_))));
+
+        // 其他私钥签发的回执无法接入
+        let (other_key, _) = CoSignProtocol::generate_keypair();
+        assert!(matches!(ReceiptChain::resume(&other_key, &receipts[2]), Err(Error::InvalidParam(_))));
+        let (_, other) = chain_of(1);
+        assert!(verify_chain(&receipts, Some(&other[0].signer)).is_err());
+    }
+
+    #[test]
+    fn test_resume_rejects_exhausted_sequence() {
+        let (private_key, receipts) = chain_of(1);
+        let mut last = receipts[0].clone();
+        last.sequence = u64::MAX;
+        last.signature.copy_from_slice(&CoSignProtocol::sign(&private_key, &last.to_bytes()).unwrap());
+        assert!(matches!(ReceiptChain::resume(&private_key, &last), Err(Error::InvalidParam(_))));
+
+        // 续接到最后一个序号后不再签发，而不是回绕到 0
+        last.sequence = u64::MAX - 1;
+        last.signature.copy_from_slice(&CoSignProtocol::sign(&private_key, &last.to_bytes()).unwrap());
+        let chain = ReceiptChain::resume(&private_key, &last).unwrap();
+        let result = chain.issue(ReceiptOperation::Sign, "user-1", &[1; 32], &[2; 32], None);
+        assert!(matches!(result, Err(Error::InvalidState(_))));
+    }
+
+    #[test]
+    fn test_pending_receipts_are_capped() {
+        let (private_key, _) = CoSignProtocol::generate_keypair();
+        let mut chain = ReceiptChain::new(&private_key).unwrap();
+        chain.max_pending = 2;
+        for _ in 0..2 {
+            chain.issue(ReceiptOperation::Sign, "user-1", &[1; 32], &[2; 32], None).unwrap();
+        }
+        let result = chain.issue(ReceiptOperation::Sign, "user-1", &[1; 32], &[2; 32], None);
+        assert!(matches!(result, Err(Error::InvalidState(_))));
+
+        // 取出后继续签发，链不中断
+        let drained = chain.take_issued();
+        let next = chain.issue(ReceiptOperation::Sign, "user-1", &[1; 32], &[2; 32], None).unwrap();
+        assert_eq!(next.sequence, 2);
+        verify_chain(&[drained[1].clone(), next], None).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! 服务端响应外层格式
+//!
+//! 默认格式为 `{"code": 0, "message": "...", "data": {...}}`。部分网关改用 `{status, msg, result}` 等字段，
+//! 可通过 [`ClientConfig::envelope`](crate::ClientConfig) 选择 [`FieldEnvelope`] 的其他字段名，
+//! 或实现 [`ResponseEnvelope`] 支持完全不同的结构。
+
+use crate::error::{Error, Result};
+use serde_json::Value;
+
+/// 响应外层格式：从完整响应 JSON 中取出业务数据
+pub trait ResponseEnvelope: std::fmt::Debug + Send + Sync {
+    /// 成功时返回业务数据（响应未携带时为 `None`），业务失败时返回 `Error::Api`
+    fn open(&self, body: Value) -> Result<Option<Value>>;
+}
+
+/// 按字段名拆解的响应外层
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldEnvelope {
+    /// 业务状态码字段
+    pub code_field: String,
+    /// 错误信息字段
+    pub message_field: String,
+    /// 业务数据字段
+    pub data_field: String,
+    /// 表示成功的状态码
+    pub success_code: i32,
+}
+
+impl FieldEnvelope {
+    /// `{code, message, data}`，code 为 0 表示成功
+    pub fn standard() -> Self {
+        Self::new("code", "message", "data", 0)
+    }
+
+    /// `{status, msg, result}`，status 为 0 表示成功
+    pub fn status_msg_result() -> Self {
+        Self::new("status", "msg", "result", 0)
+    }
+
+    pub fn new(code_field: &str, message_field: &str, data_field: &str, success_code: i32) -> Self {
+        Self {
+            code_field: code_field.to_string(),
+            message_field: message_field.to_string(),
+            data_field: data_field.to_string(),
+            success_code,
+        }
+    }
+}
+
+impl Default for FieldEnvelope {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ResponseEnvelope for FieldEnvelope {
+    fn open(&self, body: Value) -> Result<Option<Value>> {
+        let Value::Object(mut fields) = body else {
+            return Err(Error::Encoding("Response body is not a JSON object".to_string()));
+        };
+        // Reason: 部分网关以字符串返回状态码（如 "0"），两种形式都接受
+        let code = match fields.get(&self.code_field) {
+            Some(Value::Number(code)) => code.as_i64(),
+            Some(Value::String(code)) => code.trim().parse().ok(),
+            _ => None,
+        }
+        .and_then(|code| i32::try_from(code).ok())
+        .ok_or_else(|| Error::Encoding(format!("Response has no integer field {:?}", self.code_field)))?;
+
+        if code != self.success_code {
+            let message = match fields.remove(&self.message_field) {
+                Some(Value::String(message)) => message,
+                Some(Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            };
+            return Err(Error::Api { code, message });
+        }
+        Ok(fields.remove(&self.data_field).filter(|data| !data.is_null()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_standard_envelope() {
+        let envelope = FieldEnvelope::standard();
+        let data = envelope.open(json!({ "code": 0, "message": "ok", "data": { "token": "t" } })).unwrap();
+        assert_eq!(data, Some(json!({ "token": "t" })));
+        assert_eq!(envelope.open(json!({ "code": 0, "message": "ok", "data": null })).unwrap(), None);
+
+        let err = envelope.open(json!({ "code": 1001, "message": "user exists" })).unwrap_err();
+        assert!(matches!(err, Error::Api { code: 1001, ref message } if message == "user exists"));
+        assert!(matches!(envelope.open(json!({ "status": 0 })), Err(Error::Encoding(_))));
+        assert!(matches!(envelope.open(json!([1, 2])), Err(Error::Encoding(_))));
+    }
+
+    #[test]
+    fn test_status_msg_result_envelope() {
+        let envelope = FieldEnvelope::status_msg_result();
+        let data = envelope.open(json!({ "status": "0", "msg": "", "result": { "s3": "AA==" } })).unwrap();
+        assert_eq!(data, Some(json!({ "s3": "AA==" })));
+
+        let err = envelope.open(json!({ "status": 401, "msg": "invalid token" })).unwrap_err();
+        assert!(matches!(err, Error::Api { code: 401, .. }));
+    }
+}
@@ -0,0 +1,499 @@
+//! 电子印章（GB/T 38540-2020）
+//!
+//! 电子印章 `SES_Seal` 由制章者签发，内含印章图像与印章属性；盖章时签章者对
+//! `TBS_Sign { 版本, 电子印章, 签章时间, 原文杂凑, 原文属性 }` 签名，生成电子签章 `SES_Signature`，
+//! 验证方据此校验原文杂凑、签章者签名与印章有效期。签名算法均为 SM3withSM2（默认用户标识），
+//! 签名值为 DER 编码的 `SEQUENCE { r, s }`。
+//!
+//! 解析与验证只需 `std`；制章与盖章经 [`CoSignClient`](crate::CoSignClient) 协同签名，需启用 `client`。
+
+use crate::asn1::{self, DerReader, TAG_BIT_STRING, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE};
+use crate::error::{Error, Result};
+use crate::protocol::{CoSignProtocol, DEFAULT_USER_ID};
+use crate::sm3::Sm3;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+#[cfg(feature = "client")]
+use crate::client::CoSignClient;
+#[cfg(feature = "client")]
+use crate::protocol::DigestMode;
+
+/// IA5String 标签
+const TAG_IA5_STRING: u8 = 0x16;
+/// UTF8String 标签
+const TAG_UTF8_STRING: u8 = 0x0C;
+/// GeneralizedTime 标签
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+/// SM3withSM2 签名算法 (1.2.156.10197.1.501)
+pub const OID_SM3_WITH_SM2: &[u8] = &[0x2A, 0x81, 0x1C, 0xCF, 0x55, 0x01, 0x83, 0x75];
+/// 头信息中的印章标识
+pub const SEAL_ID: &str = "ES";
+/// GB/T 38540-2020 的数据格式版本
+pub const SEAL_VERSION: u32 = 4;
+/// 证书列表类型：签章者证书
+pub const CERT_LIST_CERTS: u32 = 1;
+
+const GENERALIZED_TIME_FORMAT: &str = "%Y%m%d%H%M%SZ";
+
+/// 印章类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealType {
+    /// 单位印章
+    Organization = 1,
+    /// 个人印章
+    Personal = 2,
+}
+
+/// 印章图像
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealPicture {
+    /// 图像类型，如 `png`、`jpg`、`ofd`
+    pub kind: String,
+    /// 图像数据
+    pub data: Vec<u8>,
+    /// 显示宽度（毫米）
+    pub width: u32,
+    /// 显示高度（毫米）
+    pub height: u32,
+}
+
+/// 电子印章信息（`SES_SealInfo` 中制章时填写的部分）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealInfo {
+    /// 厂商标识
+    pub vendor_id: String,
+    /// 电子印章标识（印章编码）
+    pub es_id: String,
+    /// 印章类型
+    pub seal_type: SealType,
+    /// 印章名称
+    pub name: String,
+    /// 允许使用该印章的签章者证书（DER）
+    pub signer_certificates: Vec<Vec<u8>>,
+    /// 制作日期
+    pub created: DateTime<Utc>,
+    /// 有效期起始
+    pub valid_start: DateTime<Utc>,
+    /// 有效期结束
+    pub valid_end: DateTime<Utc>,
+    /// 印章图像
+    pub picture: SealPicture,
+}
+
+impl SealInfo {
+    /// 编码为 `SES_SealInfo`
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut header = asn1::encode_tlv(TAG_IA5_STRING, SEAL_ID.as_bytes());
+        header.extend(encode_u32(SEAL_VERSION));
+        header.extend(asn1::encode_tlv(TAG_IA5_STRING, self.vendor_id.as_bytes()));
+
+        let certs: Vec<u8> = self
+            .signer_certificates
+            .iter()
+            .flat_map(|cert| asn1::encode_tlv(TAG_OCTET_STRING, cert))
+            .collect();
+        let mut property = encode_u32(self.seal_type as u32);
+        property.extend(asn1::encode_tlv(TAG_UTF8_STRING, self.name.as_bytes()));
+        property.extend(encode_u32(CERT_LIST_CERTS));
+        property.extend(asn1::encode_sequence(&certs));
+        for time in [self.created, self.valid_start, self.valid_end] {
+            property.extend(encode_time(time));
+        }
+
+        let mut picture = asn1::encode_tlv(TAG_IA5_STRING, self.picture.kind.as_bytes());
+        picture.extend(asn1::encode_tlv(TAG_OCTET_STRING, &self.picture.data));
+        picture.extend(encode_u32(self.picture.width));
+        picture.extend(encode_u32(self.picture.height));
+
+        let mut info = asn1::encode_sequence(&header);
+        info.extend(asn1::encode_tlv(TAG_IA5_STRING, self.es_id.as_bytes()));
+        info.extend(asn1::encode_sequence(&property));
+        info.extend(asn1::encode_sequence(&picture));
+        asn1::encode_sequence(&info)
+    }
+
+    fn parse(der: &[u8]) -> Result<Self> {
+        let mut info = DerReader::new(der);
+        let mut header = DerReader::new(info.read(TAG_SEQUENCE)?);
+        if read_string(&mut header, TAG_IA5_STRING)? != SEAL_ID {
+            return Err(Error::Encoding("Not an electronic seal".to_string()));
+        }
+        let version = read_u32(&mut header)?;
+        if version != SEAL_VERSION {
+            return Err(Error::Encoding(format!("Unsupported electronic seal version {}", version)));
+        }
+        let vendor_id = read_string(&mut header, TAG_IA5_STRING)?;
+        let es_id = read_string(&mut info, TAG_IA5_STRING)?;
+
+        let mut property = DerReader::new(info.read(TAG_SEQUENCE)?);
+        let seal_type = match read_u32(&mut property)? {
+            1 => SealType::Organization,
+            2 => SealType::Personal,
+            other => return Err(Error::Encoding(format!("Unknown seal type {}", other))),
+        };
+        let name = read_string(&mut property, TAG_UTF8_STRING)?;
+        let cert_list_type = read_u32(&mut property)?;
+        let mut cert_list = DerReader::new(property.read(TAG_SEQUENCE)?);
+        let mut signer_certificates = Vec::new();
+        // Reason: 证书杂凑列表（类型 2）无法还原证书，盖章时不据此限制签章者
+        if cert_list_type == CERT_LIST_CERTS {
+            while !cert_list.is_empty() {
+                signer_certificates.push(cert_list.read(TAG_OCTET_STRING)?.to_vec());
+            }
+        }
+        let created = read_time(&mut property)?;
+        let valid_start = read_time(&mut property)?;
+        let valid_end = read_time(&mut property)?;
+
+        let mut picture = DerReader::new(info.read(TAG_SEQUENCE)?);
+        let picture = SealPicture {
+            kind: read_string(&mut picture, TAG_IA5_STRING)?,
+            data: picture.read(TAG_OCTET_STRING)?.to_vec(),
+            width: read_u32(&mut picture)?,
+            height: read_u32(&mut picture)?,
+        };
+
+        Ok(Self {
+            vendor_id,
+            es_id,
+            seal_type,
+            name,
+            signer_certificates,
+            created,
+            valid_start,
+            valid_end,
+            picture,
+        })
+    }
+}
+
+/// 电子印章 `SES_Seal`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElectronicSeal {
+    /// 印章信息
+    pub info: SealInfo,
+    /// 制章者证书（DER）
+    pub maker_certificate: Vec<u8>,
+    der: Vec<u8>,
+}
+
+impl ElectronicSeal {
+    /// 解析 DER 编码的电子印章并验证制章者签名
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut seal = DerReader::new(outer.read(TAG_SEQUENCE)?);
+        let info_der = asn1::encode_sequence(seal.read(TAG_SEQUENCE)?);
+        let maker_certificate = seal.read(TAG_OCTET_STRING)?.to_vec();
+        read_algorithm(&mut seal)?;
+        let signature = read_bit_string(&mut seal)?;
+
+        verify(&maker_certificate, &seal_tbs(&info_der, &maker_certificate), signature)
+            .map_err(|_| Error::Crypto("Electronic seal signature is invalid".to_string()))?;
+        Ok(Self {
+            info: SealInfo::parse(&info_der)?,
+            maker_certificate,
+            der: der.to_vec(),
+        })
+    }
+
+    /// DER 编码
+    pub fn to_der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// 给定时间是否在印章有效期内
+    pub fn is_valid_at(&self, time: DateTime<Utc>) -> bool {
+        self.info.valid_start <= time && time <= self.info.valid_end
+    }
+
+    /// 签章者证书是否允许使用该印章（证书列表为空时不限制）
+    pub fn allows_signer(&self, certificate: &[u8]) -> bool {
+        self.info.signer_certificates.is_empty() || self.info.signer_certificates.iter().any(|c| c == certificate)
+    }
+}
+
+/// 电子签章 `SES_Signature` 的验证结果
+#[derive(Debug, Clone)]
+pub struct SealSignature {
+    /// 所用电子印章
+    pub seal: ElectronicSeal,
+    /// 签章者证书（DER）
+    pub signer_certificate: Vec<u8>,
+    /// 签章时间
+    pub time: DateTime<Utc>,
+    /// 原文属性（如文档名）
+    pub property_info: String,
+}
+
+/// 制章者签名原文 `SEQUENCE { eSealInfo, cert, signAlgID }`
+fn seal_tbs(info_der: &[u8], maker_certificate: &[u8]) -> Vec<u8> {
+    let mut tbs = info_der.to_vec();
+    tbs.extend(asn1::encode_tlv(TAG_OCTET_STRING, maker_certificate));
+    tbs.extend(asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2));
+    asn1::encode_sequence(&tbs)
+}
+
+/// 签章原文 `TBS_Sign`，原文杂凑为 SM3(原文)
+fn sign_tbs(seal: &ElectronicSeal, time: DateTime<Utc>, data: &[u8], property_info: &str) -> Vec<u8> {
+    let mut tbs = encode_u32(SEAL_VERSION);
+    tbs.extend_from_slice(seal.to_der());
+    tbs.extend(encode_time(time));
+    tbs.extend(encode_bit_string(&Sm3::digest(data)));
+    tbs.extend(asn1::encode_tlv(TAG_IA5_STRING, property_info.as_bytes()));
+    asn1::encode_sequence(&tbs)
+}
+
+/// 组装 `SEQUENCE { tbs, cert, signAlgID, signature }`
+fn encode_signed(tbs: &[u8], certificate: &[u8], signature_der: &[u8]) -> Vec<u8> {
+    let mut signed = tbs.to_vec();
+    signed.extend(asn1::encode_tlv(TAG_OCTET_STRING, certificate));
+    signed.extend(asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2));
+    signed.extend(encode_bit_string(signature_der));
+    asn1::encode_sequence(&signed)
+}
+
+/// 验证电子签章：原文杂凑、签章者签名、印章有效期与签章者授权
+pub fn verify_signature(signature: &[u8], data: &[u8]) -> Result<SealSignature> {
+    let mut outer = DerReader::new(signature);
+    let mut signed = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    let tbs_contents = signed.read(TAG_SEQUENCE)?;
+    let signer_certificate = signed.read(TAG_OCTET_STRING)?.to_vec();
+    read_algorithm(&mut signed)?;
+    let signature_value = read_bit_string(&mut signed)?;
+    verify(&signer_certificate, &asn1::encode_sequence(tbs_contents), signature_value)?;
+
+    let mut tbs = DerReader::new(tbs_contents);
+    let version = read_u32(&mut tbs)?;
+    if version != SEAL_VERSION {
+        return Err(Error::Encoding(format!("Unsupported electronic signature version {}", version)));
+    }
+    let seal = ElectronicSeal::from_der(&asn1::encode_sequence(tbs.read(TAG_SEQUENCE)?))?;
+    let time = read_time(&mut tbs)?;
+    if read_bit_string(&mut tbs)? != Sm3::digest(data) {
+        return Err(Error::Crypto("Document hash does not match the electronic signature".to_string()));
+    }
+    let property_info = read_string(&mut tbs, TAG_IA5_STRING)?;
+
+    if !seal.is_valid_at(time) {
+        return Err(Error::InvalidParam("Electronic seal was not valid at signing time".to_string()));
+    }
+    if !seal.allows_signer(&signer_certificate) {
+        return Err(Error::InvalidParam("Signer certificate is not authorized for the seal".to_string()));
+    }
+    Ok(SealSignature { seal, signer_certificate, time, property_info })
+}
+
+/// 制章：由制章者对印章信息协同签名，返回电子印章
+///
+/// `maker_certificate` 为制章者证书 DER，须与客户端的协同公钥一致。
+#[cfg(feature = "client")]
+pub async fn make_seal(client: &CoSignClient, info: &SealInfo, maker_certificate: &[u8]) -> Result<ElectronicSeal> {
+    let info_der = info.to_der();
+    let tbs = seal_tbs(&info_der, maker_certificate);
+    let signature_der = co_sign(client, maker_certificate, &tbs).await?;
+
+    let mut seal = info_der;
+    seal.extend(asn1::encode_tlv(TAG_OCTET_STRING, maker_certificate));
+    seal.extend(asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2));
+    seal.extend(encode_bit_string(&signature_der));
+    ElectronicSeal::from_der(&asn1::encode_sequence(&seal))
+}
+
+/// 盖章：对原文生成电子签章 `SES_Signature`（DER）
+///
+/// `signer_certificate` 须与客户端的协同公钥一致，且在印章的签章者证书列表中；
+/// `property_info` 为原文属性（如文档名或 OFD 签名路径），写入签章原文。
+#[cfg(feature = "client")]
+pub async fn sign_document(
+    client: &CoSignClient,
+    seal: &ElectronicSeal,
+    signer_certificate: &[u8],
+    data: &[u8],
+    property_info: &str,
+) -> Result<Vec<u8>> {
+    let now = Utc::now();
+    if !seal.is_valid_at(now) {
+        return Err(Error::InvalidParam("Electronic seal is expired or not yet valid".to_string()));
+    }
+    if !seal.allows_signer(signer_certificate) {
+        return Err(Error::InvalidParam("Signer certificate is not authorized for the seal".to_string()));
+    }
+
+    let tbs = sign_tbs(seal, now, data, property_info);
+    let signature_der = co_sign(client, signer_certificate, &tbs).await?;
+    Ok(encode_signed(&tbs, signer_certificate, &signature_der))
+}
+
+/// 协同签名并用证书公钥验证，返回 DER 编码签名值
+#[cfg(feature = "client")]
+async fn co_sign(client: &CoSignClient, certificate: &[u8], tbs: &[u8]) -> Result<Vec<u8>> {
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or(Error::InvalidState("No key pair available".to_string()))?;
+    if asn1::public_key_from_certificate(certificate)? != public_key.as_bytes() {
+        return Err(Error::InvalidParam("Certificate does not match the co-sign public key".to_string()));
+    }
+    let signature = client.sign(tbs, DigestMode::Za).await?;
+    let signature_der = asn1::signature_to_der(&signature.to_bytes())?;
+    verify(certificate, tbs, &signature_der)
+        .map_err(|_| Error::Crypto("Co-signature does not verify against the certificate".to_string()))?;
+    Ok(signature_der)
+}
+
+/// 用证书公钥按 SM3withSM2 验证 DER 签名
+fn verify(certificate: &[u8], tbs: &[u8], signature_der: &[u8]) -> Result<()> {
+    let public_key = asn1::public_key_from_certificate(certificate)?;
+    let raw = asn1::signature_from_der(signature_der)?;
+    let protocol = CoSignProtocol::new()?;
+    let e = protocol.calculate_message_hash_with_uid(tbs, DEFAULT_USER_ID, &public_key)?;
+    if !protocol.verify_digest(&public_key, &e, &raw[..32], &raw[32..])? {
+        return Err(Error::Crypto("Electronic signature is invalid".to_string()));
+    }
+    Ok(())
+}
+
+fn encode_u32(value: u32) -> Vec<u8> {
+    asn1::encode_unsigned_integer(&value.to_be_bytes())
+}
+
+fn encode_time(time: DateTime<Utc>) -> Vec<u8> {
+    asn1::encode_tlv(TAG_GENERALIZED_TIME, time.format(GENERALIZED_TIME_FORMAT).to_string().as_bytes())
+}
+
+fn encode_bit_string(data: &[u8]) -> Vec<u8> {
+    let mut bits = vec![0x00];
+    bits.extend_from_slice(data);
+    asn1::encode_tlv(TAG_BIT_STRING, &bits)
+}
+
+fn read_u32(reader: &mut DerReader) -> Result<u32> {
+    let bytes = reader.read_unsigned_integer()?;
+    if bytes.len() > 4 {
+        return Err(Error::Encoding("INTEGER out of range".to_string()));
+    }
+    Ok(bytes.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32))
+}
+
+fn read_string(reader: &mut DerReader, tag: u8) -> Result<String> {
+    String::from_utf8(reader.read(tag)?.to_vec()).map_err(|_| Error::Encoding("Invalid string in electronic seal".to_string()))
+}
+
+fn read_time(reader: &mut DerReader) -> Result<DateTime<Utc>> {
+    let text = read_string(reader, TAG_GENERALIZED_TIME)?;
+    NaiveDateTime::parse_from_str(&text, GENERALIZED_TIME_FORMAT)
+        .map(|time| Utc.from_utc_datetime(&time))
+        .map_err(|_| Error::Encoding(format!("Invalid GeneralizedTime {}", text)))
+}
+
+fn read_bit_string<'a>(reader: &mut DerReader<'a>) -> Result<&'a [u8]> {
+    match reader.read(TAG_BIT_STRING)? {
+        [0x00, rest @ ..] => Ok(rest),
+        _ => Err(Error::Encoding("Unsupported BIT STRING padding".to_string())),
+    }
+}
+
+fn read_algorithm(reader: &mut DerReader) -> Result<()> {
+    if reader.read(TAG_OID)? != OID_SM3_WITH_SM2 {
+        return Err(Error::Encoding("Unsupported electronic seal signature algorithm".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// 以完整私钥签名的测试证书与对应签名函数
+    struct TestKey {
+        private_key: Vec<u8>,
+        certificate: Vec<u8>,
+    }
+
+    impl TestKey {
+        fn new() -> Self {
+            let (private_key, public_key) = CoSignProtocol::generate_keypair();
+            let empty = asn1::encode_sequence(&[]);
+            let mut tbs = asn1::encode_unsigned_integer(&[0x01]);
+            for _ in 0..4 {
+                tbs.extend(&empty);
+            }
+            tbs.extend(asn1::public_key_to_spki(&public_key).unwrap());
+            let mut certificate = asn1::encode_sequence(&tbs);
+            certificate.extend(&empty);
+            certificate.extend(encode_bit_string(&[]));
+            Self { private_key, certificate: asn1::encode_sequence(&certificate) }
+        }
+
+        fn sign(&self, tbs: &[u8]) -> Vec<u8> {
+            asn1::signature_to_der(&CoSignProtocol::sign(&self.private_key, tbs).unwrap()).unwrap()
+        }
+    }
+
+    fn seal_info(signer: &TestKey) -> SealInfo {
+        let now = Utc::now();
+        SealInfo {
+            vendor_id: "cosign".to_string(),
+            es_id: "1101000000001".to_string(),
+            seal_type: SealType::Organization,
+            name: "测试单位公章".to_string(),
+            signer_certificates: vec![signer.certificate.clone()],
+            created: now,
+            valid_start: now - Duration::days(1),
+            valid_end: now + Duration::days(365),
+            picture: SealPicture { kind: "png".to_string(), data: vec![0x89, b'P', b'N', b'G'], width: 40, height: 40 },
+        }
+    }
+
+    fn local_seal(maker: &TestKey, info: &SealInfo) -> Vec<u8> {
+        let info_der = info.to_der();
+        let signature = maker.sign(&seal_tbs(&info_der, &maker.certificate));
+        let mut seal = info_der;
+        seal.extend(asn1::encode_tlv(TAG_OCTET_STRING, &maker.certificate));
+        seal.extend(asn1::encode_tlv(TAG_OID, OID_SM3_WITH_SM2));
+        seal.extend(encode_bit_string(&signature));
+        asn1::encode_sequence(&seal)
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let (maker, signer) = (TestKey::new(), TestKey::new());
+        let info = seal_info(&signer);
+        let seal = ElectronicSeal::from_der(&local_seal(&maker, &info)).unwrap();
+
+        assert_eq!(seal.info.es_id, info.es_id);
+        assert_eq!(seal.info.name, info.name);
+        assert_eq!(seal.info.picture, info.picture);
+        assert_eq!(seal.info.valid_end.timestamp(), info.valid_end.timestamp());
+        assert!(seal.allows_signer(&signer.certificate));
+        assert!(!seal.allows_signer(&maker.certificate));
+
+        // 篡改印章信息后制章者签名无效
+        let mut tampered = local_seal(&maker, &info);
+        let pos = tampered.windows(4).position(|w| w == [0x89, b'P', b'N', b'G']).unwrap();
+        tampered[pos] = 0x00;
+        assert!(matches!(ElectronicSeal::from_der(&tampered), Err(Error::Crypto(_))));
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let (maker, signer) = (TestKey::new(), TestKey::new());
+        let seal = ElectronicSeal::from_der(&local_seal(&maker, &seal_info(&signer))).unwrap();
+        let data = b"document contents";
+
+        let tbs = sign_tbs(&seal, Utc::now(), data, "contract.ofd");
+        let signature = encode_signed(&tbs, &signer.certificate, &signer.sign(&tbs));
+        let verified = verify_signature(&signature, data).unwrap();
+        assert_eq!(verified.property_info, "contract.ofd");
+        assert_eq!(verified.signer_certificate, signer.certificate);
+
+        assert!(matches!(verify_signature(&signature, b"other"), Err(Error::Crypto(_))));
+
+        // 未授权的签章者
+        let tbs = sign_tbs(&seal, Utc::now(), data, "contract.ofd");
+        let signature = encode_signed(&tbs, &maker.certificate, &maker.sign(&tbs));
+        assert!(matches!(verify_signature(&signature, data), Err(Error::InvalidParam(_))));
+    }
+}
@@ -0,0 +1,413 @@
+//! 密钥材料与令牌的强类型封装
+//!
+//! 构造时校验长度与取值范围：标量须在 [1, n-1] 内并统一为 32 字节，公钥须为曲线上的点。
+//! [`D1`]、[`Nonce`]、[`AuthToken`] 在 Drop 时清零，`Debug` 输出隐藏内容；[`D1`]、[`Nonce`] 存放在
+//! [`SecretBuf`] 中，启用 `mlock` feature 时位于锁定内存页；
+//! 各类型可按 `&[u8]`（[`AuthToken`] 为 `&str`）借用，直接传给协议层函数。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::asn1;
+use crate::ct_point;
+use crate::error::{Error, Result};
+use crate::pem;
+use crate::secure_mem::SecretBuf;
+use crate::protocol::{base64_decode, base64_encode};
+use crate::types::REDACTED;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use std::path::Path;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// 标量长度（字节）
+pub const SCALAR_LEN: usize = 32;
+/// 公钥长度（字节，x||y）
+pub const PUBLIC_KEY_LEN: usize = 64;
+
+/// 校验标量取值范围 [1, n-1]，左补零为 32 字节
+pub(crate) fn scalar_from_slice(bytes: &[u8], name: &str) -> Result<[u8; SCALAR_LEN]> {
+    if bytes.is_empty() || bytes.len() > SCALAR_LEN {
+        return Err(Error::InvalidParam(format!("Invalid {} length, expected at most 32 bytes", name)));
+    }
+    let mut scalar = [0u8; SCALAR_LEN];
+    scalar[SCALAR_LEN - bytes.len()..].copy_from_slice(bytes);
+    if !ct_point::is_valid_scalar(&scalar) {
+        scalar.zeroize();
+        return Err(Error::InvalidParam(format!("{} out of range [1, n-1]", name)));
+    }
+    Ok(scalar)
+}
+
+/// 定义取值范围为 [1, n-1] 的秘密标量类型
+macro_rules! secret_scalar {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Eq)]
+        pub struct $name(SecretBuf);
+
+        impl $name {
+            /// 由大端字节构造，长度不足 32 字节时左补零
+            pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+                let mut scalar = scalar_from_slice(bytes, stringify!($name))?;
+                let buf = SecretBuf::new(&scalar);
+                scalar.zeroize();
+                Ok(Self(buf))
+            }
+
+            /// 是否存放在锁定内存页中（需启用 `mlock` feature 且系统允许锁定）
+            pub fn is_memory_locked(&self) -> bool {
+                self.0.is_locked()
+            }
+
+            /// 32 字节大端表示
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl TryFrom<Vec<u8>> for $name {
+            type Error = Error;
+
+            fn try_from(mut bytes: Vec<u8>) -> Result<Self> {
+                let result = Self::from_slice(&bytes);
+                bytes.zeroize();
+                result
+            }
+        }
+
+        impl core::ops::Deref for $name {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{}({})", stringify!($name), REDACTED)
+            }
+        }
+
+        impl Zeroize for $name {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl ZeroizeOnDrop for $name {}
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(&hex::encode(&self.0))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+                let mut text = String::deserialize(deserializer)?;
+                let bytes = hex::decode(&text).map_err(serde::de::Error::custom);
+                text.zeroize();
+                Self::try_from(bytes?).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+secret_scalar!(
+    /// 客户端私钥分量 D1
+    D1
+);
+
+secret_scalar!(
+    /// 签名随机数 k1（每次签名使用一次）
+    Nonce
+);
+
+/// 协同公钥（曲线点，64 字节 x||y）
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey(Vec<u8>);
+
+impl PublicKey {
+    /// 由 64 字节（x||y）或 65 字节（04||x||y）构造，校验点在曲线上
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes = match bytes.len() {
+            64 => bytes,
+            65 if bytes[0] == 0x04 => &bytes[1..],
+            _ => return Err(Error::InvalidParam("Invalid public key length, expected 64 or 65 bytes".to_string())),
+        };
+        ct_point::validate_point(bytes)?;
+        Ok(Self(bytes.to_vec()))
+    }
+
+    /// 64 字节 x||y
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// SubjectPublicKeyInfo DER 编码
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        asn1::public_key_to_spki(&self.0).expect("public key is 64 bytes")
+    }
+
+    /// 由 SubjectPublicKeyInfo DER 构造
+    pub fn from_spki_der(der: &[u8]) -> Result<Self> {
+        Self::from_slice(&asn1::public_key_from_spki(der)?)
+    }
+
+    /// `PUBLIC KEY` PEM 编码（SubjectPublicKeyInfo），可被 OpenSSL/GmSSL 等工具读取
+    pub fn to_pem(&self) -> String {
+        pem::encode(pem::PUBLIC_KEY_LABEL, &self.to_spki_der())
+    }
+
+    /// 由 `PUBLIC KEY` PEM 构造
+    pub fn from_pem(text: &str) -> Result<Self> {
+        Self::from_spki_der(&pem::decode(text, pem::PUBLIC_KEY_LABEL)?)
+    }
+
+    /// 读取公钥文件，自动识别 PEM、DER（SubjectPublicKeyInfo）、十六进制文本与原始 64/65 字节
+    #[cfg(feature = "std")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Self::from_encoded(&data)
+    }
+
+    #[cfg(feature = "std")]
+    fn from_encoded(data: &[u8]) -> Result<Self> {
+        if let Ok(text) = core::str::from_utf8(data) {
+            if pem::contains(text, pem::PUBLIC_KEY_LABEL) {
+                return Self::from_pem(text);
+            }
+            let text = text.trim();
+            if matches!(text.len(), 128 | 130) {
+                if let Ok(bytes) = hex::decode(text) {
+                    return Self::from_slice(&bytes);
+                }
+            }
+        }
+        if data.first() == Some(&asn1::TAG_SEQUENCE) && data.len() > 65 {
+            return Self::from_spki_der(data);
+        }
+        Self::from_slice(data)
+    }
+}
+
+impl TryFrom<Vec<u8>> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_slice(&bytes)
+    }
+}
+
+impl core::ops::Deref for PublicKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl core::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "PublicKey({})", hex::encode(&self.0))
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64_encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = base64_decode(&text).map_err(serde::de::Error::custom)?;
+        Self::try_from(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 登录 Token（作为 HTTP Bearer 凭据发送）
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    /// 非空且只包含可见 ASCII 字符（可安全放入请求头）
+    pub fn new(token: String) -> Result<Self> {
+        if token.is_empty() || !token.bytes().all(|b| b.is_ascii_graphic()) {
+            let mut token = token;
+            token.zeroize();
+            return Err(Error::InvalidParam("Invalid token, expected non-empty visible ASCII".to_string()));
+        }
+        Ok(Self(token))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for AuthToken {
+    type Error = Error;
+
+    fn try_from(token: String) -> Result<Self> {
+        Self::new(token)
+    }
+}
+
+impl core::ops::Deref for AuthToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for AuthToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for AuthToken {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl core::fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AuthToken({})", REDACTED)
+    }
+}
+
+impl Zeroize for AuthToken {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for AuthToken {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for AuthToken {}
+
+impl Serialize for AuthToken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for AuthToken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        Self::new(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoSignProtocol;
+
+    #[test]
+    fn test_scalar_validation() {
+        let d1 = D1::from_slice(&[0x01]).unwrap();
+        assert_eq!(d1.len(), SCALAR_LEN);
+        assert_eq!(d1[31], 0x01);
+
+        assert!(D1::from_slice(&[]).is_err());
+        assert!(D1::from_slice(&[0u8; 32]).is_err());
+        assert!(D1::from_slice(&[0x01; 33]).is_err());
+        // n 本身超出范围，n-1 有效
+        let n = hex::decode("fffffffeffffffffffffffffffffffff7203df6b21c6052b53bbf40939d54123").unwrap();
+        assert!(Nonce::from_slice(&n).is_err());
+        let mut n_minus_1 = n.clone();
+        n_minus_1[31] -= 1;
+        assert!(Nonce::from_slice(&n_minus_1).is_ok());
+    }
+
+    #[test]
+    fn test_public_key_validation() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+
+        let public_key = PublicKey::from_slice(&p1).unwrap();
+        let mut p1_65 = vec![0x04];
+        p1_65.extend_from_slice(&p1);
+        assert_eq!(PublicKey::from_slice(&p1_65).unwrap(), public_key);
+
+        let mut off_curve = p1.clone();
+        off_curve[63] ^= 0x01;
+        assert!(PublicKey::from_slice(&off_curve).is_err());
+        assert!(PublicKey::from_slice(&p1[..32]).is_err());
+    }
+
+    #[test]
+    fn test_public_key_encodings() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let public_key = PublicKey::from_slice(&p1).unwrap();
+
+        let pem = public_key.to_pem();
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+        assert_eq!(PublicKey::from_pem(&pem).unwrap(), public_key);
+
+        let der = public_key.to_spki_der();
+        let hex_text = format!("{}\n", hex::encode(&p1));
+        for encoded in [pem.as_bytes(), der.as_slice(), hex_text.as_bytes(), p1.as_slice()] {
+            assert_eq!(PublicKey::from_encoded(encoded).unwrap(), public_key);
+        }
+
+        // SPKI 格式正确但点不在曲线上
+        let mut off_curve = p1.clone();
+        off_curve[63] ^= 0x01;
+        let der = asn1::public_key_to_spki(&off_curve).unwrap();
+        assert!(PublicKey::from_spki_der(&der).is_err());
+    }
+
+    #[test]
+    fn test_auth_token_validation() {
+        assert_eq!(AuthToken::new("abc.def-123".to_string()).unwrap().as_str(), "abc.def-123");
+        assert!(AuthToken::new(String::new()).is_err());
+        assert!(AuthToken::new("abc\r\nX-Injected: 1".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let d1 = D1::from_slice(&[0x5a; 32]).unwrap();
+        assert_eq!(format!("{:?}", d1), format!("D1({})", REDACTED));
+        let token = AuthToken::new("secret-token".to_string()).unwrap();
+        assert!(!format!("{:?}", token).contains("secret-token"));
+    }
+
+    #[test]
+    fn test_serde_validates() {
+        let d1 = D1::from_slice(&[0x5a; 32]).unwrap();
+        let json = serde_json::to_string(&d1).unwrap();
+        assert_eq!(serde_json::from_str::<D1>(&json).unwrap(), d1);
+        assert!(serde_json::from_str::<D1>(&format!("\"{}\"", "00".repeat(32))).is_err());
+        assert!(serde_json::from_str::<AuthToken>("\"\"").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! 秘密数据的内存存放
+//!
+//! 启用 `mlock` feature 时，[`SecretBuf`] 为每个秘密单独映射内存页：数据页经 mlock / VirtualLock
+//! 锁定，不会被换出到交换分区（Linux 上同时排除出 core dump）；前后各有一个不可访问的保护页，
+//! 数据紧贴尾部保护页存放，越界读写立即触发访问异常。
+//! 未启用 feature、平台不支持或系统拒绝映射时退化为普通堆内存；两种情况下 Drop 时都会清零。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use zeroize::Zeroize;
+
+/// 定长秘密缓冲区
+pub struct SecretBuf(Storage);
+
+enum Storage {
+    Heap(Vec<u8>),
+    #[cfg(feature = "mlock")]
+    Locked(locked::Region),
+}
+
+impl SecretBuf {
+    /// 分配与 `bytes` 等长的缓冲区并复制内容
+    pub fn new(bytes: &[u8]) -> Self {
+        #[cfg(feature = "mlock")]
+        {
+            if let Some(mut region) = locked::Region::new(bytes.len()) {
+                region.as_mut_slice().copy_from_slice(bytes);
+                return Self(Storage::Locked(region));
+            }
+        }
+        Self(Storage::Heap(bytes.to_vec()))
+    }
+
+    /// 数据页是否已锁定在物理内存中
+    pub fn is_locked(&self) -> bool {
+        match &self.0 {
+            Storage::Heap(_) => false,
+            #[cfg(feature = "mlock")]
+            Storage::Locked(region) => region.is_locked(),
+        }
+    }
+}
+
+impl core::ops::Deref for SecretBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            Storage::Heap(bytes) => bytes,
+            #[cfg(feature = "mlock")]
+            Storage::Locked(region) => region.as_slice(),
+        }
+    }
+}
+
+impl Clone for SecretBuf {
+    fn clone(&self) -> Self {
+        Self::new(self)
+    }
+}
+
+impl PartialEq for SecretBuf {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for SecretBuf {}
+
+impl Zeroize for SecretBuf {
+    fn zeroize(&mut self) {
+        match &mut self.0 {
+            Storage::Heap(bytes) => bytes.zeroize(),
+            #[cfg(feature = "mlock")]
+            Storage::Locked(region) => region.as_mut_slice().zeroize(),
+        }
+    }
+}
+
+impl Drop for SecretBuf {
+    fn drop(&mut self) {
+        // Reason: 锁定区域在自身 Drop 中清零后释放；堆内存需在 Vec 释放前清零
+        if let Storage::Heap(bytes) = &mut self.0 {
+            bytes.zeroize();
+        }
+    }
+}
+
+#[cfg(feature = "mlock")]
+mod locked {
+    use super::sys;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tracing::warn;
+    use zeroize::Zeroize;
+
+    /// 只提示一次锁定失败，避免长期运行的进程刷屏
+    static LOCK_WARNED: AtomicBool = AtomicBool::new(false);
+
+    /// 保护页 | 数据页 | 保护页
+    pub struct Region {
+        base: *mut u8,
+        total: usize,
+        data: *mut u8,
+        data_pages: usize,
+        len: usize,
+        locked: bool,
+    }
+
+    // Reason: 区域由 Region 独占，裸指针只在 &self / &mut self 下访问，与 Vec<u8> 的线程语义相同
+    unsafe impl Send for Region {}
+    unsafe impl Sync for Region {}
+
+    impl Region {
+        pub fn new(len: usize) -> Option<Self> {
+            let page = sys::page_size()?;
+            let data_pages = len.div_ceil(page).max(1) * page;
+            let total = data_pages + 2 * page;
+            let base = sys::map(total)?;
+            unsafe {
+                let data = base.add(page);
+                if !sys::protect_none(base, page) || !sys::protect_none(data.add(data_pages), page) {
+                    sys::unmap(base, total);
+                    return None;
+                }
+                let locked = sys::lock(data, data_pages);
+                if !locked && !LOCK_WARNED.swap(true, Ordering::Relaxed) {
+                    warn!("Failed to lock secret memory (check RLIMIT_MEMLOCK); secrets may be swapped to disk");
+                }
+                Some(Self { base, total, data, data_pages, len, locked })
+            }
+        }
+
+        pub fn is_locked(&self) -> bool {
+            self.locked
+        }
+
+        fn start(&self) -> *mut u8 {
+            // 数据紧贴尾部保护页，越界读写直接命中保护页
+            unsafe { self.data.add(self.data_pages - self.len) }
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.start(), self.len) }
+        }
+
+        pub fn as_mut_slice(&mut self) -> &mut [u8] {
+            unsafe { std::slice::from_raw_parts_mut(self.start(), self.len) }
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            self.as_mut_slice().zeroize();
+            unsafe {
+                if self.locked {
+                    sys::unlock(self.data, self.data_pages);
+                }
+                sys::unmap(self.base, self.total);
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "mlock", unix))]
+mod sys {
+    use libc::c_void;
+
+    pub fn page_size() -> Option<usize> {
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        (size > 0).then_some(size as usize)
+    }
+
+    pub fn map(len: usize) -> Option<*mut u8> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        (ptr != libc::MAP_FAILED).then_some(ptr as *mut u8)
+    }
+
+    pub unsafe fn protect_none(ptr: *mut u8, len: usize) -> bool {
+        libc::mprotect(ptr as *mut c_void, len, libc::PROT_NONE) == 0
+    }
+
+    pub unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+        #[cfg(target_os = "linux")]
+        libc::madvise(ptr as *mut c_void, len, libc::MADV_DONTDUMP);
+        libc::mlock(ptr as *const c_void, len) == 0
+    }
+
+    pub unsafe fn unlock(ptr: *mut u8, len: usize) {
+        libc::munlock(ptr as *const c_void, len);
+    }
+
+    pub unsafe fn unmap(ptr: *mut u8, len: usize) {
+        libc::munmap(ptr as *mut c_void, len);
+    }
+}
+
+#[cfg(all(feature = "mlock", windows))]
+mod sys {
+    use std::ffi::c_void;
+    use windows_sys::Win32::System::Memory::{
+        VirtualAlloc, VirtualFree, VirtualLock, VirtualProtect, VirtualUnlock, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE,
+        PAGE_NOACCESS, PAGE_READWRITE,
+    };
+    use windows_sys::Win32::System::SystemInformation::{GetSystemInfo, SYSTEM_INFO};
+
+    pub fn page_size() -> Option<usize> {
+        let mut info: SYSTEM_INFO = unsafe { std::mem::zeroed() };
+        unsafe { GetSystemInfo(&mut info) };
+        (info.dwPageSize > 0).then_some(info.dwPageSize as usize)
+    }
+
+    pub fn map(len: usize) -> Option<*mut u8> {
+        let ptr = unsafe { VirtualAlloc(std::ptr::null(), len, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE) };
+        (!ptr.is_null()).then_some(ptr as *mut u8)
+    }
+
+    pub unsafe fn protect_none(ptr: *mut u8, len: usize) -> bool {
+        let mut old = 0;
+        VirtualProtect(ptr as *const c_void, len, PAGE_NOACCESS, &mut old) != 0
+    }
+
+    pub unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+        VirtualLock(ptr as *const c_void, len) != 0
+    }
+
+    pub unsafe fn unlock(ptr: *mut u8, len: usize) {
+        VirtualUnlock(ptr as *const c_void, len);
+    }
+
+    pub unsafe fn unmap(ptr: *mut u8, _len: usize) {
+        VirtualFree(ptr as *mut c_void, 0, MEM_RELEASE);
+    }
+}
+
+/// 其他平台（如 wasm32）不支持页锁定，统一退化为堆内存
+#[cfg(all(feature = "mlock", not(any(unix, windows))))]
+mod sys {
+    pub fn page_size() -> Option<usize> {
+        None
+    }
+
+    pub fn map(_len: usize) -> Option<*mut u8> {
+        None
+    }
+
+    pub unsafe fn protect_none(_ptr: *mut u8, _len: usize) -> bool {
+        false
+    }
+
+    pub unsafe fn lock(_ptr: *mut u8, _len: usize) -> bool {
+        false
+    }
+
+    pub unsafe fn unlock(_ptr: *mut u8, _len: usize) {}
+
+    pub unsafe fn unmap(_ptr: *mut u8, _len: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_buf_roundtrip() {
+        let buf = SecretBuf::new(&[0x5a; 32]);
+        assert_eq!(&*buf, &[0x5a; 32]);
+        let copy = buf.clone();
+        assert!(copy == buf);
+        assert_eq!(copy.is_locked(), buf.is_locked());
+
+        let mut buf = SecretBuf::new(b"secret");
+        buf.zeroize();
+        assert_eq!(&*buf, &[0u8; 6]);
+    }
+
+    #[test]
+    fn test_secret_buf_locked_with_feature() {
+        let buf = SecretBuf::new(&[1u8; 32]);
+        if !cfg!(feature = "mlock") {
+            assert!(!buf.is_locked());
+        }
+        // 空缓冲区同样可用
+        assert!(SecretBuf::new(&[]).is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+//! 算法自检
+//!
+//! 部署前确认密码算法实现正确，不访问网络：
+//! - 已知答案测试（KAT）：SM3（GB/T 32905 附录 A）、SM4（GB/T 32907 附录 A）、SM4-GCM（RFC 8998 附录 A.1）
+//! - SM2 往返测试：生成临时密钥对，签名/验签、加密/解密
+
+use crate::error::{Error, Result};
+use crate::protocol::{hex_decode, hex_encode, CoSignProtocol};
+use crate::sm4;
+
+/// SM4 标准示例密钥（GB/T 32907-2016 附录 A）
+const SM4_KEY: &str = "0123456789abcdeffedcba9876543210";
+
+/// 单项自检结果
+#[derive(Debug, Clone)]
+pub struct SelfTestResult {
+    /// 测试项名称
+    pub name: &'static str,
+    /// 失败原因，通过时为 None
+    pub error: Option<String>,
+}
+
+impl SelfTestResult {
+    fn new(name: &'static str, result: Result<()>) -> Self {
+        Self { name, error: result.err().map(|e| e.to_string()) }
+    }
+
+    /// 是否通过
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 比较计算结果与期望值（十六进制）
+fn expect_hex(what: &str, actual: &[u8], expected: &str) -> Result<()> {
+    let actual = hex_encode(actual);
+    if actual != expected {
+        return Err(Error::Crypto(format!("{} mismatch: expected {}, got {}", what, expected, actual)));
+    }
+    Ok(())
+}
+
+fn expect(what: &str, ok: bool) -> Result<()> {
+    if !ok {
+        return Err(Error::Crypto(format!("{} check failed", what)));
+    }
+    Ok(())
+}
+
+fn sm3_kat() -> Result<()> {
+    expect_hex(
+        "SM3(\"abc\")",
+        &CoSignProtocol::sm3_hash(b"abc"),
+        "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0",
+    )?;
+    expect_hex(
+        "SM3(\"abcd\" x 16)",
+        &CoSignProtocol::sm3_hash("abcd".repeat(16).as_bytes()),
+        "debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732",
+    )
+}
+
+fn sm4_kat() -> Result<()> {
+    let key = hex_decode(SM4_KEY)?;
+    let ciphertext = sm4::sm4_cbc_encrypt(&key, &[0u8; sm4::SM4_BLOCK_LEN], &key)?;
+    expect_hex("SM4 block", &ciphertext[..sm4::SM4_BLOCK_LEN], "681edf34d206965e86b3e94f536e4246")?;
+    expect("SM4-CBC decrypt", sm4::sm4_cbc_decrypt(&key, &[0u8; sm4::SM4_BLOCK_LEN], &ciphertext)? == key)
+}
+
+fn sm4_gcm_kat() -> Result<()> {
+    let key = hex_decode(SM4_KEY)?;
+    let iv = hex_decode("00001234567800000000abcd")?;
+    let aad = hex_decode("feedfacedeadbeeffeedfacedeadbeefabaddad2")?;
+    let plaintext = hex_decode(concat!(
+        "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbcccccccccccccccc",
+        "ddddddddddddddddeeeeeeeeeeeeeeeeffffffffffffffff",
+        "eeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa"
+    ))?;
+
+    let out = sm4::sm4_gcm_encrypt(&key, &iv, &aad, &plaintext)?;
+    expect_hex(
+        "SM4-GCM ciphertext || tag",
+        &out,
+        concat!(
+            "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735",
+            "d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d",
+            "83de3541e4c2b58177e065a9bf7b62ec"
+        ),
+    )?;
+    expect("SM4-GCM decrypt", sm4::sm4_gcm_decrypt(&key, &iv, &aad, &out)? == plaintext)
+}
+
+fn sm2_sign_verify() -> Result<()> {
+    let (private_key, public_key) = CoSignProtocol::generate_keypair();
+    let message = b"sm2 co-sign self test";
+
+    let signature = CoSignProtocol::sign(&private_key, message)?;
+    expect("SM2 verify", CoSignProtocol::verify(&public_key, message, &signature)?)?;
+    expect("SM2 verify (tampered message)", !CoSignProtocol::verify(&public_key, b"tampered", &signature)?)
+}
+
+fn sm2_encrypt_decrypt() -> Result<()> {
+    let (private_key, public_key) = CoSignProtocol::generate_keypair();
+    let message = b"sm2 co-sign self test";
+
+    let ciphertext = CoSignProtocol::encrypt(&public_key, message)?;
+    let plaintext = CoSignProtocol::decrypt(&private_key, &ciphertext)?;
+    expect("SM2 decrypt", plaintext.as_deref() == Some(&message[..]))
+}
+
+/// 运行全部自检项，返回每项结果
+pub fn run() -> Vec<SelfTestResult> {
+    vec![
+        SelfTestResult::new("sm3_kat", sm3_kat()),
+        SelfTestResult::new("sm4_kat", sm4_kat()),
+        SelfTestResult::new("sm4_gcm_kat", sm4_gcm_kat()),
+        SelfTestResult::new("sm2_sign_verify", sm2_sign_verify()),
+        SelfTestResult::new("sm2_encrypt_decrypt", sm2_encrypt_decrypt()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        for result in run() {
+            assert!(result.passed(), "{}: {:?}", result.name, result.error);
+        }
+    }
+
+    #[test]
+    fn test_expect_hex_mismatch() {
+        assert!(expect_hex("x", &[0x01], "01").is_ok());
+        assert!(expect_hex("x", &[0x01], "02").is_err());
+    }
+}
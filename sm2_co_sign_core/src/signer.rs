@@ -0,0 +1,192 @@
+//! RustCrypto `signature` trait 实现
+//!
+//! 让本库的类型直接用于依赖 [`signature`] 的生态（x509-cert 构建器、JOSE 库等），无需适配层：
+//!
+//! - [`Signature`]：`SignatureEncoding`，编码为 64 字节 r||s
+//! - [`PublicKey`]：`Verifier`（SM3withSM2，默认用户标识）与 `PrehashVerifier`（输入为 e）
+//! - [`CoSignClient`]：`AsyncSigner`，协同签名
+//! - [`CoSignSigner`]：同步 `Signer` + `Keypair`，供只接受同步签名器的库在阻塞线程中调用
+
+use crate::error::Error;
+use crate::protocol::{CoSignProtocol, DigestMode};
+use crate::secret::PublicKey;
+use crate::types::{Signature, SIGNATURE_LEN};
+use signature::hazmat::PrehashVerifier;
+use signature::{SignatureEncoding, Verifier};
+
+#[cfg(feature = "client")]
+use crate::client::CoSignClient;
+#[cfg(feature = "client")]
+use signature::{AsyncSigner, Keypair, Signer};
+#[cfg(feature = "client")]
+use std::sync::Arc;
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> crate::error::Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl From<Signature> for [u8; SIGNATURE_LEN] {
+    fn from(signature: Signature) -> Self {
+        signature.to_bytes()
+    }
+}
+
+impl SignatureEncoding for Signature {
+    type Repr = [u8; SIGNATURE_LEN];
+
+    fn to_bytes(&self) -> Self::Repr {
+        Signature::to_bytes(self)
+    }
+
+    fn encoded_len(&self) -> usize {
+        SIGNATURE_LEN
+    }
+}
+
+/// 转换为 `signature::Error`，保留原始错误作为 source
+fn signature_error(e: Error) -> signature::Error {
+    signature::Error::from_source(e)
+}
+
+/// SM3withSM2 验签，e = SM3(ZA || M)，ZA 使用默认用户标识
+impl Verifier<Signature> for PublicKey {
+    fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), signature::Error> {
+        let protocol = CoSignProtocol::new().map_err(signature_error)?;
+        let e = protocol
+            .calculate_message_hash(msg, self, DigestMode::Za)
+            .map_err(signature_error)?;
+        self.verify_prehash(&e, signature)
+    }
+}
+
+/// 基于 32 字节消息哈希 e 的验签
+impl PrehashVerifier<Signature> for PublicKey {
+    fn verify_prehash(&self, prehash: &[u8], signature: &Signature) -> Result<(), signature::Error> {
+        let protocol = CoSignProtocol::new().map_err(signature_error)?;
+        if protocol
+            .verify_digest(self, prehash, &signature.r, &signature.s)
+            .map_err(signature_error)?
+        {
+            Ok(())
+        } else {
+            Err(signature::Error::new())
+        }
+    }
+}
+
+/// 协同签名（SM3withSM2，默认用户标识），等同于 `sign(msg, DigestMode::Za)`
+#[cfg(feature = "client")]
+impl AsyncSigner<Signature> for CoSignClient {
+    async fn sign_async(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+        self.sign(msg, DigestMode::Za).await.map_err(signature_error)
+    }
+}
+
+/// 同步协同签名器
+///
+/// 在 tokio 运行时中创建，之后在该运行时之外的线程（如 `spawn_blocking`）中调用 `Signer::try_sign`，
+/// 签名请求交回创建时的运行时执行。在异步上下文中直接调用会返回错误而不是阻塞工作线程，
+/// 异步代码应改用 [`CoSignSigner::client`] 的 `AsyncSigner` 实现。
+#[cfg(feature = "client")]
+#[derive(Clone)]
+pub struct CoSignSigner {
+    client: Arc<CoSignClient>,
+    public_key: PublicKey,
+    handle: tokio::runtime::Handle,
+}
+
+#[cfg(feature = "client")]
+impl CoSignSigner {
+    /// 读取客户端当前的协同公钥并记录当前运行时；客户端需已设置密钥对
+    pub async fn new(client: Arc<CoSignClient>) -> crate::error::Result<Self> {
+        let public_key = client
+            .get_public_key()
+            .await
+            .ok_or(Error::InvalidState("No key pair available".to_string()))?;
+        Ok(Self {
+            client,
+            public_key,
+            handle: tokio::runtime::Handle::current(),
+        })
+    }
+
+    /// 底层客户端
+    pub fn client(&self) -> &Arc<CoSignClient> {
+        &self.client
+    }
+}
+
+#[cfg(feature = "client")]
+impl Signer<Signature> for CoSignSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+        // Reason: Handle::block_on 在运行时线程上会 panic
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(signature_error(Error::InvalidState(
+                "CoSignSigner::try_sign called from an async context, use AsyncSigner instead".to_string(),
+            )));
+        }
+        self.handle
+            .block_on(self.client.sign(msg, DigestMode::Za))
+            .map_err(signature_error)
+    }
+}
+
+#[cfg(feature = "client")]
+impl Keypair for CoSignSigner {
+    type VerifyingKey = PublicKey;
+
+    fn verifying_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_encoding() {
+        let raw = [0x11u8; SIGNATURE_LEN];
+        let signature = Signature::try_from(&raw[..]).unwrap();
+        assert_eq!(SignatureEncoding::to_bytes(&signature), raw);
+        assert_eq!(signature.to_vec(), raw.to_vec());
+        assert!(Signature::try_from(&raw[..63]).is_err());
+    }
+
+    #[test]
+    fn test_public_key_verifier() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let public_key = PublicKey::from_slice(&protocol.calculate_p1(&d1).unwrap()).unwrap();
+        let raw = CoSignProtocol::sign(&d1, b"hello").unwrap();
+        let signature = Signature::try_from(raw.as_slice()).unwrap();
+
+        assert!(public_key.verify(b"hello", &signature).is_ok());
+        assert!(public_key.verify(b"world", &signature).is_err());
+
+        let e = protocol.calculate_message_hash(b"hello", &public_key, DigestMode::Za).unwrap();
+        assert!(public_key.verify_prehash(&e, &signature).is_ok());
+        assert!(public_key.verify_prehash(&CoSignProtocol::sm3_hash(b"hello"), &signature).is_err());
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cosign_signer() {
+        let client = Arc::new(CoSignClient::with_server_url("http://localhost:8080").unwrap());
+        assert!(matches!(CoSignSigner::new(client.clone()).await, Err(Error::InvalidState(_))));
+
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let public_key = protocol.calculate_p1(&d1).unwrap();
+        client.set_key_pair(d1.to_vec(), public_key.clone(), "alice".to_string()).await.unwrap();
+
+        let signer = CoSignSigner::new(client).await.unwrap();
+        assert_eq!(signer.verifying_key().as_bytes(), public_key.as_slice());
+        // 运行时线程上调用同步接口返回错误而不是 panic
+        assert!(signer.try_sign(b"hello").is_err());
+    }
+}
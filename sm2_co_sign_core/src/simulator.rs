@@ -0,0 +1,506 @@
+//! 服务端 D2 模拟器
+//!
+//! 实现服务端持有 D2 一侧的协同计算，与 `CoSignProtocol` 的客户端计算配合：
+//! - 密钥生成：P2 = d2⁻¹·G，Pa = d2⁻¹·P1 - G，完整私钥 d = d1·d2⁻¹ - 1
+//! - 协同签名：(x1, y1) = k3·Q1 + k2·G，r = (e + x1) mod n，s2 = d2·k3，s3 = d2·(k2 + r)
+//! - 随机数承诺签名：收到 Q1 之前先选定 k2、k3 并承诺 SM3(K3 || Q2)，收到与承诺一致的 Q1 后再计算
+//! - 协同解密：T2 = d2⁻¹·T1
+//! - 密钥分量刷新：d2' = d2·t，客户端同步计算 d1' = d1·t，协同公钥不变
+//! - 子密钥派生：校验 P1_p = τ·P1 后为该用途生成独立的 d2_p（规则见 [`crate::subkey`]）
+//!
+//! 仅用于本地开发与测试（CLI `mock-server`），D2 以明文保存在调用方内存中。
+
+use crate::error::{Error, Result};
+use crate::protocol::CoSignProtocol;
+use crate::subkey;
+use libsm::sm2::ecc::{EccCtx, Point};
+use libsm::sm2::field::FieldElem;
+use num_bigint::BigUint;
+use std::sync::OnceLock;
+
+/// 进程内共享的 libsm 曲线上下文
+///
+/// Reason: EccCtx 构造时需解析曲线参数，各模拟器实例共享一份即可；
+/// 上下文构造后只读，可在线程间共享
+pub(crate) fn ecc() -> &'static EccCtx {
+    static ECC: OnceLock<EccCtx> = OnceLock::new();
+    ECC.get_or_init(EccCtx::new)
+}
+
+/// 标量编码为 32 字节大端，不足时左补零
+pub(crate) fn scalar_bytes(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    // 调用方传入的值均小于 n，超过 32 字节时仅保留低 32 字节
+    let len = bytes.len().min(32);
+    let mut out = [0u8; 32];
+    out[32 - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+    out
+}
+
+/// 服务端生成的密钥（`Debug` 输出隐藏 D2）
+#[derive(Clone)]
+pub struct D2Key {
+    /// 服务端私钥分量 D2（32 字节）
+    pub d2: Vec<u8>,
+    /// P2 = d2⁻¹·G（64 字节，x||y）
+    pub p2: Vec<u8>,
+    /// 协同公钥 Pa（64 字节，x||y）
+    pub public_key: Vec<u8>,
+}
+
+impl core::fmt::Debug for D2Key {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("D2Key")
+            .field("d2", &crate::types::REDACTED)
+            .field("p2", &hex::encode(&self.p2))
+            .field("public_key", &hex::encode(&self.public_key))
+            .finish()
+    }
+}
+
+/// 服务端签名响应分量
+#[derive(Debug, Clone)]
+pub struct D2Signature {
+    pub r: Vec<u8>,
+    pub s2: Vec<u8>,
+    pub s3: Vec<u8>,
+}
+
+/// 随机数承诺签名中服务端预先选定的随机数（`Debug` 输出隐藏 k2、k3）
+pub struct D2Nonce {
+    k2: BigUint,
+    k3: BigUint,
+    /// K3 = k3·G（64 字节，x||y）
+    k3g: Vec<u8>,
+    /// Q2 = k2·G（64 字节，x||y）
+    q2: Vec<u8>,
+}
+
+impl D2Nonce {
+    /// 揭示给客户端的 K3 = k3·G
+    pub fn k3g(&self) -> &[u8] {
+        &self.k3g
+    }
+
+    /// 揭示给客户端的 Q2 = k2·G
+    pub fn q2(&self) -> &[u8] {
+        &self.q2
+    }
+
+    /// 收到 Q1 之前发送给客户端的承诺 SM3(K3 || Q2)
+    pub fn commitment(&self) -> [u8; 32] {
+        CoSignProtocol::nonce_commitment(&[&self.k3g, &self.q2])
+    }
+}
+
+impl core::fmt::Debug for D2Nonce {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("D2Nonce")
+            .field("k2", &crate::types::REDACTED)
+            .field("k3", &crate::types::REDACTED)
+            .field("k3g", &hex::encode(&self.k3g))
+            .field("q2", &hex::encode(&self.q2))
+            .finish()
+    }
+}
+
+/// 服务端 D2 模拟器
+pub struct D2Simulator {
+    ecc: &'static EccCtx,
+}
+
+impl Default for D2Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl D2Simulator {
+    pub fn new() -> Self {
+        Self { ecc: ecc() }
+    }
+
+    /// 解析 64 字节（x||y）或 65 字节（04||x||y）的曲线点
+    fn point_from_bytes(&self, bytes: &[u8]) -> Result<Point> {
+        let bytes = match bytes.len() {
+            64 => bytes,
+            65 if bytes[0] == 0x04 => &bytes[1..],
+            _ => return Err(Error::InvalidParam("Invalid point length, expected 64 or 65 bytes".to_string())),
+        };
+        let x = FieldElem::from_bytes(&bytes[0..32]).map_err(|e| Error::InvalidPoint(e.to_string()))?;
+        let y = FieldElem::from_bytes(&bytes[32..64]).map_err(|e| Error::InvalidPoint(e.to_string()))?;
+        self.ecc.new_point(&x, &y).map_err(|e| Error::InvalidPoint(e.to_string()))
+    }
+
+    /// 曲线点编码为 64 字节（x||y，各补零到 32 字节）
+    fn point_to_bytes(&self, point: &Point) -> Result<Vec<u8>> {
+        let (x, y) = self.ecc.to_affine(point).map_err(|e| Error::Crypto(e.to_string()))?;
+        let mut bytes = scalar_bytes(&BigUint::from_bytes_be(&x.to_bytes())).to_vec();
+        bytes.extend(scalar_bytes(&BigUint::from_bytes_be(&y.to_bytes())));
+        Ok(bytes)
+    }
+
+    /// d⁻¹ mod n（费马小定理，n 为素数）
+    fn inverse(&self, d: &BigUint) -> BigUint {
+        let n = self.ecc.get_n();
+        d.modpow(&(n - BigUint::from(2u32)), n)
+    }
+
+    fn parse_d2(&self, d2: &[u8]) -> Result<BigUint> {
+        let d2 = BigUint::from_bytes_be(d2);
+        if d2 == BigUint::from(0u32) || &d2 >= self.ecc.get_n() {
+            return Err(Error::InvalidParam("Invalid D2".to_string()));
+        }
+        Ok(d2)
+    }
+
+    /// 根据客户端 P1 生成 D2、P2 与协同公钥 Pa
+    pub fn generate_key(&self, p1: &[u8]) -> Result<D2Key> {
+        let p1 = self.point_from_bytes(p1)?;
+        self.derive_key(self.ecc.random_uint(), &p1)
+    }
+
+    /// 根据刷新因子 t 与客户端新的 P1' = d1'·G 计算 d2' = d2·t 及对应的 P2、Pa
+    ///
+    /// 调用方应确认返回的协同公钥与原公钥一致后再保存新的 D2。
+    pub fn refresh_key(&self, d2: &[u8], p1: &[u8], factor: &[u8]) -> Result<D2Key> {
+        let d2 = self.parse_d2(d2)?;
+        let p1 = self.point_from_bytes(p1)?;
+        let n = self.ecc.get_n();
+        let t = BigUint::from_bytes_be(factor);
+        if t == BigUint::from(0u32) || &t >= n {
+            return Err(Error::InvalidParam("Invalid refresh factor".to_string()));
+        }
+        self.derive_key((d2 * t) % n, &p1)
+    }
+
+    /// 为用途 `purpose`、代次 `generation` 生成子密钥
+    ///
+    /// `p1` 为注册时的主 P1，`sub_p1` 为客户端提交的 P1_p；两者不满足 P1_p = τ·P1 时拒绝，
+    /// 保证子密钥只能由本次注册的主 D1 派生。d2_p 独立随机生成，与主 D2 无关。
+    pub fn derive_sub_key(&self, p1: &[u8], purpose: &str, generation: u32, sub_p1: &[u8]) -> Result<D2Key> {
+        let expected = subkey::derive_sub_p1(p1, purpose, generation)?;
+        let sub_p1 = self.point_from_bytes(sub_p1)?;
+        if self.point_to_bytes(&sub_p1)? != expected {
+            return Err(Error::InvalidParam("Sub-key P1 is not derived from the enrolled P1".to_string()));
+        }
+        self.derive_key(self.ecc.random_uint(), &sub_p1)
+    }
+
+    /// 由 D2 与客户端 P1 计算 P2 与协同公钥 Pa
+    fn derive_key(&self, d2: BigUint, p1: &Point) -> Result<D2Key> {
+        let n = self.ecc.get_n();
+        let d2_inv = self.inverse(&d2);
+
+        let p2 = self.ecc.g_mul(&d2_inv).map_err(|e| Error::Crypto(e.to_string()))?;
+        // Pa = d2⁻¹·P1 + (n-1)·G
+        let d2_inv_p1 = self.ecc.mul(&d2_inv, p1).map_err(|e| Error::Crypto(e.to_string()))?;
+        let neg_g = self
+            .ecc
+            .g_mul(&(n - BigUint::from(1u32)))
+            .map_err(|e| Error::Crypto(e.to_string()))?;
+        let pa = self.ecc.add(&d2_inv_p1, &neg_g).map_err(|e| Error::Crypto(e.to_string()))?;
+
+        Ok(D2Key {
+            d2: scalar_bytes(&d2).to_vec(),
+            p2: self.point_to_bytes(&p2)?,
+            public_key: self.point_to_bytes(&pa)?,
+        })
+    }
+
+    /// 根据客户端 Q1 与消息哈希 e 计算签名分量 (r, s2, s3)
+    pub fn sign(&self, d2: &[u8], q1: &[u8], e: &[u8]) -> Result<D2Signature> {
+        let d2 = self.parse_d2(d2)?;
+        let q1 = self.point_from_bytes(q1)?;
+        if e.len() != 32 {
+            return Err(Error::InvalidParam("Message digest must be 32 bytes".to_string()));
+        }
+
+        // Reason: r = 0 时签名无效，按 SM2 标准重新选取随机数
+        loop {
+            let k2 = self.ecc.random_uint();
+            let k3 = self.ecc.random_uint();
+            if let Some(signature) = self.sign_with(&d2, &q1, e, &k2, &k3)? {
+                return Ok(signature);
+            }
+        }
+    }
+
+    /// 随机数承诺签名第一轮：在收到 Q1 之前选定 k2、k3，承诺随 [`D2Nonce::commitment`] 发给客户端
+    pub fn commit_nonce(&self) -> Result<D2Nonce> {
+        let k2 = self.ecc.random_uint();
+        let k3 = self.ecc.random_uint();
+        let q2 = self.ecc.g_mul(&k2).map_err(|e| Error::Crypto(e.to_string()))?;
+        let k3g = self.ecc.g_mul(&k3).map_err(|e| Error::Crypto(e.to_string()))?;
+        Ok(D2Nonce {
+            k3g: self.point_to_bytes(&k3g)?,
+            q2: self.point_to_bytes(&q2)?,
+            k2,
+            k3,
+        })
+    }
+
+    /// 随机数承诺签名第二轮：校验 Q1 与客户端先前的承诺 SM3(Q1) 一致，用已承诺的随机数计算 (r, s2, s3)
+    ///
+    /// 随机数按值消费，每组只能用于一次签名；r = 0 时返回错误，由客户端换新的 k1 重新开始。
+    pub fn sign_committed(
+        &self,
+        d2: &[u8],
+        nonce: D2Nonce,
+        q1_commitment: &[u8],
+        q1: &[u8],
+        e: &[u8],
+    ) -> Result<D2Signature> {
+        let d2 = self.parse_d2(d2)?;
+        let q1 = self.point_from_bytes(q1)?;
+        // Reason: 承诺按 64 字节 x||y 计算，客户端发送 65 字节编码时同样可以匹配
+        if q1_commitment != CoSignProtocol::nonce_commitment(&[&self.point_to_bytes(&q1)?]).as_slice() {
+            return Err(Error::InvalidParam("Q1 does not match the client commitment".to_string()));
+        }
+        if e.len() != 32 {
+            return Err(Error::InvalidParam("Message digest must be 32 bytes".to_string()));
+        }
+        self.sign_with(&d2, &q1, e, &nonce.k2, &nonce.k3)?
+            .ok_or_else(|| Error::Crypto("Committed nonce produced r = 0, restart the signing round".to_string()))
+    }
+
+    /// 用给定的 k2、k3 计算签名分量，r = 0 时返回 `None`
+    fn sign_with(&self, d2: &BigUint, q1: &Point, e: &[u8], k2: &BigUint, k3: &BigUint) -> Result<Option<D2Signature>> {
+        let n = self.ecc.get_n();
+        let e = BigUint::from_bytes_be(e);
+
+        let q2 = self.ecc.g_mul(k2).map_err(|e| Error::Crypto(e.to_string()))?;
+        let k3_q1 = self.ecc.mul(k3, q1).map_err(|e| Error::Crypto(e.to_string()))?;
+        let point = self.ecc.add(&k3_q1, &q2).map_err(|e| Error::Crypto(e.to_string()))?;
+        let (x1, _) = self.ecc.to_affine(&point).map_err(|e| Error::Crypto(e.to_string()))?;
+
+        let r = (&e + BigUint::from_bytes_be(&x1.to_bytes())) % n;
+        if r == BigUint::from(0u32) {
+            return Ok(None);
+        }
+        let s2 = (d2 * k3) % n;
+        let s3 = (d2 * ((k2 + &r) % n)) % n;
+
+        Ok(Some(D2Signature {
+            r: scalar_bytes(&r).to_vec(),
+            s2: scalar_bytes(&s2).to_vec(),
+            s3: scalar_bytes(&s3).to_vec(),
+        }))
+    }
+
+    /// 根据客户端 T1 计算 T2 = d2⁻¹·T1
+    pub fn decrypt(&self, d2: &[u8], t1: &[u8]) -> Result<Vec<u8>> {
+        let d2 = self.parse_d2(d2)?;
+        let t1 = self.point_from_bytes(t1)?;
+        let t2 = self
+            .ecc
+            .mul(&self.inverse(&d2), &t1)
+            .map_err(|e| Error::Crypto(e.to_string()))?;
+        self.point_to_bytes(&t2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphertext::Sm2Ciphertext;
+    use crate::types::Signature;
+
+    #[test]
+    fn test_d2_key_debug_redacts_d2() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let key = D2Simulator::new().generate_key(&p1).unwrap();
+        let debug = format!("{:?}", key);
+        assert!(!debug.contains(&hex::encode(&key.d2)) && !debug.contains(&format!("{:?}", key.d2)));
+        assert!(debug.contains(&hex::encode(&key.public_key)));
+    }
+
+    #[test]
+    fn test_co_sign_roundtrip() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        let key = simulator.generate_key(&p1).unwrap();
+        assert_eq!(key.public_key.len(), 64);
+
+        let e = CoSignProtocol::sm3_hash(b"hello world");
+        let session = protocol.sign_prepare().unwrap();
+        let response = simulator.sign(&key.d2, session.q1(), &e).unwrap();
+        let Signature { r, s, .. } = session
+            .complete(&protocol, &d1, &response.r, &response.s2, &response.s3)
+            .unwrap();
+
+        assert!(protocol.verify_digest(&key.public_key, &e, &r, &s).unwrap());
+        let other = CoSignProtocol::sm3_hash(b"other");
+        assert!(!protocol.verify_digest(&key.public_key, &other, &r, &s).unwrap());
+    }
+
+    #[test]
+    fn test_committed_sign_roundtrip() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+        let d1 = protocol.generate_d1().unwrap();
+        let key = simulator.generate_key(&protocol.calculate_p1(&d1).unwrap()).unwrap();
+        let e = CoSignProtocol::sm3_hash(b"hello world");
+
+        let session = protocol.sign_prepare().unwrap();
+        let nonce = simulator.commit_nonce().unwrap();
+        let commitment = nonce.commitment();
+        assert!(format!("{:?}", nonce).contains(crate::types::REDACTED));
+        let (k3g, q2) = (nonce.k3g().to_vec(), nonce.q2().to_vec());
+        let response = simulator
+            .sign_committed(&key.d2, nonce, &session.commitment(), session.q1(), &e)
+            .unwrap();
+
+        session.verify_server_nonce(&e, &commitment, &k3g, &q2, &response.r).unwrap();
+        // 揭示的随机数点与承诺不符
+        let err = session.verify_server_nonce(&e, &commitment, &q2, &k3g, &response.r).unwrap_err();
+        assert!(matches!(err, Error::InvalidServerResponse { ref field, .. } if field == "commitment"));
+        // r 不由承诺的随机数得出
+        let other = CoSignProtocol::sm3_hash(b"other");
+        let err = session.verify_server_nonce(&other, &commitment, &k3g, &q2, &response.r).unwrap_err();
+        assert!(matches!(err, Error::InvalidServerResponse { ref field, .. } if field == "r"));
+
+        let Signature { r, s, .. } = session
+            .complete(&protocol, &d1, &response.r, &response.s2, &response.s3)
+            .unwrap();
+        assert!(protocol.verify_digest(&key.public_key, &e, &r, &s).unwrap());
+
+        // Q1 与承诺不一致时拒绝
+        let session = protocol.sign_prepare().unwrap();
+        let other_q1 = protocol.sign_prepare().unwrap();
+        let nonce = simulator.commit_nonce().unwrap();
+        assert!(simulator
+            .sign_committed(&key.d2, nonce, &session.commitment(), other_q1.q1(), &e)
+            .is_err());
+    }
+
+    #[test]
+    fn test_co_decrypt_roundtrip() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        let key = simulator.generate_key(&p1).unwrap();
+
+        let message = b"hello world";
+        let ciphertext = CoSignProtocol::encrypt(&key.public_key, message).unwrap();
+        let parsed = Sm2Ciphertext::parse(&ciphertext).unwrap();
+
+        let t1 = protocol.decrypt_prepare(&d1, &parsed.c1).unwrap();
+        let t2 = simulator.decrypt(&key.d2, &t1).unwrap();
+        assert_eq!(protocol.complete_decryption(&t2, &parsed.c1, &parsed.c3, parsed.c2).unwrap(), message);
+    }
+
+    #[test]
+    fn test_co_decrypt_rejects_tampering() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+
+        let d1 = protocol.generate_d1().unwrap();
+        let key = simulator.generate_key(&protocol.calculate_p1(&d1).unwrap()).unwrap();
+        let ciphertext = CoSignProtocol::encrypt(&key.public_key, b"hello world").unwrap();
+        let (c1, c3, c2) = (&ciphertext[1..65], &ciphertext[65..97], &ciphertext[97..]);
+        let t2 = simulator.decrypt(&key.d2, &protocol.decrypt_prepare(&d1, c1).unwrap()).unwrap();
+
+        // 篡改 C2 或 C3 均无法通过完整性校验
+        let mut bad_c2 = c2.to_vec();
+        bad_c2[0] ^= 1;
+        assert!(matches!(protocol.complete_decryption(&t2, c1, c3, &bad_c2), Err(Error::Crypto(_))));
+        let mut bad_c3 = c3.to_vec();
+        bad_c3[31] ^= 1;
+        assert!(matches!(protocol.complete_decryption(&t2, c1, &bad_c3, c2), Err(Error::Crypto(_))));
+
+        // 服务端返回的 T2 不在曲线上或等于 C1
+        let mut bad_t2 = t2.clone();
+        bad_t2[63] ^= 1;
+        let field_of = |t2: &[u8]| match protocol.complete_decryption(t2, c1, c3, c2) {
+            Err(Error::InvalidServerResponse { field, .. }) => field,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(field_of(&bad_t2), "t2");
+        assert_eq!(field_of(c1), "t2");
+        assert_eq!(field_of(&t2[..32]), "t2");
+    }
+
+    #[test]
+    fn test_refresh_key_keeps_public_key() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+
+        let d1 = protocol.generate_d1().unwrap();
+        let key = simulator.generate_key(&protocol.calculate_p1(&d1).unwrap()).unwrap();
+
+        let factor = protocol.generate_d1().unwrap();
+        let new_d1 = protocol.refresh_d1(&d1, &factor).unwrap();
+        let refreshed = simulator
+            .refresh_key(&key.d2, &protocol.calculate_p1(&new_d1).unwrap(), &factor)
+            .unwrap();
+        assert_eq!(refreshed.public_key, key.public_key);
+        assert_ne!(refreshed.d2, key.d2);
+
+        let e = CoSignProtocol::sm3_hash(b"hello world");
+        let session = protocol.sign_prepare().unwrap();
+        let response = simulator.sign(&refreshed.d2, session.q1(), &e).unwrap();
+        let Signature { r, s, .. } = session
+            .complete(&protocol, &new_d1, &response.r, &response.s2, &response.s3)
+            .unwrap();
+        assert!(protocol.verify_digest(&key.public_key, &e, &r, &s).unwrap());
+
+        // 旧 D1 与新 D2 不再匹配
+        let session = protocol.sign_prepare().unwrap();
+        let response = simulator.sign(&refreshed.d2, session.q1(), &e).unwrap();
+        let Signature { r, s, .. } = session
+            .complete(&protocol, &d1, &response.r, &response.s2, &response.s3)
+            .unwrap();
+        assert!(!protocol.verify_digest(&key.public_key, &e, &r, &s).unwrap());
+    }
+
+    #[test]
+    fn test_sub_key_roundtrip() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let simulator = D2Simulator::new();
+
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        let master = simulator.generate_key(&p1).unwrap();
+
+        let sub_d1 = subkey::derive_sub_d1(&d1, subkey::PURPOSE_SIGN, 0).unwrap();
+        let sub_p1 = protocol.calculate_p1(&sub_d1).unwrap();
+        let key = simulator.derive_sub_key(&p1, subkey::PURPOSE_SIGN, 0, &sub_p1).unwrap();
+        assert_ne!(key.public_key, master.public_key);
+
+        let e = CoSignProtocol::sm3_hash(b"hello world");
+        let session = protocol.sign_prepare().unwrap();
+        let response = simulator.sign(&key.d2, session.q1(), &e).unwrap();
+        let Signature { r, s, .. } = session
+            .complete(&protocol, &sub_d1, &response.r, &response.s2, &response.s3)
+            .unwrap();
+        assert!(protocol.verify_digest(&key.public_key, &e, &r, &s).unwrap());
+
+        // 与主 P1 无关的 P1_p、用途或代次不符时拒绝
+        let other = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        assert!(simulator.derive_sub_key(&p1, subkey::PURPOSE_SIGN, 0, &other).is_err());
+        assert!(simulator.derive_sub_key(&p1, subkey::PURPOSE_SIGN, 1, &sub_p1).is_err());
+        assert!(simulator.derive_sub_key(&p1, subkey::PURPOSE_LOGIN, 0, &sub_p1).is_err());
+    }
+
+    #[test]
+    fn test_shared_curve_context() {
+        let a = D2Simulator::new();
+        let b = D2Simulator::new();
+        assert!(std::ptr::eq(a.ecc, b.ecc));
+        assert!(std::ptr::eq(a.ecc, ecc()));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let simulator = D2Simulator::new();
+        assert!(simulator.generate_key(&[0u8; 32]).is_err());
+        assert!(simulator.sign(&[0u8; 32], &[0u8; 64], &[0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! SM3 流式杂凑
+//!
+//! 按 GM/T 0004-2012 实现的增量接口，用于大文件分块计算摘要而无需整体读入内存；
+//! 一次性计算仍可使用 [`CoSignProtocol::sm3_hash`](crate::CoSignProtocol::sm3_hash)，两者结果一致。
+
+/// SM3 摘要长度（字节）
+pub const SM3_DIGEST_LEN: usize = 32;
+/// SM3 分组长度（字节）
+const BLOCK_LEN: usize = 64;
+
+const IV: [u32; 8] = [
+    0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e,
+];
+
+fn p0(x: u32) -> u32 {
+    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+fn p1(x: u32) -> u32 {
+    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+/// 压缩函数 CF
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 68];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for j in 16..68 {
+        w[j] = p1(w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15)) ^ w[j - 13].rotate_left(7) ^ w[j - 6];
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for j in 0..64 {
+        let (t, ff, gg) = if j < 16 {
+            (0x79cc4519u32, a ^ b ^ c, e ^ f ^ g)
+        } else {
+            (0x7a879d8au32, (a & b) | (a & c) | (b & c), (e & f) | (!e & g))
+        };
+        let ss1 = a
+            .rotate_left(12)
+            .wrapping_add(e)
+            .wrapping_add(t.rotate_left(j as u32 % 32))
+            .rotate_left(7);
+        let ss2 = ss1 ^ a.rotate_left(12);
+        let tt1 = ff.wrapping_add(d).wrapping_add(ss2).wrapping_add(w[j] ^ w[j + 4]);
+        let tt2 = gg.wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+        d = c;
+        c = b.rotate_left(9);
+        b = a;
+        a = tt1;
+        h = g;
+        g = f.rotate_left(19);
+        f = e;
+        e = p0(tt2);
+    }
+
+    for (v, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *v ^= x;
+    }
+}
+
+/// SM3 增量杂凑
+#[derive(Clone)]
+pub struct Sm3 {
+    state: [u32; 8],
+    buffer: [u8; BLOCK_LEN],
+    buffer_len: usize,
+    /// 已输入的总字节数
+    len: u64,
+}
+
+impl Default for Sm3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sm3 {
+    pub fn new() -> Self {
+        Self { state: IV, buffer: [0u8; BLOCK_LEN], buffer_len: 0, len: 0 }
+    }
+
+    /// 追加数据
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+
+        if self.buffer_len > 0 {
+            let take = data.len().min(BLOCK_LEN - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+            if self.buffer_len < BLOCK_LEN {
+                return;
+            }
+            let block = self.buffer;
+            compress(&mut self.state, &block);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            compress(&mut self.state, block);
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// 完成计算，返回 32 字节摘要
+    pub fn finalize(mut self) -> [u8; SM3_DIGEST_LEN] {
+        let bit_len = self.len.wrapping_mul(8);
+
+        // 填充：0x80 || 0x00... || 消息比特长度（u64 BE），使总长度为分组长度的整数倍
+        let mut padding = [0u8; BLOCK_LEN + 8];
+        padding[0] = 0x80;
+        let pad_len = if self.buffer_len < BLOCK_LEN - 8 {
+            BLOCK_LEN - 8 - self.buffer_len
+        } else {
+            2 * BLOCK_LEN - 8 - self.buffer_len
+        };
+        let len = self.len;
+        self.update(&padding[..pad_len]);
+        self.update(&bit_len.to_be_bytes());
+        debug_assert_eq!(self.buffer_len, 0);
+        self.len = len;
+
+        let mut digest = [0u8; SM3_DIGEST_LEN];
+        for (out, v) in digest.chunks_exact_mut(4).zip(self.state) {
+            out.copy_from_slice(&v.to_be_bytes());
+        }
+        digest
+    }
+
+    /// 一次性计算摘要
+    pub fn digest(data: &[u8]) -> [u8; SM3_DIGEST_LEN] {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoSignProtocol;
+
+    #[test]
+    fn test_sm3_standard_vectors() {
+        // GM/T 0004-2012 附录 A 示例
+        assert_eq!(
+            hex::encode(Sm3::digest(b"abc")),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+        assert_eq!(
+            hex::encode(Sm3::digest(&b"abcd".repeat(16))),
+            "debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732"
+        );
+    }
+
+    #[test]
+    fn test_sm3_incremental_matches_oneshot() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        for len in [0usize, 1, 55, 56, 63, 64, 65, 119, 120, 128, 1000] {
+            let expected = CoSignProtocol::sm3_hash(&data[..len]);
+            assert_eq!(Sm3::digest(&data[..len]).to_vec(), expected, "len {}", len);
+
+            // 不同分块方式结果一致
+            for step in [1usize, 3, 64, 100] {
+                let mut hasher = Sm3::new();
+                for chunk in data[..len].chunks(step) {
+                    hasher.update(chunk);
+                }
+                assert_eq!(hasher.finalize().to_vec(), expected, "len {} step {}", len, step);
+            }
+        }
+    }
+}
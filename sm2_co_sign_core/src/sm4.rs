@@ -0,0 +1,269 @@
+//! SM4 对称加密
+//!
+//! 基于 RustCrypto `sm4` 提供的分组运算（no_std）实现 CBC（PKCS#7 填充）和 GCM 两种工作模式，
+//! 用于数字信封等需要对大数据量做对称加密的场景。
+//!
+//! GCM 的实现遵循 NIST SP 800-38D 与 RFC 8998，仅支持 96 位 IV、128 位认证标签。
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::{Error, Result};
+use sm4_cipher::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use sm4_cipher::Sm4 as Sm4Cipher;
+
+/// SM4 密钥长度（字节）
+pub const SM4_KEY_LEN: usize = 16;
+/// SM4 分组长度（字节）
+pub const SM4_BLOCK_LEN: usize = 16;
+/// GCM IV 长度（字节）
+pub const SM4_GCM_IV_LEN: usize = 12;
+/// GCM 认证标签长度（字节）
+pub const SM4_GCM_TAG_LEN: usize = 16;
+
+fn new_cipher(key: &[u8]) -> Result<Sm4Cipher> {
+    if key.len() != SM4_KEY_LEN {
+        return Err(Error::Crypto("Invalid SM4 key length, expected 16 bytes".to_string()));
+    }
+    Ok(Sm4Cipher::new(key.into()))
+}
+
+fn encrypt_block(cipher: &Sm4Cipher, block: &[u8; SM4_BLOCK_LEN]) -> [u8; SM4_BLOCK_LEN] {
+    let mut out = (*block).into();
+    cipher.encrypt_block(&mut out);
+    out.into()
+}
+
+fn decrypt_block(cipher: &Sm4Cipher, block: &[u8; SM4_BLOCK_LEN]) -> [u8; SM4_BLOCK_LEN] {
+    let mut out = (*block).into();
+    cipher.decrypt_block(&mut out);
+    out.into()
+}
+
+/// SM4-CBC 加密（PKCS#7 填充）
+pub fn sm4_cbc_encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    if iv.len() != SM4_BLOCK_LEN {
+        return Err(Error::Crypto("Invalid SM4-CBC IV length, expected 16 bytes".to_string()));
+    }
+    let cipher = new_cipher(key)?;
+
+    let pad = SM4_BLOCK_LEN - plaintext.len() % SM4_BLOCK_LEN;
+    let mut padded = plaintext.to_vec();
+    padded.resize(plaintext.len() + pad, pad as u8);
+
+    let mut prev = [0u8; SM4_BLOCK_LEN];
+    prev.copy_from_slice(iv);
+    let mut ciphertext = Vec::with_capacity(padded.len());
+    for chunk in padded.chunks(SM4_BLOCK_LEN) {
+        let mut block = [0u8; SM4_BLOCK_LEN];
+        for (b, (c, p)) in block.iter_mut().zip(chunk.iter().zip(prev.iter())) {
+            *b = c ^ p;
+        }
+        prev = encrypt_block(&cipher, &block);
+        ciphertext.extend_from_slice(&prev);
+    }
+
+    Ok(ciphertext)
+}
+
+/// SM4-CBC 解密（校验并去除 PKCS#7 填充）
+pub fn sm4_cbc_decrypt(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    if iv.len() != SM4_BLOCK_LEN {
+        return Err(Error::Crypto("Invalid SM4-CBC IV length, expected 16 bytes".to_string()));
+    }
+    if ciphertext.is_empty() || ciphertext.len() % SM4_BLOCK_LEN != 0 {
+        return Err(Error::Crypto("Invalid SM4-CBC ciphertext length".to_string()));
+    }
+    let cipher = new_cipher(key)?;
+
+    let mut prev = [0u8; SM4_BLOCK_LEN];
+    prev.copy_from_slice(iv);
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for chunk in ciphertext.chunks(SM4_BLOCK_LEN) {
+        let mut block = [0u8; SM4_BLOCK_LEN];
+        block.copy_from_slice(chunk);
+        let decrypted = decrypt_block(&cipher, &block);
+        plaintext.extend(decrypted.iter().zip(prev.iter()).map(|(d, p)| d ^ p));
+        prev = block;
+    }
+
+    let pad = *plaintext.last().unwrap_or(&0) as usize;
+    if pad == 0
+        || pad > SM4_BLOCK_LEN
+        || plaintext[plaintext.len() - pad..].iter().any(|b| *b as usize != pad)
+    {
+        return Err(Error::Crypto("Invalid SM4-CBC padding".to_string()));
+    }
+    plaintext.truncate(plaintext.len() - pad);
+
+    Ok(plaintext)
+}
+
+/// GF(2^128) 乘法（GCM 比特序）
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0u128;
+    let mut v = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+/// GHASH(H, A, C)
+fn ghash(h: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+    let mut y = 0u128;
+    for data in [aad, ciphertext] {
+        for chunk in data.chunks(SM4_BLOCK_LEN) {
+            let mut block = [0u8; SM4_BLOCK_LEN];
+            block[..chunk.len()].copy_from_slice(chunk);
+            y = gf_mul(y ^ u128::from_be_bytes(block), h);
+        }
+    }
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    gf_mul(y ^ lengths, h)
+}
+
+/// GCM 计数器模式（从 J0 的下一个计数值开始）
+fn gctr(cipher: &Sm4Cipher, j0: &[u8; SM4_BLOCK_LEN], data: &[u8]) -> Vec<u8> {
+    let mut counter = *j0;
+    let mut out = Vec::with_capacity(data.len());
+    for chunk in data.chunks(SM4_BLOCK_LEN) {
+        // inc32：仅递增最后 32 位
+        let ctr = u32::from_be_bytes([counter[12], counter[13], counter[14], counter[15]]).wrapping_add(1);
+        counter[12..].copy_from_slice(&ctr.to_be_bytes());
+        let key_stream = encrypt_block(cipher, &counter);
+        out.extend(chunk.iter().zip(key_stream.iter()).map(|(d, k)| d ^ k));
+    }
+    out
+}
+
+/// 计算 GCM 认证标签
+fn gcm_tag(cipher: &Sm4Cipher, j0: &[u8; SM4_BLOCK_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; SM4_GCM_TAG_LEN] {
+    let h = u128::from_be_bytes(encrypt_block(cipher, &[0u8; SM4_BLOCK_LEN]));
+    let s = ghash(h, aad, ciphertext);
+    let ek_j0 = u128::from_be_bytes(encrypt_block(cipher, j0));
+    (s ^ ek_j0).to_be_bytes()
+}
+
+fn gcm_j0(iv: &[u8]) -> Result<[u8; SM4_BLOCK_LEN]> {
+    if iv.len() != SM4_GCM_IV_LEN {
+        return Err(Error::Crypto("Invalid SM4-GCM IV length, expected 12 bytes".to_string()));
+    }
+    let mut j0 = [0u8; SM4_BLOCK_LEN];
+    j0[..SM4_GCM_IV_LEN].copy_from_slice(iv);
+    j0[15] = 1;
+    Ok(j0)
+}
+
+/// SM4-GCM 加密，返回 密文 || 认证标签（16字节）
+pub fn sm4_gcm_encrypt(key: &[u8], iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = new_cipher(key)?;
+    let j0 = gcm_j0(iv)?;
+
+    let mut out = gctr(&cipher, &j0, plaintext);
+    let tag = gcm_tag(&cipher, &j0, aad, &out);
+    out.extend_from_slice(&tag);
+
+    Ok(out)
+}
+
+/// SM4-GCM 解密，输入为 密文 || 认证标签（16字节），标签校验失败时返回错误
+pub fn sm4_gcm_decrypt(key: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    if ciphertext.len() < SM4_GCM_TAG_LEN {
+        return Err(Error::Crypto("SM4-GCM ciphertext too short".to_string()));
+    }
+    let cipher = new_cipher(key)?;
+    let j0 = gcm_j0(iv)?;
+
+    let (body, tag) = ciphertext.split_at(ciphertext.len() - SM4_GCM_TAG_LEN);
+    let expected = gcm_tag(&cipher, &j0, aad, body);
+
+    // Reason: 常量时间比较，避免通过比较耗时泄露标签信息
+    let diff = expected.iter().zip(tag.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(Error::Crypto("SM4-GCM authentication failed".to_string()));
+    }
+
+    Ok(gctr(&cipher, &j0, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GB/T 32907-2016 附录 A 示例
+    const KEY: &str = "0123456789abcdeffedcba9876543210";
+
+    #[test]
+    fn test_sm4_block_known_answer() {
+        let key = hex::decode(KEY).unwrap();
+        let iv = [0u8; 16];
+        let ciphertext = sm4_cbc_encrypt(&key, &iv, &key).unwrap();
+        // 16 字节明文 + 一个完整的填充分组
+        assert_eq!(ciphertext.len(), 32);
+        assert_eq!(hex::encode(&ciphertext[..16]), "681edf34d206965e86b3e94f536e4246");
+    }
+
+    #[test]
+    fn test_sm4_cbc_roundtrip() {
+        let key = hex::decode(KEY).unwrap();
+        let iv = [0x42u8; 16];
+        for len in [0usize, 1, 15, 16, 17, 100] {
+            let plaintext = vec![0x5au8; len];
+            let ciphertext = sm4_cbc_encrypt(&key, &iv, &plaintext).unwrap();
+            assert_eq!(ciphertext.len() % 16, 0);
+            assert_eq!(sm4_cbc_decrypt(&key, &iv, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_sm4_cbc_bad_padding() {
+        let key = hex::decode(KEY).unwrap();
+        let iv = [0u8; 16];
+        let mut ciphertext = sm4_cbc_encrypt(&key, &iv, b"hello").unwrap();
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 0x01;
+        assert!(sm4_cbc_decrypt(&key, &iv, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_sm4_gcm_rfc8998() {
+        // RFC 8998 附录 A.1
+        let key = hex::decode(KEY).unwrap();
+        let iv = hex::decode("00001234567800000000abcd").unwrap();
+        let aad = hex::decode("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+        let plaintext = hex::decode(concat!(
+            "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbcccccccccccccccc",
+            "ddddddddddddddddeeeeeeeeeeeeeeeeffffffffffffffff",
+            "eeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa"
+        ))
+        .unwrap();
+
+        let out = sm4_gcm_encrypt(&key, &iv, &aad, &plaintext).unwrap();
+        let (ciphertext, tag) = out.split_at(plaintext.len());
+        assert_eq!(
+            hex::encode(ciphertext),
+            concat!(
+                "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735",
+                "d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d"
+            )
+        );
+        assert_eq!(hex::encode(tag), "83de3541e4c2b58177e065a9bf7b62ec");
+
+        assert_eq!(sm4_gcm_decrypt(&key, &iv, &aad, &out).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_sm4_gcm_tamper_detected() {
+        let key = hex::decode(KEY).unwrap();
+        let iv = [0x01u8; 12];
+        let mut out = sm4_gcm_encrypt(&key, &iv, b"aad", b"hello world").unwrap();
+        out[0] ^= 0x01;
+        assert!(sm4_gcm_decrypt(&key, &iv, b"aad", &out).is_err());
+        out[0] ^= 0x01;
+        assert!(sm4_gcm_decrypt(&key, &iv, b"other", &out).is_err());
+    }
+}
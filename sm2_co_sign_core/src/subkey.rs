@@ -0,0 +1,153 @@
+//! 按用途派生的子密钥分量
+//!
+//! 一次注册得到的主 D1 可以派生多个按用途（登录、签名、加密等）区分的子分量，各自对应服务端独立生成的
+//! D2 与独立的协同公钥，服务端一侧可单独轮换。客户端只需保存主 D1，子分量在使用时临时计算。
+//!
+//! 子密钥只在服务端一侧相互隔离，客户端一侧没有隔离：τ 是公开值，派生是乘法关系，任一子分量 D1_p 泄露即可
+//! 算出 D1 = D1_p·τ⁻¹ 及全部其他子分量，轮换代次同样无效（新的 τ 也可公开计算）。子分量与主 D1 须按同一
+//! 安全级别保管；客户端分量泄露时应刷新主分量（`/api/key/refresh`）或重新初始化主密钥，而不是轮换子密钥。
+//!
+//! 派生规则（版本 1）：
+//!
+//! ```text
+//! τ = SM3("SM2-COSIGN-SUBKEY-V1" || len(purpose) || purpose || generation || counter) mod n
+//! D1_p = D1·τ mod n            （客户端）
+//! P1_p = τ·P1 = D1_p·G         （服务端可由主 P1 独立计算并校验）
+//! ```
+//!
+//! - `purpose` 为 1–32 个 `[a-z0-9_-]` 字符的 ASCII 串，`len(purpose)`、`generation`（从 0 开始的代次）与
+//!   `counter` 均为 4 字节大端；`counter` 从 0 开始，摘要不在 [1, n-1] 内时加一重算（概率约 2⁻²²⁴）
+//! - τ 只由用途与代次决定，是公开值：服务端据此校验客户端提交的 P1_p 确由本次注册的主 D1 派生
+//! - 服务端为每个用途生成新的随机 D2_p，子协同公钥 Pa_p = D2_p⁻¹·P1_p - G 与主公钥及其他用途的公钥无关；
+//!   某个 D2_p 泄露只影响该用途，轮换代次即可换用新的 D2_p
+//!
+//! 消息约定：
+//! - `POST /api/key/derive` `{user_id, purpose, generation, p1}`：创建（generation 为 0）或轮换
+//!   （generation 比当前大 1）子密钥，服务端校验 P1_p 后生成新的 D2_p，返回 `{p2, publicKey}`
+//! - `/api/sign`、`/api/sign/commit`、`/api/decrypt` 请求可带 `purpose`、`generation`，服务端改用对应的 D2_p，
+//!   代次与服务端当前不一致时拒绝
+//! - 主密钥分量刷新（`/api/key/refresh`，D1' = D1·t）时服务端对每个子密钥同步计算 D2_p' = D2_p·t，
+//!   子公钥保持不变；重新初始化主密钥（`/api/key/init`）时服务端删除全部子密钥
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::ct_point;
+use crate::error::{Error, Result};
+use crate::secret::{scalar_from_slice, PublicKey, D1};
+use crate::sm3::Sm3;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// 派生规则的域分隔串
+pub const SUBKEY_DOMAIN: &[u8] = b"SM2-COSIGN-SUBKEY-V1";
+/// 用途标识的最大长度
+pub const MAX_PURPOSE_LEN: usize = 32;
+
+/// 用途标识：登录认证
+pub const PURPOSE_LOGIN: &str = "login";
+/// 用途标识：业务签名
+pub const PURPOSE_SIGN: &str = "sign";
+/// 用途标识：加密（协同解密）
+pub const PURPOSE_ENCRYPT: &str = "encrypt";
+
+/// 子密钥（不含私钥材料，持久化时无需加密）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubKey {
+    /// 用途标识
+    pub purpose: String,
+    /// 代次，每次轮换加一
+    pub generation: u32,
+    /// 子协同公钥 Pa_p
+    pub public_key: PublicKey,
+}
+
+/// 校验用途标识：1–32 个小写字母、数字、`_` 或 `-`
+pub fn validate_purpose(purpose: &str) -> Result<()> {
+    let valid = !purpose.is_empty()
+        && purpose.len() <= MAX_PURPOSE_LEN
+        && purpose.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-');
+    if !valid {
+        return Err(Error::InvalidParam(format!(
+            "Invalid sub-key purpose {:?}, expected 1-{} characters of [a-z0-9_-]",
+            purpose, MAX_PURPOSE_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// 计算用途与代次对应的派生因子 τ
+pub fn sub_key_factor(purpose: &str, generation: u32) -> Result<[u8; 32]> {
+    validate_purpose(purpose)?;
+    for counter in 0u32.. {
+        let mut hasher = Sm3::new();
+        hasher.update(SUBKEY_DOMAIN);
+        hasher.update(&(purpose.len() as u32).to_be_bytes());
+        hasher.update(purpose.as_bytes());
+        hasher.update(&generation.to_be_bytes());
+        hasher.update(&counter.to_be_bytes());
+        let tau = hasher.finalize();
+        if ct_point::is_valid_scalar(&tau) {
+            return Ok(tau);
+        }
+    }
+    unreachable!("SM3 output is a valid scalar with overwhelming probability")
+}
+
+/// 客户端派生子分量 D1_p = D1·τ mod n
+pub fn derive_sub_d1(d1: &[u8], purpose: &str, generation: u32) -> Result<D1> {
+    let tau = sub_key_factor(purpose, generation)?;
+    let d1 = Zeroizing::new(scalar_from_slice(d1, "D1")?);
+    let derived = Zeroizing::new(ct_point::mul_scalars(&d1[..], &tau)?);
+    D1::from_slice(&derived[..])
+}
+
+/// 服务端由主 P1 计算子分量公钥 P1_p = τ·P1（64 字节 x||y）
+pub fn derive_sub_p1(p1: &[u8], purpose: &str, generation: u32) -> Result<Vec<u8>> {
+    let tau = sub_key_factor(purpose, generation)?;
+    ct_point::mul_point(&tau, p1).map(|p1| p1.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CoSignProtocol;
+
+    #[test]
+    fn test_validate_purpose() {
+        for purpose in [PURPOSE_LOGIN, PURPOSE_SIGN, PURPOSE_ENCRYPT, "app-2_x"] {
+            validate_purpose(purpose).unwrap();
+        }
+        for purpose in ["", "Sign", "签名", "a b", "a".repeat(MAX_PURPOSE_LEN + 1).as_str()] {
+            assert!(matches!(validate_purpose(purpose), Err(Error::InvalidParam(_))));
+        }
+    }
+
+    #[test]
+    fn test_sub_p1_matches_sub_d1() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+
+        let sub_d1 = derive_sub_d1(&d1, PURPOSE_SIGN, 0).unwrap();
+        assert_eq!(protocol.calculate_p1(&sub_d1).unwrap(), derive_sub_p1(&p1, PURPOSE_SIGN, 0).unwrap());
+
+        // 用途、代次不同则因子不同
+        let tau = sub_key_factor(PURPOSE_SIGN, 0).unwrap();
+        assert_eq!(sub_key_factor(PURPOSE_SIGN, 0).unwrap(), tau);
+        assert_ne!(sub_key_factor(PURPOSE_SIGN, 1).unwrap(), tau);
+        assert_ne!(sub_key_factor(PURPOSE_LOGIN, 0).unwrap(), tau);
+        assert_ne!(sub_d1.as_bytes(), d1.as_bytes());
+    }
+
+    #[test]
+    fn test_sub_d1_follows_master_refresh() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let t = protocol.generate_d1().unwrap();
+
+        // 主分量刷新 D1' = D1·t 后，子分量恰为原子分量乘以 t，服务端同步 D2_p' = D2_p·t 即可
+        let refreshed = protocol.refresh_d1(&d1, &t).unwrap();
+        let expected = protocol.refresh_d1(&derive_sub_d1(&d1, PURPOSE_LOGIN, 3).unwrap(), &t).unwrap();
+        assert_eq!(derive_sub_d1(&refreshed, PURPOSE_LOGIN, 3).unwrap().as_bytes(), expected.as_bytes());
+    }
+}
@@ -0,0 +1,178 @@
+//! 客户端请求的请求 ID 与可观测性
+//!
+//! 每个发往服务端的请求生成随机请求 ID，通过 `X-Request-Id` 头发送，并记录在 tracing span
+//! `cosign.request`（字段 `operation`、`request_id`、`http.status_code`）中，便于与服务端日志关联。
+//!
+//! 启用 `otel` feature 后，每个请求另外：
+//!
+//! - 通过全局 tracer 创建 client span `cosign <操作>`，属性含操作名、请求 ID、HTTP 状态码与错误分类，
+//!   并按全局 propagator 注入 `traceparent` 等头，服务端可接续同一条链路
+//! - 通过全局 meter 记录请求数 `cosign.client.requests` 与耗时 `cosign.client.duration`（秒），
+//!   属性为 `operation` 与 `outcome`（`ok` 或 [`ErrorKind`](crate::ErrorKind) 名称）
+//!
+//! 本库只依赖 OpenTelemetry API，SDK 与导出器（OTLP 等）由应用安装；未安装时为空操作。
+
+use crate::error::Result;
+use crate::protocol::CoSignProtocol;
+use reqwest::RequestBuilder;
+use std::time::Instant;
+use tracing::{debug, field, Span};
+
+/// 请求 ID 头
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// OpenTelemetry instrumentation scope 名称
+pub const INSTRUMENTATION_SCOPE: &str = "sm2_co_sign_core";
+
+/// 请求 ID 的随机字节数（十六进制编码后 32 个字符）
+const REQUEST_ID_LEN: usize = 16;
+
+/// 一次服务端请求的追踪状态
+pub(crate) struct Trace {
+    operation: &'static str,
+    request_id: String,
+    started: Instant,
+    span: Span,
+    #[cfg(feature = "otel")]
+    context: opentelemetry::Context,
+}
+
+impl Trace {
+    pub(crate) fn start(operation: &'static str) -> Self {
+        let request_id = hex::encode(CoSignProtocol::generate_random(REQUEST_ID_LEN));
+        let span = tracing::info_span!(
+            "cosign.request",
+            operation,
+            request_id = %request_id,
+            http.status_code = field::Empty,
+        );
+        Self {
+            operation,
+            #[cfg(feature = "otel")]
+            context: otel::start(operation, &request_id),
+            request_id,
+            started: Instant::now(),
+            span,
+        }
+    }
+
+    /// 请求所在的 tracing span
+    pub(crate) fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// 附加请求 ID 与追踪上下文头
+    pub(crate) fn attach(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = request.header(REQUEST_ID_HEADER, self.request_id.as_str());
+        #[cfg(feature = "otel")]
+        let request = otel::inject(&self.context, request);
+        request
+    }
+
+    /// 记录服务端返回的 HTTP 状态码
+    pub(crate) fn record_status(&self, status: u16) {
+        self.span.record("http.status_code", status);
+        #[cfg(feature = "otel")]
+        otel::record_status(&self.context, status);
+    }
+
+    /// 结束请求，记录结果与耗时
+    pub(crate) fn finish<T>(self, result: &Result<T>) {
+        let error = result.as_ref().err();
+        let elapsed = self.started.elapsed();
+        let outcome = error.map_or("ok", |e| e.kind().as_str());
+        self.span.in_scope(|| debug!("{} finished in {:?}: {}", self.operation, elapsed, outcome));
+        #[cfg(feature = "otel")]
+        otel::finish(&self.context, self.operation, error, elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use super::INSTRUMENTATION_SCOPE;
+    use crate::error::Error;
+    use opentelemetry::propagation::Injector;
+    use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer};
+    use opentelemetry::{global, Context, KeyValue};
+    use reqwest::RequestBuilder;
+
+    /// 收集 propagator 输出的头
+    struct HeaderInjector(Vec<(String, String)>);
+
+    impl Injector for HeaderInjector {
+        fn set(&mut self, key: &str, value: String) {
+            self.0.push((key.to_string(), value));
+        }
+    }
+
+    pub(super) fn start(operation: &'static str, request_id: &str) -> Context {
+        let tracer = global::tracer(INSTRUMENTATION_SCOPE);
+        let span = tracer
+            .span_builder(format!("cosign {}", operation))
+            .with_kind(SpanKind::Client)
+            .with_attributes([
+                KeyValue::new("cosign.operation", operation),
+                KeyValue::new("cosign.request_id", request_id.to_string()),
+            ])
+            .start(&tracer);
+        Context::current_with_span(span)
+    }
+
+    pub(super) fn inject(context: &Context, mut request: RequestBuilder) -> RequestBuilder {
+        let mut injector = HeaderInjector(Vec::new());
+        global::get_text_map_propagator(|propagator| propagator.inject_context(context, &mut injector));
+        for (key, value) in injector.0 {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    pub(super) fn record_status(context: &Context, status: u16) {
+        context
+            .span()
+            .set_attribute(KeyValue::new("http.response.status_code", i64::from(status)));
+    }
+
+    pub(super) fn finish(context: &Context, operation: &'static str, error: Option<&Error>, elapsed: f64) {
+        let span = context.span();
+        let outcome = error.map_or("ok", |e| e.kind().as_str());
+        if let Some(e) = error {
+            span.set_attribute(KeyValue::new("error.type", outcome));
+            span.set_status(Status::error(e.to_string()));
+        }
+        span.end();
+
+        // Reason: 每次从全局 meter 取仪表，应用在首次请求之后才安装 MeterProvider 时也能生效
+        let meter = global::meter(INSTRUMENTATION_SCOPE);
+        let attributes = [KeyValue::new("operation", operation), KeyValue::new("outcome", outcome)];
+        meter
+            .u64_counter("cosign.client.requests")
+            .with_description("Requests sent to the co-sign server")
+            .build()
+            .add(1, &attributes);
+        meter
+            .f64_histogram("cosign.client.duration")
+            .with_description("Duration of requests to the co-sign server")
+            .with_unit("s")
+            .build()
+            .record(elapsed, &attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_request_ids_are_unique() {
+        let a = Trace::start("sign");
+        let b = Trace::start("sign");
+        assert_eq!(a.request_id.len(), REQUEST_ID_LEN * 2);
+        assert_ne!(a.request_id, b.request_id);
+
+        let request = a.attach(reqwest::Client::new().post("http://localhost:8080/api/sign")).build().unwrap();
+        assert_eq!(request.headers()[REQUEST_ID_HEADER], a.request_id.as_str());
+        a.finish::<()>(&Err(Error::NotAuthenticated));
+        b.finish(&Ok(()));
+    }
+}
@@ -0,0 +1,236 @@
+//! TLCP（GM/T 0024）握手中的客户端身份
+//!
+//! TLCP 使用双证书：签名证书用于 CertificateVerify / ServerKeyExchange 签名，加密证书用于
+//! ECC 套件中预主密钥的加解密。[`TlcpIdentity`] 把两张证书分别绑定到协同密钥，供国密 TLS 协议栈
+//! 在握手时回调，私钥分量 D1 不离开 [`CoSignClient`]：
+//!
+//! - 作为客户端（双向认证）：发送 [`TlcpIdentity::certificate_message`]，
+//!   以 [`TlcpIdentity::sign_certificate_verify`] 生成 CertificateVerify 签名
+//! - 作为服务端：以 [`TlcpIdentity::sign_server_key_exchange`] 签名 ECC 套件的 ServerKeyExchange，
+//!   以 [`TlcpIdentity::decrypt_pre_master_secret`] 协同解密 ClientKeyExchange 中的预主密钥
+//!
+//! ECDHE 套件需要加密私钥参与 SM2 密钥交换计算，协同密钥无法完成，只支持 ECC 套件。
+
+use crate::asn1;
+use crate::client::CoSignClient;
+use crate::error::{Error, Result};
+use crate::protocol::DigestMode;
+use crate::secret::PublicKey;
+use crate::sm3::Sm3;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// TLCP 协议版本（GMTLS 1.1）
+pub const TLCP_VERSION: [u8; 2] = [0x01, 0x01];
+/// 预主密钥长度
+pub const PRE_MASTER_SECRET_LEN: usize = 48;
+/// 握手消息中 24 位长度字段可表示的最大值
+const MAX_U24: usize = 0x00ff_ffff;
+
+/// 证书在 TLCP 中的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TlcpKeyUsage {
+    /// 签名证书
+    Sign,
+    /// 加密证书
+    Encrypt,
+}
+
+/// 绑定到协同密钥的一张证书
+#[derive(Clone)]
+pub struct TlcpKey {
+    client: Arc<CoSignClient>,
+    certificate: Vec<u8>,
+}
+
+impl TlcpKey {
+    /// `client` 需已设置会话与密钥对；`certificate` 为 DER 编码的 X.509 证书
+    pub fn new(client: Arc<CoSignClient>, certificate: Vec<u8>) -> Self {
+        Self { client, certificate }
+    }
+
+    /// 证书公钥须与客户端当前的协同公钥一致
+    async fn check(&self, usage: TlcpKeyUsage) -> Result<()> {
+        let certificate_key = PublicKey::from_slice(&asn1::public_key_from_certificate(&self.certificate)?)?;
+        let public_key = self
+            .client
+            .get_public_key()
+            .await
+            .ok_or(Error::InvalidState("No key pair available".to_string()))?;
+        if certificate_key != public_key {
+            return Err(Error::InvalidParam(format!(
+                "{:?} certificate does not match the co-sign public key",
+                usage
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// TLCP 双证书身份
+pub struct TlcpIdentity {
+    sign: TlcpKey,
+    enc: TlcpKey,
+}
+
+impl TlcpIdentity {
+    /// 绑定签名证书与加密证书，校验两张证书的公钥分别与对应客户端的协同公钥一致
+    ///
+    /// 签名密钥与加密密钥可以是同一用户的两个协同密钥（两个客户端实例），也可以共用一个。
+    pub async fn new(sign: TlcpKey, enc: TlcpKey) -> Result<Self> {
+        sign.check(TlcpKeyUsage::Sign).await?;
+        enc.check(TlcpKeyUsage::Encrypt).await?;
+        Ok(Self { sign, enc })
+    }
+
+    /// 指定用途的证书（DER）
+    pub fn certificate(&self, usage: TlcpKeyUsage) -> &[u8] {
+        match usage {
+            TlcpKeyUsage::Sign => &self.sign.certificate,
+            TlcpKeyUsage::Encrypt => &self.enc.certificate,
+        }
+    }
+
+    /// Certificate 握手消息体：24 位总长度，随后依次为签名证书、加密证书（各带 24 位长度）
+    pub fn certificate_message(&self) -> Result<Vec<u8>> {
+        let certificates = [&self.sign.certificate, &self.enc.certificate];
+        let total: usize = certificates.iter().map(|c| 3 + c.len()).sum();
+        let mut message = Vec::with_capacity(3 + total);
+        put_u24(&mut message, total)?;
+        for certificate in certificates {
+            put_u24(&mut message, certificate.len())?;
+            message.extend_from_slice(certificate);
+        }
+        Ok(message)
+    }
+
+    /// CertificateVerify 签名（DER 编码的 SM2 签名值）
+    ///
+    /// `handshake_messages` 为从 ClientHello 起至本消息之前的全部握手消息；签名内容为其 SM3 杂凑，
+    /// 按 SM3withSM2（默认用户标识）签名。
+    pub async fn sign_certificate_verify(&self, handshake_messages: &[u8]) -> Result<Vec<u8>> {
+        let digest = Sm3::digest(handshake_messages);
+        self.sign_with_za(&digest).await
+    }
+
+    /// CertificateVerify 握手消息体：16 位长度的签名值
+    pub async fn certificate_verify_message(&self, handshake_messages: &[u8]) -> Result<Vec<u8>> {
+        let signature = self.sign_certificate_verify(handshake_messages).await?;
+        let mut message = Vec::with_capacity(2 + signature.len());
+        message.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+        message.extend_from_slice(&signature);
+        Ok(message)
+    }
+
+    /// ECC 套件 ServerKeyExchange 签名（DER）
+    ///
+    /// 签名内容为 `client_random || server_random || 加密证书`（证书带 24 位长度），
+    /// 按 SM3withSM2（默认用户标识）签名。
+    pub async fn sign_server_key_exchange(&self, client_random: &[u8; 32], server_random: &[u8; 32]) -> Result<Vec<u8>> {
+        let mut content = Vec::with_capacity(64 + 3 + self.enc.certificate.len());
+        content.extend_from_slice(client_random);
+        content.extend_from_slice(server_random);
+        put_u24(&mut content, self.enc.certificate.len())?;
+        content.extend_from_slice(&self.enc.certificate);
+        self.sign_with_za(&content).await
+    }
+
+    /// 协同解密 ClientKeyExchange 中的预主密钥
+    ///
+    /// 密文为 SM2 密文（ASN.1 DER 或 C1C3C2），明文须为 48 字节且以 TLCP 版本号开头。
+    pub async fn decrypt_pre_master_secret(&self, encrypted: &[u8]) -> Result<Zeroizing<[u8; PRE_MASTER_SECRET_LEN]>> {
+        let plaintext = Zeroizing::new(self.enc.client.decrypt(encrypted).await?);
+        parse_pre_master_secret(&plaintext)
+    }
+
+    async fn sign_with_za(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let signature = self.sign.client.sign(content, DigestMode::Za).await?;
+        asn1::signature_to_der(&signature.to_bytes())
+    }
+}
+
+/// 校验预主密钥长度与版本号
+fn parse_pre_master_secret(plaintext: &[u8]) -> Result<Zeroizing<[u8; PRE_MASTER_SECRET_LEN]>> {
+    let secret: [u8; PRE_MASTER_SECRET_LEN] = plaintext
+        .try_into()
+        .map_err(|_| Error::Crypto(format!("Invalid pre-master secret length {}", plaintext.len())))?;
+    let secret = Zeroizing::new(secret);
+    if secret[..2] != TLCP_VERSION {
+        return Err(Error::Crypto("Pre-master secret has an unexpected protocol version".to_string()));
+    }
+    Ok(secret)
+}
+
+/// 写入 24 位大端长度
+fn put_u24(out: &mut Vec<u8>, len: usize) -> Result<()> {
+    if len > MAX_U24 {
+        return Err(Error::InvalidParam("Handshake field longer than 2^24 - 1 bytes".to_string()));
+    }
+    out.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只含 SubjectPublicKeyInfo 有效内容的最小证书
+    fn certificate_for(public_key: &[u8]) -> Vec<u8> {
+        let empty = asn1::encode_sequence(&[]);
+        let mut tbs = asn1::encode_unsigned_integer(&[0x01]);
+        for _ in 0..4 {
+            tbs.extend(&empty);
+        }
+        tbs.extend(asn1::public_key_to_spki(public_key).unwrap());
+        let mut certificate = asn1::encode_sequence(&tbs);
+        certificate.extend(&empty);
+        certificate.extend(asn1::encode_tlv(asn1::TAG_BIT_STRING, &[0x00]));
+        asn1::encode_sequence(&certificate)
+    }
+
+    #[test]
+    fn test_put_u24() {
+        let mut out = Vec::new();
+        put_u24(&mut out, 0x012345).unwrap();
+        assert_eq!(out, vec![0x01, 0x23, 0x45]);
+        assert!(put_u24(&mut out, MAX_U24 + 1).is_err());
+    }
+
+    #[test]
+    fn test_pre_master_secret() {
+        let mut secret = [0x5au8; PRE_MASTER_SECRET_LEN];
+        secret[..2].copy_from_slice(&TLCP_VERSION);
+        assert_eq!(*parse_pre_master_secret(&secret).unwrap(), secret);
+        assert!(parse_pre_master_secret(&secret[..47]).is_err());
+
+        // TLS 1.2 的版本号不是 TLCP 预主密钥
+        secret[..2].copy_from_slice(&[0x03, 0x03]);
+        assert!(matches!(parse_pre_master_secret(&secret), Err(Error::Crypto(_))));
+    }
+
+    #[tokio::test]
+    async fn test_identity_checks_certificates() {
+        let protocol = crate::CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let public_key = protocol.calculate_p1(&d1).unwrap();
+        let client = Arc::new(CoSignClient::with_server_url("http://localhost:8080").unwrap());
+        client.set_key_pair(d1.to_vec(), public_key.clone(), "u1".to_string()).await.unwrap();
+
+        let other = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let garbage = TlcpKey::new(client.clone(), b"not a certificate".to_vec());
+        assert!(matches!(TlcpIdentity::new(garbage.clone(), garbage).await, Err(Error::Encoding(_))));
+
+        let mismatched = TlcpKey::new(client.clone(), certificate_for(&other));
+        let matching = TlcpKey::new(client, certificate_for(&public_key));
+        assert!(matches!(
+            TlcpIdentity::new(matching.clone(), mismatched).await,
+            Err(Error::InvalidParam(_))
+        ));
+
+        let identity = TlcpIdentity::new(matching.clone(), matching).await.unwrap();
+        let message = identity.certificate_message().unwrap();
+        let len = identity.certificate(TlcpKeyUsage::Sign).len();
+        assert_eq!(message.len(), 3 + 2 * (3 + len));
+        assert_eq!(&message[3..6], &(len as u32).to_be_bytes()[1..]);
+    }
+}
@@ -0,0 +1,18 @@
+//! 可替换的请求发送方式
+//!
+//! 默认由客户端内部的 reqwest `Client` 直接发送。设置 [`ClientConfig::transport`](crate::ClientConfig)
+//! 后，所有发往服务端的请求（已附加认证头、请求 ID 与追踪上下文）交由 [`Transport`] 发送，
+//! 可用于录制 / 回放（`vcr` feature）、测试桩或自定义代理。
+
+use crate::error::Result;
+use std::future::Future;
+use std::pin::Pin;
+
+/// [`Transport::execute`] 返回的 future
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<reqwest::Response>> + Send + 'a>>;
+
+/// 请求发送方式
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// 发送请求；`client` 为客户端按配置（超时、TLS）构建的 reqwest 客户端，可直接用于转发
+    fn execute<'a>(&'a self, client: &'a reqwest::Client, request: reqwest::Request) -> TransportFuture<'a>;
+}
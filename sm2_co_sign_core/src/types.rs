@@ -1,6 +1,42 @@
 //! 数据类型定义
+//!
+//! [`Session`]、[`KeyPair`]、[`KeyRefresh`]、[`Signature`] 支持 serde：标量与签名分量以十六进制、
+//! 公钥以 Base64（与服务端接口一致）序列化，反序列化时按 [`crate::secret`] 的规则校验。
+//! 序列化结果包含 D1、Token 等敏感值，持久化时应由调用方加密保存；`Debug` 输出中敏感字段已隐藏。
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::error::{Error, Result};
+use crate::lifetime::KeyLifetime;
+#[cfg(feature = "std")]
+use crate::secret::AuthToken;
+use crate::secret::{PublicKey, D1};
+#[cfg(feature = "std")]
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::time::Duration;
+#[cfg(feature = "std")]
+use zeroize::Zeroizing;
+
+/// 签名分量以十六进制字符串序列化，反序列化时左补零为 32 字节
+mod serde_scalar {
+    #[cfg(not(feature = "std"))]
+    use crate::prelude::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = hex::decode(text).map_err(serde::de::Error::custom)?;
+        crate::asn1::left_pad_32(&bytes).map(|b| b.to_vec()).map_err(serde::de::Error::custom)
+    }
+}
 
 /// 用户信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,33 +50,344 @@ pub struct UserInfo {
     pub created_at: String,
 }
 
+/// 加密接收方：由服务端按用户名查询，首次取得后在本地固定公钥
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recipient {
+    /// 用户名
+    pub username: String,
+    /// 接收方的协同公钥
+    pub public_key: PublicKey,
+}
+
 /// 会话信息
-#[derive(Debug, Clone)]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
-    pub token: String,
+    pub token: AuthToken,
     pub user_id: String,
-    pub expires_at: String,
+    /// Token 过期时间（服务端未提供时为 `None`），序列化为 RFC 3339 字符串
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "std")]
+impl Session {
+    /// Token 是否已过期；未提供过期时间时视为未过期，以服务端校验为准
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// 距过期的剩余时间：已过期时为 0，未提供过期时间时为 `None`
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires_at
+            .map(|expires_at| (expires_at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+    }
+}
+
+/// 解析服务端返回的 Token 过期时间
+///
+/// 支持 RFC 3339（如 `2024-01-01T08:00:00+08:00`）与 Unix 时间戳（秒或毫秒）；空字符串表示未提供。
+#[cfg(feature = "std")]
+pub fn parse_expires_at(text: &str) -> Result<Option<DateTime<Utc>>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || {
+        Error::Encoding(format!(
+            "Invalid token expiry time {:?}, expected RFC 3339 or Unix timestamp",
+            text
+        ))
+    };
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        let value: i64 = text.parse().map_err(|_| invalid())?;
+        // Reason: 秒级时间戳在 2286 年之前不超过 10 位，13 位及以上按毫秒处理
+        let expires_at = if text.len() >= 13 {
+            DateTime::from_timestamp_millis(value)
+        } else {
+            DateTime::from_timestamp(value, 0)
+        };
+        return expires_at.map(Some).ok_or_else(invalid);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|expires_at| Some(expires_at.with_timezone(&Utc)))
+        .map_err(|_| invalid())
 }
 
 /// 密钥对（客户端持有的 D1 分量）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyPair {
     /// 客户端私钥分量 D1
-    pub d1: Vec<u8>,
+    pub d1: D1,
     /// 协同公钥 Pa
-    pub public_key: Vec<u8>,
+    pub public_key: PublicKey,
     /// 用户 ID
     pub user_id: String,
+    /// 当前 D1 的本地生命周期记录
+    #[serde(default)]
+    pub lifetime: KeyLifetime,
+}
+
+impl KeyPair {
+    /// D1 的年龄，创建时间未知时为 `None`
+    #[cfg(feature = "std")]
+    pub fn age(&self) -> Option<Duration> {
+        self.lifetime.age()
+    }
+
+    /// 当前 D1 已完成的签名、解密次数
+    pub fn usage_count(&self) -> u64 {
+        self.lifetime.usage_count
+    }
+
+    /// 协同公钥的 `PUBLIC KEY` PEM（SubjectPublicKeyInfo）
+    pub fn public_key_pem(&self) -> String {
+        self.public_key.to_pem()
+    }
+
+    /// 协同公钥的 SubjectPublicKeyInfo DER
+    pub fn public_key_der(&self) -> Vec<u8> {
+        self.public_key.to_spki_der()
+    }
+
+    /// 从注册后保存的文件加载密钥对
+    ///
+    /// - `d1_path`：未加密的 D1，原始 32 字节或 64 个十六进制字符
+    /// - `public_key_path`：格式见 [`PublicKey::from_file`]，须为曲线上的点
+    /// - `user_id_path`：用户 ID 文本，忽略首尾空白
+    ///
+    /// CLI 写出的口令保护密钥库须先解密，直接传入时返回 `Error::InvalidParam`。生命周期记录不在这些文件中，
+    /// 加载结果的创建时间未知、使用次数为 0。
+    #[cfg(feature = "std")]
+    pub fn from_files(
+        d1_path: impl AsRef<Path>,
+        public_key_path: impl AsRef<Path>,
+        user_id_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let d1 = read_d1(d1_path.as_ref())?;
+        let public_key = PublicKey::from_file(public_key_path)?;
+        let user_id = std::fs::read_to_string(user_id_path)?.trim().to_string();
+        if user_id.is_empty() {
+            return Err(Error::InvalidParam("User ID file is empty".to_string()));
+        }
+        Ok(Self { d1, public_key, user_id, lifetime: KeyLifetime::default() })
+    }
+}
+
+/// 读取未加密的 D1 文件（原始字节或十六进制文本）
+#[cfg(feature = "std")]
+fn read_d1(path: &Path) -> Result<D1> {
+    let data = Zeroizing::new(std::fs::read(path)?);
+    if data.first() == Some(&b'{') {
+        return Err(Error::InvalidParam(format!(
+            "D1 file {} is an encrypted keystore, decrypt it before loading",
+            path.display()
+        )));
+    }
+    let text = std::str::from_utf8(&data).ok().map(str::trim);
+    match text {
+        Some(text) if text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            let bytes = Zeroizing::new(hex::decode(text).map_err(|e| Error::Encoding(e.to_string()))?);
+            D1::from_slice(&bytes)
+        }
+        _ => D1::from_slice(&data),
+    }
+}
+
+/// 密钥分量刷新参数（由 `CoSignClient::prepare_key_refresh` 生成）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRefresh {
+    /// 随机刷新因子 t（取值范围与 D1 相同）
+    pub factor: D1,
+    /// 新的客户端私钥分量 D1' = D1·t
+    pub d1: D1,
 }
 
 /// 签名结果
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signature {
+    #[serde(with = "serde_scalar")]
     pub r: Vec<u8>,
+    #[serde(with = "serde_scalar")]
     pub s: Vec<u8>,
+    /// 签名请求携带的业务信息（交易流水号等），不参与签名编码
+    #[serde(default, skip_serializing_if = "SignOptions::is_empty")]
+    pub metadata: SignOptions,
+}
+
+/// 单笔签名的业务信息
+///
+/// 网关审计要求每笔签名登记业务流水号等信息时使用：随签名请求发送，并原样记录在返回的
+/// [`Signature::metadata`] 中。各字段为 1–[`MAX_SIGN_OPTION_LEN`] 个字符，不含控制字符。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignOptions {
+    /// 交易流水号（bizNo），服务端回显时须一致
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    /// 业务类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_type: Option<String>,
+    /// 备注
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+/// [`SignOptions`] 各字段的最大字符数
+pub const MAX_SIGN_OPTION_LEN: usize = 256;
+
+impl SignOptions {
+    /// 以交易流水号构造
+    pub fn with_transaction_id(transaction_id: impl Into<String>) -> Self {
+        Self { transaction_id: Some(transaction_id.into()), ..Self::default() }
+    }
+
+    /// 是否未设置任何字段
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|(_, value)| value.is_none())
+    }
+
+    /// 请求体中的字段名与取值
+    pub fn fields(&self) -> [(&'static str, Option<&str>); 3] {
+        [
+            ("transaction_id", self.transaction_id.as_deref()),
+            ("business_type", self.business_type.as_deref()),
+            ("remark", self.remark.as_deref()),
+        ]
+    }
+
+    /// 校验各字段非空、不超过 [`MAX_SIGN_OPTION_LEN`] 个字符且不含控制字符
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in self.fields() {
+            let Some(value) = value else { continue };
+            let len = value.chars().count();
+            if len == 0 || len > MAX_SIGN_OPTION_LEN || value.chars().any(char::is_control) {
+                return Err(Error::InvalidParam(format!(
+                    "Sign option {} must be 1-{} characters without control characters",
+                    name, MAX_SIGN_OPTION_LEN
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 签名编码长度（r||s，各 32 字节）
+pub const SIGNATURE_LEN: usize = 64;
+
+/// 分量左补零写入 32 字节；超过 32 字节时只保留低位（合法分量小于 n，不会出现）
+fn put_component(out: &mut [u8], value: &[u8]) {
+    let value = &value[value.len().saturating_sub(out.len())..];
+    let start = out.len() - value.len();
+    out[start..].copy_from_slice(value);
+}
+
+impl Signature {
+    /// 由 r、s 构造，不带业务信息
+    pub fn new(r: Vec<u8>, s: Vec<u8>) -> Self {
+        Self { r, s, metadata: SignOptions::default() }
+    }
+
+    /// 64 字节 r||s，分量不足 32 字节时左补零
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+        let mut bytes = [0u8; SIGNATURE_LEN];
+        put_component(&mut bytes[..32], &self.r);
+        put_component(&mut bytes[32..], &self.s);
+        bytes
+    }
+
+    /// 由 64 字节 r||s 构造
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != SIGNATURE_LEN {
+            return Err(Error::Encoding(format!(
+                "Invalid signature length {}, expected {} bytes (r||s)",
+                bytes.len(),
+                SIGNATURE_LEN
+            )));
+        }
+        Ok(Self::new(bytes[..32].to_vec(), bytes[32..].to_vec()))
+    }
+}
+
+/// 以 r||s 的十六进制（128 个字符）显示
+impl core::fmt::Display for Signature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&hex::encode(self.to_bytes()))
+    }
+}
+
+/// 解析 r||s 的十六进制表示（忽略首尾空白，大小写均可）
+impl core::str::FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s.trim()).map_err(|e| Error::Encoding(format!("Invalid signature hex: {}", e)))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// 待发送的 API 请求
+///
+/// 客户端内部据此发送请求；dry-run 模式下返回给调用方展示，不实际发送。`Debug` 输出为脱敏后的请求。
+#[cfg(feature = "std")]
+#[derive(Clone, Serialize)]
+pub struct ApiRequest {
+    /// HTTP 方法
+    pub method: String,
+    /// 请求 URL
+    pub url: String,
+    /// 是否携带 Bearer Token
+    pub authenticated: bool,
+    /// JSON 请求体
+    pub body: serde_json::Value,
+}
+
+#[cfg(feature = "std")]
+impl ApiRequest {
+    /// 脱敏后的请求：隐藏口令类字段
+    pub fn redacted(&self) -> Self {
+        let mut request = self.clone();
+        if let Some(body) = request.body.as_object_mut() {
+            for (key, value) in body.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                }
+            }
+        }
+        request
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for ApiRequest {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let redacted = self.redacted();
+        f.debug_struct("ApiRequest")
+            .field("method", &redacted.method)
+            .field("url", &redacted.url)
+            .field("authenticated", &redacted.authenticated)
+            .field("body", &redacted.body)
+            .finish()
+    }
 }
 
-/// 统一 API 响应
+/// 脱敏后的占位符
+pub const REDACTED: &str = "******";
+
+/// 需要脱敏的请求体字段
+///
+/// Reason: 刷新因子 t 与新的 D1' = D1·t 一同泄露即可还原 D1，与口令同样不能出现在日志中
+#[cfg(feature = "std")]
+const REDACTED_FIELDS: &[&str] = &["password", "factor"];
+
+/// 日志中代替数据本身的指纹：SM3 杂凑的前 8 字节（十六进制）
+///
+/// 只用于关联同一份数据的多条日志；口令等低熵数据可被穷举，不应记录其指纹。
+#[cfg(feature = "std")]
+pub fn fingerprint(data: &[u8]) -> String {
+    hex::encode(&crate::sm3::Sm3::digest(data)[..8])
+}
+
+/// 统一 API 响应（默认外层格式，客户端按 [`crate::response::FieldEnvelope::standard`] 解析）
 #[derive(Debug, Clone, Deserialize)]
 pub struct ApiResponse<T> {
     pub code: i32,
@@ -59,7 +406,7 @@ pub struct RegisterResponse {
 }
 
 /// 登录响应数据
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct LoginResponse {
     pub token: String,
     #[serde(rename = "userId")]
@@ -68,6 +415,16 @@ pub struct LoginResponse {
     pub expires_at: String,
 }
 
+impl core::fmt::Debug for LoginResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LoginResponse")
+            .field("token", &REDACTED)
+            .field("user_id", &self.user_id)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
 /// 密钥初始化响应数据
 #[derive(Debug, Clone, Deserialize)]
 pub struct KeyInitResponse {
@@ -82,6 +439,33 @@ pub struct SignResponse {
     pub r: String,
     pub s2: String,
     pub s3: String,
+    /// 服务端回显的交易流水号
+    #[serde(default, rename = "transactionId")]
+    pub transaction_id: Option<String>,
+}
+
+/// 随机数承诺签名第一轮响应数据
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignCommitResponse {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    /// 服务端随机数承诺 SM3(K3 || Q2)
+    pub commitment: String,
+}
+
+/// 随机数承诺签名第二轮响应数据
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignRevealResponse {
+    pub r: String,
+    pub s2: String,
+    pub s3: String,
+    /// K3 = k3·G
+    pub k3g: String,
+    /// Q2 = k2·G
+    pub q2: String,
+    /// 服务端回显的交易流水号
+    #[serde(default, rename = "transactionId")]
+    pub transaction_id: Option<String>,
 }
 
 /// 解密响应数据
@@ -101,3 +485,205 @@ pub struct UserInfoResponse {
     #[serde(rename = "createdAt")]
     pub created_at: String,
 }
+
+/// 用户公钥查询响应数据，`publicKey` 与 `certificate` 至少返回其一
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserLookupResponse {
+    pub username: String,
+    #[serde(rename = "publicKey", default)]
+    pub public_key: Option<String>,
+    /// DER 编码的 X.509 证书（Base64）
+    #[serde(default)]
+    pub certificate: Option<String>,
+}
+
+/// 用户证书响应数据
+#[derive(Debug, Clone, Deserialize)]
+pub struct CertificateResponse {
+    /// DER 编码的 X.509 证书（Base64）
+    pub certificate: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoSignProtocol;
+
+    fn key_pair() -> KeyPair {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let public_key = PublicKey::try_from(protocol.calculate_p1(&d1).unwrap()).unwrap();
+        KeyPair { d1, public_key, user_id: "u1".to_string(), lifetime: KeyLifetime::starting_at(1_700_000_000) }
+    }
+
+    #[test]
+    fn test_key_pair_serde_roundtrip() {
+        let key_pair = key_pair();
+        let value = serde_json::to_value(&key_pair).unwrap();
+        assert_eq!(value["d1"], hex::encode(&key_pair.d1));
+        assert_eq!(value["public_key"], crate::protocol::base64_encode(&key_pair.public_key));
+
+        let decoded: KeyPair = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.d1, key_pair.d1);
+        assert_eq!(decoded.public_key, key_pair.public_key);
+        assert_eq!(decoded.user_id, key_pair.user_id);
+        assert_eq!(decoded.lifetime, key_pair.lifetime);
+        assert_eq!(decoded.usage_count(), 0);
+
+        assert!(serde_json::from_str::<KeyPair>(r#"{"d1":"zz","public_key":"","user_id":"u1"}"#).is_err());
+    }
+
+    #[test]
+    fn test_signature_serde_roundtrip() {
+        let signature = Signature::new(vec![0x12; 32], vec![0x34; 31]);
+        let json = serde_json::to_string(&signature).unwrap();
+        assert_eq!(json, format!(r#"{{"r":"{}","s":"{}"}}"#, "12".repeat(32), "34".repeat(31)));
+        let decoded: Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.r, signature.r);
+        // 不足 32 字节的分量左补零
+        assert_eq!(decoded.s, [vec![0x00], vec![0x34; 31]].concat());
+
+        let too_long = format!(r#"{{"r":"{}","s":"{}"}}"#, "12".repeat(33), "34".repeat(32));
+        assert!(serde_json::from_str::<Signature>(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_sign_options() {
+        let mut signature = Signature::new(vec![0x12; 32], vec![0x34; 32]);
+        signature.metadata = SignOptions {
+            business_type: Some("contract".to_string()),
+            ..SignOptions::with_transaction_id("TX-20260101-0001")
+        };
+        let json = serde_json::to_value(&signature).unwrap();
+        assert_eq!(json["metadata"], serde_json::json!({ "transaction_id": "TX-20260101-0001", "business_type": "contract" }));
+        let decoded: Signature = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.metadata, signature.metadata);
+        signature.metadata.validate().unwrap();
+
+        assert!(SignOptions::default().is_empty());
+        for invalid in [String::new(), "a\nb".to_string(), "x".repeat(MAX_SIGN_OPTION_LEN + 1)] {
+            assert!(matches!(SignOptions::with_transaction_id(invalid).validate(), Err(Error::InvalidParam(_))));
+        }
+    }
+
+    #[test]
+    fn test_signature_bytes() {
+        let signature = Signature::new(vec![0x12; 32], vec![0x34; 31]);
+        let bytes = signature.to_bytes();
+        assert_eq!(&bytes[..32], &[0x12; 32]);
+        assert_eq!(bytes[32], 0x00);
+        assert_eq!(&bytes[33..], &[0x34; 31]);
+
+        let decoded = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.s.len(), 32);
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(Signature::from_bytes(&bytes[..63]).is_err());
+    }
+
+    #[test]
+    fn test_signature_display_from_str() {
+        let signature = Signature::new(vec![0xab; 32], vec![0x01]);
+        let text = signature.to_string();
+        assert_eq!(text, format!("{}{}01", "ab".repeat(32), "00".repeat(31)));
+
+        let parsed: Signature = format!(" {} ", text.to_uppercase()).parse().unwrap();
+        assert_eq!(parsed.to_bytes(), signature.to_bytes());
+        assert!("abcd".parse::<Signature>().is_err());
+        assert!("zz".repeat(64).parse::<Signature>().is_err());
+    }
+
+    #[test]
+    fn test_key_pair_from_files() {
+        let key_pair = key_pair();
+        let dir = std::env::temp_dir().join(format!("sm2-cosign-keypair-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (d1_path, public_key_path, user_id_path) = (dir.join("d1"), dir.join("public_key"), dir.join("user_id"));
+        std::fs::write(&public_key_path, key_pair.public_key_pem()).unwrap();
+        std::fs::write(&user_id_path, "u1\n").unwrap();
+
+        for d1 in [key_pair.d1.to_vec(), hex::encode(&key_pair.d1).into_bytes()] {
+            std::fs::write(&d1_path, d1).unwrap();
+            let loaded = KeyPair::from_files(&d1_path, &public_key_path, &user_id_path).unwrap();
+            assert_eq!(loaded.d1, key_pair.d1);
+            assert_eq!(loaded.public_key, key_pair.public_key);
+            assert_eq!(loaded.user_id, "u1");
+        }
+
+        std::fs::write(&d1_path, br#"{"version":1,"ciphertext":"00"}"#).unwrap();
+        assert!(matches!(
+            KeyPair::from_files(&d1_path, &public_key_path, &user_id_path),
+            Err(Error::InvalidParam(_))
+        ));
+        std::fs::write(&d1_path, key_pair.d1.as_bytes()).unwrap();
+        std::fs::write(&public_key_path, [0x11u8; 64]).unwrap();
+        assert!(KeyPair::from_files(&d1_path, &public_key_path, &user_id_path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_expires_at() {
+        let expected = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        for text in ["1704067200", "1704067200000", "2024-01-01T08:00:00+08:00", " 2024-01-01T00:00:00Z\n"] {
+            assert_eq!(parse_expires_at(text).unwrap(), Some(expected), "{}", text);
+        }
+        assert_eq!(parse_expires_at("").unwrap(), None);
+        assert!(matches!(parse_expires_at("tomorrow"), Err(Error::Encoding(_))));
+        assert!(parse_expires_at("2024-01-01 00:00:00").is_err());
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let token = AuthToken::new("t".to_string()).unwrap();
+        let mut session = Session { token, user_id: "u1".to_string(), expires_at: None };
+        assert!(!session.is_expired());
+        assert_eq!(session.remaining(), None);
+
+        session.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert!(session.is_expired());
+        assert_eq!(session.remaining(), Some(Duration::ZERO));
+
+        session.expires_at = Some(Utc::now() + chrono::Duration::hours(1));
+        assert!(!session.is_expired());
+        assert!(session.remaining().unwrap() > Duration::from_secs(3500));
+
+        let json = serde_json::to_value(&session).unwrap();
+        assert!(json["expires_at"].is_string());
+        let decoded: Session = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.expires_at, session.expires_at);
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let token = AuthToken::new("secret-token".to_string()).unwrap();
+        let session = Session { token, user_id: "u1".to_string(), expires_at: None };
+        let key_pair = key_pair();
+
+        assert!(!format!("{:?}", session).contains("secret-token"));
+        assert!(!format!("{:?}", key_pair).contains(&format!("{:?}", key_pair.d1.as_bytes())));
+        assert!(format!("{:?}", key_pair).contains(REDACTED));
+
+        let refresh = KeyRefresh { factor: key_pair.d1.clone(), d1: key_pair.d1.clone() };
+        assert!(!format!("{:?}", refresh).contains(&format!("{:?}", key_pair.d1.as_bytes())));
+
+        let response: LoginResponse =
+            serde_json::from_value(serde_json::json!({"token": "secret-token", "userId": "u1", "expiresAt": ""})).unwrap();
+        assert!(!format!("{:?}", response).contains("secret-token"));
+
+        let request = ApiRequest {
+            method: "POST".to_string(),
+            url: "http://localhost/api/key/refresh".to_string(),
+            authenticated: true,
+            body: serde_json::json!({"password": "hunter2", "factor": "00ff", "p1": "AAAA"}),
+        };
+        let debug = format!("{:?}", request);
+        assert!(!debug.contains("hunter2") && !debug.contains("00ff"));
+        assert!(debug.contains("AAAA"));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(b"abc"), hex::encode(&crate::sm3::Sm3::digest(b"abc")[..8]));
+        assert_ne!(fingerprint(b"abc"), fingerprint(b"abd"));
+    }
+}
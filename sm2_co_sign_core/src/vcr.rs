@@ -0,0 +1,379 @@
+//! 录制 / 回放服务端交互（VCR）
+//!
+//! [`Recorder`] 转发请求到真实服务端，并把每次交互写入 JSON 夹具（cassette）；[`Replayer`] 按顺序
+//! 回放夹具，集成测试因此可以在没有网关的 CI 中运行。两者都实现 [`Transport`]，设置到
+//! [`ClientConfig::transport`](crate::ClientConfig) 即可：
+//!
+//! ```no_run
+//! # fn main() -> sm2_co_sign_core::Result<()> {
+//! use sm2_co_sign_core::vcr::Replayer;
+//! use sm2_co_sign_core::{ClientConfig, CoSignClient};
+//! use std::sync::Arc;
+//!
+//! let client = CoSignClient::new(ClientConfig {
+//!     transport: Some(Arc::new(Replayer::from_file("tests/fixtures/sign.json")?)),
+//!     ..ClientConfig::default()
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 夹具只保存方法、路径（不含主机与查询参数）、请求体与响应，不保存请求头（认证 Token、请求 ID）；
+//! 请求体与响应中的 `token`、`password`、`factor` 字段替换为 `******`，其余需要隐去的值（如随机生成的
+//! 测试用户名）可用 [`Recorder::substitute`] 登记。
+//!
+//! CBOR 请求体与响应（见 [`crate::body`]）录制时转换为 JSON 保存，回放时一律以 JSON 返回，客户端按
+//! `Content-Type` 解析，同一夹具可供两种请求体编码的客户端使用。
+//!
+//! 回放只按方法与路径依次匹配请求，不比较请求体：D1 与 k1 每次随机生成，回放的 r/s2/s3 与本地分量
+//! 组合出的签名不能通过验签。回放验证的是请求流程与响应解析，密码学正确性由 D2 模拟器覆盖。
+
+use crate::body::BodyFormat;
+use crate::client::transport_error;
+use crate::error::{Error, Result};
+use crate::transport::{Transport, TransportFuture};
+use crate::types::REDACTED;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 选择集成测试运行模式的环境变量，取值见 [`Mode::from_env`]
+pub const VCR_MODE_ENV: &str = "SM2_COSIGN_VCR";
+
+/// 录制时脱敏的 JSON 字段
+const SCRUBBED_FIELDS: &[&str] = &["token", "password", "factor"];
+
+/// 集成测试运行模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// 连接真实服务端并重新录制夹具
+    Record,
+    /// 回放已有夹具
+    Replay,
+    /// 直接连接真实服务端，不读写夹具
+    Live,
+}
+
+impl Mode {
+    /// 读取 `SM2_COSIGN_VCR`：`record` / `replay` / `live`，未设置时为 `replay`
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(VCR_MODE_ENV).as_deref() {
+            Err(_) | Ok("") | Ok("replay") => Ok(Mode::Replay),
+            Ok("record") => Ok(Mode::Record),
+            Ok("live") => Ok(Mode::Live),
+            Ok(other) => Err(Error::InvalidParam(format!(
+                "Unknown {} value {:?}, expected record, replay or live",
+                VCR_MODE_ENV, other
+            ))),
+        }
+    }
+}
+
+/// 录制的请求
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// 路径，不含主机与查询参数
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// 录制的响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// JSON 响应体；非 JSON 响应体保存为字符串，回放时原样返回
+    pub body: Value,
+}
+
+/// 一次请求与响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// 夹具：按发送顺序排列的交互
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// 读取夹具文件
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| Error::Encoding(format!("Invalid cassette {}: {}", path.display(), e)))
+    }
+
+    /// 写入夹具文件，必要时创建上级目录
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Encoding(format!("Failed to encode cassette: {}", e)))?;
+        data.push(b'\n');
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+/// 录制传输：转发请求到真实服务端，脱敏后把交互写入夹具
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+    substitutions: Vec<(String, String)>,
+}
+
+impl Recorder {
+    /// 录制到 `path`，覆盖已有夹具
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+            substitutions: Vec::new(),
+        }
+    }
+
+    /// 写入夹具时把 `actual` 替换为 `placeholder`，如随机生成的测试用户名
+    pub fn substitute(mut self, actual: impl Into<String>, placeholder: impl Into<String>) -> Self {
+        self.substitutions.push((actual.into(), placeholder.into()));
+        self
+    }
+
+    /// 已录制的交互
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 替换登记的值并脱敏口令类字段，CBOR 先转换为 JSON
+    fn scrub(&self, body: &[u8], content_type: Option<&str>) -> Value {
+        let json = match content_type.and_then(BodyFormat::from_content_type) {
+            Some(BodyFormat::Cbor) => BodyFormat::Cbor
+                .decode::<Value>(body)
+                .and_then(|value| BodyFormat::Json.encode(&value))
+                .ok(),
+            _ => None,
+        };
+        let mut text = String::from_utf8_lossy(json.as_deref().unwrap_or(body)).into_owned();
+        for (actual, placeholder) in &self.substitutions {
+            text = text.replace(actual.as_str(), placeholder);
+        }
+        match serde_json::from_str(&text) {
+            Ok(mut value) => {
+                scrub_fields(&mut value);
+                value
+            }
+            Err(_) => Value::String(text),
+        }
+    }
+
+    async fn record(&self, client: &reqwest::Client, request: reqwest::Request) -> Result<reqwest::Response> {
+        let method = request.method().to_string();
+        let path = request.url().path().to_string();
+        let request_type = content_type(request.headers());
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| self.scrub(body, request_type.as_deref()));
+
+        let response = client.execute(request).await.map_err(transport_error("Request failed"))?;
+        let status = response.status().as_u16();
+        let response_type = content_type(response.headers());
+        let bytes = response.bytes().await.map_err(transport_error("Failed to read response"))?;
+
+        let interaction = Interaction {
+            request: RecordedRequest { method, path, body },
+            response: RecordedResponse { status, body: self.scrub(&bytes, response_type.as_deref()) },
+        };
+        // Reason: 每次交互后立即写盘，测试中途失败时已发生的交互仍保留在夹具中便于排查
+        {
+            let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+            cassette.interactions.push(interaction);
+            cassette.save(&self.path)?;
+        }
+
+        // 原始响应（未脱敏）交还客户端，录制过程中的会话照常进行
+        build_response(status, response_type.as_deref().unwrap_or("application/json"), bytes.to_vec())
+    }
+}
+
+impl Transport for Recorder {
+    fn execute<'a>(&'a self, client: &'a reqwest::Client, request: reqwest::Request) -> TransportFuture<'a> {
+        Box::pin(self.record(client, request))
+    }
+}
+
+/// 回放传输：按顺序返回夹具中的响应，不发起网络请求
+#[derive(Debug)]
+pub struct Replayer {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(cassette.interactions.into()),
+        }
+    }
+
+    /// 读取夹具文件
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    /// 尚未回放的交互数，测试结束时为 0 说明请求流程与录制时一致
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn replay(&self, request: &reqwest::Request) -> Result<reqwest::Response> {
+        let method = request.method().as_str();
+        let path = request.url().path();
+        let interaction = self
+            .interactions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front()
+            .ok_or_else(|| Error::InvalidState(format!("No recorded interaction left for {} {}", method, path)))?;
+        let expected = &interaction.request;
+        if expected.method != method || expected.path != path {
+            return Err(Error::InvalidState(format!(
+                "Unexpected request {} {}, cassette expects {} {}",
+                method, path, expected.method, expected.path
+            )));
+        }
+
+        let body = match interaction.response.body {
+            Value::String(text) => text,
+            value => value.to_string(),
+        };
+        build_response(interaction.response.status, "application/json", body.into_bytes())
+    }
+}
+
+impl Transport for Replayer {
+    fn execute<'a>(&'a self, _client: &'a reqwest::Client, request: reqwest::Request) -> TransportFuture<'a> {
+        Box::pin(std::future::ready(self.replay(&request)))
+    }
+}
+
+/// 把 `token`、`password`、`factor` 字段的字符串值替换为 `******`
+fn scrub_fields(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if value.is_string() && SCRUBBED_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    scrub_fields(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(scrub_fields),
+        _ => {}
+    }
+}
+
+fn content_type(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn build_response(status: u16, content_type: &str, body: Vec<u8>) -> Result<reqwest::Response> {
+    let response = http::Response::builder()
+        .status(status)
+        .header("content-type", content_type)
+        .body(body)
+        .map_err(|e| Error::InvalidState(format!("Invalid recorded response: {}", e)))?;
+    Ok(reqwest::Response::from(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientConfig, CoSignClient};
+    use std::sync::Arc;
+
+    fn interaction(method: &str, path: &str, status: u16, body: Value) -> Interaction {
+        Interaction {
+            request: RecordedRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                body: None,
+            },
+            response: RecordedResponse { status, body },
+        }
+    }
+
+    #[test]
+    fn test_scrub_secrets_and_substitutions() {
+        let recorder = Recorder::new("unused.json").substitute("test_user_1234", "test_user");
+        let body = br#"{"username":"test_user_1234","password":"p","data":{"token":"abc","list":[{"factor":"f"}]}}"#;
+        let scrubbed = recorder.scrub(body, Some("application/json"));
+        assert_eq!(
+            scrubbed,
+            serde_json::json!({
+                "username": "test_user",
+                "password": REDACTED,
+                "data": {"token": REDACTED, "list": [{"factor": REDACTED}]},
+            })
+        );
+        assert_eq!(recorder.scrub(b"OK", None), Value::String("OK".to_string()));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_scrub_cbor_as_json() {
+        let recorder = Recorder::new("unused.json");
+        let body = BodyFormat::Cbor.encode(&serde_json::json!({ "password": "p", "p1": "AA==" })).unwrap();
+        assert_eq!(
+            recorder.scrub(&body, Some("application/cbor")),
+            serde_json::json!({ "password": REDACTED, "p1": "AA==" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_in_order() {
+        let replayer = Arc::new(Replayer::new(Cassette {
+            interactions: vec![
+                interaction("GET", "/mapi/health", 200, Value::String("OK".to_string())),
+                interaction("GET", "/mapi/health", 503, Value::Null),
+            ],
+        }));
+        let client = CoSignClient::new(ClientConfig {
+            transport: Some(replayer.clone()),
+            ..ClientConfig::default()
+        })
+        .unwrap();
+
+        assert!(client.health_check().await.unwrap());
+        assert!(!client.health_check().await.unwrap());
+        assert_eq!(replayer.remaining(), 0);
+        assert!(matches!(client.health_check().await, Err(Error::InvalidState(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_unexpected_request() {
+        let client = CoSignClient::new(ClientConfig {
+            transport: Some(Arc::new(Replayer::new(Cassette {
+                interactions: vec![interaction("POST", "/api/login", 200, Value::Null)],
+            }))),
+            ..ClientConfig::default()
+        })
+        .unwrap();
+
+        let err = client.health_check().await.unwrap_err();
+        assert!(err.to_string().contains("/api/login"));
+    }
+}
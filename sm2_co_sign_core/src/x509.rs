@@ -0,0 +1,186 @@
+//! 基于 `der` / `x509-cert` 的证书请求与自签名证书构造
+//!
+//! 待签名结构（CertificationRequestInfo、TBSCertificate）由 x509-cert 的类型表示并编码，
+//! 签名算法与公钥算法统一使用 SM3withSM2 / SM2 OID，签名经任意 [`AsyncSigner`]（通常是
+//! [`CoSignClient`](crate::CoSignClient)）按 SM3withSM2（默认用户标识）协同完成，签名后用协同公钥在本地验证。
+
+use crate::asn1;
+use crate::error::{Error, Result};
+use crate::secret::PublicKey;
+use crate::types::Signature;
+use signature::{AsyncSigner, Verifier};
+use std::time::Duration;
+use x509_cert::certificate::{Certificate, TbsCertificate, Version};
+use x509_cert::der::asn1::{BitString, ObjectIdentifier, OctetString};
+use x509_cert::der::oid::AssociatedOid;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage, KeyUsages};
+use x509_cert::ext::Extension;
+use x509_cert::name::Name;
+use x509_cert::request::{CertReq, CertReqInfo};
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned};
+use x509_cert::time::Validity;
+
+pub use x509_cert;
+
+/// SM3withSM2 签名算法 (1.2.156.10197.1.501)
+pub const OID_SM3_WITH_SM2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.156.10197.1.501");
+
+/// 自签名证书序列号长度（RFC 5280 要求不超过 20 字节）
+const SERIAL_NUMBER_LEN: usize = 16;
+
+fn der_error(e: x509_cert::der::Error) -> Error {
+    Error::Encoding(format!("DER encoding failed: {}", e))
+}
+
+/// SM3withSM2 算法标识（无参数）
+pub fn sm3_with_sm2() -> AlgorithmIdentifierOwned {
+    AlgorithmIdentifierOwned { oid: OID_SM3_WITH_SM2, parameters: None }
+}
+
+/// 协同公钥的 SubjectPublicKeyInfo（id-ecPublicKey，曲线参数为 SM2 OID）
+pub fn subject_public_key_info(public_key: &PublicKey) -> Result<SubjectPublicKeyInfoOwned> {
+    SubjectPublicKeyInfoOwned::from_der(&asn1::public_key_to_spki(public_key)?).map_err(der_error)
+}
+
+/// 证书请求中被签名的 CertificationRequestInfo
+pub fn csr_info(subject: Name, public_key: &PublicKey) -> Result<CertReqInfo> {
+    Ok(CertReqInfo {
+        version: x509_cert::request::Version::V1,
+        subject,
+        public_key: subject_public_key_info(public_key)?,
+        attributes: Default::default(),
+    })
+}
+
+/// 生成 PKCS#10 证书请求
+pub async fn build_csr<S: AsyncSigner<Signature>>(signer: &S, subject: Name, public_key: &PublicKey) -> Result<CertReq> {
+    let info = csr_info(subject, public_key)?;
+    let signature = sign_der(signer, public_key, &info.to_der().map_err(der_error)?).await?;
+    Ok(CertReq { info, algorithm: sm3_with_sm2(), signature })
+}
+
+/// 自签名证书的 TBSCertificate（X.509 v3，颁发者即主题，序列号随机）
+///
+/// `ca` 为 true 时 basicConstraints 的 cA 为真、keyUsage 含 keyCertSign，可作为信任锚签发下级证书；
+/// 否则 keyUsage 为 digitalSignature | nonRepudiation。
+pub fn self_signed_tbs(subject: Name, public_key: &PublicKey, validity: Duration, ca: bool) -> Result<TbsCertificate> {
+    let mut serial = crate::CoSignProtocol::generate_random(SERIAL_NUMBER_LEN);
+    // Reason: 序列号须为正整数，且首字节非零以保持编码长度固定
+    serial[0] = (serial[0] & 0x7f) | 0x01;
+
+    let key_usage = if ca {
+        KeyUsages::DigitalSignature | KeyUsages::KeyCertSign | KeyUsages::CRLSign
+    } else {
+        KeyUsages::DigitalSignature | KeyUsages::NonRepudiation
+    };
+    let extensions = vec![
+        extension(BasicConstraints { ca, path_len_constraint: None }, true)?,
+        extension(KeyUsage(key_usage), true)?,
+    ];
+
+    Ok(TbsCertificate {
+        version: Version::V3,
+        serial_number: SerialNumber::new(&serial).map_err(der_error)?,
+        signature: sm3_with_sm2(),
+        issuer: subject.clone(),
+        validity: Validity::from_now(validity).map_err(der_error)?,
+        subject,
+        subject_public_key_info: subject_public_key_info(public_key)?,
+        issuer_unique_id: None,
+        subject_unique_id: None,
+        extensions: Some(extensions),
+    })
+}
+
+/// 生成自签名证书
+pub async fn build_self_signed<S: AsyncSigner<Signature>>(
+    signer: &S,
+    subject: Name,
+    public_key: &PublicKey,
+    validity: Duration,
+    ca: bool,
+) -> Result<Certificate> {
+    let tbs_certificate = self_signed_tbs(subject, public_key, validity, ca)?;
+    let signature = sign_der(signer, public_key, &tbs_certificate.to_der().map_err(der_error)?).await?;
+    Ok(Certificate { tbs_certificate, signature_algorithm: sm3_with_sm2(), signature })
+}
+
+fn extension<T: AssociatedOid + Encode>(value: T, critical: bool) -> Result<Extension> {
+    Ok(Extension {
+        extn_id: T::OID,
+        critical,
+        extn_value: OctetString::new(value.to_der().map_err(der_error)?).map_err(der_error)?,
+    })
+}
+
+/// 对待签名结构签名并验证，返回 DER 编码签名值的 BIT STRING
+async fn sign_der<S: AsyncSigner<Signature>>(signer: &S, public_key: &PublicKey, tbs: &[u8]) -> Result<BitString> {
+    let signature = signer
+        .sign_async(tbs)
+        .await
+        .map_err(|e| Error::Crypto(format!("Signing failed: {}", e)))?;
+    // Reason: CA 按证书请求中的公钥验签，签名者与公钥不匹配时尽早报错
+    public_key
+        .verify(tbs, &signature)
+        .map_err(|_| Error::Crypto("Signature does not verify against the public key".to_string()))?;
+    BitString::from_bytes(&asn1::signature_to_der(&signature.to_bytes())?).map_err(der_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoSignProtocol;
+    use std::str::FromStr;
+
+    /// 以完整私钥本地签名的测试签名器
+    struct LocalSigner(Vec<u8>);
+
+    impl AsyncSigner<Signature> for LocalSigner {
+        async fn sign_async(&self, msg: &[u8]) -> core::result::Result<Signature, signature::Error> {
+            let raw = CoSignProtocol::sign(&self.0, msg).map_err(signature::Error::from_source)?;
+            Signature::from_bytes(&raw).map_err(signature::Error::from_source)
+        }
+    }
+
+    fn local_key() -> (LocalSigner, PublicKey) {
+        let (private_key, public_key) = CoSignProtocol::generate_keypair();
+        (LocalSigner(private_key), PublicKey::from_slice(&public_key).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_build_csr() {
+        let (signer, public_key) = local_key();
+        let subject = Name::from_str("CN=Alice,O=Corp").unwrap();
+        let request = build_csr(&signer, subject.clone(), &public_key).await.unwrap();
+
+        let decoded = CertReq::from_der(&request.to_der().unwrap()).unwrap();
+        assert_eq!(decoded.info.subject, subject);
+        assert_eq!(decoded.algorithm.oid, OID_SM3_WITH_SM2);
+        assert_eq!(
+            decoded.info.public_key.to_der().unwrap(),
+            asn1::public_key_to_spki(&public_key).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_self_signed() {
+        let (signer, public_key) = local_key();
+        let subject = Name::from_str("CN=Root").unwrap();
+        let certificate = build_self_signed(&signer, subject, &public_key, Duration::from_secs(86400), true)
+            .await
+            .unwrap();
+        let der = certificate.to_der().unwrap();
+
+        let tbs = &certificate.tbs_certificate;
+        assert_eq!(tbs.issuer, tbs.subject);
+        assert_eq!(tbs.extensions.as_ref().unwrap().len(), 2);
+        assert_eq!(asn1::public_key_from_certificate(&der).unwrap(), public_key.to_vec());
+
+        // 与公钥不匹配的签名器
+        let (other, _) = local_key();
+        let err = build_csr(&other, Name::from_str("CN=Alice").unwrap(), &public_key).await.unwrap_err();
+        assert!(matches!(err, Error::Crypto(_)));
+    }
+}
@@ -0,0 +1,785 @@
+//! XML 数字签名（XMLDSig，国密算法）
+//!
+//! 支持两种形式：
+//!
+//! - 封内签名（enveloped）：`<ds:Signature>` 插入根元素末尾，引用 `URI=""` 为整个文档，
+//!   变换为 enveloped-signature + Exclusive C14N
+//! - 分离签名（detached）：`<ds:Signature>` 单独成文件，引用外部数据 `URI`，对原始字节计算摘要
+//!
+//! 算法标识采用 RFC 9231：签名 `xmldsig-more#sm2-sm3`（SM3withSM2，默认用户标识），
+//! 摘要 `xmldsig-more#sm3`；规范化统一为 Exclusive XML Canonicalization 1.0（不含注释）。
+//! `SignatureValue` 为 Base64 编码的 64 字节 r||s，验证时也接受 DER 编码。
+//!
+//! XML 由内置的精简解析器处理：不支持 DTD（遇到 `<!DOCTYPE` 直接拒绝，避免实体扩展），
+//! 只接受 UTF-8 编码；每个签名只含一个 `Reference`。
+
+use crate::asn1;
+use crate::client::CoSignClient;
+use crate::error::{Error, Result};
+use crate::protocol::{base64_decode, base64_encode, CoSignProtocol, DigestMode, DEFAULT_USER_ID};
+use crate::sm3::Sm3;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// XMLDSig 命名空间
+pub const NS_DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+/// SM3withSM2 签名算法（RFC 9231）
+pub const ALG_SM2_SM3: &str = "http://www.w3.org/2007/05/xmldsig-more#sm2-sm3";
+/// SM3 摘要算法（RFC 9231）
+pub const ALG_SM3: &str = "http://www.w3.org/2007/05/xmldsig-more#sm3";
+/// Exclusive XML Canonicalization 1.0（不含注释）
+pub const ALG_EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+/// enveloped-signature 变换
+pub const ALG_ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+const NS_XML: &str = "http://www.w3.org/XML/1998/namespace";
+/// 元素嵌套深度上限，防止恶意输入耗尽栈空间
+const MAX_DEPTH: usize = 256;
+
+/// 前缀 → 命名空间 URI，默认命名空间的前缀为空串
+type Namespaces = BTreeMap<String, String>;
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+    ProcessingInstruction(String),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    /// 属性（含命名空间声明），值已解码
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    /// 结束标签 `</` 在输入中的偏移，自闭合元素为 None
+    close: Option<usize>,
+}
+
+impl Element {
+    /// 本元素的命名空间作用域
+    fn scope(&self, parent: &Namespaces) -> Namespaces {
+        let mut scope = parent.clone();
+        for (key, value) in &self.attributes {
+            if key == "xmlns" {
+                scope.insert(String::new(), value.clone());
+            } else if let Some(prefix) = key.strip_prefix("xmlns:") {
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+        scope
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// 指定本地名的 XMLDSig 命名空间子元素及其作用域
+    fn dsig_children<'e>(&'e self, scope: &Namespaces, local: &str) -> Vec<(&'e Element, Namespaces)> {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(child) => {
+                    let child_scope = child.scope(scope);
+                    is_dsig(child, &child_scope, local).then_some((child, child_scope))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn dsig_child<'e>(&'e self, scope: &Namespaces, local: &str) -> Result<(&'e Element, Namespaces)> {
+        let mut children = self.dsig_children(scope, local);
+        if children.len() != 1 {
+            return Err(Error::InvalidParam(format!("Expected exactly one ds:{} element", local)));
+        }
+        Ok(children.remove(0))
+    }
+
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 元素内容按 Base64 解码（忽略换行等空白）
+    fn base64(&self) -> Result<Vec<u8>> {
+        let text: String = self.text().chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        base64_decode(&text)
+    }
+}
+
+#[derive(Debug)]
+struct Document {
+    /// 根元素之前的处理指令（不含 XML 声明）
+    before: Vec<String>,
+    root: Element,
+    after: Vec<String>,
+}
+
+fn split_name(name: &str) -> (&str, &str) {
+    name.split_once(':').unwrap_or(("", name))
+}
+
+fn is_dsig(element: &Element, scope: &Namespaces, local: &str) -> bool {
+    let (prefix, name) = split_name(&element.name);
+    name == local && scope.get(prefix).map(String::as_str) == Some(NS_DSIG)
+}
+
+fn malformed(reason: &str) -> Error {
+    Error::Encoding(format!("Malformed XML: {}", reason))
+}
+
+struct Parser<'a> {
+    xml: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn starts(&self, prefix: &str) -> bool {
+        self.rest().starts_with(prefix)
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if !self.starts(token) {
+            return Err(malformed(&format!("expected '{}' at offset {}", token, self.pos)));
+        }
+        self.pos += token.len();
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\r', '\n']).len();
+    }
+
+    /// 读取到 `terminator` 为止的内容并跳过终止符
+    fn until(&mut self, terminator: &str) -> Result<&'a str> {
+        let len = self
+            .rest()
+            .find(terminator)
+            .ok_or_else(|| malformed(&format!("missing '{}'", terminator)))?;
+        let content = &self.rest()[..len];
+        self.pos += len + terminator.len();
+        Ok(content)
+    }
+
+    fn name(&mut self) -> Result<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '>' | '=' | '<'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(malformed(&format!("expected a name at offset {}", self.pos)));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    /// `<?target data?>`，返回规范化形式；XML 声明返回 None
+    fn processing_instruction(&mut self) -> Result<Option<String>> {
+        self.expect("<?")?;
+        let content = self.until("?>")?;
+        let (target, data) = content
+            .split_once(|c: char| c.is_ascii_whitespace())
+            .unwrap_or((content, ""));
+        if target.eq_ignore_ascii_case("xml") {
+            return Ok(None);
+        }
+        let data = data.trim_start();
+        Ok(Some(if data.is_empty() {
+            format!("<?{}?>", target)
+        } else {
+            format!("<?{} {}?>", target, data)
+        }))
+    }
+
+    fn document(mut self) -> Result<Document> {
+        self.pos = if self.starts("\u{feff}") { 3 } else { 0 };
+        let (mut before, mut after, mut root) = (Vec::new(), Vec::new(), None);
+        loop {
+            self.skip_whitespace();
+            if self.rest().is_empty() {
+                break;
+            }
+            if self.starts("<?") {
+                if let Some(pi) = self.processing_instruction()? {
+                    let target = if root.is_some() { &mut after } else { &mut before };
+                    target.push(pi);
+                }
+            } else if self.starts("<!--") {
+                self.until("-->")?;
+            } else if self.starts("<!") {
+                return Err(Error::InvalidParam("XML with a DTD is not supported".to_string()));
+            } else if self.starts("<") && root.is_none() {
+                root = Some(self.element()?);
+            } else {
+                return Err(malformed("content outside the root element"));
+            }
+        }
+        let root = root.ok_or_else(|| malformed("no root element"))?;
+        Ok(Document { before, root, after })
+    }
+
+    fn element(&mut self) -> Result<Element> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(malformed("elements nested too deeply"));
+        }
+        self.expect("<")?;
+        let name = self.name()?.to_string();
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.starts("/>") {
+                self.pos += 2;
+                self.depth -= 1;
+                return Ok(Element { name, attributes, children: Vec::new(), close: None });
+            }
+            if self.starts(">") {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?.to_string();
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.starts("\"") { "\"" } else { "'" };
+            self.expect(quote)?;
+            let raw = self.until(quote)?;
+            if raw.contains('<') {
+                return Err(malformed("'<' in attribute value"));
+            }
+            if attributes.iter().any(|(existing, _)| *existing == key) {
+                return Err(malformed(&format!("duplicate attribute {}", key)));
+            }
+            // Reason: 属性值规范化，字面空白字符替换为空格（字符引用除外）
+            let normalized = normalize_newlines(raw).replace(['\t', '\n'], " ");
+            attributes.push((key, decode_entities(&normalized)?));
+        }
+
+        let mut children = Vec::new();
+        let close = loop {
+            if self.starts("</") {
+                let close = self.pos;
+                self.pos += 2;
+                if self.name()? != name {
+                    return Err(malformed(&format!("mismatched end tag for {}", name)));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                break close;
+            } else if self.starts("<!--") {
+                self.until("-->")?;
+            } else if self.starts("<![CDATA[") {
+                self.pos += 9;
+                children.push(Node::Text(normalize_newlines(self.until("]]>")?)));
+            } else if self.starts("<?") {
+                if let Some(pi) = self.processing_instruction()? {
+                    children.push(Node::ProcessingInstruction(pi));
+                }
+            } else if self.starts("<!") {
+                return Err(malformed("unexpected declaration in content"));
+            } else if self.starts("<") {
+                children.push(Node::Element(self.element()?));
+            } else {
+                let len = self.rest().find('<').ok_or_else(|| malformed(&format!("unterminated element {}", name)))?;
+                let text = &self.rest()[..len];
+                self.pos += len;
+                children.push(Node::Text(decode_entities(&normalize_newlines(text))?));
+            }
+        };
+        self.depth -= 1;
+        Ok(Element { name, attributes, children, close: Some(close) })
+    }
+}
+
+fn parse(xml: &[u8]) -> Result<Document> {
+    let xml = std::str::from_utf8(xml).map_err(|_| Error::Encoding("XML must be UTF-8".to_string()))?;
+    Parser { xml, pos: 0, depth: 0 }.document()
+}
+
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+fn decode_entities(text: &str) -> Result<String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| malformed("unterminated entity reference"))? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| malformed(&format!("unknown entity &{};", entity)))?
+            }
+        };
+        decoded.push(c);
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Exclusive C14N 输出一个元素（不含注释）
+///
+/// `scope` 为父元素的命名空间作用域，`rendered` 为输出祖先已声明的命名空间；
+/// `exclude` 指向的元素（enveloped-signature 变换中的签名元素）连同子树被省略。
+fn canonicalize_element(
+    element: &Element,
+    scope: &Namespaces,
+    rendered: &Namespaces,
+    exclude: Option<&Element>,
+    out: &mut String,
+) -> Result<()> {
+    let scope = element.scope(scope);
+    let (prefix, _) = split_name(&element.name);
+
+    // Reason: Exclusive C14N 只输出元素名与属性名实际用到的命名空间
+    let mut utilized = BTreeSet::from([prefix]);
+    let mut attributes = Vec::new();
+    for (key, value) in &element.attributes {
+        if key == "xmlns" || key.starts_with("xmlns:") {
+            continue;
+        }
+        let (attribute_prefix, local) = split_name(key);
+        let namespace = match attribute_prefix {
+            "" => "",
+            "xml" => NS_XML,
+            _ => {
+                utilized.insert(attribute_prefix);
+                scope
+                    .get(attribute_prefix)
+                    .ok_or_else(|| malformed(&format!("unbound prefix {}", attribute_prefix)))?
+            }
+        };
+        attributes.push((namespace, local, key, value));
+    }
+    attributes.sort();
+
+    let mut rendered_here = rendered.clone();
+    let mut declarations = Vec::new();
+    for prefix in utilized {
+        let uri = match (prefix, scope.get(prefix)) {
+            ("", uri) => uri.map(String::as_str).unwrap_or(""),
+            (_, Some(uri)) => uri.as_str(),
+            (prefix, None) => return Err(malformed(&format!("unbound prefix {}", prefix))),
+        };
+        if rendered.get(prefix).map(String::as_str).unwrap_or("") != uri {
+            declarations.push((prefix, uri));
+            rendered_here.insert(prefix.to_string(), uri.to_string());
+        }
+    }
+
+    out.push('<');
+    out.push_str(&element.name);
+    for (prefix, uri) in declarations {
+        out.push_str(if prefix.is_empty() { " xmlns" } else { " xmlns:" });
+        out.push_str(prefix);
+        out.push_str("=\"");
+        escape_attribute(uri, out);
+        out.push('"');
+    }
+    for (_, _, key, value) in attributes {
+        out.push(' ');
+        out.push_str(key);
+        out.push_str("=\"");
+        escape_attribute(value, out);
+        out.push('"');
+    }
+    out.push('>');
+    for child in &element.children {
+        match child {
+            Node::Element(child) if exclude.is_some_and(|excluded| std::ptr::eq(child, excluded)) => {}
+            Node::Element(child) => canonicalize_element(child, &scope, &rendered_here, exclude, out)?,
+            Node::Text(text) => escape_text(text, out),
+            Node::ProcessingInstruction(pi) => out.push_str(pi),
+        }
+    }
+    out.push_str("</");
+    out.push_str(&element.name);
+    out.push('>');
+    Ok(())
+}
+
+fn canonicalize_document(document: &Document, exclude: Option<&Element>) -> Result<Vec<u8>> {
+    let mut out = String::new();
+    for pi in &document.before {
+        out.push_str(pi);
+        out.push('\n');
+    }
+    canonicalize_element(&document.root, &Namespaces::new(), &Namespaces::new(), exclude, &mut out)?;
+    for pi in &document.after {
+        out.push('\n');
+        out.push_str(pi);
+    }
+    Ok(out.into_bytes())
+}
+
+/// 文档的 Exclusive C14N 规范化形式（不含注释）
+pub fn canonicalize(xml: &[u8]) -> Result<Vec<u8>> {
+    canonicalize_document(&parse(xml)?, None)
+}
+
+/// 文档中第一个 `ds:Signature` 元素及其父元素作用域
+fn find_signature<'d>(element: &'d Element, scope: &Namespaces) -> Option<(&'d Element, Namespaces)> {
+    let element_scope = element.scope(scope);
+    if is_dsig(element, &element_scope, "Signature") {
+        return Some((element, scope.clone()));
+    }
+    element.children.iter().find_map(|node| match node {
+        Node::Element(child) => find_signature(child, &element_scope),
+        _ => None,
+    })
+}
+
+/// 签名引用的对象
+enum Referenced<'a> {
+    /// 封内签名：整个文档去掉签名元素
+    Enveloped,
+    /// 分离签名：外部数据原始字节
+    Detached(&'a [u8]),
+}
+
+/// 签名验证结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlSignature {
+    /// 引用的 URI，封内签名为空串
+    pub reference_uri: String,
+    /// KeyInfo 中的签名者证书（DER）
+    pub certificate: Option<Vec<u8>>,
+    /// 验签所用公钥（64 字节 x||y）
+    pub public_key: Vec<u8>,
+}
+
+fn verify(xml: &[u8], referenced: Referenced, public_key: Option<&[u8]>) -> Result<XmlSignature> {
+    let document = parse(xml)?;
+    let (signature, parent_scope) =
+        find_signature(&document.root, &Namespaces::new()).ok_or_else(|| Error::InvalidParam("No ds:Signature element".to_string()))?;
+    let scope = signature.scope(&parent_scope);
+
+    let (signed_info, signed_info_scope) = signature.dsig_child(&scope, "SignedInfo")?;
+    let (method, _) = signed_info.dsig_child(&signed_info_scope, "CanonicalizationMethod")?;
+    expect_algorithm(method, ALG_EXC_C14N)?;
+    let (method, _) = signed_info.dsig_child(&signed_info_scope, "SignatureMethod")?;
+    expect_algorithm(method, ALG_SM2_SM3)?;
+
+    let (reference, reference_scope) = signed_info.dsig_child(&signed_info_scope, "Reference")?;
+    let reference_uri = reference.attribute("URI").unwrap_or("").to_string();
+    let transforms: Vec<&str> = match reference.dsig_children(&reference_scope, "Transforms").first() {
+        Some((element, element_scope)) => element
+            .dsig_children(element_scope, "Transform")
+            .iter()
+            .map(|(transform, _)| transform.attribute("Algorithm").unwrap_or(""))
+            .collect(),
+        None => Vec::new(),
+    };
+    let (method, _) = reference.dsig_child(&reference_scope, "DigestMethod")?;
+    expect_algorithm(method, ALG_SM3)?;
+    let (digest_value, _) = reference.dsig_child(&reference_scope, "DigestValue")?;
+
+    let digest = match (referenced, reference_uri.as_str(), transforms.as_slice()) {
+        (Referenced::Enveloped, "", [ALG_ENVELOPED] | [ALG_ENVELOPED, ALG_EXC_C14N]) => {
+            Sm3::digest(&canonicalize_document(&document, Some(signature))?)
+        }
+        (Referenced::Detached(data), uri, []) if !uri.is_empty() => Sm3::digest(data),
+        _ => return Err(Error::InvalidParam("Unsupported XML signature reference profile".to_string())),
+    };
+    if digest_value.base64()? != digest {
+        return Err(Error::Crypto("XML signature digest does not match the referenced data".to_string()));
+    }
+
+    let certificate = match signature.dsig_children(&scope, "KeyInfo").first() {
+        Some((key_info, key_info_scope)) => match key_info.dsig_children(key_info_scope, "X509Data").first() {
+            Some((data, data_scope)) => match data.dsig_children(data_scope, "X509Certificate").first() {
+                Some((certificate, _)) => Some(certificate.base64()?),
+                None => None,
+            },
+            None => None,
+        },
+        None => None,
+    };
+    let public_key = match (public_key, &certificate) {
+        (Some(public_key), _) => public_key.to_vec(),
+        (None, Some(certificate)) => asn1::public_key_from_certificate(certificate)?,
+        (None, None) => return Err(Error::InvalidParam("No public key or ds:X509Certificate to verify with".to_string())),
+    };
+
+    let (signature_value, _) = signature.dsig_child(&scope, "SignatureValue")?;
+    let signature_value = signature_value.base64()?;
+    let raw = if signature_value.len() == 64 {
+        signature_value
+    } else {
+        asn1::signature_from_der(&signature_value)?
+    };
+    let mut canonical = String::new();
+    canonicalize_element(signed_info, &scope, &Namespaces::new(), None, &mut canonical)?;
+    let protocol = CoSignProtocol::new()?;
+    let e = protocol.calculate_message_hash_with_uid(canonical.as_bytes(), DEFAULT_USER_ID, &public_key)?;
+    if !protocol.verify_digest(&public_key, &e, &raw[..32], &raw[32..])? {
+        return Err(Error::Crypto("XML signature is invalid".to_string()));
+    }
+    Ok(XmlSignature { reference_uri, certificate, public_key })
+}
+
+fn expect_algorithm(element: &Element, algorithm: &str) -> Result<()> {
+    match element.attribute("Algorithm") {
+        Some(value) if value == algorithm => Ok(()),
+        value => Err(Error::InvalidParam(format!("Unsupported algorithm {}", value.unwrap_or("")))),
+    }
+}
+
+/// 验证封内签名；`public_key` 为 None 时使用 KeyInfo 中的证书公钥
+pub fn verify_enveloped(xml: &[u8], public_key: Option<&[u8]>) -> Result<XmlSignature> {
+    verify(xml, Referenced::Enveloped, public_key)
+}
+
+/// 验证分离签名；`data` 为 Reference URI 指向的原始数据
+pub fn verify_detached(signature: &[u8], data: &[u8], public_key: Option<&[u8]>) -> Result<XmlSignature> {
+    verify(signature, Referenced::Detached(data), public_key)
+}
+
+/// `<ds:SignedInfo>`，自带 `xmlns:ds` 声明，可单独规范化
+fn signed_info(uri: &str, transforms: &[&str], digest: &[u8]) -> String {
+    let mut xml = format!(
+        "<ds:SignedInfo xmlns:ds=\"{}\"><ds:CanonicalizationMethod Algorithm=\"{}\"></ds:CanonicalizationMethod>\
+         <ds:SignatureMethod Algorithm=\"{}\"></ds:SignatureMethod><ds:Reference URI=\"",
+        NS_DSIG, ALG_EXC_C14N, ALG_SM2_SM3
+    );
+    escape_attribute(uri, &mut xml);
+    xml.push_str("\">");
+    if !transforms.is_empty() {
+        xml.push_str("<ds:Transforms>");
+        for transform in transforms {
+            xml.push_str(&format!("<ds:Transform Algorithm=\"{}\"></ds:Transform>", transform));
+        }
+        xml.push_str("</ds:Transforms>");
+    }
+    xml.push_str(&format!(
+        "<ds:DigestMethod Algorithm=\"{}\"></ds:DigestMethod><ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>",
+        ALG_SM3,
+        base64_encode(digest)
+    ));
+    xml
+}
+
+/// 组装 `<ds:Signature>`
+fn signature_element(signed_info: &str, signature: &[u8], certificate: Option<&[u8]>) -> String {
+    let mut xml = format!(
+        "<ds:Signature xmlns:ds=\"{}\">{}<ds:SignatureValue>{}</ds:SignatureValue>",
+        NS_DSIG,
+        signed_info,
+        base64_encode(signature)
+    );
+    if let Some(certificate) = certificate {
+        xml.push_str(&format!(
+            "<ds:KeyInfo><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo>",
+            base64_encode(certificate)
+        ));
+    }
+    xml.push_str("</ds:Signature>");
+    xml
+}
+
+/// 协同签名 SignedInfo 并在本地验证，返回 64 字节 r||s
+async fn co_sign(client: &CoSignClient, signed_info: &str, certificate: Option<&[u8]>) -> Result<Vec<u8>> {
+    let public_key = client
+        .get_public_key()
+        .await
+        .ok_or(Error::InvalidState("No key pair available".to_string()))?;
+    if let Some(certificate) = certificate {
+        if asn1::public_key_from_certificate(certificate)? != public_key.as_bytes() {
+            return Err(Error::InvalidParam("Certificate does not match the co-sign public key".to_string()));
+        }
+    }
+
+    let canonical = canonicalize(signed_info.as_bytes())?;
+    let signature = client.sign(&canonical, DigestMode::Za).await?;
+    let protocol = CoSignProtocol::new()?;
+    let e = protocol.calculate_message_hash_with_uid(&canonical, DEFAULT_USER_ID, &public_key)?;
+    if !protocol.verify_digest(&public_key, &e, &signature.r, &signature.s)? {
+        return Err(Error::Crypto("Co-signature does not verify against the public key".to_string()));
+    }
+    Ok(signature.to_bytes().to_vec())
+}
+
+/// 生成封内签名，`<ds:Signature>` 插入根元素的结束标签之前，文档其余部分保持原样
+///
+/// `certificate` 为签名者证书 DER，给出时须与协同公钥一致并写入 KeyInfo。
+pub async fn sign_enveloped(client: &CoSignClient, xml: &[u8], certificate: Option<&[u8]>) -> Result<Vec<u8>> {
+    let document = parse(xml)?;
+    if find_signature(&document.root, &Namespaces::new()).is_some() {
+        return Err(Error::InvalidParam("Document already contains a ds:Signature element".to_string()));
+    }
+    let close = document
+        .root
+        .close
+        .ok_or_else(|| Error::InvalidParam("Root element must not be self-closing".to_string()))?;
+
+    let digest = Sm3::digest(&canonicalize_document(&document, None)?);
+    let signed_info = signed_info("", &[ALG_ENVELOPED, ALG_EXC_C14N], &digest);
+    let signature = co_sign(client, &signed_info, certificate).await?;
+
+    let mut signed = xml[..close].to_vec();
+    signed.extend_from_slice(signature_element(&signed_info, &signature, certificate).as_bytes());
+    signed.extend_from_slice(&xml[close..]);
+    Ok(signed)
+}
+
+/// 生成分离签名，返回独立的 `<ds:Signature>` 文档
+///
+/// `uri` 写入 Reference，标识被签名的数据（如文件名或 URL）；摘要为 SM3(data)。
+pub async fn sign_detached(client: &CoSignClient, data: &[u8], uri: &str, certificate: Option<&[u8]>) -> Result<Vec<u8>> {
+    if uri.is_empty() {
+        return Err(Error::InvalidParam("Detached signature requires a reference URI".to_string()));
+    }
+    let signed_info = signed_info(uri, &[], &Sm3::digest(data));
+    let signature = co_sign(client, &signed_info, certificate).await?;
+    Ok(signature_element(&signed_info, &signature, certificate).into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以完整私钥签名的测试证书
+    fn test_key() -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let (private_key, public_key) = CoSignProtocol::generate_keypair();
+        let empty = asn1::encode_sequence(&[]);
+        let mut tbs = asn1::encode_unsigned_integer(&[0x01]);
+        for _ in 0..4 {
+            tbs.extend(&empty);
+        }
+        tbs.extend(asn1::public_key_to_spki(&public_key).unwrap());
+        let mut certificate = asn1::encode_sequence(&tbs);
+        certificate.extend(&empty);
+        certificate.extend(asn1::encode_tlv(asn1::TAG_BIT_STRING, &[0x00]));
+        (private_key, public_key, asn1::encode_sequence(&certificate))
+    }
+
+    fn local_sign(private_key: &[u8], signed_info: &str) -> Vec<u8> {
+        CoSignProtocol::sign(private_key, &canonicalize(signed_info.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let xml = "<?xml version=\"1.0\"?>\r\n<!-- c -->\n<a:doc xmlns:a=\"urn:a\" xmlns:unused=\"urn:u\" z='1' b=\"x&amp;y\">\
+                   <e/><![CDATA[<t>]]>&#x41;<!-- c --></a:doc>\n";
+        assert_eq!(
+            canonicalize(xml.as_bytes()).unwrap(),
+            b"<a:doc xmlns:a=\"urn:a\" b=\"x&amp;y\" z=\"1\"><e></e>&lt;t&gt;A</a:doc>"
+        );
+
+        // 子元素重新声明默认命名空间，已声明的前缀不重复输出
+        let xml = b"<r xmlns=\"urn:r\" xmlns:p=\"urn:p\"><p:x><y xmlns=\"\" p:k=\"v\"/></p:x></r>";
+        assert_eq!(
+            canonicalize(xml).unwrap(),
+            b"<r xmlns=\"urn:r\"><p:x xmlns:p=\"urn:p\"><y xmlns=\"\" p:k=\"v\"></y></p:x></r>"
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(canonicalize(b"<a><b></a>").is_err());
+        assert!(canonicalize(b"<a>&unknown;</a>").is_err());
+        assert!(canonicalize(b"<a x=\"1\" x=\"2\"/>").is_err());
+        assert!(canonicalize(b"<a/><b/>").is_err());
+        assert!(matches!(
+            canonicalize(b"<!DOCTYPE a [<!ENTITY e \"x\">]><a>&e;</a>"),
+            Err(Error::InvalidParam(_))
+        ));
+        assert!(canonicalize("<a>".repeat(MAX_DEPTH + 1).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_verify_enveloped() {
+        let (private_key, public_key, certificate) = test_key();
+        let xml = "<doc xmlns=\"urn:doc\">\n  <item id=\"1\">内容</item>\n</doc>\n";
+        let document = parse(xml.as_bytes()).unwrap();
+        let digest = Sm3::digest(&canonicalize_document(&document, None).unwrap());
+        let info = signed_info("", &[ALG_ENVELOPED, ALG_EXC_C14N], &digest);
+        let signature = signature_element(&info, &local_sign(&private_key, &info), Some(&certificate));
+        let close = document.root.close.unwrap();
+        let signed = format!("{}{}{}", &xml[..close], signature, &xml[close..]);
+
+        let verified = verify_enveloped(signed.as_bytes(), None).unwrap();
+        assert_eq!(verified.certificate, Some(certificate));
+        assert_eq!(verified.public_key, public_key);
+
+        // 篡改内容
+        let tampered = signed.replace("内容", "篡改");
+        assert!(matches!(verify_enveloped(tampered.as_bytes(), None), Err(Error::Crypto(_))));
+        // 指定其他公钥
+        let (_, other, _) = test_key();
+        assert!(matches!(verify_enveloped(signed.as_bytes(), Some(&other)), Err(Error::Crypto(_))));
+    }
+
+    #[test]
+    fn test_verify_detached() {
+        let (private_key, public_key, _) = test_key();
+        let info = signed_info("data.bin", &[], &Sm3::digest(b"payload"));
+        let signature = signature_element(&info, &local_sign(&private_key, &info), None);
+
+        let verified = verify_detached(signature.as_bytes(), b"payload", Some(&public_key)).unwrap();
+        assert_eq!(verified.reference_uri, "data.bin");
+        assert!(matches!(
+            verify_detached(signature.as_bytes(), b"other", Some(&public_key)),
+            Err(Error::Crypto(_))
+        ));
+        // 无证书时必须指定公钥
+        assert!(matches!(
+            verify_detached(signature.as_bytes(), b"payload", None),
+            Err(Error::InvalidParam(_))
+        ));
+        // 分离签名不能按封内签名验证
+        assert!(verify_enveloped(signature.as_bytes(), Some(&public_key)).is_err());
+    }
+}
@@ -1,109 +1,198 @@
 //! 集成测试 - 连接后台服务
+//!
+//! 默认回放 `tests/fixtures/` 中录制的服务端交互，不需要后台服务。环境变量 `SM2_COSIGN_VCR=record`
+//! 时连接后台服务并重新录制夹具，`SM2_COSIGN_VCR=live` 时直接连接后台服务、不读写夹具。
 
-use sm2_co_sign_core::{CoSignClient, ClientConfig};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use sm2_co_sign_core::transport::Transport;
+use sm2_co_sign_core::vcr::{Cassette, Mode, Recorder, Replayer};
+use sm2_co_sign_core::{CoSignClient, ClientConfig, CoSignProtocol, DigestMode};
+use std::sync::Arc;
+
+/// 录制与直连模式使用的后台服务
+const SERVER_URL: &str = "http://127.0.0.1:8080";
+
+/// 测试客户端与本次使用的用户名
+struct Fixture {
+    client: CoSignClient,
+    username: String,
+    replayer: Option<Arc<Replayer>>,
+}
+
+impl Fixture {
+    /// 连接真实服务时注册 / 登录失败（如用户名已存在）跳过测试；回放时失败说明夹具与客户端不一致
+    fn skip(&self, reason: String) {
+        assert!(self.replayer.is_none(), "{}", reason);
+        eprintln!("{}", reason);
+    }
+
+    /// 回放模式下检查夹具中的交互已全部发生
+    fn finish(self) {
+        if let Some(replayer) = self.replayer {
+            assert_eq!(replayer.remaining(), 0, "Recorded interactions left unplayed");
+        }
+    }
+}
+
+/// 夹具文件路径
+fn cassette_path(name: &str) -> String {
+    format!("{}/tests/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// 夹具 `name` 中第一个发往 `path` 的请求体
+fn recorded_body(name: &str, path: &str) -> serde_json::Value {
+    let cassette = Cassette::load(cassette_path(name)).expect("Failed to load cassette");
+    let interaction = cassette.interactions.into_iter().find(|i| i.request.path == path).expect("No recorded request");
+    interaction.request.body.expect("Recorded request has no body")
+}
+
+/// 按 `SM2_COSIGN_VCR` 创建客户端，`name` 为夹具名，`prefix` 为测试用户名前缀
+fn setup(name: &str, prefix: &str) -> Fixture {
+    let cassette = cassette_path(name);
+    let mode = Mode::from_env().expect("Invalid VCR mode");
+
+    // Reason: 夹具中的用户名固定为前缀；连接真实服务时加随机后缀避免与已有用户冲突，录制时再替换回前缀
+    let username = match mode {
+        Mode::Replay => prefix.to_string(),
+        Mode::Record | Mode::Live => format!("{}_{}", prefix, hex::encode(CoSignProtocol::generate_random(4))),
+    };
+    let mut replayer = None;
+    let transport: Option<Arc<dyn Transport>> = match mode {
+        Mode::Replay => {
+            let loaded = Arc::new(Replayer::from_file(&cassette).expect("Failed to load cassette"));
+            replayer = Some(loaded.clone());
+            Some(loaded)
+        }
+        Mode::Record => Some(Arc::new(Recorder::new(cassette).substitute(username.as_str(), prefix))),
+        Mode::Live => None,
+    };
 
-fn get_client() -> CoSignClient {
     let config = ClientConfig {
-        server_url: "http://127.0.0.1:8080".to_string(),
+        server_url: SERVER_URL.to_string(),
         timeout: 30,
         verify_tls: false,
+        transport,
+        ..ClientConfig::default()
     };
-    CoSignClient::new(config).expect("Failed to create client")
+    Fixture {
+        client: CoSignClient::new(config).expect("Failed to create client"),
+        username,
+        replayer,
+    }
 }
 
 #[tokio::test]
 async fn test_health_check() {
-    let client = get_client();
-    let result = client.health_check().await;
+    let fixture = setup("health", "health");
+    let result = fixture.client.health_check().await;
     assert!(result.is_ok());
     assert!(result.unwrap());
+    fixture.finish();
 }
 
 #[tokio::test]
 async fn test_register_and_login() {
-    let client = get_client();
-    
-    // 使用随机用户名避免冲突
-    let username = format!("test_user_{}", rand::random::<u32>());
+    let fixture = setup("register_and_login", "test_user");
+    let client = &fixture.client;
+    let username = fixture.username.as_str();
     let password = "test_password";
-    
+
     // 注册
-    let key_pair = client.register(&username, password).await;
+    let key_pair = client.register(username, password).await;
     if key_pair.is_err() {
         // 如果注册失败，可能是用户名已存在，跳过此测试
-        eprintln!("Register failed (user may exist): {:?}", key_pair.err());
+        fixture.skip(format!("Register failed (user may exist): {:?}", key_pair.err()));
         return;
     }
     let key_pair = key_pair.unwrap();
     assert!(!key_pair.d1.is_empty());
     assert!(!key_pair.public_key.is_empty());
     assert!(!key_pair.user_id.is_empty());
-    
+
     // 登录
-    let session = client.login(&username, password).await;
+    let session = client.login(username, password).await;
     assert!(session.is_ok());
     let session = session.unwrap();
     assert!(!session.token.is_empty());
-    
+
     // 登出
     let logout_result = client.logout().await;
     assert!(logout_result.is_ok());
+    fixture.finish();
 }
 
 #[tokio::test]
 async fn test_get_user_info() {
-    let client = get_client();
-    
-    // 先登录
-    let username = format!("test_user_info_{}", rand::random::<u32>());
+    let fixture = setup("user_info", "test_user_info");
+    let client = &fixture.client;
+    let username = fixture.username.as_str();
     let password = "test_password";
-    
+
     // 注册
-    let register_result = client.register(&username, password).await;
+    let register_result = client.register(username, password).await;
     if register_result.is_err() {
-        eprintln!("Register failed: {:?}", register_result.err());
+        fixture.skip(format!("Register failed: {:?}", register_result.err()));
         return;
     }
-    
+
     // 登录
-    let login_result = client.login(&username, password).await;
+    let login_result = client.login(username, password).await;
     if login_result.is_err() {
-        eprintln!("Login failed: {:?}", login_result.err());
+        fixture.skip(format!("Login failed: {:?}", login_result.err()));
         return;
     }
-    
+
     // 获取用户信息
     let user_info = client.get_user_info().await;
     assert!(user_info.is_ok());
     let user_info = user_info.unwrap();
     assert_eq!(user_info.username, username);
+    fixture.finish();
 }
 
 #[tokio::test]
 async fn test_sign() {
-    let client = get_client();
-    
-    // 先注册和登录
-    let username = format!("test_user_sign_{}", rand::random::<u32>());
+    let fixture = setup("sign", "test_user_sign");
+    let client = &fixture.client;
+    let username = fixture.username.as_str();
     let password = "test_password";
-    
-    let register_result = client.register(&username, password).await;
+
+    // 先注册和登录
+    let register_result = client.register(username, password).await;
     if register_result.is_err() {
-        eprintln!("Register failed: {:?}", register_result.err());
+        fixture.skip(format!("Register failed: {:?}", register_result.err()));
         return;
     }
-    
-    let login_result = client.login(&username, password).await;
+
+    let login_result = client.login(username, password).await;
     if login_result.is_err() {
-        eprintln!("Login failed: {:?}", login_result.err());
+        fixture.skip(format!("Login failed: {:?}", login_result.err()));
         return;
     }
-    
-    // 签名
+
+    // 签名请求：e 由协同公钥与消息确定
     let message = b"Hello, SM2 Co-Sign!";
-    let signature = client.sign(message).await;
+    let request = client.dry_run_sign(message, DigestMode::Za).await.expect("Dry-run sign failed");
+    let e = BASE64.decode(request.body["e"].as_str().expect("Missing e")).expect("Invalid e");
+
+    // 签名
+    let signature = client.sign(message, DigestMode::Za).await;
     assert!(signature.is_ok());
     let signature = signature.unwrap();
     assert_eq!(signature.r.len(), 32);
     assert!(signature.s.len() <= 32);
+
+    if fixture.replayer.is_some() {
+        // Reason: 回放时 D1、k1 每次随机生成，与录制的服务端响应不对应，签名无法验证，只核对请求与录制的一致
+        let recorded = recorded_body("sign", "/api/sign");
+        let keys = |body: &serde_json::Value| body.as_object().map(|o| o.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(keys(&request.body), keys(&recorded));
+        assert_eq!(request.body["user_id"], recorded["user_id"]);
+        assert_eq!(request.body["e"], recorded["e"]);
+    } else {
+        let public_key = client.get_public_key().await.expect("Missing public key");
+        let protocol = CoSignProtocol::new().unwrap();
+        assert!(protocol.verify_digest(&public_key, &e, &signature.r, &signature.s).unwrap());
+    }
+    fixture.finish();
 }
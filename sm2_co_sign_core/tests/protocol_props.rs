@@ -0,0 +1,223 @@
+//! 协议数学的性质测试
+//!
+//! 服务端一侧使用独立于 `simulator` 的参考实现，随机数 d1/d2/k1/k2/k3 均由 proptest 生成，
+//! 以便重构 `protocol.rs` 时及时发现计算结果的变化。
+
+use libsm::sm2::ecc::{EccCtx, Point};
+use libsm::sm2::field::FieldElem;
+use num_bigint::BigUint;
+use proptest::prelude::*;
+use sm2_co_sign_core::asn1;
+use sm2_co_sign_core::{CiphertextLayout, CoSignProtocol, DigestMode, Signature, Sm2Ciphertext, D1};
+
+/// 32 字节大端，左补零
+fn pad32(value: &BigUint) -> Vec<u8> {
+    let bytes = value.to_bytes_be();
+    let mut out = vec![0u8; 32 - bytes.len()];
+    out.extend_from_slice(&bytes);
+    out
+}
+
+/// 服务端参考实现：直接按协议公式计算，不复用 simulator
+struct Reference {
+    ecc: EccCtx,
+}
+
+impl Reference {
+    fn new() -> Self {
+        Self { ecc: EccCtx::new() }
+    }
+
+    fn n(&self) -> &BigUint {
+        self.ecc.get_n()
+    }
+
+    fn point(&self, bytes: &[u8]) -> Point {
+        let x = FieldElem::from_bytes(&bytes[..32]).unwrap();
+        let y = FieldElem::from_bytes(&bytes[32..]).unwrap();
+        self.ecc.new_point(&x, &y).unwrap()
+    }
+
+    fn bytes(&self, point: &Point) -> Vec<u8> {
+        let (x, y) = self.ecc.to_affine(point).unwrap();
+        let mut out = pad32(&BigUint::from_bytes_be(&x.to_bytes()));
+        out.extend(pad32(&BigUint::from_bytes_be(&y.to_bytes())));
+        out
+    }
+
+    fn inverse(&self, value: &BigUint) -> BigUint {
+        value.modpow(&(self.n() - BigUint::from(2u32)), self.n())
+    }
+
+    /// Pa = d2⁻¹·P1 - G
+    fn public_key(&self, d2: &BigUint, p1: &[u8]) -> Vec<u8> {
+        let d2_p1 = self.ecc.mul(&self.inverse(d2), &self.point(p1)).unwrap();
+        let neg_g = self.ecc.g_mul(&(self.n() - BigUint::from(1u32))).unwrap();
+        self.bytes(&self.ecc.add(&d2_p1, &neg_g).unwrap())
+    }
+
+    /// (x1, y1) = k3·Q1 + k2·G，r = (e + x1) mod n，s2 = d2·k3，s3 = d2·(k2 + r)
+    fn sign(&self, d2: &BigUint, k2: &BigUint, k3: &BigUint, q1: &[u8], e: &[u8]) -> (BigUint, BigUint, BigUint) {
+        let n = self.n();
+        let point = self
+            .ecc
+            .add(&self.ecc.mul(k3, &self.point(q1)).unwrap(), &self.ecc.g_mul(k2).unwrap())
+            .unwrap();
+        let x1 = BigUint::from_bytes_be(&self.bytes(&point)[..32]);
+        let r = (BigUint::from_bytes_be(e) + x1) % n;
+        let s2 = (d2 * k3) % n;
+        let s3 = (d2 * ((k2 + &r) % n)) % n;
+        (r, s2, s3)
+    }
+
+    /// T2 = d2⁻¹·T1
+    fn decrypt(&self, d2: &BigUint, t1: &[u8]) -> Vec<u8> {
+        self.bytes(&self.ecc.mul(&self.inverse(d2), &self.point(t1)).unwrap())
+    }
+}
+
+/// [1, n-1] 内的 32 字节标量
+fn scalar() -> impl Strategy<Value = Vec<u8>> {
+    any::<[u8; 32]>()
+        .prop_filter("scalar in [1, n-1]", |bytes| D1::from_slice(bytes).is_ok())
+        .prop_map(|bytes| bytes.to_vec())
+}
+
+fn big(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn prop_cosign_round_trip(
+        d1 in scalar(),
+        d2 in scalar(),
+        k1 in scalar(),
+        k2 in scalar(),
+        k3 in scalar(),
+        message in proptest::collection::vec(any::<u8>(), 0..128),
+    ) {
+        let protocol = CoSignProtocol::new().unwrap();
+        let reference = Reference::new();
+        let d2 = big(&d2);
+
+        let p1 = protocol.calculate_p1(&d1).unwrap();
+        let public_key = reference.public_key(&d2, &p1);
+
+        // 完整私钥 d = d1·d2⁻¹ - 1，且 Pa = d·G
+        let n = reference.n();
+        let d = (big(&d1) * reference.inverse(&d2) + n - BigUint::from(1u32)) % n;
+        prop_assume!(d != BigUint::from(0u32));
+        prop_assert_eq!(protocol.calculate_p1(&pad32(&d)).unwrap(), public_key.clone());
+
+        let e = protocol.calculate_message_hash(&message, &public_key, DigestMode::Za).unwrap();
+        let q1 = protocol.calculate_p1(&k1).unwrap();
+        let (r, s2, s3) = reference.sign(&d2, &big(&k2), &big(&k3), &q1, &e);
+        prop_assume!(r != BigUint::from(0u32) && s2 != BigUint::from(0u32) && s3 != BigUint::from(0u32));
+
+        let (r, s) = protocol.complete_signature(&k1, &d1, &pad32(&r), &pad32(&s2), &pad32(&s3)).unwrap();
+        prop_assume!(!protocol.is_degenerate_signature(&r, &s));
+        prop_assert_eq!(r.len(), 32);
+        prop_assert_eq!(s.len(), 32);
+        prop_assert!(protocol.verify_digest(&public_key, &e, &r, &s).unwrap());
+
+        let signature = Signature::new(r, s).to_bytes();
+        prop_assert!(CoSignProtocol::verify(&public_key, &message, &signature).unwrap());
+    }
+
+    #[test]
+    fn prop_refresh_keeps_public_key(d1 in scalar(), d2 in scalar(), factor in scalar()) {
+        let protocol = CoSignProtocol::new().unwrap();
+        let reference = Reference::new();
+        let d2 = big(&d2);
+
+        let public_key = reference.public_key(&d2, &protocol.calculate_p1(&d1).unwrap());
+        let new_d1 = protocol.refresh_d1(&d1, &factor).unwrap();
+        let new_d2 = (&d2 * big(&factor)) % reference.n();
+        let new_public_key = reference.public_key(&new_d2, &protocol.calculate_p1(&new_d1).unwrap());
+        prop_assert_eq!(new_public_key, public_key);
+    }
+
+    #[test]
+    fn prop_codecrypt_round_trip(
+        d1 in scalar(),
+        d2 in scalar(),
+        message in proptest::collection::vec(any::<u8>(), 1..256),
+    ) {
+        let protocol = CoSignProtocol::new().unwrap();
+        let reference = Reference::new();
+        let d2 = big(&d2);
+        let public_key = reference.public_key(&d2, &protocol.calculate_p1(&d1).unwrap());
+
+        let ciphertext = CoSignProtocol::encrypt(&public_key, &message).unwrap();
+        let parsed = Sm2Ciphertext::parse(&ciphertext).unwrap();
+        let t1 = protocol.decrypt_prepare(&d1, &parsed.c1).unwrap();
+        let t2 = reference.decrypt(&d2, &t1);
+        let plaintext = protocol.complete_decryption(&t2, &parsed.c1, &parsed.c3, parsed.c2).unwrap();
+        prop_assert_eq!(plaintext, message);
+    }
+
+    #[test]
+    fn prop_encrypt_decrypt_round_trip(
+        d in scalar(),
+        message in proptest::collection::vec(any::<u8>(), 1..256),
+    ) {
+        let protocol = CoSignProtocol::new().unwrap();
+        let public_key = protocol.calculate_p1(&d).unwrap();
+        let ciphertext = CoSignProtocol::encrypt(&public_key, &message).unwrap();
+        prop_assert_eq!(ciphertext.len(), 1 + 64 + 32 + message.len());
+        prop_assert_eq!(CoSignProtocol::decrypt(&d, &ciphertext).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn prop_ciphertext_encoding_round_trip(
+        d in scalar(),
+        message in proptest::collection::vec(any::<u8>(), 1..256),
+    ) {
+        let public_key = CoSignProtocol::new().unwrap().calculate_p1(&d).unwrap();
+        let ciphertext = CoSignProtocol::encrypt(&public_key, &message).unwrap();
+        let parsed = Sm2Ciphertext::parse(&ciphertext).unwrap();
+
+        prop_assert_eq!(parsed.to_bytes(CiphertextLayout::C1C3C2), ciphertext.clone());
+        let c1c2c3 = parsed.to_bytes(CiphertextLayout::C1C2C3);
+        prop_assert_eq!(Sm2Ciphertext::parse_with_layout(&c1c2c3, CiphertextLayout::C1C2C3).unwrap(), parsed.clone());
+        let der = parsed.to_der();
+        prop_assert_eq!(Sm2Ciphertext::parse(&der).unwrap(), parsed.clone());
+        prop_assert_eq!(Sm2Ciphertext::parse(&ciphertext[1..]).unwrap(), parsed);
+    }
+
+    #[test]
+    fn prop_signature_encoding_round_trip(r in scalar(), s in scalar()) {
+        let signature = Signature::new(r.clone(), s.clone());
+        let raw = signature.to_bytes();
+        let decoded = Signature::from_bytes(&raw).unwrap();
+        prop_assert_eq!((&decoded.r, &decoded.s), (&r, &s));
+
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        prop_assert_eq!(parsed.to_bytes(), raw);
+
+        let json = serde_json::to_string(&signature).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Signature>(&json).unwrap().to_bytes(), raw);
+
+        let der = asn1::signature_to_der(&raw).unwrap();
+        prop_assert_eq!(asn1::signature_from_der(&der).unwrap(), raw.to_vec());
+    }
+
+    #[test]
+    fn prop_public_key_encoding_round_trip(d in scalar()) {
+        let public_key = CoSignProtocol::new().unwrap().calculate_p1(&d).unwrap();
+        let spki = asn1::public_key_to_spki(&public_key).unwrap();
+        prop_assert_eq!(asn1::public_key_from_spki(&spki).unwrap(), public_key);
+    }
+
+    #[test]
+    fn prop_text_encoding_round_trip(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+        use sm2_co_sign_core::protocol::{base64_decode, base64_encode, base64url_decode, base64url_encode, hex_decode, hex_encode};
+
+        prop_assert_eq!(base64_decode(&base64_encode(&data)).unwrap(), data.clone());
+        prop_assert_eq!(base64url_decode(&base64url_encode(&data)).unwrap(), data.clone());
+        prop_assert_eq!(hex_decode(&hex_encode(&data).to_uppercase()).unwrap(), data);
+    }
+}
@@ -21,16 +21,123 @@ extern "C" {
 #define COSIGN_ERR_CRYPTO       -3
 #define COSIGN_ERR_NETWORK      -4
 #define COSIGN_ERR_ENCODING     -5
+#define COSIGN_ERR_BUFFER_TOO_SMALL -6
+#define COSIGN_ERR_VERIFY_FAILED    -7
+#define COSIGN_ERR_NOT_AUTHENTICATED -8
+#define COSIGN_ERR_INVALID_POINT    -9
+#define COSIGN_ERR_SESSION_EXPIRED  -10
+#define COSIGN_ERR_INTERNAL         -11
+#define COSIGN_ERR_KEY_PROTECTION   -12
+
+/* 原始拼接密文的分量顺序 */
+#define COSIGN_LAYOUT_C1C3C2    0
+#define COSIGN_LAYOUT_C1C2C3    1
+
+/* 二进制数据的文本编码（cosign_encode / cosign_decode） */
+#define COSIGN_ENCODING_HEX       0
+#define COSIGN_ENCODING_BASE64    1
+#define COSIGN_ENCODING_BASE64URL 2
+
+/*
+ * 输出缓冲区约定：每个输出缓冲区都需同时传入容量（*_cap）。
+ * 容量不足时返回 COSIGN_ERR_BUFFER_TOO_SMALL，不写入任何数据，
+ * 并通过对应的长度参数回传所需长度。
+ *
+ * 库内部的 panic 不会跨越 C 边界：返回错误码的接口返回 COSIGN_ERR_INTERNAL，
+ * 返回上下文指针的接口返回 NULL。
+ */
+
+/**
+ * 获取错误码的描述字符串
+ * @param code 错误码
+ * @return 静态字符串，调用方不得释放
+ */
+const char *cosign_strerror(int code);
+
+/*
+ * 线程模型：协议上下文内部同步，同一上下文可被多个线程并发使用；
+ * 也可通过 cosign_context_clone 为每个工作线程创建独立上下文。
+ * cosign_context_free 必须在所有线程停止使用该上下文之后调用。
+ */
 
 /* 协议上下文（不透明指针） */
 typedef struct CoSignContext CoSignContext;
 
+/* 签名结果（r、s 均左补零到 32 字节） */
+typedef struct {
+    unsigned char r[32];
+    unsigned char s[32];
+} cosign_signature_t;
+
+/* 客户端密钥对（D1 左补零到 32 字节，P1 为 x||y） */
+typedef struct {
+    unsigned char d1[32];
+    unsigned char p1[64];
+} cosign_keypair_t;
+
+/* 服务端签名响应分量（均左补零到 32 字节） */
+typedef struct {
+    unsigned char r[32];
+    unsigned char s2[32];
+    unsigned char s3[32];
+} cosign_sign_response_t;
+
+/* 密文分量（c2 指向原密文缓冲区内部，不得释放） */
+typedef struct {
+    unsigned char c1[64];
+    unsigned char c3[32];
+    const unsigned char *c2;
+    unsigned long c2_len;
+} cosign_ciphertext_parts_t;
+
 /**
  * 创建协议上下文
  * @return 协议上下文指针，失败返回 NULL
  */
 CoSignContext *cosign_context_new(void);
 
+/**
+ * 复制协议上下文
+ * 新上下文与原上下文相互独立，进行中的签名会话不会被复制
+ * @param ctx 协议上下文指针
+ * @return 新的协议上下文指针，失败返回 NULL
+ */
+CoSignContext *cosign_context_clone(const CoSignContext *ctx);
+
+/**
+ * 导出上下文状态（会话 ID 计数与 ZA 缓存）
+ * 进行中的签名会话（k1）不导出，挂起时正在进行的签名轮次恢复后需重新 cosign_sign_begin；
+ * 导出数据使用调用方提供的密钥以 SM4-GCM 加密
+ * @param ctx 协议上下文指针
+ * @param key 加密密钥（16字节）
+ * @param key_len 密钥长度
+ * @param out_blob 输出缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_context_export(const CoSignContext *ctx,
+                          const unsigned char *key,
+                          unsigned long key_len,
+                          unsigned char *out_blob,
+                          unsigned long out_cap,
+                          unsigned long *out_len);
+
+/**
+ * 导入上下文状态，创建新的协议上下文
+ * @param blob cosign_context_export 导出的数据
+ * @param blob_len 数据长度
+ * @param key 加密密钥（16字节）
+ * @param key_len 密钥长度
+ * @param out_ctx 输出新的协议上下文指针（使用完毕需调用 cosign_context_free）
+ * @return 错误码，密钥错误或数据被篡改时返回 COSIGN_ERR_CRYPTO，格式不符时返回 COSIGN_ERR_ENCODING
+ */
+int cosign_context_import(const unsigned char *blob,
+                          unsigned long blob_len,
+                          const unsigned char *key,
+                          unsigned long key_len,
+                          CoSignContext **out_ctx);
+
 /**
  * 销毁协议上下文
  * @param ctx 协议上下文指针
@@ -41,10 +148,14 @@ void cosign_context_free(CoSignContext *ctx);
  * 生成客户端私钥分量 D1
  * @param ctx 协议上下文指针
  * @param out_d1 输出缓冲区（至少32字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
-int cosign_generate_d1(CoSignContext *ctx, unsigned char *out_d1, unsigned long *out_len);
+int cosign_generate_d1(const CoSignContext *ctx,
+                       unsigned char *out_d1,
+                       unsigned long out_cap,
+                       unsigned long *out_len);
 
 /**
  * 计算 P1 = d1 * G
@@ -52,6 +163,7 @@ int cosign_generate_d1(CoSignContext *ctx, unsigned char *out_d1, unsigned long
  * @param d1 私钥分量 D1
  * @param d1_len D1 长度
  * @param out_p1 输出缓冲区（至少64字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -59,23 +171,118 @@ int cosign_calculate_p1(const CoSignContext *ctx,
                         const unsigned char *d1,
                         unsigned long d1_len,
                         unsigned char *out_p1,
+                        unsigned long out_cap,
                         unsigned long *out_len);
 
+/**
+ * 构造设备密钥证明的声明编码，宿主以设备密钥签名后随注册请求提交
+ * @param p1 新 D1 对应的 P1
+ * @param p1_len P1 长度
+ * @param subject 用户名（注册）或用户 ID（重新初始化密钥），NUL 结尾 UTF-8
+ * @param is_protected 非 0 表示 D1 只以 wrapped 形式保存
+ * @param out_statement 输出声明编码
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_attestation_statement(const unsigned char *p1,
+                                 unsigned long p1_len,
+                                 const char *subject,
+                                 int is_protected,
+                                 unsigned char *out_statement,
+                                 unsigned long out_cap,
+                                 unsigned long *out_len);
+
 /**
  * 签名预处理：生成 k1，计算 Q1 = k1 * G
  * @param ctx 协议上下文指针
  * @param out_k1 输出缓冲区（至少32字节）
+ * @param k1_cap K1 缓冲区容量
  * @param k1_len 输出长度
  * @param out_q1 输出缓冲区（至少64字节）
+ * @param q1_cap Q1 缓冲区容量
  * @param q1_len 输出长度
  * @return 错误码
  */
 int cosign_sign_prepare(const CoSignContext *ctx,
                         unsigned char *out_k1,
+                        unsigned long k1_cap,
                         unsigned long *k1_len,
                         unsigned char *out_q1,
+                        unsigned long q1_cap,
                         unsigned long *q1_len);
 
+/**
+ * 开始签名会话：生成 k1 并计算 Q1 = k1 * G
+ * k1 保存在上下文内部，不会返回给调用方
+ * @param ctx 协议上下文指针
+ * @param out_q1 输出缓冲区（至少64字节）
+ * @param q1_cap Q1 缓冲区容量
+ * @param q1_len 输出长度
+ * @param out_session 输出会话 ID
+ * @return 错误码
+ */
+int cosign_sign_begin(const CoSignContext *ctx,
+                      unsigned char *out_q1,
+                      unsigned long q1_cap,
+                      unsigned long *q1_len,
+                      uint64_t *out_session);
+
+/**
+ * 完成签名会话（会话随即失效，不能重复使用）
+ * @param ctx 协议上下文指针
+ * @param session cosign_sign_begin 返回的会话 ID
+ * @param d1 私钥分量 D1
+ * @param d1_len D1 长度
+ * @param r 签名分量 R
+ * @param r_len R 长度
+ * @param s2 签名分量 S2
+ * @param s2_len S2 长度
+ * @param s3 签名分量 S3
+ * @param s3_len S3 长度
+ * @param out_r 输出 R（至少32字节）
+ * @param out_r_cap R 缓冲区容量
+ * @param out_r_len 输出长度
+ * @param out_s 输出 S（至少32字节）
+ * @param out_s_cap S 缓冲区容量
+ * @param out_s_len 输出长度
+ * @return 错误码，会话不存在或已使用时返回 COSIGN_ERR_SESSION_EXPIRED
+ */
+int cosign_sign_finish(const CoSignContext *ctx,
+                       uint64_t session,
+                       const unsigned char *d1,
+                       unsigned long d1_len,
+                       const unsigned char *r,
+                       unsigned long r_len,
+                       const unsigned char *s2,
+                       unsigned long s2_len,
+                       const unsigned char *s3,
+                       unsigned long s3_len,
+                       unsigned char *out_r,
+                       unsigned long out_r_cap,
+                       unsigned long *out_r_len,
+                       unsigned char *out_s,
+                       unsigned long out_s_cap,
+                       unsigned long *out_s_len);
+
+/**
+ * 放弃签名会话，丢弃其中的 k1
+ * @param ctx 协议上下文指针
+ * @param session 会话 ID
+ * @return 错误码，会话不存在或已使用时返回 COSIGN_ERR_SESSION_EXPIRED
+ */
+int cosign_sign_abort(const CoSignContext *ctx, uint64_t session);
+
+/**
+ * 安全清零内存（不会被编译器优化掉）
+ * 库内部产生的 d1、k1 副本在函数返回前均已自动清零，
+ * 宿主应用可用本函数清理自己持有的敏感缓冲区
+ * @param data 待清零的缓冲区
+ * @param len 缓冲区长度
+ * @return 错误码
+ */
+int cosign_secure_zero(unsigned char *data, unsigned long len);
+
 /**
  * 计算消息哈希
  * @param ctx 协议上下文指针
@@ -84,6 +291,7 @@ int cosign_sign_prepare(const CoSignContext *ctx,
  * @param public_key 公钥（可选）
  * @param public_key_len 公钥长度
  * @param out_hash 输出缓冲区（至少32字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -93,8 +301,35 @@ int cosign_hash_message(const CoSignContext *ctx,
                         const unsigned char *public_key,
                         unsigned long public_key_len,
                         unsigned char *out_hash,
+                        unsigned long out_cap,
                         unsigned long *out_len);
 
+/**
+ * 计算带用户标识的消息哈希 e = SM3(ZA || M)
+ * 与标准 SM2 签名预处理一致，签名结果可被标准工具验证
+ * @param ctx 协议上下文指针
+ * @param message 消息数据
+ * @param message_len 消息长度
+ * @param uid 用户标识（NULL 时使用默认值 "1234567812345678"）
+ * @param uid_len 用户标识长度
+ * @param public_key 公钥（64字节 x||y 或 65字节 04||x||y）
+ * @param public_key_len 公钥长度
+ * @param out_hash 输出缓冲区（至少32字节）
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_hash_message_with_uid(const CoSignContext *ctx,
+                                 const unsigned char *message,
+                                 unsigned long message_len,
+                                 const unsigned char *uid,
+                                 unsigned long uid_len,
+                                 const unsigned char *public_key,
+                                 unsigned long public_key_len,
+                                 unsigned char *out_hash,
+                                 unsigned long out_cap,
+                                 unsigned long *out_len);
+
 /**
  * 完成签名计算
  * @param ctx 协议上下文指针
@@ -109,8 +344,10 @@ int cosign_hash_message(const CoSignContext *ctx,
  * @param s3 签名分量 S3
  * @param s3_len S3 长度
  * @param out_r 输出 R（至少32字节）
+ * @param out_r_cap R 缓冲区容量
  * @param out_r_len 输出长度
  * @param out_s 输出 S（至少32字节）
+ * @param out_s_cap S 缓冲区容量
  * @param out_s_len 输出长度
  * @return 错误码
  */
@@ -126,8 +363,10 @@ int cosign_complete_signature(const CoSignContext *ctx,
                               const unsigned char *s3,
                               unsigned long s3_len,
                               unsigned char *out_r,
+                              unsigned long out_r_cap,
                               unsigned long *out_r_len,
                               unsigned char *out_s,
+                              unsigned long out_s_cap,
                               unsigned long *out_s_len);
 
 /**
@@ -138,6 +377,7 @@ int cosign_complete_signature(const CoSignContext *ctx,
  * @param c1 密文分量 C1
  * @param c1_len C1 长度
  * @param out_t1 输出缓冲区（至少64字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -147,6 +387,7 @@ int cosign_decrypt_prepare(const CoSignContext *ctx,
                            const unsigned char *c1,
                            unsigned long c1_len,
                            unsigned char *out_t1,
+                           unsigned long out_cap,
                            unsigned long *out_len);
 
 /**
@@ -154,22 +395,28 @@ int cosign_decrypt_prepare(const CoSignContext *ctx,
  * @param ctx 协议上下文指针
  * @param t2 服务端返回的 T2
  * @param t2_len T2 长度
+ * @param c1 密文分量 C1（64字节，x||y）
+ * @param c1_len C1 长度
  * @param c3 密文分量 C3
  * @param c3_len C3 长度
  * @param c2 密文分量 C2
  * @param c2_len C2 长度
  * @param out_plaintext 输出明文缓冲区
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
 int cosign_complete_decryption(const CoSignContext *ctx,
                                const unsigned char *t2,
                                unsigned long t2_len,
+                               const unsigned char *c1,
+                               unsigned long c1_len,
                                const unsigned char *c3,
                                unsigned long c3_len,
                                const unsigned char *c2,
                                unsigned long c2_len,
                                unsigned char *out_plaintext,
+                               unsigned long out_cap,
                                unsigned long *out_len);
 
 /**
@@ -177,12 +424,14 @@ int cosign_complete_decryption(const CoSignContext *ctx,
  * @param data 输入数据
  * @param data_len 数据长度
  * @param out_hash 输出缓冲区（至少32字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
 int cosign_sm3_hash(const unsigned char *data,
                     unsigned long data_len,
                     unsigned char *out_hash,
+                    unsigned long out_cap,
                     unsigned long *out_len);
 
 /**
@@ -192,6 +441,7 @@ int cosign_sm3_hash(const unsigned char *data,
  * @param message 消息
  * @param message_len 消息长度
  * @param out_signature 输出签名缓冲区（至少64字节）
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -200,6 +450,7 @@ int cosign_sm2_sign(const unsigned char *private_key,
                     const unsigned char *message,
                     unsigned long message_len,
                     unsigned char *out_signature,
+                    unsigned long out_cap,
                     unsigned long *out_len);
 
 /**
@@ -210,7 +461,7 @@ int cosign_sm2_sign(const unsigned char *private_key,
  * @param message_len 消息长度
  * @param signature 签名（64字节）
  * @param signature_len 签名长度
- * @return COSIGN_OK 验签成功，其他值验签失败
+ * @return COSIGN_OK 验签成功，COSIGN_ERR_VERIFY_FAILED 签名不匹配，其他值为参数错误
  */
 int cosign_sm2_verify(const unsigned char *public_key,
                       unsigned long public_key_len,
@@ -226,6 +477,7 @@ int cosign_sm2_verify(const unsigned char *public_key,
  * @param message 明文
  * @param message_len 明文长度
  * @param out_ciphertext 输出密文缓冲区
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -234,6 +486,7 @@ int cosign_sm2_encrypt(const unsigned char *public_key,
                        const unsigned char *message,
                        unsigned long message_len,
                        unsigned char *out_ciphertext,
+                       unsigned long out_cap,
                        unsigned long *out_len);
 
 /**
@@ -243,6 +496,7 @@ int cosign_sm2_encrypt(const unsigned char *public_key,
  * @param ciphertext 密文
  * @param ciphertext_len 密文长度
  * @param out_plaintext 输出明文缓冲区
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
@@ -251,32 +505,481 @@ int cosign_sm2_decrypt(const unsigned char *private_key,
                        const unsigned char *ciphertext,
                        unsigned long ciphertext_len,
                        unsigned char *out_plaintext,
+                       unsigned long out_cap,
                        unsigned long *out_len);
 
+/**
+ * 按指定编码编码数据
+ * @param encoding COSIGN_ENCODING_HEX、COSIGN_ENCODING_BASE64 或 COSIGN_ENCODING_BASE64URL
+ * @param data 输入数据
+ * @param data_len 数据长度
+ * @param out_str 输出字符串缓冲区
+ * @param out_cap 输出缓冲区容量（含结尾 NUL）
+ * @param out_len 输出长度
+ * @return 错误码，编码取值无效时返回 COSIGN_ERR_INVALID_PARAM
+ */
+int cosign_encode(int encoding,
+                  const unsigned char *data,
+                  unsigned long data_len,
+                  char *out_str,
+                  unsigned long out_cap,
+                  unsigned long *out_len);
+
+/**
+ * 按指定编码解码字符串
+ * @param encoding COSIGN_ENCODING_HEX、COSIGN_ENCODING_BASE64 或 COSIGN_ENCODING_BASE64URL
+ * @param str 编码后的字符串
+ * @param out_data 输出数据缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_decode(int encoding,
+                  const char *str,
+                  unsigned char *out_data,
+                  unsigned long out_cap,
+                  unsigned long *out_len);
+
 /**
  * Base64 编码
  * @param data 输入数据
  * @param data_len 数据长度
  * @param out_str 输出字符串缓冲区
+ * @param out_cap 输出缓冲区容量（含结尾 NUL）
  * @param out_len 输出长度
  * @return 错误码
  */
 int cosign_base64_encode(const unsigned char *data,
                          unsigned long data_len,
                          char *out_str,
+                         unsigned long out_cap,
                          unsigned long *out_len);
 
 /**
  * Base64 解码
  * @param str Base64 字符串
  * @param out_data 输出数据缓冲区
+ * @param out_cap 输出缓冲区容量
  * @param out_len 输出长度
  * @return 错误码
  */
 int cosign_base64_decode(const char *str,
                          unsigned char *out_data,
+                         unsigned long out_cap,
                          unsigned long *out_len);
 
+/**
+ * Base64URL 编码（URL 安全字符集，无填充）
+ * @param data 输入数据
+ * @param data_len 数据长度
+ * @param out_str 输出字符串缓冲区
+ * @param out_cap 输出缓冲区容量（含结尾 NUL）
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_base64url_encode(const unsigned char *data,
+                            unsigned long data_len,
+                            char *out_str,
+                            unsigned long out_cap,
+                            unsigned long *out_len);
+
+/**
+ * Base64URL 解码
+ * @param str Base64URL 字符串
+ * @param out_data 输出数据缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_base64url_decode(const char *str,
+                            unsigned char *out_data,
+                            unsigned long out_cap,
+                            unsigned long *out_len);
+
+/**
+ * Hex 编码（小写）
+ * @param data 输入数据
+ * @param data_len 数据长度
+ * @param out_str 输出字符串缓冲区
+ * @param out_cap 输出缓冲区容量（含结尾 NUL）
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_hex_encode(const unsigned char *data,
+                      unsigned long data_len,
+                      char *out_str,
+                      unsigned long out_cap,
+                      unsigned long *out_len);
+
+/**
+ * Hex 解码（大小写均可）
+ * @param str Hex 字符串
+ * @param out_data 输出数据缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_hex_decode(const char *str,
+                      unsigned char *out_data,
+                      unsigned long out_cap,
+                      unsigned long *out_len);
+
+/**
+ * 原始签名 r||s 转换为 DER 编码（SEQUENCE { r INTEGER, s INTEGER }）
+ * @param signature 原始签名（64字节）
+ * @param signature_len 签名长度
+ * @param out_der 输出缓冲区（至少72字节）
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_signature_to_der(const unsigned char *signature,
+                            unsigned long signature_len,
+                            unsigned char *out_der,
+                            unsigned long out_cap,
+                            unsigned long *out_len);
+
+/**
+ * DER 编码签名转换为原始签名 r||s
+ * @param der DER 编码签名
+ * @param der_len DER 长度
+ * @param out_signature 输出缓冲区（至少64字节）
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_signature_from_der(const unsigned char *der,
+                              unsigned long der_len,
+                              unsigned char *out_signature,
+                              unsigned long out_cap,
+                              unsigned long *out_len);
+
+/**
+ * SM4-CBC 加密（PKCS#7 填充，输出长度为 16 的整数倍）
+ * @param key 密钥（16字节）
+ * @param key_len 密钥长度
+ * @param iv IV（16字节）
+ * @param iv_len IV 长度
+ * @param data 明文
+ * @param data_len 数据长度
+ * @param out_data 输出缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_sm4_cbc_encrypt(const unsigned char *key,
+                           unsigned long key_len,
+                           const unsigned char *iv,
+                           unsigned long iv_len,
+                           const unsigned char *data,
+                           unsigned long data_len,
+                           unsigned char *out_data,
+                           unsigned long out_cap,
+                           unsigned long *out_len);
+
+/**
+ * SM4-CBC 解密（去除 PKCS#7 填充）
+ * @param key 密钥（16字节）
+ * @param key_len 密钥长度
+ * @param iv IV（16字节）
+ * @param iv_len IV 长度
+ * @param data 密文
+ * @param data_len 数据长度
+ * @param out_data 输出缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_sm4_cbc_decrypt(const unsigned char *key,
+                           unsigned long key_len,
+                           const unsigned char *iv,
+                           unsigned long iv_len,
+                           const unsigned char *data,
+                           unsigned long data_len,
+                           unsigned char *out_data,
+                           unsigned long out_cap,
+                           unsigned long *out_len);
+
+/**
+ * SM4-GCM 加密，输出 密文 || 认证标签（16字节）
+ * @param key 密钥（16字节）
+ * @param key_len 密钥长度
+ * @param iv IV（12字节）
+ * @param iv_len IV 长度
+ * @param aad 附加认证数据（可为 NULL）
+ * @param aad_len AAD 长度
+ * @param data 明文
+ * @param data_len 数据长度
+ * @param out_data 输出缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_sm4_gcm_encrypt(const unsigned char *key,
+                           unsigned long key_len,
+                           const unsigned char *iv,
+                           unsigned long iv_len,
+                           const unsigned char *aad,
+                           unsigned long aad_len,
+                           const unsigned char *data,
+                           unsigned long data_len,
+                           unsigned char *out_data,
+                           unsigned long out_cap,
+                           unsigned long *out_len);
+
+/**
+ * SM4-GCM 解密，输入 密文 || 认证标签（16字节）
+ * 认证失败返回 COSIGN_ERR_CRYPTO
+ * @param key 密钥（16字节）
+ * @param key_len 密钥长度
+ * @param iv IV（12字节）
+ * @param iv_len IV 长度
+ * @param aad 附加认证数据（可为 NULL）
+ * @param aad_len AAD 长度
+ * @param data 密文（含认证标签）
+ * @param data_len 数据长度
+ * @param out_data 输出缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_sm4_gcm_decrypt(const unsigned char *key,
+                           unsigned long key_len,
+                           const unsigned char *iv,
+                           unsigned long iv_len,
+                           const unsigned char *aad,
+                           unsigned long aad_len,
+                           const unsigned char *data,
+                           unsigned long data_len,
+                           unsigned char *out_data,
+                           unsigned long out_cap,
+                           unsigned long *out_len);
+
+/**
+ * 生成客户端密钥对（D1 与 P1 = d1 * G）
+ * @param ctx 协议上下文指针
+ * @param out_keypair 输出密钥对
+ * @return 错误码
+ */
+int cosign_keypair_generate(const CoSignContext *ctx, cosign_keypair_t *out_keypair);
+
+/**
+ * 完成签名计算（结构体版本）
+ * @param ctx 协议上下文指针
+ * @param k1 随机数 K1
+ * @param k1_len K1 长度
+ * @param d1 私钥分量 D1
+ * @param d1_len D1 长度
+ * @param response 服务端签名响应分量
+ * @param out_signature 输出签名
+ * @return 错误码
+ */
+int cosign_complete_signature_ex(const CoSignContext *ctx,
+                                 const unsigned char *k1,
+                                 unsigned long k1_len,
+                                 const unsigned char *d1,
+                                 unsigned long d1_len,
+                                 const cosign_sign_response_t *response,
+                                 cosign_signature_t *out_signature);
+
+/**
+ * 完成签名会话（结构体版本），会话随即失效
+ * @param ctx 协议上下文指针
+ * @param session cosign_sign_begin 返回的会话 ID
+ * @param d1 私钥分量 D1
+ * @param d1_len D1 长度
+ * @param response 服务端签名响应分量
+ * @param out_signature 输出签名
+ * @return 错误码
+ */
+int cosign_sign_finish_ex(const CoSignContext *ctx,
+                          uint64_t session,
+                          const unsigned char *d1,
+                          unsigned long d1_len,
+                          const cosign_sign_response_t *response,
+                          cosign_signature_t *out_signature);
+
+/**
+ * 一次完成协同签名：计算 e = SM3(ZA || M)、完成签名会话并在本地验签，会话随即失效
+ * message、uid、public_key 必须与发送给服务端的 e 一致
+ * @param ctx 协议上下文指针
+ * @param session cosign_sign_begin 返回的会话 ID
+ * @param d1 私钥分量 D1
+ * @param d1_len D1 长度
+ * @param message 消息
+ * @param message_len 消息长度
+ * @param uid 用户标识（NULL 表示默认 "1234567812345678"）
+ * @param uid_len 用户标识长度
+ * @param public_key 协同公钥（64 或 65 字节）
+ * @param public_key_len 公钥长度
+ * @param response 服务端签名响应分量
+ * @param out_signature 输出签名
+ * @return 错误码，签名无法通过本地验签时返回 COSIGN_ERR_VERIFY_FAILED
+ */
+int cosign_cosign_message(const CoSignContext *ctx,
+                          uint64_t session,
+                          const unsigned char *d1,
+                          unsigned long d1_len,
+                          const unsigned char *message,
+                          unsigned long message_len,
+                          const unsigned char *uid,
+                          unsigned long uid_len,
+                          const unsigned char *public_key,
+                          unsigned long public_key_len,
+                          const cosign_sign_response_t *response,
+                          cosign_signature_t *out_signature);
+
+/**
+ * 拆分 C1C3C2 格式密文（0x04 前缀可选），或 ASN.1 DER 编码密文
+ * @param ciphertext 密文
+ * @param ciphertext_len 密文长度
+ * @param out_parts 输出密文分量（c2 指向 ciphertext 内部）
+ * @return 错误码
+ */
+int cosign_ciphertext_split(const unsigned char *ciphertext,
+                            unsigned long ciphertext_len,
+                            cosign_ciphertext_parts_t *out_parts);
+
+/**
+ * 按指定分量顺序拆分密文，DER 编码自动识别
+ * @param ciphertext 密文
+ * @param ciphertext_len 密文长度
+ * @param layout COSIGN_LAYOUT_C1C3C2 或 COSIGN_LAYOUT_C1C2C3
+ * @param out_parts 输出密文分量（c2 指向 ciphertext 内部）
+ * @return 错误码
+ */
+int cosign_ciphertext_split_ex(const unsigned char *ciphertext,
+                               unsigned long ciphertext_len,
+                               int layout,
+                               cosign_ciphertext_parts_t *out_parts);
+
+/**
+ * 完成解密计算（结构体版本）
+ * @param ctx 协议上下文指针
+ * @param t2 服务端返回的 T2
+ * @param t2_len T2 长度
+ * @param parts 密文分量
+ * @param out_plaintext 输出明文缓冲区
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_complete_decryption_ex(const CoSignContext *ctx,
+                                  const unsigned char *t2,
+                                  unsigned long t2_len,
+                                  const cosign_ciphertext_parts_t *parts,
+                                  unsigned char *out_plaintext,
+                                  unsigned long out_cap,
+                                  unsigned long *out_len);
+
+/*
+ * 平台密钥保护：宿主只保存平台密钥（Android Keystore 等）加密后的 D1（wrapped D1），
+ * 库在签名、解密时通过 unwrap 回调临时解开，用完立即清零。
+ * 回调返回 0 表示成功；非零时接口返回 COSIGN_ERR_KEY_PROTECTION。
+ * wrap 回调容量不足时应返回 COSIGN_ERR_BUFFER_TOO_SMALL 并回传所需长度，库会按该长度重试一次。
+ * 回调可能在任意调用线程上执行。
+ */
+typedef struct {
+    void *user_data;
+    int (*wrap)(void *user_data,
+                const unsigned char *d1,
+                unsigned long d1_len,
+                unsigned char *out_wrapped,
+                unsigned long out_cap,
+                unsigned long *out_len);
+    int (*unwrap)(void *user_data,
+                  const unsigned char *wrapped,
+                  unsigned long wrapped_len,
+                  unsigned char *out_d1,
+                  unsigned long out_cap,
+                  unsigned long *out_len);
+    /* 可为 NULL：回调被替换或上下文销毁时调用 */
+    void (*release)(void *user_data);
+} cosign_key_protector_t;
+
+/**
+ * 注册平台密钥保护回调，protector 为 NULL 时清除
+ * 结构体按值复制，原有回调的 release 随即被调用；cosign_context_clone 不继承保护回调
+ * @param ctx 协议上下文指针
+ * @param protector 保护回调
+ * @return 错误码，wrap 或 unwrap 为 NULL 时返回 COSIGN_ERR_NULL_PTR
+ */
+int cosign_context_set_key_protector(const CoSignContext *ctx, const cosign_key_protector_t *protector);
+
+/**
+ * 生成密钥对，D1 只以 wrapped 形式输出
+ * @param ctx 协议上下文指针
+ * @param out_wrapped 输出 wrapped D1
+ * @param wrapped_cap wrapped D1 缓冲区容量
+ * @param wrapped_len 输出 wrapped D1 长度
+ * @param out_p1 输出 P1（64 字节 x||y）
+ * @param p1_cap P1 缓冲区容量
+ * @param p1_len 输出 P1 长度
+ * @return 错误码
+ */
+int cosign_keypair_generate_wrapped(const CoSignContext *ctx,
+                                    unsigned char *out_wrapped,
+                                    unsigned long wrapped_cap,
+                                    unsigned long *wrapped_len,
+                                    unsigned char *out_p1,
+                                    unsigned long p1_cap,
+                                    unsigned long *p1_len);
+
+/**
+ * 用平台密钥加密已有的 D1（如迁移此前明文保存的 D1）
+ * @param ctx 协议上下文指针
+ * @param d1 私钥分量 D1
+ * @param d1_len D1 长度
+ * @param out_wrapped 输出 wrapped D1
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_wrap_d1(const CoSignContext *ctx,
+                   const unsigned char *d1,
+                   unsigned long d1_len,
+                   unsigned char *out_wrapped,
+                   unsigned long out_cap,
+                   unsigned long *out_len);
+
+/**
+ * 使用 wrapped D1 完成签名会话（结构体版本），会话随即失效
+ * @param ctx 协议上下文指针
+ * @param session cosign_sign_begin 返回的会话 ID
+ * @param wrapped_d1 wrapped D1
+ * @param wrapped_len wrapped D1 长度
+ * @param response 服务端签名响应分量
+ * @param out_signature 输出签名
+ * @return 错误码
+ */
+int cosign_sign_finish_wrapped(const CoSignContext *ctx,
+                               uint64_t session,
+                               const unsigned char *wrapped_d1,
+                               unsigned long wrapped_len,
+                               const cosign_sign_response_t *response,
+                               cosign_signature_t *out_signature);
+
+/**
+ * 使用 wrapped D1 进行解密预处理：计算 T1 = d1 * C1
+ * @param ctx 协议上下文指针
+ * @param wrapped_d1 wrapped D1
+ * @param wrapped_len wrapped D1 长度
+ * @param c1 密文 C1
+ * @param c1_len C1 长度
+ * @param out_t1 输出 T1
+ * @param out_cap 输出缓冲区容量
+ * @param out_len 输出长度
+ * @return 错误码
+ */
+int cosign_decrypt_prepare_wrapped(const CoSignContext *ctx,
+                                   const unsigned char *wrapped_d1,
+                                   unsigned long wrapped_len,
+                                   const unsigned char *c1,
+                                   unsigned long c1_len,
+                                   unsigned char *out_t1,
+                                   unsigned long out_cap,
+                                   unsigned long *out_len);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,93 @@
+package com.kintai.cosign
+
+import android.os.Build
+import android.security.keystore.KeyGenParameterSpec
+import android.security.keystore.KeyProperties
+import java.security.KeyStore
+import java.security.ProviderException
+import javax.crypto.Cipher
+import javax.crypto.KeyGenerator
+import javax.crypto.SecretKey
+import javax.crypto.spec.GCMParameterSpec
+
+/**
+ * 用 Android Keystore 硬件密钥保护 D1 的参考实现（需以 `android` feature 编译 libsm2_co_sign_ffi）
+ *
+ * wrapped D1 格式：IV(12) || 密文 || GCM 标签(16)。AES-256 密钥不可导出，设备支持时生成在 StrongBox 中。
+ * requireUserAuth 为 true 时，距上次生物识别或锁屏验证超过 authValiditySeconds 秒后 unwrap 会失败，
+ * FFI 接口返回 COSIGN_ERR_KEY_PROTECTION，应用应提示用户验证后重试。
+ */
+class KeystoreKeyProtector(
+    private val alias: String,
+    private val requireUserAuth: Boolean = false,
+    private val authValiditySeconds: Int = 30,
+) {
+    /** 由 native 回调：加密 D1 */
+    fun wrap(d1: ByteArray): ByteArray {
+        val cipher = Cipher.getInstance(TRANSFORMATION)
+        cipher.init(Cipher.ENCRYPT_MODE, key())
+        return cipher.iv + cipher.doFinal(d1)
+    }
+
+    /** 由 native 回调：解开 wrapped D1 */
+    fun unwrap(wrapped: ByteArray): ByteArray {
+        require(wrapped.size > IV_LEN + TAG_LEN) { "Wrapped D1 too short" }
+        val cipher = Cipher.getInstance(TRANSFORMATION)
+        cipher.init(Cipher.DECRYPT_MODE, key(), GCMParameterSpec(TAG_LEN * 8, wrapped, 0, IV_LEN))
+        return cipher.doFinal(wrapped, IV_LEN, wrapped.size - IV_LEN)
+    }
+
+    private fun key(): SecretKey {
+        val keyStore = KeyStore.getInstance(ANDROID_KEYSTORE).apply { load(null) }
+        (keyStore.getKey(alias, null) as? SecretKey)?.let { return it }
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.P) {
+            try {
+                return generate(strongBox = true)
+            } catch (e: ProviderException) {
+                // StrongBoxUnavailableException：设备没有 StrongBox，退回 TEE
+            }
+        }
+        return generate(strongBox = false)
+    }
+
+    private fun generate(strongBox: Boolean): SecretKey {
+        val spec = KeyGenParameterSpec.Builder(alias, KeyProperties.PURPOSE_ENCRYPT or KeyProperties.PURPOSE_DECRYPT)
+            .setBlockModes(KeyProperties.BLOCK_MODE_GCM)
+            .setEncryptionPaddings(KeyProperties.ENCRYPTION_PADDING_NONE)
+            .setKeySize(256)
+            .apply {
+                if (strongBox) setIsStrongBoxBacked(true)
+                if (requireUserAuth) {
+                    setUserAuthenticationRequired(true)
+                    if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.R) {
+                        setUserAuthenticationParameters(
+                            authValiditySeconds,
+                            KeyProperties.AUTH_BIOMETRIC_STRONG or KeyProperties.AUTH_DEVICE_CREDENTIAL,
+                        )
+                    } else {
+                        @Suppress("DEPRECATION")
+                        setUserAuthenticationValidityDurationSeconds(authValiditySeconds)
+                    }
+                }
+            }
+            .build()
+        val generator = KeyGenerator.getInstance(KeyProperties.KEY_ALGORITHM_AES, ANDROID_KEYSTORE)
+        generator.init(spec)
+        return generator.generateKey()
+    }
+
+    companion object {
+        private const val ANDROID_KEYSTORE = "AndroidKeyStore"
+        private const val TRANSFORMATION = "AES/GCM/NoPadding"
+        private const val IV_LEN = 12
+        private const val TAG_LEN = 16
+
+        init {
+            System.loadLibrary("sm2_co_sign_ffi")
+        }
+
+        /** 为 CoSignContext*（ctx 为其地址）注册 protector，返回 FFI 错误码 */
+        @JvmStatic
+        external fun nativeInstall(ctx: Long, protector: KeystoreKeyProtector): Int
+    }
+}
@@ -0,0 +1,132 @@
+import Foundation
+import LocalAuthentication
+import Security
+
+/// 用 Secure Enclave 保护 D1 的参考实现
+///
+/// Secure Enclave 只支持 P-256，D1 以 ECIES（X9.63 SHA-256 + AES-GCM）加密到 Secure Enclave 中的 P-256 密钥：
+/// wrap 只用公钥，不需要用户验证；unwrap 在 Secure Enclave 内解密，按 `accessFlags` 要求 Face ID / Touch ID
+/// 或设备密码。私钥不可导出，卸载应用或更改生物识别（`.biometryCurrentSet`）后 wrapped D1 永久无法解开，
+/// 需要走密钥轮换。
+///
+/// ```swift
+/// let protector = try SecureEnclaveKeyProtector(tag: "com.example.cosign.d1")
+/// var callbacks = protector.callbacks()
+/// cosign_context_set_key_protector(ctx, &callbacks)
+/// ```
+final class SecureEnclaveKeyProtector {
+    private static let algorithm = SecKeyAlgorithm.eciesEncryptionCofactorVariableIVX963SHA256AESGCM
+
+    private let tag: Data
+    private let accessFlags: SecAccessControlCreateFlags
+    private let prompt: String
+
+    init(tag: String,
+         accessFlags: SecAccessControlCreateFlags = [.privateKeyUsage, .biometryCurrentSet],
+         prompt: String = "验证身份以使用签名密钥") throws {
+        self.tag = Data(tag.utf8)
+        self.accessFlags = accessFlags
+        self.prompt = prompt
+        _ = try privateKey()
+    }
+
+    /// 加密 D1
+    func wrap(_ d1: Data) throws -> Data {
+        guard let publicKey = SecKeyCopyPublicKey(try privateKey()) else {
+            throw KeyProtectorError.keyUnavailable
+        }
+        var error: Unmanaged<CFError>?
+        guard let wrapped = SecKeyCreateEncryptedData(publicKey, Self.algorithm, d1 as CFData, &error) else {
+            throw error!.takeRetainedValue() as Error
+        }
+        return wrapped as Data
+    }
+
+    /// 解开 wrapped D1，按访问控制要求用户验证
+    func unwrap(_ wrapped: Data) throws -> Data {
+        var error: Unmanaged<CFError>?
+        guard let d1 = SecKeyCreateDecryptedData(try privateKey(), Self.algorithm, wrapped as CFData, &error) else {
+            throw error!.takeRetainedValue() as Error
+        }
+        return d1 as Data
+    }
+
+    /// 生成 FFI 回调；上下文持有本对象的强引用，直到回调被替换或上下文销毁
+    func callbacks() -> cosign_key_protector_t {
+        cosign_key_protector_t(
+            user_data: Unmanaged.passRetained(self).toOpaque(),
+            wrap: { userData, d1, d1Len, out, outCap, outLen in
+                let protector = Unmanaged<SecureEnclaveKeyProtector>.fromOpaque(userData!).takeUnretainedValue()
+                guard let wrapped = try? protector.wrap(Data(bytes: d1!, count: Int(d1Len))) else {
+                    return COSIGN_ERR_KEY_PROTECTION
+                }
+                return SecureEnclaveKeyProtector.write(wrapped, out, outCap, outLen)
+            },
+            unwrap: { userData, wrapped, wrappedLen, out, outCap, outLen in
+                let protector = Unmanaged<SecureEnclaveKeyProtector>.fromOpaque(userData!).takeUnretainedValue()
+                guard var d1 = try? protector.unwrap(Data(bytes: wrapped!, count: Int(wrappedLen))) else {
+                    return COSIGN_ERR_KEY_PROTECTION
+                }
+                // Data 的缓冲区不会自动清零，拷贝后立即覆盖
+                defer { d1.resetBytes(in: 0..<d1.count) }
+                return SecureEnclaveKeyProtector.write(d1, out, outCap, outLen)
+            },
+            release: { userData in
+                Unmanaged<SecureEnclaveKeyProtector>.fromOpaque(userData!).release()
+            }
+        )
+    }
+
+    private static func write(_ data: Data,
+                              _ out: UnsafeMutablePointer<UInt8>?,
+                              _ outCap: UInt,
+                              _ outLen: UnsafeMutablePointer<UInt>?) -> Int32 {
+        outLen!.pointee = UInt(data.count)
+        guard data.count <= Int(outCap) else {
+            return COSIGN_ERR_BUFFER_TOO_SMALL
+        }
+        data.copyBytes(to: out!, count: data.count)
+        return COSIGN_OK
+    }
+
+    /// 取得 Secure Enclave 私钥，不存在时生成
+    private func privateKey() throws -> SecKey {
+        let context = LAContext()
+        context.localizedReason = prompt
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassKey,
+            kSecAttrApplicationTag as String: tag,
+            kSecAttrKeyType as String: kSecAttrKeyTypeECSECPrimeRandom,
+            kSecReturnRef as String: true,
+            kSecUseAuthenticationContext as String: context,
+        ]
+        var item: CFTypeRef?
+        if SecItemCopyMatching(query as CFDictionary, &item) == errSecSuccess {
+            return item as! SecKey
+        }
+
+        var error: Unmanaged<CFError>?
+        guard let access = SecAccessControlCreateWithFlags(nil, kSecAttrAccessibleWhenUnlockedThisDeviceOnly,
+                                                           accessFlags, &error) else {
+            throw error!.takeRetainedValue() as Error
+        }
+        let attributes: [String: Any] = [
+            kSecAttrKeyType as String: kSecAttrKeyTypeECSECPrimeRandom,
+            kSecAttrKeySizeInBits as String: 256,
+            kSecAttrTokenID as String: kSecAttrTokenIDSecureEnclave,
+            kSecPrivateKeyAttrs as String: [
+                kSecAttrIsPermanent as String: true,
+                kSecAttrApplicationTag as String: tag,
+                kSecAttrAccessControl as String: access,
+            ],
+        ]
+        guard let key = SecKeyCreateRandomKey(attributes as CFDictionary, &error) else {
+            throw error!.takeRetainedValue() as Error
+        }
+        return key
+    }
+}
+
+enum KeyProtectorError: Error {
+    case keyUnavailable
+}
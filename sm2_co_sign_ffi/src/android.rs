@@ -0,0 +1,125 @@
+//! Android Keystore 密钥保护的 JNI 参考实现
+//!
+//! 配合 `android/KeystoreKeyProtector.kt` 使用：Kotlin 侧用 Android Keystore 中不可导出的 AES-256-GCM 密钥
+//! （设备支持时生成在 StrongBox 中）加解密 D1，本模块把它的 `wrap` / `unwrap` 方法适配为
+//! [`cosign_key_protector_t`] 回调。
+//!
+//! ```kotlin
+//! // ctx 为宿主绑定层持有的 CoSignContext* 地址
+//! val rc = KeystoreKeyProtector.nativeInstall(ctx, KeystoreKeyProtector("cosign-d1"))
+//! ```
+
+use std::ffi::{c_int, c_uchar, c_ulong, c_void};
+use std::slice;
+
+use jni::objects::{GlobalRef, JByteArray, JClass, JObject, JValue};
+use jni::sys::{jint, jlong};
+use jni::{JNIEnv, JavaVM};
+use tracing::warn;
+use zeroize::Zeroizing;
+
+use crate::key_protection::{cosign_context_set_key_protector, cosign_key_protector_t};
+use crate::{ffi_guard, write_output, CoSignContext, COSIGN_ERR_KEY_PROTECTION, COSIGN_ERR_NULL_PTR, COSIGN_OK};
+
+/// 回调的 `user_data`：Kotlin `KeystoreKeyProtector` 实例的全局引用
+struct JniProtector {
+    vm: JavaVM,
+    protector: GlobalRef,
+}
+
+impl JniProtector {
+    /// 调用 Kotlin 侧的 `wrap([B)[B` 或 `unwrap([B)[B`
+    fn call(&self, method: &str, input: &[u8]) -> jni::errors::Result<Zeroizing<Vec<u8>>> {
+        let mut env = self.vm.attach_current_thread()?;
+        let input_array = env.byte_array_from_slice(input)?;
+        let result = env
+            .call_method(self.protector.as_obj(), method, "([B)[B", &[JValue::Object(&input_array)])
+            .and_then(|value| value.l());
+        // Reason: Keystore 异常（如用户认证过期）留在线程上会使后续 JNI 调用失败，先清除再返回错误
+        if env.exception_check()? {
+            env.exception_clear()?;
+        }
+        // Reason: Java 数组由 GC 回收，时机不可控；经过 D1 明文的数组用完立即覆盖
+        env.set_byte_array_region(&input_array, 0, &vec![0i8; input.len()])?;
+
+        let output = JByteArray::from(result?);
+        let bytes = Zeroizing::new(env.convert_byte_array(&output)?);
+        env.set_byte_array_region(&output, 0, &vec![0i8; bytes.len()])?;
+        Ok(bytes)
+    }
+}
+
+unsafe extern "C" fn jni_wrap(
+    user_data: *mut c_void,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    out_wrapped: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    let protector = &*(user_data as *const JniProtector);
+    match protector.call("wrap", slice::from_raw_parts(d1, d1_len as usize)) {
+        Ok(wrapped) => write_output(&wrapped, out_wrapped, out_cap, out_len),
+        Err(e) => {
+            warn!(error = %e, "Keystore wrap failed");
+            COSIGN_ERR_KEY_PROTECTION
+        }
+    }
+}
+
+unsafe extern "C" fn jni_unwrap(
+    user_data: *mut c_void,
+    wrapped: *const c_uchar,
+    wrapped_len: c_ulong,
+    out_d1: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    let protector = &*(user_data as *const JniProtector);
+    match protector.call("unwrap", slice::from_raw_parts(wrapped, wrapped_len as usize)) {
+        Ok(d1) => write_output(&d1, out_d1, out_cap, out_len),
+        Err(e) => {
+            warn!(error = %e, "Keystore unwrap failed");
+            COSIGN_ERR_KEY_PROTECTION
+        }
+    }
+}
+
+unsafe extern "C" fn jni_release(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut JniProtector));
+}
+
+/// `KeystoreKeyProtector.nativeInstall(ctx: Long, protector: KeystoreKeyProtector): Int`
+///
+/// 为 `ctx` 注册 `protector`，返回 FFI 错误码。上下文持有 `protector` 的全局引用，
+/// 直到回调被替换或上下文销毁。
+#[no_mangle]
+pub extern "system" fn Java_com_kintai_cosign_KeystoreKeyProtector_nativeInstall(
+    env: JNIEnv,
+    _class: JClass,
+    ctx: jlong,
+    protector: JObject,
+) -> jint {
+    ffi_guard(|| {
+        if ctx == 0 || protector.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let handle = match (env.get_java_vm(), env.new_global_ref(&protector)) {
+            (Ok(vm), Ok(protector)) => Box::new(JniProtector { vm, protector }),
+            _ => return COSIGN_ERR_KEY_PROTECTION,
+        };
+        let callbacks = cosign_key_protector_t {
+            user_data: Box::into_raw(handle) as *mut c_void,
+            wrap: Some(jni_wrap),
+            unwrap: Some(jni_unwrap),
+            release: Some(jni_release),
+        };
+
+        let rc = cosign_context_set_key_protector(ctx as *const CoSignContext, &callbacks);
+        if rc != COSIGN_OK {
+            // 注册失败时上下文未接管 user_data
+            unsafe { jni_release(callbacks.user_data) };
+        }
+        rc
+    })
+}
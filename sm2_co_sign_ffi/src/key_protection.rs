@@ -0,0 +1,424 @@
+//! D1 的平台密钥保护
+//!
+//! 移动端可以把 D1 交给平台密钥库（Android Keystore 等）保护：宿主只保存平台密钥加密后的 D1
+//! （下称 wrapped D1），库在签名、解密需要 D1 时通过回调临时解开，用完立即清零，明文 D1 不经过宿主代码。
+//!
+//! 宿主通过 `cosign_context_set_key_protector` 注册 [`cosign_key_protector_t`]：
+//!
+//! - `wrap`：用平台密钥加密 D1，写入输出缓冲区；容量不足时返回 `COSIGN_ERR_BUFFER_TOO_SMALL` 并回传所需长度
+//! - `unwrap`：解开 wrapped D1，写入 32 字节输出缓冲区
+//! - `release`（可选）：保护回调被替换或上下文销毁时调用，释放 `user_data`
+//!
+//! 回调返回非零值（平台密钥不可用、用户取消生物识别等）时，接口返回 `COSIGN_ERR_KEY_PROTECTION`。
+//! 回调可能在调用 FFI 接口的任意线程上执行，需自行保证线程安全。
+//!
+//! Android 的 JNI 参考实现见 `android` feature（[`crate::android`]）与 `android/KeystoreKeyProtector.kt`。
+
+use std::ffi::{c_int, c_uchar, c_ulong, c_void};
+use std::slice;
+
+use zeroize::Zeroizing;
+
+use crate::{
+    cosign_sign_response_t, cosign_signature_t, error_code, ffi_guard, fill_signature, write_output, CoSignContext,
+    COSIGN_ERR_BUFFER_TOO_SMALL, COSIGN_ERR_KEY_PROTECTION, COSIGN_ERR_NULL_PTR, COSIGN_ERR_SESSION_EXPIRED, COSIGN_OK,
+};
+
+/// wrapped D1 的初始输出容量，回调报告容量不足时按所需长度重试一次
+const WRAPPED_INITIAL_CAP: usize = 256;
+
+/// 用平台密钥加密 D1
+#[allow(non_camel_case_types)]
+pub type cosign_wrap_fn = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        d1: *const c_uchar,
+        d1_len: c_ulong,
+        out_wrapped: *mut c_uchar,
+        out_cap: c_ulong,
+        out_len: *mut c_ulong,
+    ) -> c_int,
+>;
+
+/// 解开 wrapped D1，输出 D1（至多 32 字节）
+#[allow(non_camel_case_types)]
+pub type cosign_unwrap_fn = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        wrapped: *const c_uchar,
+        wrapped_len: c_ulong,
+        out_d1: *mut c_uchar,
+        out_cap: c_ulong,
+        out_len: *mut c_ulong,
+    ) -> c_int,
+>;
+
+/// 释放 `user_data`
+#[allow(non_camel_case_types)]
+pub type cosign_release_fn = Option<unsafe extern "C" fn(user_data: *mut c_void)>;
+
+/// 平台密钥保护回调
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cosign_key_protector_t {
+    /// 原样传给各回调的宿主数据
+    pub user_data: *mut c_void,
+    pub wrap: cosign_wrap_fn,
+    pub unwrap: cosign_unwrap_fn,
+    /// 可为 NULL
+    pub release: cosign_release_fn,
+}
+
+/// 上下文持有的保护回调，丢弃时调用 `release`
+pub(crate) struct KeyProtector {
+    user_data: *mut c_void,
+    wrap: unsafe extern "C" fn(*mut c_void, *const c_uchar, c_ulong, *mut c_uchar, c_ulong, *mut c_ulong) -> c_int,
+    unwrap: unsafe extern "C" fn(*mut c_void, *const c_uchar, c_ulong, *mut c_uchar, c_ulong, *mut c_ulong) -> c_int,
+    release: cosign_release_fn,
+}
+
+// Reason: user_data 只原样传回宿主回调，回调的线程安全由宿主保证（见模块文档）
+unsafe impl Send for KeyProtector {}
+unsafe impl Sync for KeyProtector {}
+
+impl Drop for KeyProtector {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self.user_data) };
+        }
+    }
+}
+
+impl KeyProtector {
+    /// 用平台密钥加密 D1
+    pub(crate) fn wrap(&self, d1: &[u8]) -> Result<Vec<u8>, c_int> {
+        let mut wrapped = vec![0u8; WRAPPED_INITIAL_CAP];
+        let mut len: c_ulong = 0;
+        let mut rc = unsafe {
+            (self.wrap)(self.user_data, d1.as_ptr(), d1.len() as c_ulong, wrapped.as_mut_ptr(), wrapped.len() as c_ulong, &mut len)
+        };
+        if rc == COSIGN_ERR_BUFFER_TOO_SMALL && len as usize > wrapped.len() {
+            wrapped.resize(len as usize, 0);
+            rc = unsafe {
+                (self.wrap)(self.user_data, d1.as_ptr(), d1.len() as c_ulong, wrapped.as_mut_ptr(), wrapped.len() as c_ulong, &mut len)
+            };
+        }
+        if rc != COSIGN_OK || len as usize > wrapped.len() {
+            return Err(COSIGN_ERR_KEY_PROTECTION);
+        }
+        wrapped.truncate(len as usize);
+        Ok(wrapped)
+    }
+
+    /// 解开 wrapped D1，返回左补零到 32 字节的 D1
+    pub(crate) fn unwrap(&self, wrapped: &[u8]) -> Result<Zeroizing<[u8; 32]>, c_int> {
+        let mut d1 = Zeroizing::new([0u8; 32]);
+        let mut len: c_ulong = 0;
+        let rc = unsafe {
+            (self.unwrap)(self.user_data, wrapped.as_ptr(), wrapped.len() as c_ulong, d1.as_mut_ptr(), d1.len() as c_ulong, &mut len)
+        };
+        if rc != COSIGN_OK || len == 0 || len as usize > d1.len() {
+            return Err(COSIGN_ERR_KEY_PROTECTION);
+        }
+        let len = len as usize;
+        d1.copy_within(..len, 32 - len);
+        d1[..32 - len].fill(0);
+        Ok(d1)
+    }
+}
+
+impl CoSignContext {
+    /// 用已注册的保护回调解开 wrapped D1，未注册时返回 `COSIGN_ERR_KEY_PROTECTION`
+    fn unwrap_d1(&self, wrapped: &[u8]) -> Result<Zeroizing<[u8; 32]>, c_int> {
+        match self.protector().as_ref() {
+            Some(protector) => protector.unwrap(wrapped),
+            None => Err(COSIGN_ERR_KEY_PROTECTION),
+        }
+    }
+
+    fn wrap_d1(&self, d1: &[u8]) -> Result<Vec<u8>, c_int> {
+        match self.protector().as_ref() {
+            Some(protector) => protector.wrap(d1),
+            None => Err(COSIGN_ERR_KEY_PROTECTION),
+        }
+    }
+}
+
+/// 注册平台密钥保护回调，`protector` 为 NULL 时清除
+///
+/// 回调结构体按值复制；原有回调的 `release` 随即被调用。`wrap` 或 `unwrap` 为 NULL 时返回 `COSIGN_ERR_NULL_PTR`。
+/// `cosign_context_clone` 创建的上下文不继承保护回调。
+#[no_mangle]
+pub extern "C" fn cosign_context_set_key_protector(
+    ctx: *const CoSignContext,
+    protector: *const cosign_key_protector_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let ctx = unsafe { &*ctx };
+
+        let protector = if protector.is_null() {
+            None
+        } else {
+            let protector = unsafe { &*protector };
+            let (Some(wrap), Some(unwrap)) = (protector.wrap, protector.unwrap) else {
+                return COSIGN_ERR_NULL_PTR;
+            };
+            Some(KeyProtector {
+                user_data: protector.user_data,
+                wrap,
+                unwrap,
+                release: protector.release,
+            })
+        };
+        // Reason: 先换出旧回调再释放，release 回调中重入上下文接口时不会死锁
+        let previous = std::mem::replace(&mut *ctx.protector(), protector);
+        drop(previous);
+        COSIGN_OK
+    })
+}
+
+/// 生成密钥对，D1 只以 wrapped 形式输出
+///
+/// D1 在库内生成、计算 P1 并经 `wrap` 回调加密后立即清零；`out_p1` 为 64 字节 x||y。
+#[no_mangle]
+pub extern "C" fn cosign_keypair_generate_wrapped(
+    ctx: *const CoSignContext,
+    out_wrapped: *mut c_uchar,
+    wrapped_cap: c_ulong,
+    wrapped_len: *mut c_ulong,
+    out_p1: *mut c_uchar,
+    p1_cap: c_ulong,
+    p1_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || out_wrapped.is_null() || wrapped_len.is_null() || out_p1.is_null() || p1_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let ctx = unsafe { &*ctx };
+
+        let d1 = match ctx.protocol.generate_d1() {
+            Ok(d1) => d1,
+            Err(e) => return error_code(&e),
+        };
+        let p1 = match ctx.protocol.calculate_p1(&d1) {
+            Ok(p1) => p1,
+            Err(e) => return error_code(&e),
+        };
+        let wrapped = match ctx.wrap_d1(&d1) {
+            Ok(wrapped) => wrapped,
+            Err(code) => return code,
+        };
+
+        unsafe {
+            // 先检查两个缓冲区容量，避免只写入一半结果
+            *wrapped_len = wrapped.len() as c_ulong;
+            *p1_len = p1.len() as c_ulong;
+            if wrapped.len() > wrapped_cap as usize || p1.len() > p1_cap as usize {
+                return COSIGN_ERR_BUFFER_TOO_SMALL;
+            }
+            write_output(&wrapped, out_wrapped, wrapped_cap, wrapped_len);
+            write_output(&p1, out_p1, p1_cap, p1_len)
+        }
+    })
+}
+
+/// 用平台密钥加密已有的 D1（如迁移此前明文保存的 D1）
+#[no_mangle]
+pub extern "C" fn cosign_wrap_d1(
+    ctx: *const CoSignContext,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    out_wrapped: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || out_wrapped.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let ctx = unsafe { &*ctx };
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+
+        match ctx.wrap_d1(d1_slice) {
+            Ok(wrapped) => unsafe { write_output(&wrapped, out_wrapped, out_cap, out_len) },
+            Err(code) => code,
+        }
+    })
+}
+
+/// 使用 wrapped D1 完成签名会话（结构体版本），会话随即失效
+///
+/// D1 经 `unwrap` 回调解开，仅在本次计算期间存在于库内。
+#[no_mangle]
+pub extern "C" fn cosign_sign_finish_wrapped(
+    ctx: *const CoSignContext,
+    session: u64,
+    wrapped_d1: *const c_uchar,
+    wrapped_len: c_ulong,
+    response: *const cosign_sign_response_t,
+    out_signature: *mut cosign_signature_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || wrapped_d1.is_null() || response.is_null() || out_signature.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let ctx = unsafe { &*ctx };
+
+        let k1 = match ctx.sessions().remove(&session) {
+            Some(k1) => k1,
+            None => return COSIGN_ERR_SESSION_EXPIRED,
+        };
+
+        let wrapped = unsafe { slice::from_raw_parts(wrapped_d1, wrapped_len as usize) };
+        let d1 = match ctx.unwrap_d1(wrapped) {
+            Ok(d1) => d1,
+            Err(code) => return code,
+        };
+        let response = unsafe { &*response };
+
+        match ctx.protocol.complete_signature(&k1, &d1[..], &response.r, &response.s2, &response.s3) {
+            Ok((r, s)) => fill_signature(&r, &s, unsafe { &mut *out_signature }),
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 使用 wrapped D1 进行解密预处理：计算 T1 = d1 * C1
+#[no_mangle]
+pub extern "C" fn cosign_decrypt_prepare_wrapped(
+    ctx: *const CoSignContext,
+    wrapped_d1: *const c_uchar,
+    wrapped_len: c_ulong,
+    c1: *const c_uchar,
+    c1_len: c_ulong,
+    out_t1: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || wrapped_d1.is_null() || c1.is_null() || out_t1.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let ctx = unsafe { &*ctx };
+
+        let wrapped = unsafe { slice::from_raw_parts(wrapped_d1, wrapped_len as usize) };
+        let d1 = match ctx.unwrap_d1(wrapped) {
+            Ok(d1) => d1,
+            Err(code) => return code,
+        };
+        let c1_slice = unsafe { slice::from_raw_parts(c1, c1_len as usize) };
+
+        match ctx.protocol.decrypt_prepare(&d1[..], c1_slice) {
+            Ok(t1) => unsafe { write_output(&t1, out_t1, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cosign_context_free, cosign_context_new, cosign_sign_begin};
+    use sm2_co_sign_core::simulator::D2Simulator;
+    use sm2_co_sign_core::CoSignProtocol;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 测试用保护回调：与固定字节异或并加 4 字节标记
+    const MARK: &[u8; 4] = b"WRAP";
+
+    unsafe extern "C" fn test_wrap(
+        _user_data: *mut c_void,
+        d1: *const c_uchar,
+        d1_len: c_ulong,
+        out: *mut c_uchar,
+        out_cap: c_ulong,
+        out_len: *mut c_ulong,
+    ) -> c_int {
+        let d1 = slice::from_raw_parts(d1, d1_len as usize);
+        let mut wrapped = MARK.to_vec();
+        wrapped.extend(d1.iter().map(|b| b ^ 0x5a));
+        write_output(&wrapped, out, out_cap, out_len)
+    }
+
+    unsafe extern "C" fn test_unwrap(
+        _user_data: *mut c_void,
+        wrapped: *const c_uchar,
+        wrapped_len: c_ulong,
+        out: *mut c_uchar,
+        out_cap: c_ulong,
+        out_len: *mut c_ulong,
+    ) -> c_int {
+        let wrapped = slice::from_raw_parts(wrapped, wrapped_len as usize);
+        match wrapped.strip_prefix(MARK) {
+            Some(body) => write_output(&body.iter().map(|b| b ^ 0x5a).collect::<Vec<_>>(), out, out_cap, out_len),
+            None => -1,
+        }
+    }
+
+    unsafe extern "C" fn test_release(user_data: *mut c_void) {
+        (*(user_data as *const AtomicUsize)).fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_wrapped_sign_and_decrypt() {
+        let released = AtomicUsize::new(0);
+        let ctx = cosign_context_new();
+        let protector = cosign_key_protector_t {
+            user_data: &released as *const AtomicUsize as *mut c_void,
+            wrap: Some(test_wrap),
+            unwrap: Some(test_unwrap),
+            release: Some(test_release),
+        };
+
+        // 未注册回调时不能使用 wrapped D1
+        let (mut wrapped, mut wrapped_len) = ([0u8; 64], 0 as c_ulong);
+        let (mut p1, mut p1_len) = ([0u8; 64], 0 as c_ulong);
+        let keypair = |wrapped: &mut [u8], wrapped_len: &mut c_ulong, p1: &mut [u8], p1_len: &mut c_ulong| {
+            cosign_keypair_generate_wrapped(ctx, wrapped.as_mut_ptr(), 64, wrapped_len, p1.as_mut_ptr(), 64, p1_len)
+        };
+        assert_eq!(keypair(&mut wrapped, &mut wrapped_len, &mut p1, &mut p1_len), COSIGN_ERR_KEY_PROTECTION);
+
+        assert_eq!(cosign_context_set_key_protector(ctx, &protector), COSIGN_OK);
+        assert_eq!(keypair(&mut wrapped, &mut wrapped_len, &mut p1, &mut p1_len), COSIGN_OK);
+        assert_eq!(&wrapped[..4], MARK);
+        let wrapped = &wrapped[..wrapped_len as usize];
+
+        // 服务端按 P1 生成 D2，用 wrapped D1 完成签名
+        let simulator = D2Simulator::new();
+        let key = simulator.generate_key(&p1).unwrap();
+        let e = CoSignProtocol::sm3_hash(b"hello");
+        let (mut q1, mut q1_len, mut session) = ([0u8; 64], 0 as c_ulong, 0u64);
+        assert_eq!(cosign_sign_begin(ctx, q1.as_mut_ptr(), 64, &mut q1_len, &mut session), COSIGN_OK);
+        let d2 = simulator.sign(&key.d2, &q1, &e).unwrap();
+        let mut response = cosign_sign_response_t { r: [0; 32], s2: [0; 32], s3: [0; 32] };
+        response.r.copy_from_slice(&d2.r);
+        response.s2.copy_from_slice(&d2.s2);
+        response.s3.copy_from_slice(&d2.s3);
+        let mut signature = cosign_signature_t { r: [0; 32], s: [0; 32] };
+        let rc = cosign_sign_finish_wrapped(ctx, session, wrapped.as_ptr(), wrapped.len() as c_ulong, &response, &mut signature);
+        assert_eq!(rc, COSIGN_OK);
+        let protocol = CoSignProtocol::new().unwrap();
+        assert!(protocol.verify_digest(&key.public_key, &e, &signature.r, &signature.s).unwrap());
+
+        // wrapped D1 被篡改时解不开
+        let mut tampered = wrapped.to_vec();
+        tampered[0] ^= 1;
+        let (mut t1, mut t1_len) = ([0u8; 64], 0 as c_ulong);
+        let c1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let prepare = |wrapped: &[u8], t1: &mut [u8], t1_len: &mut c_ulong| {
+            cosign_decrypt_prepare_wrapped(ctx, wrapped.as_ptr(), wrapped.len() as c_ulong, c1.as_ptr(), 64, t1.as_mut_ptr(), 64, t1_len)
+        };
+        assert_eq!(prepare(&tampered, &mut t1, &mut t1_len), COSIGN_ERR_KEY_PROTECTION);
+        assert_eq!(prepare(wrapped, &mut t1, &mut t1_len), COSIGN_OK);
+        assert_eq!(t1_len, 64);
+
+        // 替换回调与销毁上下文各释放一次
+        assert_eq!(cosign_context_set_key_protector(ctx, &protector), COSIGN_OK);
+        assert_eq!(released.load(Ordering::SeqCst), 1);
+        cosign_context_free(ctx);
+        assert_eq!(released.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,12 +1,32 @@
 //! SM2 协同签名 FFI 绑定
 //!
 //! 提供 C ABI 兼容的接口，供其他语言调用
+//!
+//! # 线程模型
+//!
+//! `CoSignContext` 内部同步：所有接口只通过共享引用访问上下文，
+//! 签名会话表由互斥锁保护，因此同一个上下文可以被多个线程并发使用。
+//! 需要隔离签名会话的宿主（例如每个工作线程一个上下文）可使用
+//! `cosign_context_clone` 创建独立副本；`cosign_context_free` 必须在
+//! 所有线程停止使用该上下文之后调用。
 
+use std::collections::HashMap;
 use std::ffi::{c_char, c_int, c_uchar, c_ulong, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
 
-use sm2_co_sign_core::CoSignProtocol;
+use sm2_co_sign_core::attestation::AttestationStatement;
+use sm2_co_sign_core::{asn1, protocol, sm4, CiphertextLayout, CoSignProtocol, DigestMode, Encoding, Error, Sm2Ciphertext};
+use zeroize::{Zeroize, Zeroizing};
+
+#[cfg(feature = "android")]
+pub mod android;
+pub mod key_protection;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
 
 /// 错误码定义
 pub const COSIGN_OK: c_int = 0;
@@ -15,58 +35,363 @@ pub const COSIGN_ERR_INVALID_PARAM: c_int = -2;
 pub const COSIGN_ERR_CRYPTO: c_int = -3;
 pub const COSIGN_ERR_NETWORK: c_int = -4;
 pub const COSIGN_ERR_ENCODING: c_int = -5;
+pub const COSIGN_ERR_BUFFER_TOO_SMALL: c_int = -6;
+pub const COSIGN_ERR_VERIFY_FAILED: c_int = -7;
+pub const COSIGN_ERR_NOT_AUTHENTICATED: c_int = -8;
+pub const COSIGN_ERR_INVALID_POINT: c_int = -9;
+pub const COSIGN_ERR_SESSION_EXPIRED: c_int = -10;
+pub const COSIGN_ERR_INTERNAL: c_int = -11;
+pub const COSIGN_ERR_KEY_PROTECTION: c_int = -12;
+
+// 消息预处理方式（`cosign_hash_message_ex` 的 `mode` 参数）
+/// e = SM3(M)
+pub const COSIGN_DIGEST_SM3: c_int = 0;
+/// e = SM3(ZA || M)，默认用户标识
+pub const COSIGN_DIGEST_ZA: c_int = 1;
+/// 输入即为外部计算好的 32 字节 e
+pub const COSIGN_DIGEST_PREHASHED: c_int = 2;
+
+/// 将 FFI 预处理方式常量映射为核心库枚举
+fn digest_mode(mode: c_int) -> Option<DigestMode> {
+    match mode {
+        COSIGN_DIGEST_SM3 => Some(DigestMode::Sm3),
+        COSIGN_DIGEST_ZA => Some(DigestMode::Za),
+        COSIGN_DIGEST_PREHASHED => Some(DigestMode::Prehashed),
+        _ => None,
+    }
+}
+
+// 原始拼接密文的分量顺序（`cosign_ciphertext_split_ex` 的 `layout` 参数）
+/// C1 || C3 || C2
+pub const COSIGN_LAYOUT_C1C3C2: c_int = 0;
+/// C1 || C2 || C3
+pub const COSIGN_LAYOUT_C1C2C3: c_int = 1;
+
+/// 将 FFI 密文顺序常量映射为核心库枚举
+fn ciphertext_layout(layout: c_int) -> Option<CiphertextLayout> {
+    match layout {
+        COSIGN_LAYOUT_C1C3C2 => Some(CiphertextLayout::C1C3C2),
+        COSIGN_LAYOUT_C1C2C3 => Some(CiphertextLayout::C1C2C3),
+        _ => None,
+    }
+}
+
+// 二进制数据的文本编码（`cosign_encode` / `cosign_decode` 的 `encoding` 参数）
+/// 十六进制（编码为小写，解码大小写均可）
+pub const COSIGN_ENCODING_HEX: c_int = 0;
+/// 标准 Base64（带填充）
+pub const COSIGN_ENCODING_BASE64: c_int = 1;
+/// URL 安全字符集的 Base64（无填充）
+pub const COSIGN_ENCODING_BASE64URL: c_int = 2;
+
+/// 将 FFI 编码常量映射为核心库枚举（只支持文本编码）
+fn text_encoding(encoding: c_int) -> Option<Encoding> {
+    match encoding {
+        COSIGN_ENCODING_HEX => Some(Encoding::Hex),
+        COSIGN_ENCODING_BASE64 => Some(Encoding::Base64),
+        COSIGN_ENCODING_BASE64URL => Some(Encoding::Base64Url),
+        _ => None,
+    }
+}
+
+/// 将核心库错误映射为 FFI 错误码
+fn error_code(err: &Error) -> c_int {
+    match err {
+        Error::InvalidPoint(_) => COSIGN_ERR_INVALID_POINT,
+        Error::InvalidParam(_) | Error::InvalidState(_) | Error::InvalidServerResponse { .. } => {
+            COSIGN_ERR_INVALID_PARAM
+        }
+        Error::Encoding(_) => COSIGN_ERR_ENCODING,
+        Error::Network(_) | Error::Transport { .. } | Error::Http { .. } | Error::Api { .. } => {
+            COSIGN_ERR_NETWORK
+        }
+        Error::NotAuthenticated => COSIGN_ERR_NOT_AUTHENTICATED,
+        Error::Crypto(_) | Error::Io(_) => COSIGN_ERR_CRYPTO,
+    }
+}
+
+/// 返回错误码对应的描述字符串
+///
+/// 返回的指针指向静态字符串，调用方不得释放或修改。
+#[no_mangle]
+pub extern "C" fn cosign_strerror(code: c_int) -> *const c_char {
+    let msg: &'static [u8] = match code {
+        COSIGN_OK => b"Success\0",
+        COSIGN_ERR_NULL_PTR => b"Null pointer argument\0",
+        COSIGN_ERR_INVALID_PARAM => b"Invalid parameter\0",
+        COSIGN_ERR_CRYPTO => b"Cryptographic operation failed\0",
+        COSIGN_ERR_NETWORK => b"Network error\0",
+        COSIGN_ERR_ENCODING => b"Encoding/decoding error\0",
+        COSIGN_ERR_BUFFER_TOO_SMALL => b"Output buffer too small\0",
+        COSIGN_ERR_VERIFY_FAILED => b"Signature verification failed\0",
+        COSIGN_ERR_NOT_AUTHENTICATED => b"Not authenticated\0",
+        COSIGN_ERR_INVALID_POINT => b"Invalid elliptic curve point\0",
+        COSIGN_ERR_SESSION_EXPIRED => b"Session expired or not found\0",
+        COSIGN_ERR_INTERNAL => b"Internal error (panic caught at FFI boundary)\0",
+        COSIGN_ERR_KEY_PROTECTION => b"Key protection callback failed\0",
+        _ => b"Unknown error\0",
+    };
+    msg.as_ptr() as *const c_char
+}
+
+/// 执行 FFI 接口主体并捕获 panic
+///
+/// panic 跨越 `extern "C"` 边界展开属于未定义行为，
+/// 因此所有入口都经由此函数执行，panic 统一转换为 `COSIGN_ERR_INTERNAL`。
+fn ffi_guard<F: FnOnce() -> c_int>(f: F) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(COSIGN_ERR_INTERNAL)
+}
+
+/// 将结果写入调用方提供的输出缓冲区
+///
+/// 容量不足时不写入任何数据，返回 `COSIGN_ERR_BUFFER_TOO_SMALL`，
+/// 并通过 `out_len` 回传所需长度，调用方可据此重新分配缓冲区。
+///
+/// # Safety
+/// `out` 必须指向至少 `capacity` 字节的可写内存，`out_len` 必须可写。
+unsafe fn write_output(data: &[u8], out: *mut c_uchar, capacity: c_ulong, out_len: *mut c_ulong) -> c_int {
+    *out_len = data.len() as c_ulong;
+    if data.len() > capacity as usize {
+        return COSIGN_ERR_BUFFER_TOO_SMALL;
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    COSIGN_OK
+}
 
 /// 协议上下文
 pub struct CoSignContext {
     protocol: CoSignProtocol,
+    /// 进行中的签名会话：会话 ID -> k1
+    /// Reason: k1 只保存在库内部，调用方仅持有不透明的会话 ID；
+    /// 会话移除或上下文销毁时 k1 自动清零
+    sign_sessions: Mutex<HashMap<u64, Zeroizing<Vec<u8>>>>,
+    /// 下一个会话 ID
+    next_session_id: AtomicU64,
+    /// 平台密钥保护回调（见 [`key_protection`]）
+    key_protector: Mutex<Option<key_protection::KeyProtector>>,
+}
+
+impl CoSignContext {
+    fn new(protocol: CoSignProtocol) -> Self {
+        Self {
+            protocol,
+            sign_sessions: Mutex::new(HashMap::new()),
+            next_session_id: AtomicU64::new(1),
+            key_protector: Mutex::new(None),
+        }
+    }
+
+    /// 获取签名会话表
+    ///
+    /// 持锁期间发生的 panic 已在 FFI 边界被捕获，会话表本身仍然一致，
+    /// 因此忽略锁中毒，避免上下文在一次 panic 后永久不可用。
+    fn sessions(&self) -> MutexGuard<'_, HashMap<u64, Zeroizing<Vec<u8>>>> {
+        self.sign_sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 获取平台密钥保护回调，同样忽略锁中毒
+    fn protector(&self) -> MutexGuard<'_, Option<key_protection::KeyProtector>> {
+        self.key_protector.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 /// 创建协议上下文
 #[no_mangle]
 pub extern "C" fn cosign_context_new() -> *mut CoSignContext {
-    match CoSignProtocol::new() {
-        Ok(protocol) => {
-            let ctx = Box::new(CoSignContext { protocol });
-            Box::into_raw(ctx)
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        match CoSignProtocol::new() {
+            Ok(protocol) => {
+                let ctx = Box::new(CoSignContext::new(protocol));
+                Box::into_raw(ctx)
+            }
+            Err(_) => ptr::null_mut(),
         }
-        Err(_) => ptr::null_mut(),
-    }
+    }))
+    .unwrap_or(ptr::null_mut())
+}
+
+/// 复制协议上下文
+///
+/// 新上下文与原上下文相互独立，可交给其他线程使用。
+/// 进行中的签名会话不会被复制（避免同一个 k1 出现在两个上下文中），
+/// 平台密钥保护回调也不会被复制（`user_data` 的所有权只属于注册它的上下文）。
+#[no_mangle]
+pub extern "C" fn cosign_context_clone(ctx: *const CoSignContext) -> *mut CoSignContext {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        if ctx.is_null() {
+            return ptr::null_mut();
+        }
+        cosign_context_new()
+    }))
+    .unwrap_or(ptr::null_mut())
 }
 
 /// 销毁协议上下文
 #[no_mangle]
 pub extern "C" fn cosign_context_free(ctx: *mut CoSignContext) {
-    if !ctx.is_null() {
-        unsafe {
-            drop(Box::from_raw(ctx));
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        if !ctx.is_null() {
+            unsafe {
+                drop(Box::from_raw(ctx));
+            }
+        }
+    }));
+}
+
+/// 导出数据魔数
+const CONTEXT_BLOB_MAGIC: &[u8; 4] = b"CSCX";
+/// 导出数据格式版本
+const CONTEXT_BLOB_VERSION: u8 = 1;
+
+impl CoSignContext {
+    /// 序列化上下文状态：next_session_id(8) [|| uid_len(2) || uid || 公钥(64)]
+    ///
+    /// 可选部分为 ZA 缓存的输入，未计算过 ZA 时省略。
+    /// Reason: k1 只能使用一次，而导出数据可被复制、重复导入，库无法在新进程中识别已用过的 k1，
+    /// 因此进行中的签名会话不导出，恢复后由调用方重新开始签名。
+    fn export_state(&self) -> Zeroizing<Vec<u8>> {
+        let mut state = Zeroizing::new(self.next_session_id.load(Ordering::Relaxed).to_be_bytes().to_vec());
+        if let Some((uid, public_key)) = self.protocol.cached_za_input() {
+            // Reason: ZA 计算时已限制 uid 的比特长度不超过 u16::MAX，字节长度必然可用 2 字节表示
+            state.extend_from_slice(&(uid.len() as u16).to_be_bytes());
+            state.extend_from_slice(&uid);
+            state.extend_from_slice(&public_key);
+        }
+        state
+    }
+
+    /// 从序列化数据恢复上下文状态，ZA 缓存按导出的输入重新计算
+    fn import_state(&self, state: &[u8]) -> Option<()> {
+        let (next_id, rest) = state.split_first_chunk::<8>()?;
+        let next_id = u64::from_be_bytes(*next_id);
+        // Reason: 会话 ID 从 1 开始，0 只会来自构造的数据
+        if next_id == 0 {
+            return None;
+        }
+        if !rest.is_empty() {
+            let (uid_len, rest) = rest.split_first_chunk::<2>()?;
+            let uid_len = u16::from_be_bytes(*uid_len) as usize;
+            if rest.len() != uid_len + 64 {
+                return None;
+            }
+            let (uid, public_key) = rest.split_at(uid_len);
+            self.protocol.za(uid, public_key).ok()?;
         }
+        self.next_session_id.store(next_id, Ordering::Relaxed);
+        Some(())
     }
 }
 
+/// 导出上下文状态
+///
+/// 导出内容为会话 ID 计数与 ZA 缓存（用户标识与公钥），恢复后 `COSIGN_DIGEST_ZA` 哈希无需重新计算 ZA。
+/// 导出数据使用调用方提供的 16 字节密钥以 SM4-GCM 加密：`"CSCX" || 版本(1) || IV(12) || 密文 || 标签(16)`。
+/// 进行中的签名会话（k1）不导出，挂起时正在进行的签名轮次须重新开始：恢复后挂起前取得的会话 ID 返回
+/// `COSIGN_ERR_SESSION_EXPIRED`，需重新 `cosign_sign_begin`；会话 ID 计数随导出保留，旧会话 ID 不会与恢复后的新会话冲突。
+#[no_mangle]
+pub extern "C" fn cosign_context_export(
+    ctx: *const CoSignContext,
+    key: *const c_uchar,
+    key_len: c_ulong,
+    out_blob: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || key.is_null() || out_blob.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+
+        let state = ctx.export_state();
+        let iv = CoSignProtocol::generate_random(sm4::SM4_GCM_IV_LEN);
+        let mut header = CONTEXT_BLOB_MAGIC.to_vec();
+        header.push(CONTEXT_BLOB_VERSION);
+
+        match sm4::sm4_gcm_encrypt(key_slice, &iv, &header, &state) {
+            Ok(sealed) => {
+                let mut blob = header;
+                blob.extend_from_slice(&iv);
+                blob.extend_from_slice(&sealed);
+                unsafe { write_output(&blob, out_blob, out_cap, out_len) }
+            }
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 导入上下文状态，创建新的协议上下文
+///
+/// 密钥错误或数据被篡改时返回 `COSIGN_ERR_CRYPTO`，格式不符时返回 `COSIGN_ERR_ENCODING`。
+#[no_mangle]
+pub extern "C" fn cosign_context_import(
+    blob: *const c_uchar,
+    blob_len: c_ulong,
+    key: *const c_uchar,
+    key_len: c_ulong,
+    out_ctx: *mut *mut CoSignContext,
+) -> c_int {
+    ffi_guard(|| {
+        if blob.is_null() || key.is_null() || out_ctx.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let blob_slice = unsafe { slice::from_raw_parts(blob, blob_len as usize) };
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+
+        let header_len = CONTEXT_BLOB_MAGIC.len() + 1;
+        if blob_slice.len() < header_len + sm4::SM4_GCM_IV_LEN
+            || &blob_slice[..4] != CONTEXT_BLOB_MAGIC
+            || blob_slice[4] != CONTEXT_BLOB_VERSION
+        {
+            return COSIGN_ERR_ENCODING;
+        }
+        let (header, rest) = blob_slice.split_at(header_len);
+        let (iv, sealed) = rest.split_at(sm4::SM4_GCM_IV_LEN);
+
+        let state = match sm4::sm4_gcm_decrypt(key_slice, iv, header, sealed) {
+            Ok(state) => Zeroizing::new(state),
+            Err(e) => return error_code(&e),
+        };
+
+        let protocol = match CoSignProtocol::new() {
+            Ok(protocol) => protocol,
+            Err(e) => return error_code(&e),
+        };
+        let ctx = CoSignContext::new(protocol);
+        if ctx.import_state(&state).is_none() {
+            return COSIGN_ERR_ENCODING;
+        }
+
+        unsafe {
+            *out_ctx = Box::into_raw(Box::new(ctx));
+        }
+        COSIGN_OK
+    })
+}
+
 /// 生成客户端私钥分量 D1
 #[no_mangle]
 pub extern "C" fn cosign_generate_d1(
-    ctx: *mut CoSignContext,
+    ctx: *const CoSignContext,
     out_d1: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || out_d1.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || out_d1.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &mut *ctx };
+        let ctx = unsafe { &*ctx };
 
-    match ctx.protocol.generate_d1() {
-        Ok(d1) => {
-            let len = d1.len();
-            unsafe {
-                ptr::copy_nonoverlapping(d1.as_ptr(), out_d1, len);
-                *out_len = len as c_ulong;
-            }
-            COSIGN_OK
+        match ctx.protocol.generate_d1() {
+            Ok(d1) => unsafe { write_output(&d1, out_d1, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// 计算 P1 = d1 * G
@@ -76,26 +401,53 @@ pub extern "C" fn cosign_calculate_p1(
     d1: *const c_uchar,
     d1_len: c_ulong,
     out_p1: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || d1.is_null() || out_p1.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || out_p1.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
-    let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let ctx = unsafe { &*ctx };
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
 
-    match ctx.protocol.calculate_p1(d1_slice) {
-        Ok(p1) => {
-            let len = p1.len();
-            unsafe {
-                ptr::copy_nonoverlapping(p1.as_ptr(), out_p1, len);
-                *out_len = len as c_ulong;
-            }
-            COSIGN_OK
+        match ctx.protocol.calculate_p1(d1_slice) {
+            Ok(p1) => unsafe { write_output(&p1, out_p1, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
+}
+
+/// 构造设备密钥证明的声明编码，宿主以设备密钥签名后随注册请求提交
+///
+/// `subject` 为用户名（注册）或用户 ID（重新初始化密钥），`is_protected` 非 0 表示 D1 只以 wrapped 形式保存
+#[no_mangle]
+pub extern "C" fn cosign_attestation_statement(
+    p1: *const c_uchar,
+    p1_len: c_ulong,
+    subject: *const c_char,
+    is_protected: c_int,
+    out_statement: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if p1.is_null() || subject.is_null() || out_statement.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let p1_slice = unsafe { slice::from_raw_parts(p1, p1_len as usize) };
+        let subject = match unsafe { CStr::from_ptr(subject) }.to_str() {
+            Ok(subject) => subject,
+            Err(_) => return COSIGN_ERR_ENCODING,
+        };
+
+        match AttestationStatement::new(subject, p1_slice, is_protected != 0) {
+            Ok(statement) => unsafe { write_output(&statement.to_bytes(), out_statement, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
 }
 
 /// 签名预处理：生成 k1，计算 Q1 = k1 * G
@@ -103,28 +455,162 @@ pub extern "C" fn cosign_calculate_p1(
 pub extern "C" fn cosign_sign_prepare(
     ctx: *const CoSignContext,
     out_k1: *mut c_uchar,
+    k1_cap: c_ulong,
     k1_len: *mut c_ulong,
     out_q1: *mut c_uchar,
+    q1_cap: c_ulong,
     q1_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || out_k1.is_null() || k1_len.is_null() || out_q1.is_null() || q1_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || out_k1.is_null() || k1_len.is_null() || out_q1.is_null() || q1_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
+        let ctx = unsafe { &*ctx };
 
-    match ctx.protocol.sign_prepare() {
-        Ok((k1, q1)) => {
-            unsafe {
-                ptr::copy_nonoverlapping(k1.as_ptr(), out_k1, k1.len());
+        // Reason: k1 需返回给调用方，由 C 侧自行保证只使用一次；推荐改用 cosign_sign_begin
+        match ctx.protocol.sign_prepare().map(|session| session.into_parts()) {
+            Ok((k1, q1)) => unsafe {
+                // 先检查两个缓冲区容量，避免只写入一半结果
                 *k1_len = k1.len() as c_ulong;
-                ptr::copy_nonoverlapping(q1.as_ptr(), out_q1, q1.len());
                 *q1_len = q1.len() as c_ulong;
-            }
-            COSIGN_OK
+                if k1.len() > k1_cap as usize || q1.len() > q1_cap as usize {
+                    return COSIGN_ERR_BUFFER_TOO_SMALL;
+                }
+                write_output(&k1, out_k1, k1_cap, k1_len);
+                write_output(&q1, out_q1, q1_cap, q1_len)
+            },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
+}
+
+/// 开始签名会话：生成 k1 并计算 Q1 = k1 * G
+///
+/// k1 保存在上下文内部，不会返回给调用方；调用方通过 `out_session`
+/// 拿到会话 ID，随后调用 `cosign_sign_finish` 完成签名。
+#[no_mangle]
+pub extern "C" fn cosign_sign_begin(
+    ctx: *const CoSignContext,
+    out_q1: *mut c_uchar,
+    q1_cap: c_ulong,
+    q1_len: *mut c_ulong,
+    out_session: *mut u64,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || out_q1.is_null() || q1_len.is_null() || out_session.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+
+        let (k1, q1) = match ctx.protocol.sign_prepare().map(|session| session.into_parts()) {
+            Ok((k1, q1)) => (Zeroizing::new(k1.to_vec()), q1),
+            Err(e) => return error_code(&e),
+        };
+
+        let ret = unsafe { write_output(&q1, out_q1, q1_cap, q1_len) };
+        if ret != COSIGN_OK {
+            return ret;
+        }
+
+        let session_id = ctx.next_session_id.fetch_add(1, Ordering::Relaxed);
+        ctx.sessions().insert(session_id, k1);
+
+        unsafe {
+            *out_session = session_id;
+        }
+        COSIGN_OK
+    })
+}
+
+/// 完成签名会话
+///
+/// 使用会话内部保存的 k1 计算最终签名。会话无论成功与否都会被消耗，
+/// 同一会话不能完成两次（防止 k1 复用）。
+#[no_mangle]
+pub extern "C" fn cosign_sign_finish(
+    ctx: *const CoSignContext,
+    session: u64,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    r: *const c_uchar,
+    r_len: c_ulong,
+    s2: *const c_uchar,
+    s2_len: c_ulong,
+    s3: *const c_uchar,
+    s3_len: c_ulong,
+    out_r: *mut c_uchar,
+    out_r_cap: c_ulong,
+    out_r_len: *mut c_ulong,
+    out_s: *mut c_uchar,
+    out_s_cap: c_ulong,
+    out_s_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || r.is_null() || s2.is_null() || s3.is_null()
+            || out_r.is_null() || out_r_len.is_null() || out_s.is_null() || out_s_len.is_null()
+        {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+
+        let k1 = match ctx.sessions().remove(&session) {
+            Some(k1) => k1,
+            None => return COSIGN_ERR_SESSION_EXPIRED,
+        };
+
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let r_slice = unsafe { slice::from_raw_parts(r, r_len as usize) };
+        let s2_slice = unsafe { slice::from_raw_parts(s2, s2_len as usize) };
+        let s3_slice = unsafe { slice::from_raw_parts(s3, s3_len as usize) };
+
+        match ctx.protocol.complete_signature(&k1, d1_slice, r_slice, s2_slice, s3_slice) {
+            Ok((r_out, s_out)) => unsafe {
+                *out_r_len = r_out.len() as c_ulong;
+                *out_s_len = s_out.len() as c_ulong;
+                if r_out.len() > out_r_cap as usize || s_out.len() > out_s_cap as usize {
+                    return COSIGN_ERR_BUFFER_TOO_SMALL;
+                }
+                write_output(&r_out, out_r, out_r_cap, out_r_len);
+                write_output(&s_out, out_s, out_s_cap, out_s_len)
+            },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 放弃签名会话（例如服务端请求失败时），丢弃其中的 k1
+#[no_mangle]
+pub extern "C" fn cosign_sign_abort(ctx: *const CoSignContext, session: u64) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        match ctx.sessions().remove(&session) {
+            Some(_) => COSIGN_OK,
+            None => COSIGN_ERR_SESSION_EXPIRED,
+        }
+    })
+}
+
+/// 安全清零调用方内存
+///
+/// 使用不会被编译器优化掉的写入方式清零 `len` 字节，供宿主应用清理 d1、k1 等敏感数据。
+#[no_mangle]
+pub extern "C" fn cosign_secure_zero(data: *mut c_uchar, len: c_ulong) -> c_int {
+    ffi_guard(|| {
+        if data.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let data_slice = unsafe { slice::from_raw_parts_mut(data, len as usize) };
+        data_slice.zeroize();
+        COSIGN_OK
+    })
 }
 
 /// 计算消息哈希
@@ -136,31 +622,103 @@ pub extern "C" fn cosign_hash_message(
     public_key: *const c_uchar,
     public_key_len: c_ulong,
     out_hash: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || message.is_null() || out_hash.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || message.is_null() || out_hash.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
-    let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
-    let pk_slice = if public_key.is_null() || public_key_len == 0 {
-        &[]
-    } else {
-        unsafe { slice::from_raw_parts(public_key, public_key_len as usize) }
-    };
+        let ctx = unsafe { &*ctx };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let pk_slice = if public_key.is_null() || public_key_len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(public_key, public_key_len as usize) }
+        };
+
+        match ctx.protocol.calculate_message_hash(message_slice, pk_slice, DigestMode::Sm3) {
+            Ok(hash) => unsafe { write_output(&hash, out_hash, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
 
-    match ctx.protocol.calculate_message_hash(message_slice, pk_slice) {
-        Ok(hash) => {
-            let len = hash.len();
-            unsafe {
-                ptr::copy_nonoverlapping(hash.as_ptr(), out_hash, len);
-                *out_len = len as c_ulong;
-            }
-            COSIGN_OK
+/// 按预处理方式计算消息哈希 e
+///
+/// `mode` 取 `COSIGN_DIGEST_*`：`COSIGN_DIGEST_ZA` 需要 `public_key`；`COSIGN_DIGEST_PREHASHED`
+/// 要求 `input` 恰为 32 字节并原样输出。未知的 `mode` 返回 `COSIGN_ERR_INVALID_PARAM`。
+#[no_mangle]
+pub extern "C" fn cosign_hash_message_ex(
+    ctx: *const CoSignContext,
+    mode: c_int,
+    input: *const c_uchar,
+    input_len: c_ulong,
+    public_key: *const c_uchar,
+    public_key_len: c_ulong,
+    out_hash: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || input.is_null() || out_hash.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+        let Some(mode) = digest_mode(mode) else {
+            return COSIGN_ERR_INVALID_PARAM;
+        };
+
+        let ctx = unsafe { &*ctx };
+        let input_slice = unsafe { slice::from_raw_parts(input, input_len as usize) };
+        let pk_slice = if public_key.is_null() || public_key_len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(public_key, public_key_len as usize) }
+        };
+
+        match ctx.protocol.calculate_message_hash(input_slice, pk_slice, mode) {
+            Ok(hash) => unsafe { write_output(&hash, out_hash, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 计算带用户标识的消息哈希 e = SM3(ZA || M)
+///
+/// `uid` 为 NULL 时使用默认用户标识 "1234567812345678"。
+#[no_mangle]
+pub extern "C" fn cosign_hash_message_with_uid(
+    ctx: *const CoSignContext,
+    message: *const c_uchar,
+    message_len: c_ulong,
+    uid: *const c_uchar,
+    uid_len: c_ulong,
+    public_key: *const c_uchar,
+    public_key_len: c_ulong,
+    out_hash: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || message.is_null() || public_key.is_null() || out_hash.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let uid_slice = if uid.is_null() {
+            protocol::DEFAULT_USER_ID
+        } else {
+            unsafe { slice::from_raw_parts(uid, uid_len as usize) }
+        };
+        let pk_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
+
+        match ctx.protocol.calculate_message_hash_with_uid(message_slice, uid_slice, pk_slice) {
+            Ok(hash) => unsafe { write_output(&hash, out_hash, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
 }
 
 /// 完成签名计算
@@ -178,35 +736,39 @@ pub extern "C" fn cosign_complete_signature(
     s3: *const c_uchar,
     s3_len: c_ulong,
     out_r: *mut c_uchar,
+    out_r_cap: c_ulong,
     out_r_len: *mut c_ulong,
     out_s: *mut c_uchar,
+    out_s_cap: c_ulong,
     out_s_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || k1.is_null() || d1.is_null() || r.is_null() || s2.is_null() || s3.is_null()
-        || out_r.is_null() || out_s.is_null()
-    {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || k1.is_null() || d1.is_null() || r.is_null() || s2.is_null() || s3.is_null()
+            || out_r.is_null() || out_r_len.is_null() || out_s.is_null() || out_s_len.is_null()
+        {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
-    let k1_slice = unsafe { slice::from_raw_parts(k1, k1_len as usize) };
-    let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
-    let r_slice = unsafe { slice::from_raw_parts(r, r_len as usize) };
-    let s2_slice = unsafe { slice::from_raw_parts(s2, s2_len as usize) };
-    let s3_slice = unsafe { slice::from_raw_parts(s3, s3_len as usize) };
+        let ctx = unsafe { &*ctx };
+        let k1_slice = unsafe { slice::from_raw_parts(k1, k1_len as usize) };
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let r_slice = unsafe { slice::from_raw_parts(r, r_len as usize) };
+        let s2_slice = unsafe { slice::from_raw_parts(s2, s2_len as usize) };
+        let s3_slice = unsafe { slice::from_raw_parts(s3, s3_len as usize) };
 
-    match ctx.protocol.complete_signature(k1_slice, d1_slice, r_slice, s2_slice, s3_slice) {
-        Ok((r_out, s_out)) => {
-            unsafe {
-                ptr::copy_nonoverlapping(r_out.as_ptr(), out_r, r_out.len());
+        match ctx.protocol.complete_signature(k1_slice, d1_slice, r_slice, s2_slice, s3_slice) {
+            Ok((r_out, s_out)) => unsafe {
                 *out_r_len = r_out.len() as c_ulong;
-                ptr::copy_nonoverlapping(s_out.as_ptr(), out_s, s_out.len());
                 *out_s_len = s_out.len() as c_ulong;
-            }
-            COSIGN_OK
+                if r_out.len() > out_r_cap as usize || s_out.len() > out_s_cap as usize {
+                    return COSIGN_ERR_BUFFER_TOO_SMALL;
+                }
+                write_output(&r_out, out_r, out_r_cap, out_r_len);
+                write_output(&s_out, out_s, out_s_cap, out_s_len)
+            },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// 解密预处理：计算 T1 = d1 * C1
@@ -218,27 +780,23 @@ pub extern "C" fn cosign_decrypt_prepare(
     c1: *const c_uchar,
     c1_len: c_ulong,
     out_t1: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || d1.is_null() || c1.is_null() || out_t1.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || c1.is_null() || out_t1.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
-    let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
-    let c1_slice = unsafe { slice::from_raw_parts(c1, c1_len as usize) };
+        let ctx = unsafe { &*ctx };
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let c1_slice = unsafe { slice::from_raw_parts(c1, c1_len as usize) };
 
-    match ctx.protocol.decrypt_prepare(d1_slice, c1_slice) {
-        Ok(t1) => {
-            let len = t1.len();
-            unsafe {
-                ptr::copy_nonoverlapping(t1.as_ptr(), out_t1, len);
-                *out_len = len as c_ulong;
-            }
-            COSIGN_OK
+        match ctx.protocol.decrypt_prepare(d1_slice, c1_slice) {
+            Ok(t1) => unsafe { write_output(&t1, out_t1, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// 完成解密计算
@@ -254,29 +812,25 @@ pub extern "C" fn cosign_complete_decryption(
     c2: *const c_uchar,
     c2_len: c_ulong,
     out_plaintext: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if ctx.is_null() || t2.is_null() || c1.is_null() || c3.is_null() || c2.is_null() || out_plaintext.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if ctx.is_null() || t2.is_null() || c1.is_null() || c3.is_null() || c2.is_null() || out_plaintext.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let ctx = unsafe { &*ctx };
-    let t2_slice = unsafe { slice::from_raw_parts(t2, t2_len as usize) };
-    let c1_slice = unsafe { slice::from_raw_parts(c1, c1_len as usize) };
-    let c3_slice = unsafe { slice::from_raw_parts(c3, c3_len as usize) };
-    let c2_slice = unsafe { slice::from_raw_parts(c2, c2_len as usize) };
+        let ctx = unsafe { &*ctx };
+        let t2_slice = unsafe { slice::from_raw_parts(t2, t2_len as usize) };
+        let c1_slice = unsafe { slice::from_raw_parts(c1, c1_len as usize) };
+        let c3_slice = unsafe { slice::from_raw_parts(c3, c3_len as usize) };
+        let c2_slice = unsafe { slice::from_raw_parts(c2, c2_len as usize) };
 
-    match ctx.protocol.complete_decryption(t2_slice, c1_slice, c3_slice, c2_slice) {
-        Ok(plaintext) => {
-            let len = plaintext.len();
-            unsafe {
-                ptr::copy_nonoverlapping(plaintext.as_ptr(), out_plaintext, len);
-                *out_len = len as c_ulong;
-            }
-            COSIGN_OK
+        match ctx.protocol.complete_decryption(t2_slice, c1_slice, c3_slice, c2_slice) {
+            Ok(plaintext) => unsafe { write_output(&plaintext, out_plaintext, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// 计算 SM3 哈希
@@ -285,21 +839,19 @@ pub extern "C" fn cosign_sm3_hash(
     data: *const c_uchar,
     data_len: c_ulong,
     out_hash: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if data.is_null() || out_hash.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
-
-    let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
-    let hash = CoSignProtocol::sm3_hash(data_slice);
+    ffi_guard(|| {
+        if data.is_null() || out_hash.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    unsafe {
-        ptr::copy_nonoverlapping(hash.as_ptr(), out_hash, hash.len());
-        *out_len = hash.len() as c_ulong;
-    }
+        let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
+        let hash = CoSignProtocol::sm3_hash(data_slice);
 
-    COSIGN_OK
+        unsafe { write_output(&hash, out_hash, out_cap, out_len) }
+    })
 }
 
 /// SM2 签名（标准签名）
@@ -310,25 +862,22 @@ pub extern "C" fn cosign_sm2_sign(
     message: *const c_uchar,
     message_len: c_ulong,
     out_signature: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if private_key.is_null() || message.is_null() || out_signature.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if private_key.is_null() || message.is_null() || out_signature.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let private_key_slice = unsafe { slice::from_raw_parts(private_key, private_key_len as usize) };
-    let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let private_key_slice = unsafe { slice::from_raw_parts(private_key, private_key_len as usize) };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
 
-    match CoSignProtocol::sign(private_key_slice, message_slice) {
-        Ok(signature) => {
-            unsafe {
-                ptr::copy_nonoverlapping(signature.as_ptr(), out_signature, signature.len());
-                *out_len = signature.len() as c_ulong;
-            }
-            COSIGN_OK
+        match CoSignProtocol::sign(private_key_slice, message_slice) {
+            Ok(signature) => unsafe { write_output(&signature, out_signature, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// SM2 验签（标准验签）
@@ -341,19 +890,21 @@ pub extern "C" fn cosign_sm2_verify(
     signature: *const c_uchar,
     signature_len: c_ulong,
 ) -> c_int {
-    if public_key.is_null() || message.is_null() || signature.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if public_key.is_null() || message.is_null() || signature.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let public_key_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
-    let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
-    let signature_slice = unsafe { slice::from_raw_parts(signature, signature_len as usize) };
+        let public_key_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let signature_slice = unsafe { slice::from_raw_parts(signature, signature_len as usize) };
 
-    match CoSignProtocol::verify(public_key_slice, message_slice, signature_slice) {
-        Ok(true) => COSIGN_OK,
-        Ok(false) => COSIGN_ERR_CRYPTO,
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+        match CoSignProtocol::verify(public_key_slice, message_slice, signature_slice) {
+            Ok(true) => COSIGN_OK,
+            Ok(false) => COSIGN_ERR_VERIFY_FAILED,
+            Err(e) => error_code(&e),
+        }
+    })
 }
 
 /// SM2 加密（标准加密）
@@ -364,25 +915,22 @@ pub extern "C" fn cosign_sm2_encrypt(
     message: *const c_uchar,
     message_len: c_ulong,
     out_ciphertext: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if public_key.is_null() || message.is_null() || out_ciphertext.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if public_key.is_null() || message.is_null() || out_ciphertext.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let public_key_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
-    let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let public_key_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
 
-    match CoSignProtocol::encrypt(public_key_slice, message_slice) {
-        Ok(ciphertext) => {
-            unsafe {
-                ptr::copy_nonoverlapping(ciphertext.as_ptr(), out_ciphertext, ciphertext.len());
-                *out_len = ciphertext.len() as c_ulong;
-            }
-            COSIGN_OK
+        match CoSignProtocol::encrypt(public_key_slice, message_slice) {
+            Ok(ciphertext) => unsafe { write_output(&ciphertext, out_ciphertext, out_cap, out_len) },
+            Err(e) => error_code(&e),
         }
-        Err(_) => COSIGN_ERR_CRYPTO,
-    }
+    })
 }
 
 /// SM2 解密（标准解密）
@@ -393,62 +941,73 @@ pub extern "C" fn cosign_sm2_decrypt(
     ciphertext: *const c_uchar,
     ciphertext_len: c_ulong,
     out_plaintext: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
 ) -> c_int {
-    if private_key.is_null() || ciphertext.is_null() || out_plaintext.is_null() || out_len.is_null() {
-        return COSIGN_ERR_NULL_PTR;
-    }
+    ffi_guard(|| {
+        if private_key.is_null() || ciphertext.is_null() || out_plaintext.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
 
-    let private_key_slice = unsafe { slice::from_raw_parts(private_key, private_key_len as usize) };
-    let ciphertext_slice = unsafe { slice::from_raw_parts(ciphertext, ciphertext_len as usize) };
+        let private_key_slice = unsafe { slice::from_raw_parts(private_key, private_key_len as usize) };
+        let ciphertext_slice = unsafe { slice::from_raw_parts(ciphertext, ciphertext_len as usize) };
 
-    match CoSignProtocol::decrypt(private_key_slice, ciphertext_slice) {
-        Ok(Some(plaintext)) => {
-            unsafe {
-                ptr::copy_nonoverlapping(plaintext.as_ptr(), out_plaintext, plaintext.len());
-                *out_len = plaintext.len() as c_ulong;
+        match CoSignProtocol::decrypt(private_key_slice, ciphertext_slice) {
+            Ok(Some(plaintext)) => unsafe { write_output(&plaintext, out_plaintext, out_cap, out_len) },
+            Ok(None) => COSIGN_ERR_CRYPTO,
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 将字符串以 NUL 结尾写入调用方缓冲区
+///
+/// `out_cap` 需包含结尾 NUL 字符；成功时 `out_len` 返回不含 NUL 的字符串长度，
+/// 容量不足时 `out_len` 返回所需容量（含 NUL）。
+///
+/// # Safety
+/// `out_str` 必须指向至少 `out_cap` 字节的可写内存，`out_len` 必须可写。
+unsafe fn write_c_string(value: String, out_str: *mut c_char, out_cap: c_ulong, out_len: *mut c_ulong) -> c_int {
+    match CString::new(value) {
+        Ok(c_str) => {
+            let bytes = c_str.as_bytes_with_nul();
+            let ret = write_output(bytes, out_str as *mut u8, out_cap, out_len);
+            if ret == COSIGN_OK {
+                *out_len = (bytes.len() - 1) as c_ulong;
             }
-            COSIGN_OK
+            ret
         }
-        Ok(None) => COSIGN_ERR_CRYPTO,
-        Err(_) => COSIGN_ERR_CRYPTO,
+        Err(_) => COSIGN_ERR_ENCODING,
     }
 }
 
-/// Base64 编码
-#[no_mangle]
-pub extern "C" fn cosign_base64_encode(
+/// 按指定编码编码数据并写出字符串
+fn encode_to_c_string(
     data: *const c_uchar,
     data_len: c_ulong,
     out_str: *mut c_char,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
+    encoding: Encoding,
 ) -> c_int {
     if data.is_null() || out_str.is_null() || out_len.is_null() {
         return COSIGN_ERR_NULL_PTR;
     }
 
     let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
-    let encoded = sm2_co_sign_core::protocol::base64_encode(data_slice);
+    match encoding.encode_str(data_slice) {
+        Ok(text) => unsafe { write_c_string(text, out_str, out_cap, out_len) },
+        Err(e) => error_code(&e),
+    }
+}
 
-    match CString::new(encoded) {
-        Ok(c_str) => {
-            let bytes = c_str.as_bytes_with_nul();
-            unsafe {
-                ptr::copy_nonoverlapping(bytes.as_ptr(), out_str as *mut u8, bytes.len());
-                *out_len = (bytes.len() - 1) as c_ulong;
-            }
-            COSIGN_OK
-        }
-        Err(_) => COSIGN_ERR_ENCODING,
-    }
-}
-
-/// Base64 解码
-#[no_mangle]
-pub extern "C" fn cosign_base64_decode(
+/// 按指定编码解码 NUL 结尾字符串并写出数据
+fn decode_from_c_string(
     str: *const c_char,
     out_data: *mut c_uchar,
+    out_cap: c_ulong,
     out_len: *mut c_ulong,
+    encoding: Encoding,
 ) -> c_int {
     if str.is_null() || out_data.is_null() || out_len.is_null() {
         return COSIGN_ERR_NULL_PTR;
@@ -460,18 +1019,586 @@ pub extern "C" fn cosign_base64_decode(
         Err(_) => return COSIGN_ERR_ENCODING,
     };
 
-    match sm2_co_sign_core::protocol::base64_decode(str_slice) {
-        Ok(data) => {
-            unsafe {
-                ptr::copy_nonoverlapping(data.as_ptr(), out_data, data.len());
-                *out_len = data.len() as c_ulong;
-            }
+    match encoding.decode_str(str_slice) {
+        Ok(data) => unsafe { write_output(&data, out_data, out_cap, out_len) },
+        Err(_) => COSIGN_ERR_ENCODING,
+    }
+}
+
+/// 按 `encoding`（`COSIGN_ENCODING_*`）编码数据
+///
+/// `out_cap` 需包含结尾 NUL 字符；`out_len` 返回不含 NUL 的字符串长度。
+#[no_mangle]
+pub extern "C" fn cosign_encode(
+    encoding: c_int,
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_str: *mut c_char,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| match text_encoding(encoding) {
+        Some(encoding) => encode_to_c_string(data, data_len, out_str, out_cap, out_len, encoding),
+        None => COSIGN_ERR_INVALID_PARAM,
+    })
+}
+
+/// 按 `encoding`（`COSIGN_ENCODING_*`）解码 NUL 结尾字符串
+#[no_mangle]
+pub extern "C" fn cosign_decode(
+    encoding: c_int,
+    str: *const c_char,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| match text_encoding(encoding) {
+        Some(encoding) => decode_from_c_string(str, out_data, out_cap, out_len, encoding),
+        None => COSIGN_ERR_INVALID_PARAM,
+    })
+}
+
+/// Base64 编码
+///
+/// `out_cap` 需包含结尾 NUL 字符；`out_len` 返回不含 NUL 的字符串长度。
+#[no_mangle]
+pub extern "C" fn cosign_base64_encode(
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_str: *mut c_char,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        encode_to_c_string(data, data_len, out_str, out_cap, out_len, Encoding::Base64)
+    })
+}
+
+/// Base64 解码
+#[no_mangle]
+pub extern "C" fn cosign_base64_decode(
+    str: *const c_char,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        decode_from_c_string(str, out_data, out_cap, out_len, Encoding::Base64)
+    })
+}
+
+/// Base64URL 编码（URL 安全字符集，无填充）
+///
+/// `out_cap` 需包含结尾 NUL 字符；`out_len` 返回不含 NUL 的字符串长度。
+#[no_mangle]
+pub extern "C" fn cosign_base64url_encode(
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_str: *mut c_char,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        encode_to_c_string(data, data_len, out_str, out_cap, out_len, Encoding::Base64Url)
+    })
+}
+
+/// Base64URL 解码（URL 安全字符集，无填充）
+#[no_mangle]
+pub extern "C" fn cosign_base64url_decode(
+    str: *const c_char,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        decode_from_c_string(str, out_data, out_cap, out_len, Encoding::Base64Url)
+    })
+}
+
+/// Hex 编码（小写）
+///
+/// `out_cap` 需包含结尾 NUL 字符；`out_len` 返回不含 NUL 的字符串长度。
+#[no_mangle]
+pub extern "C" fn cosign_hex_encode(
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_str: *mut c_char,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        encode_to_c_string(data, data_len, out_str, out_cap, out_len, Encoding::Hex)
+    })
+}
+
+/// Hex 解码（大小写均可）
+#[no_mangle]
+pub extern "C" fn cosign_hex_decode(
+    str: *const c_char,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        decode_from_c_string(str, out_data, out_cap, out_len, Encoding::Hex)
+    })
+}
+
+/// 原始签名 r||s（64字节）转换为 DER 编码
+#[no_mangle]
+pub extern "C" fn cosign_signature_to_der(
+    signature: *const c_uchar,
+    signature_len: c_ulong,
+    out_der: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if signature.is_null() || out_der.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let signature_slice = unsafe { slice::from_raw_parts(signature, signature_len as usize) };
+
+        match sm2_co_sign_core::asn1::signature_to_der(signature_slice) {
+            Ok(der) => unsafe { write_output(&der, out_der, out_cap, out_len) },
+            Err(_) => COSIGN_ERR_INVALID_PARAM,
+        }
+    })
+}
+
+/// DER 编码签名转换为原始签名 r||s（64字节）
+#[no_mangle]
+pub extern "C" fn cosign_signature_from_der(
+    der: *const c_uchar,
+    der_len: c_ulong,
+    out_signature: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if der.is_null() || out_signature.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let der_slice = unsafe { slice::from_raw_parts(der, der_len as usize) };
+
+        match sm2_co_sign_core::asn1::signature_from_der(der_slice) {
+            Ok(raw) => unsafe { write_output(&raw, out_signature, out_cap, out_len) },
+            Err(_) => COSIGN_ERR_ENCODING,
+        }
+    })
+}
+
+/// SM4-CBC 加密（PKCS#7 填充）
+#[no_mangle]
+pub extern "C" fn cosign_sm4_cbc_encrypt(
+    key: *const c_uchar,
+    key_len: c_ulong,
+    iv: *const c_uchar,
+    iv_len: c_ulong,
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if key.is_null() || iv.is_null() || data.is_null() || out_data.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+        let iv_slice = unsafe { slice::from_raw_parts(iv, iv_len as usize) };
+        let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
+
+        match sm4::sm4_cbc_encrypt(key_slice, iv_slice, data_slice) {
+            Ok(result) => unsafe { write_output(&result, out_data, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// SM4-CBC 解密（去除 PKCS#7 填充）
+#[no_mangle]
+pub extern "C" fn cosign_sm4_cbc_decrypt(
+    key: *const c_uchar,
+    key_len: c_ulong,
+    iv: *const c_uchar,
+    iv_len: c_ulong,
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if key.is_null() || iv.is_null() || data.is_null() || out_data.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+        let iv_slice = unsafe { slice::from_raw_parts(iv, iv_len as usize) };
+        let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
+
+        match sm4::sm4_cbc_decrypt(key_slice, iv_slice, data_slice) {
+            Ok(result) => unsafe { write_output(&result, out_data, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// SM4-GCM 加密，输出 密文 || 认证标签（16字节）
+#[no_mangle]
+pub extern "C" fn cosign_sm4_gcm_encrypt(
+    key: *const c_uchar,
+    key_len: c_ulong,
+    iv: *const c_uchar,
+    iv_len: c_ulong,
+    aad: *const c_uchar,
+    aad_len: c_ulong,
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if key.is_null() || iv.is_null() || data.is_null() || out_data.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+        let iv_slice = unsafe { slice::from_raw_parts(iv, iv_len as usize) };
+        // AAD 可为空
+        let aad_slice = if aad.is_null() || aad_len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(aad, aad_len as usize) }
+        };
+        let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
+
+        match sm4::sm4_gcm_encrypt(key_slice, iv_slice, aad_slice, data_slice) {
+            Ok(result) => unsafe { write_output(&result, out_data, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// SM4-GCM 解密，输入 密文 || 认证标签（16字节），认证失败返回 COSIGN_ERR_CRYPTO
+#[no_mangle]
+pub extern "C" fn cosign_sm4_gcm_decrypt(
+    key: *const c_uchar,
+    key_len: c_ulong,
+    iv: *const c_uchar,
+    iv_len: c_ulong,
+    aad: *const c_uchar,
+    aad_len: c_ulong,
+    data: *const c_uchar,
+    data_len: c_ulong,
+    out_data: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if key.is_null() || iv.is_null() || data.is_null() || out_data.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let key_slice = unsafe { slice::from_raw_parts(key, key_len as usize) };
+        let iv_slice = unsafe { slice::from_raw_parts(iv, iv_len as usize) };
+        // AAD 可为空
+        let aad_slice = if aad.is_null() || aad_len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(aad, aad_len as usize) }
+        };
+        let data_slice = unsafe { slice::from_raw_parts(data, data_len as usize) };
+
+        match sm4::sm4_gcm_decrypt(key_slice, iv_slice, aad_slice, data_slice) {
+            Ok(result) => unsafe { write_output(&result, out_data, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 签名结果（r、s 均左补零到 32 字节）
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cosign_signature_t {
+    pub r: [c_uchar; 32],
+    pub s: [c_uchar; 32],
+}
+
+/// 客户端密钥对（D1 左补零到 32 字节，P1 为 x||y）
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cosign_keypair_t {
+    pub d1: [c_uchar; 32],
+    pub p1: [c_uchar; 64],
+}
+
+/// 服务端签名响应分量（r、s2、s3 均左补零到 32 字节）
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cosign_sign_response_t {
+    pub r: [c_uchar; 32],
+    pub s2: [c_uchar; 32],
+    pub s3: [c_uchar; 32],
+}
+
+/// 密文分量（C1C3C2 格式拆分结果）
+///
+/// `c2` 指向原密文缓冲区内部，生命周期与原缓冲区相同，调用方不得释放。
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct cosign_ciphertext_parts_t {
+    pub c1: [c_uchar; 64],
+    pub c3: [c_uchar; 32],
+    pub c2: *const c_uchar,
+    pub c2_len: c_ulong,
+}
+
+/// 将 (r, s) 写入签名结构体
+fn fill_signature(r: &[u8], s: &[u8], out: &mut cosign_signature_t) -> c_int {
+    match (asn1::left_pad_32(r), asn1::left_pad_32(s)) {
+        (Ok(r), Ok(s)) => {
+            out.r = r;
+            out.s = s;
             COSIGN_OK
         }
-        Err(_) => COSIGN_ERR_ENCODING,
+        _ => COSIGN_ERR_CRYPTO,
     }
 }
 
+/// 生成客户端密钥对（D1 与 P1 = d1 * G）
+#[no_mangle]
+pub extern "C" fn cosign_keypair_generate(ctx: *const CoSignContext, out_keypair: *mut cosign_keypair_t) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || out_keypair.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        let out = unsafe { &mut *out_keypair };
+
+        let d1 = match ctx.protocol.generate_d1() {
+            Ok(d1) => d1,
+            Err(e) => return error_code(&e),
+        };
+        let p1 = match ctx.protocol.calculate_p1(&d1) {
+            Ok(p1) => p1,
+            Err(e) => return error_code(&e),
+        };
+
+        match asn1::left_pad_32(&d1) {
+            Ok(d1) => out.d1 = d1,
+            Err(_) => return COSIGN_ERR_CRYPTO,
+        }
+        out.p1.copy_from_slice(&p1);
+        COSIGN_OK
+    })
+}
+
+/// 完成签名计算（结构体版本）
+#[no_mangle]
+pub extern "C" fn cosign_complete_signature_ex(
+    ctx: *const CoSignContext,
+    k1: *const c_uchar,
+    k1_len: c_ulong,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    response: *const cosign_sign_response_t,
+    out_signature: *mut cosign_signature_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || k1.is_null() || d1.is_null() || response.is_null() || out_signature.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        let k1_slice = unsafe { slice::from_raw_parts(k1, k1_len as usize) };
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let response = unsafe { &*response };
+
+        match ctx.protocol.complete_signature(k1_slice, d1_slice, &response.r, &response.s2, &response.s3) {
+            Ok((r, s)) => fill_signature(&r, &s, unsafe { &mut *out_signature }),
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 完成签名会话（结构体版本），会话随即失效
+#[no_mangle]
+pub extern "C" fn cosign_sign_finish_ex(
+    ctx: *const CoSignContext,
+    session: u64,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    response: *const cosign_sign_response_t,
+    out_signature: *mut cosign_signature_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || response.is_null() || out_signature.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+
+        let k1 = match ctx.sessions().remove(&session) {
+            Some(k1) => k1,
+            None => return COSIGN_ERR_SESSION_EXPIRED,
+        };
+
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let response = unsafe { &*response };
+
+        match ctx.protocol.complete_signature(&k1, d1_slice, &response.r, &response.s2, &response.s3) {
+            Ok((r, s)) => fill_signature(&r, &s, unsafe { &mut *out_signature }),
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 一次完成协同签名：计算 e = SM3(ZA || M)、完成签名会话并在本地验签
+///
+/// `message`、`uid`、`public_key` 必须与发送给服务端的 e 一致；`uid` 为 NULL 时使用默认用户标识。
+/// 会话无论成功与否都会被消耗。服务端响应有误导致签名无法通过验签时返回
+/// `COSIGN_ERR_VERIFY_FAILED`，此时 `out_signature` 不会被写入。
+#[no_mangle]
+pub extern "C" fn cosign_cosign_message(
+    ctx: *const CoSignContext,
+    session: u64,
+    d1: *const c_uchar,
+    d1_len: c_ulong,
+    message: *const c_uchar,
+    message_len: c_ulong,
+    uid: *const c_uchar,
+    uid_len: c_ulong,
+    public_key: *const c_uchar,
+    public_key_len: c_ulong,
+    response: *const cosign_sign_response_t,
+    out_signature: *mut cosign_signature_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || d1.is_null() || message.is_null() || public_key.is_null()
+            || response.is_null() || out_signature.is_null()
+        {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+
+        let k1 = match ctx.sessions().remove(&session) {
+            Some(k1) => k1,
+            None => return COSIGN_ERR_SESSION_EXPIRED,
+        };
+
+        let d1_slice = unsafe { slice::from_raw_parts(d1, d1_len as usize) };
+        let message_slice = unsafe { slice::from_raw_parts(message, message_len as usize) };
+        let uid_slice = if uid.is_null() {
+            protocol::DEFAULT_USER_ID
+        } else {
+            unsafe { slice::from_raw_parts(uid, uid_len as usize) }
+        };
+        let pk_slice = unsafe { slice::from_raw_parts(public_key, public_key_len as usize) };
+        let response = unsafe { &*response };
+
+        let e = match ctx.protocol.calculate_message_hash_with_uid(message_slice, uid_slice, pk_slice) {
+            Ok(e) => e,
+            Err(e) => return error_code(&e),
+        };
+
+        let (r, s) = match ctx.protocol.complete_signature(&k1, d1_slice, &response.r, &response.s2, &response.s3) {
+            Ok(signature) => signature,
+            Err(e) => return error_code(&e),
+        };
+
+        match ctx.protocol.verify_digest(pk_slice, &e, &r, &s) {
+            Ok(true) => fill_signature(&r, &s, unsafe { &mut *out_signature }),
+            Ok(false) => COSIGN_ERR_VERIFY_FAILED,
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// 拆分 C1C3C2 格式密文（0x04 前缀可选），或 ASN.1 DER 编码密文
+#[no_mangle]
+pub extern "C" fn cosign_ciphertext_split(
+    ciphertext: *const c_uchar,
+    ciphertext_len: c_ulong,
+    out_parts: *mut cosign_ciphertext_parts_t,
+) -> c_int {
+    cosign_ciphertext_split_ex(ciphertext, ciphertext_len, COSIGN_LAYOUT_C1C3C2, out_parts)
+}
+
+/// 按指定分量顺序拆分密文；`layout` 取 `COSIGN_LAYOUT_*`，DER 编码自动识别
+#[no_mangle]
+pub extern "C" fn cosign_ciphertext_split_ex(
+    ciphertext: *const c_uchar,
+    ciphertext_len: c_ulong,
+    layout: c_int,
+    out_parts: *mut cosign_ciphertext_parts_t,
+) -> c_int {
+    ffi_guard(|| {
+        if ciphertext.is_null() || out_parts.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let Some(layout) = ciphertext_layout(layout) else {
+            return COSIGN_ERR_INVALID_PARAM;
+        };
+
+        let ciphertext_slice = unsafe { slice::from_raw_parts(ciphertext, ciphertext_len as usize) };
+        let parsed = match Sm2Ciphertext::parse_with_layout(ciphertext_slice, layout) {
+            Ok(parsed) => parsed,
+            Err(e) => return error_code(&e),
+        };
+
+        let out = unsafe { &mut *out_parts };
+        out.c1 = parsed.c1;
+        out.c3 = parsed.c3;
+        out.c2 = parsed.c2.as_ptr();
+        out.c2_len = parsed.c2.len() as c_ulong;
+        COSIGN_OK
+    })
+}
+
+/// 完成解密计算（结构体版本）
+#[no_mangle]
+pub extern "C" fn cosign_complete_decryption_ex(
+    ctx: *const CoSignContext,
+    t2: *const c_uchar,
+    t2_len: c_ulong,
+    parts: *const cosign_ciphertext_parts_t,
+    out_plaintext: *mut c_uchar,
+    out_cap: c_ulong,
+    out_len: *mut c_ulong,
+) -> c_int {
+    ffi_guard(|| {
+        if ctx.is_null() || t2.is_null() || parts.is_null() || out_plaintext.is_null() || out_len.is_null() {
+            return COSIGN_ERR_NULL_PTR;
+        }
+
+        let ctx = unsafe { &*ctx };
+        let t2_slice = unsafe { slice::from_raw_parts(t2, t2_len as usize) };
+        let parts = unsafe { &*parts };
+        if parts.c2.is_null() && parts.c2_len > 0 {
+            return COSIGN_ERR_NULL_PTR;
+        }
+        let c2_slice = if parts.c2_len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(parts.c2, parts.c2_len as usize) }
+        };
+
+        match ctx.protocol.complete_decryption(t2_slice, &parts.c1, &parts.c3, c2_slice) {
+            Ok(plaintext) => unsafe { write_output(&plaintext, out_plaintext, out_cap, out_len) },
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -490,20 +1617,44 @@ mod tests {
         let mut d1 = [0u8; 32];
         let mut len: c_ulong = 0;
 
-        let result = cosign_generate_d1(ctx, d1.as_mut_ptr(), &mut len);
+        let result = cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut len);
         assert_eq!(result, COSIGN_OK);
         assert!(len > 0);
 
         cosign_context_free(ctx);
     }
 
+    #[test]
+    fn test_attestation_statement() {
+        let protocol = CoSignProtocol::new().unwrap();
+        let p1 = protocol.calculate_p1(&protocol.generate_d1().unwrap()).unwrap();
+        let subject = CString::new("alice").unwrap();
+        let mut out = [0u8; 256];
+        let mut len: c_ulong = 0;
+
+        let result = cosign_attestation_statement(
+            p1.as_ptr(),
+            p1.len() as c_ulong,
+            subject.as_ptr(),
+            1,
+            out.as_mut_ptr(),
+            out.len() as c_ulong,
+            &mut len,
+        );
+        assert_eq!(result, COSIGN_OK);
+        let statement = AttestationStatement::from_bytes(&out[..len as usize]).unwrap();
+        assert_eq!(statement.subject, "alice");
+        assert_eq!(statement.p1, p1);
+        assert!(statement.protected);
+    }
+
     #[test]
     fn test_sm3_hash() {
         let data = b"hello world";
         let mut hash = [0u8; 32];
         let mut len: c_ulong = 0;
 
-        let result = cosign_sm3_hash(data.as_ptr(), data.len() as c_ulong, hash.as_mut_ptr(), &mut len);
+        let result = cosign_sm3_hash(data.as_ptr(), data.len() as c_ulong, hash.as_mut_ptr(), hash.len() as c_ulong, &mut len);
         assert_eq!(result, COSIGN_OK);
         assert_eq!(len, 32);
     }
@@ -513,17 +1664,17 @@ mod tests {
         let ctx = cosign_context_new();
         let mut d1 = [0u8; 32];
         let mut d1_len: c_ulong = 0;
-        cosign_generate_d1(ctx, d1.as_mut_ptr(), &mut d1_len);
+        cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut d1_len);
 
         let mut p1 = [0u8; 64];
         let mut p1_len: c_ulong = 0;
-        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), &mut p1_len);
+        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), p1.len() as c_ulong, &mut p1_len);
 
         let message = b"hello world";
         let mut signature = [0u8; 64];
         let mut sig_len: c_ulong = 0;
 
-        let result = cosign_sm2_sign(d1.as_ptr(), d1_len, message.as_ptr(), message.len() as c_ulong, signature.as_mut_ptr(), &mut sig_len);
+        let result = cosign_sm2_sign(d1.as_ptr(), d1_len, message.as_ptr(), message.len() as c_ulong, signature.as_mut_ptr(), signature.len() as c_ulong, &mut sig_len);
         assert_eq!(result, COSIGN_OK);
         assert_eq!(sig_len, 64);
 
@@ -538,23 +1689,23 @@ mod tests {
         let ctx = cosign_context_new();
         let mut d1 = [0u8; 32];
         let mut d1_len: c_ulong = 0;
-        cosign_generate_d1(ctx, d1.as_mut_ptr(), &mut d1_len);
+        cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut d1_len);
 
         let mut p1 = [0u8; 64];
         let mut p1_len: c_ulong = 0;
-        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), &mut p1_len);
+        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), p1.len() as c_ulong, &mut p1_len);
 
         let message = b"hello world";
         let mut ciphertext = [0u8; 256];
         let mut cipher_len: c_ulong = 0;
 
-        let result = cosign_sm2_encrypt(p1.as_ptr(), p1_len, message.as_ptr(), message.len() as c_ulong, ciphertext.as_mut_ptr(), &mut cipher_len);
+        let result = cosign_sm2_encrypt(p1.as_ptr(), p1_len, message.as_ptr(), message.len() as c_ulong, ciphertext.as_mut_ptr(), ciphertext.len() as c_ulong, &mut cipher_len);
         assert_eq!(result, COSIGN_OK);
 
         let mut plaintext = [0u8; 256];
         let mut plain_len: c_ulong = 0;
 
-        let result = cosign_sm2_decrypt(d1.as_ptr(), d1_len, ciphertext.as_ptr(), cipher_len, plaintext.as_mut_ptr(), &mut plain_len);
+        let result = cosign_sm2_decrypt(d1.as_ptr(), d1_len, ciphertext.as_ptr(), cipher_len, plaintext.as_mut_ptr(), plaintext.len() as c_ulong, &mut plain_len);
         assert_eq!(result, COSIGN_OK);
         assert_eq!(&plaintext[..plain_len as usize], message);
 
@@ -567,7 +1718,7 @@ mod tests {
         let mut out_str = [0i8; 64];
         let mut len: c_ulong = 0;
 
-        let result = cosign_base64_encode(data.as_ptr(), data.len() as c_ulong, out_str.as_mut_ptr(), &mut len);
+        let result = cosign_base64_encode(data.as_ptr(), data.len() as c_ulong, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
         assert_eq!(result, COSIGN_OK);
 
         let encoded = unsafe { CStr::from_ptr(out_str.as_ptr()) };
@@ -575,8 +1726,494 @@ mod tests {
 
         let mut decoded = [0u8; 64];
         let mut decoded_len: c_ulong = 0;
-        let result = cosign_base64_decode(out_str.as_ptr(), decoded.as_mut_ptr(), &mut decoded_len);
+        let result = cosign_base64_decode(out_str.as_ptr(), decoded.as_mut_ptr(), decoded.len() as c_ulong, &mut decoded_len);
         assert_eq!(result, COSIGN_OK);
         assert_eq!(&decoded[..decoded_len as usize], data);
     }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let data = b"hello world";
+        let mut hash = [0u8; 16];
+        let mut len: c_ulong = 0;
+
+        let result = cosign_sm3_hash(data.as_ptr(), data.len() as c_ulong, hash.as_mut_ptr(), hash.len() as c_ulong, &mut len);
+        assert_eq!(result, COSIGN_ERR_BUFFER_TOO_SMALL);
+        // 回传所需长度，且未写入任何数据
+        assert_eq!(len, 32);
+        assert_eq!(hash, [0u8; 16]);
+    }
+
+    #[test]
+    fn test_base64_encode_capacity_includes_nul() {
+        let data = b"hello world";
+        // "aGVsbG8gd29ybGQ=" 为 16 个字符，还需 1 字节 NUL
+        let mut out_str = [0i8; 16];
+        let mut len: c_ulong = 0;
+
+        let result = cosign_base64_encode(data.as_ptr(), data.len() as c_ulong, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
+        assert_eq!(result, COSIGN_ERR_BUFFER_TOO_SMALL);
+        assert_eq!(len, 17);
+    }
+
+    #[test]
+    fn test_sign_session_single_use() {
+        let ctx = cosign_context_new();
+        let mut d1 = [0u8; 32];
+        let mut d1_len: c_ulong = 0;
+        cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut d1_len);
+
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        let result = cosign_sign_begin(ctx, q1.as_mut_ptr(), q1.len() as c_ulong, &mut q1_len, &mut session);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(q1_len, 64);
+
+        let server_part = [0x11u8; 32];
+        let mut r = [0u8; 32];
+        let mut r_len: c_ulong = 0;
+        let mut s = [0u8; 32];
+        let mut s_len: c_ulong = 0;
+
+        let finish = |r: &mut [u8; 32], r_len: &mut c_ulong, s: &mut [u8; 32], s_len: &mut c_ulong| {
+            cosign_sign_finish(
+                ctx, session, d1.as_ptr(), d1_len,
+                server_part.as_ptr(), 32, server_part.as_ptr(), 32, server_part.as_ptr(), 32,
+                r.as_mut_ptr(), 32, r_len, s.as_mut_ptr(), 32, s_len,
+            )
+        };
+        assert_eq!(finish(&mut r, &mut r_len, &mut s, &mut s_len), COSIGN_OK);
+        // 会话已被消耗，不能再次使用同一个 k1
+        assert_eq!(finish(&mut r, &mut r_len, &mut s, &mut s_len), COSIGN_ERR_SESSION_EXPIRED);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_sign_session_abort() {
+        let ctx = cosign_context_new();
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        cosign_sign_begin(ctx, q1.as_mut_ptr(), q1.len() as c_ulong, &mut q1_len, &mut session);
+
+        assert_eq!(cosign_sign_abort(ctx, session), COSIGN_OK);
+        assert_eq!(cosign_sign_abort(ctx, session), COSIGN_ERR_SESSION_EXPIRED);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_secure_zero() {
+        let mut secret = [0xa5u8; 32];
+        let result = cosign_secure_zero(secret.as_mut_ptr(), secret.len() as c_ulong);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(secret, [0u8; 32]);
+
+        assert_eq!(cosign_secure_zero(ptr::null_mut(), 32), COSIGN_ERR_NULL_PTR);
+    }
+
+    #[test]
+    fn test_signature_der_roundtrip() {
+        let mut raw = [0u8; 64];
+        raw[0] = 0x80;
+        raw[63] = 0x01;
+
+        let mut der = [0u8; 72];
+        let mut der_len: c_ulong = 0;
+        let result = cosign_signature_to_der(raw.as_ptr(), 64, der.as_mut_ptr(), der.len() as c_ulong, &mut der_len);
+        assert_eq!(result, COSIGN_OK);
+
+        let mut decoded = [0u8; 64];
+        let mut decoded_len: c_ulong = 0;
+        let result = cosign_signature_from_der(der.as_ptr(), der_len, decoded.as_mut_ptr(), decoded.len() as c_ulong, &mut decoded_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(decoded_len, 64);
+        assert_eq!(decoded, raw);
+    }
+
+    #[test]
+    fn test_hex_and_base64url() {
+        let data = [0xfbu8, 0xff, 0xfe];
+
+        let mut out_str = [0i8; 16];
+        let mut len: c_ulong = 0;
+        let result = cosign_hex_encode(data.as_ptr(), 3, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(len, 6);
+        let encoded = unsafe { CStr::from_ptr(out_str.as_ptr()) };
+        assert_eq!(encoded.to_str().unwrap(), "fbfffe");
+
+        let mut decoded = [0u8; 16];
+        let mut decoded_len: c_ulong = 0;
+        let result = cosign_hex_decode(out_str.as_ptr(), decoded.as_mut_ptr(), decoded.len() as c_ulong, &mut decoded_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(&decoded[..decoded_len as usize], &data);
+
+        let result = cosign_base64url_encode(data.as_ptr(), 3, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        let encoded = unsafe { CStr::from_ptr(out_str.as_ptr()) };
+        assert_eq!(encoded.to_str().unwrap(), "-__-");
+
+        let result = cosign_base64url_decode(out_str.as_ptr(), decoded.as_mut_ptr(), decoded.len() as c_ulong, &mut decoded_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(&decoded[..decoded_len as usize], &data);
+    }
+
+    #[test]
+    fn test_encode_with_constant() {
+        let data = [0xfbu8, 0xff, 0xfe];
+        let mut out_str = [0i8; 16];
+        let mut len: c_ulong = 0;
+        let mut decoded = [0u8; 16];
+        let mut decoded_len: c_ulong = 0;
+
+        for (encoding, expected) in [
+            (COSIGN_ENCODING_HEX, "fbfffe"),
+            (COSIGN_ENCODING_BASE64, "+//+"),
+            (COSIGN_ENCODING_BASE64URL, "-__-"),
+        ] {
+            let result = cosign_encode(encoding, data.as_ptr(), 3, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
+            assert_eq!(result, COSIGN_OK);
+            let encoded = unsafe { CStr::from_ptr(out_str.as_ptr()) };
+            assert_eq!(encoded.to_str().unwrap(), expected);
+
+            let result = cosign_decode(encoding, out_str.as_ptr(), decoded.as_mut_ptr(), decoded.len() as c_ulong, &mut decoded_len);
+            assert_eq!(result, COSIGN_OK);
+            assert_eq!(&decoded[..decoded_len as usize], &data);
+        }
+
+        let result = cosign_encode(3, data.as_ptr(), 3, out_str.as_mut_ptr(), out_str.len() as c_ulong, &mut len);
+        assert_eq!(result, COSIGN_ERR_INVALID_PARAM);
+    }
+
+    #[test]
+    fn test_sm4_cbc_and_gcm() {
+        let key = [0x01u8; 16];
+        let cbc_iv = [0x02u8; 16];
+        let gcm_iv = [0x03u8; 12];
+        let aad = b"header";
+        let message = b"hello world";
+
+        let mut ciphertext = [0u8; 64];
+        let mut cipher_len: c_ulong = 0;
+        let mut plaintext = [0u8; 64];
+        let mut plain_len: c_ulong = 0;
+
+        let result = cosign_sm4_cbc_encrypt(key.as_ptr(), 16, cbc_iv.as_ptr(), 16, message.as_ptr(), message.len() as c_ulong, ciphertext.as_mut_ptr(), ciphertext.len() as c_ulong, &mut cipher_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(cipher_len, 16);
+        let result = cosign_sm4_cbc_decrypt(key.as_ptr(), 16, cbc_iv.as_ptr(), 16, ciphertext.as_ptr(), cipher_len, plaintext.as_mut_ptr(), plaintext.len() as c_ulong, &mut plain_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(&plaintext[..plain_len as usize], message);
+
+        let result = cosign_sm4_gcm_encrypt(key.as_ptr(), 16, gcm_iv.as_ptr(), 12, aad.as_ptr(), aad.len() as c_ulong, message.as_ptr(), message.len() as c_ulong, ciphertext.as_mut_ptr(), ciphertext.len() as c_ulong, &mut cipher_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(cipher_len, message.len() as c_ulong + 16);
+        let result = cosign_sm4_gcm_decrypt(key.as_ptr(), 16, gcm_iv.as_ptr(), 12, aad.as_ptr(), aad.len() as c_ulong, ciphertext.as_ptr(), cipher_len, plaintext.as_mut_ptr(), plaintext.len() as c_ulong, &mut plain_len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(&plaintext[..plain_len as usize], message);
+
+        // AAD 不一致时认证失败
+        let result = cosign_sm4_gcm_decrypt(key.as_ptr(), 16, gcm_iv.as_ptr(), 12, ptr::null(), 0, ciphertext.as_ptr(), cipher_len, plaintext.as_mut_ptr(), plaintext.len() as c_ulong, &mut plain_len);
+        assert_eq!(result, COSIGN_ERR_CRYPTO);
+    }
+
+    #[test]
+    fn test_hash_message_with_uid() {
+        let ctx = cosign_context_new();
+        let mut d1 = [0u8; 32];
+        let mut d1_len: c_ulong = 0;
+        cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut d1_len);
+        let mut p1 = [0u8; 64];
+        let mut p1_len: c_ulong = 0;
+        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), p1.len() as c_ulong, &mut p1_len);
+
+        let message = b"hello world";
+        let uid = protocol::DEFAULT_USER_ID;
+        let mut with_uid = [0u8; 32];
+        let mut with_default = [0u8; 32];
+        let mut len: c_ulong = 0;
+
+        let result = cosign_hash_message_with_uid(ctx, message.as_ptr(), message.len() as c_ulong, uid.as_ptr(), uid.len() as c_ulong, p1.as_ptr(), p1_len, with_uid.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        let result = cosign_hash_message_with_uid(ctx, message.as_ptr(), message.len() as c_ulong, ptr::null(), 0, p1.as_ptr(), p1_len, with_default.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(with_uid, with_default);
+
+        // _ex 版本与专用接口结果一致
+        let mut za = [0u8; 32];
+        let result = cosign_hash_message_ex(ctx, COSIGN_DIGEST_ZA, message.as_ptr(), message.len() as c_ulong, p1.as_ptr(), p1_len, za.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(za, with_default);
+        let mut prehashed = [0u8; 32];
+        let result = cosign_hash_message_ex(ctx, COSIGN_DIGEST_PREHASHED, za.as_ptr(), 32, ptr::null(), 0, prehashed.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(prehashed, za);
+
+        let result = cosign_hash_message_ex(ctx, COSIGN_DIGEST_PREHASHED, message.as_ptr(), message.len() as c_ulong, ptr::null(), 0, prehashed.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_ERR_INVALID_PARAM);
+        let result = cosign_hash_message_ex(ctx, 7, message.as_ptr(), message.len() as c_ulong, ptr::null(), 0, prehashed.as_mut_ptr(), 32, &mut len);
+        assert_eq!(result, COSIGN_ERR_INVALID_PARAM);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_strerror() {
+        for code in [COSIGN_OK, COSIGN_ERR_BUFFER_TOO_SMALL, COSIGN_ERR_SESSION_EXPIRED, -1000] {
+            let msg = unsafe { CStr::from_ptr(cosign_strerror(code)) };
+            assert!(!msg.to_bytes().is_empty());
+        }
+        let msg = unsafe { CStr::from_ptr(cosign_strerror(COSIGN_ERR_VERIFY_FAILED)) };
+        assert_eq!(msg.to_str().unwrap(), "Signature verification failed");
+    }
+
+    #[test]
+    fn test_panic_caught_at_boundary() {
+        assert_eq!(ffi_guard(|| panic!("boom")), COSIGN_ERR_INTERNAL);
+        assert_eq!(ffi_guard(|| COSIGN_OK), COSIGN_OK);
+
+        // 持锁期间 panic 后上下文仍可继续使用
+        let ctx = cosign_context_new();
+        let shared = unsafe { &*ctx };
+        assert_eq!(
+            ffi_guard(|| {
+                let _sessions = shared.sessions();
+                panic!("boom while holding lock");
+            }),
+            COSIGN_ERR_INTERNAL
+        );
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        assert_eq!(cosign_sign_begin(ctx, q1.as_mut_ptr(), 64, &mut q1_len, &mut session), COSIGN_OK);
+        assert_eq!(cosign_sign_abort(ctx, session), COSIGN_OK);
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_verify_failed_code() {
+        let ctx = cosign_context_new();
+        let mut d1 = [0u8; 32];
+        let mut d1_len: c_ulong = 0;
+        cosign_generate_d1(ctx, d1.as_mut_ptr(), d1.len() as c_ulong, &mut d1_len);
+        let mut p1 = [0u8; 64];
+        let mut p1_len: c_ulong = 0;
+        cosign_calculate_p1(ctx, d1.as_ptr(), d1_len, p1.as_mut_ptr(), p1.len() as c_ulong, &mut p1_len);
+
+        let message = b"hello world";
+        let mut signature = [0u8; 64];
+        let mut sig_len: c_ulong = 0;
+        cosign_sm2_sign(d1.as_ptr(), d1_len, message.as_ptr(), message.len() as c_ulong, signature.as_mut_ptr(), signature.len() as c_ulong, &mut sig_len);
+        signature[0] ^= 0xff;
+
+        let result = cosign_sm2_verify(p1.as_ptr(), p1_len, message.as_ptr(), message.len() as c_ulong, signature.as_ptr(), sig_len);
+        assert_eq!(result, COSIGN_ERR_VERIFY_FAILED);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_invalid_point_code() {
+        let ctx = cosign_context_new();
+        let d1 = [0x01u8; 32];
+        // (1, 1) 不在曲线上
+        let mut c1 = [0u8; 64];
+        c1[31] = 1;
+        c1[63] = 1;
+        let mut t1 = [0u8; 64];
+        let mut t1_len: c_ulong = 0;
+
+        let result = cosign_decrypt_prepare(ctx, d1.as_ptr(), 32, c1.as_ptr(), 64, t1.as_mut_ptr(), t1.len() as c_ulong, &mut t1_len);
+        assert_eq!(result, COSIGN_ERR_INVALID_POINT);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_context_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CoSignContext>();
+    }
+
+    #[test]
+    fn test_context_shared_across_threads() {
+        struct SharedCtx(*mut CoSignContext);
+        // Safety: CoSignContext 内部同步，见模块文档
+        unsafe impl Send for SharedCtx {}
+        unsafe impl Sync for SharedCtx {}
+        impl SharedCtx {
+            // Reason: 通过方法访问，避免闭包按字段捕获裸指针
+            fn get(&self) -> *mut CoSignContext {
+                self.0
+            }
+        }
+
+        let ctx = SharedCtx(cosign_context_new());
+        let clone = cosign_context_clone(ctx.0);
+        assert!(!clone.is_null());
+        assert_ne!(clone, ctx.0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut q1 = [0u8; 64];
+                    let mut q1_len: c_ulong = 0;
+                    let mut session: u64 = 0;
+                    let result = cosign_sign_begin(ctx.get(), q1.as_mut_ptr(), 64, &mut q1_len, &mut session);
+                    assert_eq!(result, COSIGN_OK);
+                    assert_eq!(cosign_sign_abort(ctx.get(), session), COSIGN_OK);
+                });
+            }
+        });
+
+        cosign_context_free(clone);
+        cosign_context_free(ctx.0);
+    }
+
+    #[test]
+    fn test_structured_api() {
+        let ctx = cosign_context_new();
+        let mut keypair = cosign_keypair_t { d1: [0u8; 32], p1: [0u8; 64] };
+        assert_eq!(cosign_keypair_generate(ctx, &mut keypair), COSIGN_OK);
+        assert_ne!(keypair.p1, [0u8; 64]);
+
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        cosign_sign_begin(ctx, q1.as_mut_ptr(), 64, &mut q1_len, &mut session);
+
+        let response = cosign_sign_response_t { r: [0x11u8; 32], s2: [0x22u8; 32], s3: [0x33u8; 32] };
+        let mut signature = cosign_signature_t { r: [0u8; 32], s: [0u8; 32] };
+        let result = cosign_sign_finish_ex(ctx, session, keypair.d1.as_ptr(), 32, &response, &mut signature);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(signature.r, response.r);
+
+        cosign_context_free(ctx);
+    }
+
+    #[test]
+    fn test_ciphertext_split() {
+        let (_, public_key) = CoSignProtocol::generate_keypair();
+        let ciphertext = CoSignProtocol::encrypt(&public_key, b"c2 data").unwrap();
+
+        let mut parts = cosign_ciphertext_parts_t { c1: [0u8; 64], c3: [0u8; 32], c2: ptr::null(), c2_len: 0 };
+        let result = cosign_ciphertext_split(ciphertext.as_ptr(), ciphertext.len() as c_ulong, &mut parts);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!(parts.c1, ciphertext[1..65]);
+        assert_eq!(parts.c3, ciphertext[65..97]);
+        let c2 = unsafe { slice::from_raw_parts(parts.c2, parts.c2_len as usize) };
+        assert_eq!(c2, &ciphertext[97..]);
+
+        let result = cosign_ciphertext_split(ciphertext.as_ptr(), 96, &mut parts);
+        assert_eq!(result, COSIGN_ERR_INVALID_PARAM);
+
+        // C1C2C3 顺序与 DER 编码得到相同分量
+        let parsed = Sm2Ciphertext::parse(&ciphertext).unwrap();
+        let c1c2c3 = parsed.to_bytes(CiphertextLayout::C1C2C3);
+        let mut other = cosign_ciphertext_parts_t { c1: [0u8; 64], c3: [0u8; 32], c2: ptr::null(), c2_len: 0 };
+        let result = cosign_ciphertext_split_ex(c1c2c3.as_ptr(), c1c2c3.len() as c_ulong, COSIGN_LAYOUT_C1C2C3, &mut other);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!((other.c1, other.c3, other.c2_len), (parts.c1, parts.c3, parts.c2_len));
+
+        let der = parsed.to_der();
+        let result = cosign_ciphertext_split(der.as_ptr(), der.len() as c_ulong, &mut other);
+        assert_eq!(result, COSIGN_OK);
+        assert_eq!((other.c1, other.c3, other.c2_len), (parts.c1, parts.c3, parts.c2_len));
+
+        let result = cosign_ciphertext_split_ex(ciphertext.as_ptr(), ciphertext.len() as c_ulong, 7, &mut other);
+        assert_eq!(result, COSIGN_ERR_INVALID_PARAM);
+    }
+
+    #[test]
+    fn test_context_export_import() {
+        let ctx = cosign_context_new();
+        let key = [0x5au8; 16];
+
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        cosign_sign_begin(ctx, q1.as_mut_ptr(), 64, &mut q1_len, &mut session);
+        let (_, p1) = CoSignProtocol::generate_keypair();
+        unsafe { (*ctx).protocol.za(b"alice", &p1).unwrap() };
+
+        let mut blob = [0u8; 256];
+        let mut blob_len: c_ulong = 0;
+        let result = cosign_context_export(ctx, key.as_ptr(), 16, blob.as_mut_ptr(), blob.len() as c_ulong, &mut blob_len);
+        assert_eq!(result, COSIGN_OK);
+        cosign_context_free(ctx);
+
+        // 错误密钥无法导入
+        let wrong_key = [0u8; 16];
+        let mut restored: *mut CoSignContext = ptr::null_mut();
+        let result = cosign_context_import(blob.as_ptr(), blob_len, wrong_key.as_ptr(), 16, &mut restored);
+        assert_eq!(result, COSIGN_ERR_CRYPTO);
+        assert!(restored.is_null());
+
+        let result = cosign_context_import(blob.as_ptr(), blob_len, key.as_ptr(), 16, &mut restored);
+        assert_eq!(result, COSIGN_OK);
+
+        // ZA 缓存随导出恢复
+        let (uid, public_key) = unsafe { (*restored).protocol.cached_za_input().unwrap() };
+        assert_eq!((&uid[..], &public_key[..]), (&b"alice"[..], &p1[..]));
+
+        // 导出数据不含 k1，挂起前的会话在恢复后失效，重复导入也无法重复使用 k1
+        let d1 = [0x01u8; 32];
+        let response = cosign_sign_response_t { r: [0x11u8; 32], s2: [0x22u8; 32], s3: [0x33u8; 32] };
+        let mut signature = cosign_signature_t { r: [0u8; 32], s: [0u8; 32] };
+        let result = cosign_sign_finish_ex(restored, session, d1.as_ptr(), 32, &response, &mut signature);
+        assert_eq!(result, COSIGN_ERR_SESSION_EXPIRED);
+
+        // 新会话 ID 不与恢复的会话冲突
+        let mut next_session: u64 = 0;
+        cosign_sign_begin(restored, q1.as_mut_ptr(), 64, &mut q1_len, &mut next_session);
+        assert!(next_session > session);
+        cosign_context_free(restored);
+
+        // 不支持的版本
+        blob[4] = CONTEXT_BLOB_VERSION + 1;
+        let mut stale: *mut CoSignContext = ptr::null_mut();
+        let result = cosign_context_import(blob.as_ptr(), blob_len, key.as_ptr(), 16, &mut stale);
+        assert_eq!(result, COSIGN_ERR_ENCODING);
+        assert!(stale.is_null());
+    }
+
+    #[test]
+    fn test_cosign_message_rejects_bad_response() {
+        let ctx = cosign_context_new();
+        let mut keypair = cosign_keypair_t { d1: [0u8; 32], p1: [0u8; 64] };
+        assert_eq!(cosign_keypair_generate(ctx, &mut keypair), COSIGN_OK);
+
+        let mut q1 = [0u8; 64];
+        let mut q1_len: c_ulong = 0;
+        let mut session: u64 = 0;
+        cosign_sign_begin(ctx, q1.as_mut_ptr(), 64, &mut q1_len, &mut session);
+
+        // 伪造的服务端响应无法通过本地验签
+        let message = b"hello world";
+        let response = cosign_sign_response_t { r: [0x11u8; 32], s2: [0x22u8; 32], s3: [0x33u8; 32] };
+        let mut signature = cosign_signature_t { r: [0u8; 32], s: [0u8; 32] };
+        let result = cosign_cosign_message(
+            ctx,
+            session,
+            keypair.d1.as_ptr(),
+            32,
+            message.as_ptr(),
+            message.len() as c_ulong,
+            ptr::null(),
+            0,
+            keypair.p1.as_ptr(),
+            64,
+            &response,
+            &mut signature,
+        );
+        assert_eq!(result, COSIGN_ERR_VERIFY_FAILED);
+        assert_eq!(signature.r, [0u8; 32]);
+
+        // 会话已被消耗
+        assert_eq!(cosign_sign_abort(ctx, session), COSIGN_ERR_SESSION_EXPIRED);
+        cosign_context_free(ctx);
+    }
 }
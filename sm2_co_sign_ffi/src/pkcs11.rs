@@ -0,0 +1,1415 @@
+//! PKCS#11（Cryptoki 2.40）模块
+//!
+//! 启用 `pkcs11` feature 后，动态库额外导出 `C_GetFunctionList`，可作为 PKCS#11 模块被浏览器（NSS）、
+//! Java SunPKCS11、OpenSC 工具等加载。模块只有一个插槽与一个令牌，令牌中的对象为：
+//!
+//! - 协同私钥（`CKO_PRIVATE_KEY`，`CKK_EC`，曲线 OID 为 SM2）：登录后可见，支持 `C_Sign` 与 `C_Decrypt`，
+//!   运算经 [`CoSignClient`] 与服务端协同完成，D1 不出模块
+//! - 协同公钥（`CKO_PUBLIC_KEY`）
+//! - 用户证书（`CKO_CERTIFICATE`，可选）
+//!
+//! 令牌只读：不支持创建、修改、删除对象与生成密钥。`C_Login` 的 PIN 即协同签名服务的登录密码。
+//!
+//! # 配置
+//!
+//! PKCS#11 宿主无法向模块传递参数，配置从环境变量读取（`C_Initialize` 时）：
+//!
+//! | 环境变量 | 说明 |
+//! |----------|------|
+//! | `SM2_COSIGN_PKCS11_SERVER` | 服务端地址（必填） |
+//! | `SM2_COSIGN_PKCS11_USERNAME` | 登录用户名（必填） |
+//! | `SM2_COSIGN_PKCS11_D1` | 未加密的 D1 文件，格式见 `KeyPair::from_files`（必填） |
+//! | `SM2_COSIGN_PKCS11_PUBLIC_KEY` | 协同公钥文件（必填） |
+//! | `SM2_COSIGN_PKCS11_USER_ID` | 用户 ID 文件（必填） |
+//! | `SM2_COSIGN_PKCS11_CERT` | 用户证书（PEM 或 DER，可选） |
+//! | `SM2_COSIGN_PKCS11_LABEL` | 令牌与对象标签（可选，默认 `SM2 Co-Sign`） |
+//!
+//! # 机制
+//!
+//! | 机制 | 运算 | 输入 |
+//! |------|------|------|
+//! | `CKM_ECDSA` | 签名 | 32 字节 e（如 SM3(ZA ‖ M)），输出 64 字节 r‖s |
+//! | [`CKM_SM2_SIGN`] | 签名 | 32 字节 e，同 `CKM_ECDSA` |
+//! | [`CKM_SM3_SM2`] | 签名（可分段） | 原始消息，按默认用户标识计算 e = SM3(ZA ‖ M) |
+//! | [`CKM_SM2_ENCRYPT`] | 解密 | C1C3C2 或 ASN.1 DER 密文 |
+//!
+//! SM2 机制在 PKCS#11 标准中没有编号，`CKM_SM2_*` 使用厂商自定义区间（`CKM_VENDOR_DEFINED` 起）。
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::collections::HashMap;
+use std::ffi::{c_uchar, c_ulong, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use sm2_co_sign_core::{
+    asn1, pem, ClientConfig, CoSignClient, CoSignProtocol, DigestMode, Error, KeyPair, Sm2Ciphertext,
+};
+use tokio::runtime::Runtime;
+use tracing::warn;
+use zeroize::Zeroizing;
+
+pub type CK_BYTE = c_uchar;
+pub type CK_BBOOL = CK_BYTE;
+pub type CK_ULONG = c_ulong;
+pub type CK_RV = CK_ULONG;
+pub type CK_FLAGS = CK_ULONG;
+pub type CK_SLOT_ID = CK_ULONG;
+pub type CK_SESSION_HANDLE = CK_ULONG;
+pub type CK_OBJECT_HANDLE = CK_ULONG;
+pub type CK_USER_TYPE = CK_ULONG;
+pub type CK_STATE = CK_ULONG;
+pub type CK_MECHANISM_TYPE = CK_ULONG;
+pub type CK_ATTRIBUTE_TYPE = CK_ULONG;
+pub type CK_OBJECT_CLASS = CK_ULONG;
+pub type CK_NOTIFY = Option<unsafe extern "C" fn(CK_SESSION_HANDLE, CK_ULONG, *mut c_void) -> CK_RV>;
+
+/// Cryptoki 版本
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct CK_VERSION {
+    pub major: CK_BYTE,
+    pub minor: CK_BYTE,
+}
+
+// Reason: PKCS#11 要求 Windows 上的结构体按 1 字节对齐，其他平台使用默认对齐
+macro_rules! ck_struct {
+    ($(#[$meta:meta])* pub struct $name:ident { $($field:ident: $ty:ty,)* }) => {
+        $(#[$meta])*
+        #[cfg_attr(windows, repr(C, packed(1)))]
+        #[cfg_attr(not(windows), repr(C))]
+        pub struct $name {
+            $(pub $field: $ty,)*
+        }
+    };
+}
+
+ck_struct! {
+    /// `C_GetInfo` 输出
+    pub struct CK_INFO {
+        cryptokiVersion: CK_VERSION,
+        manufacturerID: [CK_BYTE; 32],
+        flags: CK_FLAGS,
+        libraryDescription: [CK_BYTE; 32],
+        libraryVersion: CK_VERSION,
+    }
+}
+
+ck_struct! {
+    /// `C_GetSlotInfo` 输出
+    pub struct CK_SLOT_INFO {
+        slotDescription: [CK_BYTE; 64],
+        manufacturerID: [CK_BYTE; 32],
+        flags: CK_FLAGS,
+        hardwareVersion: CK_VERSION,
+        firmwareVersion: CK_VERSION,
+    }
+}
+
+ck_struct! {
+    /// `C_GetTokenInfo` 输出
+    pub struct CK_TOKEN_INFO {
+        label: [CK_BYTE; 32],
+        manufacturerID: [CK_BYTE; 32],
+        model: [CK_BYTE; 16],
+        serialNumber: [CK_BYTE; 16],
+        flags: CK_FLAGS,
+        ulMaxSessionCount: CK_ULONG,
+        ulSessionCount: CK_ULONG,
+        ulMaxRwSessionCount: CK_ULONG,
+        ulRwSessionCount: CK_ULONG,
+        ulMaxPinLen: CK_ULONG,
+        ulMinPinLen: CK_ULONG,
+        ulTotalPublicMemory: CK_ULONG,
+        ulFreePublicMemory: CK_ULONG,
+        ulTotalPrivateMemory: CK_ULONG,
+        ulFreePrivateMemory: CK_ULONG,
+        hardwareVersion: CK_VERSION,
+        firmwareVersion: CK_VERSION,
+        utcTime: [CK_BYTE; 16],
+    }
+}
+
+ck_struct! {
+    /// `C_GetSessionInfo` 输出
+    pub struct CK_SESSION_INFO {
+        slotID: CK_SLOT_ID,
+        state: CK_STATE,
+        flags: CK_FLAGS,
+        ulDeviceError: CK_ULONG,
+    }
+}
+
+ck_struct! {
+    /// `C_GetMechanismInfo` 输出
+    pub struct CK_MECHANISM_INFO {
+        ulMinKeySize: CK_ULONG,
+        ulMaxKeySize: CK_ULONG,
+        flags: CK_FLAGS,
+    }
+}
+
+ck_struct! {
+    /// 机制与参数
+    pub struct CK_MECHANISM {
+        mechanism: CK_MECHANISM_TYPE,
+        pParameter: *mut c_void,
+        ulParameterLen: CK_ULONG,
+    }
+}
+
+ck_struct! {
+    /// 属性模板项
+    pub struct CK_ATTRIBUTE {
+        type_: CK_ATTRIBUTE_TYPE,
+        pValue: *mut c_void,
+        ulValueLen: CK_ULONG,
+    }
+}
+
+ck_struct! {
+    /// `C_Initialize` 参数
+    pub struct CK_C_INITIALIZE_ARGS {
+        CreateMutex: *mut c_void,
+        DestroyMutex: *mut c_void,
+        LockMutex: *mut c_void,
+        UnlockMutex: *mut c_void,
+        flags: CK_FLAGS,
+        pReserved: *mut c_void,
+    }
+}
+
+// 返回值
+pub const CKR_OK: CK_RV = 0x00;
+pub const CKR_SLOT_ID_INVALID: CK_RV = 0x03;
+pub const CKR_GENERAL_ERROR: CK_RV = 0x05;
+pub const CKR_FUNCTION_FAILED: CK_RV = 0x06;
+pub const CKR_ARGUMENTS_BAD: CK_RV = 0x07;
+pub const CKR_ATTRIBUTE_SENSITIVE: CK_RV = 0x11;
+pub const CKR_ATTRIBUTE_TYPE_INVALID: CK_RV = 0x12;
+pub const CKR_DATA_INVALID: CK_RV = 0x20;
+pub const CKR_DATA_LEN_RANGE: CK_RV = 0x21;
+pub const CKR_DEVICE_ERROR: CK_RV = 0x30;
+pub const CKR_ENCRYPTED_DATA_INVALID: CK_RV = 0x40;
+pub const CKR_FUNCTION_NOT_SUPPORTED: CK_RV = 0x54;
+pub const CKR_KEY_HANDLE_INVALID: CK_RV = 0x60;
+pub const CKR_KEY_FUNCTION_NOT_PERMITTED: CK_RV = 0x68;
+pub const CKR_MECHANISM_INVALID: CK_RV = 0x70;
+pub const CKR_OBJECT_HANDLE_INVALID: CK_RV = 0x82;
+pub const CKR_OPERATION_ACTIVE: CK_RV = 0x90;
+pub const CKR_OPERATION_NOT_INITIALIZED: CK_RV = 0x91;
+pub const CKR_PIN_INCORRECT: CK_RV = 0xA0;
+pub const CKR_SESSION_HANDLE_INVALID: CK_RV = 0xB3;
+pub const CKR_SESSION_PARALLEL_NOT_SUPPORTED: CK_RV = 0xB4;
+pub const CKR_TOKEN_WRITE_PROTECTED: CK_RV = 0xE2;
+pub const CKR_USER_ALREADY_LOGGED_IN: CK_RV = 0x100;
+pub const CKR_USER_NOT_LOGGED_IN: CK_RV = 0x101;
+pub const CKR_USER_TYPE_INVALID: CK_RV = 0x103;
+pub const CKR_BUFFER_TOO_SMALL: CK_RV = 0x150;
+pub const CKR_CRYPTOKI_NOT_INITIALIZED: CK_RV = 0x190;
+pub const CKR_CRYPTOKI_ALREADY_INITIALIZED: CK_RV = 0x191;
+
+// 标志位
+pub const CKF_TOKEN_PRESENT: CK_FLAGS = 0x01;
+pub const CKF_RNG: CK_FLAGS = 0x01;
+pub const CKF_WRITE_PROTECTED: CK_FLAGS = 0x02;
+pub const CKF_LOGIN_REQUIRED: CK_FLAGS = 0x04;
+pub const CKF_USER_PIN_INITIALIZED: CK_FLAGS = 0x08;
+pub const CKF_TOKEN_INITIALIZED: CK_FLAGS = 0x400;
+pub const CKF_RW_SESSION: CK_FLAGS = 0x02;
+pub const CKF_SERIAL_SESSION: CK_FLAGS = 0x04;
+pub const CKF_DECRYPT: CK_FLAGS = 0x200;
+pub const CKF_SIGN: CK_FLAGS = 0x800;
+pub const CKF_EC_F_P: CK_FLAGS = 0x0010_0000;
+pub const CKF_EC_NAMEDCURVE: CK_FLAGS = 0x0080_0000;
+pub const CKF_EC_UNCOMPRESS: CK_FLAGS = 0x0100_0000;
+
+// 用户类型与会话状态
+pub const CKU_SO: CK_USER_TYPE = 0;
+pub const CKU_USER: CK_USER_TYPE = 1;
+pub const CKS_RO_PUBLIC_SESSION: CK_STATE = 0;
+pub const CKS_RO_USER_FUNCTIONS: CK_STATE = 1;
+pub const CKS_RW_PUBLIC_SESSION: CK_STATE = 2;
+pub const CKS_RW_USER_FUNCTIONS: CK_STATE = 3;
+
+// 对象类别、密钥类型与属性
+pub const CKO_CERTIFICATE: CK_OBJECT_CLASS = 1;
+pub const CKO_PUBLIC_KEY: CK_OBJECT_CLASS = 2;
+pub const CKO_PRIVATE_KEY: CK_OBJECT_CLASS = 3;
+pub const CKK_EC: CK_ULONG = 3;
+pub const CKC_X_509: CK_ULONG = 0;
+pub const CKA_CLASS: CK_ATTRIBUTE_TYPE = 0x00;
+pub const CKA_TOKEN: CK_ATTRIBUTE_TYPE = 0x01;
+pub const CKA_PRIVATE: CK_ATTRIBUTE_TYPE = 0x02;
+pub const CKA_LABEL: CK_ATTRIBUTE_TYPE = 0x03;
+pub const CKA_VALUE: CK_ATTRIBUTE_TYPE = 0x11;
+pub const CKA_CERTIFICATE_TYPE: CK_ATTRIBUTE_TYPE = 0x80;
+pub const CKA_KEY_TYPE: CK_ATTRIBUTE_TYPE = 0x100;
+pub const CKA_ID: CK_ATTRIBUTE_TYPE = 0x102;
+pub const CKA_SENSITIVE: CK_ATTRIBUTE_TYPE = 0x103;
+pub const CKA_ENCRYPT: CK_ATTRIBUTE_TYPE = 0x104;
+pub const CKA_DECRYPT: CK_ATTRIBUTE_TYPE = 0x105;
+pub const CKA_SIGN: CK_ATTRIBUTE_TYPE = 0x108;
+pub const CKA_VERIFY: CK_ATTRIBUTE_TYPE = 0x10A;
+pub const CKA_EXTRACTABLE: CK_ATTRIBUTE_TYPE = 0x162;
+pub const CKA_LOCAL: CK_ATTRIBUTE_TYPE = 0x163;
+pub const CKA_NEVER_EXTRACTABLE: CK_ATTRIBUTE_TYPE = 0x164;
+pub const CKA_ALWAYS_SENSITIVE: CK_ATTRIBUTE_TYPE = 0x165;
+pub const CKA_MODIFIABLE: CK_ATTRIBUTE_TYPE = 0x170;
+pub const CKA_EC_PARAMS: CK_ATTRIBUTE_TYPE = 0x180;
+pub const CKA_EC_POINT: CK_ATTRIBUTE_TYPE = 0x181;
+pub const CK_UNAVAILABLE_INFORMATION: CK_ULONG = !0;
+
+// 机制
+pub const CKM_ECDSA: CK_MECHANISM_TYPE = 0x1041;
+pub const CKM_VENDOR_DEFINED: CK_MECHANISM_TYPE = 0x8000_0000;
+/// SM2 签名，输入为 32 字节 e（厂商自定义）
+pub const CKM_SM2_SIGN: CK_MECHANISM_TYPE = CKM_VENDOR_DEFINED + 0x0001_0001;
+/// SM3withSM2 签名，输入为原始消息（厂商自定义）
+pub const CKM_SM3_SM2: CK_MECHANISM_TYPE = CKM_VENDOR_DEFINED + 0x0001_0002;
+/// SM2 加解密（厂商自定义）
+pub const CKM_SM2_ENCRYPT: CK_MECHANISM_TYPE = CKM_VENDOR_DEFINED + 0x0001_0003;
+
+/// 唯一的插槽
+const SLOT_ID: CK_SLOT_ID = 1;
+/// 协同私钥对象句柄
+const PRIVATE_KEY_HANDLE: CK_OBJECT_HANDLE = 1;
+/// 协同公钥对象句柄
+const PUBLIC_KEY_HANDLE: CK_OBJECT_HANDLE = 2;
+/// 用户证书对象句柄
+const CERTIFICATE_HANDLE: CK_OBJECT_HANDLE = 3;
+/// 默认令牌与对象标签
+const DEFAULT_LABEL: &str = "SM2 Co-Sign";
+/// 模块支持的机制
+const MECHANISMS: [CK_MECHANISM_TYPE; 4] = [CKM_ECDSA, CKM_SM2_SIGN, CKM_SM3_SM2, CKM_SM2_ENCRYPT];
+/// SM2 签名长度（r‖s）
+const SIGNATURE_LEN: usize = 64;
+
+const CK_TRUE: CK_BBOOL = 1;
+const CK_FALSE: CK_BBOOL = 0;
+
+/// 从环境变量读取的模块配置
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    pub server_url: String,
+    pub username: String,
+    pub d1_path: String,
+    pub public_key_path: String,
+    pub user_id_path: String,
+    pub certificate_path: Option<String>,
+    pub label: String,
+}
+
+impl Pkcs11Config {
+    /// 读取 `SM2_COSIGN_PKCS11_*` 环境变量，缺少必填项时返回 `Error::InvalidParam`
+    pub fn from_env() -> Result<Self, Error> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        let required = |name: &str| var(name).ok_or_else(|| Error::InvalidParam(format!("{} is not set", name)));
+        Ok(Self {
+            server_url: required("SM2_COSIGN_PKCS11_SERVER")?,
+            username: required("SM2_COSIGN_PKCS11_USERNAME")?,
+            d1_path: required("SM2_COSIGN_PKCS11_D1")?,
+            public_key_path: required("SM2_COSIGN_PKCS11_PUBLIC_KEY")?,
+            user_id_path: required("SM2_COSIGN_PKCS11_USER_ID")?,
+            certificate_path: var("SM2_COSIGN_PKCS11_CERT"),
+            label: var("SM2_COSIGN_PKCS11_LABEL").unwrap_or_else(|| DEFAULT_LABEL.to_string()),
+        })
+    }
+}
+
+/// 令牌中的对象（公开属性在初始化时计算）
+struct TokenObjects {
+    label: String,
+    /// CKA_ID：SM3(公钥) 的前 20 字节，私钥、公钥与证书相同，便于宿主配对
+    id: Vec<u8>,
+    /// CKA_EC_PARAMS：SM2 曲线 OID 的 DER 编码
+    ec_params: Vec<u8>,
+    /// CKA_EC_POINT：DER OCTET STRING 包裹的 04‖x‖y
+    ec_point: Vec<u8>,
+    /// 用户证书 DER
+    certificate: Option<Vec<u8>>,
+}
+
+impl TokenObjects {
+    fn new(key_pair: &KeyPair, label: String, certificate: Option<Vec<u8>>) -> Self {
+        let mut point = vec![0x04];
+        point.extend_from_slice(key_pair.public_key.as_bytes());
+        Self {
+            label,
+            id: CoSignProtocol::sm3_hash(key_pair.public_key.as_bytes())[..20].to_vec(),
+            ec_params: asn1::encode_tlv(asn1::TAG_OID, asn1::OID_SM2),
+            ec_point: asn1::encode_tlv(asn1::TAG_OCTET_STRING, &point),
+            certificate,
+        }
+    }
+
+    /// 当前可见的对象句柄：私钥仅在登录后可见，证书仅在已配置时存在
+    fn handles(&self, logged_in: bool) -> Vec<CK_OBJECT_HANDLE> {
+        let mut handles = Vec::with_capacity(3);
+        if logged_in {
+            handles.push(PRIVATE_KEY_HANDLE);
+        }
+        handles.push(PUBLIC_KEY_HANDLE);
+        if self.certificate.is_some() {
+            handles.push(CERTIFICATE_HANDLE);
+        }
+        handles
+    }
+
+    /// 对象的属性值；`Err` 为属性不可读的原因
+    fn attribute(&self, handle: CK_OBJECT_HANDLE, kind: CK_ATTRIBUTE_TYPE) -> Result<Vec<u8>, CK_RV> {
+        let ulong = |value: CK_ULONG| value.to_ne_bytes().to_vec();
+        let flag = |value: bool| vec![if value { CK_TRUE } else { CK_FALSE }];
+        let common = match kind {
+            CKA_TOKEN => Some(flag(true)),
+            CKA_LABEL => Some(self.label.as_bytes().to_vec()),
+            CKA_MODIFIABLE => Some(flag(false)),
+            CKA_ID => Some(self.id.clone()),
+            _ => None,
+        };
+        if let Some(value) = common {
+            return Ok(value);
+        }
+        let value = match (handle, kind) {
+            (PRIVATE_KEY_HANDLE, CKA_CLASS) => ulong(CKO_PRIVATE_KEY),
+            (PRIVATE_KEY_HANDLE, CKA_PRIVATE) => flag(true),
+            (PRIVATE_KEY_HANDLE, CKA_KEY_TYPE) => ulong(CKK_EC),
+            (PRIVATE_KEY_HANDLE, CKA_SIGN | CKA_DECRYPT | CKA_SENSITIVE | CKA_ALWAYS_SENSITIVE) => flag(true),
+            (PRIVATE_KEY_HANDLE, CKA_NEVER_EXTRACTABLE | CKA_LOCAL) => flag(true),
+            (PRIVATE_KEY_HANDLE, CKA_EXTRACTABLE) => flag(false),
+            (PRIVATE_KEY_HANDLE, CKA_EC_PARAMS) => self.ec_params.clone(),
+            (PRIVATE_KEY_HANDLE, CKA_VALUE) => return Err(CKR_ATTRIBUTE_SENSITIVE),
+            (PUBLIC_KEY_HANDLE, CKA_CLASS) => ulong(CKO_PUBLIC_KEY),
+            (PUBLIC_KEY_HANDLE, CKA_PRIVATE) => flag(false),
+            (PUBLIC_KEY_HANDLE, CKA_KEY_TYPE) => ulong(CKK_EC),
+            (PUBLIC_KEY_HANDLE, CKA_VERIFY | CKA_ENCRYPT) => flag(true),
+            (PUBLIC_KEY_HANDLE, CKA_EC_PARAMS) => self.ec_params.clone(),
+            (PUBLIC_KEY_HANDLE, CKA_EC_POINT) => self.ec_point.clone(),
+            (CERTIFICATE_HANDLE, CKA_CLASS) => ulong(CKO_CERTIFICATE),
+            (CERTIFICATE_HANDLE, CKA_PRIVATE) => flag(false),
+            (CERTIFICATE_HANDLE, CKA_CERTIFICATE_TYPE) => ulong(CKC_X_509),
+            (CERTIFICATE_HANDLE, CKA_VALUE) => self.certificate.clone().ok_or(CKR_ATTRIBUTE_TYPE_INVALID)?,
+            _ => return Err(CKR_ATTRIBUTE_TYPE_INVALID),
+        };
+        Ok(value)
+    }
+
+    /// 对象是否与查找模板全部匹配
+    fn matches(&self, handle: CK_OBJECT_HANDLE, template: &[(CK_ATTRIBUTE_TYPE, Vec<u8>)]) -> bool {
+        template
+            .iter()
+            .all(|(kind, value)| self.attribute(handle, *kind).is_ok_and(|actual| actual == *value))
+    }
+}
+
+/// 进行中的签名或解密运算
+struct Operation {
+    mechanism: CK_MECHANISM_TYPE,
+    /// `C_SignUpdate` 累积的消息
+    data: Zeroizing<Vec<u8>>,
+}
+
+/// 会话状态
+#[derive(Default)]
+struct SessionState {
+    flags: CK_FLAGS,
+    find: Option<Vec<CK_OBJECT_HANDLE>>,
+    sign: Option<Operation>,
+    decrypt: Option<Operation>,
+}
+
+/// 已初始化的模块
+struct Module {
+    runtime: Runtime,
+    client: CoSignClient,
+    username: String,
+    objects: TokenObjects,
+    logged_in: AtomicBool,
+    sessions: Mutex<HashMap<CK_SESSION_HANDLE, SessionState>>,
+    next_session: Mutex<CK_SESSION_HANDLE>,
+}
+
+impl Module {
+    fn load(config: Pkcs11Config) -> Result<Self, Error> {
+        let key_pair = KeyPair::from_files(&config.d1_path, &config.public_key_path, &config.user_id_path)?;
+        let certificate = config.certificate_path.as_deref().map(read_certificate).transpose()?;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(Error::Io)?;
+        let client = CoSignClient::new(ClientConfig {
+            server_url: config.server_url,
+            ..ClientConfig::default()
+        })?;
+        runtime.block_on(client.set_key_pair(
+            key_pair.d1.to_vec(),
+            key_pair.public_key.to_vec(),
+            key_pair.user_id.clone(),
+        ))?;
+
+        Ok(Self {
+            runtime,
+            client,
+            username: config.username,
+            objects: TokenObjects::new(&key_pair, config.label, certificate),
+            logged_in: AtomicBool::new(false),
+            sessions: Mutex::new(HashMap::new()),
+            next_session: Mutex::new(1),
+        })
+    }
+
+    /// 会话表；持锁期间的 panic 已在入口捕获，忽略锁中毒
+    fn sessions(&self) -> MutexGuard<'_, HashMap<CK_SESSION_HANDLE, SessionState>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_logged_in(&self) -> bool {
+        self.logged_in.load(Ordering::SeqCst)
+    }
+}
+
+/// 读取证书文件（PEM 或 DER）
+fn read_certificate(path: &str) -> Result<Vec<u8>, Error> {
+    let data = std::fs::read(path)?;
+    match std::str::from_utf8(&data) {
+        Ok(text) if pem::contains(text, "CERTIFICATE") => pem::decode(text, "CERTIFICATE"),
+        _ => Ok(data),
+    }
+}
+
+/// 全局模块状态，`C_Initialize` 创建、`C_Finalize` 销毁
+static MODULE: RwLock<Option<Arc<Module>>> = RwLock::new(None);
+
+/// 当前模块；未初始化时返回 `CKR_CRYPTOKI_NOT_INITIALIZED`
+fn module() -> Result<Arc<Module>, CK_RV> {
+    MODULE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or(CKR_CRYPTOKI_NOT_INITIALIZED)
+}
+
+/// 将核心库错误映射为 PKCS#11 返回值
+fn error_rv(err: &Error) -> CK_RV {
+    match err {
+        Error::NotAuthenticated => CKR_USER_NOT_LOGGED_IN,
+        Error::InvalidParam(_) | Error::Encoding(_) | Error::InvalidPoint(_) => CKR_DATA_INVALID,
+        Error::Network(_) | Error::Transport { .. } | Error::Http { .. } | Error::Api { .. } => CKR_DEVICE_ERROR,
+        _ => CKR_FUNCTION_FAILED,
+    }
+}
+
+/// 执行入口主体并捕获 panic（panic 不能跨越 `extern "C"` 边界展开）
+fn guard<F: FnOnce() -> Result<(), CK_RV>>(f: F) -> CK_RV {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CKR_OK,
+        Ok(Err(rv)) => rv,
+        Err(_) => CKR_GENERAL_ERROR,
+    }
+}
+
+/// 以空格填充定长文本字段（PKCS#11 字符串不以 NUL 结尾）
+fn padded<const N: usize>(text: &str) -> [CK_BYTE; N] {
+    let mut out = [b' '; N];
+    let mut len = text.len().min(N);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    out[..len].copy_from_slice(&text.as_bytes()[..len]);
+    out
+}
+
+/// 按 PKCS#11 约定写出变长结果：`out` 为空时只回传长度，容量不足时返回 `CKR_BUFFER_TOO_SMALL`
+///
+/// # Safety
+/// `out` 非空时必须指向 `*out_len` 字节的可写内存，`out_len` 必须可写。
+unsafe fn write_output(data: &[u8], out: *mut CK_BYTE, out_len: *mut CK_ULONG) -> Result<(), CK_RV> {
+    if out_len.is_null() {
+        return Err(CKR_ARGUMENTS_BAD);
+    }
+    let capacity = *out_len as usize;
+    *out_len = data.len() as CK_ULONG;
+    if out.is_null() {
+        return Ok(());
+    }
+    if data.len() > capacity {
+        return Err(CKR_BUFFER_TOO_SMALL);
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    Ok(())
+}
+
+/// 借用调用方传入的字节数组，空指针且长度为 0 时视为空切片
+///
+/// # Safety
+/// `data` 非空时必须指向 `len` 字节的可读内存。
+unsafe fn input<'a>(data: *const CK_BYTE, len: CK_ULONG) -> Result<&'a [u8], CK_RV> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(CKR_ARGUMENTS_BAD),
+        (false, _) => Ok(slice::from_raw_parts(data, len as usize)),
+    }
+}
+
+/// 签名结果长度已知时，在发起网络请求前处理长度查询与容量不足
+///
+/// # Safety
+/// 同 [`write_output`]。
+unsafe fn query_only(len: usize, out: *mut CK_BYTE, out_len: *mut CK_ULONG) -> Result<bool, CK_RV> {
+    if out_len.is_null() {
+        return Err(CKR_ARGUMENTS_BAD);
+    }
+    if out.is_null() {
+        *out_len = len as CK_ULONG;
+        return Ok(true);
+    }
+    if (*out_len as usize) < len {
+        *out_len = len as CK_ULONG;
+        return Err(CKR_BUFFER_TOO_SMALL);
+    }
+    Ok(false)
+}
+
+extern "C" fn C_Initialize(args: *mut c_void) -> CK_RV {
+    guard(|| {
+        if !args.is_null() {
+            let reserved = unsafe { (*(args as *const CK_C_INITIALIZE_ARGS)).pReserved };
+            if !reserved.is_null() {
+                return Err(CKR_ARGUMENTS_BAD);
+            }
+        }
+        let mut slot = MODULE.write().unwrap_or_else(|e| e.into_inner());
+        if slot.is_some() {
+            return Err(CKR_CRYPTOKI_ALREADY_INITIALIZED);
+        }
+        let module = Pkcs11Config::from_env().and_then(Module::load).map_err(|e| {
+            warn!("PKCS#11 module initialization failed: {}", e);
+            CKR_GENERAL_ERROR
+        })?;
+        *slot = Some(Arc::new(module));
+        Ok(())
+    })
+}
+
+extern "C" fn C_Finalize(reserved: *mut c_void) -> CK_RV {
+    guard(|| {
+        if !reserved.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let module = MODULE.write().unwrap_or_else(|e| e.into_inner()).take();
+        module.map(drop).ok_or(CKR_CRYPTOKI_NOT_INITIALIZED)
+    })
+}
+
+extern "C" fn C_GetInfo(info: *mut CK_INFO) -> CK_RV {
+    guard(|| {
+        module()?;
+        if info.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        unsafe {
+            info.write(CK_INFO {
+                cryptokiVersion: CK_VERSION { major: 2, minor: 40 },
+                manufacturerID: padded("SM2 Co-Sign Team"),
+                flags: 0,
+                libraryDescription: padded("SM2 Co-Sign PKCS#11"),
+                libraryVersion: library_version(),
+            });
+        }
+        Ok(())
+    })
+}
+
+/// 库版本（取 crate 版本的主次版本号）
+fn library_version() -> CK_VERSION {
+    let mut parts = env!("CARGO_PKG_VERSION").split('.').map(|part| part.parse().unwrap_or(0));
+    CK_VERSION { major: parts.next().unwrap_or(0), minor: parts.next().unwrap_or(0) }
+}
+
+/// 导出的函数表入口
+///
+/// # Safety
+/// `list` 必须可写；写入的函数表为静态数据，调用方不得修改。
+#[no_mangle]
+pub unsafe extern "C" fn C_GetFunctionList(list: *mut *const CK_FUNCTION_LIST) -> CK_RV {
+    if list.is_null() {
+        return CKR_ARGUMENTS_BAD;
+    }
+    *list = &FUNCTION_LIST;
+    CKR_OK
+}
+
+extern "C" fn C_GetSlotList(_token_present: CK_BBOOL, slots: *mut CK_SLOT_ID, count: *mut CK_ULONG) -> CK_RV {
+    guard(|| {
+        module()?;
+        unsafe { write_ulongs(&[SLOT_ID], slots, count) }
+    })
+}
+
+/// 写出句柄、机制等 `CK_ULONG` 列表，约定同 [`write_output`]
+///
+/// # Safety
+/// `out` 非空时必须指向 `*count` 个元素的可写内存，`count` 必须可写。
+unsafe fn write_ulongs(values: &[CK_ULONG], out: *mut CK_ULONG, count: *mut CK_ULONG) -> Result<(), CK_RV> {
+    if count.is_null() {
+        return Err(CKR_ARGUMENTS_BAD);
+    }
+    let capacity = *count as usize;
+    *count = values.len() as CK_ULONG;
+    if out.is_null() {
+        return Ok(());
+    }
+    if values.len() > capacity {
+        return Err(CKR_BUFFER_TOO_SMALL);
+    }
+    ptr::copy_nonoverlapping(values.as_ptr(), out, values.len());
+    Ok(())
+}
+
+fn check_slot(slot: CK_SLOT_ID) -> Result<(), CK_RV> {
+    if slot == SLOT_ID {
+        Ok(())
+    } else {
+        Err(CKR_SLOT_ID_INVALID)
+    }
+}
+
+extern "C" fn C_GetSlotInfo(slot: CK_SLOT_ID, info: *mut CK_SLOT_INFO) -> CK_RV {
+    guard(|| {
+        module()?;
+        check_slot(slot)?;
+        if info.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        unsafe {
+            info.write(CK_SLOT_INFO {
+                slotDescription: padded("SM2 Co-Sign collaborative key"),
+                manufacturerID: padded("SM2 Co-Sign Team"),
+                flags: CKF_TOKEN_PRESENT,
+                hardwareVersion: CK_VERSION::default(),
+                firmwareVersion: library_version(),
+            });
+        }
+        Ok(())
+    })
+}
+
+extern "C" fn C_GetTokenInfo(slot: CK_SLOT_ID, info: *mut CK_TOKEN_INFO) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        check_slot(slot)?;
+        if info.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let (count, rw_count) = {
+            let sessions = module.sessions();
+            let rw = sessions.values().filter(|s| s.flags & CKF_RW_SESSION != 0).count();
+            (sessions.len() as CK_ULONG, rw as CK_ULONG)
+        };
+        let serial: String = module.objects.id[..8].iter().map(|b| format!("{:02X}", b)).collect();
+        unsafe {
+            info.write(CK_TOKEN_INFO {
+                label: padded(&module.objects.label),
+                manufacturerID: padded("SM2 Co-Sign Team"),
+                model: padded("CoSign"),
+                serialNumber: padded(&serial),
+                flags: CKF_RNG
+                    | CKF_WRITE_PROTECTED
+                    | CKF_LOGIN_REQUIRED
+                    | CKF_USER_PIN_INITIALIZED
+                    | CKF_TOKEN_INITIALIZED,
+                ulMaxSessionCount: CK_UNAVAILABLE_INFORMATION,
+                ulSessionCount: count,
+                ulMaxRwSessionCount: CK_UNAVAILABLE_INFORMATION,
+                ulRwSessionCount: rw_count,
+                ulMaxPinLen: 256,
+                ulMinPinLen: 1,
+                ulTotalPublicMemory: CK_UNAVAILABLE_INFORMATION,
+                ulFreePublicMemory: CK_UNAVAILABLE_INFORMATION,
+                ulTotalPrivateMemory: CK_UNAVAILABLE_INFORMATION,
+                ulFreePrivateMemory: CK_UNAVAILABLE_INFORMATION,
+                hardwareVersion: CK_VERSION::default(),
+                firmwareVersion: library_version(),
+                utcTime: [b' '; 16],
+            });
+        }
+        Ok(())
+    })
+}
+
+extern "C" fn C_GetMechanismList(slot: CK_SLOT_ID, list: *mut CK_MECHANISM_TYPE, count: *mut CK_ULONG) -> CK_RV {
+    guard(|| {
+        module()?;
+        check_slot(slot)?;
+        unsafe { write_ulongs(&MECHANISMS, list, count) }
+    })
+}
+
+extern "C" fn C_GetMechanismInfo(slot: CK_SLOT_ID, kind: CK_MECHANISM_TYPE, info: *mut CK_MECHANISM_INFO) -> CK_RV {
+    guard(|| {
+        module()?;
+        check_slot(slot)?;
+        if info.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let flags = match kind {
+            CKM_ECDSA | CKM_SM2_SIGN | CKM_SM3_SM2 => CKF_SIGN,
+            CKM_SM2_ENCRYPT => CKF_DECRYPT,
+            _ => return Err(CKR_MECHANISM_INVALID),
+        };
+        unsafe {
+            info.write(CK_MECHANISM_INFO {
+                ulMinKeySize: 256,
+                ulMaxKeySize: 256,
+                flags: flags | CKF_EC_F_P | CKF_EC_NAMEDCURVE | CKF_EC_UNCOMPRESS,
+            });
+        }
+        Ok(())
+    })
+}
+
+extern "C" fn C_OpenSession(
+    slot: CK_SLOT_ID,
+    flags: CK_FLAGS,
+    _application: *mut c_void,
+    _notify: CK_NOTIFY,
+    session: *mut CK_SESSION_HANDLE,
+) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        check_slot(slot)?;
+        if flags & CKF_SERIAL_SESSION == 0 {
+            return Err(CKR_SESSION_PARALLEL_NOT_SUPPORTED);
+        }
+        if session.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let handle = {
+            let mut next = module.next_session.lock().unwrap_or_else(|e| e.into_inner());
+            let handle = *next;
+            *next += 1;
+            handle
+        };
+        module.sessions().insert(handle, SessionState { flags, ..SessionState::default() });
+        unsafe { *session = handle };
+        Ok(())
+    })
+}
+
+extern "C" fn C_CloseSession(session: CK_SESSION_HANDLE) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let mut sessions = module.sessions();
+        sessions.remove(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        // Reason: PKCS#11 的登录状态属于应用；最后一个会话关闭时回到未登录状态
+        if sessions.is_empty() {
+            module.logged_in.store(false, Ordering::SeqCst);
+        }
+        Ok(())
+    })
+}
+
+extern "C" fn C_CloseAllSessions(slot: CK_SLOT_ID) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        check_slot(slot)?;
+        module.sessions().clear();
+        module.logged_in.store(false, Ordering::SeqCst);
+        Ok(())
+    })
+}
+
+extern "C" fn C_GetSessionInfo(session: CK_SESSION_HANDLE, info: *mut CK_SESSION_INFO) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let flags = module.sessions().get(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?.flags;
+        if info.is_null() {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let state = match (flags & CKF_RW_SESSION != 0, module.is_logged_in()) {
+            (false, false) => CKS_RO_PUBLIC_SESSION,
+            (false, true) => CKS_RO_USER_FUNCTIONS,
+            (true, false) => CKS_RW_PUBLIC_SESSION,
+            (true, true) => CKS_RW_USER_FUNCTIONS,
+        };
+        unsafe {
+            info.write(CK_SESSION_INFO { slotID: SLOT_ID, state, flags, ulDeviceError: 0 });
+        }
+        Ok(())
+    })
+}
+
+extern "C" fn C_Login(session: CK_SESSION_HANDLE, user_type: CK_USER_TYPE, pin: *mut CK_BYTE, pin_len: CK_ULONG) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        if !module.sessions().contains_key(&session) {
+            return Err(CKR_SESSION_HANDLE_INVALID);
+        }
+        match user_type {
+            CKU_USER => {}
+            CKU_SO => return Err(CKR_FUNCTION_NOT_SUPPORTED),
+            _ => return Err(CKR_USER_TYPE_INVALID),
+        }
+        if module.is_logged_in() {
+            return Err(CKR_USER_ALREADY_LOGGED_IN);
+        }
+        let pin = unsafe { input(pin, pin_len)? };
+        let password = Zeroizing::new(std::str::from_utf8(pin).map_err(|_| CKR_PIN_INCORRECT)?.to_string());
+        module
+            .runtime
+            .block_on(module.client.login(&module.username, &password))
+            .map_err(|e| match e {
+                Error::Api { .. } | Error::Http { status: 401 | 403, .. } => CKR_PIN_INCORRECT,
+                e => error_rv(&e),
+            })?;
+        module.logged_in.store(true, Ordering::SeqCst);
+        Ok(())
+    })
+}
+
+extern "C" fn C_Logout(session: CK_SESSION_HANDLE) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        if !module.sessions().contains_key(&session) {
+            return Err(CKR_SESSION_HANDLE_INVALID);
+        }
+        if !module.logged_in.swap(false, Ordering::SeqCst) {
+            return Err(CKR_USER_NOT_LOGGED_IN);
+        }
+        if let Err(e) = module.runtime.block_on(module.client.logout()) {
+            warn!("Logout request failed: {}", e);
+        }
+        Ok(())
+    })
+}
+
+/// 读取属性模板的 (类型, 值) 列表
+///
+/// # Safety
+/// `template` 非空时必须指向 `count` 个有效的 `CK_ATTRIBUTE`。
+unsafe fn read_template(template: *const CK_ATTRIBUTE, count: CK_ULONG) -> Result<Vec<(CK_ATTRIBUTE_TYPE, Vec<u8>)>, CK_RV> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if template.is_null() {
+        return Err(CKR_ARGUMENTS_BAD);
+    }
+    slice::from_raw_parts(template, count as usize)
+        .iter()
+        .map(|attr| Ok((attr.type_, input(attr.pValue as *const CK_BYTE, attr.ulValueLen)?.to_vec())))
+        .collect()
+}
+
+extern "C" fn C_GetAttributeValue(
+    session: CK_SESSION_HANDLE,
+    object: CK_OBJECT_HANDLE,
+    template: *mut CK_ATTRIBUTE,
+    count: CK_ULONG,
+) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        if !module.sessions().contains_key(&session) {
+            return Err(CKR_SESSION_HANDLE_INVALID);
+        }
+        if !module.objects.handles(module.is_logged_in()).contains(&object) {
+            return Err(CKR_OBJECT_HANDLE_INVALID);
+        }
+        if template.is_null() && count != 0 {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        // Reason: 按规范逐项处理模板，个别属性失败时其余属性照常返回，最后报告其中一个错误
+        let mut result = Ok(());
+        for i in 0..count as usize {
+            let attr = unsafe { &mut *template.add(i) };
+            match module.objects.attribute(object, attr.type_) {
+                Ok(value) if attr.pValue.is_null() => attr.ulValueLen = value.len() as CK_ULONG,
+                Ok(value) if (attr.ulValueLen as usize) < value.len() => {
+                    attr.ulValueLen = CK_UNAVAILABLE_INFORMATION;
+                    result = Err(CKR_BUFFER_TOO_SMALL);
+                }
+                Ok(value) => {
+                    unsafe { ptr::copy_nonoverlapping(value.as_ptr(), attr.pValue as *mut CK_BYTE, value.len()) };
+                    attr.ulValueLen = value.len() as CK_ULONG;
+                }
+                Err(rv) => {
+                    attr.ulValueLen = CK_UNAVAILABLE_INFORMATION;
+                    result = Err(rv);
+                }
+            }
+        }
+        result
+    })
+}
+
+extern "C" fn C_FindObjectsInit(session: CK_SESSION_HANDLE, template: *mut CK_ATTRIBUTE, count: CK_ULONG) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let template = unsafe { read_template(template, count)? };
+        let found: Vec<_> = module
+            .objects
+            .handles(module.is_logged_in())
+            .into_iter()
+            .filter(|handle| module.objects.matches(*handle, &template))
+            .collect();
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        if state.find.is_some() {
+            return Err(CKR_OPERATION_ACTIVE);
+        }
+        state.find = Some(found);
+        Ok(())
+    })
+}
+
+extern "C" fn C_FindObjects(
+    session: CK_SESSION_HANDLE,
+    objects: *mut CK_OBJECT_HANDLE,
+    max_count: CK_ULONG,
+    count: *mut CK_ULONG,
+) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        let found = state.find.as_mut().ok_or(CKR_OPERATION_NOT_INITIALIZED)?;
+        if count.is_null() || (objects.is_null() && max_count != 0) {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let n = found.len().min(max_count as usize);
+        unsafe {
+            ptr::copy_nonoverlapping(found.as_ptr(), objects, n);
+            *count = n as CK_ULONG;
+        }
+        found.drain(..n);
+        Ok(())
+    })
+}
+
+extern "C" fn C_FindObjectsFinal(session: CK_SESSION_HANDLE) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        state.find.take().map(drop).ok_or(CKR_OPERATION_NOT_INITIALIZED)
+    })
+}
+
+/// 检查运算初始化参数，返回机制类型
+///
+/// # Safety
+/// `mechanism` 非空时必须指向有效的 `CK_MECHANISM`。
+unsafe fn init_operation(
+    module: &Module,
+    mechanism: *const CK_MECHANISM,
+    key: CK_OBJECT_HANDLE,
+    allowed: &[CK_MECHANISM_TYPE],
+) -> Result<CK_MECHANISM_TYPE, CK_RV> {
+    if mechanism.is_null() {
+        return Err(CKR_ARGUMENTS_BAD);
+    }
+    if !module.is_logged_in() {
+        return Err(CKR_USER_NOT_LOGGED_IN);
+    }
+    if key != PRIVATE_KEY_HANDLE {
+        return Err(if module.objects.handles(true).contains(&key) {
+            CKR_KEY_FUNCTION_NOT_PERMITTED
+        } else {
+            CKR_KEY_HANDLE_INVALID
+        });
+    }
+    let kind = (*mechanism).mechanism;
+    if !allowed.contains(&kind) {
+        return Err(CKR_MECHANISM_INVALID);
+    }
+    Ok(kind)
+}
+
+extern "C" fn C_SignInit(session: CK_SESSION_HANDLE, mechanism: *mut CK_MECHANISM, key: CK_OBJECT_HANDLE) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let kind = unsafe { init_operation(&module, mechanism, key, &[CKM_ECDSA, CKM_SM2_SIGN, CKM_SM3_SM2])? };
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        if state.sign.is_some() {
+            return Err(CKR_OPERATION_ACTIVE);
+        }
+        state.sign = Some(Operation { mechanism: kind, data: Zeroizing::new(Vec::new()) });
+        Ok(())
+    })
+}
+
+/// 完成签名：长度查询与容量不足时保留运算，其余情况（含失败）结束运算
+///
+/// # Safety
+/// 同 [`write_output`]。
+unsafe fn finish_sign(
+    module: &Module,
+    session: CK_SESSION_HANDLE,
+    data: Option<&[u8]>,
+    signature: *mut CK_BYTE,
+    signature_len: *mut CK_ULONG,
+) -> Result<(), CK_RV> {
+    let operation = {
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        if state.sign.is_none() {
+            return Err(CKR_OPERATION_NOT_INITIALIZED);
+        }
+        if query_only(SIGNATURE_LEN, signature, signature_len)? {
+            return Ok(());
+        }
+        state.sign.take().expect("checked above")
+    };
+    let message = data.unwrap_or(operation.data.as_slice());
+    let result = match operation.mechanism {
+        CKM_SM3_SM2 => module.runtime.block_on(module.client.sign(message, DigestMode::Za)),
+        _ if message.len() != 32 => return Err(CKR_DATA_LEN_RANGE),
+        _ => module.runtime.block_on(module.client.sign_digest(message)),
+    };
+    let signature_bytes = result.map_err(|e| error_rv(&e))?.to_bytes();
+    write_output(&signature_bytes, signature, signature_len)
+}
+
+extern "C" fn C_Sign(
+    session: CK_SESSION_HANDLE,
+    data: *mut CK_BYTE,
+    data_len: CK_ULONG,
+    signature: *mut CK_BYTE,
+    signature_len: *mut CK_ULONG,
+) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let data = unsafe { input(data, data_len)? };
+        unsafe { finish_sign(&module, session, Some(data), signature, signature_len) }
+    })
+}
+
+extern "C" fn C_SignUpdate(session: CK_SESSION_HANDLE, part: *mut CK_BYTE, part_len: CK_ULONG) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let part = unsafe { input(part, part_len)? };
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        let operation = state.sign.as_mut().ok_or(CKR_OPERATION_NOT_INITIALIZED)?;
+        if operation.mechanism != CKM_SM3_SM2 {
+            // Reason: 其余机制的输入是单个摘要，不支持分段；按规范分段失败时结束运算
+            state.sign = None;
+            return Err(CKR_FUNCTION_NOT_SUPPORTED);
+        }
+        operation.data.extend_from_slice(part);
+        Ok(())
+    })
+}
+
+extern "C" fn C_SignFinal(session: CK_SESSION_HANDLE, signature: *mut CK_BYTE, signature_len: *mut CK_ULONG) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        unsafe { finish_sign(&module, session, None, signature, signature_len) }
+    })
+}
+
+extern "C" fn C_DecryptInit(session: CK_SESSION_HANDLE, mechanism: *mut CK_MECHANISM, key: CK_OBJECT_HANDLE) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let kind = unsafe { init_operation(&module, mechanism, key, &[CKM_SM2_ENCRYPT])? };
+        let mut sessions = module.sessions();
+        let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+        if state.decrypt.is_some() {
+            return Err(CKR_OPERATION_ACTIVE);
+        }
+        state.decrypt = Some(Operation { mechanism: kind, data: Zeroizing::new(Vec::new()) });
+        Ok(())
+    })
+}
+
+extern "C" fn C_Decrypt(
+    session: CK_SESSION_HANDLE,
+    encrypted: *mut CK_BYTE,
+    encrypted_len: CK_ULONG,
+    data: *mut CK_BYTE,
+    data_len: *mut CK_ULONG,
+) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        let ciphertext = unsafe { input(encrypted, encrypted_len)? };
+        {
+            let mut sessions = module.sessions();
+            let state = sessions.get_mut(&session).ok_or(CKR_SESSION_HANDLE_INVALID)?;
+            if state.decrypt.is_none() {
+                return Err(CKR_OPERATION_NOT_INITIALIZED);
+            }
+            let plaintext_len = match Sm2Ciphertext::parse(ciphertext) {
+                Ok(parsed) => parsed.c2.len(),
+                Err(_) => {
+                    state.decrypt = None;
+                    return Err(CKR_ENCRYPTED_DATA_INVALID);
+                }
+            };
+            if unsafe { query_only(plaintext_len, data, data_len)? } {
+                return Ok(());
+            }
+            state.decrypt = None;
+        }
+        let plaintext = Zeroizing::new(
+            module
+                .runtime
+                .block_on(module.client.decrypt(ciphertext))
+                .map_err(|e| match e {
+                    Error::Crypto(_) => CKR_ENCRYPTED_DATA_INVALID,
+                    e => error_rv(&e),
+                })?,
+        );
+        unsafe { write_output(&plaintext, data, data_len) }
+    })
+}
+
+extern "C" fn C_GenerateRandom(session: CK_SESSION_HANDLE, out: *mut CK_BYTE, len: CK_ULONG) -> CK_RV {
+    guard(|| {
+        let module = module()?;
+        if !module.sessions().contains_key(&session) {
+            return Err(CKR_SESSION_HANDLE_INVALID);
+        }
+        if out.is_null() && len != 0 {
+            return Err(CKR_ARGUMENTS_BAD);
+        }
+        let random = Zeroizing::new(CoSignProtocol::generate_random(len as usize));
+        unsafe { ptr::copy_nonoverlapping(random.as_ptr(), out, random.len()) };
+        Ok(())
+    })
+}
+
+/// 生成返回 `ret` 的占位入口（只读令牌不支持的函数）
+macro_rules! unsupported {
+    ($($name:ident($($ty:ty),*) => $ret:expr;)*) => {
+        $(
+            #[allow(clippy::too_many_arguments)]
+            extern "C" fn $name($(_: $ty),*) -> CK_RV {
+                if module().is_err() {
+                    return CKR_CRYPTOKI_NOT_INITIALIZED;
+                }
+                $ret
+            }
+        )*
+    };
+}
+
+type Ptr = *mut c_void;
+type BytePtr = *mut CK_BYTE;
+type UlongPtr = *mut CK_ULONG;
+type Mech = *mut CK_MECHANISM;
+type Attrs = *mut CK_ATTRIBUTE;
+type Handle = CK_OBJECT_HANDLE;
+type HandlePtr = *mut CK_OBJECT_HANDLE;
+type Session = CK_SESSION_HANDLE;
+
+unsupported! {
+    C_InitToken(CK_SLOT_ID, BytePtr, CK_ULONG, BytePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_InitPIN(Session, BytePtr, CK_ULONG) => CKR_TOKEN_WRITE_PROTECTED;
+    C_SetPIN(Session, BytePtr, CK_ULONG, BytePtr, CK_ULONG) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_GetOperationState(Session, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_SetOperationState(Session, BytePtr, CK_ULONG, Handle, Handle) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_CreateObject(Session, Attrs, CK_ULONG, HandlePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_CopyObject(Session, Handle, Attrs, CK_ULONG, HandlePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_DestroyObject(Session, Handle) => CKR_TOKEN_WRITE_PROTECTED;
+    C_GetObjectSize(Session, Handle, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_SetAttributeValue(Session, Handle, Attrs, CK_ULONG) => CKR_TOKEN_WRITE_PROTECTED;
+    C_EncryptInit(Session, Mech, Handle) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_Encrypt(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_EncryptUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_EncryptFinal(Session, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_DecryptUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_DecryptFinal(Session, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_DigestInit(Session, Mech) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_Digest(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_DigestUpdate(Session, BytePtr, CK_ULONG) => CKR_OPERATION_NOT_INITIALIZED;
+    C_DigestKey(Session, Handle) => CKR_OPERATION_NOT_INITIALIZED;
+    C_DigestFinal(Session, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_SignRecoverInit(Session, Mech, Handle) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_SignRecover(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_VerifyInit(Session, Mech, Handle) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_Verify(Session, BytePtr, CK_ULONG, BytePtr, CK_ULONG) => CKR_OPERATION_NOT_INITIALIZED;
+    C_VerifyUpdate(Session, BytePtr, CK_ULONG) => CKR_OPERATION_NOT_INITIALIZED;
+    C_VerifyFinal(Session, BytePtr, CK_ULONG) => CKR_OPERATION_NOT_INITIALIZED;
+    C_VerifyRecoverInit(Session, Mech, Handle) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_VerifyRecover(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_OPERATION_NOT_INITIALIZED;
+    C_DigestEncryptUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_DecryptDigestUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_SignEncryptUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_DecryptVerifyUpdate(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_GenerateKey(Session, Mech, Attrs, CK_ULONG, HandlePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_GenerateKeyPair(Session, Mech, Attrs, CK_ULONG, Attrs, CK_ULONG, HandlePtr, HandlePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_WrapKey(Session, Mech, Handle, Handle, BytePtr, UlongPtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_UnwrapKey(Session, Mech, Handle, BytePtr, CK_ULONG, Attrs, CK_ULONG, HandlePtr) => CKR_TOKEN_WRITE_PROTECTED;
+    C_DeriveKey(Session, Mech, Handle, Attrs, CK_ULONG, HandlePtr) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_SeedRandom(Session, BytePtr, CK_ULONG) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_GetFunctionStatus(Session) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_CancelFunction(Session) => CKR_FUNCTION_NOT_SUPPORTED;
+    C_WaitForSlotEvent(CK_FLAGS, *mut CK_SLOT_ID, Ptr) => CKR_FUNCTION_NOT_SUPPORTED;
+}
+
+/// 声明函数表结构体与静态实例，字段顺序即 PKCS#11 2.40 规定的顺序
+macro_rules! function_list {
+    ($($name:ident: fn($($ty:ty),*);)*) => {
+        /// PKCS#11 2.40 函数表
+        #[cfg_attr(windows, repr(C, packed(1)))]
+        #[cfg_attr(not(windows), repr(C))]
+        pub struct CK_FUNCTION_LIST {
+            pub version: CK_VERSION,
+            $(pub $name: unsafe extern "C" fn($($ty),*) -> CK_RV,)*
+        }
+
+        static FUNCTION_LIST: CK_FUNCTION_LIST = CK_FUNCTION_LIST {
+            version: CK_VERSION { major: 2, minor: 40 },
+            $($name,)*
+        };
+    };
+}
+
+function_list! {
+    C_Initialize: fn(Ptr);
+    C_Finalize: fn(Ptr);
+    C_GetInfo: fn(*mut CK_INFO);
+    C_GetFunctionList: fn(*mut *const CK_FUNCTION_LIST);
+    C_GetSlotList: fn(CK_BBOOL, *mut CK_SLOT_ID, UlongPtr);
+    C_GetSlotInfo: fn(CK_SLOT_ID, *mut CK_SLOT_INFO);
+    C_GetTokenInfo: fn(CK_SLOT_ID, *mut CK_TOKEN_INFO);
+    C_GetMechanismList: fn(CK_SLOT_ID, *mut CK_MECHANISM_TYPE, UlongPtr);
+    C_GetMechanismInfo: fn(CK_SLOT_ID, CK_MECHANISM_TYPE, *mut CK_MECHANISM_INFO);
+    C_InitToken: fn(CK_SLOT_ID, BytePtr, CK_ULONG, BytePtr);
+    C_InitPIN: fn(Session, BytePtr, CK_ULONG);
+    C_SetPIN: fn(Session, BytePtr, CK_ULONG, BytePtr, CK_ULONG);
+    C_OpenSession: fn(CK_SLOT_ID, CK_FLAGS, Ptr, CK_NOTIFY, *mut CK_SESSION_HANDLE);
+    C_CloseSession: fn(Session);
+    C_CloseAllSessions: fn(CK_SLOT_ID);
+    C_GetSessionInfo: fn(Session, *mut CK_SESSION_INFO);
+    C_GetOperationState: fn(Session, BytePtr, UlongPtr);
+    C_SetOperationState: fn(Session, BytePtr, CK_ULONG, Handle, Handle);
+    C_Login: fn(Session, CK_USER_TYPE, BytePtr, CK_ULONG);
+    C_Logout: fn(Session);
+    C_CreateObject: fn(Session, Attrs, CK_ULONG, HandlePtr);
+    C_CopyObject: fn(Session, Handle, Attrs, CK_ULONG, HandlePtr);
+    C_DestroyObject: fn(Session, Handle);
+    C_GetObjectSize: fn(Session, Handle, UlongPtr);
+    C_GetAttributeValue: fn(Session, Handle, Attrs, CK_ULONG);
+    C_SetAttributeValue: fn(Session, Handle, Attrs, CK_ULONG);
+    C_FindObjectsInit: fn(Session, Attrs, CK_ULONG);
+    C_FindObjects: fn(Session, HandlePtr, CK_ULONG, UlongPtr);
+    C_FindObjectsFinal: fn(Session);
+    C_EncryptInit: fn(Session, Mech, Handle);
+    C_Encrypt: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_EncryptUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_EncryptFinal: fn(Session, BytePtr, UlongPtr);
+    C_DecryptInit: fn(Session, Mech, Handle);
+    C_Decrypt: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DecryptUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DecryptFinal: fn(Session, BytePtr, UlongPtr);
+    C_DigestInit: fn(Session, Mech);
+    C_Digest: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DigestUpdate: fn(Session, BytePtr, CK_ULONG);
+    C_DigestKey: fn(Session, Handle);
+    C_DigestFinal: fn(Session, BytePtr, UlongPtr);
+    C_SignInit: fn(Session, Mech, Handle);
+    C_Sign: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_SignUpdate: fn(Session, BytePtr, CK_ULONG);
+    C_SignFinal: fn(Session, BytePtr, UlongPtr);
+    C_SignRecoverInit: fn(Session, Mech, Handle);
+    C_SignRecover: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_VerifyInit: fn(Session, Mech, Handle);
+    C_Verify: fn(Session, BytePtr, CK_ULONG, BytePtr, CK_ULONG);
+    C_VerifyUpdate: fn(Session, BytePtr, CK_ULONG);
+    C_VerifyFinal: fn(Session, BytePtr, CK_ULONG);
+    C_VerifyRecoverInit: fn(Session, Mech, Handle);
+    C_VerifyRecover: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DigestEncryptUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DecryptDigestUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_SignEncryptUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_DecryptVerifyUpdate: fn(Session, BytePtr, CK_ULONG, BytePtr, UlongPtr);
+    C_GenerateKey: fn(Session, Mech, Attrs, CK_ULONG, HandlePtr);
+    C_GenerateKeyPair: fn(Session, Mech, Attrs, CK_ULONG, Attrs, CK_ULONG, HandlePtr, HandlePtr);
+    C_WrapKey: fn(Session, Mech, Handle, Handle, BytePtr, UlongPtr);
+    C_UnwrapKey: fn(Session, Mech, Handle, BytePtr, CK_ULONG, Attrs, CK_ULONG, HandlePtr);
+    C_DeriveKey: fn(Session, Mech, Handle, Attrs, CK_ULONG, HandlePtr);
+    C_SeedRandom: fn(Session, BytePtr, CK_ULONG);
+    C_GenerateRandom: fn(Session, BytePtr, CK_ULONG);
+    C_GetFunctionStatus: fn(Session);
+    C_CancelFunction: fn(Session);
+    C_WaitForSlotEvent: fn(CK_FLAGS, *mut CK_SLOT_ID, Ptr);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm2_co_sign_core::{KeyLifetime, PublicKey};
+
+    fn objects(certificate: Option<Vec<u8>>) -> TokenObjects {
+        let protocol = CoSignProtocol::new().unwrap();
+        let d1 = protocol.generate_d1().unwrap();
+        let public_key = PublicKey::try_from(protocol.calculate_p1(&d1).unwrap()).unwrap();
+        let key_pair = KeyPair { d1, public_key, user_id: "u1".to_string(), lifetime: KeyLifetime::default() };
+        TokenObjects::new(&key_pair, DEFAULT_LABEL.to_string(), certificate)
+    }
+
+    #[test]
+    fn test_function_list() {
+        let mut list: *const CK_FUNCTION_LIST = ptr::null();
+        assert_eq!(unsafe { C_GetFunctionList(&mut list) }, CKR_OK);
+        let list = unsafe { &*list };
+        let version = list.version;
+        assert_eq!((version.major, version.minor), (2, 40));
+        assert_eq!(unsafe { C_GetFunctionList(ptr::null_mut()) }, CKR_ARGUMENTS_BAD);
+    }
+
+    #[test]
+    fn test_not_initialized() {
+        let mut count: CK_ULONG = 0;
+        assert_eq!(C_GetSlotList(CK_TRUE, ptr::null_mut(), &mut count), CKR_CRYPTOKI_NOT_INITIALIZED);
+        assert_eq!(C_Finalize(ptr::null_mut()), CKR_CRYPTOKI_NOT_INITIALIZED);
+        assert_eq!(C_CreateObject(1, ptr::null_mut(), 0, ptr::null_mut()), CKR_CRYPTOKI_NOT_INITIALIZED);
+    }
+
+    #[test]
+    fn test_object_attributes() {
+        let objects = objects(None);
+        assert_eq!(objects.handles(false), vec![PUBLIC_KEY_HANDLE]);
+        assert_eq!(objects.handles(true), vec![PRIVATE_KEY_HANDLE, PUBLIC_KEY_HANDLE]);
+
+        assert_eq!(objects.attribute(PRIVATE_KEY_HANDLE, CKA_CLASS).unwrap(), CKO_PRIVATE_KEY.to_ne_bytes());
+        assert_eq!(objects.attribute(PRIVATE_KEY_HANDLE, CKA_VALUE), Err(CKR_ATTRIBUTE_SENSITIVE));
+        assert_eq!(objects.attribute(PRIVATE_KEY_HANDLE, CKA_EC_POINT), Err(CKR_ATTRIBUTE_TYPE_INVALID));
+        assert_eq!(objects.attribute(PRIVATE_KEY_HANDLE, CKA_EXTRACTABLE).unwrap(), vec![CK_FALSE]);
+        assert_eq!(
+            objects.attribute(PUBLIC_KEY_HANDLE, CKA_EC_PARAMS).unwrap(),
+            [&[asn1::TAG_OID, asn1::OID_SM2.len() as u8][..], asn1::OID_SM2].concat()
+        );
+
+        let ec_point = objects.attribute(PUBLIC_KEY_HANDLE, CKA_EC_POINT).unwrap();
+        assert_eq!(&ec_point[..3], &[asn1::TAG_OCTET_STRING, 65, 0x04]);
+        assert_eq!(objects.attribute(PUBLIC_KEY_HANDLE, CKA_ID), objects.attribute(PRIVATE_KEY_HANDLE, CKA_ID));
+    }
+
+    #[test]
+    fn test_find_template() {
+        let objects = objects(Some(vec![0x30, 0x00]));
+        let class = |class: CK_OBJECT_CLASS| (CKA_CLASS, class.to_ne_bytes().to_vec());
+        let found = |template: &[(CK_ATTRIBUTE_TYPE, Vec<u8>)]| -> Vec<_> {
+            objects.handles(true).into_iter().filter(|h| objects.matches(*h, template)).collect()
+        };
+
+        assert_eq!(found(&[]), vec![PRIVATE_KEY_HANDLE, PUBLIC_KEY_HANDLE, CERTIFICATE_HANDLE]);
+        assert_eq!(found(&[class(CKO_PRIVATE_KEY)]), vec![PRIVATE_KEY_HANDLE]);
+        assert_eq!(found(&[class(CKO_CERTIFICATE)]), vec![CERTIFICATE_HANDLE]);
+        assert_eq!(found(&[(CKA_SIGN, vec![CK_TRUE])]), vec![PRIVATE_KEY_HANDLE]);
+        assert!(found(&[(CKA_LABEL, b"other".to_vec())]).is_empty());
+    }
+
+    #[test]
+    fn test_padded() {
+        assert_eq!(&padded::<8>("abc"), b"abc     ");
+        assert_eq!(&padded::<4>("abcdef"), b"abcd");
+        // 不截断多字节字符
+        assert_eq!(&padded::<4>("协同"), b"\xe5\x8d\x8f ");
+    }
+}
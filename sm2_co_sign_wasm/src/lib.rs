@@ -0,0 +1,152 @@
+//! SM2 协同签名 WASM 绑定
+//!
+//! 通过 wasm-bindgen 导出协议层的客户端计算步骤，供浏览器应用使用。
+//! HTTP 交互（注册、登录、签名请求等）由 JS 层自行完成，本模块只负责 D1 侧的密码学运算。
+
+use wasm_bindgen::prelude::*;
+
+use sm2_co_sign_core::{CoSignProtocol, Error};
+
+/// 将核心库错误转换为 JS 异常
+fn to_js_error(e: Error) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+/// 签名预处理结果（k1 与 Q1）
+#[wasm_bindgen]
+pub struct SignPrepareResult {
+    k1: Vec<u8>,
+    q1: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl SignPrepareResult {
+    /// 随机数 k1（需保留到 complete_signature 调用）
+    #[wasm_bindgen(getter)]
+    pub fn k1(&self) -> Vec<u8> {
+        self.k1.clone()
+    }
+
+    /// Q1 = k1 * G（64字节，x||y），发送给服务端
+    #[wasm_bindgen(getter)]
+    pub fn q1(&self) -> Vec<u8> {
+        self.q1.clone()
+    }
+}
+
+/// 最终签名结果（r, s）
+#[wasm_bindgen]
+pub struct SignatureResult {
+    r: Vec<u8>,
+    s: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl SignatureResult {
+    /// 签名分量 r
+    #[wasm_bindgen(getter)]
+    pub fn r(&self) -> Vec<u8> {
+        self.r.clone()
+    }
+
+    /// 签名分量 s
+    #[wasm_bindgen(getter)]
+    pub fn s(&self) -> Vec<u8> {
+        self.s.clone()
+    }
+}
+
+/// 协议上下文
+#[wasm_bindgen]
+pub struct CoSignContext {
+    protocol: CoSignProtocol,
+}
+
+#[wasm_bindgen]
+impl CoSignContext {
+    /// 创建协议上下文
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<CoSignContext, JsValue> {
+        let protocol = CoSignProtocol::new().map_err(to_js_error)?;
+        Ok(Self { protocol })
+    }
+
+    /// 生成客户端私钥分量 D1
+    #[wasm_bindgen(js_name = generateD1)]
+    pub fn generate_d1(&self) -> Result<Vec<u8>, JsValue> {
+        self.protocol.generate_d1().map(|d1| d1.to_vec()).map_err(to_js_error)
+    }
+
+    /// 计算 P1 = d1 * G
+    #[wasm_bindgen(js_name = calculateP1)]
+    pub fn calculate_p1(&self, d1: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.protocol.calculate_p1(d1).map_err(to_js_error)
+    }
+
+    /// 签名预处理：生成 k1，计算 Q1 = k1 * G
+    #[wasm_bindgen(js_name = signPrepare)]
+    pub fn sign_prepare(&self) -> Result<SignPrepareResult, JsValue> {
+        let (k1, q1) = self.protocol.sign_prepare().map_err(to_js_error)?.into_parts();
+        Ok(SignPrepareResult { k1: k1.to_vec(), q1: q1.to_vec() })
+    }
+
+    /// 完成签名计算
+    #[wasm_bindgen(js_name = completeSignature)]
+    pub fn complete_signature(
+        &self,
+        k1: &[u8],
+        d1: &[u8],
+        r: &[u8],
+        s2: &[u8],
+        s3: &[u8],
+    ) -> Result<SignatureResult, JsValue> {
+        let (r, s) = self
+            .protocol
+            .complete_signature(k1, d1, r, s2, s3)
+            .map_err(to_js_error)?;
+        Ok(SignatureResult { r, s })
+    }
+
+    /// 解密预处理：计算 T1 = d1 * C1
+    #[wasm_bindgen(js_name = decryptPrepare)]
+    pub fn decrypt_prepare(&self, d1: &[u8], c1: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.protocol.decrypt_prepare(d1, c1).map_err(to_js_error)
+    }
+
+    /// 完成解密计算
+    #[wasm_bindgen(js_name = completeDecryption)]
+    pub fn complete_decryption(
+        &self,
+        t2: &[u8],
+        c1: &[u8],
+        c3: &[u8],
+        c2: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.protocol
+            .complete_decryption(t2, c1, c3, c2)
+            .map_err(to_js_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_d1_and_p1() {
+        let ctx = CoSignContext::new().unwrap();
+        let d1 = ctx.generate_d1().unwrap();
+        assert!(!d1.is_empty());
+
+        let p1 = ctx.calculate_p1(&d1).unwrap();
+        assert_eq!(p1.len(), 64);
+    }
+
+    #[test]
+    fn test_sign_prepare() {
+        let ctx = CoSignContext::new().unwrap();
+        let prepared = ctx.sign_prepare().unwrap();
+        assert!(!prepared.k1().is_empty());
+        assert_eq!(prepared.q1().len(), 64);
+    }
+}
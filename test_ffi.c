@@ -30,7 +30,7 @@ int test_sm3_hash() {
     unsigned char hash[32];
     unsigned long hash_len = 0;
     
-    int result = cosign_sm3_hash((const unsigned char *)data, strlen(data), hash, &hash_len);
+    int result = cosign_sm3_hash((const unsigned char *)data, strlen(data), hash, sizeof(hash), &hash_len);
     
     if (result != COSIGN_OK) {
         printf("SM3 哈希失败: %d\n", result);
@@ -56,7 +56,7 @@ int test_sm2_sign_verify() {
     // 生成私钥 D1
     unsigned char d1[32];
     unsigned long d1_len = 0;
-    int result = cosign_generate_d1(ctx, d1, &d1_len);
+    int result = cosign_generate_d1(ctx, d1, sizeof(d1), &d1_len);
     if (result != COSIGN_OK) {
         printf("生成 D1 失败: %d\n", result);
         cosign_context_free(ctx);
@@ -67,7 +67,7 @@ int test_sm2_sign_verify() {
     // 计算公钥 P1 = D1 * G
     unsigned char p1[64];
     unsigned long p1_len = 0;
-    result = cosign_calculate_p1(ctx, d1, d1_len, p1, &p1_len);
+    result = cosign_calculate_p1(ctx, d1, d1_len, p1, sizeof(p1), &p1_len);
     if (result != COSIGN_OK) {
         printf("计算 P1 失败: %d\n", result);
         cosign_context_free(ctx);
@@ -83,7 +83,7 @@ int test_sm2_sign_verify() {
     // SM2 签名
     unsigned char signature[64];
     unsigned long sig_len = 0;
-    result = cosign_sm2_sign(d1, d1_len, (const unsigned char *)message, message_len, signature, &sig_len);
+    result = cosign_sm2_sign(d1, d1_len, (const unsigned char *)message, message_len, signature, sizeof(signature), &sig_len);
     if (result != COSIGN_OK) {
         printf("SM2 签名失败: %d\n", result);
         cosign_context_free(ctx);
@@ -129,7 +129,7 @@ int test_sm2_encrypt_decrypt() {
     // 生成私钥 D1
     unsigned char d1[32];
     unsigned long d1_len = 0;
-    int result = cosign_generate_d1(ctx, d1, &d1_len);
+    int result = cosign_generate_d1(ctx, d1, sizeof(d1), &d1_len);
     if (result != COSIGN_OK) {
         printf("生成 D1 失败: %d\n", result);
         cosign_context_free(ctx);
@@ -140,7 +140,7 @@ int test_sm2_encrypt_decrypt() {
     // 计算公钥 P1 = D1 * G
     unsigned char p1[64];
     unsigned long p1_len = 0;
-    result = cosign_calculate_p1(ctx, d1, d1_len, p1, &p1_len);
+    result = cosign_calculate_p1(ctx, d1, d1_len, p1, sizeof(p1), &p1_len);
     if (result != COSIGN_OK) {
         printf("计算 P1 失败: %d\n", result);
         cosign_context_free(ctx);
@@ -156,7 +156,7 @@ int test_sm2_encrypt_decrypt() {
     // SM2 加密
     unsigned char ciphertext[256];
     unsigned long cipher_len = 0;
-    result = cosign_sm2_encrypt(p1, p1_len, (const unsigned char *)plaintext, plaintext_len, ciphertext, &cipher_len);
+    result = cosign_sm2_encrypt(p1, p1_len, (const unsigned char *)plaintext, plaintext_len, ciphertext, sizeof(ciphertext), &cipher_len);
     if (result != COSIGN_OK) {
         printf("SM2 加密失败: %d\n", result);
         cosign_context_free(ctx);
@@ -167,7 +167,7 @@ int test_sm2_encrypt_decrypt() {
     // SM2 解密
     unsigned char decrypted[256];
     unsigned long decrypted_len = 0;
-    result = cosign_sm2_decrypt(d1, d1_len, ciphertext, cipher_len, decrypted, &decrypted_len);
+    result = cosign_sm2_decrypt(d1, d1_len, ciphertext, cipher_len, decrypted, sizeof(decrypted) - 1, &decrypted_len);
     if (result != COSIGN_OK) {
         printf("SM2 解密失败: %d\n", result);
         cosign_context_free(ctx);
@@ -187,7 +187,7 @@ int test_sm2_encrypt_decrypt() {
     
     // 测试错误密文解密
     ciphertext[10] ^= 0xff;  // 篡改密文
-    result = cosign_sm2_decrypt(d1, d1_len, ciphertext, cipher_len, decrypted, &decrypted_len);
+    result = cosign_sm2_decrypt(d1, d1_len, ciphertext, cipher_len, decrypted, sizeof(decrypted) - 1, &decrypted_len);
     if (result == COSIGN_OK) {
         printf("警告：篡改后的密文解密成功（可能需要检查解密验证）\n");
     } else {
@@ -208,7 +208,7 @@ int test_base64() {
     // Base64 编码
     char encoded[64];
     unsigned long encoded_len = 0;
-    int result = cosign_base64_encode((const unsigned char *)data, data_len, encoded, &encoded_len);
+    int result = cosign_base64_encode((const unsigned char *)data, data_len, encoded, sizeof(encoded), &encoded_len);
     if (result != COSIGN_OK) {
         printf("Base64 编码失败: %d\n", result);
         return -1;
@@ -220,7 +220,7 @@ int test_base64() {
     // Base64 解码
     unsigned char decoded[64];
     unsigned long decoded_len = 0;
-    result = cosign_base64_decode(encoded, decoded, &decoded_len);
+    result = cosign_base64_decode(encoded, decoded, sizeof(decoded) - 1, &decoded_len);
     if (result != COSIGN_OK) {
         printf("Base64 解码失败: %d\n", result);
         return -1;